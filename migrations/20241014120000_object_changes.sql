@@ -0,0 +1,16 @@
+-- Written by the VTN when the change log is enabled, for change-data-capture consumers
+create table object_changes
+(
+    seq          bigserial   not null
+        constraint object_changes_pk primary key,
+    object_type  text        not null,
+    object_id    text        not null,
+    operation    text        not null,
+    actor        text        not null,
+    changed_at   timestamptz not null,
+    -- hex encoded SHA-256 of the canonical JSON of the object
+    payload_hash text        not null
+);
+
+create index object_changes_changed_at_index
+    on object_changes (changed_at);
@@ -0,0 +1,3 @@
+-- The priority of the events of a program that do not specify one
+alter table program
+    add column default_priority bigint;
@@ -0,0 +1,3 @@
+-- The latest reports of a client, e.g., for `GET /vens/{venID}/resources/{resourceID}/reports/latest`
+create index report_client_name_modification_date_time_index
+    on report (client_name, modification_date_time desc);
@@ -0,0 +1,16 @@
+-- Set by the event archiver to the end of the last interval, once that has passed
+alter table event
+    add column completed_date_time timestamptz;
+
+-- Completed events are moved here after the archive period, to keep the queries on `event` fast.
+-- The columns must match those of `event`, such that events can be moved with `select *`.
+create table event_archive
+(
+    like event including all
+);
+
+alter table event_archive
+    add constraint event_archive_program_id_fkey foreign key (program_id) references program (id);
+
+create index event_completed_date_time_index
+    on event (completed_date_time);
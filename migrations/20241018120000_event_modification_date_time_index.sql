@@ -0,0 +1,3 @@
+-- The order of the event list when paginating by cursor, see `QueryParams::cursor`
+create index event_modification_date_time_id_index
+    on event (modification_date_time, id);
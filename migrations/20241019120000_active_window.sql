@@ -0,0 +1,36 @@
+-- Whether an interval period overlaps `window_start..window_end`, like `IntervalPeriod::overlaps`.
+-- A period without a duration never ends, and a missing period or bound counts as unbounded.
+create function interval_period_overlaps(period jsonb, window_start timestamptz, window_end timestamptz)
+    returns boolean
+    language sql
+    stable
+as
+$$
+select period is null
+    or ((window_end is null or (period ->> 'start')::timestamptz < window_end)
+        and (window_start is null
+            or period ->> 'duration' is null
+            or (period ->> 'start')::timestamptz
+                   -- the ISO 8601 format of postgres has no leading sign, e.g., `-PT5M`
+                   + case
+                         when period ->> 'duration' like '-%'
+                             then -substr(period ->> 'duration', 2)::interval
+                         else (period ->> 'duration')::interval
+                     end > window_start))
+$$;
+
+-- Whether any interval of an event overlaps the window, falling back to the interval period of the
+-- event for intervals without one. Events without intervals are considered always active.
+create function event_overlaps(interval_period jsonb, intervals jsonb, window_start timestamptz,
+                               window_end timestamptz)
+    returns boolean
+    language sql
+    stable
+as
+$$
+select jsonb_array_length(intervals) = 0
+    or exists (select
+               from jsonb_array_elements(intervals) i
+               where interval_period_overlaps(coalesce(i -> 'intervalPeriod', interval_period),
+                                              window_start, window_end))
+$$;
@@ -0,0 +1,23 @@
+-- The end of the interval of an event that ends last, like `EventContent::end`, falling back to the
+-- interval period of the event for intervals without one.
+-- Null if the event never ends, because an interval has no duration, or if no interval has a period.
+create function event_end(interval_period jsonb, intervals jsonb)
+    returns timestamptz
+    language sql
+    stable
+as
+$$
+select case
+           when bool_and(period ->> 'duration' is not null)
+               then max((period ->> 'start')::timestamptz
+                   -- the ISO 8601 format of postgres has no leading sign, e.g., `-PT5M`
+                   + case
+                         when period ->> 'duration' like '-%'
+                             then -substr(period ->> 'duration', 2)::interval
+                         else (period ->> 'duration')::interval
+                     end)
+           end
+from (select coalesce(nullif(i -> 'intervalPeriod', 'null'::jsonb), nullif(interval_period, 'null'::jsonb)) as period
+      from jsonb_array_elements(intervals) i) periods
+where period is not null
+$$;
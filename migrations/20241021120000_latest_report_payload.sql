@@ -0,0 +1,117 @@
+-- The payload of each type that a client reported last for one of its resources, see `ReportCrud::latest_payloads`.
+-- Maintained by a trigger on each change of the reports, such that the latest payloads are looked up
+-- without scanning all reports of the client.
+create table latest_report_payload
+(
+    client_name     text        not null,
+    resource_name   text        not null,
+    payload_type    text        not null,
+    report_id       text        not null,
+    payload_values  jsonb       not null,
+    interval_id     integer     not null,
+    -- the period of the interval, falling back to the period of the resource
+    interval_period jsonb,
+    reported_at     timestamptz not null,
+    primary key (client_name, resource_name, payload_type)
+);
+
+-- The elements of a json array, or none for other values, e.g., the `{}` resources of reports without any.
+create function jsonb_array_elements_or_none(value jsonb)
+    returns setof jsonb
+    language sql
+    immutable
+as
+$$
+select element
+from jsonb_array_elements(case when jsonb_typeof(value) = 'array' then value else '[]'::jsonb end) element
+$$;
+
+-- The latest payloads of a client for a resource, computed from all of its reports.
+-- Within a report, a later interval wins.
+create function report_latest_payloads(for_client text, for_resource text)
+    returns setof latest_report_payload
+    language sql
+    stable
+as
+$$
+select distinct on (payload ->> 'type') r.client_name,
+                                        resource ->> 'resourceName',
+                                        payload ->> 'type',
+                                        r.id,
+                                        payload -> 'values',
+                                        (i.interval ->> 'id')::integer,
+                                        coalesce(nullif(i.interval -> 'intervalPeriod', 'null'::jsonb),
+                                                 resource -> 'intervalPeriod'),
+                                        r.modification_date_time
+from report r
+         cross join lateral jsonb_array_elements_or_none(r.resources) resource
+         cross join lateral jsonb_array_elements_or_none(resource -> 'intervals') with ordinality as i(interval, position)
+         cross join lateral jsonb_array_elements_or_none(i.interval -> 'payloads') payload
+where r.client_name = for_client
+  and resource ->> 'resourceName' = for_resource
+order by payload ->> 'type', r.modification_date_time desc, i.position desc
+$$;
+
+create function update_latest_report_payloads()
+    returns trigger
+    language plpgsql
+as
+$$
+begin
+    -- the payloads of a changed or deleted report may no longer be the latest,
+    -- so the resources it reported on are computed again from the remaining reports
+    if tg_op in ('UPDATE', 'DELETE') then
+        delete
+        from latest_report_payload latest
+            using jsonb_array_elements_or_none(old.resources) resource
+        where latest.client_name = old.client_name
+          and latest.resource_name = resource ->> 'resourceName';
+
+        insert into latest_report_payload
+        select latest.*
+        from (select distinct resource ->> 'resourceName' as resource_name
+              from jsonb_array_elements_or_none(old.resources) resource) resources
+                 cross join lateral report_latest_payloads(old.client_name, resources.resource_name) latest
+        on conflict (client_name, resource_name, payload_type) do nothing;
+    end if;
+
+    if tg_op in ('INSERT', 'UPDATE') then
+        insert into latest_report_payload
+        select distinct on (resource ->> 'resourceName', payload ->> 'type') new.client_name,
+                                                                             resource ->> 'resourceName',
+                                                                             payload ->> 'type',
+                                                                             new.id,
+                                                                             payload -> 'values',
+                                                                             (i.interval ->> 'id')::integer,
+                                                                             coalesce(nullif(i.interval -> 'intervalPeriod', 'null'::jsonb),
+                                                                                      resource -> 'intervalPeriod'),
+                                                                             new.modification_date_time
+        from jsonb_array_elements_or_none(new.resources) resource
+                 cross join lateral jsonb_array_elements_or_none(resource -> 'intervals') with ordinality as i(interval, position)
+                 cross join lateral jsonb_array_elements_or_none(i.interval -> 'payloads') payload
+        order by resource ->> 'resourceName', payload ->> 'type', i.position desc
+        on conflict (client_name, resource_name, payload_type) do update
+            set report_id       = excluded.report_id,
+                payload_values  = excluded.payload_values,
+                interval_id     = excluded.interval_id,
+                interval_period = excluded.interval_period,
+                reported_at     = excluded.reported_at
+        where latest_report_payload.reported_at <= excluded.reported_at;
+    end if;
+
+    return null;
+end
+$$;
+
+create trigger report_latest_payloads
+    after insert or update or delete
+    on report
+    for each row
+execute function update_latest_report_payloads();
+
+insert into latest_report_payload
+select latest.*
+from (select distinct client_name, resource ->> 'resourceName' as resource_name
+      from report
+               cross join lateral jsonb_array_elements_or_none(resources) resource) resources
+         cross join lateral report_latest_payloads(resources.client_name, resources.resource_name) latest;
@@ -0,0 +1,246 @@
+//! A business logic (BL) that runs a dynamic pricing program: it enrolls a VEN,
+//! publishes the day-ahead prices as events, corrects a price, and monitors the VEN
+//! through the reports the events request.
+//!
+//! ```sh
+//! cargo run --example bl_scheduler
+//! ```
+//!
+//! The example runs against a VTN embedded in the process, and runs with the tests as well.
+
+use chrono::{DateTime, Days, TimeDelta, Utc};
+use openadr_client::{Error, Filter, Filters, PaginationOptions, ProgramClient, SyncSummary};
+use openadr_vtn::jwt::AuthRole;
+use openadr_wire::{
+    event::{EventContent, EventInterval, EventPayloadDescriptor, EventType, EventValuesMap},
+    interval::IntervalPeriod,
+    program::{PayloadDescriptor, ProgramContent},
+    report::{
+        ReportDescriptor, ReportInterval, ReportResource, ReportType, ReportValuesMap, ResourceName,
+    },
+    resource::ResourceContent,
+    values_map::Value,
+    ven::{VenContent, VenId},
+    Duration, OperatingState, SortBy, SortOrder, Unit,
+};
+
+mod common;
+
+const PROGRAM_NAME: &str = "dynamic-pricing";
+const VEN_NAME: &str = "ven-1";
+const RESOURCE_NAME: &str = "meter-1";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let vtn = common::EmbeddedVtn::new(None);
+
+    // The BL manages the objects of any business, and the VENs taking part in its programs
+    let bl = vtn
+        .client("bl", &[AuthRole::AnyBusiness, AuthRole::VenManager])
+        .await;
+    let whoami = bl.whoami().await?;
+    println!(
+        "authenticated as {} with roles {:?}",
+        whoami.client_id, whoami.roles
+    );
+    let capabilities = bl.capabilities().await?;
+    println!(
+        "the VTN implements OpenADR {}, in pages of at most {} objects",
+        capabilities.spec_version, capabilities.max_page_size
+    );
+
+    let program = bl
+        .create_program(ProgramContent {
+            payload_descriptors: Some(vec![PayloadDescriptor::EventPayloadDescriptor(
+                EventPayloadDescriptor {
+                    units: Some(Unit::KWH),
+                    ..EventPayloadDescriptor::new(EventType::Price)
+                },
+            )]),
+            ..ProgramContent::new(PROGRAM_NAME)
+        })
+        .await?;
+
+    let ven = bl.create_ven(VenContent::new(VEN_NAME)).await?;
+    ven.create_resource(ResourceContent::new(RESOURCE_NAME))
+        .await?;
+    program.assign_ven(ven.id()).await?;
+
+    // Publish the prices of today and tomorrow. The events are matched by name,
+    // such that publishing the same schedule again leaves the events alone.
+    let today = Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_utc();
+    let tomorrow = today + Days::new(1);
+    let schedule = vec![
+        price_event(&program, today, 0.0),
+        price_event(&program, tomorrow, 0.0),
+    ];
+    let plan = program.plan_events(schedule).await?;
+    println!("publishing prices: {:?}", plan.summary());
+    plan.execute().await?;
+
+    // A correction of the prices of tomorrow only updates that event
+    let corrected = vec![
+        price_event(&program, today, 0.0),
+        price_event(&program, tomorrow, 0.02),
+    ];
+    let summary = program.sync_events(corrected).await?;
+    println!("correcting prices: {summary:?}");
+    assert_eq!(
+        summary,
+        SyncSummary {
+            updated: 1,
+            unchanged: 1,
+            ..Default::default()
+        }
+    );
+
+    // See the `ven_agent` example for a VEN that schedules its reports
+    report_as_ven(&vtn, ven.id(), today).await?;
+
+    monitor(&bl, program).await?;
+    Ok(())
+}
+
+/// The hourly prices of the day starting at `day`, raised by `correction` per kWh.
+/// The events request the usage of each hour, reported at the end of the day.
+fn price_event(program: &ProgramClient, day: DateTime<Utc>, correction: f64) -> EventContent {
+    let intervals = (0..24)
+        .map(|hour| EventInterval {
+            interval_period: Some(IntervalPeriod {
+                duration: Some(Duration::PT1H),
+                ..IntervalPeriod::new(day + TimeDelta::hours(hour.into()))
+            }),
+            ..EventInterval::new(
+                hour,
+                vec![EventValuesMap {
+                    value_type: EventType::Price,
+                    values: vec![Value::Number(price(hour) + correction)],
+                }],
+            )
+        })
+        .collect();
+
+    program
+        .new_event()
+        .with_event_name(format!("prices-{}", day.format("%Y-%m-%d")))
+        .with_intervals(intervals)
+        .with_report_descriptors(vec![ReportDescriptor {
+            units: Some(Unit::KWH),
+            ..ReportDescriptor::new(ReportType::Usage)
+        }])
+}
+
+/// The price per kWh in the given hour of the day, highest in the evening
+fn price(hour: i32) -> f64 {
+    match hour {
+        17..=20 => 0.35,
+        7..=16 => 0.25,
+        _ => 0.18,
+    }
+}
+
+/// Stand in for the VEN: report the usage of the first hours of `day`, and the operating state of its meter
+async fn report_as_ven(
+    vtn: &common::EmbeddedVtn,
+    ven_id: &VenId,
+    day: DateTime<Utc>,
+) -> Result<(), Error> {
+    let client = vtn.client(VEN_NAME, &[AuthRole::VEN(ven_id.clone())]).await;
+    let program = client.get_program_by_name(PROGRAM_NAME).await?;
+    let event = program
+        .get_event_by_name(&format!("prices-{}", day.format("%Y-%m-%d")))
+        .await?;
+
+    let start = event.content().intervals[0]
+        .interval_period
+        .as_ref()
+        .map(|period| period.start)
+        .ok_or(Error::InvalidInterval)?;
+    let usage = [1.2, 0.9, 0.8].into_iter().zip(0..).map(|(usage, hour)| {
+        ReportInterval::new(
+            hour,
+            vec![ReportValuesMap::new(
+                ReportType::Usage,
+                vec![Value::Number(usage)],
+            )],
+        )
+        .with_interval_period(IntervalPeriod {
+            duration: Some(Duration::PT1H),
+            ..IntervalPeriod::new(start + TimeDelta::hours(hour.into()))
+        })
+    });
+    let report = event.new_report().with_client_name(VEN_NAME).with_resource(
+        ReportResource::new(ResourceName::Private(RESOURCE_NAME.to_string()))
+            .with_intervals(usage.collect()),
+    );
+    event.create_report(report).await?;
+
+    event
+        .report_operating_state(
+            VEN_NAME,
+            ResourceName::Private(RESOURCE_NAME.to_string()),
+            OperatingState::RunningNormal,
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Check on the program the way a dashboard would
+async fn monitor(bl: &openadr_client::Client, mut program: ProgramClient) -> Result<(), Error> {
+    let pagination = PaginationOptions {
+        skip: 0,
+        limit: 10,
+        sort_by: Some(SortBy::Modified),
+        sort_order: Some(SortOrder::Desc),
+    };
+    let events = program.get_events_request(Filter::None, pagination).await?;
+    let last_changed = events[0].content().event_name.as_deref();
+    println!("last changed event: {}", last_changed.unwrap_or_default());
+
+    let timeline = program.get_timeline().await?;
+    if let Some((range, interval)) = timeline.at_datetime(&bl.vtn_now()) {
+        let values = interval.value_map.iter().map(ToString::to_string);
+        println!(
+            "from {} until {}: {}",
+            range.start,
+            range.end,
+            values.collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let reports = bl
+        .get_reports_matching(Filters::new().program_id(program.id()))
+        .await?;
+    let usage: f64 = reports
+        .iter()
+        .flat_map(|report| &report.data().resources)
+        .flat_map(|resource| &resource.intervals)
+        .flat_map(|interval| &interval.payloads)
+        .filter(|payload| payload.value_type == ReportType::Usage)
+        .flat_map(|payload| &payload.values)
+        .filter_map(Value::as_f64)
+        .sum();
+    println!("reported usage: {usage:.1} kWh");
+    assert!(usage > 0.0);
+
+    let states = bl.latest_operating_states().await?;
+    for state in &states {
+        println!(
+            "{:?} of {}: {:?} as of {}",
+            state.resource_name, state.client_name, state.operating_state, state.reported_at
+        );
+    }
+    assert_eq!(states.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn runs() -> Result<(), Box<dyn std::error::Error>> {
+    main()
+}
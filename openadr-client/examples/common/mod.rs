@@ -0,0 +1,48 @@
+//! The VTN the examples run against: the router of `openadr-vtn`, embedded in the process
+//! and keeping its objects in memory, such that the examples run without a database.
+//!
+//! Against a real VTN, create the client with [`Client::with_url`] instead,
+//! with the credentials handed out by the operator of the VTN.
+
+use openadr_client::{Client, ClientCredentials, MockClientRef};
+use openadr_vtn::{
+    data_source::{DataSource, InMemoryStorage},
+    jwt::{AuthRole, JwtManager},
+    notifier::Notifier,
+    state::AppState,
+};
+
+pub struct EmbeddedVtn {
+    storage: InMemoryStorage,
+    router: axum::Router,
+}
+
+impl EmbeddedVtn {
+    /// A VTN without any objects, notifying the subscribers of the `notifier`, if any
+    pub fn new(notifier: Option<Notifier>) -> Self {
+        let storage = InMemoryStorage::new();
+        let mut state = AppState::new(storage.clone(), JwtManager::from_secret(b"example"));
+        if let Some(notifier) = notifier {
+            state = state.with_notifier(notifier);
+        }
+
+        Self {
+            storage,
+            router: state.into_router(),
+        }
+    }
+
+    /// Create a user with the roles, like the operator of the VTN would,
+    /// and a client authenticating with the credentials of that user
+    pub async fn client(&self, client_id: &str, roles: &[AuthRole]) -> Client {
+        let auth = self.storage.auth();
+        let user = auth.add_user(client_id, None, roles).await.unwrap();
+        let client_secret = format!("{client_id}-secret");
+        auth.add_credential(user.id(), client_id, &client_secret)
+            .await
+            .unwrap();
+
+        let credentials = ClientCredentials::new(client_id.to_string(), client_secret);
+        MockClientRef::new(self.router.clone()).into_client(Some(credentials))
+    }
+}
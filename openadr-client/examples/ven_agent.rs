@@ -0,0 +1,227 @@
+//! A VEN that follows the events of a program: it receives the notifications of the VTN,
+//! polls the VTN in case a notification gets lost, acts on each interval of an event
+//! as it becomes active, and sends the reports the event requests.
+//!
+//! ```sh
+//! cargo run --example ven_agent
+//! ```
+//!
+//! The example runs against a VTN embedded in the process, and runs with the tests as well.
+
+use std::{pin::pin, sync::Arc, time::Duration};
+
+use chrono::{TimeDelta, Utc};
+use futures_util::StreamExt;
+use openadr_client::{
+    notification_listener, Client, Error, EventClient, EventUpdate, Filters, IntoIntervalStream,
+    ProgramClient, ReportScheduler,
+};
+use openadr_vtn::{
+    jwt::AuthRole,
+    notifier::{Notifier, RetryPolicy, StaticSubscriptions},
+};
+use openadr_wire::{
+    event::{EventInterval, EventType, EventValuesMap},
+    interval::IntervalPeriod,
+    notification::{Notification, NotificationObject},
+    program::ProgramContent,
+    report::{
+        ReportDescriptor, ReportInterval, ReportResource, ReportType, ReportValuesMap, ResourceName,
+    },
+    resource::ResourceContent,
+    values_map::Value,
+    ven::VenContent,
+    OperatingState,
+};
+use tokio::{net::TcpListener, sync::mpsc};
+
+mod common;
+
+const PROGRAM_NAME: &str = "load-shed";
+const VEN_NAME: &str = "ven-1";
+const RESOURCE_NAME: &str = "heat-pump";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // The VEN receives the notifications at its callback URL, and hands them to a channel
+    // to acknowledge them right away
+    let (sender, mut notifications) = mpsc::unbounded_channel();
+    let handler = move |notification: Notification| {
+        let _ = sender.send(notification);
+        async {}
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let callback_url = format!("http://{}/", listener.local_addr()?);
+    tokio::spawn(async move { axum::serve(listener, notification_listener(handler)).await });
+
+    // This VTN notifies the subscribers it was started with
+    let subscriptions: StaticSubscriptions = format!("EVENT={callback_url}").parse()?;
+    let notifier = Notifier::spawn(Arc::new(subscriptions), RetryPolicy::default());
+    let vtn = common::EmbeddedVtn::new(Some(notifier));
+
+    // The BL sets up the program and enrolls the VEN, see the `bl_scheduler` example
+    let bl = vtn
+        .client("bl", &[AuthRole::AnyBusiness, AuthRole::VenManager])
+        .await;
+    let program = bl.create_program(ProgramContent::new(PROGRAM_NAME)).await?;
+    let ven = bl.create_ven(VenContent::new(VEN_NAME)).await?;
+    ven.create_resource(ResourceContent::new(RESOURCE_NAME))
+        .await?;
+    program.assign_ven(ven.id()).await?;
+
+    // The VEN authenticates with the credentials handed out by the operator of the VTN
+    let client = vtn
+        .client(VEN_NAME, &[AuthRole::VEN(ven.id().clone())])
+        .await;
+    let whoami = client.whoami().await?;
+    println!(
+        "authenticated as {} with roles {:?}",
+        whoami.client_id, whoami.roles
+    );
+    let ven = client.get_ven_by_id(&whoami.ven_ids[0]).await?;
+    let resources = ven.get_all_resources().await?;
+    let mut ven_program = client.get_program_by_name(PROGRAM_NAME).await?;
+
+    // Poll as well, in case a notification gets lost
+    let mut updates = pin!(ven_program.watch_events(Duration::from_secs(30)));
+
+    publish_load_shed(&program).await?;
+
+    let event = loop {
+        tokio::select! {
+            Some(notification) = notifications.recv() => {
+                if let NotificationObject::Event(event) = notification.object {
+                    println!("notified of event {}", event.id);
+                    break client.get_event_by_id(&event.id).await?;
+                }
+            }
+            Some(update) = updates.next() => {
+                if let EventUpdate::Created(event) = update {
+                    println!("polled event {}", event.id());
+                    break event;
+                }
+            }
+        }
+    };
+
+    // Follow the intervals of the events of the program in the time of the VTN
+    let timeline = ven_program.get_timeline().await?;
+    let mut intervals = pin!(timeline.into_stream(client.vtn_clock()));
+    while let Some((range, values)) = intervals.next().await {
+        let level = values
+            .iter()
+            .filter(|values| values.value_type == EventType::Simple)
+            .flat_map(|values| &values.values)
+            .find_map(Value::as_i64)
+            .unwrap_or_default();
+        println!(
+            "from {} until {}: shed level {level}",
+            range.start, range.end
+        );
+
+        let state = match level {
+            0 => OperatingState::RunningNormal,
+            _ => OperatingState::RunningCurtailed,
+        };
+        event
+            .report_operating_state(
+                VEN_NAME,
+                ResourceName::Private(RESOURCE_NAME.to_string()),
+                state,
+            )
+            .await?;
+    }
+
+    for descriptor in event.content().report_descriptors.iter().flatten() {
+        let resources = resources.iter().map(|resource| resource.content());
+        send_reports(&client, &event, descriptor, ven.content(), resources).await?;
+    }
+
+    // The BL finds the reports of the VEN
+    let reports = bl
+        .get_reports_matching(Filters::new().event_id(event.id()))
+        .await?;
+    println!("the VEN sent {} reports", reports.len());
+    assert!(reports.iter().any(|report| report.data().resources[0]
+        .intervals
+        .iter()
+        .any(|interval| interval.payloads[0].value_type == ReportType::Usage)));
+
+    let states = bl.latest_operating_states().await?;
+    assert_eq!(states[0].operating_state, OperatingState::RunningNormal);
+
+    Ok(())
+}
+
+/// Shed load for three seconds, starting a second from now, and request the usage of each second
+async fn publish_load_shed(program: &ProgramClient) -> Result<(), Error> {
+    let start = Utc::now() + TimeDelta::seconds(1);
+    let intervals = [1, 2, 0]
+        .into_iter()
+        .zip(0..)
+        .map(|(level, id)| EventInterval {
+            interval_period: Some(IntervalPeriod {
+                duration: Some("PT1S".parse().unwrap()),
+                ..IntervalPeriod::new(start + TimeDelta::seconds(id.into()))
+            }),
+            ..EventInterval::new(
+                id,
+                vec![EventValuesMap {
+                    value_type: EventType::Simple,
+                    values: vec![Value::Integer(level)],
+                }],
+            )
+        })
+        .collect();
+    let event = program
+        .new_event()
+        .with_event_name("load-shed")
+        .with_intervals(intervals)
+        .with_report_descriptors(vec![ReportDescriptor::new(ReportType::Usage)]);
+
+    program.create_event(event).await?;
+    Ok(())
+}
+
+/// Send the reports of the descriptor when they are due, with made up meter readings
+async fn send_reports<'a>(
+    client: &Client,
+    event: &EventClient,
+    descriptor: &ReportDescriptor,
+    ven: &VenContent,
+    resources: impl IntoIterator<Item = &'a ResourceContent>,
+) -> Result<(), Error> {
+    let mut scheduler =
+        ReportScheduler::new(client.vtn_clock(), event.content(), descriptor.clone())?;
+    let resource_names = scheduler.resource_names(ven, resources);
+
+    while let Some(scheduled) = scheduler.wait().await {
+        let intervals = scheduled
+            .intervals
+            .filter_map(|index| {
+                let payload =
+                    ReportValuesMap::new(descriptor.payload_type.clone(), vec![Value::Number(0.5)]);
+                let interval = ReportInterval::new(index.try_into().ok()?, vec![payload]);
+                Some(interval.with_interval_period(scheduler.interval_period(index)?))
+            })
+            .collect::<Vec<_>>();
+        let resources = resource_names
+            .iter()
+            .map(|name| ReportResource::new(name.clone()).with_intervals(intervals.clone()))
+            .collect();
+
+        let report = event
+            .new_report()
+            .with_client_name(VEN_NAME)
+            .with_resources(resources);
+        event.create_report(report).await?;
+        println!("sent report {}", scheduled.sequence);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn runs() -> Result<(), Box<dyn std::error::Error>> {
+    main()
+}
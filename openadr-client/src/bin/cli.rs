@@ -1,60 +1,64 @@
-use openadr_client::ClientCredentials;
-use openadr_wire::program::ProgramContent;
+//! A small command line interface to inspect the objects on a VTN
+//!
+//! ```text
+//! cli [--url <vtn-url>] event show <program-name> [<event-name>]
+//! ```
+//!
+//! The credentials are read from the `OPENADR_CLIENT_ID` and `OPENADR_CLIENT_SECRET`
+//! environment variables, and default to the admin user of a development VTN.
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let client = openadr_client::Client::with_url(
-        "http://localhost:3000/".try_into()?,
-        Some(ClientCredentials::admin()),
-    );
-    let _created_program = client.create_program(ProgramContent::new("name")).await?;
-    // let created_program_1 = client.create_program(ProgramContent::new("name1")).await?;
-    let program = client.get_program_by_name("name").await?;
-    // let created_event = program
-    //     .create_event(program.new_event().with_event_name("prices3").with_priority(0))
-    //     .await?;
-    let events = program.get_all_events().await?;
-    // let reports = events[0].get_all_reports().await?;
-    // let event = program.get_event(Target::Event("prices3")).await?;
-    dbg!(events);
-    // dbg!(reports);
-
-    // let programs: Vec<Program> = client.get_all_programs()?;
-    // let programs = client.get_programs(TargetLabel::ProgramName, &["name"])?;
-
-    // let program = client.get_program_by_id("id").await?;
-
-    // let evt = program.send_event(Event {
+use openadr_client::{Client, ClientCredentials};
 
-    // })?;
+const USAGE: &str = "usage: cli [--url <vtn-url>] event show <program-name> [<event-name>]";
 
-    // let events = program.get_events(TargetLabel::EventName, &["name"], 0, 10)?;
-
-    // program.get_event_by_name("prices").await?;
-
-    // let events = program.get_all_events().await?;
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1).peekable();
 
-    // // find the event you want, each event contains all relevant information to reconstruct periods
-    // let event = events[0];
+    let mut url = "http://localhost:3000/".to_string();
+    if args.peek().map(String::as_str) == Some("--url") {
+        args.next();
+        url = args.next().ok_or(USAGE)?;
+    }
 
-    // for interval in event.intervals {
-    //     for payload in interval.payloads { // Iterator<Item = Payload
-    //         // do something with the payload
-    //         payload.timestamp;
-    //         payload.unit;
-    //         payload.currency;
-    //         payload.value::<T = f64>();
-    //     }
-    // }
+    let credentials = match (
+        std::env::var("OPENADR_CLIENT_ID"),
+        std::env::var("OPENADR_CLIENT_SECRET"),
+    ) {
+        (Ok(client_id), Ok(client_secret)) => ClientCredentials::new(client_id, client_secret),
+        _ => ClientCredentials::admin(),
+    };
+    let client = Client::with_url(url.parse()?, Some(credentials));
 
-    // send a report
-    // event.send_report(Report {
+    match (args.next().as_deref(), args.next().as_deref()) {
+        (Some("event"), Some("show")) => {
+            let program_name = args.next().ok_or(USAGE)?;
+            let event_name = args.next();
+            event_show(&client, &program_name, event_name.as_deref()).await
+        }
+        _ => Err(USAGE.into()),
+    }
+}
 
-    // }).await?;
+/// Print the intervals of the events of a program, or only of the event with the given name
+async fn event_show(
+    client: &Client,
+    program_name: &str,
+    event_name: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let program = client.get_program_by_name(program_name).await?;
 
-    // program.on_event(|evt| {
+    let events = program.get_all_events().await?;
+    let events = events
+        .iter()
+        .map(|event| event.content())
+        .filter(|content| event_name.is_none() || content.event_name.as_deref() == event_name);
 
-    // })?;
+    for content in events {
+        println!("{content}");
+        print!("{}", content.to_table());
+        println!();
+    }
 
     Ok(())
 }
@@ -4,7 +4,7 @@ use openadr_wire::{
     values_map::Value,
 };
 
-use openadr_client::{ProgramClient, Timeline};
+use openadr_client::{Clock, ProgramClient, Timeline};
 use std::{error::Error, time::Duration};
 use tokio::{
     select,
@@ -22,23 +22,8 @@ async fn wait_for_next_start(clock: &impl Clock, timeline: &Timeline) {
         return std::future::pending().await; // Wait forever
     };
 
-    // if the wait time is negative, return immediately
-    match (next - now).to_std() {
-        Err(_) => {}
-        Ok(wait_time) => tokio::time::sleep(wait_time).await,
-    }
-}
-
-trait Clock {
-    fn now(&self) -> DateTime<Utc>;
-}
-
-struct ChronoClock;
-
-impl Clock for ChronoClock {
-    fn now(&self) -> DateTime<Utc> {
-        Utc::now()
-    }
+    // resilient to jumps of the clock, e.g., after a time synchronization
+    openadr_client::sleep_until(clock, next).await
 }
 
 #[tokio::main]
@@ -52,7 +37,8 @@ async fn main() -> Result<(), Box<dyn Error>> {
     tokio::spawn(poll_timeline(program, poll_interval, sender));
 
     let (output_sender, mut output_receiver) = mpsc::channel(1);
-    tokio::spawn(update_listener(ChronoClock, receiver, output_sender));
+    // intervals are activated in the time of the VTN, in case the local clock is off
+    tokio::spawn(update_listener(client.vtn_clock(), receiver, output_sender));
 
     tokio::spawn(async move {
         while let Some(enforced_limits) = output_receiver.recv().await {
@@ -191,37 +177,17 @@ impl LimitsRes {
 #[cfg(test)]
 mod test {
     use super::*;
+    use openadr_client::MockClock;
     use openadr_wire::{
         event::{EventContent, EventInterval},
         interval::IntervalPeriod,
         program::{ProgramContent, ProgramId},
     };
-    use std::sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    };
 
-    struct TestingClock(AtomicU64);
-
-    impl Clock for Arc<TestingClock> {
-        fn now(&self) -> DateTime<Utc> {
-            let millis = self.0.load(Ordering::Relaxed);
-            chrono::DateTime::<Utc>::from_timestamp_millis(millis as i64).unwrap()
-        }
-    }
-
-    impl TestingClock {
-        pub fn new(now: DateTime<Utc>) -> Arc<Self> {
-            Arc::new(Self(AtomicU64::new(
-                now.timestamp_millis().try_into().unwrap(),
-            )))
-        }
-
-        async fn advance(&self, duration: Duration) {
-            self.0
-                .fetch_add(duration.as_millis().try_into().unwrap(), Ordering::Relaxed);
-            tokio::time::advance(duration).await;
-        }
+    /// Advance both the clock and the paused tokio time
+    async fn advance(clock: &MockClock, duration: Duration) {
+        clock.advance(chrono::TimeDelta::from_std(duration).unwrap());
+        tokio::time::advance(duration).await;
     }
 
     const HOUR: chrono::TimeDelta = chrono::TimeDelta::hours(1);
@@ -229,14 +195,14 @@ mod test {
 
     #[tokio::test(start_paused = true)]
     async fn test_everest_update() {
-        let clock = TestingClock::new(chrono::DateTime::UNIX_EPOCH + (HOUR * 9) + (MINUTE * 42));
+        let clock = MockClock::new(chrono::DateTime::UNIX_EPOCH + (HOUR * 9) + (MINUTE * 42));
         let past = tokio::time::Instant::now();
 
         let (input_sender, input_receiver) = mpsc::channel(1);
         let (output_sender, mut output_receiver) = mpsc::channel(1);
 
         let handle = tokio::spawn(update_listener(
-            Arc::clone(&clock),
+            clock.clone(),
             input_receiver,
             output_sender,
         ));
@@ -245,7 +211,7 @@ mod test {
         assert!(output_receiver.is_empty());
 
         assert_eq!(past, tokio::time::Instant::now());
-        clock.advance(Duration::from_secs(60)).await;
+        advance(&clock, Duration::from_secs(60)).await;
         assert!(output_receiver.is_empty());
 
         let event1_ts = chrono::DateTime::UNIX_EPOCH + (HOUR * 9);
@@ -274,7 +240,7 @@ mod test {
             ]
         );
 
-        clock.advance(Duration::from_secs(60 * 60)).await;
+        advance(&clock, Duration::from_secs(60 * 60)).await;
         let output = output_receiver.recv().await.unwrap();
         assert_eq!(output.limits_root_side.total_power_w, 21.0);
         assert_eq!(
@@ -0,0 +1,336 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::{
+    diagnostics::TimedResolver,
+    failover::{Endpoints, DEFAULT_RECOVERY_INTERVAL},
+    throttle::Throttle,
+    Client, ClientCredentials, ClientRef, Clock, Diagnostics, HttpClient, ReqwestClientRef,
+    SystemClock,
+};
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+const DEFAULT_PAGE_CONCURRENCY: usize = 4;
+
+/// The HTTP version the client uses to connect to the VTN
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    /// Use HTTP/2 if the VTN offers it during the TLS handshake, and HTTP/1.1 otherwise
+    #[default]
+    Negotiate,
+    /// Only use HTTP/1.1, e.g., for proxies that do not support HTTP/2
+    Http1Only,
+    /// Use HTTP/2 without negotiating it first, which also works without TLS
+    Http2PriorKnowledge,
+}
+
+/// The settings of the connections to the VTN, applied to the default reqwest client
+#[derive(Debug, Default, PartialEq)]
+struct ConnectionSettings {
+    http_version: HttpVersion,
+    tcp_keepalive: Option<Duration>,
+    http2_keep_alive_interval: Option<Duration>,
+    http2_keep_alive_timeout: Option<Duration>,
+    http2_keep_alive_while_idle: bool,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+}
+
+impl ConnectionSettings {
+    fn reqwest_client(&self, diagnostics: Option<&Arc<Diagnostics>>) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .tcp_keepalive(self.tcp_keepalive)
+            .http2_keep_alive_interval(self.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(self.http2_keep_alive_while_idle);
+
+        builder = match self.http_version {
+            HttpVersion::Negotiate => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::Http2PriorKnowledge => builder.http2_prior_knowledge(),
+        };
+
+        if let Some(timeout) = self.http2_keep_alive_timeout {
+            builder = builder.http2_keep_alive_timeout(timeout);
+        }
+
+        // `None` disables the timeout in reqwest, so only override the default if set
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+
+        if let Some(diagnostics) = diagnostics {
+            builder = builder.dns_resolver(Arc::new(TimedResolver {
+                diagnostics: diagnostics.clone(),
+            }));
+        }
+
+        builder
+            .build()
+            .expect("could not initialize the HTTP client")
+    }
+}
+
+/// Builder to configure a [`Client`] in more detail than the constructors on [`Client`] allow
+#[derive(Debug)]
+pub struct ClientBuilder {
+    base_url: Url,
+    fallback_urls: Vec<Url>,
+    recovery_interval: Duration,
+    auth: Option<ClientCredentials>,
+    reqwest_client: Option<reqwest::Client>,
+    page_size: usize,
+    page_concurrency: usize,
+    max_concurrent_requests: Option<usize>,
+    min_request_interval: Duration,
+    clock: Arc<dyn Clock>,
+    connection: ConnectionSettings,
+    diagnostics: Option<Arc<Diagnostics>>,
+}
+
+impl ClientBuilder {
+    /// Start building a client for a VTN located at the specified URL
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            base_url,
+            fallback_urls: vec![],
+            recovery_interval: DEFAULT_RECOVERY_INTERVAL,
+            auth: None,
+            reqwest_client: None,
+            page_size: DEFAULT_PAGE_SIZE,
+            page_concurrency: DEFAULT_PAGE_CONCURRENCY,
+            max_concurrent_requests: None,
+            min_request_interval: Duration::ZERO,
+            clock: Arc::new(SystemClock),
+            connection: ConnectionSettings::default(),
+            diagnostics: None,
+        }
+    }
+
+    /// Other URLs of the same VTN, in order of preference, e.g., a backup site.
+    ///
+    /// If the VTN cannot be reached, the request is retried on the next URL.
+    /// Requests that timed out are only retried if they are idempotent, e.g., not when creating an object,
+    /// as the VTN may have processed them nonetheless.
+    /// A URL that could not be reached is skipped for the [`Self::recovery_interval`],
+    /// after which the client tries it again, such that it returns to the preferred URL.
+    pub fn fallback_urls(mut self, fallback_urls: Vec<Url>) -> Self {
+        self.fallback_urls = fallback_urls;
+        self
+    }
+
+    /// How long a VTN URL that could not be reached is skipped, see [`Self::fallback_urls`].
+    /// Defaults to one minute.
+    pub fn recovery_interval(mut self, recovery_interval: Duration) -> Self {
+        self.recovery_interval = recovery_interval;
+        self
+    }
+
+    /// Authenticate to the VTN with the given credentials
+    pub fn credentials(mut self, auth: ClientCredentials) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Use the specific reqwest client instead of the default one.
+    /// This allows you to configure proxy settings, timeouts, etc.
+    ///
+    /// Cannot be combined with the connection settings of this builder,
+    /// like [`Self::http_version`], which configure the default client instead.
+    pub fn reqwest_client(mut self, client: reqwest::Client) -> Self {
+        self.reqwest_client = Some(client);
+        self
+    }
+
+    /// The number of objects requested per page when retrieving all objects of a kind.
+    ///
+    /// Defaults to 50, the maximum page size that the OpenADR specification requires a VTN to
+    /// support. If the VTN rejects the page size, the client automatically uses smaller pages.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        assert!(page_size > 0, "the page size must be at least 1");
+        self.page_size = page_size;
+        self
+    }
+
+    /// The number of pages retrieved at the same time when retrieving all objects of a kind,
+    /// once the first page told the total number of objects. Defaults to 4.
+    ///
+    /// VTNs that do not send the total retrieve the pages one after the other.
+    /// The pages still count towards the [`Self::max_concurrent_requests`].
+    /// Set to 1 to always retrieve the pages one after the other.
+    pub fn page_concurrency(mut self, page_concurrency: usize) -> Self {
+        assert!(
+            page_concurrency > 0,
+            "the page concurrency must be at least 1"
+        );
+        self.page_concurrency = page_concurrency;
+        self
+    }
+
+    /// Limit the number of requests that are in-flight to the VTN at the same time.
+    /// A request is in flight until the body of its response is received.
+    /// By default, the number of concurrent requests is not limited.
+    pub fn max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.max_concurrent_requests = Some(max_concurrent_requests);
+        self
+    }
+
+    /// Wait at least this long between sending two requests to the VTN.
+    /// By default, requests are sent immediately.
+    pub fn min_request_interval(mut self, min_request_interval: Duration) -> Self {
+        self.min_request_interval = min_request_interval;
+        self
+    }
+
+    /// The HTTP version used to connect to the VTN, see [`HttpVersion`]
+    pub fn http_version(mut self, http_version: HttpVersion) -> Self {
+        self.connection.http_version = http_version;
+        self
+    }
+
+    /// Send TCP keep-alive probes at this interval, to detect connections that were silently
+    /// dropped, e.g., by a NAT gateway of a mobile network. Disabled by default.
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.connection.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Send HTTP/2 pings at this interval to keep the connection alive.
+    /// If no pong arrives within `timeout`, the connection is closed and a new one is opened
+    /// for the next request. Disabled by default.
+    ///
+    /// With `while_idle`, the pings are also sent when there are no requests in flight,
+    /// such that a broken connection is detected before the next request.
+    pub fn http2_keep_alive(
+        mut self,
+        interval: Duration,
+        timeout: Duration,
+        while_idle: bool,
+    ) -> Self {
+        self.connection.http2_keep_alive_interval = Some(interval);
+        self.connection.http2_keep_alive_timeout = Some(timeout);
+        self.connection.http2_keep_alive_while_idle = while_idle;
+        self
+    }
+
+    /// Close connections that were idle for this long. Defaults to 90 seconds.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.connection.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Keep at most this many idle connections to the VTN open. Not limited by default.
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.connection.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    /// Use another source of the current time than the system time, see [`Clock`]
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Record the time spent in each phase of the calls to the VTN, i.e., obtaining an access
+    /// token, throttling, resolving the host name, waiting for the response, receiving and
+    /// deserializing it, see [`Client::diagnostics`]. Disabled by default.
+    ///
+    /// The time of resolving the host name is only recorded by the default reqwest client,
+    /// not by a [custom one](Self::reqwest_client).
+    pub fn diagnostics(mut self) -> Self {
+        self.diagnostics = Some(Arc::default());
+        self
+    }
+
+    /// Build the client
+    pub fn build(mut self) -> Client {
+        let reqwest_client = match self.reqwest_client.take() {
+            Some(client) => {
+                assert_eq!(
+                    self.connection,
+                    ConnectionSettings::default(),
+                    "the connection settings cannot be applied to a custom reqwest client"
+                );
+                client
+            }
+            None => self.connection.reqwest_client(self.diagnostics.as_ref()),
+        };
+        self.build_with(Box::new(ReqwestClientRef {
+            client: reqwest_client,
+        }))
+    }
+
+    pub(crate) fn build_with(self, client: Box<dyn HttpClient + Send + Sync>) -> Client {
+        let client_ref = ClientRef {
+            client,
+            endpoints: Endpoints::new(self.base_url, self.fallback_urls, self.recovery_interval),
+            page_size: AtomicUsize::new(self.page_size),
+            page_concurrency: self.page_concurrency,
+            cursor_pagination: AtomicBool::new(false),
+            auth_data: self.auth,
+            auth_token: RwLock::new(None),
+            throttle: Throttle::new(self.max_concurrent_requests, self.min_request_interval),
+            clock: self.clock,
+            skew: Default::default(),
+            #[cfg(feature = "metrics")]
+            metrics: Default::default(),
+            diagnostics: self.diagnostics,
+        };
+
+        Client::new(client_ref)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder() -> ClientBuilder {
+        ClientBuilder::new("https://example.com/".parse().unwrap())
+    }
+
+    #[test]
+    fn connection_settings() {
+        let builder = builder()
+            .http_version(HttpVersion::Http2PriorKnowledge)
+            .tcp_keepalive(Duration::from_secs(30))
+            .http2_keep_alive(Duration::from_secs(20), Duration::from_secs(5), true)
+            .pool_idle_timeout(Duration::from_secs(300))
+            .pool_max_idle_per_host(1);
+
+        assert_eq!(
+            builder.connection,
+            ConnectionSettings {
+                http_version: HttpVersion::Http2PriorKnowledge,
+                tcp_keepalive: Some(Duration::from_secs(30)),
+                http2_keep_alive_interval: Some(Duration::from_secs(20)),
+                http2_keep_alive_timeout: Some(Duration::from_secs(5)),
+                http2_keep_alive_while_idle: true,
+                pool_idle_timeout: Some(Duration::from_secs(300)),
+                pool_max_idle_per_host: Some(1),
+            }
+        );
+
+        builder.build();
+    }
+
+    #[test]
+    #[should_panic(expected = "custom reqwest client")]
+    fn connection_settings_with_custom_client() {
+        builder()
+            .reqwest_client(reqwest::Client::new())
+            .http_version(HttpVersion::Http1Only)
+            .build();
+    }
+}
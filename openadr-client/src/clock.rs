@@ -0,0 +1,175 @@
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc,
+    },
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+/// The source of the current time of a [`Client`](crate::Client).
+///
+/// The client uses it to decide when its access token must be refreshed.
+/// Applications can use the same clock to decide which interval of a
+/// [`Timeline`](crate::Timeline) is active, such that tests can control time with a [`MockClock`].
+/// To wait for a moment in the time of a clock, use [`sleep_until`](crate::sleep_until) or a
+/// [`Schedule`](crate::Schedule), which do not misfire when the clock jumps.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system time, used by default
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when told to, such that tests can simulate days of operation in seconds.
+///
+/// Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    millis: Arc<AtomicI64>,
+}
+
+impl MockClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            millis: Arc::new(AtomicI64::new(now.timestamp_millis())),
+        }
+    }
+
+    /// Move the clock forward by `delta`, or backward if it is negative
+    pub fn advance(&self, delta: TimeDelta) {
+        self.millis
+            .fetch_add(delta.num_milliseconds(), Ordering::Relaxed);
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        self.millis.store(now.timestamp_millis(), Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(self.millis.load(Ordering::Relaxed))
+            .expect("mock clock out of range")
+    }
+}
+
+/// The time of the VTN as estimated by a [`Client`](crate::Client), see [`Client::vtn_now`](crate::Client::vtn_now)
+#[derive(Debug, Clone)]
+pub struct VtnClock {
+    pub(crate) client_ref: Arc<crate::ClientRef>,
+}
+
+impl Clock for VtnClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.client_ref.vtn_now()
+    }
+}
+
+/// The estimated difference between the clock of the VTN and the [`Clock`] of a client,
+/// derived from the `Date` header of the responses of the VTN.
+#[derive(Debug, Default)]
+pub(crate) struct ClockSkew {
+    millis: AtomicI64,
+    known: AtomicBool,
+}
+
+/// Each response moves the estimate by this fraction of its difference to the response,
+/// averaging out the imprecision of the `Date` header and varying latencies
+const SMOOTHING: i64 = 8;
+
+/// A response differing more than this from the estimate replaces it,
+/// such that a jump of either clock is compensated right away
+const MAX_SMOOTHED_MILLIS: i64 = 2_000;
+
+impl ClockSkew {
+    /// The time of the VTN minus the local time, if any response had a `Date` header
+    pub(crate) fn estimate(&self) -> Option<TimeDelta> {
+        self.known
+            .load(Ordering::Relaxed)
+            .then(|| TimeDelta::milliseconds(self.millis.load(Ordering::Relaxed)))
+    }
+
+    /// Update the estimate with the `Date` header of a response to a request
+    /// sent at `sent` and received at `received`, in local time.
+    ///
+    /// The VTN sent the response somewhere in between, of which the midpoint is the best estimate
+    /// without knowing the latency in either direction.
+    /// The header only has a precision of seconds, and the VTN truncates its time,
+    /// so the middle of that second is the best estimate of the time the VTN sent the response.
+    pub(crate) fn update(&self, date: &str, sent: DateTime<Utc>, received: DateTime<Utc>) {
+        let Ok(vtn) = DateTime::parse_from_rfc2822(date) else {
+            return;
+        };
+
+        let local = sent + (received - sent) / 2;
+        let skew =
+            (vtn.with_timezone(&Utc) + TimeDelta::milliseconds(500) - local).num_milliseconds();
+
+        if !self.known.load(Ordering::Relaxed) {
+            self.millis.store(skew, Ordering::Relaxed);
+            self.known.store(true, Ordering::Relaxed);
+            return;
+        }
+
+        let _ = self
+            .millis
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |estimate| {
+                let difference = skew - estimate;
+                Some(if difference.abs() > MAX_SMOOTHED_MILLIS {
+                    skew
+                } else {
+                    estimate + difference / SMOOTHING
+                })
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skew_from_date_header() {
+        let skew = ClockSkew::default();
+        assert_eq!(skew.estimate(), None);
+
+        let local = "2024-06-01T12:00:00Z".parse().unwrap();
+        skew.update("Sat, 01 Jun 2024 12:01:30 GMT", local, local);
+        assert_eq!(skew.estimate(), Some(TimeDelta::milliseconds(90_500)));
+
+        // a jump of either clock replaces the estimate
+        skew.update("Sat, 01 Jun 2024 11:59:00 GMT", local, local);
+        assert_eq!(skew.estimate(), Some(TimeDelta::milliseconds(-59_500)));
+
+        // invalid dates leave the estimate untouched
+        skew.update("yesterday", local, local);
+        assert_eq!(skew.estimate(), Some(TimeDelta::milliseconds(-59_500)));
+    }
+
+    #[test]
+    fn skew_is_smoothed_around_the_round_trip_midpoint() {
+        let skew = ClockSkew::default();
+        let sent: DateTime<Utc> = "2024-06-01T12:00:00Z".parse().unwrap();
+
+        // sent by the VTN at 12:00:01.5 by its own clock, 12:00:01 by the local clock
+        skew.update(
+            "Sat, 01 Jun 2024 12:00:01 GMT",
+            sent,
+            sent + TimeDelta::seconds(2),
+        );
+        assert_eq!(skew.estimate(), Some(TimeDelta::milliseconds(500)));
+
+        // a single response a second off moves the estimate by a fraction only
+        skew.update("Sat, 01 Jun 2024 12:00:01 GMT", sent, sent);
+        assert_eq!(skew.estimate(), Some(TimeDelta::milliseconds(625)));
+    }
+}
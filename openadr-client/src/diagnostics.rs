@@ -0,0 +1,283 @@
+//! Where the time of the calls to the VTN goes, to tell which layer makes a VTN "slow",
+//! see [`ClientBuilder::diagnostics`](crate::ClientBuilder::diagnostics)
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    Method,
+};
+use tokio::time::Instant;
+use tracing::debug;
+
+/// The phases of a call to the VTN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Obtaining an access token, which is usually cached
+    Auth,
+    /// Waiting for the limits of [`ClientBuilder::max_concurrent_requests`](crate::ClientBuilder::max_concurrent_requests)
+    /// and [`ClientBuilder::min_request_interval`](crate::ClientBuilder::min_request_interval)
+    Throttle,
+    /// Resolving the host name of the VTN, only when a new connection is opened
+    Dns,
+    /// From sending the request until the headers of the response arrived,
+    /// including opening a connection and the processing time of the VTN
+    TimeToFirstByte,
+    /// Receiving the body of the response
+    Download,
+    /// Deserializing the body of the response
+    Deserialize,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 6] = [
+        Phase::Auth,
+        Phase::Throttle,
+        Phase::Dns,
+        Phase::TimeToFirstByte,
+        Phase::Download,
+        Phase::Deserialize,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Phase::Auth => "auth",
+            Phase::Throttle => "throttle",
+            Phase::Dns => "dns",
+            Phase::TimeToFirstByte => "time to first byte",
+            Phase::Download => "download",
+            Phase::Deserialize => "deserialize",
+        }
+    }
+}
+
+/// The durations of a single phase, aggregated over all calls
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseStats {
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl PhaseStats {
+    fn record(&mut self, duration: Duration) {
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    pub fn mean(&self) -> Duration {
+        match u32::try_from(self.count) {
+            Ok(0) => Duration::ZERO,
+            Ok(count) => self.total / count,
+            Err(_) => Duration::from_secs_f64(self.total.as_secs_f64() / self.count as f64),
+        }
+    }
+}
+
+/// The aggregated timings of all successful calls since the diagnostics were enabled or reset.
+///
+/// Its [`Display`](fmt::Display) implementation renders a table of the phases,
+/// with the share of each phase in the total time of the calls.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyReport {
+    /// The duration of the calls from start to end
+    pub calls: PhaseStats,
+    pub auth: PhaseStats,
+    pub throttle: PhaseStats,
+    pub dns: PhaseStats,
+    pub time_to_first_byte: PhaseStats,
+    pub download: PhaseStats,
+    pub deserialize: PhaseStats,
+}
+
+impl LatencyReport {
+    pub fn phase(&self, phase: Phase) -> &PhaseStats {
+        match phase {
+            Phase::Auth => &self.auth,
+            Phase::Throttle => &self.throttle,
+            Phase::Dns => &self.dns,
+            Phase::TimeToFirstByte => &self.time_to_first_byte,
+            Phase::Download => &self.download,
+            Phase::Deserialize => &self.deserialize,
+        }
+    }
+
+    fn phase_mut(&mut self, phase: Phase) -> &mut PhaseStats {
+        match phase {
+            Phase::Auth => &mut self.auth,
+            Phase::Throttle => &mut self.throttle,
+            Phase::Dns => &mut self.dns,
+            Phase::TimeToFirstByte => &mut self.time_to_first_byte,
+            Phase::Download => &mut self.download,
+            Phase::Deserialize => &mut self.deserialize,
+        }
+    }
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<20} {:>8} {:>12} {:>12} {:>7}",
+            "phase", "count", "mean", "max", "share"
+        )?;
+
+        let total = self.calls.total.as_secs_f64();
+        for phase in Phase::ALL {
+            let stats = self.phase(phase);
+            let share = if total > 0.0 {
+                100.0 * stats.total.as_secs_f64() / total
+            } else {
+                0.0
+            };
+            writeln!(
+                f,
+                "{:<20} {:>8} {:>12?} {:>12?} {:>6.1}%",
+                phase.name(),
+                stats.count,
+                stats.mean(),
+                stats.max,
+                share
+            )?;
+        }
+
+        write!(
+            f,
+            "{:<20} {:>8} {:>12?} {:>12?}",
+            "total",
+            self.calls.count,
+            self.calls.mean(),
+            self.calls.max
+        )
+    }
+}
+
+/// Records the timings of the calls of a [`Client`](crate::Client) to the VTN.
+///
+/// Shared by all clones of a client and the program, event and report clients derived from it.
+/// The timings of every call are also logged at `DEBUG` level.
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    report: Mutex<LatencyReport>,
+}
+
+impl Diagnostics {
+    /// The timings aggregated since the diagnostics were enabled or [reset](Self::reset)
+    pub fn report(&self) -> LatencyReport {
+        self.report.lock().unwrap().clone()
+    }
+
+    /// Start aggregating anew, e.g., to compare the timings before and after a change
+    pub fn reset(&self) {
+        *self.report.lock().unwrap() = LatencyReport::default();
+    }
+
+    fn record(&self, phase: Phase, duration: Duration) {
+        self.report
+            .lock()
+            .unwrap()
+            .phase_mut(phase)
+            .record(duration);
+    }
+
+    fn record_call(&self, timer: &CallTimer) {
+        let mut report = self.report.lock().unwrap();
+        for (phase, duration) in &timer.phases {
+            report.phase_mut(*phase).record(*duration);
+        }
+        report.calls.record(timer.start.elapsed());
+    }
+}
+
+/// Times the phases of a single call, if the diagnostics are enabled
+pub(crate) struct CallTimer {
+    method: Method,
+    url: String,
+    start: Instant,
+    lap_start: Instant,
+    phases: Vec<(Phase, Duration)>,
+}
+
+impl CallTimer {
+    pub(crate) fn start(method: Method, url: String) -> Self {
+        let now = Instant::now();
+        Self {
+            method,
+            url,
+            start: now,
+            lap_start: now,
+            phases: Vec::with_capacity(Phase::ALL.len()),
+        }
+    }
+
+    /// The time since the previous phase ended was spent in `phase`
+    pub(crate) fn lap(&mut self, phase: Phase) {
+        let now = Instant::now();
+        self.phases.push((phase, now - self.lap_start));
+        self.lap_start = now;
+    }
+
+    pub(crate) fn finish(self, diagnostics: &Diagnostics) {
+        debug!(
+            method = %self.method,
+            url = %self.url,
+            total = ?self.start.elapsed(),
+            phases = ?self.phases,
+            "call timings"
+        );
+        diagnostics.record_call(&self);
+    }
+}
+
+/// Resolves host names like the default resolver of reqwest, recording how long it takes
+#[derive(Debug)]
+pub(crate) struct TimedResolver {
+    pub(crate) diagnostics: Arc<Diagnostics>,
+}
+
+impl Resolve for TimedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let diagnostics = self.diagnostics.clone();
+
+        Box::pin(async move {
+            let start = Instant::now();
+            let addrs = tokio::net::lookup_host((name.as_str().to_owned(), 0)).await?;
+            diagnostics.record(Phase::Dns, start.elapsed());
+
+            Ok(Box::new(addrs) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregates_phases() {
+        let diagnostics = Diagnostics::default();
+        diagnostics.record(Phase::Dns, Duration::from_millis(30));
+        diagnostics.record(Phase::Dns, Duration::from_millis(10));
+
+        let report = diagnostics.report();
+        assert_eq!(
+            report.dns,
+            PhaseStats {
+                count: 2,
+                total: Duration::from_millis(40),
+                max: Duration::from_millis(30),
+            }
+        );
+        assert_eq!(report.dns.mean(), Duration::from_millis(20));
+        assert_eq!(report.auth, PhaseStats::default());
+        assert!(report.to_string().contains("dns"));
+
+        diagnostics.reset();
+        assert_eq!(diagnostics.report(), LatencyReport::default());
+    }
+}
@@ -1,39 +1,86 @@
 /// Errors that can occur using the [`Client`](crate::Client)
-#[derive(Debug)]
+///
+/// Errors caused by another error, e.g., of `reqwest` or `serde_json`, return it as their
+/// [`source`](std::error::Error::source), such that reporting the whole chain,
+/// e.g., with `anyhow`, shows the real cause.
+#[derive(Debug, thiserror::Error)]
 pub enum Error {
-    Reqwest(reqwest::Error),
-    Serde(serde_json::Error),
-    UrlParseError(url::ParseError),
+    #[error("Request to the VTN failed")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("Could not (de)serialize JSON")]
+    Serde(#[from] serde_json::Error),
+    /// The body of a response of the VTN does not deserialize, e.g., because
+    /// the VTN implements another revision of the specification
+    #[error("Invalid response from {url}")]
+    InvalidResponse {
+        url: url::Url,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Invalid URL")]
+    UrlParseError(#[from] url::ParseError),
+    #[error("OpenADR Problem: {0:?}")]
     Problem(openadr_wire::problem::Problem),
+    /// The VTN did not accept the token of the client, e.g., because it expired or the VTN
+    /// rotated its keys. The client drops the token, such that the next request authenticates again.
+    #[error("Not authenticated: {0:?}")]
+    Unauthorized(openadr_wire::problem::Problem),
+    /// The client is authenticated, but lacks the role or ownership for the request
+    #[error("Not allowed: {0:?}")]
+    Forbidden(openadr_wire::problem::Problem),
+    /// The VTN is temporarily unavailable, e.g., because it is in maintenance mode.
+    /// The request can be retried after the `retry_after` the VTN sent, if any.
+    #[error("VTN temporarily unavailable: {problem:?}")]
+    ServiceUnavailable {
+        problem: openadr_wire::problem::Problem,
+        retry_after: Option<std::time::Duration>,
+    },
+    #[error("Authentication problem: {0:?}")]
     AuthProblem(openadr_wire::oauth::OAuthError),
+    #[error("OAuth token received is not a Bearer token")]
     OAuthTokenNotBearer,
+    #[error("Object not found")]
     ObjectNotFound,
+    #[error("Found more than one object matching the filter")]
     DuplicateObject,
+    #[error("Invalid parent object")]
     InvalidParentObject,
+    #[error("Invalid interval specified")]
     InvalidInterval,
-}
-
-impl From<reqwest::Error> for Error {
-    fn from(err: reqwest::Error) -> Self {
-        Error::Reqwest(err)
-    }
-}
-
-impl From<serde_json::Error> for Error {
-    fn from(err: serde_json::Error) -> Self {
-        Error::Serde(err)
-    }
-}
-
-impl From<url::ParseError> for Error {
-    fn from(err: url::ParseError) -> Self {
-        Error::UrlParseError(err)
-    }
+    /// The [`Filters`](crate::Filters) contain targets of different types,
+    /// while the VTN accepts a single target type per request
+    #[error("Cannot filter by target type {0} as well as by another target type")]
+    ConflictingTargets(openadr_wire::target::TargetLabel),
+    /// The name of a VTN of a [`MultiClient`](crate::MultiClient) contains the
+    /// [`VTN_SEPARATOR`](crate::VTN_SEPARATOR)
+    #[error("Invalid VTN name {0:?}")]
+    InvalidVtnName(String),
+    /// No timeline can be built from the stored program and events,
+    /// because an interval has no interval period of its own, nor of its event or program
+    #[cfg(feature = "store")]
+    #[error("No timeline for the stored program and events")]
+    NoTimeline,
+    /// An event to sync with [`ProgramClient::sync_events`](crate::ProgramClient::sync_events) has no name
+    #[error("Events to sync must have a name")]
+    UnnamedEvent,
+    #[error("Invalid signature")]
+    Signature(#[from] jsonwebtoken::errors::Error),
+    #[error("The VTN did not sign the object")]
+    MissingSignature,
+    #[error("The signed object does not match the received object")]
+    SignatureMismatch,
+    #[cfg(feature = "store")]
+    #[error("Local store error")]
+    Store(#[from] sled::Error),
 }
 
 impl From<openadr_wire::problem::Problem> for Error {
     fn from(err: openadr_wire::problem::Problem) -> Self {
-        Error::Problem(err)
+        match err.status {
+            reqwest::StatusCode::UNAUTHORIZED => Error::Unauthorized(err),
+            reqwest::StatusCode::FORBIDDEN => Error::Forbidden(err),
+            _ => Error::Problem(err),
+        }
     }
 }
 
@@ -43,23 +90,59 @@ impl From<openadr_wire::oauth::OAuthError> for Error {
     }
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl Error {
+    /// Whether the error is temporary, such that the same request may succeed when retried later,
+    /// e.g., when the VTN is unreachable, overloaded, or in maintenance mode
+    pub fn is_retryable(&self) -> bool {
         match self {
-            Error::Reqwest(err) => write!(f, "Reqwest error: {}", err),
-            Error::Serde(err) => write!(f, "Serde error: {}", err),
-            Error::UrlParseError(err) => write!(f, "URL parse error: {}", err),
-            Error::Problem(err) => write!(f, "OpenADR Problem: {:?}", err),
-            Error::AuthProblem(err) => write!(f, "Authentication problem: {:?}", err),
-            Error::ObjectNotFound => write!(f, "Object not found"),
-            Error::DuplicateObject => write!(f, "Found more than one object matching the filter"),
-            Error::InvalidParentObject => write!(f, "Invalid parent object"),
-            Error::InvalidInterval => write!(f, "Invalid interval specified"),
-            Error::OAuthTokenNotBearer => write!(f, "OAuth token received is not a Bearer token"),
+            Error::Reqwest(err) => crate::failover::is_unreachable(err),
+            Error::ServiceUnavailable { .. } => true,
+            Error::Problem(problem) => matches!(
+                problem.status,
+                reqwest::StatusCode::TOO_MANY_REQUESTS
+                    | reqwest::StatusCode::BAD_GATEWAY
+                    | reqwest::StatusCode::SERVICE_UNAVAILABLE
+                    | reqwest::StatusCode::GATEWAY_TIMEOUT
+            ),
+            _ => false,
         }
     }
-}
 
-impl std::error::Error for Error {}
+    /// How long the VTN asked to wait before retrying, see [`Error::ServiceUnavailable`]
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Error::ServiceUnavailable { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn keeps_the_cause() {
+        let source = serde_json::from_str::<u32>("\"one\"").unwrap_err();
+        let err = Error::InvalidResponse {
+            url: "https://vtn.example.com/programs".parse().unwrap(),
+            source,
+        };
+
+        assert_eq!(
+            err.to_string(),
+            "Invalid response from https://vtn.example.com/programs"
+        );
+        let cause = err.source().unwrap();
+        assert!(cause.is::<serde_json::Error>());
+        assert!(cause.to_string().contains("expected u32"));
+
+        let err = Error::from(url::Url::parse("no url").unwrap_err());
+        assert!(err.source().unwrap().is::<url::ParseError>());
+        assert!(Error::ObjectNotFound.source().is_none());
+    }
+}
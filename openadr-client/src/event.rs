@@ -1,13 +1,24 @@
 use std::sync::Arc;
 
+use reqwest::StatusCode;
+
 use crate::{
     error::{Error, Result},
-    ClientRef, ReportClient,
+    ClientRef, Filters, ProgramClient, ReportClient, Target,
 };
 use openadr_wire::{
-    event::EventContent,
-    report::{ReportContent, ReportObjectType},
-    Event, Report,
+    event::{
+        EventContent, EventDelta, EventPayloadDescriptor, EventType, EVENT_DELTA_CONTENT_TYPE,
+    },
+    interval::IntervalPeriod,
+    report::{
+        ReportContent, ReportInterval, ReportObjectType, ReportResource, ReportValuesMap,
+        ResourceName,
+    },
+    resource::ResourceContent,
+    target::{TargetEntry, TargetMap},
+    ven::VenContent,
+    Event, OperatingState,
 };
 
 #[derive(Debug)]
@@ -36,6 +47,11 @@ impl EventClient {
         self.data.modification_date_time
     }
 
+    /// The event as it was last received from the VTN
+    pub fn event(&self) -> &Event {
+        &self.data
+    }
+
     pub fn content(&self) -> &EventContent {
         &self.data.content
     }
@@ -44,6 +60,83 @@ impl EventClient {
         &mut self.data.content
     }
 
+    /// The targets of the event. An entry may contain multiple values of its label
+    pub fn targets(&self) -> &[TargetEntry] {
+        self.data
+            .content
+            .targets
+            .as_ref()
+            .map_or(&[], |targets| &targets.0)
+    }
+
+    /// Add the values of the target to the targets of the event, skipping values the event
+    /// already targets. The values are added to the first entry with the label of the target,
+    /// if any, such that the event has a single entry per label.
+    /// Make sure to [update](Self::update) the event on the VTN afterward.
+    pub fn add_target(&mut self, target: Target<'_>) {
+        let label = target.target_label();
+        let targets = &mut self
+            .data
+            .content
+            .targets
+            .get_or_insert_with(TargetMap::default)
+            .0;
+
+        for value in target.target_values() {
+            if targets.iter().any(|entry| entry.contains(&label, value)) {
+                continue;
+            }
+            match targets.iter_mut().find(|entry| entry.label == label) {
+                Some(entry) => entry.values.push(value.to_string()),
+                None => targets.push(TargetEntry::new(label.clone(), value)),
+            }
+        }
+    }
+
+    /// Remove the values of the target from the targets of the event.
+    /// Make sure to [update](Self::update) the event on the VTN afterward.
+    pub fn remove_target(&mut self, target: Target<'_>) {
+        let label = target.target_label();
+        let values = target.target_values();
+
+        let Some(targets) = &mut self.data.content.targets else {
+            return;
+        };
+
+        for entry in targets.0.iter_mut().filter(|entry| entry.label == label) {
+            entry
+                .values
+                .retain(|value| !values.contains(&value.as_str()));
+        }
+        targets.0.retain(|entry| !entry.values.is_empty());
+
+        // an event without targets applies to all VENs, which is sent as `null`
+        if targets.0.is_empty() {
+            self.data.content.targets = None;
+        }
+    }
+
+    /// The descriptor of the payloads of the given type, e.g., with the units of a capacity limit,
+    /// as described by the event, or otherwise by its program
+    pub fn payload_descriptor<'a>(
+        &'a self,
+        payload_type: &EventType,
+        program: &'a ProgramClient,
+    ) -> Option<&'a EventPayloadDescriptor> {
+        self.data
+            .content
+            .payload_descriptor(payload_type, program.content())
+    }
+
+    /// Whether the event applies to the VEN or any of the given resources of the VEN,
+    /// evaluated locally with [`TargetMap::matches_ven_resources`].
+    /// An event without targets applies to all VENs.
+    pub fn applies_to(&self, ven: &VenContent, resources: &[ResourceContent]) -> bool {
+        self.data.content.targets.as_ref().map_or(true, |targets| {
+            targets.matches_ven_resources(ven, resources)
+        })
+    }
+
     /// Save any modifications of the event to the VTN
     pub async fn update(&mut self) -> Result<()> {
         let res = self
@@ -54,6 +147,34 @@ impl EventClient {
         Ok(())
     }
 
+    /// Save modifications of the intervals of the event to the VTN, sending only the changes.
+    /// Use [`EventContent::delta_to`] to determine the changes between two versions of an event.
+    ///
+    /// The VTN only supports this if its [capabilities](crate::Client::capabilities) list
+    /// [`Feature::EventDeltas`](openadr_wire::capabilities::Feature::EventDeltas).
+    /// If the VTN rejects the content type, the complete event is sent instead.
+    pub async fn update_delta(&mut self, delta: EventDelta) -> Result<()> {
+        let path = format!("events/{}", self.id());
+
+        match self
+            .client
+            .put_as(&path, &delta, EVENT_DELTA_CONTENT_TYPE)
+            .await
+        {
+            Ok(event) => {
+                self.data = event;
+                Ok(())
+            }
+            Err(Error::Problem(problem))
+                if problem.status == StatusCode::UNSUPPORTED_MEDIA_TYPE =>
+            {
+                self.data.content.apply_delta(delta);
+                self.update().await
+            }
+            Err(err) => Err(err),
+        }
+    }
+
     /// Delete the event from the VTN
     pub async fn delete(self) -> Result<Event> {
         self.client
@@ -84,82 +205,44 @@ impl EventClient {
             return Err(Error::InvalidParentObject);
         }
 
-        let report = self.client.post("events", &report_data, &[]).await?;
+        let report = self.client.post("reports", &report_data, &[]).await?;
         Ok(ReportClient::from_report(self.client.clone(), report))
     }
 
-    async fn get_reports_req(
+    /// Report that a resource changed to the given operating state, as of now in the time of the VTN
+    pub async fn report_operating_state(
         &self,
-        client_name: Option<&str>,
-        skip: usize,
-        limit: usize,
-    ) -> Result<Vec<ReportClient>> {
-        let skip_str = skip.to_string();
-        let limit_str = limit.to_string();
-
-        let mut query = vec![
-            ("programID", self.content().program_id.as_str()),
-            ("eventID", self.id().as_str()),
-            ("skip", &skip_str),
-            ("limit", &limit_str),
-        ];
-
-        if let Some(client_name) = client_name {
-            query.push(("clientName", client_name));
-        }
+        client_name: &str,
+        resource_name: ResourceName,
+        state: OperatingState,
+    ) -> Result<ReportClient> {
+        let interval = ReportInterval::new(0, vec![ReportValuesMap::operating_state(state)])
+            .with_interval_period(IntervalPeriod::new(self.client.vtn_now()));
+        let report = self
+            .new_report()
+            .with_client_name(client_name)
+            .with_resource(ReportResource::new(resource_name).with_interval(interval));
+
+        self.create_report(report).await
+    }
 
-        let reports: Vec<Report> = self.client.get("reports", &query).await?;
-        Ok(reports
-            .into_iter()
-            .map(|report| ReportClient::from_report(self.client.clone(), report))
-            .collect())
+    fn report_filters(&self) -> Filters<'_> {
+        Filters::new()
+            .program_id(&self.content().program_id)
+            .event_id(self.id())
     }
 
     /// Get all reports from the VTN for a specific client, trying to paginate whenever possible
     pub async fn get_client_reports(&self, client_name: &str) -> Result<Vec<ReportClient>> {
-        let page_size = self.client.default_page_size();
-        let mut reports = vec![];
-        let mut page = 0;
-        loop {
-            let received = self
-                .get_reports_req(Some(client_name), page * page_size, page_size)
-                .await?;
-            let received_all = received.len() < page_size;
-            for report in received {
-                reports.push(report);
-            }
-
-            if received_all {
-                break;
-            } else {
-                page += 1;
-            }
-        }
-
-        Ok(reports)
+        self.client
+            .get_reports_matching(&self.report_filters().client_name(client_name))
+            .await
     }
 
     /// Get all reports from the VTN, trying to paginate whenever possible
     pub async fn get_all_reports(&self) -> Result<Vec<ReportClient>> {
-        let page_size = self.client.default_page_size();
-        let mut reports = vec![];
-        let mut page = 0;
-        loop {
-            let received = self
-                .get_reports_req(None, page * page_size, page_size)
-                .await?;
-            let received_all = received.len() < page_size;
-            for report in received {
-                reports.push(report);
-            }
-
-            if received_all {
-                break;
-            } else {
-                page += 1;
-            }
-        }
-
-        Ok(reports)
+        self.client
+            .get_reports_matching(&self.report_filters())
+            .await
     }
 }
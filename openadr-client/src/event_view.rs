@@ -0,0 +1,262 @@
+//! Borrowed views of the events of a page, for VENs polling the VTN at a high frequency.
+//!
+//! Deserializing an [`Event`](openadr_wire::Event) allocates every string it contains,
+//! which adds up to thousands of allocations per poll for events with many intervals.
+//! An [`EventsBuffer`] instead keeps the body of the response as received,
+//! and its [`EventView`]s borrow their strings and values from it.
+
+use std::{borrow::Cow, fmt, ops::Deref};
+
+use axum::body::Bytes;
+use chrono::{DateTime, FixedOffset, Utc};
+use openadr_wire::{
+    event::{EventType, Priority},
+    values_map::Value,
+    Event,
+};
+use serde::{
+    de::{value::StrDeserializer, Error as _, Unexpected, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::value::RawValue;
+
+use crate::error;
+
+/// The raw body of a page of events, see [`Client::get_events_buffer`](crate::Client::get_events_buffer)
+#[derive(Debug, Clone)]
+pub struct EventsBuffer {
+    body: Bytes,
+    total: Option<usize>,
+}
+
+impl EventsBuffer {
+    pub(crate) fn new(body: Bytes, total: Option<usize>) -> Self {
+        Self { body, total }
+    }
+
+    /// Views of the events in this page, borrowing from the buffer
+    pub fn events(&self) -> error::Result<Vec<EventView<'_>>> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    /// Fully deserialize the events, e.g., for the events that changed since the previous poll
+    pub fn to_events(&self) -> error::Result<Vec<Event>> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    /// The total number of events matching the query, if the VTN sent it
+    pub fn total(&self) -> Option<usize> {
+        self.total
+    }
+
+    /// The body of the response as received
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// A string borrowed from the buffer, unless it contains escape sequences
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Str<'a>(Cow<'a, str>);
+
+impl Str<'_> {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether the string could be borrowed from the buffer
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.0, Cow::Borrowed(_))
+    }
+}
+
+impl Deref for Str<'_> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Str<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Str<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for Str<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Str<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Str<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StrVisitor;
+
+        impl<'de> Visitor<'de> for StrVisitor {
+            type Value = Str<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E: serde::de::Error>(
+                self,
+                v: &'de str,
+            ) -> Result<Self::Value, E> {
+                Ok(Str(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(Str(Cow::Owned(v.to_owned())))
+            }
+
+            fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Self::Value, E> {
+                Ok(Str(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+/// A borrowed view of an [`Event`], with the fields needed to act on an event.
+///
+/// The other fields, like the targets and descriptors, are skipped.
+/// Use [`EventsBuffer::to_events`] to get them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventView<'a> {
+    #[serde(borrow)]
+    pub id: Str<'a>,
+    #[serde(rename = "programID", borrow)]
+    pub program_id: Str<'a>,
+    #[serde(default, borrow)]
+    pub event_name: Option<Str<'a>>,
+    pub priority: Priority,
+    /// To tell whether the event changed since the previous poll
+    #[serde(deserialize_with = "borrowed_rfc3339")]
+    pub modification_date_time: DateTime<Utc>,
+    /// The default interval period of the intervals, as JSON
+    #[serde(default, borrow)]
+    pub interval_period: Option<&'a RawValue>,
+    #[serde(borrow)]
+    pub intervals: Vec<IntervalView<'a>>,
+}
+
+/// A borrowed view of an [`EventInterval`](openadr_wire::event::EventInterval)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntervalView<'a> {
+    pub id: i32,
+    /// The interval period, if it differs from the one of the event, as JSON
+    #[serde(default, borrow)]
+    pub interval_period: Option<&'a RawValue>,
+    #[serde(borrow)]
+    pub payloads: Vec<PayloadView<'a>>,
+}
+
+/// A borrowed view of an [`EventValuesMap`](openadr_wire::event::EventValuesMap)
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayloadView<'a> {
+    #[serde(rename = "type", borrow)]
+    pub value_type: Str<'a>,
+    /// The values, parsed on demand with [`PayloadView::value`]
+    #[serde(borrow)]
+    pub values: Vec<&'a RawValue>,
+}
+
+impl PayloadView<'_> {
+    /// The type of the payload as [`EventType`], which allocates for private types
+    pub fn event_type(&self) -> EventType {
+        EventType::deserialize(StrDeserializer::<serde::de::value::Error>::new(
+            self.value_type.as_str(),
+        ))
+        .expect("any string is an event type")
+    }
+
+    /// Parse the value at `index`
+    pub fn value(&self, index: usize) -> Option<error::Result<Value>> {
+        self.values
+            .get(index)
+            .map(|value| Ok(serde_json::from_str(value.get())?))
+    }
+}
+
+fn borrowed_rfc3339<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let rfc_str = <Str<'de>>::deserialize(deserializer)?;
+
+    DateTime::<FixedOffset>::parse_from_rfc3339(&rfc_str)
+        .map(Into::into)
+        .map_err(|_| D::Error::invalid_value(Unexpected::Str(&rfc_str), &"Invalid RFC3339 string"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrows_from_buffer() {
+        let body = r#"[{
+            "id": "event-1",
+            "createdDateTime": "2024-07-25T08:31:10.776Z",
+            "modificationDateTime": "2024-07-25T08:31:10.776Z",
+            "objectType": "EVENT",
+            "programID": "program-1",
+            "eventName": "peak \"shaving\"",
+            "priority": 4,
+            "intervalPeriod": {"start": "2024-07-25T09:00:00Z", "duration": "PT1H"},
+            "intervals": [
+                {"id": 0, "payloads": [{"type": "PRICE", "values": [0.17, 0.23]}]},
+                {"id": 1, "payloads": [{"type": "PRIVATE_LABEL", "values": ["high"]}]}
+            ]
+        }]"#;
+        let buffer = EventsBuffer::new(Bytes::from_static(body.as_bytes()), Some(1));
+
+        let events = buffer.events().unwrap();
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!(event.id, "event-1");
+        assert!(event.id.is_borrowed());
+        assert_eq!(event.program_id, "program-1");
+        // strings with escape sequences are unescaped into an owned string
+        let name = event.event_name.as_ref().unwrap();
+        assert_eq!(name, &r#"peak "shaving""#);
+        assert!(!name.is_borrowed());
+        assert_eq!(event.priority, Priority::new(4));
+        assert_eq!(
+            event.modification_date_time,
+            "2024-07-25T08:31:10.776Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        let payload = &event.intervals[0].payloads[0];
+        assert_eq!(payload.event_type(), EventType::Price);
+        assert_eq!(payload.values.len(), 2);
+        assert_eq!(payload.value(1).unwrap().unwrap(), Value::Number(0.23));
+        assert!(payload.value(2).is_none());
+
+        let payload = &event.intervals[1].payloads[0];
+        assert_eq!(payload.value_type, "PRIVATE_LABEL");
+        assert_eq!(
+            payload.value(0).unwrap().unwrap(),
+            Value::String("high".to_string())
+        );
+
+        let full = buffer.to_events().unwrap();
+        assert_eq!(full[0].id.as_str(), "event-1");
+        assert_eq!(buffer.total(), Some(1));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{sync::Mutex, time::Duration};
+
+use reqwest::Method;
+use tokio::time::Instant;
+use url::Url;
+
+/// How long a VTN URL is skipped after it was unreachable, before it is tried again
+pub(crate) const DEFAULT_RECOVERY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The base URLs of a VTN in order of preference, and which of them were unreachable recently.
+///
+/// Requests go to the most preferred URL that did not fail within the recovery interval.
+/// Once the interval elapsed, a failed URL is tried again, such that the client returns to the
+/// primary URL when it recovers.
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    urls: Vec<Url>,
+    recovery_interval: Duration,
+    /// When each URL was unreachable most recently, `None` if it was reachable since
+    failed_at: Mutex<Vec<Option<Instant>>>,
+}
+
+impl Endpoints {
+    pub(crate) fn new(primary: Url, fallbacks: Vec<Url>, recovery_interval: Duration) -> Self {
+        let urls: Vec<Url> = std::iter::once(primary).chain(fallbacks).collect();
+
+        Self {
+            failed_at: Mutex::new(vec![None; urls.len()]),
+            urls,
+            recovery_interval,
+        }
+    }
+
+    /// The URL all requests are built with, which is replaced by [`Self::rebase`] on failover
+    pub(crate) fn primary(&self) -> &Url {
+        &self.urls[0]
+    }
+
+    pub(crate) fn has_fallbacks(&self) -> bool {
+        self.urls.len() > 1
+    }
+
+    /// The indices of the URLs to try for a request, in order.
+    ///
+    /// If all URLs failed recently, all of them are tried in order of preference anyway.
+    pub(crate) fn candidates(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let failed_at = self.failed_at.lock().unwrap();
+
+        let (healthy, failed): (Vec<usize>, Vec<usize>) = (0..self.urls.len()).partition(|&i| {
+            failed_at[i].map_or(true, |at| now.duration_since(at) >= self.recovery_interval)
+        });
+
+        if healthy.is_empty() {
+            failed
+        } else {
+            healthy
+        }
+    }
+
+    pub(crate) fn url(&self, index: usize) -> &Url {
+        &self.urls[index]
+    }
+
+    /// Move a URL built from the [primary URL](Self::primary) to the URL with the given index
+    pub(crate) fn rebase(&self, url: &Url, index: usize) -> Url {
+        let Some(path) = url.as_str().strip_prefix(self.primary().as_str()) else {
+            return url.clone();
+        };
+
+        self.urls[index].join(path).unwrap_or_else(|_| url.clone())
+    }
+
+    pub(crate) fn mark_failed(&self, index: usize) {
+        self.failed_at.lock().unwrap()[index] = Some(Instant::now());
+    }
+
+    pub(crate) fn mark_reachable(&self, index: usize) {
+        self.failed_at.lock().unwrap()[index] = None;
+    }
+}
+
+/// Whether the error indicates that the VTN could not be reached at all,
+/// such that another URL of the VTN may succeed
+pub(crate) fn is_unreachable(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout()
+}
+
+/// Whether a request that failed with the error may be sent to another URL of the VTN.
+///
+/// A request that timed out may have been processed by the VTN nonetheless,
+/// so only idempotent requests are sent to another URL after a timeout.
+pub(crate) fn may_fail_over(err: &reqwest::Error, method: &Method) -> bool {
+    err.is_connect() || (err.is_timeout() && is_idempotent(method))
+}
+
+/// RFC 9110 section 9.2.2
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::OPTIONS | Method::TRACE | Method::PUT | Method::DELETE
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn endpoints() -> Endpoints {
+        Endpoints::new(
+            "https://primary.example.com/openadr3/".parse().unwrap(),
+            vec![
+                "https://backup-1.example.com/".parse().unwrap(),
+                "https://backup-2.example.com/vtn/".parse().unwrap(),
+            ],
+            Duration::from_secs(60),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failover_and_recovery() {
+        let endpoints = endpoints();
+        assert_eq!(endpoints.candidates(), vec![0, 1, 2]);
+
+        endpoints.mark_failed(0);
+        assert_eq!(endpoints.candidates(), vec![1, 2]);
+
+        endpoints.mark_failed(1);
+        assert_eq!(endpoints.candidates(), vec![2]);
+
+        // the primary URL is tried again after the recovery interval
+        tokio::time::advance(Duration::from_secs(30)).await;
+        endpoints.mark_failed(1);
+        tokio::time::advance(Duration::from_secs(30)).await;
+        assert_eq!(endpoints.candidates(), vec![0, 2]);
+
+        endpoints.mark_reachable(0);
+        assert_eq!(endpoints.candidates(), vec![0, 2]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn all_failed() {
+        let endpoints = endpoints();
+        for i in 0..3 {
+            endpoints.mark_failed(i);
+        }
+
+        assert_eq!(endpoints.candidates(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn idempotent_methods() {
+        assert!(is_idempotent(&Method::GET));
+        assert!(is_idempotent(&Method::PUT));
+        assert!(is_idempotent(&Method::DELETE));
+        assert!(!is_idempotent(&Method::POST));
+    }
+
+    #[test]
+    fn rebase() {
+        let endpoints = endpoints();
+        let url = endpoints.primary().join("events?skip=0&limit=50").unwrap();
+
+        assert_eq!(endpoints.rebase(&url, 0), url);
+        assert_eq!(
+            endpoints.rebase(&url, 1).as_str(),
+            "https://backup-1.example.com/events?skip=0&limit=50"
+        );
+        assert_eq!(
+            endpoints.rebase(&url, 2).as_str(),
+            "https://backup-2.example.com/vtn/events?skip=0&limit=50"
+        );
+
+        let elsewhere: Url = "https://elsewhere.example.com/events".parse().unwrap();
+        assert_eq!(endpoints.rebase(&elsewhere, 1), elsewhere);
+    }
+}
@@ -0,0 +1,341 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use openadr_wire::{
+    event::{EventContent, EventId, EventOrder},
+    interval::IntervalPeriod,
+    program::{ProgramContent, ProgramId},
+    resource::ResourceContent,
+    target::TargetLabel,
+    ven::VenContent,
+};
+use std::time::Duration;
+
+use crate::{error::Result, Error, Filter, PaginationOptions, Target};
+
+/// Criteria to select objects on the list endpoints of the VTN.
+///
+/// ```
+/// # use openadr_client::{Filters, Target};
+/// # use chrono::{TimeDelta, Utc};
+/// let now = Utc::now();
+/// let filters = Filters::new()
+///     .target(Target::Group("group-1"))
+///     .active_between(now, now + TimeDelta::hours(24));
+/// ```
+///
+/// The target, program, event and client name criteria, and the time window, are sent to the VTN
+/// as query parameters. As the VTN accepts a single target type per request, the name criterion is
+/// sent to the VTN only if no other target is set. Otherwise, it is applied by the client on the
+/// results. The time window is applied by the client as well, for VTNs that do not support it.
+///
+/// Targets of the same type add up, selecting the objects that target any of their values.
+/// Targets of different types cannot be sent in a single request, so listing objects
+/// with them fails with [`Error::ConflictingTargets`].
+#[derive(Clone, Debug, Default)]
+pub struct Filters<'a> {
+    targets: Vec<(TargetLabel, Vec<&'a str>)>,
+    program_id: Option<&'a ProgramId>,
+    event_id: Option<&'a EventId>,
+    client_name: Option<&'a str>,
+    name: Option<&'a str>,
+    window: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    event_order: Option<EventOrder>,
+    wait: Option<Duration>,
+}
+
+impl<'a> Filters<'a> {
+    /// No criteria, i.e., select all objects
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only select objects targeting the given target.
+    ///
+    /// A target of a type that was given before adds its values to that target,
+    /// such that objects targeting any of the values are selected.
+    pub fn target(mut self, target: Target<'a>) -> Self {
+        let label = target.target_label();
+        match self
+            .targets
+            .iter_mut()
+            .find(|(existing, _)| *existing == label)
+        {
+            Some((_, values)) => values.extend_from_slice(target.target_values()),
+            None => self.targets.push((label, target.target_values().to_vec())),
+        }
+        self
+    }
+
+    /// Only select objects belonging to the given program
+    pub fn program_id(mut self, program_id: &'a ProgramId) -> Self {
+        self.program_id = Some(program_id);
+        self
+    }
+
+    /// Only select reports on the given event
+    pub fn event_id(mut self, event_id: &'a EventId) -> Self {
+        self.event_id = Some(event_id);
+        self
+    }
+
+    /// Only select reports created by the given client
+    pub fn client_name(mut self, client_name: &'a str) -> Self {
+        self.client_name = Some(client_name);
+        self
+    }
+
+    /// Only select programs, events, VENs or resources with the given name
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Only select programs or events that are active somewhere within `start..end`.
+    ///
+    /// Objects without an interval period are assumed to be always active.
+    pub fn active_between(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.window = Some((start, end));
+        self
+    }
+
+    /// Let the VTN order the events, e.g., to find the controlling event in the first page.
+    /// Does not apply to programs and reports.
+    pub fn order_events_by(mut self, order: EventOrder) -> Self {
+        self.event_order = Some(order);
+        self
+    }
+
+    /// Long-poll the event list: the VTN holds the request until any event changes,
+    /// or until `wait` elapses, before sending the first page. Does not apply to programs and reports.
+    ///
+    /// The VTN only supports this if its [capabilities](crate::Client::capabilities) list
+    /// [`Feature::LongPolling`](openadr_wire::capabilities::Feature::LongPolling).
+    /// Make sure the timeout of the HTTP client exceeds `wait`.
+    pub fn wait_for_changes(mut self, wait: Duration) -> Self {
+        self.wait = Some(wait);
+        self
+    }
+
+    /// Build the query parameters for a list endpoint,
+    /// using `name_label` as the target type of the name criterion, if the endpoint supports it.
+    pub(crate) fn query_params(
+        &self,
+        name_label: Option<TargetLabel>,
+        pagination: PaginationOptions,
+    ) -> Result<Vec<(&'static str, String)>> {
+        // VENs and resources cannot be sorted
+        let sortable = !matches!(
+            name_label,
+            Some(TargetLabel::VENName | TargetLabel::ResourceName)
+        );
+        let first_page = pagination.skip == 0;
+        let mut query = self.criteria_query_params(name_label, first_page)?;
+        query.push(("skip", pagination.skip.to_string()));
+        query.push(("limit", pagination.limit.to_string()));
+
+        if let Some(sort_by) = pagination.sort_by.filter(|_| sortable) {
+            query.push(("sortBy", sort_by.as_str().to_string()));
+            if let Some(sort_order) = pagination.sort_order {
+                query.push(("sortOrder", sort_order.as_str().to_string()));
+            }
+        }
+
+        Ok(query)
+    }
+
+    /// Whether the VTN can paginate the event list by cursor, which requires its default order
+    pub(crate) fn allows_event_cursor(&self) -> bool {
+        self.event_order.is_none()
+    }
+
+    /// Build the query parameters for a page of the event list,
+    /// continuing after the event with the given modification time and ID, if any
+    pub(crate) fn event_cursor_query_params(
+        &self,
+        after: Option<(DateTime<Utc>, &EventId)>,
+        limit: usize,
+    ) -> Result<Vec<(&'static str, String)>> {
+        let mut query =
+            self.criteria_query_params(Some(TargetLabel::EventName), after.is_none())?;
+
+        if let Some((modification_date_time, id)) = after {
+            query.push(("afterID", id.to_string()));
+            query.push((
+                "afterModificationDateTime",
+                modification_date_time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+        }
+        query.push(("limit", limit.to_string()));
+
+        Ok(query)
+    }
+
+    /// The query parameters of the criteria the VTN applies, without the pagination
+    fn criteria_query_params(
+        &self,
+        name_label: Option<TargetLabel>,
+        first_page: bool,
+    ) -> Result<Vec<(&'static str, String)>> {
+        if let [_, (label, _), ..] = self.targets.as_slice() {
+            return Err(Error::ConflictingTargets(label.clone()));
+        }
+
+        let mut query = vec![];
+        let is_event_list = name_label == Some(TargetLabel::EventName);
+        let is_program_list = name_label == Some(TargetLabel::ProgramName);
+
+        // endpoints without a name label, i.e., reports, do not support targets either
+        let target = name_label.and_then(|name_label| match (self.targets.first(), &self.name) {
+            (Some((label, values)), _) => Some((label.clone(), values.as_slice())),
+            (None, Some(name)) => Some((name_label, std::slice::from_ref(name))),
+            (None, None) => None,
+        });
+
+        if let Some((label, values)) = target {
+            query.push(("targetType", label.to_string()));
+            for value in values {
+                query.push(("targetValues", value.to_string()));
+            }
+        }
+
+        if let Some(program_id) = self.program_id {
+            query.push(("programID", program_id.to_string()));
+        }
+
+        if let Some(event_id) = self.event_id {
+            query.push(("eventID", event_id.to_string()));
+        }
+
+        if let Some(client_name) = self.client_name {
+            query.push(("clientName", client_name.to_string()));
+        }
+
+        if let Some((start, end)) = self.window.filter(|_| is_event_list || is_program_list) {
+            query.push((
+                "activeAfter",
+                start.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+            query.push((
+                "activeBefore",
+                end.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            ));
+        }
+
+        if let Some(order) = self.event_order.filter(|_| is_event_list) {
+            query.push(("orderBy", order.as_str().to_string()));
+        }
+
+        // only the first page waits, such that the following pages are consistent with it
+        if let Some(wait) = self.wait.filter(|_| is_event_list && first_page) {
+            query.push(("wait", format!("{}s", wait.as_secs())));
+        }
+
+        Ok(query)
+    }
+
+    /// Whether the program matches the criteria the VTN does not apply
+    pub(crate) fn matches_program(&self, program: &ProgramContent) -> bool {
+        let name_matches = match (self.targets.is_empty(), self.name) {
+            (false, Some(name)) => program.program_name == name,
+            _ => true,
+        };
+
+        name_matches && self.in_window(program.interval_period.as_ref())
+    }
+
+    /// Whether the event matches the criteria the VTN does not apply
+    pub(crate) fn matches_event(&self, event: &EventContent) -> bool {
+        let name_matches = match (self.targets.is_empty(), self.name) {
+            (false, Some(name)) => event.event_name.as_deref() == Some(name),
+            _ => true,
+        };
+
+        let in_window = event.intervals.is_empty()
+            || event.intervals.iter().any(|interval| {
+                self.in_window(
+                    interval
+                        .interval_period
+                        .as_ref()
+                        .or(event.interval_period.as_ref()),
+                )
+            });
+
+        name_matches && in_window
+    }
+
+    /// Whether the VEN matches the criteria the VTN does not apply
+    pub(crate) fn matches_ven(&self, ven: &VenContent) -> bool {
+        match (self.targets.is_empty(), self.name) {
+            (false, Some(name)) => ven.ven_name == name,
+            _ => true,
+        }
+    }
+
+    /// Whether the resource matches the criteria the VTN does not apply
+    pub(crate) fn matches_resource(&self, resource: &ResourceContent) -> bool {
+        match (self.targets.is_empty(), self.name) {
+            (false, Some(name)) => resource.resource_name == name,
+            _ => true,
+        }
+    }
+
+    fn in_window(&self, period: Option<&IntervalPeriod>) -> bool {
+        match (self.window, period) {
+            (Some((start, end)), Some(period)) => period.overlaps(start, end),
+            _ => true,
+        }
+    }
+}
+
+impl<'a> From<Filter<'a>> for Filters<'a> {
+    fn from(filter: Filter<'a>) -> Self {
+        match filter {
+            Filter::None => Filters::new(),
+            Filter::By(label, values) => Filters {
+                targets: vec![(label, values.to_vec())],
+                ..Filters::new()
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn target_query(filters: &Filters) -> Result<Vec<(&'static str, String)>> {
+        let query = filters.query_params(Some(TargetLabel::EventName), Default::default())?;
+        Ok(query
+            .into_iter()
+            .filter(|(key, _)| key.starts_with("target"))
+            .collect())
+    }
+
+    #[test]
+    fn targets_of_the_same_type_add_up() {
+        let filters = Filters::new()
+            .target(Target::Group("group-1"))
+            .target(Target::Groups(&["group-2", "group-3"]));
+
+        assert_eq!(
+            target_query(&filters).unwrap(),
+            [
+                ("targetType", "GROUP".to_string()),
+                ("targetValues", "group-1".to_string()),
+                ("targetValues", "group-2".to_string()),
+                ("targetValues", "group-3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn targets_of_different_types_conflict() {
+        let filters = Filters::new()
+            .target(Target::Group("group-1"))
+            .target(Target::Resource("resource-1"));
+
+        assert!(matches!(
+            target_query(&filters),
+            Err(Error::ConflictingTargets(TargetLabel::ResourceName))
+        ));
+    }
+}
@@ -1,36 +1,101 @@
+// the error carries the problem the VTN responded with, which is returned rarely enough not to box it
+#![allow(clippy::result_large_err)]
+
+mod builder;
+mod clock;
+mod diagnostics;
 mod error;
 mod event;
+#[cfg(feature = "zero-copy")]
+mod event_view;
+mod failover;
+mod filters;
+mod listener;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod multi;
 mod program;
 mod report;
+mod report_scheduler;
+mod resource;
+mod schedule;
+mod signature;
+#[cfg(feature = "store")]
+mod store;
+mod sync;
 mod target;
-mod timeline;
+mod tasks;
+#[cfg(feature = "otel")]
+mod telemetry;
+mod throttle;
+mod timeline_stream;
+mod ven;
+mod watch;
 
 use axum::async_trait;
-use openadr_wire::{event::EventId, Event};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use openadr_wire::{
+    auth::{WhoAmI, WHOAMI_PATH},
+    capabilities::{Capabilities, Feature, CAPABILITIES_PATH},
+    event::{EventId, EVENT_SIGNATURE_HEADER},
+    oauth::Scope,
+    problem::Problem,
+    report::{ResourceOperatingState, OPERATING_STATES_PATH},
+    ven::{VenContent, VenId},
+    Event, Report, SortBy, SortOrder, Ven, TOTAL_COUNT_HEADER,
+};
 use std::{
+    collections::HashSet,
     fmt::Debug,
-    sync::Arc,
-    time::{Duration, Instant},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use tokio::sync::RwLock;
 
 use axum::body::Body;
 use http_body_util::BodyExt;
-use reqwest::{Method, RequestBuilder, Response};
+use reqwest::{header::HeaderMap, Method, RequestBuilder, Response, StatusCode};
 use tower::{Service, ServiceExt};
+use tracing::{field, info_span, warn, Instrument};
 use url::Url;
 
+pub use builder::*;
+pub use clock::*;
+pub use diagnostics::*;
 pub use error::*;
 pub use event::*;
+#[cfg(feature = "zero-copy")]
+pub use event_view::*;
+pub use filters::*;
+pub use listener::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+pub use multi::*;
+pub use openadr_wire::timeline::*;
 pub use program::*;
 pub use report::*;
+pub use report_scheduler::*;
+pub use resource::*;
+pub use schedule::*;
+pub use signature::*;
+#[cfg(feature = "store")]
+pub use store::*;
+pub use sync::*;
 pub use target::*;
-pub use timeline::*;
+pub use tasks::*;
+pub use timeline_stream::*;
+pub use ven::*;
+pub use watch::EventUpdate;
 
-use crate::error::Result;
+use crate::{diagnostics::CallTimer, error::Result, failover::Endpoints, throttle::Throttle};
 pub(crate) use openadr_wire::{
     event::EventContent,
     program::{ProgramContent, ProgramId},
+    report::ReportContent,
     target::TargetLabel,
     Program,
 };
@@ -54,6 +119,8 @@ pub struct ClientCredentials {
     client_secret: String,
     pub refresh_margin: Duration,
     pub default_credential_expires_in: Duration,
+    /// The scopes to request, or all scopes of the roles of the client if empty
+    pub scopes: Vec<Scope>,
 }
 
 impl Debug for ClientCredentials {
@@ -65,6 +132,7 @@ impl Debug for ClientCredentials {
                 "default_credential_expires_in",
                 &self.default_credential_expires_in,
             )
+            .field("scopes", &self.scopes)
             .finish_non_exhaustive()
     }
 }
@@ -76,9 +144,16 @@ impl ClientCredentials {
             client_secret,
             refresh_margin: Duration::from_secs(60),
             default_credential_expires_in: Duration::from_secs(3600),
+            scopes: vec![],
         }
     }
 
+    /// Request a token restricted to the `scopes`, e.g., only [`Scope::ReadAll`] for a dashboard
+    pub fn with_scopes(mut self, scopes: impl IntoIterator<Item = Scope>) -> Self {
+        self.scopes = scopes.into_iter().collect();
+        self
+    }
+
     pub fn admin() -> Self {
         Self::new("admin".to_string(), "admin".to_string())
     }
@@ -87,7 +162,22 @@ impl ClientCredentials {
 struct AuthToken {
     token: String,
     expires_in: Duration,
-    since: Instant,
+    since: chrono::DateTime<chrono::Utc>,
+    /// To request the next token without the client credentials, if the VTN issued one
+    refresh_token: Option<String>,
+}
+
+#[derive(Default, serde::Serialize)]
+struct AccessTokenRequest {
+    grant_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_secret: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
 }
 
 impl Debug for AuthToken {
@@ -102,59 +192,109 @@ impl Debug for AuthToken {
 #[derive(Debug)]
 pub struct ClientRef {
     client: Box<dyn HttpClient + Send + Sync>,
-    base_url: Url,
-    default_page_size: usize,
+    endpoints: Endpoints,
+    /// The page size used when retrieving all objects of a kind.
+    /// May shrink when the VTN indicates that it is too large.
+    page_size: AtomicUsize,
+    /// The number of pages retrieved at the same time once the total number of objects is known
+    page_concurrency: usize,
+    /// Whether the VTN listed [`Feature::CursorPagination`] in its capabilities
+    cursor_pagination: AtomicBool,
     auth_data: Option<ClientCredentials>,
     auth_token: RwLock<Option<AuthToken>>,
+    throttle: Throttle,
+    clock: Arc<dyn Clock>,
+    skew: ClockSkew,
+    #[cfg(feature = "metrics")]
+    metrics: Metrics,
+    diagnostics: Option<Arc<Diagnostics>>,
 }
 
 impl ClientRef {
+    /// The current time at the VTN, estimated from the [`Clock`] and the [`ClockSkew`]
+    fn vtn_now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.clock.now() + self.skew.estimate().unwrap_or_default()
+    }
+
     /// This ensures the client is authenticated.
     ///
     /// We follow the process according to RFC 6749, section 4.4 (client
     /// credentials grant). The client id and secret are by default sent via
-    /// HTTP Basic Auth.
+    /// HTTP Basic Auth. If the VTN issued a refresh token with the previous
+    /// token, it is used first (section 6), falling back to the client
+    /// credentials if the VTN rejects it.
     async fn ensure_auth(&self) -> Result<()> {
         // if there is no auth data we don't do any authentication
         let Some(auth_data) = &self.auth_data else {
             return Ok(());
         };
 
-        // if there is a token and it is valid long enough, we don't have to do anything
-        if let Some(token) = self.auth_token.read().await.as_ref() {
-            if token.since.elapsed() < token.expires_in - auth_data.refresh_margin {
-                return Ok(());
+        // if there is a token and it is valid long enough, we don't have to do anything.
+        // The lifetime is measured in the time of the VTN, which issued the token, such that
+        // a jump of the local clock, e.g., by an NTP correction, is compensated with the next response.
+        let refresh_token = match self.auth_token.read().await.as_ref() {
+            Some(token) => {
+                let elapsed = (self.vtn_now() - token.since).to_std().unwrap_or_default();
+                if elapsed < token.expires_in.saturating_sub(auth_data.refresh_margin) {
+                    return Ok(());
+                }
+                token.refresh_token.clone()
             }
-        }
-
-        #[derive(serde::Serialize)]
-        struct AccessTokenRequest {
-            grant_type: &'static str,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            scope: Option<String>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            client_id: Option<String>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            client_secret: Option<String>,
-        }
+            None => None,
+        };
 
         // we should authenticate
-        let auth_url = self.base_url.join("auth/token")?;
-        let request =
-            self.client
-                .request_builder(Method::POST, auth_url)
+        let auth_url = self.endpoints.primary().join("auth/token")?;
+
+        if let Some(refresh_token) = refresh_token {
+            let request = self
+                .client
+                .request_builder(Method::POST, auth_url.clone())
                 .form(&AccessTokenRequest {
-                    grant_type: "client_credentials",
-                    scope: None,
-                    client_id: None,
-                    client_secret: None,
+                    grant_type: "refresh_token",
+                    refresh_token: Some(refresh_token),
+                    ..Default::default()
                 });
-        let request = request.basic_auth(&auth_data.client_id, Some(&auth_data.client_secret));
+            match self.request_token(request, auth_data).await {
+                Ok(()) => return Ok(()),
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        "could not refresh the token, using the client credentials"
+                    )
+                }
+            }
+        }
+
+        let request = self
+            .client
+            .request_builder(Method::POST, auth_url)
+            .form(&AccessTokenRequest {
+                grant_type: "client_credentials",
+                scope: (!auth_data.scopes.is_empty())
+                    .then(|| Scope::format_list(&auth_data.scopes)),
+                ..Default::default()
+            })
+            .basic_auth(&auth_data.client_id, Some(&auth_data.client_secret));
+        self.request_token(request, auth_data).await
+    }
+
+    /// Send a request to the token endpoint, and store the token of the response
+    async fn request_token(
+        &self,
+        request: RequestBuilder,
+        auth_data: &ClientCredentials,
+    ) -> Result<()> {
         let request = request.header("Accept", "application/json");
-        let since = Instant::now();
-        let res = self.client.send(request).await?;
+        // the token was issued after the request was sent, converted to the time of the VTN
+        // only once the response updated the skew, which may be unknown before the first response
+        let sent = self.clock.now();
+        let res = self.send(request).await?;
+        let since = sent + self.skew.estimate().unwrap_or_default();
         if !res.status().is_success() {
-            let problem = res.json::<openadr_wire::oauth::OAuthError>().await?;
+            let problem = self
+                .read_json::<openadr_wire::oauth::OAuthError>(res)
+                .await?;
             return Err(Error::AuthProblem(problem));
         }
 
@@ -164,16 +304,15 @@ impl ClientRef {
             token_type: String,
             #[serde(default)]
             expires_in: Option<u64>,
-            // Refresh tokens aren't supported currently
-            // #[serde(default)]
-            // refresh_token: Option<String>,
+            #[serde(default)]
+            refresh_token: Option<String>,
             // #[serde(default)]
             // scope: Option<String>,
             // #[serde(flatten)]
             // other: std::collections::HashMap<String, serde_json::Value>,
         }
 
-        let auth_result = res.json::<AuthResult>().await?;
+        let auth_result = self.read_json::<AuthResult>(res).await?;
         if auth_result.token_type.to_lowercase() != "bearer" {
             return Err(Error::OAuthTokenNotBearer);
         }
@@ -184,6 +323,7 @@ impl ClientRef {
                 .map(Duration::from_secs)
                 .unwrap_or(auth_data.default_credential_expires_in),
             since,
+            refresh_token: auth_result.refresh_token,
         };
 
         *self.auth_token.write().await = Some(token);
@@ -192,10 +332,38 @@ impl ClientRef {
 
     async fn request<T: serde::de::DeserializeOwned>(
         &self,
-        mut request: RequestBuilder,
+        request: RequestBuilder,
         query: &[(&str, &str)],
     ) -> Result<T> {
+        let (body, _) = self.request_with_headers(request, query).await?;
+        Ok(body)
+    }
+
+    async fn request_with_headers<T: serde::de::DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        query: &[(&str, &str)],
+    ) -> Result<(T, HeaderMap)> {
+        let mut timer = self.start_timer(&request);
+        let (body, headers, url) = self.request_body(request, query, &mut timer).await?;
+        let body = decode(url, &body)?;
+        lap(&mut timer, Phase::Deserialize);
+        self.finish_timer(timer);
+
+        Ok((body, headers))
+    }
+
+    /// Send the request, returning the raw body of a successful response,
+    /// with the URL it was received from
+    async fn request_body(
+        &self,
+        mut request: RequestBuilder,
+        query: &[(&str, &str)],
+        timer: &mut Option<CallTimer>,
+    ) -> Result<(axum::body::Bytes, HeaderMap, Url)> {
         self.ensure_auth().await?;
+        lap(timer, Phase::Auth);
+
         request = request.header("Accept", "application/json");
         if !query.is_empty() {
             request = request.query(&query);
@@ -208,15 +376,170 @@ impl ClientRef {
                 request = request.bearer_auth(&token.token);
             }
         }
-        let res = self.client.send(request).await?;
+
+        // the request is in flight until its body is received, which is when the permit is dropped
+        let _permit = self.throttle.acquire().await;
+        lap(timer, Phase::Throttle);
+        let res = self.send(request).await?;
+        lap(timer, Phase::TimeToFirstByte);
 
         // handle any errors returned by the server
+        if res.status() == StatusCode::SERVICE_UNAVAILABLE {
+            return Err(self.read_unavailable(res).await);
+        }
         if !res.status().is_success() {
-            let problem = res.json::<openadr_wire::problem::Problem>().await?;
-            return Err(crate::error::Error::from(problem));
+            let problem = self
+                .read_json::<openadr_wire::problem::Problem>(res)
+                .await?;
+            let err = crate::error::Error::from(problem);
+            if matches!(err, Error::Unauthorized(_)) {
+                *self.auth_token.write().await = None;
+            }
+            return Err(err);
         }
 
-        Ok(res.json().await?)
+        let headers = res.headers().clone();
+        let url = res.url().clone();
+        let body = self.read_body(res).await?;
+        lap(timer, Phase::Download);
+
+        Ok((body, headers, url))
+    }
+
+    fn start_timer(&self, request: &RequestBuilder) -> Option<CallTimer> {
+        self.diagnostics.as_ref().map(|_| {
+            let (method, url) = request
+                .try_clone()
+                .and_then(|request| request.build().ok())
+                .map(|request| (request.method().clone(), request.url().to_string()))
+                .unwrap_or_default();
+            CallTimer::start(method, url)
+        })
+    }
+
+    fn finish_timer(&self, timer: Option<CallTimer>) {
+        if let (Some(timer), Some(diagnostics)) = (timer, &self.diagnostics) {
+            timer.finish(diagnostics);
+        }
+    }
+
+    /// Send the request to the VTN in a span of its own,
+    /// updating the [`ClockSkew`] with the `Date` of the response.
+    ///
+    /// With the `otel` feature, the trace context of the span is sent in a `traceparent` header.
+    async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let (client, request) = request.build_split();
+        let request = request?;
+        let span = info_span!(
+            "vtn_request",
+            method = %request.method(),
+            path = %request.url().path(),
+            status = field::Empty,
+        );
+        #[cfg(feature = "otel")]
+        let request = telemetry::with_trace_context(&span, request);
+
+        let sent = self.clock.now();
+        let res = self
+            .send_with_failover(RequestBuilder::from_parts(client, request))
+            .instrument(span.clone())
+            .await?;
+        span.record("status", res.status().as_u16());
+
+        if let Some(date) = res
+            .headers()
+            .get(reqwest::header::DATE)
+            .and_then(|date| date.to_str().ok())
+        {
+            self.skew.update(date, sent, self.clock.now());
+        }
+
+        Ok(res)
+    }
+
+    /// Send the request to the VTN, failing over to the fallback URLs if it is unreachable
+    async fn send_with_failover(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_request(&request);
+
+        if !self.endpoints.has_fallbacks() {
+            return self.client.send(request).await;
+        }
+
+        let (client, request) = request.build_split();
+        let request = request?;
+        let candidates = self.endpoints.candidates();
+
+        for (attempt, &index) in candidates.iter().enumerate() {
+            let is_last = attempt + 1 == candidates.len();
+
+            // cloning fails for streaming bodies, which the client never sends
+            let Some(mut rebased) = request.try_clone() else {
+                break;
+            };
+            *rebased.url_mut() = self.endpoints.rebase(request.url(), index);
+
+            match self
+                .client
+                .send(RequestBuilder::from_parts(client.clone(), rebased))
+                .await
+            {
+                Err(err) if failover::may_fail_over(&err, request.method()) => {
+                    self.endpoints.mark_failed(index);
+                    if is_last {
+                        return Err(err);
+                    }
+                    warn!(url = %self.endpoints.url(index), %err, "VTN unreachable, trying the next URL");
+                }
+                result => {
+                    self.endpoints.mark_reachable(index);
+                    return result;
+                }
+            }
+        }
+
+        self.client
+            .send(RequestBuilder::from_parts(client, request))
+            .await
+    }
+
+    /// A `503 Service Unavailable` may also be sent by a proxy in front of the VTN,
+    /// so the body is not required to be a problem.
+    /// Only a `Retry-After` in seconds is supported, not an HTTP date.
+    async fn read_unavailable(&self, res: Response) -> Error {
+        let status = res.status();
+        let retry_after = res
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|retry_after| retry_after.to_str().ok())
+            .and_then(|retry_after| retry_after.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        let problem = self.read_json(res).await.unwrap_or_else(|_| Problem {
+            title: Some(status.to_string()),
+            status,
+            ..Default::default()
+        });
+
+        Error::ServiceUnavailable {
+            problem,
+            retry_after,
+        }
+    }
+
+    async fn read_json<T: serde::de::DeserializeOwned>(&self, res: Response) -> Result<T> {
+        let url = res.url().clone();
+        let body = self.read_body(res).await?;
+        decode(url, &body)
+    }
+
+    async fn read_body(&self, res: Response) -> Result<axum::body::Bytes> {
+        let body = res.bytes().await?;
+
+        #[cfg(feature = "metrics")]
+        self.metrics.record_response(body.len());
+
+        Ok(body)
     }
 
     async fn get<T: serde::de::DeserializeOwned>(
@@ -224,17 +547,58 @@ impl ClientRef {
         path: &str,
         query: &[(&str, &str)],
     ) -> Result<T> {
-        let url = self.base_url.join(path)?;
+        let url = self.endpoints.primary().join(path)?;
         let request = self.client.request_builder(Method::GET, url);
         self.request(request, query).await
     }
 
+    async fn get_with_headers<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<(T, HeaderMap)> {
+        let url = self.endpoints.primary().join(path)?;
+        let request = self.client.request_builder(Method::GET, url);
+        self.request_with_headers(request, query).await
+    }
+
+    /// Retrieve a page of a list endpoint without deserializing it,
+    /// together with the total number of matching objects if the VTN sends it
+    #[cfg(feature = "zero-copy")]
+    async fn get_raw_page(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<(axum::body::Bytes, Option<usize>)> {
+        let url = self.endpoints.primary().join(path)?;
+        let request = self.client.request_builder(Method::GET, url);
+
+        let mut timer = self.start_timer(&request);
+        let (body, headers, _) = self.request_body(request, query, &mut timer).await?;
+        self.finish_timer(timer);
+
+        Ok((body, total_count(&headers)))
+    }
+
+    /// Retrieve a page of a list endpoint, together with the total number of matching objects
+    /// if the VTN sends it
+    async fn get_page<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Page<T>> {
+        let (items, headers) = self.get_with_headers(path, query).await?;
+        let total = total_count(&headers);
+
+        Ok(Page { items, total })
+    }
+
     async fn post<S, T>(&self, path: &str, body: &S, query: &[(&str, &str)]) -> Result<T>
     where
         S: serde::ser::Serialize + Sync,
         T: serde::de::DeserializeOwned,
     {
-        let url = self.base_url.join(path)?;
+        let url = self.endpoints.primary().join(path)?;
         let request = self.client.request_builder(Method::POST, url).json(body);
         self.request(request, query).await
     }
@@ -244,25 +608,195 @@ impl ClientRef {
         S: serde::ser::Serialize + Sync,
         T: serde::de::DeserializeOwned,
     {
-        let url = self.base_url.join(path)?;
+        let url = self.endpoints.primary().join(path)?;
         let request = self.client.request_builder(Method::PUT, url).json(body);
         self.request(request, query).await
     }
 
+    /// Like [`Self::put`], but sending the body with a custom JSON content type
+    async fn put_as<S, T>(&self, path: &str, body: &S, content_type: &'static str) -> Result<T>
+    where
+        S: serde::ser::Serialize + Sync,
+        T: serde::de::DeserializeOwned,
+    {
+        let url = self.endpoints.primary().join(path)?;
+        let request = self
+            .client
+            .request_builder(Method::PUT, url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(serde_json::to_vec(body)?);
+        self.request(request, &[]).await
+    }
+
     async fn delete<T>(&self, path: &str, query: &[(&str, &str)]) -> Result<T>
     where
         T: serde::de::DeserializeOwned,
     {
-        let url = self.base_url.join(path)?;
+        let url = self.endpoints.primary().join(path)?;
         let request = self.client.request_builder(Method::DELETE, url);
         self.request(request, query).await
     }
 
-    fn default_page_size(&self) -> usize {
-        self.default_page_size
+    fn page_size(&self) -> usize {
+        self.page_size.load(Ordering::Relaxed)
+    }
+
+    /// Lower the page size after the VTN rejected a request with the given page size.
+    /// Returns whether it makes sense to retry with the new page size.
+    fn reduce_page_size(&self, rejected: usize) -> bool {
+        let reduced = rejected / 2;
+        if reduced == 0 {
+            return false;
+        }
+
+        warn!(
+            rejected,
+            reduced, "VTN rejected the page size, retrying with a smaller page size"
+        );
+        self.page_size.fetch_min(reduced, Ordering::Relaxed);
+        true
+    }
+
+    async fn get_reports_page(
+        self: &Arc<Self>,
+        filters: &Filters<'_>,
+        pagination: PaginationOptions,
+    ) -> Result<Page<ReportClient>> {
+        let query = filters.query_params(None, pagination)?;
+
+        let reports: Page<Report> = self.get_page("reports", &borrow_query(&query)).await?;
+        Ok(reports.map(|report| ReportClient::from_report(self.clone(), report)))
+    }
+
+    async fn get_reports_matching(
+        self: &Arc<Self>,
+        filters: &Filters<'_>,
+    ) -> Result<Vec<ReportClient>> {
+        self.get_all_pages(|pagination| self.get_reports_page(filters, pagination))
+            .await
+    }
+
+    /// Retrieve all pages of a list endpoint and concatenate the results.
+    ///
+    /// The pagination ends once the total number of objects the VTN sent in the
+    /// [`TOTAL_COUNT_HEADER`] is received. VTNs that do not send this header end the pagination
+    /// with a page shorter than the requested `limit`.
+    /// Once the total is known, the remaining pages are retrieved concurrently,
+    /// up to the [`ClientBuilder::page_concurrency`] at the same time.
+    ///
+    /// If the VTN rejects the `limit` of a page, the page size of the client is reduced,
+    /// and the request is retried.
+    async fn get_all_pages<T, F, Fut>(&self, fetch_page: F) -> Result<Vec<T>>
+    where
+        F: Fn(PaginationOptions) -> Fut,
+        Fut: Future<Output = Result<Page<T>>>,
+    {
+        let mut items = vec![];
+
+        loop {
+            let page_size = self.page_size();
+            let pagination = PaginationOptions {
+                skip: items.len(),
+                limit: page_size,
+                ..Default::default()
+            };
+
+            let received = match fetch_page(pagination).await {
+                Ok(received) => received,
+                Err(Error::Problem(problem))
+                    if is_limit_rejection(&problem) && self.reduce_page_size(page_size) =>
+                {
+                    continue
+                }
+                Err(err) => return Err(err),
+            };
+
+            // an empty page ends the pagination even if objects were deleted in the meantime
+            let received_all = match received.total {
+                Some(total) => {
+                    received.items.is_empty() || items.len() + received.items.len() >= total
+                }
+                None => received.items.len() < page_size,
+            };
+            let received_full_page = received.items.len() == page_size;
+            items.extend(received.items);
+
+            // a shorter page than requested means the VTN limits the page size further,
+            // so the offsets of the remaining pages are not known in advance
+            if !received_all && received_full_page && self.page_concurrency > 1 {
+                if let Some(total) = received.total {
+                    let pages = stream::iter((items.len()..total).step_by(page_size))
+                        .map(|skip| {
+                            fetch_page(PaginationOptions {
+                                skip,
+                                limit: page_size,
+                                ..Default::default()
+                            })
+                        })
+                        .buffered(self.page_concurrency)
+                        .try_collect::<Vec<_>>()
+                        .await?;
+
+                    items.extend(pages.into_iter().flat_map(|page| page.items));
+                    return Ok(self.received_all(items));
+                }
+            }
+
+            if received_all {
+                return Ok(self.received_all(items));
+            }
+        }
+    }
+
+    fn received_all<T>(&self, items: Vec<T>) -> Vec<T> {
+        #[cfg(feature = "metrics")]
+        self.metrics.record_list_call(items.len());
+
+        items
     }
 }
 
+fn total_count(headers: &HeaderMap) -> Option<usize> {
+    headers
+        .get(TOTAL_COUNT_HEADER)
+        .and_then(|total| total.to_str().ok())
+        .and_then(|total| total.parse().ok())
+}
+
+/// Deserialize the body of a response, keeping the URL it was received from in the error
+fn decode<T: serde::de::DeserializeOwned>(url: Url, body: &[u8]) -> Result<T> {
+    serde_json::from_slice(body).map_err(|source| Error::InvalidResponse { url, source })
+}
+
+/// End the current phase of the call, if it is timed
+fn lap(timer: &mut Option<CallTimer>, phase: Phase) {
+    if let Some(timer) = timer {
+        timer.lap(phase);
+    }
+}
+
+fn borrow_query<'a>(query: &'a [(&'static str, String)]) -> Vec<(&'a str, &'a str)> {
+    query
+        .iter()
+        .map(|(key, value)| (*key, value.as_str()))
+        .collect()
+}
+
+/// Whether the VTN rejected the `limit` of a list request,
+/// which it lists in the `invalidParams` of the problem
+fn is_limit_rejection(problem: &Problem) -> bool {
+    problem.status == StatusCode::BAD_REQUEST
+        && problem
+            .extensions
+            .get("invalidParams")
+            .and_then(serde_json::Value::as_array)
+            .is_some_and(|params| {
+                params.iter().any(|param| {
+                    param.get("name").and_then(serde_json::Value::as_str) == Some("limit")
+                })
+            })
+}
+
 #[derive(Debug)]
 pub struct ReqwestClientRef {
     client: reqwest::Client,
@@ -292,15 +826,17 @@ impl MockClientRef {
     }
 
     pub fn into_client(self, auth: Option<ClientCredentials>) -> Client {
-        let client = ClientRef {
-            client: Box::new(self),
-            base_url: Url::parse("https://example.com/").unwrap(),
-            default_page_size: 50,
-            auth_data: auth,
-            auth_token: RwLock::new(None),
-        };
+        let mut builder = ClientBuilder::new(Url::parse("https://example.com/").unwrap());
+        if let Some(auth) = auth {
+            builder = builder.credentials(auth);
+        }
 
-        Client::new(client)
+        self.into_client_with(builder)
+    }
+
+    /// Create a client configured by the builder, which sends all requests to the router
+    pub fn into_client_with(self, builder: ClientBuilder) -> Client {
+        builder.build_with(Box::new(self))
     }
 }
 
@@ -313,13 +849,14 @@ impl HttpClient for MockClientRef {
     async fn send(&self, req: RequestBuilder) -> reqwest::Result<Response> {
         let request = axum::http::Request::try_from(req.build().unwrap()).unwrap();
 
-        let response =
-            ServiceExt::<axum::http::Request<Body>>::ready(&mut *self.router.lock().await)
-                .await
-                .unwrap()
-                .call(request)
-                .await
-                .unwrap();
+        // don't hold the lock during the call, such that concurrent requests are not serialized
+        let mut router = self.router.lock().await.clone();
+        let response = ServiceExt::<axum::http::Request<Body>>::ready(&mut router)
+            .await
+            .unwrap()
+            .call(request)
+            .await
+            .unwrap();
 
         let (parts, body) = response.into_parts();
         let body = body.collect().await.unwrap().to_bytes();
@@ -330,11 +867,36 @@ impl HttpClient for MockClientRef {
     }
 }
 
+/// A page of a list endpoint, see [`ClientRef::get_all_pages`]
+struct Page<T> {
+    items: Vec<T>,
+    /// The total number of objects matching the query, if the VTN sent it
+    total: Option<usize>,
+}
+
+impl<T> Page<T> {
+    fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+        }
+    }
+}
+
+/// The page of a list endpoint to retrieve
+///
+/// The `sort_by` and `sort_order` only apply to programs, events and reports,
+/// which are otherwise listed in the default order of the VTN.
+/// A `sort_by` cannot be combined with [`Filters::order_events_by`].
+#[derive(Clone, Copy, Debug, Default)]
 pub struct PaginationOptions {
     pub skip: usize,
     pub limit: usize,
+    pub sort_by: Option<SortBy>,
+    pub sort_order: Option<SortOrder>,
 }
 
+/// A single target criterion, see [`Filters`] to combine multiple criteria
 pub enum Filter<'a> {
     None,
     By(TargetLabel, &'a [&'a str]),
@@ -354,15 +916,17 @@ impl Client {
         client: reqwest::Client,
         auth: Option<ClientCredentials>,
     ) -> Self {
-        let client_ref = ClientRef {
-            client: Box::new(ReqwestClientRef { client }),
-            base_url,
-            default_page_size: 50,
-            auth_data: auth,
-            auth_token: RwLock::new(None),
-        };
+        let mut builder = ClientBuilder::new(base_url).reqwest_client(client);
+        if let Some(auth) = auth {
+            builder = builder.credentials(auth);
+        }
+
+        builder.build()
+    }
 
-        Self::new(client_ref)
+    /// Configure a client in more detail, see [`ClientBuilder`]
+    pub fn builder(base_url: Url) -> ClientBuilder {
+        ClientBuilder::new(base_url)
     }
 
     fn new(client_ref: ClientRef) -> Self {
@@ -371,6 +935,48 @@ impl Client {
         }
     }
 
+    /// The clock of this client, see [`ClientBuilder::clock`]
+    pub fn clock(&self) -> &dyn Clock {
+        self.client_ref.clock.as_ref()
+    }
+
+    /// The time of the VTN minus the time of the [`Clock`] of this client,
+    /// estimated from the `Date` header of the last response of the VTN with a precision of about a second.
+    /// Positive if the clock of the VTN is ahead.
+    ///
+    /// `None` until the client received a response with a `Date` header.
+    pub fn estimated_skew(&self) -> Option<chrono::TimeDelta> {
+        self.client_ref.skew.estimate()
+    }
+
+    /// The current time according to the VTN, i.e., the [`Clock`] corrected by the [`Self::estimated_skew`].
+    ///
+    /// Use this time to decide which interval of a [`Timeline`] is active,
+    /// such that the VEN activates intervals at the moment the VTN intended,
+    /// even if the local clock is off.
+    pub fn vtn_now(&self) -> chrono::DateTime<chrono::Utc> {
+        self.client_ref.vtn_now()
+    }
+
+    /// A [`Clock`] following [`Self::vtn_now`], e.g., to schedule the activation of intervals
+    pub fn vtn_clock(&self) -> VtnClock {
+        VtnClock {
+            client_ref: self.client_ref.clone(),
+        }
+    }
+
+    /// The counters of the traffic between this client and the VTN
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> &Metrics {
+        &self.client_ref.metrics
+    }
+
+    /// The timings of the calls to the VTN,
+    /// if enabled with [`ClientBuilder::diagnostics`]
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        self.client_ref.diagnostics.as_deref()
+    }
+
     /// Create a new program on the VTN
     pub async fn create_program(&self, program_content: ProgramContent) -> Result<ProgramClient> {
         let program = self
@@ -380,106 +986,94 @@ impl Client {
         Ok(ProgramClient::from_program(self.clone(), program))
     }
 
+    /// Create the program and the events of a bundle, e.g., one exported with
+    /// [`ProgramClient::export_bundle`] from another VTN.
+    ///
+    /// If any event cannot be created, the events created so far and the program are deleted again.
+    pub async fn import_bundle(&self, bundle: ProgramBundle) -> Result<ProgramClient> {
+        let program = self.create_program(bundle.program).await?;
+
+        let mut events = Vec::with_capacity(bundle.events.len());
+        for mut event in bundle.events {
+            event.program_id = program.id().clone();
+
+            match program.create_event(event).await {
+                Ok(event) => events.push(event),
+                Err(err) => {
+                    for event in events {
+                        if let Err(err) = event.delete().await {
+                            warn!(?err, "could not delete event of failed import");
+                        }
+                    }
+                    if let Err(err) = program.delete().await {
+                        warn!(?err, "could not delete program of failed import");
+                    }
+
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(program)
+    }
+
     /// Lowlevel operation that gets a list of programs from the VTN with the given query parameters
-    pub async fn get_programs(
+    pub async fn get_programs<'a>(
         &self,
-        filter: Filter<'_>,
+        filters: impl Into<Filters<'a>>,
         pagination: PaginationOptions,
     ) -> Result<Vec<ProgramClient>> {
-        // convert query params
-        let skip_str = pagination.skip.to_string();
-        let limit_str = pagination.limit.to_string();
-
-        // insert into query params
-        let mut query = vec![];
-
-        if let Filter::By(ref target_label, target_values) = filter {
-            query.push(("targetType", target_label.as_str()));
-
-            for target_value in target_values {
-                query.push(("targetValues", *target_value));
-            }
-        }
+        let filters = filters.into();
+        let mut programs = self.get_programs_page(&filters, pagination).await?.items;
+        programs.retain(|program| filters.matches_program(program.content()));
+        Ok(programs)
+    }
 
-        query.push(("skip", &skip_str));
-        query.push(("limit", &limit_str));
+    async fn get_programs_page(
+        &self,
+        filters: &Filters<'_>,
+        pagination: PaginationOptions,
+    ) -> Result<Page<ProgramClient>> {
+        let query = filters.query_params(Some(TargetLabel::ProgramName), pagination)?;
 
         // send request and return response
-        let programs: Vec<Program> = self.client_ref.get("programs", &query).await?;
-        Ok(programs
-            .into_iter()
-            .map(|program| ProgramClient::from_program(self.clone(), program))
-            .collect())
+        let programs: Page<Program> = self
+            .client_ref
+            .get_page("programs", &borrow_query(&query))
+            .await?;
+        Ok(programs.map(|program| ProgramClient::from_program(self.clone(), program)))
     }
 
     /// Get a list of programs from the VTN with the given query parameters
     pub async fn get_program_list(&self, target: Target<'_>) -> Result<Vec<ProgramClient>> {
-        let page_size = self.client_ref.default_page_size();
-        let mut programs = vec![];
-        let mut page = 0;
-        loop {
-            let pagination = PaginationOptions {
-                skip: page * page_size,
-                limit: page_size,
-            };
-
-            let received = self
-                .get_programs(
-                    Filter::By(target.target_label(), target.target_values()),
-                    pagination,
-                )
-                .await?;
-            let received_all = received.len() < page_size;
-            for program in received {
-                programs.push(program);
-            }
-
-            if received_all {
-                break;
-            } else {
-                page += 1;
-            }
-        }
+        self.get_programs_matching(Filters::new().target(target))
+            .await
+    }
 
+    /// Get all programs matching the filters, trying to paginate whenever possible
+    pub async fn get_programs_matching(&self, filters: Filters<'_>) -> Result<Vec<ProgramClient>> {
+        let mut programs = self
+            .client_ref
+            .get_all_pages(|pagination| self.get_programs_page(&filters, pagination))
+            .await?;
+        programs.retain(|program| filters.matches_program(program.content()));
         Ok(programs)
     }
 
     /// Get all programs from the VTN, trying to paginate whenever possible
     pub async fn get_all_programs(&self) -> Result<Vec<ProgramClient>> {
-        let page_size = self.client_ref.default_page_size();
-        let mut programs = vec![];
-
-        for page in 0.. {
-            // TODO: this pagination should really depend on that the server indicated there are more results
-            let pagination = PaginationOptions {
-                skip: page * page_size,
-                limit: page_size,
-            };
-
-            let received = self.get_programs(Filter::None, pagination).await?;
-            let received_all = received.len() < page_size;
-            for program in received {
-                programs.push(program);
-            }
-
-            if received_all {
-                break;
-            }
-        }
-
-        Ok(programs)
+        self.get_programs_matching(Filters::new()).await
     }
 
     /// Get a program by name
     pub async fn get_program_by_name(&self, name: &str) -> Result<ProgramClient> {
-        let target = Target::Program(name);
-
-        let pagination = PaginationOptions { skip: 0, limit: 2 };
+        let pagination = PaginationOptions {
+            skip: 0,
+            limit: 2,
+            ..Default::default()
+        };
         let mut programs = self
-            .get_programs(
-                Filter::By(target.target_label(), target.target_values()),
-                pagination,
-            )
+            .get_programs(Filters::new().name(name), pagination)
             .await?;
 
         match programs[..] {
@@ -506,38 +1100,54 @@ impl Client {
     }
 
     /// Lowlevel operation that gets a list of events from the VTN with the given query parameters
-    pub async fn get_events(
+    pub async fn get_events<'a>(
         &self,
-        program_id: Option<&ProgramId>,
-        filter: Filter<'_>,
+        program_id: Option<&'a ProgramId>,
+        filters: impl Into<Filters<'a>>,
         pagination: PaginationOptions,
     ) -> Result<Vec<EventClient>> {
-        let mut query = vec![];
-
-        if let Filter::By(ref target_label, target_values) = filter {
-            query.push(("targetType", target_label.as_str()));
-
-            for target_value in target_values {
-                query.push(("targetValues", *target_value));
-            }
-        }
-
+        let mut filters = filters.into();
         if let Some(program_id) = program_id {
-            query.push(("programID", program_id.as_str()));
+            filters = filters.program_id(program_id);
         }
 
-        let skip_str = pagination.skip.to_string();
-        let limit_str = pagination.limit.to_string();
+        let mut events = self.get_events_page(&filters, pagination).await?.items;
+        events.retain(|event| filters.matches_event(event.content()));
+        Ok(events)
+    }
 
-        query.push(("skip", &skip_str));
-        query.push(("limit", &limit_str));
+    async fn get_events_page(
+        &self,
+        filters: &Filters<'_>,
+        pagination: PaginationOptions,
+    ) -> Result<Page<EventClient>> {
+        let query = filters.query_params(Some(TargetLabel::EventName), pagination)?;
 
         // send request and return response
-        let events: Vec<Event> = self.client_ref.get("events", &query).await?;
-        Ok(events
-            .into_iter()
-            .map(|event| EventClient::from_event(self.client_ref.clone(), event))
-            .collect())
+        let events: Page<Event> = self
+            .client_ref
+            .get_page("events", &borrow_query(&query))
+            .await?;
+        Ok(events.map(|event| EventClient::from_event(self.client_ref.clone(), event)))
+    }
+
+    /// Get a page of events as received from the VTN, to read borrowed [`EventView`]s from,
+    /// avoiding to allocate every string of the events when polling at a high frequency.
+    ///
+    /// Unlike [`Client::get_events`], the events are only filtered by the VTN.
+    #[cfg(feature = "zero-copy")]
+    pub async fn get_events_buffer(
+        &self,
+        filters: &Filters<'_>,
+        pagination: PaginationOptions,
+    ) -> Result<EventsBuffer> {
+        let query = filters.query_params(Some(TargetLabel::EventName), pagination)?;
+        let (body, total) = self
+            .client_ref
+            .get_raw_page("events", &borrow_query(&query))
+            .await?;
+
+        Ok(EventsBuffer::new(body, total))
     }
 
     /// Get a list of events from the VTN with the given query parameters
@@ -546,63 +1156,95 @@ impl Client {
         program_id: Option<&ProgramId>,
         target: Target<'_>,
     ) -> Result<Vec<EventClient>> {
-        let page_size = self.client_ref.default_page_size();
-        let mut events = vec![];
-        let mut page = 0;
-        loop {
-            let pagination = PaginationOptions {
-                skip: page * page_size,
-                limit: page_size,
-            };
-
-            let received = self
-                .get_events(
-                    program_id,
-                    Filter::By(target.target_label(), target.target_values()),
-                    pagination,
-                )
-                .await?;
-            let received_all = received.len() < page_size;
-            for event in received {
-                events.push(event);
-            }
-
-            if received_all {
-                break;
-            } else {
-                page += 1;
-            }
+        let mut filters = Filters::new().target(target);
+        if let Some(program_id) = program_id {
+            filters = filters.program_id(program_id);
         }
 
+        self.get_events_matching(filters).await
+    }
+
+    /// Get all events matching the filters, trying to paginate whenever possible.
+    ///
+    /// Once the [capabilities](Client::capabilities) of the VTN are known to list
+    /// [`Feature::CursorPagination`], each page continues after the last event of the previous page,
+    /// unless the events are [ordered](Filters::order_events_by) otherwise.
+    pub async fn get_events_matching(&self, filters: Filters<'_>) -> Result<Vec<EventClient>> {
+        let mut events = if self.client_ref.cursor_pagination.load(Ordering::Relaxed)
+            && filters.allows_event_cursor()
+        {
+            self.get_events_by_cursor(&filters).await?
+        } else {
+            self.client_ref
+                .get_all_pages(|pagination| self.get_events_page(&filters, pagination))
+                .await?
+        };
+        events.retain(|event| filters.matches_event(event.content()));
         Ok(events)
     }
 
-    /// Get all events from the VTN, trying to paginate whenever possible
-    pub async fn get_all_events(&self) -> Result<Vec<EventClient>> {
-        let page_size = self.client_ref.default_page_size();
-        let mut events = vec![];
-        let mut page = 0;
+    /// Retrieve all pages of the event list, each continuing after the last event of the previous page
+    async fn get_events_by_cursor(&self, filters: &Filters<'_>) -> Result<Vec<EventClient>> {
+        let mut events: Vec<EventClient> = vec![];
+
         loop {
-            // TODO: this pagination should really depend on that the server indicated there are more results
-            let pagination = PaginationOptions {
-                skip: page * page_size,
-                limit: page_size,
+            let page_size = self.client_ref.page_size();
+            let after = events
+                .last()
+                .map(|event| (event.modification_date_time(), event.id()));
+            let query = filters.event_cursor_query_params(after, page_size)?;
+
+            let received: Page<Event> = match self
+                .client_ref
+                .get_page("events", &borrow_query(&query))
+                .await
+            {
+                Ok(received) => received,
+                Err(Error::Problem(problem))
+                    if is_limit_rejection(&problem)
+                        && self.client_ref.reduce_page_size(page_size) =>
+                {
+                    continue
+                }
+                Err(err) => return Err(err),
             };
 
-            let received = self.get_events(None, Filter::None, pagination).await?;
-            let received_all = received.len() < page_size;
-            for event in received {
-                events.push(event);
-            }
+            let received_all = received.items.len() < page_size;
+            events.extend(
+                received
+                    .items
+                    .into_iter()
+                    .map(|event| EventClient::from_event(self.client_ref.clone(), event)),
+            );
 
             if received_all {
                 break;
-            } else {
-                page += 1;
             }
         }
 
-        Ok(events)
+        // an event updated during the pagination moves to the end, keep its latest version
+        let mut seen = HashSet::new();
+        events.reverse();
+        events.retain(|event| seen.insert(event.id().clone()));
+        events.reverse();
+
+        Ok(self.client_ref.received_all(events))
+    }
+
+    /// Get all events from the VTN, trying to paginate whenever possible
+    pub async fn get_all_events(&self) -> Result<Vec<EventClient>> {
+        self.get_events_matching(Filters::new()).await
+    }
+
+    /// Get all events that are active somewhere within `start..end`, e.g., the upcoming events.
+    /// See [`Filters::active_between`].
+    pub async fn get_events_in_range(
+        &self,
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<EventClient>> {
+        self.get_events_matching(Filters::new().active_between(start, end))
+            .await
     }
 
     /// Get a event by id
@@ -614,4 +1256,151 @@ impl Client {
 
         Ok(EventClient::from_event(self.client_ref.clone(), event))
     }
+
+    /// Create a new VEN on the VTN, e.g., for a VEN to register itself
+    pub async fn create_ven(&self, ven_content: VenContent) -> Result<VenClient> {
+        let ven = self.client_ref.post("vens", &ven_content, &[]).await?;
+        Ok(VenClient::from_ven(self.clone(), ven))
+    }
+
+    /// Lowlevel operation that gets a list of VENs from the VTN with the given query parameters
+    pub async fn get_vens<'a>(
+        &self,
+        filters: impl Into<Filters<'a>>,
+        pagination: PaginationOptions,
+    ) -> Result<Vec<VenClient>> {
+        let filters = filters.into();
+        let mut vens = self.get_vens_page(&filters, pagination).await?.items;
+        vens.retain(|ven| filters.matches_ven(ven.content()));
+        Ok(vens)
+    }
+
+    async fn get_vens_page(
+        &self,
+        filters: &Filters<'_>,
+        pagination: PaginationOptions,
+    ) -> Result<Page<VenClient>> {
+        let query = filters.query_params(Some(TargetLabel::VENName), pagination)?;
+
+        let vens: Page<Ven> = self
+            .client_ref
+            .get_page("vens", &borrow_query(&query))
+            .await?;
+        Ok(vens.map(|ven| VenClient::from_ven(self.clone(), ven)))
+    }
+
+    /// Get all VENs matching the filters, trying to paginate whenever possible
+    pub async fn get_vens_matching(&self, filters: Filters<'_>) -> Result<Vec<VenClient>> {
+        let mut vens = self
+            .client_ref
+            .get_all_pages(|pagination| self.get_vens_page(&filters, pagination))
+            .await?;
+        vens.retain(|ven| filters.matches_ven(ven.content()));
+        Ok(vens)
+    }
+
+    /// Get all VENs from the VTN the client has access to, trying to paginate whenever possible
+    pub async fn get_all_vens(&self) -> Result<Vec<VenClient>> {
+        self.get_vens_matching(Filters::new()).await
+    }
+
+    /// Get a VEN by name
+    pub async fn get_ven_by_name(&self, name: &str) -> Result<VenClient> {
+        let pagination = PaginationOptions {
+            skip: 0,
+            limit: 2,
+            ..Default::default()
+        };
+        let mut vens = self.get_vens(Filters::new().name(name), pagination).await?;
+
+        match vens[..] {
+            [] => Err(crate::Error::ObjectNotFound),
+            [_] => Ok(vens.remove(0)),
+            [..] => Err(crate::Error::DuplicateObject),
+        }
+    }
+
+    /// Get a VEN by id
+    pub async fn get_ven_by_id(&self, id: &VenId) -> Result<VenClient> {
+        let ven = self
+            .client_ref
+            .get(&format!("vens/{}", id.as_str()), &[])
+            .await?;
+
+        Ok(VenClient::from_ven(self.clone(), ven))
+    }
+
+    /// Get the capabilities of the VTN.
+    ///
+    /// The client adapts its behavior to the capabilities, e.g.,
+    /// it makes sure its page size does not exceed the maximum page size of the VTN,
+    /// and it paginates the event list by cursor if the VTN supports it.
+    pub async fn capabilities(&self) -> Result<Capabilities> {
+        let capabilities: Capabilities = self.client_ref.get(CAPABILITIES_PATH, &[]).await?;
+
+        self.client_ref
+            .page_size
+            .fetch_min(capabilities.max_page_size.max(1), Ordering::Relaxed);
+        self.client_ref.cursor_pagination.store(
+            capabilities.features.contains(&Feature::CursorPagination),
+            Ordering::Relaxed,
+        );
+
+        Ok(capabilities)
+    }
+
+    /// Get the claims of the token the client authenticates with, as resolved by the VTN,
+    /// e.g., to debug why the VTN denies access to an object
+    pub async fn whoami(&self) -> Result<WhoAmI> {
+        self.client_ref.get(WHOAMI_PATH, &[]).await
+    }
+
+    /// Get the latest operating state of each resource, as reported with
+    /// [`EventClient::report_operating_state`]. Not part of the OpenADR specification.
+    pub async fn latest_operating_states(&self) -> Result<Vec<ResourceOperatingState>> {
+        self.client_ref.get(OPERATING_STATES_PATH, &[]).await
+    }
+
+    /// Create a new report on the VTN
+    pub async fn create_report(&self, report_data: ReportContent) -> Result<ReportClient> {
+        let report = self.client_ref.post("reports", &report_data, &[]).await?;
+        Ok(ReportClient::from_report(self.client_ref.clone(), report))
+    }
+
+    /// Get all reports matching the filters, trying to paginate whenever possible.
+    ///
+    /// Only the program, event and client name criteria apply to reports.
+    pub async fn get_reports_matching(&self, filters: Filters<'_>) -> Result<Vec<ReportClient>> {
+        self.client_ref.get_reports_matching(&filters).await
+    }
+
+    /// Get an event by id and verify the signature the VTN attached to it.
+    ///
+    /// Returns the event together with its signature,
+    /// such that the authenticity of the event can be proven later on.
+    pub async fn get_verified_event_by_id(
+        &self,
+        id: &EventId,
+        verifier: &EventVerifier,
+    ) -> Result<(EventClient, String)> {
+        let (event, headers): (Event, _) = self
+            .client_ref
+            .get_with_headers(&format!("events/{}", id.as_str()), &[])
+            .await?;
+
+        let signature = headers
+            .get(EVENT_SIGNATURE_HEADER)
+            .and_then(|signature| signature.to_str().ok())
+            .ok_or(Error::MissingSignature)?
+            .to_string();
+
+        if verifier.verify(&signature)? != event {
+            return Err(Error::SignatureMismatch);
+        }
+
+        Ok((
+            EventClient::from_event(self.client_ref.clone(), event),
+            signature,
+        ))
+    }
 }
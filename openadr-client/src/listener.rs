@@ -0,0 +1,170 @@
+//! Receiving the [`Notification`]s a VTN sends to the callback URLs of subscriptions,
+//! e.g., to react to new events without polling the VTN.
+
+use std::{future::Future, sync::Arc};
+
+use axum::{async_trait, body::Bytes, extract::State, http::StatusCode, routing::post, Router};
+use openadr_wire::notification::Notification;
+use serde::Deserialize;
+use tracing::{trace, warn};
+use validator::Validate;
+
+/// Handles the valid notifications received by a [`notification_listener`].
+///
+/// Implemented for async closures taking a [`Notification`].
+#[async_trait]
+pub trait NotificationHandler: Send + Sync + 'static {
+    async fn handle(&self, notification: Notification);
+}
+
+#[async_trait]
+impl<F, Fut> NotificationHandler for F
+where
+    F: Fn(Notification) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    async fn handle(&self, notification: Notification) {
+        self(notification).await
+    }
+}
+
+/// A router receiving the notifications of a VTN with `POST /`, to nest at the path
+/// of the callback URL of the subscriptions, e.g., `Router::new().nest("/openadr", listener)`.
+///
+/// Besides single notifications, the router receives batches of notifications as a JSON array,
+/// as the VTN sends to subscriptions with a batch window, and handles them in order.
+///
+/// Notifications that do not deserialize, or whose object is invalid, are rejected with
+/// `400 Bad Request`, as are batches with any such notification. Others are acknowledged
+/// with `200 OK` once the handler returns,
+/// so slow handlers should hand off their work, e.g., to a channel, to avoid retries by the VTN.
+pub fn notification_listener<H: NotificationHandler>(handler: H) -> Router {
+    Router::new()
+        .route("/", post(receive::<H>))
+        .with_state(Arc::new(handler))
+}
+
+/// The body of a callback
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Received {
+    Single(Notification),
+    Batch(Vec<Notification>),
+}
+
+async fn receive<H: NotificationHandler>(State(handler): State<Arc<H>>, body: Bytes) -> StatusCode {
+    let notifications = match serde_json::from_slice::<Received>(&body) {
+        Ok(Received::Single(notification)) => vec![notification],
+        Ok(Received::Batch(notifications)) => notifications,
+        Err(err) => {
+            warn!(?err, "received a notification that does not deserialize");
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+
+    for notification in &notifications {
+        if let Err(err) = notification.validate() {
+            warn!(?err, "received an invalid notification");
+            return StatusCode::BAD_REQUEST;
+        }
+    }
+
+    for notification in notifications {
+        trace!(
+            object_type = ?notification.object_type,
+            operation = ?notification.operation,
+            "received notification"
+        );
+        handler.handle(notification).await;
+    }
+
+    StatusCode::OK
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{body::Body, http::Request};
+    use openadr_wire::{
+        event::{EventContent, EventInterval},
+        notification::{NotificationObject, NotificationObjectType, NotificationOperation},
+        Event,
+    };
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn event() -> Event {
+        Event {
+            id: "event-1".parse().unwrap(),
+            created_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            modification_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            content: EventContent::new(
+                "program-1".parse().unwrap(),
+                vec![EventInterval::new(0, vec![])],
+            ),
+        }
+    }
+
+    async fn post(router: Router, body: serde_json::Value) -> StatusCode {
+        let request = Request::post("/")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn invokes_handler() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let router = notification_listener(move |notification: Notification| {
+            let sender = sender.clone();
+            async move { sender.send(notification).unwrap() }
+        });
+
+        let notification = Notification::new(NotificationOperation::Post, event().into());
+        let status = post(router.clone(), serde_json::to_value(&notification).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let received = receiver.try_recv().unwrap();
+        assert_eq!(received.object_type, NotificationObjectType::Event);
+        assert!(matches!(received.object, NotificationObject::Event(_)));
+
+        // the object is not a program
+        let mut json = serde_json::to_value(&notification).unwrap();
+        json["objectType"] = "PROGRAM".into();
+        assert_eq!(post(router.clone(), json).await, StatusCode::BAD_REQUEST);
+
+        // the ids of the intervals of an event must be unique
+        let mut json = serde_json::to_value(&notification).unwrap();
+        let interval = json["object"]["intervals"][0].clone();
+        json["object"]["intervals"] = serde_json::json!([interval, interval]);
+        assert_eq!(post(router, json).await, StatusCode::BAD_REQUEST);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn handles_batches_in_order() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let router = notification_listener(move |notification: Notification| {
+            let sender = sender.clone();
+            async move { sender.send(notification).unwrap() }
+        });
+
+        let created = Notification::new(NotificationOperation::Post, event().into());
+        let deleted = Notification::new(NotificationOperation::Delete, event().into());
+        let batch = serde_json::to_value([&created, &deleted]).unwrap();
+        assert_eq!(post(router.clone(), batch).await, StatusCode::OK);
+
+        assert_eq!(receiver.try_recv().unwrap(), created);
+        assert_eq!(receiver.try_recv().unwrap(), deleted);
+
+        // a batch with an invalid notification is rejected as a whole
+        let mut invalid = serde_json::to_value(&created).unwrap();
+        invalid["objectType"] = "PROGRAM".into();
+        let batch = serde_json::json!([serde_json::to_value(&created).unwrap(), invalid]);
+        assert_eq!(post(router, batch).await, StatusCode::BAD_REQUEST);
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use reqwest::RequestBuilder;
+
+/// Counters describing the traffic between a [`Client`](crate::Client) and its VTN,
+/// e.g., to quantify the bandwidth used by a VEN on a metered connection.
+///
+/// Only the bodies of the requests and responses are counted, not the HTTP headers.
+/// The counters are shared by all clones of a client and the program, event and report
+/// clients derived from it.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    list_calls: AtomicU64,
+    objects_fetched: AtomicU64,
+}
+
+impl Metrics {
+    /// Number of HTTP requests sent to the VTN, including requests for an access token
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    /// Number of bytes in the bodies of the requests sent to the VTN
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    /// Number of bytes in the bodies of the responses received from the VTN
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    /// Number of calls retrieving all matching objects of a kind, which may span multiple pages
+    pub fn list_calls(&self) -> u64 {
+        self.list_calls.load(Ordering::Relaxed)
+    }
+
+    /// Total number of objects retrieved by the [list calls](Self::list_calls)
+    pub fn objects_fetched(&self) -> u64 {
+        self.objects_fetched.load(Ordering::Relaxed)
+    }
+
+    /// Record a request about to be sent
+    pub(crate) fn record_request(&self, request: &RequestBuilder) {
+        // cloning fails for streaming bodies, which the client never sends
+        let body_len = request
+            .try_clone()
+            .and_then(|request| request.build().ok())
+            .and_then(|request| Some(request.body()?.as_bytes()?.len()))
+            .unwrap_or(0);
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.bytes_sent
+            .fetch_add(body_len as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_response(&self, body_len: usize) {
+        self.bytes_received
+            .fetch_add(body_len as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_list_call(&self, objects: usize) {
+        self.list_calls.fetch_add(1, Ordering::Relaxed);
+        self.objects_fetched
+            .fetch_add(objects as u64, Ordering::Relaxed);
+    }
+}
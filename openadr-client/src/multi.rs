@@ -0,0 +1,160 @@
+use std::{collections::BTreeMap, future::Future};
+
+use futures_util::future::join_all;
+use openadr_wire::report::ReportContent;
+
+use crate::{error::Result, Client, Error, EventClient, ProgramClient, ReportClient};
+
+/// Separates the name of a VTN from the name of an object in qualified names
+pub const VTN_SEPARATOR: char = '/';
+
+/// Client used to interact with multiple VTNs at once, e.g., by an aggregator participating in
+/// the programs of several utilities.
+///
+/// Every VTN is registered under a unique name. Objects retrieved via the multi-client are
+/// tagged with the name of the VTN they originate from, such that they live in a single
+/// namespace: `<vtn name>/<object name>`.
+#[derive(Debug, Clone, Default)]
+pub struct MultiClient {
+    clients: BTreeMap<String, Client>,
+}
+
+/// An object retrieved from one of the VTNs of a [`MultiClient`]
+#[derive(Debug)]
+pub struct VtnObject<T> {
+    /// The name of the VTN the object originates from
+    pub vtn: String,
+    pub object: T,
+}
+
+/// The objects retrieved from all VTNs of a [`MultiClient`].
+///
+/// A VTN that cannot be reached, or answers with an error, does not fail the whole retrieval:
+/// its error is reported in [`failed`](Self::failed), next to the objects of the other VTNs.
+#[derive(Debug)]
+pub struct VtnObjects<T> {
+    pub objects: Vec<VtnObject<T>>,
+    /// The error of each VTN the objects could not be retrieved from, by the name of the VTN
+    pub failed: BTreeMap<String, Error>,
+}
+
+impl VtnObject<ProgramClient> {
+    /// The name of the program, prefixed by the name of its VTN
+    pub fn qualified_name(&self) -> String {
+        qualify(&self.vtn, &self.object.content().program_name)
+    }
+}
+
+impl VtnObject<EventClient> {
+    /// The name of the event (if any), prefixed by the name of its VTN
+    pub fn qualified_name(&self) -> Option<String> {
+        let name = self.object.content().event_name.as_ref()?;
+        Some(qualify(&self.vtn, name))
+    }
+}
+
+fn qualify(vtn: &str, name: &str) -> String {
+    format!("{vtn}{VTN_SEPARATOR}{name}")
+}
+
+impl MultiClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the client of a VTN under the given name.
+    ///
+    /// Returns the client previously registered under that name, if any.
+    /// Fails with [`Error::InvalidVtnName`] if the name contains the [`VTN_SEPARATOR`].
+    pub fn add_vtn(&mut self, name: impl ToString, client: Client) -> Result<Option<Client>> {
+        let name = name.to_string();
+        if name.contains(VTN_SEPARATOR) {
+            return Err(Error::InvalidVtnName(name));
+        }
+        Ok(self.clients.insert(name, client))
+    }
+
+    /// Remove a VTN from the multi-client
+    pub fn remove_vtn(&mut self, name: &str) -> Option<Client> {
+        self.clients.remove(name)
+    }
+
+    /// The client of a specific VTN
+    pub fn vtn(&self, name: &str) -> Option<&Client> {
+        self.clients.get(name)
+    }
+
+    /// The names of all registered VTNs
+    pub fn vtn_names(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    /// Get all programs of all VTNs, querying the VTNs concurrently
+    pub async fn get_all_programs(&self) -> VtnObjects<ProgramClient> {
+        self.get_all(Client::get_all_programs).await
+    }
+
+    /// Get all events of all VTNs, querying the VTNs concurrently
+    pub async fn get_all_events(&self) -> VtnObjects<EventClient> {
+        self.get_all(Client::get_all_events).await
+    }
+
+    async fn get_all<'a, T, F>(&'a self, get: impl Fn(&'a Client) -> F) -> VtnObjects<T>
+    where
+        F: Future<Output = Result<Vec<T>>>,
+    {
+        let results = join_all(self.clients.iter().map(|(vtn, client)| {
+            let objects = get(client);
+            async move { (vtn, objects.await) }
+        }))
+        .await;
+
+        let mut all = VtnObjects {
+            objects: vec![],
+            failed: BTreeMap::new(),
+        };
+        for (vtn, result) in results {
+            match result {
+                Ok(objects) => all
+                    .objects
+                    .extend(objects.into_iter().map(|object| VtnObject {
+                        vtn: vtn.clone(),
+                        object,
+                    })),
+                Err(err) => {
+                    all.failed.insert(vtn.clone(), err);
+                }
+            }
+        }
+
+        all
+    }
+
+    /// Get a program by its qualified name, i.e., `<vtn name>/<program name>`
+    pub async fn get_program_by_name(
+        &self,
+        qualified_name: &str,
+    ) -> Result<VtnObject<ProgramClient>> {
+        let (vtn, name) = qualified_name
+            .split_once(VTN_SEPARATOR)
+            .ok_or(Error::ObjectNotFound)?;
+        let client = self.vtn(vtn).ok_or(Error::ObjectNotFound)?;
+
+        Ok(VtnObject {
+            vtn: vtn.to_string(),
+            object: client.get_program_by_name(name).await?,
+        })
+    }
+
+    /// Submit a report for an event to the VTN the event originates from
+    pub async fn create_report(
+        &self,
+        event: &VtnObject<EventClient>,
+        report_data: ReportContent,
+    ) -> Result<VtnObject<ReportClient>> {
+        Ok(VtnObject {
+            vtn: event.vtn.clone(),
+            object: event.object.create_report(report_data).await?,
+        })
+    }
+}
@@ -1,12 +1,18 @@
+use std::time::Duration;
+
+use futures_util::Stream;
 use openadr_wire::{
-    event::{EventObjectType, Priority},
+    event::{EventObjectType, EventPayloadDescriptor, EventType, Priority},
+    program::ProgramVen,
+    ven::VenId,
     Program,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{
     error::{Error, Result},
-    Client, EventClient, EventContent, Filter, PaginationOptions, ProgramContent, ProgramId,
-    Target, Timeline,
+    watch, Client, EventClient, EventContent, EventPlan, EventUpdate, Filters, PaginationOptions,
+    ProgramContent, ProgramId, SyncSummary, Target, Timeline, TimelineOptions,
 };
 
 /// A client for interacting with the data in a specific program and the events
@@ -40,6 +46,11 @@ impl ProgramClient {
         self.data.modification_date_time
     }
 
+    /// The program as it was last received from the VTN
+    pub fn program(&self) -> &Program {
+        &self.data
+    }
+
     /// Read the data of the program
     pub fn content(&self) -> &ProgramContent {
         &self.data.content
@@ -51,6 +62,12 @@ impl ProgramClient {
         &mut self.data.content
     }
 
+    /// The descriptor of the event payloads of the given type, e.g., with the currency of prices.
+    /// Events may override it, see [`EventClient::payload_descriptor`].
+    pub fn payload_descriptor(&self, payload_type: &EventType) -> Option<&EventPayloadDescriptor> {
+        self.data.content.event_payload_descriptor(payload_type)
+    }
+
     /// Save any modifications of the program to the VTN
     pub async fn update(&mut self) -> Result<()> {
         let res = self
@@ -70,6 +87,43 @@ impl ProgramClient {
             .await
     }
 
+    /// Get the VENs assigned to the program. Not part of the OpenADR specification.
+    pub async fn vens(&self) -> Result<Vec<VenId>> {
+        let vens: Vec<ProgramVen> = self
+            .client
+            .client_ref
+            .get(&format!("programs/{}/vens", self.id()), &[])
+            .await?;
+        Ok(vens
+            .into_iter()
+            .map(|program_ven| program_ven.ven_id)
+            .collect())
+    }
+
+    /// Assign a VEN to the program, without updating the rest of the program.
+    /// Not part of the OpenADR specification.
+    pub async fn assign_ven(&self, ven_id: &VenId) -> Result<()> {
+        let program_ven = ProgramVen {
+            ven_id: ven_id.clone(),
+        };
+        let _: ProgramVen = self
+            .client
+            .client_ref
+            .post(&format!("programs/{}/vens", self.id()), &program_ven, &[])
+            .await?;
+        Ok(())
+    }
+
+    /// Remove a VEN from the program. Not part of the OpenADR specification.
+    pub async fn unassign_ven(&self, ven_id: &VenId) -> Result<()> {
+        let _: ProgramVen = self
+            .client
+            .client_ref
+            .delete(&format!("programs/{}/vens/{}", self.id(), ven_id), &[])
+            .await?;
+        Ok(())
+    }
+
     /// Create a new event on the VTN
     pub async fn create_event(&self, event_data: EventContent) -> Result<EventClient> {
         if &event_data.program_id != self.id() {
@@ -101,13 +155,13 @@ impl ProgramClient {
         }
     }
 
-    pub async fn get_events_request(
-        &self,
-        filter: Filter<'_>,
+    pub async fn get_events_request<'a>(
+        &'a self,
+        filters: impl Into<Filters<'a>>,
         pagination: PaginationOptions,
     ) -> Result<Vec<EventClient>> {
         self.client
-            .get_events(Some(self.id()), filter, pagination)
+            .get_events(Some(self.id()), filters, pagination)
             .await
     }
 
@@ -118,38 +172,95 @@ impl ProgramClient {
 
     /// Get all events from the VTN, trying to paginate whenever possible
     pub async fn get_all_events(&self) -> Result<Vec<EventClient>> {
-        let page_size = self.client.client_ref.default_page_size();
-        let mut events = vec![];
-        let mut page = 0;
-        loop {
-            // TODO: this pagination should really depend on that the server indicated there are more results
-            let pagination = PaginationOptions {
-                skip: page * page_size,
-                limit: page_size,
-            };
-
-            let received = self
-                .client
-                .get_events(Some(self.id()), Filter::None, pagination)
-                .await?;
-            let received_all = received.len() < page_size;
-            for event in received {
-                events.push(event);
-            }
-
-            if received_all {
-                break;
-            } else {
-                page += 1;
-            }
+        self.client
+            .get_events_matching(Filters::new().program_id(self.id()))
+            .await
+    }
+
+    /// Get an event of this program by name
+    pub async fn get_event_by_name(&self, name: &str) -> Result<EventClient> {
+        let pagination = PaginationOptions {
+            skip: 0,
+            limit: 2,
+            ..Default::default()
+        };
+        let mut events = self
+            .get_events_request(Filters::new().name(name), pagination)
+            .await?;
+
+        match events[..] {
+            [] => Err(Error::ObjectNotFound),
+            [_] => Ok(events.remove(0)),
+            [..] => Err(Error::DuplicateObject),
         }
+    }
+
+    /// Compare the events of the program on the VTN with the desired events by name,
+    /// to create, update and delete only the events that differ.
+    ///
+    /// Fails if a desired event has no name, or if names occur more than once.
+    pub async fn plan_events(&self, desired: Vec<EventContent>) -> Result<EventPlan> {
+        let current = self.get_all_events().await?;
+        EventPlan::new(self.client.client_ref.clone(), self.id(), current, desired)
+    }
+
+    /// Make the events of the program match the desired events, see [`Self::plan_events`]
+    pub async fn sync_events(&self, desired: Vec<EventContent>) -> Result<SyncSummary> {
+        self.plan_events(desired).await?.execute().await
+    }
+
+    /// The program together with all its events,
+    /// e.g., to copy the program to another VTN with [`Client::import_bundle`]
+    pub async fn export_bundle(&self) -> Result<ProgramBundle> {
+        let events = self.get_all_events().await?;
 
-        Ok(events)
+        Ok(ProgramBundle {
+            program: self.content().clone(),
+            events: events.iter().map(|event| event.content().clone()).collect(),
+        })
+    }
+
+    /// Watch the events of the program by polling the VTN every `poll_interval`,
+    /// for VENs that cannot receive notifications.
+    ///
+    /// The first poll yields all events of the program as created.
+    /// Failed polls are logged and retried at the next interval.
+    pub fn watch_events(&self, poll_interval: Duration) -> impl Stream<Item = EventUpdate> {
+        watch::watch_events(self.client.clone(), self.id().clone(), poll_interval)
     }
 
     pub async fn get_timeline(&mut self) -> Result<Timeline> {
+        self.get_timeline_with(TimelineOptions::default()).await
+    }
+
+    /// Like [`Self::get_timeline`], but with options, e.g., to apply the `randomizeStart`
+    /// of the intervals with [`TimelineOptions::randomize_start`]
+    pub async fn get_timeline_with(&mut self, options: TimelineOptions) -> Result<Timeline> {
         let events = self.get_all_events().await?;
         let events = events.iter().map(|e| e.content()).collect();
-        Timeline::from_events(self.content(), events).ok_or(Error::InvalidInterval)
+        Timeline::from_events_with(self.content(), events, options).ok_or(Error::InvalidInterval)
+    }
+}
+
+/// A program and its events in one JSON document,
+/// e.g., to copy a program from a staging to a production VTN.
+/// Not part of the OpenADR specification.
+///
+/// Only the content of the objects is included, the VTN assigns new IDs on import.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramBundle {
+    pub program: ProgramContent,
+    /// The events of the program, of which the `programID` is replaced on import
+    pub events: Vec<EventContent>,
+}
+
+impl ProgramBundle {
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
     }
 }
@@ -0,0 +1,355 @@
+//! When to send the reports that a VTN requests with the [`ReportDescriptor`]s of an event,
+//! and which intervals and resources they cover.
+
+use std::ops::Range;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use openadr_wire::{
+    event::EventContent,
+    interval::IntervalPeriod,
+    report::{ReportDescriptor, ResourceName},
+    resource::ResourceContent,
+    target::TargetMap,
+    ven::VenContent,
+};
+
+use crate::{
+    error::{Error, Result},
+    sleep_until, Clock,
+};
+
+/// A report requested by a [`ReportDescriptor`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledReport {
+    /// Counts the reports of the descriptor, starting at 0
+    pub sequence: u32,
+    /// When to send the report
+    pub at: DateTime<Utc>,
+    /// The indices of the intervals to include in the report.
+    /// Indices past the intervals of the event continue with intervals of the same duration.
+    pub intervals: Range<u32>,
+}
+
+/// Schedules the reports of a [`ReportDescriptor`] of an event.
+///
+/// The intervals of the event are assumed to follow each other without gaps,
+/// with the duration of the [`interval_period`](EventContent::interval_period) of the event,
+/// or else that of its first interval.
+///
+/// Reports are sent at the start of the interval with index
+/// [`start_interval`](ReportDescriptor::start_interval), or at the end of the last interval
+/// of the event if unspecified, and then every [`frequency`](ReportDescriptor::frequency) intervals,
+/// until the report was sent [`repeat`](ReportDescriptor::repeat) times.
+/// Historical reports cover the [`num_intervals`](ReportDescriptor::num_intervals)
+/// intervals before the report, other reports, e.g., forecasts, those from the report onward.
+#[derive(Debug)]
+pub struct ReportScheduler<C> {
+    clock: C,
+    descriptor: ReportDescriptor,
+    period: IntervalPeriod,
+    duration: TimeDelta,
+    event_intervals: u32,
+    next: u32,
+}
+
+impl<C: Clock> ReportScheduler<C> {
+    /// Fails with [`Error::InvalidInterval`] if the event has no interval period with a positive duration
+    pub fn new(clock: C, event: &EventContent, descriptor: ReportDescriptor) -> Result<Self> {
+        let period = event
+            .interval_period
+            .as_ref()
+            .or_else(|| event.intervals.first()?.interval_period.as_ref())
+            .ok_or(Error::InvalidInterval)?
+            .clone();
+
+        // months and years differ in length, the first interval determines the duration of all
+        let duration = period
+            .duration
+            .as_ref()
+            .map(|duration| duration.to_chrono_at_datetime(period.start))
+            .filter(|duration| *duration > TimeDelta::zero())
+            .ok_or(Error::InvalidInterval)?;
+
+        Ok(Self {
+            clock,
+            descriptor,
+            period,
+            duration,
+            event_intervals: event.intervals.len().try_into().unwrap_or(u32::MAX),
+            next: 0,
+        })
+    }
+
+    pub fn descriptor(&self) -> &ReportDescriptor {
+        &self.descriptor
+    }
+
+    /// The report with the given sequence number,
+    /// or `None` if the descriptor requests fewer reports
+    pub fn report(&self, sequence: u32) -> Option<ScheduledReport> {
+        if self
+            .descriptor
+            .repeat
+            .get()
+            .is_some_and(|repeat| sequence >= repeat)
+        {
+            return None;
+        }
+
+        let num_intervals = self.descriptor.num_intervals.get();
+        let frequency = self
+            .descriptor
+            .frequency
+            .get()
+            .or(num_intervals)
+            .unwrap_or(self.event_intervals)
+            .max(1);
+        let first = self
+            .descriptor
+            .start_interval
+            .get()
+            .unwrap_or(self.event_intervals);
+        let at_interval = first.checked_add(sequence.checked_mul(frequency)?)?;
+
+        let intervals = match (self.descriptor.historical, num_intervals) {
+            (true, Some(num_intervals)) => at_interval.saturating_sub(num_intervals)..at_interval,
+            (true, None) => 0..at_interval,
+            (false, Some(num_intervals)) => at_interval..at_interval.saturating_add(num_intervals),
+            (false, None) => at_interval..self.event_intervals.max(at_interval),
+        };
+
+        Some(ScheduledReport {
+            sequence,
+            at: self.interval_start(at_interval)?,
+            intervals,
+        })
+    }
+
+    /// The next report to send, if any
+    pub fn next_report(&self) -> Option<ScheduledReport> {
+        self.report(self.next)
+    }
+
+    /// Wait until the next report is due, returning it,
+    /// or `None` once all requested reports were sent.
+    ///
+    /// Reports that were due already are returned immediately.
+    /// Cancel safe: dropping the future before it completes does not skip a report.
+    pub async fn wait(&mut self) -> Option<ScheduledReport> {
+        let report = self.next_report()?;
+        sleep_until(&self.clock, report.at).await;
+
+        self.next = report.sequence + 1;
+        Some(report)
+    }
+
+    /// The period of the interval with the given index, e.g., for the intervals of a report
+    pub fn interval_period(&self, index: u32) -> Option<IntervalPeriod> {
+        Some(IntervalPeriod {
+            start: self.interval_start(index)?,
+            duration: self.period.duration.clone(),
+            randomize_start: None,
+        })
+    }
+
+    fn interval_start(&self, index: u32) -> Option<DateTime<Utc>> {
+        let offset = self.duration.checked_mul(index.try_into().ok()?)?;
+        self.period.start.checked_add_signed(offset)
+    }
+
+    /// The names of the resources to report on: the resources targeted by the descriptor,
+    /// or a single [`ResourceName::AggregatedReport`] if the descriptor requests an aggregate
+    /// and any resource is targeted.
+    ///
+    /// Descriptors without targets target all resources.
+    pub fn resource_names<'a>(
+        &self,
+        ven: &VenContent,
+        resources: impl IntoIterator<Item = &'a ResourceContent>,
+    ) -> Vec<ResourceName> {
+        let ven_attributes = TargetMap::from_ven(ven);
+        let names = resources
+            .into_iter()
+            .filter(|resource| {
+                self.descriptor.targets.as_ref().map_or(true, |targets| {
+                    targets.matches(&ven_attributes.union(&TargetMap::from_resource(resource)))
+                })
+            })
+            .map(|resource| ResourceName::Private(resource.resource_name.clone()))
+            .collect::<Vec<_>>();
+
+        if self.descriptor.aggregate && !names.is_empty() {
+            return vec![ResourceName::AggregatedReport];
+        }
+
+        names
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use openadr_wire::{
+        event::EventInterval,
+        report::{DescriptorCount, ReportType},
+        target::{TargetEntry, TargetLabel},
+    };
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::{MockClock, CLOCK_CHECK_INTERVAL};
+
+    fn start() -> DateTime<Utc> {
+        "2024-06-01T00:00:00Z".parse().unwrap()
+    }
+
+    fn event(intervals: i32) -> EventContent {
+        let mut period = IntervalPeriod::new(start());
+        period.duration = Some("PT1H".parse().unwrap());
+
+        let mut event = EventContent::new(
+            "program-1".parse().unwrap(),
+            (0..intervals)
+                .map(|id| EventInterval::new(id, vec![]))
+                .collect(),
+        );
+        event.interval_period = Some(period);
+        event
+    }
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        start() + TimeDelta::hours(hours)
+    }
+
+    #[test]
+    fn defaults_report_once_at_the_end() {
+        let scheduler = ReportScheduler::new(
+            MockClock::new(start()),
+            &event(4),
+            ReportDescriptor::new(ReportType::Usage),
+        )
+        .unwrap();
+
+        assert_eq!(
+            scheduler.report(0),
+            Some(ScheduledReport {
+                sequence: 0,
+                at: at(4),
+                intervals: 0..4,
+            })
+        );
+        assert_eq!(scheduler.report(1), None);
+    }
+
+    #[test]
+    fn frequency_and_repeat() {
+        let mut descriptor = ReportDescriptor::new(ReportType::Usage);
+        descriptor.start_interval = DescriptorCount::new(2);
+        descriptor.num_intervals = DescriptorCount::new(2);
+        descriptor.frequency = DescriptorCount::new(1);
+        descriptor.repeat = DescriptorCount::new(3);
+        let scheduler =
+            ReportScheduler::new(MockClock::new(start()), &event(4), descriptor.clone()).unwrap();
+
+        let reports = (0..4).map(|n| scheduler.report(n)).collect::<Vec<_>>();
+        assert_eq!(reports[0].as_ref().unwrap().intervals, 0..2);
+        assert_eq!(reports[1].as_ref().unwrap().at, at(3));
+        assert_eq!(reports[2].as_ref().unwrap().intervals, 2..4);
+        assert_eq!(reports[3], None);
+
+        // forecasts cover the intervals from the report onward
+        descriptor.historical = false;
+        descriptor.repeat = DescriptorCount::UNSPECIFIED;
+        let scheduler =
+            ReportScheduler::new(MockClock::new(start()), &event(4), descriptor).unwrap();
+        assert_eq!(scheduler.report(0).unwrap().intervals, 2..4);
+        assert_eq!(scheduler.report(10).unwrap().at, at(12));
+        assert_eq!(
+            scheduler.interval_period(3).unwrap().start,
+            scheduler.report(1).unwrap().at
+        );
+    }
+
+    #[test]
+    fn requires_interval_duration() {
+        let mut event = event(4);
+        event.interval_period.as_mut().unwrap().duration = None;
+
+        assert!(ReportScheduler::new(
+            MockClock::new(start()),
+            &event,
+            ReportDescriptor::new(ReportType::Usage)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn targeted_resources() {
+        let ven = VenContent {
+            object_type: None,
+            ven_name: "ven-1".to_string(),
+            attributes: None,
+            targets: None,
+            resources: None,
+        };
+        let resources = ["resource-1", "resource-2"].map(|name| ResourceContent {
+            object_type: None,
+            resource_name: name.to_string(),
+            attributes: None,
+            targets: None,
+        });
+
+        let mut descriptor = ReportDescriptor::new(ReportType::Usage);
+        descriptor.targets = Some(TargetMap(vec![TargetEntry::new(
+            TargetLabel::ResourceName,
+            "resource-2",
+        )]));
+        let mut scheduler =
+            ReportScheduler::new(MockClock::new(start()), &event(4), descriptor).unwrap();
+        assert_eq!(
+            scheduler.resource_names(&ven, &resources),
+            vec![ResourceName::Private("resource-2".to_string())]
+        );
+
+        scheduler.descriptor.aggregate = true;
+        assert_eq!(
+            scheduler.resource_names(&ven, &resources),
+            vec![ResourceName::AggregatedReport]
+        );
+
+        scheduler.descriptor.targets = None;
+        scheduler.descriptor.aggregate = false;
+        assert_eq!(scheduler.resource_names(&ven, &resources).len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_reports() {
+        let clock = MockClock::new(start());
+        let mut descriptor = ReportDescriptor::new(ReportType::Usage);
+        descriptor.start_interval = DescriptorCount::new(1);
+        descriptor.num_intervals = DescriptorCount::new(1);
+        descriptor.repeat = DescriptorCount::new(2);
+        let mut scheduler = ReportScheduler::new(clock.clone(), &event(4), descriptor).unwrap();
+
+        assert!(timeout(Duration::from_secs(60), scheduler.wait())
+            .await
+            .is_err());
+
+        clock.set(at(1));
+        let report = timeout(CLOCK_CHECK_INTERVAL, scheduler.wait())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.intervals, 0..1);
+
+        // the clock jumped past the last report
+        clock.set(at(5));
+        let report = timeout(CLOCK_CHECK_INTERVAL, scheduler.wait())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.intervals, 1..2);
+        assert_eq!(scheduler.wait().await, None);
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use openadr_wire::{
+    resource::{Resource, ResourceContent, ResourceId},
+    ven::VenId,
+};
+
+use crate::{error::Result, ClientRef};
+
+/// A client for interacting with the data of a specific resource of a VEN
+#[derive(Debug)]
+pub struct ResourceClient {
+    client: Arc<ClientRef>,
+    data: Resource,
+}
+
+impl ResourceClient {
+    pub(super) fn from_resource(client: Arc<ClientRef>, resource: Resource) -> Self {
+        Self {
+            client,
+            data: resource,
+        }
+    }
+
+    /// Get the id of the resource
+    pub fn id(&self) -> &ResourceId {
+        &self.data.id
+    }
+
+    /// Get the id of the VEN the resource belongs to
+    pub fn ven_id(&self) -> &VenId {
+        &self.data.ven_id
+    }
+
+    /// Get the time the resource was created on the VTN
+    pub fn created_date_time(&self) -> chrono::DateTime<chrono::Utc> {
+        self.data.created_date_time
+    }
+
+    /// Get the time the resource was last modified on the VTN
+    pub fn modification_date_time(&self) -> chrono::DateTime<chrono::Utc> {
+        self.data.modification_date_time
+    }
+
+    /// The resource as it was last received from the VTN
+    pub fn resource(&self) -> &Resource {
+        &self.data
+    }
+
+    /// Read the data of the resource
+    pub fn content(&self) -> &ResourceContent {
+        &self.data.content
+    }
+
+    /// Modify the data of the resource, make sure to update the resource on the
+    /// VTN once your modifications are complete.
+    pub fn content_mut(&mut self) -> &mut ResourceContent {
+        &mut self.data.content
+    }
+
+    fn path(&self) -> String {
+        format!("vens/{}/resources/{}", self.ven_id(), self.id())
+    }
+
+    /// Save any modifications of the resource to the VTN
+    pub async fn update(&mut self) -> Result<()> {
+        let res = self
+            .client
+            .put(&self.path(), &self.data.content, &[])
+            .await?;
+        self.data = res;
+        Ok(())
+    }
+
+    /// Delete the resource from the VTN
+    pub async fn delete(self) -> Result<Resource> {
+        self.client.delete(&self.path(), &[]).await
+    }
+}
@@ -0,0 +1,154 @@
+//! Timers for moments in wall-clock time, e.g., the start of the next interval of a
+//! [`Timeline`](crate::Timeline) or the next moment to report a measurement.
+//!
+//! Sleeping for `deadline - clock.now()` misfires when the wall clock jumps while sleeping,
+//! e.g., when an embedded device synchronizes its time with NTP shortly after booting.
+//! These timers instead sleep on the monotonic clock of tokio in steps of at most
+//! [`CLOCK_CHECK_INTERVAL`], and compare the [`Clock`] with the deadline after each step.
+
+use std::time::Duration;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::Clock;
+
+/// The longest a timer sleeps before checking the [`Clock`] again,
+/// i.e., the maximum delay of a timer after the wall clock jumped past its deadline
+pub const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Wait until the `clock` reaches the `deadline`, with millisecond precision.
+///
+/// Returns immediately if the deadline already passed.
+pub async fn sleep_until<C: Clock + ?Sized>(clock: &C, deadline: DateTime<Utc>) {
+    // a negative duration means the deadline passed
+    while let Ok(remaining) = (deadline - clock.now()).to_std() {
+        if remaining.is_zero() {
+            return;
+        }
+
+        tokio::time::sleep(remaining.min(CLOCK_CHECK_INTERVAL)).await;
+    }
+}
+
+/// Ticks at `start + n * period` in the time of a [`Clock`], e.g., to report a measurement
+/// at the start of every quarter of an hour.
+///
+/// Ticks that were missed because the clock jumped forward are skipped instead of fired in a burst,
+/// and a jump backward does not repeat a tick.
+#[derive(Debug)]
+pub struct Schedule<C> {
+    clock: C,
+    period: TimeDelta,
+    next: DateTime<Utc>,
+}
+
+impl<C: Clock> Schedule<C> {
+    /// # Panics
+    ///
+    /// If the period is shorter than a millisecond
+    pub fn new(clock: C, start: DateTime<Utc>, period: Duration) -> Self {
+        let period = TimeDelta::from_std(period).expect("period out of range");
+        assert!(
+            period >= TimeDelta::milliseconds(1),
+            "period must be at least a millisecond"
+        );
+
+        Self {
+            clock,
+            period,
+            next: start,
+        }
+    }
+
+    /// The time of the next tick
+    pub fn next(&self) -> DateTime<Utc> {
+        self.next
+    }
+
+    /// Wait for the next tick, returning its scheduled time.
+    ///
+    /// Cancel safe: dropping the future before it completes does not skip a tick.
+    pub async fn tick(&mut self) -> DateTime<Utc> {
+        sleep_until(&self.clock, self.next).await;
+
+        // the last scheduled time that passed, skipping those missed due to a jump of the clock
+        let period = self.period.num_milliseconds();
+        let missed = (self.clock.now() - self.next).num_milliseconds() / period;
+        let tick = self.next + TimeDelta::milliseconds(missed.max(0) * period);
+
+        self.next = tick + self.period;
+        tick
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::MockClock;
+    use tokio::time::timeout;
+
+    fn start() -> DateTime<Utc> {
+        "2024-06-01T12:00:00Z".parse().unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sleep_until_follows_clock() {
+        let clock = MockClock::new(start());
+        let sleep = sleep_until(&clock, start() + TimeDelta::milliseconds(2500));
+        tokio::pin!(sleep);
+
+        // only the time of the clock counts
+        assert!(timeout(Duration::from_secs(10), &mut sleep).await.is_err());
+
+        clock.advance(TimeDelta::milliseconds(2499));
+        assert!(timeout(Duration::from_secs(10), &mut sleep).await.is_err());
+
+        clock.advance(TimeDelta::milliseconds(1));
+        timeout(CLOCK_CHECK_INTERVAL, &mut sleep).await.unwrap();
+
+        // the deadline passed
+        timeout(Duration::ZERO, sleep_until(&clock, start()))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sleep_until_survives_clock_jump() {
+        let clock = MockClock::new(start());
+        let sleep = sleep_until(&clock, start() + TimeDelta::hours(1));
+        tokio::pin!(sleep);
+        assert!(timeout(Duration::from_secs(60), &mut sleep).await.is_err());
+
+        // a time synchronization moves the clock past the deadline
+        clock.advance(TimeDelta::hours(2));
+        timeout(CLOCK_CHECK_INTERVAL, &mut sleep).await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn schedule_skips_and_does_not_repeat() {
+        let clock = MockClock::new(start());
+        let mut schedule = Schedule::new(clock.clone(), start(), Duration::from_secs(15 * 60));
+
+        assert_eq!(schedule.tick().await, start());
+        assert_eq!(schedule.next(), start() + TimeDelta::minutes(15));
+
+        // the clock jumps forward past two ticks, of which only the last fires
+        clock.advance(TimeDelta::minutes(40));
+        let tick = timeout(CLOCK_CHECK_INTERVAL, schedule.tick())
+            .await
+            .unwrap();
+        assert_eq!(tick, start() + TimeDelta::minutes(30));
+
+        // the clock jumps backward, the tick at 12:30 is not repeated
+        clock.advance(TimeDelta::minutes(-20));
+        assert!(timeout(Duration::from_secs(60), schedule.tick())
+            .await
+            .is_err());
+
+        clock.set(start() + TimeDelta::minutes(45));
+        let tick = timeout(CLOCK_CHECK_INTERVAL, schedule.tick())
+            .await
+            .unwrap();
+        assert_eq!(tick, start() + TimeDelta::minutes(45));
+    }
+}
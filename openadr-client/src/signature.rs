@@ -0,0 +1,65 @@
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use openadr_wire::Event;
+
+use crate::error::Result;
+
+/// Verifies the JWS signatures a VTN can attach to events, see
+/// [`EVENT_SIGNATURE_HEADER`](openadr_wire::event::EVENT_SIGNATURE_HEADER).
+///
+/// Keep the signature together with the event to be able to prove its authenticity later on.
+pub struct EventVerifier {
+    decoding_key: DecodingKey,
+    validation: Validation,
+}
+
+impl std::fmt::Debug for EventVerifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct(std::any::type_name::<Self>())
+            .field("algorithms", &self.validation.algorithms)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventVerifier {
+    /// Create a verifier for signatures created with the given algorithm and key
+    pub fn new(algorithm: Algorithm, decoding_key: DecodingKey) -> Self {
+        let mut validation = Validation::new(algorithm);
+        // the payload is an event, not a set of JWT claims
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+
+        Self {
+            decoding_key,
+            validation,
+        }
+    }
+
+    /// Create a verifier from a PEM encoded public key.
+    /// The type of key is determined by the algorithm.
+    pub fn from_pem(algorithm: Algorithm, pem: &[u8]) -> Result<Self> {
+        let decoding_key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => DecodingKey::from_rsa_pem(pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(pem)?,
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(pem)?,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                return Err(jsonwebtoken::errors::Error::from(
+                    jsonwebtoken::errors::ErrorKind::InvalidAlgorithm,
+                )
+                .into())
+            }
+        };
+
+        Ok(Self::new(algorithm, decoding_key))
+    }
+
+    /// Verify a signature, returning the signed event
+    pub fn verify(&self, signature: &str) -> Result<Event> {
+        let token = jsonwebtoken::decode::<Event>(signature, &self.decoding_key, &self.validation)?;
+        Ok(token.claims)
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use openadr_wire::{program::ProgramId, report::ReportContent, Event, Program};
+use tracing::{trace, warn};
+
+use crate::{error::Result, Client, Error, ProgramClient, Timeline};
+
+/// Embedded store persisting the programs and events received from a VTN and the reports that
+/// still have to be sent to it.
+///
+/// A VEN that loses connectivity can use the store to keep following the last known schedule,
+/// and to upload the queued reports once the connection to the VTN is restored.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    db: sled::Db,
+    programs: sled::Tree,
+    events: sled::Tree,
+    reports: sled::Tree,
+    last_contact: sled::Tree,
+}
+
+impl LocalStore {
+    /// Open (or create) a store at the given location on disk
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_db(sled::open(path)?)
+    }
+
+    /// Create a store that is removed once it is dropped, useful for testing
+    pub fn temporary() -> Result<Self> {
+        Self::from_db(sled::Config::new().temporary(true).open()?)
+    }
+
+    fn from_db(db: sled::Db) -> Result<Self> {
+        Ok(Self {
+            programs: db.open_tree("programs")?,
+            events: db.open_tree("events")?,
+            reports: db.open_tree("reports")?,
+            last_contact: db.open_tree("last_contact")?,
+            db,
+        })
+    }
+
+    /// Store a program, replacing any previous version of it
+    pub fn store_program(&self, program: &Program) -> Result<()> {
+        self.programs
+            .insert(program.id.as_str(), serde_json::to_vec(program)?)?;
+        Ok(())
+    }
+
+    /// Get a previously stored program
+    pub fn program(&self, program_id: &ProgramId) -> Result<Option<Program>> {
+        match self.programs.get(program_id.as_str())? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Replace all stored events of a program with the given events
+    pub fn store_events(&self, program_id: &ProgramId, events: &[Event]) -> Result<()> {
+        let prefix = Self::event_prefix(program_id);
+
+        let mut batch = sled::Batch::default();
+        for key in self.events.scan_prefix(&prefix).keys() {
+            batch.remove(key?);
+        }
+        for event in events {
+            let key = format!("{prefix}{}", event.id);
+            batch.insert(key.as_bytes(), serde_json::to_vec(event)?);
+        }
+
+        self.events.apply_batch(batch)?;
+        Ok(())
+    }
+
+    /// Get all stored events of a program
+    pub fn events(&self, program_id: &ProgramId) -> Result<Vec<Event>> {
+        self.events
+            .scan_prefix(Self::event_prefix(program_id))
+            .values()
+            .map(|bytes| -> Result<Event> { Ok(serde_json::from_slice(&bytes?)?) })
+            .collect()
+    }
+
+    /// The last time the events of a program were successfully retrieved from the VTN
+    pub fn last_contact(&self, program_id: &ProgramId) -> Result<Option<DateTime<Utc>>> {
+        let Some(bytes) = self.last_contact.get(program_id.as_str())? else {
+            return Ok(None);
+        };
+
+        let Ok(timestamp) = <[u8; 8]>::try_from(bytes.as_ref()) else {
+            warn!(program_id = %program_id, "ignoring malformed last contact timestamp");
+            return Ok(None);
+        };
+
+        Ok(DateTime::from_timestamp_micros(i64::from_be_bytes(
+            timestamp,
+        )))
+    }
+
+    /// The timeline of a program based on the stored program and events.
+    /// The [staleness](Timeline::staleness) of the timeline is based on the last successful
+    /// [sync](Self::sync_timeline) with the VTN.
+    ///
+    /// Returns `None` if the program is not stored, or if no timeline can be constructed
+    pub fn timeline(&self, program_id: &ProgramId) -> Result<Option<Timeline>> {
+        let Some(program) = self.program(program_id)? else {
+            return Ok(None);
+        };
+
+        let events = self.events(program_id)?;
+        let events = events.iter().map(|e| &e.content).collect();
+
+        let Some(timeline) = Timeline::from_events(&program.content, events) else {
+            return Ok(None);
+        };
+
+        Ok(Some(match self.last_contact(program_id)? {
+            Some(last_contact) => timeline.with_last_contact(last_contact),
+            None => timeline,
+        }))
+    }
+
+    /// Fetch the events of a program from the VTN and store them.
+    ///
+    /// If the VTN cannot be reached or is temporarily unavailable, see [`Error::is_retryable`],
+    /// the last known timeline of the program is returned instead. Other errors are returned.
+    /// Fails with [`Error::NoTimeline`] if an interval of the events has no interval period.
+    pub async fn sync_timeline(&self, program: &ProgramClient) -> Result<Timeline> {
+        self.store_program(program.program())?;
+
+        match program.get_all_events().await {
+            Ok(events) => {
+                let events: Vec<Event> = events.iter().map(|e| e.event().clone()).collect();
+                self.store_events(program.id(), &events)?;
+                self.last_contact.insert(
+                    program.id().as_str(),
+                    &Utc::now().timestamp_micros().to_be_bytes(),
+                )?;
+            }
+            Err(err) if err.is_retryable() => {
+                warn!(program_id = %program.id(), "could not reach VTN, using stored events: {}", err);
+            }
+            Err(err) => return Err(err),
+        }
+
+        self.timeline(program.id())?.ok_or(Error::NoTimeline)
+    }
+
+    /// Queue a report to be sent to the VTN, see [`Self::flush_reports`]
+    pub fn queue_report(&self, report: &ReportContent) -> Result<()> {
+        // ids are monotonic, such that reports are sent in the order they were queued
+        let id = self.db.generate_id()?;
+        self.reports
+            .insert(id.to_be_bytes(), serde_json::to_vec(report)?)?;
+        Ok(())
+    }
+
+    /// All reports that are queued, but not yet sent
+    pub fn queued_reports(&self) -> Result<Vec<ReportContent>> {
+        self.reports
+            .iter()
+            .values()
+            .map(|bytes| -> Result<ReportContent> { Ok(serde_json::from_slice(&bytes?)?) })
+            .collect()
+    }
+
+    /// Send the queued reports to the VTN, in the order they were queued.
+    ///
+    /// Stops at the first report that could not be sent,
+    /// leaving it and all subsequent reports in the queue.
+    /// Returns the number of reports that were sent.
+    pub async fn flush_reports(&self, client: &Client) -> Result<usize> {
+        let mut sent = 0;
+
+        for entry in self.reports.iter() {
+            let (key, bytes) = entry?;
+            let report: ReportContent = serde_json::from_slice(&bytes)?;
+
+            client.create_report(report).await?;
+            self.reports.remove(key)?;
+            sent += 1;
+        }
+
+        trace!(sent, "flushed queued reports");
+        self.reports.flush_async().await?;
+
+        Ok(sent)
+    }
+
+    fn event_prefix(program_id: &ProgramId) -> String {
+        format!("{}/", program_id.as_str())
+    }
+}
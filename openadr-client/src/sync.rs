@@ -0,0 +1,153 @@
+//! Syncing the events of a program with a desired set of events, e.g., a schedule that a
+//! business logic system regenerates in full, with as few requests to the VTN as possible.
+
+use std::{collections::HashSet, sync::Arc};
+
+use openadr_wire::{
+    event::{EventContent, EventId},
+    program::ProgramId,
+};
+
+use crate::{
+    error::{Error, Result},
+    ClientRef, EventClient,
+};
+
+/// The number of events per kind of change of an [`EventPlan`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
+    pub unchanged: usize,
+}
+
+/// The requests that change the events of a program into the desired events,
+/// see [`ProgramClient::plan_events`](crate::ProgramClient::plan_events).
+///
+/// Events are matched by name. Events of the program without a name are left alone.
+#[derive(Debug)]
+pub struct EventPlan {
+    client: Arc<ClientRef>,
+    create: Vec<EventContent>,
+    update: Vec<(EventClient, EventContent)>,
+    delete: Vec<EventClient>,
+    unchanged: usize,
+}
+
+impl EventPlan {
+    pub(crate) fn new(
+        client: Arc<ClientRef>,
+        program_id: &ProgramId,
+        current: Vec<EventClient>,
+        desired: Vec<EventContent>,
+    ) -> Result<Self> {
+        let mut names = HashSet::new();
+        for event in &desired {
+            let name = event.event_name.as_deref().ok_or(Error::UnnamedEvent)?;
+            if !names.insert(name) {
+                return Err(Error::DuplicateObject);
+            }
+        }
+
+        let mut current = current
+            .into_iter()
+            .filter(|event| event.content().event_name.is_some())
+            .collect::<Vec<_>>();
+
+        let mut plan = Self {
+            client,
+            create: vec![],
+            update: vec![],
+            delete: vec![],
+            unchanged: 0,
+        };
+
+        for mut content in desired {
+            content.program_id = program_id.clone();
+
+            let existing = current
+                .iter()
+                .position(|event| event.content().event_name == content.event_name);
+
+            match existing.map(|index| current.swap_remove(index)) {
+                None => plan.create.push(content),
+                Some(event) if same_content(event.content(), &content) => plan.unchanged += 1,
+                Some(event) => plan.update.push((event, content)),
+            }
+        }
+
+        // not desired anymore, or a duplicate of a desired name
+        plan.delete = current;
+
+        Ok(plan)
+    }
+
+    pub fn summary(&self) -> SyncSummary {
+        SyncSummary {
+            created: self.create.len(),
+            updated: self.update.len(),
+            deleted: self.delete.len(),
+            unchanged: self.unchanged,
+        }
+    }
+
+    /// Whether the events of the program are as desired already
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.update.is_empty() && self.delete.is_empty()
+    }
+
+    /// The events to create
+    pub fn creates(&self) -> &[EventContent] {
+        &self.create
+    }
+
+    /// The IDs of the events to update
+    pub fn updates(&self) -> impl Iterator<Item = &EventId> {
+        self.update.iter().map(|(event, _)| event.id())
+    }
+
+    /// The IDs of the events to delete
+    pub fn deletes(&self) -> impl Iterator<Item = &EventId> {
+        self.delete.iter().map(|event| event.id())
+    }
+
+    /// Send the requests to the VTN.
+    ///
+    /// Events are created and updated before others are deleted, such that the program does not
+    /// lack events in between. The requests stop at the first error, after which the plan can be made again.
+    pub async fn execute(self) -> Result<SyncSummary> {
+        let summary = self.summary();
+
+        for content in self.create {
+            self.client
+                .post::<_, openadr_wire::Event>("events", &content, &[])
+                .await?;
+        }
+
+        for (mut event, content) in self.update {
+            *event.content_mut() = content;
+            event.update().await?;
+        }
+
+        for event in self.delete {
+            event.delete().await?;
+        }
+
+        Ok(summary)
+    }
+}
+
+/// Whether the events are the same, apart from the discriminator that the VTN may fill in
+fn same_content(current: &EventContent, desired: &EventContent) -> bool {
+    let current = EventContent {
+        object_type: None,
+        ..current.clone()
+    };
+    let desired = EventContent {
+        object_type: None,
+        ..desired.clone()
+    };
+
+    current == desired
+}
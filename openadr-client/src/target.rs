@@ -1,4 +1,4 @@
-use openadr_wire::target::TargetLabel;
+use openadr_wire::{capabilities::Capabilities, target::TargetLabel};
 
 /// Target for a query to the VTN
 #[derive(Copy, Clone, Debug)]
@@ -70,7 +70,7 @@ impl<'a> Target<'a> {
     }
 
     /// Get the list of target values for this specific target
-    pub fn target_values(&self) -> &[&str] {
+    pub fn target_values(&self) -> &[&'a str] {
         match self {
             Target::Program(v) => std::slice::from_ref(v),
             Target::Programs(v) => v,
@@ -90,4 +90,11 @@ impl<'a> Target<'a> {
             Target::Others(_, v) => v,
         }
     }
+
+    /// Whether the VTN with these capabilities accepts this target type,
+    /// see [`Client::capabilities`](crate::Client::capabilities).
+    /// The standard labels are available as [`TargetLabel::STANDARD`].
+    pub fn is_supported_by(&self, capabilities: &Capabilities) -> bool {
+        capabilities.supports_target_type(&self.target_label())
+    }
 }
@@ -0,0 +1,286 @@
+//! Supervises the background tasks of a client, like pollers, report schedulers,
+//! and the notification server, see [`ClientTasks`]
+
+use std::{
+    any::Any,
+    future::Future,
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::FutureExt;
+use tokio::{sync::watch, task::JoinSet};
+use tracing::{error, warn};
+
+use crate::error::{Error, Result};
+
+/// Tells a task that it should stop, see [`ClientTasks::shutdown`]
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Wait until the shutdown is requested, or the [`ClientTasks`] were dropped
+    pub async fn requested(&mut self) {
+        let _ = self.0.wait_for(|requested| *requested).await;
+    }
+}
+
+/// How a task ended
+#[derive(Debug)]
+pub enum TaskOutcome {
+    Finished,
+    Failed(Error),
+    /// The task panicked, with the panic message
+    Panicked(String),
+    /// The task did not stop within the grace period of [`ClientTasks::shutdown_and_join`]
+    Aborted,
+}
+
+#[derive(Debug)]
+pub struct TaskExit {
+    pub name: String,
+    pub outcome: TaskOutcome,
+}
+
+/// Owns the background tasks of a client, such that an application can stop them together,
+/// and learns when one of them fails or panics.
+///
+/// Each task gets a [`Shutdown`] to stop gracefully. Failures and panics are logged as they happen,
+/// and reported by [`next_exit`](Self::next_exit). Dropping the `ClientTasks` aborts all tasks.
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use openadr_client::{ClientTasks, Error, ProgramClient, Shutdown};
+/// async fn poll_events(program: ProgramClient, mut shutdown: Shutdown) -> Result<(), Error> {
+///     loop {
+///         tokio::select! {
+///             _ = shutdown.requested() => return Ok(()),
+///             _ = tokio::time::sleep(Duration::from_secs(30)) => {}
+///         }
+///         let events = program.get_all_events().await?;
+///         // ...
+///     }
+/// }
+///
+/// # async fn run(program: ProgramClient) {
+/// let mut tasks = ClientTasks::new();
+/// tasks.spawn("poll events", |shutdown| poll_events(program, shutdown));
+///
+/// tokio::select! {
+///     _ = tokio::signal::ctrl_c() => {}
+///     Some(exit) = tasks.next_exit() => eprintln!("{} stopped: {:?}", exit.name, exit.outcome),
+/// }
+/// tasks.shutdown_and_join(Duration::from_secs(5)).await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ClientTasks {
+    shutdown: watch::Sender<bool>,
+    tasks: JoinSet<TaskExit>,
+    /// The names of the tasks that did not end yet
+    running: Arc<Mutex<Vec<String>>>,
+}
+
+impl Default for ClientTasks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ClientTasks {
+    pub fn new() -> Self {
+        Self {
+            shutdown: watch::Sender::new(false),
+            tasks: JoinSet::new(),
+            running: Default::default(),
+        }
+    }
+
+    /// Spawn a task on the current Tokio runtime. The `name` identifies it in the logs and [`TaskExit`]s.
+    pub fn spawn<F, Fut>(&mut self, name: impl Into<String>, task: F)
+    where
+        F: FnOnce(Shutdown) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let name = name.into();
+        let task = task(self.signal());
+        let running = RunningGuard::new(self.running.clone(), name.clone());
+
+        self.tasks.spawn(async move {
+            let outcome = match AssertUnwindSafe(task).catch_unwind().await {
+                Ok(Ok(())) => TaskOutcome::Finished,
+                Ok(Err(err)) => {
+                    warn!(task = %name, ?err, "client task failed");
+                    TaskOutcome::Failed(err)
+                }
+                Err(panic) => {
+                    let message = panic_message(panic);
+                    error!(task = %name, panic = %message, "client task panicked");
+                    TaskOutcome::Panicked(message)
+                }
+            };
+            drop(running);
+
+            TaskExit { name, outcome }
+        });
+    }
+
+    /// A [`Shutdown`] for work that is not spawned as a task of this supervisor,
+    /// e.g., the graceful shutdown of a notification server
+    pub fn signal(&self) -> Shutdown {
+        Shutdown(self.shutdown.subscribe())
+    }
+
+    /// Ask all tasks to stop, without waiting for them
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// The names of the tasks that are still running
+    pub fn running(&self) -> Vec<String> {
+        self.running.lock().unwrap().clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    /// Wait until the next task ends, or `None` if no tasks are left
+    pub async fn next_exit(&mut self) -> Option<TaskExit> {
+        loop {
+            match self.tasks.join_next().await? {
+                Ok(exit) => return Some(exit),
+                // tasks are only aborted by `shutdown_and_join`, which reports them itself
+                Err(err) if err.is_cancelled() => continue,
+                Err(err) => panic!("client task could not be joined: {err}"),
+            }
+        }
+    }
+
+    /// Wait until all tasks ended by themselves
+    pub async fn join_all(&mut self) -> Vec<TaskExit> {
+        let mut exits = Vec::with_capacity(self.tasks.len());
+        while let Some(exit) = self.next_exit().await {
+            exits.push(exit);
+        }
+        exits
+    }
+
+    /// Ask all tasks to stop, and abort those still running after the `grace` period
+    pub async fn shutdown_and_join(mut self, grace: Duration) -> Vec<TaskExit> {
+        self.shutdown();
+
+        let mut exits = Vec::with_capacity(self.tasks.len());
+        let _ = tokio::time::timeout(grace, async {
+            while let Some(exit) = self.next_exit().await {
+                exits.push(exit);
+            }
+        })
+        .await;
+
+        let mut aborted = self.running();
+        if !aborted.is_empty() {
+            warn!(tasks = ?aborted, "aborting client tasks that did not stop in time");
+            self.tasks.abort_all();
+            while let Some(exit) = self.next_exit().await {
+                // the task ended by itself in the meantime
+                if let Some(index) = aborted.iter().position(|name| name == &exit.name) {
+                    aborted.swap_remove(index);
+                }
+                exits.push(exit);
+            }
+        }
+
+        aborted.sort();
+        exits.extend(aborted.into_iter().map(|name| TaskExit {
+            name,
+            outcome: TaskOutcome::Aborted,
+        }));
+
+        exits
+    }
+}
+
+impl Drop for ClientTasks {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Removes the name of a task from the running tasks when it ends, also when it is aborted
+struct RunningGuard {
+    running: Arc<Mutex<Vec<String>>>,
+    name: String,
+}
+
+impl RunningGuard {
+    fn new(running: Arc<Mutex<Vec<String>>>, name: String) -> Self {
+        running.lock().unwrap().push(name.clone());
+        Self { running, name }
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        let mut running = self.running.lock().unwrap();
+        if let Some(index) = running.iter().position(|name| name == &self.name) {
+            running.swap_remove(index);
+        }
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&'static str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "unknown panic".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn oops() -> Result<()> {
+        panic!("oops")
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn supervises_tasks() {
+        let mut tasks = ClientTasks::new();
+        tasks.spawn("finishes", |_| async { Ok(()) });
+        tasks.spawn("fails", |_| async { Err(Error::ObjectNotFound) });
+        tasks.spawn("panics", |_| async { oops() });
+
+        let mut exits = tasks.join_all().await;
+        exits.sort_by(|a, b| a.name.cmp(&b.name));
+        assert!(matches!(
+            exits[0].outcome,
+            TaskOutcome::Failed(Error::ObjectNotFound)
+        ));
+        assert!(matches!(exits[1].outcome, TaskOutcome::Finished));
+        assert!(matches!(&exits[2].outcome, TaskOutcome::Panicked(message) if message == "oops"));
+        assert!(tasks.is_empty());
+
+        tasks.spawn("graceful", |mut shutdown| async move {
+            shutdown.requested().await;
+            Ok(())
+        });
+        tasks.spawn("stubborn", |_| std::future::pending());
+        assert_eq!(tasks.running().len(), 2);
+
+        let exits = tasks.shutdown_and_join(Duration::from_secs(1)).await;
+        assert_eq!(exits.len(), 2);
+        assert_eq!(exits[0].name, "graceful");
+        assert!(matches!(exits[0].outcome, TaskOutcome::Finished));
+        assert_eq!(exits[1].name, "stubborn");
+        assert!(matches!(exits[1].outcome, TaskOutcome::Aborted));
+    }
+}
@@ -0,0 +1,18 @@
+//! Propagation of the trace context to the VTN, only available with the `otel` feature.
+//!
+//! The context is taken from the OpenTelemetry layer of the tracing subscriber of the application,
+//! see `tracing-opentelemetry`. Without such a layer, no `traceparent` header is sent.
+
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry_http::HeaderInjector;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use reqwest::Request;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Add the W3C `traceparent` header of the span to the request
+pub(crate) fn with_trace_context(span: &Span, mut request: Request) -> Request {
+    TraceContextPropagator::new()
+        .inject_context(&span.context(), &mut HeaderInjector(request.headers_mut()));
+    request
+}
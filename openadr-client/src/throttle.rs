@@ -0,0 +1,91 @@
+use std::time::Duration;
+
+use tokio::{
+    sync::{Mutex, Semaphore, SemaphorePermit},
+    time::Instant,
+};
+
+/// Limits the load a client puts on a VTN,
+/// such that large fleets of VENs cannot accidentally overload a VTN.
+#[derive(Debug)]
+pub(crate) struct Throttle {
+    concurrent_requests: Semaphore,
+    min_request_interval: Duration,
+    next_request: Mutex<Instant>,
+}
+
+impl Default for Throttle {
+    fn default() -> Self {
+        Self::new(None, Duration::ZERO)
+    }
+}
+
+impl Throttle {
+    pub(crate) fn new(
+        max_concurrent_requests: Option<usize>,
+        min_request_interval: Duration,
+    ) -> Self {
+        let max_concurrent_requests = max_concurrent_requests
+            .unwrap_or(Semaphore::MAX_PERMITS)
+            .clamp(1, Semaphore::MAX_PERMITS);
+
+        Self {
+            concurrent_requests: Semaphore::new(max_concurrent_requests),
+            min_request_interval,
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until a request may be sent. The request counts as in-flight until the
+    /// returned permit is dropped.
+    pub(crate) async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self
+            .concurrent_requests
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+
+        if !self.min_request_interval.is_zero() {
+            let mut next_request = self.next_request.lock().await;
+            tokio::time::sleep_until(*next_request).await;
+            *next_request = Instant::now() + self.min_request_interval;
+        }
+
+        permit
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn min_request_interval() {
+        let throttle = Throttle::new(None, Duration::from_secs(5));
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            drop(throttle.acquire().await);
+        }
+
+        assert_eq!(start.elapsed(), Duration::from_secs(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn max_concurrent_requests() {
+        let throttle = Throttle::new(Some(2), Duration::ZERO);
+
+        let first = throttle.acquire().await;
+        let _second = throttle.acquire().await;
+
+        assert!(
+            tokio::time::timeout(Duration::from_secs(1), throttle.acquire())
+                .await
+                .is_err(),
+            "a third concurrent request must wait"
+        );
+
+        drop(first);
+        let _third = throttle.acquire().await;
+    }
+}
@@ -0,0 +1,208 @@
+//! Following the intervals of a [`Timeline`] as they become active, e.g., to switch the
+//! setpoint of a device at the start of every interval without sleeping yourself.
+
+use futures_util::{stream, Stream};
+use openadr_wire::event::EventValuesMap;
+use tokio::sync::watch;
+
+use crate::{sleep_until, Clock, IntervalRange, Timeline};
+
+/// Streams the intervals of a [`Timeline`] in the time of a [`Clock`], see [`interval_stream`]
+pub trait IntoIntervalStream {
+    /// Yields every interval of the timeline when it becomes active, with its values.
+    ///
+    /// The stream ends after the last interval became active.
+    /// To also follow newly arriving events, use [`interval_stream`] instead.
+    fn into_stream<C: Clock>(
+        self,
+        clock: C,
+    ) -> impl Stream<Item = (IntervalRange, Vec<EventValuesMap>)> + Send;
+}
+
+impl IntoIntervalStream for Timeline {
+    fn into_stream<C: Clock>(
+        self,
+        clock: C,
+    ) -> impl Stream<Item = (IntervalRange, Vec<EventValuesMap>)> + Send {
+        // the sender is dropped right away, no updates will arrive
+        let (_, timelines) = watch::channel(self);
+        interval_stream(clock, timelines)
+    }
+}
+
+struct Active<C> {
+    clock: C,
+    timelines: watch::Receiver<Timeline>,
+    /// Whether the sender of the timelines is alive, i.e., whether updates can still arrive
+    updates: bool,
+    /// The interval that was yielded last, if it is still active
+    last: Option<(IntervalRange, Vec<EventValuesMap>)>,
+}
+
+impl<C: Clock> Active<C> {
+    async fn next(&mut self) -> Option<(IntervalRange, Vec<EventValuesMap>)> {
+        loop {
+            let now = self.clock.now();
+            let (active, next_update) = {
+                let timeline = self.timelines.borrow_and_update();
+                let active = timeline
+                    .at_datetime(&now)
+                    .map(|(range, interval)| (range.clone(), interval.value_map.to_vec()));
+                (active, timeline.next_update(&now))
+            };
+
+            if active != self.last {
+                self.last.clone_from(&active);
+                if active.is_some() {
+                    return active;
+                }
+            }
+
+            match (next_update, self.updates) {
+                (None, false) => return None,
+                (Some(deadline), false) => sleep_until(&self.clock, deadline).await,
+                (None, true) => self.updates = self.timelines.changed().await.is_ok(),
+                (Some(deadline), true) => tokio::select! {
+                    _ = sleep_until(&self.clock, deadline) => {}
+                    changed = self.timelines.changed() => self.updates = changed.is_ok(),
+                },
+            }
+        }
+    }
+}
+
+/// Yields the active interval of the latest timeline whenever it changes, with its values.
+///
+/// An interval is yielded when it becomes active in the time of the `clock`,
+/// or when a new timeline is sent on the channel that changes the active interval,
+/// e.g., after [`ProgramClient::get_timeline`](crate::ProgramClient::get_timeline) found new events.
+/// As with a [`Schedule`](crate::Schedule), jumps of the clock do not cause misfires:
+/// intervals that were skipped entirely because the clock jumped forward are not yielded.
+///
+/// The stream ends when the sender is dropped and the last interval of the timeline became active.
+pub fn interval_stream<C: Clock>(
+    clock: C,
+    timelines: watch::Receiver<Timeline>,
+) -> impl Stream<Item = (IntervalRange, Vec<EventValuesMap>)> + Send {
+    let active = Active {
+        clock,
+        timelines,
+        updates: true,
+        last: None,
+    };
+
+    stream::unfold(active, |mut active| async move {
+        let item = active.next().await?;
+        Some((item, active))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::{DateTime, TimeDelta, Utc};
+    use futures_util::StreamExt;
+    use openadr_wire::{
+        event::{EventContent, EventInterval, EventType},
+        interval::IntervalPeriod,
+        values_map::Value,
+    };
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::{MockClock, ProgramContent, CLOCK_CHECK_INTERVAL};
+
+    fn start() -> DateTime<Utc> {
+        "2024-06-01T12:00:00Z".parse().unwrap()
+    }
+
+    fn price(value: i64) -> Vec<EventValuesMap> {
+        vec![EventValuesMap {
+            value_type: EventType::Price,
+            values: vec![Value::Integer(value)],
+        }]
+    }
+
+    /// A timeline of consecutive hours, starting at [`start`], with the given prices
+    fn timeline(prices: &[i64]) -> Timeline {
+        let intervals = prices
+            .iter()
+            .enumerate()
+            .map(|(i, value)| EventInterval {
+                id: i as i32,
+                interval_period: Some(IntervalPeriod {
+                    start: start() + TimeDelta::hours(i as i64),
+                    duration: Some(openadr_wire::Duration::PT1H),
+                    randomize_start: None,
+                }),
+                payloads: price(*value),
+            })
+            .collect();
+        let event = EventContent::new("program-1".parse().unwrap(), intervals);
+
+        Timeline::from_events(&ProgramContent::new("program-1"), vec![&event]).unwrap()
+    }
+
+    fn hour(i: i64) -> IntervalRange {
+        start() + TimeDelta::hours(i)..start() + TimeDelta::hours(i + 1)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fires_at_interval_boundaries() {
+        let clock = MockClock::new(start() + TimeDelta::minutes(30));
+        let stream = timeline(&[1, 2, 3]).into_stream(clock.clone());
+        tokio::pin!(stream);
+
+        // the interval that is already active is yielded right away
+        assert_eq!(stream.next().await, Some((hour(0), price(1))));
+        assert!(timeout(Duration::from_secs(3600), stream.next())
+            .await
+            .is_err());
+
+        clock.advance(TimeDelta::minutes(30));
+        let next = timeout(CLOCK_CHECK_INTERVAL, stream.next()).await.unwrap();
+        assert_eq!(next, Some((hour(1), price(2))));
+
+        // the clock jumps past the end of the timeline, skipping the last interval
+        clock.advance(TimeDelta::hours(5));
+        let next = timeout(CLOCK_CHECK_INTERVAL, stream.next()).await.unwrap();
+        assert_eq!(next, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn follows_new_timelines() {
+        let clock = MockClock::new(start());
+        let (sender, receiver) = watch::channel(Timeline::new());
+        let stream = interval_stream(clock.clone(), receiver);
+        tokio::pin!(stream);
+
+        // nothing is active yet
+        assert!(timeout(Duration::from_secs(60), stream.next())
+            .await
+            .is_err());
+
+        sender.send(timeline(&[1, 2])).unwrap();
+        assert_eq!(stream.next().await, Some((hour(0), price(1))));
+
+        // an update that does not change the active interval is not yielded
+        sender.send(timeline(&[1, 5])).unwrap();
+        assert!(timeout(Duration::from_secs(60), stream.next())
+            .await
+            .is_err());
+
+        // the price of the active interval changed
+        sender.send(timeline(&[4, 5])).unwrap();
+        assert_eq!(stream.next().await, Some((hour(0), price(4))));
+
+        clock.advance(TimeDelta::hours(1));
+        let next = timeout(CLOCK_CHECK_INTERVAL, stream.next()).await.unwrap();
+        assert_eq!(next, Some((hour(1), price(5))));
+
+        // without a sender, the stream ends once the last interval ended
+        drop(sender);
+        clock.advance(TimeDelta::hours(1));
+        let next = timeout(CLOCK_CHECK_INTERVAL, stream.next()).await.unwrap();
+        assert_eq!(next, None);
+    }
+}
@@ -0,0 +1,179 @@
+use openadr_wire::{
+    resource::{Resource, ResourceContent, ResourceId},
+    target::TargetLabel,
+    ven::{VenContent, VenId},
+    Ven,
+};
+
+use crate::{
+    borrow_query,
+    error::{Error, Result},
+    Client, Filters, Page, PaginationOptions, ResourceClient,
+};
+
+/// A client for interacting with the data of a specific VEN and its resources,
+/// e.g., for a VEN to register its resources with the VTN.
+#[derive(Debug)]
+pub struct VenClient {
+    client: Client,
+    data: Ven,
+}
+
+impl VenClient {
+    pub(super) fn from_ven(client: Client, ven: Ven) -> Self {
+        Self { client, data: ven }
+    }
+
+    /// Get the id of the VEN
+    pub fn id(&self) -> &VenId {
+        &self.data.id
+    }
+
+    /// Get the time the VEN was created on the VTN
+    pub fn created_date_time(&self) -> chrono::DateTime<chrono::Utc> {
+        self.data.created_date_time
+    }
+
+    /// Get the time the VEN was last modified on the VTN
+    pub fn modification_date_time(&self) -> chrono::DateTime<chrono::Utc> {
+        self.data.modification_date_time
+    }
+
+    /// The VEN as it was last received from the VTN
+    pub fn ven(&self) -> &Ven {
+        &self.data
+    }
+
+    /// Read the data of the VEN
+    pub fn content(&self) -> &VenContent {
+        &self.data.content
+    }
+
+    /// Modify the data of the VEN, make sure to update the VEN on the
+    /// VTN once your modifications are complete.
+    pub fn content_mut(&mut self) -> &mut VenContent {
+        &mut self.data.content
+    }
+
+    /// Save any modifications of the VEN to the VTN
+    pub async fn update(&mut self) -> Result<()> {
+        let res = self
+            .client
+            .client_ref
+            .put(&format!("vens/{}", self.id()), &self.data.content, &[])
+            .await?;
+        self.data = res;
+        Ok(())
+    }
+
+    /// Delete the VEN from the VTN
+    pub async fn delete(self) -> Result<Ven> {
+        self.client
+            .client_ref
+            .delete(&format!("vens/{}", self.id()), &[])
+            .await
+    }
+
+    /// Create a new resource of the VEN on the VTN
+    pub async fn create_resource(&self, resource_data: ResourceContent) -> Result<ResourceClient> {
+        let resource = self
+            .client
+            .client_ref
+            .post(
+                &format!("vens/{}/resources", self.id()),
+                &resource_data,
+                &[],
+            )
+            .await?;
+        Ok(ResourceClient::from_resource(
+            self.client.client_ref.clone(),
+            resource,
+        ))
+    }
+
+    /// Lowlevel operation that gets a list of resources of the VEN from the VTN with the given query parameters
+    pub async fn get_resources<'a>(
+        &self,
+        filters: impl Into<Filters<'a>>,
+        pagination: PaginationOptions,
+    ) -> Result<Vec<ResourceClient>> {
+        let filters = filters.into();
+        let mut resources = self.get_resources_page(&filters, pagination).await?.items;
+        resources.retain(|resource| filters.matches_resource(resource.content()));
+        Ok(resources)
+    }
+
+    async fn get_resources_page(
+        &self,
+        filters: &Filters<'_>,
+        pagination: PaginationOptions,
+    ) -> Result<Page<ResourceClient>> {
+        let query = filters.query_params(Some(TargetLabel::ResourceName), pagination)?;
+
+        let resources: Page<Resource> = self
+            .client
+            .client_ref
+            .get_page(
+                &format!("vens/{}/resources", self.id()),
+                &borrow_query(&query),
+            )
+            .await?;
+        Ok(resources.map(|resource| {
+            ResourceClient::from_resource(self.client.client_ref.clone(), resource)
+        }))
+    }
+
+    /// Get all resources of the VEN matching the filters, trying to paginate whenever possible
+    pub async fn get_resources_matching(
+        &self,
+        filters: Filters<'_>,
+    ) -> Result<Vec<ResourceClient>> {
+        let mut resources = self
+            .client
+            .client_ref
+            .get_all_pages(|pagination| self.get_resources_page(&filters, pagination))
+            .await?;
+        resources.retain(|resource| filters.matches_resource(resource.content()));
+        Ok(resources)
+    }
+
+    /// Get all resources of the VEN from the VTN, trying to paginate whenever possible
+    pub async fn get_all_resources(&self) -> Result<Vec<ResourceClient>> {
+        self.get_resources_matching(Filters::new()).await
+    }
+
+    /// Get a resource of the VEN by name
+    pub async fn get_resource_by_name(&self, name: &str) -> Result<ResourceClient> {
+        let pagination = PaginationOptions {
+            skip: 0,
+            limit: 2,
+            ..Default::default()
+        };
+        let mut resources = self
+            .get_resources(Filters::new().name(name), pagination)
+            .await?;
+
+        match resources[..] {
+            [] => Err(Error::ObjectNotFound),
+            [_] => Ok(resources.remove(0)),
+            [..] => Err(Error::DuplicateObject),
+        }
+    }
+
+    /// Get a resource of the VEN by id
+    pub async fn get_resource_by_id(&self, id: &ResourceId) -> Result<ResourceClient> {
+        let resource = self
+            .client
+            .client_ref
+            .get(
+                &format!("vens/{}/resources/{}", self.id(), id.as_str()),
+                &[],
+            )
+            .await?;
+
+        Ok(ResourceClient::from_resource(
+            self.client.client_ref.clone(),
+            resource,
+        ))
+    }
+}
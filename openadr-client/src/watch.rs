@@ -0,0 +1,93 @@
+//! Watching the events of a program by polling the VTN, for VENs that cannot receive notifications.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use futures_util::{stream, Stream};
+use openadr_wire::{event::EventId, Event};
+use tokio::time::{interval, Interval, MissedTickBehavior};
+use tracing::warn;
+
+use crate::{Client, EventClient, Filters, ProgramId};
+
+/// A change to the events of a program, see [`ProgramClient::watch_events`](crate::ProgramClient::watch_events)
+#[derive(Debug)]
+pub enum EventUpdate {
+    Created(EventClient),
+    Updated(EventClient),
+    /// The event as it was seen last, before it was deleted
+    Deleted(Event),
+}
+
+struct Watch {
+    client: Client,
+    program_id: ProgramId,
+    interval: Interval,
+    /// The events of the previous poll
+    seen: HashMap<EventId, Event>,
+    pending: VecDeque<EventUpdate>,
+}
+
+impl Watch {
+    async fn poll(&mut self) {
+        let events = match self
+            .client
+            .get_events_matching(Filters::new().program_id(&self.program_id))
+            .await
+        {
+            Ok(events) => events,
+            Err(err) => {
+                warn!(?err, program_id = %self.program_id, "could not poll the events of the program, retrying");
+                return;
+            }
+        };
+
+        let mut previous = std::mem::take(&mut self.seen);
+        for event in events {
+            self.seen.insert(event.id().clone(), event.event().clone());
+
+            match previous.remove(event.id()) {
+                None => self.pending.push_back(EventUpdate::Created(event)),
+                // besides the modification time, compare the content, in case the event
+                // changed twice within the precision of the timestamps of the VTN
+                Some(seen) if seen != *event.event() => {
+                    self.pending.push_back(EventUpdate::Updated(event))
+                }
+                Some(_) => {}
+            }
+        }
+
+        self.pending
+            .extend(previous.into_values().map(EventUpdate::Deleted));
+    }
+}
+
+pub(crate) fn watch_events(
+    client: Client,
+    program_id: ProgramId,
+    poll_interval: Duration,
+) -> impl Stream<Item = EventUpdate> {
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let watch = Watch {
+        client,
+        program_id,
+        interval,
+        seen: HashMap::new(),
+        pending: VecDeque::new(),
+    };
+
+    stream::unfold(watch, |mut watch| async move {
+        loop {
+            if let Some(update) = watch.pending.pop_front() {
+                return Some((update, watch));
+            }
+
+            watch.interval.tick().await;
+            watch.poll().await;
+        }
+    })
+}
@@ -1,4 +1,6 @@
-use openadr_wire::program::ProgramContent;
+use openadr_client::{ClientCredentials, MockClientRef, Target};
+use openadr_vtn::{data_source::PostgresStorage, jwt::JwtManager, state::AppState};
+use openadr_wire::{oauth::Scope, program::ProgramContent};
 use sqlx::PgPool;
 
 mod common;
@@ -17,3 +19,47 @@ async fn basic_create_read(db: PgPool) -> Result<(), openadr_client::Error> {
 
     Ok(())
 }
+
+#[sqlx::test(fixtures("users"))]
+async fn capabilities(db: PgPool) -> Result<(), openadr_client::Error> {
+    let client = common::setup_client(db).await;
+
+    let capabilities = client.capabilities().await?;
+    assert_eq!(capabilities.spec_version, "3.0.1");
+    assert!(capabilities.max_page_size >= 1);
+    assert!(Target::Group("group-1").is_supported_by(&capabilities));
+    assert!(Target::Other("METER_ID", "meter-1").is_supported_by(&capabilities));
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn whoami(db: PgPool) -> Result<(), openadr_client::Error> {
+    let client = common::setup_client(db).await;
+
+    let who_am_i = client.whoami().await?;
+    assert_eq!(who_am_i.client_id, "admin");
+    assert!(who_am_i.roles.contains(&"UserManager".to_string()));
+    assert!(who_am_i.any_business);
+    assert!(who_am_i.expires_at > chrono::Utc::now());
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn restricted_scopes(db: PgPool) -> Result<(), openadr_client::Error> {
+    let storage = PostgresStorage::new(db).unwrap();
+    let vtn = AppState::new(storage, JwtManager::from_secret(b"test")).into_router();
+    let credentials = ClientCredentials::admin().with_scopes([Scope::ReadAll]);
+    let client = MockClientRef::new(vtn).into_client(Some(credentials));
+    assert_eq!(client.whoami().await?.scopes, [Scope::ReadAll]);
+
+    client.get_all_programs().await?;
+    let err = client
+        .create_program(openadr_testing::program("read-only"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, openadr_client::Error::Forbidden(_)), "{err}");
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use openadr_client::{Client, ClientCredentials, MockClientRef, ProgramClient};
+use openadr_client::{Client, ClientBuilder, ClientCredentials, MockClientRef, ProgramClient};
 use openadr_wire::program::ProgramContent;
 use sqlx::PgPool;
 use std::env::VarError;
@@ -19,6 +19,17 @@ pub async fn setup_mock_client(db: PgPool) -> Client {
     MockClientRef::new(app_state.into_router()).into_client(Some(client_credentials))
 }
 
+#[allow(unused)]
+pub async fn setup_mock_client_with(db: PgPool, builder: ClientBuilder) -> Client {
+    use openadr_vtn::{data_source::PostgresStorage, jwt::JwtManager, state::AppState};
+
+    let storage = PostgresStorage::new(db).unwrap();
+    let app_state = AppState::new(storage, JwtManager::from_secret(b"test"));
+
+    MockClientRef::new(app_state.into_router())
+        .into_client_with(builder.credentials(ClientCredentials::admin()))
+}
+
 pub fn setup_url_client(url: Url) -> Client {
     Client::with_url(url, Some(ClientCredentials::admin()))
 }
@@ -53,6 +64,7 @@ pub async fn setup_program_client(program_name: impl ToString, db: PgPool) -> Pr
         binding_events: None,
         local_price: None,
         payload_descriptors: None,
+        default_priority: None,
         targets: None,
     };
 
@@ -0,0 +1,43 @@
+use openadr_client::{ClientBuilder, Phase};
+use openadr_wire::program::ProgramContent;
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+async fn records_phases_of_calls(db: PgPool) {
+    let builder = ClientBuilder::new("https://example.com/".parse().unwrap()).diagnostics();
+    let client = common::setup_mock_client_with(db, builder).await;
+    let diagnostics = client.diagnostics().unwrap();
+    assert_eq!(diagnostics.report().calls.count, 0);
+
+    client
+        .create_program(ProgramContent::new("program-1"))
+        .await
+        .unwrap();
+    client.get_all_programs().await.unwrap();
+
+    let report = diagnostics.report();
+    assert_eq!(report.calls.count, 2);
+    for phase in [
+        Phase::Auth,
+        Phase::Throttle,
+        Phase::TimeToFirstByte,
+        Phase::Download,
+        Phase::Deserialize,
+    ] {
+        assert_eq!(report.phase(phase).count, 2, "{phase:?}");
+        assert!(report.phase(phase).total <= report.calls.total);
+    }
+    // the mock client never resolves a host name
+    assert_eq!(report.dns.count, 0);
+
+    diagnostics.reset();
+    assert_eq!(diagnostics.report().calls.count, 0);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn disabled_by_default(db: PgPool) {
+    let client = common::setup_mock_client(db).await;
+    assert!(client.diagnostics().is_none());
+}
@@ -1,9 +1,15 @@
 use axum::http::StatusCode;
-use openadr_client::{Error, Filter, PaginationOptions};
+use chrono::{DateTime, TimeDelta, Utc};
+use openadr_client::{Error, Filter, Filters, PaginationOptions, Target};
 use openadr_wire::{
-    event::{EventContent, Priority},
+    capabilities::Feature,
+    event::{EventContent, EventDelta, EventInterval, EventOrder, Priority},
+    interval::IntervalPeriod,
     program::{ProgramContent, ProgramId},
-    target::TargetLabel,
+    resource::ResourceContent,
+    target::{TargetEntry, TargetLabel, TargetMap},
+    values_map::{Value, ValueType, ValuesMap},
+    ven::VenContent,
 };
 use sqlx::PgPool;
 
@@ -53,7 +59,11 @@ async fn delete(db: PgPool) {
         client.create_event(content).await.unwrap();
     }
 
-    let pagination = PaginationOptions { skip: 0, limit: 2 };
+    let pagination = PaginationOptions {
+        skip: 0,
+        limit: 2,
+        ..Default::default()
+    };
     let filter = Filter::By(TargetLabel::EventName, &["event2"]);
     let mut events = client.get_events_request(filter, pagination).await.unwrap();
     assert_eq!(events.len(), 1);
@@ -67,6 +77,108 @@ async fn delete(db: PgPool) {
     assert_eq!(events.len(), 2);
 }
 
+#[sqlx::test(fixtures("users"))]
+async fn filters(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+
+    let start = DateTime::<Utc>::UNIX_EPOCH;
+    let interval_at = |offset: TimeDelta| EventInterval {
+        id: 0,
+        interval_period: Some(IntervalPeriod {
+            start: start + offset,
+            duration: Some(openadr_wire::Duration::hours(1.0)),
+            randomize_start: None,
+        }),
+        payloads: vec![],
+    };
+
+    let today = EventContent {
+        event_name: Some("today".to_string()),
+        intervals: vec![interval_at(TimeDelta::zero())],
+        targets: Some(TargetMap(vec![TargetEntry {
+            label: TargetLabel::Group,
+            values: vec!["group-1".to_string()],
+        }])),
+        ..default_content(client.id())
+    };
+    let tomorrow = EventContent {
+        event_name: Some("tomorrow".to_string()),
+        intervals: vec![interval_at(TimeDelta::days(1))],
+        ..default_content(client.id())
+    };
+
+    for content in [today.clone(), tomorrow.clone()] {
+        client.create_event(content).await.unwrap();
+    }
+
+    let events = client
+        .get_events_request(
+            Filters::new().active_between(start, start + TimeDelta::hours(2)),
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].content(), &today);
+
+    let events = client
+        .get_events_request(
+            Filters::new().name("tomorrow"),
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].content(), &tomorrow);
+
+    // the name is applied on the client, as the VTN only accepts a single target type
+    let events = client
+        .get_events_request(
+            Filters::new()
+                .target(Target::Group("group-1"))
+                .name("tomorrow"),
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(events.is_empty());
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn long_poll(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+
+    let waiting = client.get_events_request(
+        Filters::new().wait_for_changes(std::time::Duration::from_secs(30)),
+        PaginationOptions {
+            skip: 0,
+            limit: 50,
+            ..Default::default()
+        },
+    );
+    let creating = async {
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        client.create_event(default_content(client.id())).await
+    };
+
+    let (events, created) = tokio::join!(waiting, creating);
+    let events = events.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), created.unwrap().id());
+}
+
 #[sqlx::test(fixtures("users"))]
 async fn update(db: PgPool) {
     let client = common::setup_program_client("program", db).await;
@@ -92,6 +204,43 @@ async fn update(db: PgPool) {
     assert!(event.modification_date_time() > creation_date_time);
 }
 
+#[sqlx::test(fixtures("users"))]
+async fn update_delta(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+
+    let start = DateTime::<Utc>::UNIX_EPOCH;
+    let prices = (0..288).map(|slot| slot as f64 * 0.25).collect::<Vec<_>>();
+    let mut event = client
+        .create_event(openadr_testing::price_event(client.id(), start, &prices))
+        .await
+        .unwrap();
+    let old = event.content().clone();
+
+    let mut new = old.clone();
+    new.intervals[100].payloads[0].values = vec![Value::Number(-1.0)];
+    let delta = old.delta_to(&new).unwrap();
+    assert_eq!(delta.intervals.len(), 1);
+
+    event.update_delta(delta).await.unwrap();
+    assert_eq!(event.content(), &new);
+
+    // interval ids must stay unique
+    let mut duplicate = new.intervals[1].clone();
+    duplicate.interval_period = None;
+    let invalid = EventDelta {
+        intervals: vec![duplicate.clone(), duplicate],
+        removed_intervals: vec![],
+    };
+    let err = event.update_delta(invalid).await.unwrap_err();
+    let Error::Problem(problem) = err else {
+        unreachable!()
+    };
+    assert_eq!(problem.status, StatusCode::BAD_REQUEST);
+
+    let stored = client.get_all_events().await.unwrap();
+    assert_eq!(stored[0].content(), &new);
+}
+
 #[sqlx::test(fixtures("users"))]
 async fn update_same_name(db: PgPool) {
     let client = common::setup_program_client("program", db).await;
@@ -162,21 +311,42 @@ async fn retrieve_all_with_filter(db: PgPool) {
     }
 
     let events = client
-        .get_events_request(Filter::None, PaginationOptions { skip: 0, limit: 50 })
+        .get_events_request(
+            Filter::None,
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap();
     assert_eq!(events.len(), 3);
 
     // skip
     let events = client
-        .get_events_request(Filter::None, PaginationOptions { skip: 1, limit: 50 })
+        .get_events_request(
+            Filter::None,
+            PaginationOptions {
+                skip: 1,
+                limit: 50,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap();
     assert_eq!(events.len(), 2);
 
     // limit
     let events = client
-        .get_events_request(Filter::None, PaginationOptions { skip: 0, limit: 2 })
+        .get_events_request(
+            Filter::None,
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap();
     assert_eq!(events.len(), 2);
@@ -185,7 +355,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let events = client
         .get_events_request(
             Filter::By(TargetLabel::Private("NONSENSE".to_string()), &["test"]),
-            PaginationOptions { skip: 0, limit: 2 },
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -194,7 +368,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let err = client
         .get_events_request(
             Filter::By(TargetLabel::Private("NONSENSE".to_string()), &[""]),
-            PaginationOptions { skip: 0, limit: 2 },
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
         )
         .await
         .unwrap_err();
@@ -210,7 +388,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let err = client
         .get_events_request(
             Filter::By(TargetLabel::Private("NONSENSE".to_string()), &[]),
-            PaginationOptions { skip: 0, limit: 2 },
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
         )
         .await
         .unwrap_err();
@@ -226,7 +408,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let events = client
         .get_events_request(
             Filter::By(TargetLabel::ProgramName, &["program1", "program2"]),
-            PaginationOptions { skip: 0, limit: 50 },
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -235,7 +421,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let events = client
         .get_events_request(
             Filter::By(TargetLabel::ProgramName, &["program2"]),
-            PaginationOptions { skip: 0, limit: 50 },
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -284,7 +474,15 @@ async fn filter_constraint_violation(db: PgPool) {
     let client = common::setup_client(db).await;
 
     let err = client
-        .get_events(None, Filter::None, PaginationOptions { skip: 0, limit: 51 })
+        .get_events(
+            None,
+            Filter::None,
+            PaginationOptions {
+                skip: 0,
+                limit: 51,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap_err();
     let Error::Problem(problem) = err else {
@@ -293,7 +491,15 @@ async fn filter_constraint_violation(db: PgPool) {
     assert_eq!(problem.status, StatusCode::BAD_REQUEST);
 
     let err = client
-        .get_events(None, Filter::None, PaginationOptions { skip: 0, limit: 0 })
+        .get_events(
+            None,
+            Filter::None,
+            PaginationOptions {
+                skip: 0,
+                limit: 0,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap_err();
     let Error::Problem(problem) = err else {
@@ -301,3 +507,324 @@ async fn filter_constraint_violation(db: PgPool) {
     };
     assert_eq!(problem.status, StatusCode::BAD_REQUEST);
 }
+
+#[sqlx::test(fixtures("users"))]
+async fn verified_event(db: PgPool) {
+    use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+    use openadr_client::{ClientCredentials, EventVerifier, MockClientRef};
+    use openadr_vtn::{
+        data_source::PostgresStorage, jwt::JwtManager, signing::EventSigner, state::AppState,
+    };
+
+    let storage = PostgresStorage::new(db).unwrap();
+    let app_state = AppState::new(storage, JwtManager::from_secret(b"test")).with_event_signer(
+        EventSigner::new(Algorithm::HS256, EncodingKey::from_secret(b"signing")),
+    );
+    let client =
+        MockClientRef::new(app_state.into_router()).into_client(Some(ClientCredentials::admin()));
+
+    let program = client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+    let event = program
+        .create_event(default_content(program.id()))
+        .await
+        .unwrap();
+
+    let verifier = EventVerifier::new(Algorithm::HS256, DecodingKey::from_secret(b"signing"));
+    let (verified, signature) = client
+        .get_verified_event_by_id(event.id(), &verifier)
+        .await
+        .unwrap();
+    assert_eq!(verified.content(), event.content());
+    assert_eq!(verifier.verify(&signature).unwrap().id, *event.id());
+
+    let wrong_verifier = EventVerifier::new(Algorithm::HS256, DecodingKey::from_secret(b"other"));
+    let err = client
+        .get_verified_event_by_id(event.id(), &wrong_verifier)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Signature(_)));
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn get_by_name(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+
+    for name in ["event1", "event2", "event2"] {
+        let content = EventContent {
+            event_name: Some(name.to_string()),
+            ..default_content(client.id())
+        };
+        client.create_event(content).await.unwrap();
+    }
+
+    let event = client.get_event_by_name("event1").await.unwrap();
+    assert_eq!(event.content().event_name.as_deref(), Some("event1"));
+
+    assert!(matches!(
+        client.get_event_by_name("event2").await,
+        Err(Error::DuplicateObject)
+    ));
+    assert!(matches!(
+        client.get_event_by_name("event3").await,
+        Err(Error::ObjectNotFound)
+    ));
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn edit_targets(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+    let mut event = client
+        .create_event(default_content(client.id()))
+        .await
+        .unwrap();
+    assert!(event.targets().is_empty());
+
+    event.add_target(Target::Groups(&["group-1", "group-2"]));
+    event.add_target(Target::Group("group-1"));
+    event.add_target(Target::Other("METER_ID", "meter-1"));
+    event.add_target(Target::Group("group-3"));
+    assert_eq!(event.targets().len(), 2);
+
+    event.update().await.unwrap();
+    let event_on_vtn = client.get_event_by_name("event_name").await.unwrap();
+    assert_eq!(
+        event_on_vtn.targets(),
+        &[
+            TargetEntry {
+                label: TargetLabel::Group,
+                values: vec![
+                    "group-1".to_string(),
+                    "group-2".to_string(),
+                    "group-3".to_string()
+                ]
+            },
+            TargetEntry {
+                label: TargetLabel::Private("METER_ID".to_string()),
+                values: vec!["meter-1".to_string()]
+            },
+        ]
+    );
+
+    event.remove_target(Target::Groups(&["group-1", "group-2", "group-3"]));
+    assert_eq!(event.targets().len(), 1);
+
+    // removing a value that is not targeted changes nothing
+    event.remove_target(Target::Group("group-4"));
+    assert_eq!(event.targets().len(), 1);
+
+    event.remove_target(Target::Other("METER_ID", "meter-1"));
+    assert_eq!(event.content().targets, None);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn multi_value_targets(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+    let content = EventContent {
+        targets: Some(TargetMap(vec![TargetEntry::with_values(
+            TargetLabel::Group,
+            ["group-1", "group-2"],
+        )])),
+        ..default_content(client.id())
+    };
+    let mut event = client.create_event(content).await.unwrap();
+
+    let targeted = client
+        .get_events_request(
+            Filters::new().target(Target::Group("group-2")),
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(targeted.len(), 1);
+
+    // a value the entry already contains is not added again
+    event.add_target(Target::Group("group-1"));
+    assert_eq!(event.targets().len(), 1);
+
+    event.remove_target(Target::Group("group-1"));
+    assert_eq!(
+        event.targets(),
+        &[TargetEntry::new(TargetLabel::Group, "group-2")]
+    );
+    event.update().await.unwrap();
+
+    let targeted = client
+        .get_events_request(
+            Filters::new().target(Target::Group("group-1")),
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(targeted.is_empty());
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn applies_to(db: PgPool) {
+    let client = common::setup_program_client("program", db).await;
+    let mut event = client
+        .create_event(default_content(client.id()))
+        .await
+        .unwrap();
+
+    let groups = |groups: &[&str]| {
+        Some(vec![ValuesMap {
+            value_type: ValueType("GROUP".to_string()),
+            values: groups
+                .iter()
+                .map(|g| Value::String(g.to_string()))
+                .collect(),
+        }])
+    };
+    let ven = VenContent {
+        object_type: None,
+        ven_name: "ven-1".to_string(),
+        attributes: None,
+        targets: groups(&["site-1"]),
+        resources: None,
+    };
+    let resource = |name: &str, targets| ResourceContent {
+        object_type: None,
+        resource_name: name.to_string(),
+        attributes: None,
+        targets,
+    };
+    let resources = [
+        resource("battery", groups(&["storage"])),
+        resource("heat-pump", None),
+    ];
+
+    // without targets, the event applies to everyone
+    assert!(event.applies_to(&ven, &[]));
+
+    event.add_target(Target::VEN("ven-2"));
+    assert!(!event.applies_to(&ven, &resources));
+
+    event.remove_target(Target::VEN("ven-2"));
+    event.add_target(Target::Group("site-1"));
+    assert!(event.applies_to(&ven, &[]));
+
+    event.add_target(Target::Group("storage"));
+    event.add_target(Target::Resource("battery"));
+    assert!(event.applies_to(&ven, &resources));
+    assert!(!event.applies_to(&ven, &resources[1..]));
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn cursor_pagination(db: PgPool) {
+    let builder =
+        openadr_client::ClientBuilder::new("https://example.com/".parse().unwrap()).page_size(2);
+    let client = common::setup_mock_client_with(db, builder).await;
+
+    // the client only paginates by cursor once it knows the VTN supports it
+    let capabilities = client.capabilities().await.unwrap();
+    assert!(capabilities.features.contains(&Feature::CursorPagination));
+
+    let program = client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+
+    let mut created = vec![];
+    for i in 0..5 {
+        let content = EventContent {
+            event_name: Some(format!("event{i}")),
+            ..default_content(program.id())
+        };
+        created.push(program.create_event(content).await.unwrap().id().clone());
+    }
+
+    let events = client.get_all_events().await.unwrap();
+    let ids = events
+        .iter()
+        .map(|event| event.id().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ids, created);
+
+    // other orders are paginated by offset
+    let events = client
+        .get_events_matching(Filters::new().order_events_by(EventOrder::Priority))
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 5);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn in_range(db: PgPool) {
+    let client = common::setup_client(db).await;
+    let program = client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+
+    let now = Utc::now();
+    let lasting = |start: DateTime<Utc>, duration: Option<openadr_wire::Duration>| EventInterval {
+        id: 0,
+        interval_period: Some(IntervalPeriod {
+            start,
+            duration,
+            randomize_start: None,
+        }),
+        payloads: vec![],
+    };
+
+    for (name, intervals) in [
+        (
+            "past",
+            vec![lasting(
+                now - TimeDelta::days(1),
+                Some(openadr_wire::Duration::PT1H),
+            )],
+        ),
+        (
+            "upcoming",
+            vec![lasting(
+                now + TimeDelta::hours(1),
+                Some(openadr_wire::Duration::PT1H),
+            )],
+        ),
+        ("endless", vec![lasting(now - TimeDelta::days(1), None)]),
+        ("always", vec![]),
+    ] {
+        let content = EventContent {
+            event_name: Some(name.to_string()),
+            intervals,
+            ..default_content(program.id())
+        };
+        program.create_event(content).await.unwrap();
+    }
+
+    let mut names = client
+        .get_events_in_range(now, now + TimeDelta::days(1))
+        .await
+        .unwrap()
+        .iter()
+        .filter_map(|event| event.content().event_name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["always", "endless", "upcoming"]);
+
+    // the VTN applies the window, so the past event does not take up the first page
+    let events = program
+        .get_events_request(
+            Filters::new().active_between(now, now + TimeDelta::days(1)),
+            PaginationOptions {
+                skip: 0,
+                limit: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+}
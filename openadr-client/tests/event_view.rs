@@ -0,0 +1,77 @@
+#![cfg(feature = "zero-copy")]
+
+use openadr_client::{Filters, PaginationOptions};
+use openadr_wire::{
+    event::{EventContent, EventInterval, EventType, EventValuesMap, Priority},
+    program::ProgramContent,
+    values_map::Value,
+};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+async fn views_of_polled_events(db: PgPool) {
+    let client = common::setup_mock_client(db).await;
+    let program = client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+
+    for (name, price) in [("event-1", 0.17), ("event-2", 0.23)] {
+        let interval = EventInterval {
+            id: 0,
+            interval_period: None,
+            payloads: vec![EventValuesMap {
+                value_type: EventType::Price,
+                values: vec![Value::Number(price)],
+            }],
+        };
+        let content = EventContent {
+            event_name: Some(name.to_string()),
+            priority: Priority::new(2),
+            ..EventContent::new(program.id().clone(), vec![interval])
+        };
+        program.create_event(content).await.unwrap();
+    }
+
+    let filters = Filters::new().program_id(program.id());
+    let buffer = client
+        .get_events_buffer(
+            &filters,
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let events = buffer.events().unwrap();
+
+    let mut prices = events
+        .iter()
+        .map(|event| {
+            assert_eq!(event.program_id, program.id().as_str());
+            assert!(event.id.is_borrowed());
+            assert_eq!(event.priority, Priority::new(2));
+            let payload = &event.intervals[0].payloads[0];
+            assert_eq!(payload.event_type(), EventType::Price);
+            (
+                event.event_name.as_ref().unwrap().to_string(),
+                payload.value(0).unwrap().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    prices.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        prices,
+        [
+            ("event-1".to_string(), Value::Number(0.17)),
+            ("event-2".to_string(), Value::Number(0.23)),
+        ]
+    );
+
+    let full = buffer.to_events().unwrap();
+    assert_eq!(full.len(), 2);
+}
@@ -0,0 +1,60 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use openadr_client::ClientBuilder;
+use openadr_wire::program::ProgramContent;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Serve the programs with the given delay, counting the requests
+async fn serve(delay: Duration) -> (Url, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let respond = move |State(requests): State<Arc<AtomicUsize>>| async move {
+        requests.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(delay).await;
+        (StatusCode::OK, Json(Vec::<()>::new()))
+    };
+    let router = Router::new()
+        .route("/programs", get(respond).post(respond))
+        .with_state(requests.clone());
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    (url.parse().unwrap(), requests)
+}
+
+#[tokio::test]
+async fn only_idempotent_requests_fail_over_on_timeout() {
+    let (primary, primary_requests) = serve(Duration::from_secs(5)).await;
+    let (fallback, fallback_requests) = serve(Duration::ZERO).await;
+
+    let client = ClientBuilder::new(primary)
+        .fallback_urls(vec![fallback])
+        .reqwest_client(
+            reqwest::Client::builder()
+                .timeout(Duration::from_millis(200))
+                .build()
+                .unwrap(),
+        )
+        .build();
+
+    // the primary VTN may still create the program, so it is not created at the fallback as well
+    assert!(client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .is_err());
+    assert_eq!(primary_requests.load(Ordering::SeqCst), 1);
+    assert_eq!(fallback_requests.load(Ordering::SeqCst), 0);
+
+    assert!(client.get_all_programs().await.unwrap().is_empty());
+    assert_eq!(primary_requests.load(Ordering::SeqCst), 2);
+    assert_eq!(fallback_requests.load(Ordering::SeqCst), 1);
+}
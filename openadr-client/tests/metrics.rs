@@ -0,0 +1,60 @@
+#![cfg(feature = "metrics")]
+
+use openadr_wire::program::ProgramContent;
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+async fn count_traffic(db: PgPool) {
+    let client = common::setup_mock_client(db).await;
+    let metrics = client.metrics();
+    assert_eq!(metrics.requests(), 0);
+
+    client
+        .create_program(ProgramContent::new("program-1"))
+        .await
+        .unwrap();
+    client
+        .create_program(ProgramContent::new("program-2"))
+        .await
+        .unwrap();
+
+    // one request for the access token, and one per program
+    assert_eq!(metrics.requests(), 3);
+    let sent = metrics.bytes_sent();
+    let received = metrics.bytes_received();
+    assert!(sent > 0);
+    assert!(received > 0);
+    assert_eq!(metrics.list_calls(), 0);
+
+    let programs = client.get_all_programs().await.unwrap();
+    assert_eq!(programs.len(), 2);
+
+    assert_eq!(metrics.requests(), 4);
+    assert_eq!(metrics.bytes_sent(), sent);
+    assert!(metrics.bytes_received() > received);
+    assert_eq!(metrics.list_calls(), 1);
+    assert_eq!(metrics.objects_fetched(), 2);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn pagination_ends_at_total_count(db: PgPool) {
+    let builder =
+        openadr_client::ClientBuilder::new("https://example.com/".parse().unwrap()).page_size(2);
+    let client = common::setup_mock_client_with(db, builder).await;
+
+    for i in 0..4 {
+        client
+            .create_program(ProgramContent::new(format!("program-{i}")))
+            .await
+            .unwrap();
+    }
+
+    let requests = client.metrics().requests();
+    let programs = client.get_all_programs().await.unwrap();
+    assert_eq!(programs.len(), 4);
+
+    // two full pages, without a third request to find out that there are no more programs
+    assert_eq!(client.metrics().requests(), requests + 2);
+}
@@ -0,0 +1,89 @@
+use openadr_client::{Error, MultiClient};
+use openadr_wire::program::ProgramContent;
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+async fn programs_are_prefixed_by_vtn(db: PgPool) {
+    // both VTNs share the same database, so they contain the same programs
+    let utility_a = common::setup_mock_client(db.clone()).await;
+    let utility_b = common::setup_mock_client(db).await;
+
+    utility_a
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+
+    let mut multi = MultiClient::new();
+    assert!(multi.add_vtn("utility-a", utility_a).unwrap().is_none());
+    assert!(multi
+        .add_vtn("utility-b", utility_b.clone())
+        .unwrap()
+        .is_none());
+    assert!(matches!(
+        multi.add_vtn("utility/c", utility_b),
+        Err(Error::InvalidVtnName(name)) if name == "utility/c"
+    ));
+    assert_eq!(
+        multi.vtn_names().collect::<Vec<_>>(),
+        vec!["utility-a", "utility-b"]
+    );
+
+    let programs = multi.get_all_programs().await;
+    assert!(programs.failed.is_empty());
+    let names: Vec<_> = programs
+        .objects
+        .iter()
+        .map(|program| program.qualified_name())
+        .collect();
+    assert_eq!(names, vec!["utility-a/program", "utility-b/program"]);
+
+    let program = multi
+        .get_program_by_name("utility-b/program")
+        .await
+        .unwrap();
+    assert_eq!(program.vtn, "utility-b");
+    assert_eq!(program.object.content().program_name, "program");
+
+    assert!(matches!(
+        multi.get_program_by_name("utility-c/program").await,
+        Err(Error::ObjectNotFound)
+    ));
+    assert!(matches!(
+        multi.get_program_by_name("program").await,
+        Err(Error::ObjectNotFound)
+    ));
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn unreachable_vtn_is_reported(db: PgPool) {
+    let utility_a = common::setup_mock_client(db).await;
+    utility_a
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+    // nothing listens on the discard port
+    let utility_b = common::setup_url_client("http://127.0.0.1:9/".parse().unwrap());
+
+    let mut multi = MultiClient::new();
+    multi.add_vtn("utility-a", utility_a).unwrap();
+    multi.add_vtn("utility-b", utility_b).unwrap();
+
+    let programs = multi.get_all_programs().await;
+    let names: Vec<_> = programs
+        .objects
+        .iter()
+        .map(|program| program.qualified_name())
+        .collect();
+    assert_eq!(names, vec!["utility-a/program"]);
+    assert_eq!(
+        programs.failed.keys().collect::<Vec<_>>(),
+        vec!["utility-b"]
+    );
+    assert!(programs.failed["utility-b"].is_retryable());
+
+    let events = multi.get_all_events().await;
+    assert!(events.objects.is_empty());
+    assert!(events.failed.contains_key("utility-b"));
+}
@@ -1,6 +1,16 @@
+use std::{pin::pin, time::Duration};
+
 use axum::http::StatusCode;
-use openadr_client::{Error, Filter, PaginationOptions};
-use openadr_wire::{program::ProgramContent, target::TargetLabel};
+use chrono::{DateTime, TimeDelta, Utc};
+use futures_util::{Stream, StreamExt};
+use openadr_client::{
+    ClientCredentials, Error, EventUpdate, Filter, MockClientRef, PaginationOptions, ProgramBundle,
+    SyncSummary,
+};
+use openadr_wire::{
+    event::Priority, program::ProgramContent, target::TargetLabel, ven::VenContent, SortBy,
+    SortOrder,
+};
 use sqlx::PgPool;
 
 mod common;
@@ -21,6 +31,7 @@ fn default_content() -> ProgramContent {
         binding_events: None,
         local_price: None,
         payload_descriptors: None,
+        default_priority: None,
         targets: None,
     }
 }
@@ -64,6 +75,46 @@ async fn delete(db: PgPool) {
     assert_eq!(programs.len(), 2);
 }
 
+#[sqlx::test(fixtures("users"))]
+async fn create_without_role_is_forbidden(db: PgPool) {
+    use openadr_vtn::{data_source::PostgresStorage, jwt::JwtManager, state::AppState};
+
+    let vtn = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test"),
+    )
+    .into_router();
+    let client = MockClientRef::new(vtn).into_client(Some(ClientCredentials::new(
+        "user-1-client-id".to_string(),
+        "user-1".to_string(),
+    )));
+
+    let err = client.create_program(default_content()).await.unwrap_err();
+    let Error::Forbidden(problem) = err else {
+        unreachable!()
+    };
+    assert_eq!(problem.status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn assign_vens(db: PgPool) {
+    let client = common::setup_client(db).await;
+    let program = client.create_program(default_content()).await.unwrap();
+    let ven = client.create_ven(VenContent::new("ven-1")).await.unwrap();
+
+    program.assign_ven(ven.id()).await.unwrap();
+    assert_eq!(program.vens().await.unwrap(), vec![ven.id().clone()]);
+
+    let err = program.assign_ven(ven.id()).await.unwrap_err();
+    let Error::Problem(problem) = err else {
+        unreachable!()
+    };
+    assert_eq!(problem.status, StatusCode::CONFLICT);
+
+    program.unassign_ven(ven.id()).await.unwrap();
+    assert!(program.vens().await.unwrap().is_empty());
+}
+
 #[sqlx::test(fixtures("users"))]
 async fn update(db: PgPool) {
     let client = common::setup_client(db).await;
@@ -162,21 +213,42 @@ async fn retrieve_all_with_filter(db: PgPool) {
     }
 
     let programs = client
-        .get_programs(Filter::None, PaginationOptions { skip: 0, limit: 50 })
+        .get_programs(
+            Filter::None,
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap();
     assert_eq!(programs.len(), 3);
 
     // skip
     let programs = client
-        .get_programs(Filter::None, PaginationOptions { skip: 1, limit: 50 })
+        .get_programs(
+            Filter::None,
+            PaginationOptions {
+                skip: 1,
+                limit: 50,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap();
     assert_eq!(programs.len(), 2);
 
     // limit
     let programs = client
-        .get_programs(Filter::None, PaginationOptions { skip: 0, limit: 2 })
+        .get_programs(
+            Filter::None,
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
+        )
         .await
         .unwrap();
     assert_eq!(programs.len(), 2);
@@ -185,7 +257,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let err = client
         .get_programs(
             Filter::By(TargetLabel::Private("NONSENSE".to_string()), &[]),
-            PaginationOptions { skip: 0, limit: 2 },
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
         )
         .await
         .unwrap_err();
@@ -201,7 +277,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let err = client
         .get_programs(
             Filter::By(TargetLabel::Private("NONSENSE".to_string()), &[""]),
-            PaginationOptions { skip: 0, limit: 2 },
+            PaginationOptions {
+                skip: 0,
+                limit: 2,
+                ..Default::default()
+            },
         )
         .await
         .unwrap_err();
@@ -217,7 +297,11 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let programs = client
         .get_programs(
             Filter::By(TargetLabel::Private("NONSENSE".to_string()), &["test"]),
-            PaginationOptions { skip: 0, limit: 50 },
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
@@ -226,9 +310,332 @@ async fn retrieve_all_with_filter(db: PgPool) {
     let programs = client
         .get_programs(
             Filter::By(TargetLabel::ProgramName, &["program1", "program2"]),
-            PaginationOptions { skip: 0, limit: 50 },
+            PaginationOptions {
+                skip: 0,
+                limit: 50,
+                ..Default::default()
+            },
         )
         .await
         .unwrap();
     assert_eq!(programs.len(), 2);
 }
+
+#[sqlx::test(fixtures("users"))]
+async fn retrieve_all_sorted(db: PgPool) {
+    let client = common::setup_client(db).await;
+
+    for (name, priority) in [
+        ("program-b", Some(2)),
+        ("program-c", None),
+        ("program-a", Some(1)),
+    ] {
+        let content = ProgramContent {
+            program_name: name.to_string(),
+            default_priority: priority.map(Priority::new),
+            ..default_content()
+        };
+        client.create_program(content).await.unwrap();
+    }
+
+    let names = |sort_by, sort_order| {
+        let client = &client;
+        async move {
+            client
+                .get_programs(
+                    Filter::None,
+                    PaginationOptions {
+                        skip: 0,
+                        limit: 50,
+                        sort_by: Some(sort_by),
+                        sort_order,
+                    },
+                )
+                .await
+                .unwrap()
+                .iter()
+                .map(|program| program.content().program_name.clone())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    assert_eq!(
+        names(SortBy::Created, None).await,
+        ["program-b", "program-c", "program-a"]
+    );
+    assert_eq!(
+        names(SortBy::Name, Some(SortOrder::Desc)).await,
+        ["program-c", "program-b", "program-a"]
+    );
+    // programs without a priority come last
+    assert_eq!(
+        names(SortBy::Priority, Some(SortOrder::Desc)).await,
+        ["program-b", "program-a", "program-c"]
+    );
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn page_size_too_large_for_vtn(db: PgPool) {
+    let builder =
+        openadr_client::ClientBuilder::new("https://example.com/".parse().unwrap()).page_size(120);
+    let client = common::setup_mock_client_with(db, builder).await;
+
+    for i in 0..3 {
+        let content = ProgramContent {
+            program_name: format!("program{i}"),
+            ..default_content()
+        };
+        client.create_program(content).await.unwrap();
+    }
+
+    // the VTN only accepts a limit up to 50, the client should figure that out by itself
+    let programs = client.get_all_programs().await.unwrap();
+    assert_eq!(programs.len(), 3);
+}
+
+#[sqlx::test(fixtures("users", "programs"))]
+async fn maintenance_mode_is_retryable(db: PgPool) {
+    use openadr_client::{ClientCredentials, MockClientRef};
+    use openadr_vtn::{
+        data_source::PostgresStorage,
+        jwt::JwtManager,
+        maintenance::{MaintenanceMode, MaintenanceStatus},
+        state::AppState,
+    };
+
+    let state = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test"),
+    );
+    let maintenance = state.maintenance.clone();
+    let client =
+        MockClientRef::new(state.into_router()).into_client(Some(ClientCredentials::admin()));
+
+    maintenance.set_status(MaintenanceStatus {
+        mode: MaintenanceMode::ReadOnly,
+        retry_after: 30,
+    });
+
+    assert_eq!(client.get_all_programs().await.unwrap().len(), 3);
+
+    let err = client.create_program(default_content()).await.unwrap_err();
+    let Error::ServiceUnavailable { problem, .. } = &err else {
+        panic!("expected the VTN to be unavailable, got {err:?}");
+    };
+    assert_eq!(problem.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(err.is_retryable());
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+
+    maintenance.set_status(MaintenanceStatus::default());
+    client.create_program(default_content()).await.unwrap();
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn export_import_bundle(db: PgPool) {
+    let client = common::setup_client(db).await;
+    let start: DateTime<Utc> = "2024-06-01T00:00:00Z".parse().unwrap();
+
+    let staging = client
+        .create_program(openadr_testing::program("staging"))
+        .await
+        .unwrap();
+    for (day, prices) in [&[0.1, 0.2][..], &[0.3]].into_iter().enumerate() {
+        let start = start + TimeDelta::days(day as i64);
+        staging
+            .create_event(openadr_testing::price_event(staging.id(), start, prices))
+            .await
+            .unwrap();
+    }
+
+    let json = staging.export_bundle().await.unwrap().to_json().unwrap();
+    let mut bundle = ProgramBundle::from_json(&json).unwrap();
+    assert_eq!(bundle.events.len(), 2);
+    bundle.program.program_name = "production".to_string();
+
+    let production = client.import_bundle(bundle.clone()).await.unwrap();
+    assert_ne!(production.id(), staging.id());
+    assert_eq!(production.content(), &bundle.program);
+
+    let mut events = production
+        .get_all_events()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| event.content().clone())
+        .collect::<Vec<_>>();
+    let mut expected = bundle
+        .events
+        .into_iter()
+        .map(|mut event| {
+            event.program_id = production.id().clone();
+            event
+        })
+        .collect::<Vec<_>>();
+    events.sort_by_key(|event| event.intervals.len());
+    expected.sort_by_key(|event| event.intervals.len());
+    assert_eq!(events, expected);
+
+    // a failed import leaves no program behind
+    let mut bundle = staging.export_bundle().await.unwrap();
+    bundle.program.program_name = "failed".to_string();
+    let interval = bundle.events[0].intervals[0].clone();
+    bundle.events[1].intervals.push(interval);
+    assert!(client.import_bundle(bundle).await.is_err());
+    assert!(matches!(
+        client.get_program_by_name("failed").await,
+        Err(Error::ObjectNotFound)
+    ));
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn sync_events(db: PgPool) {
+    let client = common::setup_client(db).await;
+    let program = client
+        .create_program(openadr_testing::program("sync"))
+        .await
+        .unwrap();
+    let day =
+        |day: i64| "2024-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + TimeDelta::days(day);
+
+    for start in [day(0), day(1), day(2)] {
+        program
+            .create_event(openadr_testing::price_event(program.id(), start, &[0.1]))
+            .await
+            .unwrap();
+    }
+
+    // the first day stays, the second day changes, the third day is replaced by the fourth
+    let desired = vec![
+        openadr_testing::price_event(program.id(), day(0), &[0.1]),
+        openadr_testing::price_event(program.id(), day(1), &[0.2]),
+        openadr_testing::price_event(program.id(), day(3), &[0.3]),
+    ];
+
+    let plan = program.plan_events(desired.clone()).await.unwrap();
+    assert_eq!(
+        plan.summary(),
+        SyncSummary {
+            created: 1,
+            updated: 1,
+            deleted: 1,
+            unchanged: 1,
+        }
+    );
+    assert_eq!(plan.creates()[0].event_name, desired[2].event_name);
+    plan.execute().await.unwrap();
+
+    let mut events = program
+        .get_all_events()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|event| event.content().clone())
+        .collect::<Vec<_>>();
+    events.sort_by(|a, b| a.event_name.cmp(&b.event_name));
+    assert_eq!(events.len(), 3);
+    for (event, desired) in events.iter().zip(&desired) {
+        assert_eq!(event.event_name, desired.event_name);
+        assert_eq!(event.intervals, desired.intervals);
+    }
+
+    assert!(program
+        .plan_events(desired.clone())
+        .await
+        .unwrap()
+        .is_empty());
+
+    let mut unnamed = desired[0].clone();
+    unnamed.event_name = None;
+    assert!(matches!(
+        program.plan_events(vec![unnamed]).await,
+        Err(Error::UnnamedEvent)
+    ));
+    assert!(matches!(
+        program
+            .plan_events(vec![desired[0].clone(), desired[0].clone()])
+            .await,
+        Err(Error::DuplicateObject)
+    ));
+}
+
+async fn next_update(updates: &mut (impl Stream<Item = EventUpdate> + Unpin)) -> EventUpdate {
+    tokio::time::timeout(Duration::from_secs(5), updates.next())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn watch_events(db: PgPool) {
+    let client = common::setup_client(db).await;
+    let program = client
+        .create_program(openadr_testing::program("watch"))
+        .await
+        .unwrap();
+    let day =
+        |day: i64| "2024-06-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap() + TimeDelta::days(day);
+
+    let existing = program
+        .create_event(openadr_testing::price_event(program.id(), day(0), &[0.1]))
+        .await
+        .unwrap();
+
+    let updates = program.watch_events(Duration::from_millis(10));
+    let mut updates = pin!(updates);
+
+    // existing events are created as far as the watch is concerned
+    assert!(
+        matches!(next_update(&mut updates).await, EventUpdate::Created(event) if event.id() == existing.id())
+    );
+
+    let mut created = program
+        .create_event(openadr_testing::price_event(program.id(), day(1), &[0.2]))
+        .await
+        .unwrap();
+    assert!(
+        matches!(next_update(&mut updates).await, EventUpdate::Created(event) if event.id() == created.id())
+    );
+
+    created.content_mut().priority = Priority::MAX;
+    created.update().await.unwrap();
+    assert!(matches!(
+        next_update(&mut updates).await,
+        EventUpdate::Updated(event) if event.content().priority == Priority::MAX
+    ));
+
+    let deleted = existing.delete().await.unwrap();
+    assert!(
+        matches!(next_update(&mut updates).await, EventUpdate::Deleted(event) if event.id == deleted.id)
+    );
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn get_all_in_concurrent_pages(db: PgPool) {
+    let builder =
+        openadr_client::ClientBuilder::new("https://example.com/".parse().unwrap()).page_size(2);
+    let client = common::setup_mock_client_with(db.clone(), builder).await;
+    for i in 0..7 {
+        client
+            .create_program(ProgramContent::new(format!("program-{i}")))
+            .await
+            .unwrap();
+    }
+
+    let builder = openadr_client::ClientBuilder::new("https://example.com/".parse().unwrap())
+        .page_size(2)
+        .page_concurrency(1);
+    let sequential = common::setup_mock_client_with(db, builder).await;
+
+    let names = |programs: Vec<openadr_client::ProgramClient>| {
+        programs
+            .iter()
+            .map(|program| program.content().program_name.clone())
+            .collect::<Vec<_>>()
+    };
+    let concurrent = names(client.get_all_programs().await.unwrap());
+    assert_eq!(concurrent.len(), 7);
+    assert_eq!(
+        concurrent,
+        names(sequential.get_all_programs().await.unwrap())
+    );
+}
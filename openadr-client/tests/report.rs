@@ -0,0 +1,70 @@
+use openadr_client::{ClientCredentials, MockClientRef};
+use openadr_vtn::{data_source::PostgresStorage, jwt::JwtManager, state::AppState};
+use openadr_wire::{report::ResourceName, OperatingState};
+use sqlx::PgPool;
+
+#[sqlx::test(fixtures("users", "vens"))]
+async fn latest_operating_states(db: PgPool) {
+    let vtn = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test"),
+    )
+    .into_router();
+
+    let business = MockClientRef::new(vtn.clone()).into_client(Some(ClientCredentials::admin()));
+    let program = business
+        .create_program(openadr_testing::program("operating-states"))
+        .await
+        .unwrap();
+    program
+        .create_event(openadr_testing::price_event(
+            program.id(),
+            "2024-01-01T00:00:00Z".parse().unwrap(),
+            &[0.25],
+        ))
+        .await
+        .unwrap();
+    assert!(business.latest_operating_states().await.unwrap().is_empty());
+
+    let ven = MockClientRef::new(vtn).into_client(Some(ClientCredentials::new(
+        "user-1-client-id".to_string(),
+        "user-1".to_string(),
+    )));
+    let event = ven
+        .get_program_by_name("operating-states")
+        .await
+        .unwrap()
+        .get_all_events()
+        .await
+        .unwrap()
+        .remove(0);
+
+    let charger = ResourceName::Private("charger-1".to_string());
+    let battery = ResourceName::Private("battery-1".to_string());
+    event
+        .report_operating_state("ven-1-name", charger.clone(), OperatingState::IdleNormal)
+        .await
+        .unwrap();
+    event
+        .report_operating_state("ven-1-name", battery.clone(), OperatingState::RunningNormal)
+        .await
+        .unwrap();
+    let last = event
+        .report_operating_state(
+            "ven-1-name",
+            charger.clone(),
+            OperatingState::RunningCurtailed,
+        )
+        .await
+        .unwrap();
+
+    let mut states = business.latest_operating_states().await.unwrap();
+    states.sort_by_key(|state| state.resource_name == charger);
+    assert_eq!(states.len(), 2);
+    assert_eq!(states[0].resource_name, battery);
+    assert_eq!(states[0].operating_state, OperatingState::RunningNormal);
+    assert_eq!(states[1].resource_name, charger);
+    assert_eq!(states[1].operating_state, OperatingState::RunningCurtailed);
+    assert_eq!(&states[1].report_id, last.id());
+    assert_eq!(states[1].client_name, "ven-1-name");
+}
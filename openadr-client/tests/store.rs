@@ -0,0 +1,147 @@
+#![cfg(feature = "store")]
+
+use openadr_client::{Error, LocalStore};
+use openadr_wire::{
+    event::{EventInterval, EventType, EventValuesMap},
+    report::ReportContent,
+    values_map::Value,
+};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+async fn sync_timeline(db: PgPool) {
+    let program = common::setup_program_client("program", db).await;
+    let store = LocalStore::temporary().unwrap();
+
+    let event = openadr_testing::simple_event(program.id(), chrono::Utc::now(), &[1]);
+    let event = program.create_event(event).await.unwrap();
+
+    store.sync_timeline(&program).await.unwrap();
+
+    let stored = store.events(program.id()).unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(&stored[0].content, event.content());
+    let timeline = store.timeline(program.id()).unwrap().unwrap();
+    assert!(timeline.staleness().unwrap() < chrono::Duration::minutes(1));
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn sync_timeline_offline(db: PgPool) {
+    use axum::{
+        body::Body,
+        extract::Request,
+        middleware::{self, Next},
+        response::Response,
+    };
+    use openadr_client::{ClientCredentials, MockClientRef};
+    use openadr_vtn::{
+        data_source::PostgresStorage,
+        jwt::JwtManager,
+        maintenance::{MaintenanceMode, MaintenanceStatus},
+        state::AppState,
+    };
+    use openadr_wire::program::ProgramContent;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let state = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test"),
+    );
+    let maintenance = state.maintenance.clone();
+    // when set, the event list is answered with a body that is not JSON
+    let corrupt = Arc::new(AtomicBool::new(false));
+    let router = state.into_router().layer(middleware::from_fn({
+        let corrupt = corrupt.clone();
+        move |request: Request, next: Next| {
+            let corrupt = corrupt.load(Ordering::Relaxed) && request.uri().path() == "/events";
+            async move {
+                if corrupt {
+                    Response::new(Body::from("not json"))
+                } else {
+                    next.run(request).await
+                }
+            }
+        }
+    }));
+    let client = MockClientRef::new(router).into_client(Some(ClientCredentials::admin()));
+
+    let program = client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+    let event = openadr_testing::simple_event(program.id(), chrono::Utc::now(), &[1]);
+    program.create_event(event).await.unwrap();
+
+    let store = LocalStore::temporary().unwrap();
+    store.sync_timeline(&program).await.unwrap();
+
+    // an unavailable VTN falls back to the stored events
+    maintenance.set_status(MaintenanceStatus {
+        mode: MaintenanceMode::Unavailable,
+        retry_after: 30,
+    });
+    store.sync_timeline(&program).await.unwrap();
+    maintenance.set_status(MaintenanceStatus::default());
+
+    // other errors are returned
+    corrupt.store(true, Ordering::Relaxed);
+    let err = store.sync_timeline(&program).await.unwrap_err();
+    assert!(!err.is_retryable(), "{err:?}");
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn sync_timeline_without_interval_period(db: PgPool) {
+    let program = common::setup_program_client("program", db).await;
+    let store = LocalStore::temporary().unwrap();
+
+    // neither the program, the event, nor the interval has an interval period
+    let interval = EventInterval::new(
+        0,
+        vec![EventValuesMap {
+            value_type: EventType::Simple,
+            values: vec![Value::Integer(1)],
+        }],
+    );
+    program
+        .create_event(program.new_event().with_intervals(vec![interval]))
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        store.sync_timeline(&program).await,
+        Err(Error::NoTimeline)
+    ));
+    assert!(store.program(program.id()).unwrap().is_some());
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn failed_flush_keeps_reports(db: PgPool) {
+    let program = common::setup_program_client("program", db.clone()).await;
+    let client = common::setup_client(db).await;
+    let store = LocalStore::temporary().unwrap();
+
+    let event = program
+        .create_event(program.new_event().with_intervals(vec![]))
+        .await
+        .unwrap();
+
+    let report = ReportContent {
+        client_name: "client".to_string(),
+        ..event.new_report()
+    };
+    store.queue_report(&report).unwrap();
+    store.queue_report(&report).unwrap();
+    assert_eq!(store.queued_reports().unwrap().len(), 2);
+
+    // the admin user is not a VEN, so it is not allowed to create reports
+    assert!(store.flush_reports(&client).await.is_err());
+    assert_eq!(
+        store.queued_reports().unwrap(),
+        vec![report.clone(), report]
+    );
+}
@@ -0,0 +1,85 @@
+#![cfg(feature = "otel")]
+
+use std::sync::{Arc, Mutex};
+
+use futures_util::future::BoxFuture;
+use openadr_vtn::{data_source::PostgresStorage, jwt::JwtManager, state::AppState};
+use openadr_wire::program::ProgramContent;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::{
+    export::trace::{ExportResult, SpanData, SpanExporter},
+    trace::TracerProvider,
+};
+use sqlx::PgPool;
+use tokio::net::TcpListener;
+use tracing::{info_span, Instrument};
+use tracing_subscriber::layer::SubscriberExt;
+
+mod common;
+
+/// Collects the spans as they end
+#[derive(Debug, Clone, Default)]
+struct Spans(Arc<Mutex<Vec<SpanData>>>);
+
+impl SpanExporter for Spans {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        self.0.lock().unwrap().extend(batch);
+        Box::pin(std::future::ready(Ok(())))
+    }
+}
+
+impl Spans {
+    fn find(&self, name: &str) -> SpanData {
+        let spans = self.0.lock().unwrap();
+        let span = spans.iter().rev().find(|span| span.name == name);
+        span.unwrap_or_else(|| panic!("no span {name}")).clone()
+    }
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn trace_from_client_to_database(db: PgPool) {
+    let spans = Spans::default();
+    let provider = TracerProvider::builder()
+        .with_simple_exporter(spans.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    // serve the VTN over HTTP, such that only the `traceparent` header links its spans to those of the client
+    let router = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test"),
+    )
+    .into_router();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let client = common::setup_url_client(url.parse().unwrap());
+    let program = client
+        .create_program(ProgramContent::new("program"))
+        .await
+        .unwrap();
+
+    program
+        .get_all_events()
+        .instrument(info_span!("poll"))
+        .await
+        .unwrap();
+
+    let poll = spans.find("poll");
+    let client_request = spans.find("vtn_request");
+    let vtn_request = spans.find("request");
+    let query = spans.find("PgEventStorage::retrieve_all");
+
+    let trace_id = poll.span_context.trace_id();
+    for span in [&client_request, &vtn_request, &query] {
+        assert_eq!(span.span_context.trace_id(), trace_id, "{}", span.name);
+    }
+    assert_eq!(client_request.parent_span_id, poll.span_context.span_id());
+    assert_eq!(
+        vtn_request.parent_span_id,
+        client_request.span_context.span_id()
+    );
+}
@@ -0,0 +1,67 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{body::Body, extract::State, routing::get, Router};
+use futures_util::stream;
+use openadr_client::ClientBuilder;
+use tokio::net::TcpListener;
+
+/// Counts the responses of which the body is still being sent
+#[derive(Debug, Default)]
+struct InFlight {
+    current: AtomicUsize,
+    max: AtomicUsize,
+}
+
+struct Guard(Arc<InFlight>);
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        self.0.current.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// An empty list, of which the closing bracket is sent after a while
+async fn slow_list(State(in_flight): State<Arc<InFlight>>) -> Body {
+    let current = in_flight.current.fetch_add(1, Ordering::SeqCst) + 1;
+    in_flight.max.fetch_max(current, Ordering::SeqCst);
+    let guard = Guard(in_flight);
+
+    let chunks = stream::unfold((0, guard), |(chunk, guard)| async move {
+        match chunk {
+            0 => Some((Ok::<_, std::io::Error>("["), (1, guard))),
+            1 => {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Some((Ok("]"), (2, guard)))
+            }
+            _ => None,
+        }
+    });
+    Body::from_stream(chunks)
+}
+
+#[tokio::test]
+async fn permit_is_held_until_the_body_is_received() {
+    let in_flight = Arc::new(InFlight::default());
+    let router = Router::new()
+        .route("/programs", get(slow_list))
+        .with_state(in_flight.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let client = ClientBuilder::new(url.parse().unwrap())
+        .max_concurrent_requests(1)
+        .build();
+
+    let (first, second) = tokio::join!(client.get_all_programs(), client.get_all_programs());
+    assert!(first.unwrap().is_empty());
+    assert!(second.unwrap().is_empty());
+
+    assert_eq!(in_flight.max.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,201 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use axum::{body::Body, extract::Request, middleware::Next};
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use openadr_client::{
+    Client, ClientBuilder, ClientCredentials, Clock, Filters, MockClientRef, MockClock,
+};
+use openadr_vtn::{data_source::PostgresStorage, jwt::JwtManager, state::AppState};
+use openadr_wire::{event::EventType, values_map::Value};
+use sqlx::PgPool;
+use url::Url;
+
+/// Long enough for the VEN to refresh its access token, which the VTN issues for 30 days
+const DAYS: i64 = 35;
+
+/// A VTN that counts the requests for an access token
+fn setup_vtn(db: PgPool, token_requests: Arc<AtomicUsize>) -> axum::Router {
+    let state = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test"),
+    );
+
+    state.into_router().layer(axum::middleware::from_fn(
+        move |request: Request, next: Next| {
+            if request.uri().path() == "/auth/token" {
+                token_requests.fetch_add(1, Ordering::Relaxed);
+            }
+            next.run(request)
+        },
+    ))
+}
+
+fn setup_client(vtn: axum::Router, credentials: ClientCredentials, clock: &MockClock) -> Client {
+    let builder = ClientBuilder::new(Url::parse("https://example.com/").unwrap())
+        .credentials(credentials)
+        .clock(clock.clone());
+
+    MockClientRef::new(vtn).into_client_with(builder)
+}
+
+/// A unique price for every hour, which is exactly representable in JSON
+fn price_at(day: i64, hour: i64) -> f64 {
+    (day * 24 + hour) as f64 * 0.25
+}
+
+fn day_start(day: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + TimeDelta::days(day)
+}
+
+#[sqlx::test(fixtures("users", "vens"))]
+async fn simulate_days_of_ven_operation(db: PgPool) {
+    let clock = MockClock::new(day_start(0));
+    let token_requests = Arc::new(AtomicUsize::new(0));
+    let vtn = setup_vtn(db, token_requests.clone());
+
+    let business = setup_client(vtn.clone(), ClientCredentials::admin(), &clock);
+    let program = business
+        .create_program(openadr_testing::program("time-travel"))
+        .await
+        .unwrap();
+    for day in 0..DAYS {
+        let prices = (0..24).map(|hour| price_at(day, hour)).collect::<Vec<_>>();
+        program
+            .create_event(openadr_testing::price_event(
+                program.id(),
+                day_start(day),
+                &prices,
+            ))
+            .await
+            .unwrap();
+    }
+    assert_eq!(token_requests.load(Ordering::Relaxed), 1);
+
+    let ven = setup_client(
+        vtn,
+        ClientCredentials::new("user-1-client-id".to_string(), "user-1".to_string()),
+        &clock,
+    );
+    let mut program = ven.get_program_by_name("time-travel").await.unwrap();
+
+    for day in 0..DAYS {
+        // poll the timeline a few times a day, and act on the active price
+        for hour in (0..24).step_by(6) {
+            clock.set(day_start(day) + TimeDelta::hours(hour));
+
+            let timeline = program.get_timeline().await.unwrap();
+            let (range, interval) = timeline.at_datetime(&ven.clock().now()).unwrap();
+            assert_eq!(range.start, ven.clock().now());
+            assert_eq!(interval.value_map[0].value_type, EventType::Price);
+            assert_eq!(
+                interval.value_map[0].values,
+                [Value::Number(price_at(day, hour))]
+            );
+        }
+
+        // report the usage of the day once it is over
+        clock.set(day_start(day + 1));
+        let event = program
+            .get_all_events()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|event| event.content().interval_period.as_ref().unwrap().start == day_start(day))
+            .unwrap();
+        event
+            .create_report(openadr_testing::usage_report(
+                event.event(),
+                "ven-1-name",
+                "meter-1",
+                &[1.5; 24],
+            ))
+            .await
+            .unwrap();
+    }
+
+    // the VEN only refreshed its token once it was about to expire
+    assert_eq!(token_requests.load(Ordering::Relaxed), 3);
+
+    let reports = business
+        .get_reports_matching(Filters::new().program_id(program.id()))
+        .await
+        .unwrap();
+    assert_eq!(reports.len(), DAYS as usize);
+    assert_eq!(token_requests.load(Ordering::Relaxed), 4);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn estimates_clock_skew(db: PgPool) {
+    let clock = MockClock::new(day_start(0));
+
+    // the clock of the VTN runs an hour ahead
+    let vtn_clock = clock.clone();
+    let vtn = setup_vtn(db, Arc::new(AtomicUsize::new(0))).layer(axum::middleware::map_response(
+        move |mut response: axum::response::Response| {
+            let date = (vtn_clock.now() + TimeDelta::hours(1))
+                .format("%a, %d %b %Y %H:%M:%S GMT")
+                .to_string();
+            response
+                .headers_mut()
+                .insert(axum::http::header::DATE, date.parse().unwrap());
+            async { response }
+        },
+    ));
+
+    let client = setup_client(vtn, ClientCredentials::admin(), &clock);
+    assert_eq!(client.estimated_skew(), None);
+    assert_eq!(client.vtn_now(), clock.now());
+
+    client.get_all_programs().await.unwrap();
+    let skew = TimeDelta::hours(1) + TimeDelta::milliseconds(500);
+    assert_eq!(client.estimated_skew(), Some(skew));
+    assert_eq!(client.vtn_now(), clock.now() + skew);
+    assert_eq!(client.vtn_clock().now(), clock.now() + skew);
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn refresh_token_before_client_credentials(db: PgPool) {
+    let clock = MockClock::new(day_start(0));
+    let grant_types = Arc::new(Mutex::new(Vec::new()));
+
+    let state = AppState::new(
+        PostgresStorage::new(db).unwrap(),
+        JwtManager::from_secret(b"test").with_refresh_tokens(Duration::from_secs(90 * 24 * 3600)),
+    );
+    let recorded = grant_types.clone();
+    let vtn = state.into_router().layer(axum::middleware::from_fn(
+        move |request: Request, next: Next| {
+            let recorded = recorded.clone();
+            async move {
+                if request.uri().path() != "/auth/token" {
+                    return next.run(request).await;
+                }
+                let (parts, body) = request.into_parts();
+                let body = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+                let grant_type = url::form_urlencoded::parse(&body)
+                    .find(|(key, _)| key == "grant_type")
+                    .map(|(_, value)| value.into_owned());
+                recorded.lock().unwrap().push(grant_type.unwrap());
+                next.run(Request::from_parts(parts, Body::from(body))).await
+            }
+        },
+    ));
+
+    let client = setup_client(vtn, ClientCredentials::admin(), &clock);
+    client.get_all_programs().await.unwrap();
+
+    // the access token of the VTN expires after 30 days
+    clock.set(day_start(DAYS));
+    client.get_all_programs().await.unwrap();
+
+    assert_eq!(
+        *grant_types.lock().unwrap(),
+        ["client_credentials", "refresh_token"]
+    );
+}
@@ -0,0 +1,83 @@
+use openadr_client::Error;
+use openadr_wire::{resource::ResourceContent, ven::VenContent};
+use sqlx::PgPool;
+
+mod common;
+
+#[sqlx::test(fixtures("users"))]
+async fn crud(db: PgPool) {
+    let client = common::setup_client(db).await;
+
+    let mut ven = client
+        .create_ven(VenContent::new("ven-crud"))
+        .await
+        .unwrap();
+    assert_eq!(ven.content().ven_name, "ven-crud");
+
+    // the VTN only lists the resources of retrieved VENs
+    let retrieved = client.get_ven_by_name("ven-crud").await.unwrap();
+    assert_eq!(retrieved.id(), ven.id());
+    assert_eq!(retrieved.content().resources, Some(vec![]));
+    let retrieved = client.get_ven_by_id(ven.id()).await.unwrap();
+    assert_eq!(retrieved.id(), ven.id());
+    assert!(matches!(
+        client.get_ven_by_name("unknown").await,
+        Err(Error::ObjectNotFound)
+    ));
+
+    ven.content_mut().ven_name = "ven-renamed".to_string();
+    ven.update().await.unwrap();
+    assert_eq!(
+        client.get_ven_by_name("ven-renamed").await.unwrap().ven(),
+        ven.ven()
+    );
+
+    let deleted = ven.delete().await.unwrap();
+    assert_eq!(deleted.content.ven_name, "ven-renamed");
+    assert!(client.get_all_vens().await.unwrap().is_empty());
+}
+
+#[sqlx::test(fixtures("users"))]
+async fn resources(db: PgPool) {
+    let client = common::setup_client(db).await;
+    let ven = client.create_ven(VenContent::new("ven-1")).await.unwrap();
+
+    let mut charger = ven
+        .create_resource(ResourceContent::new("charger"))
+        .await
+        .unwrap();
+    let battery = ven
+        .create_resource(ResourceContent::new("battery"))
+        .await
+        .unwrap();
+    assert_eq!(charger.ven_id(), ven.id());
+
+    let mut names: Vec<_> = ven
+        .get_all_resources()
+        .await
+        .unwrap()
+        .iter()
+        .map(|resource| resource.content().resource_name.clone())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["battery", "charger"]);
+
+    let retrieved = ven.get_resource_by_name("battery").await.unwrap();
+    assert_eq!(retrieved.resource(), battery.resource());
+    let retrieved = ven.get_resource_by_id(charger.id()).await.unwrap();
+    assert_eq!(retrieved.resource(), charger.resource());
+
+    charger.content_mut().resource_name = "fast-charger".to_string();
+    charger.update().await.unwrap();
+    assert_eq!(
+        ven.get_resource_by_name("fast-charger")
+            .await
+            .unwrap()
+            .resource(),
+        charger.resource()
+    );
+
+    charger.delete().await.unwrap();
+    battery.delete().await.unwrap();
+    assert!(ven.get_all_resources().await.unwrap().is_empty());
+}
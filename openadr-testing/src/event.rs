@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use openadr_wire::{
+    event::{EventContent, EventInterval, EventPayloadDescriptor, EventType, EventValuesMap},
+    program::ProgramId,
+    values_map::Value,
+    Unit,
+};
+
+use crate::hourly_period;
+
+/// An event with a price per kWh for each hour, starting at `start`.
+///
+/// Panics if `prices` is empty, as an event needs at least one interval.
+pub fn price_event(program_id: &ProgramId, start: DateTime<Utc>, prices: &[f64]) -> EventContent {
+    hourly_event(program_id, start, EventType::Price, prices, Value::Number)
+        .with_event_name(format!("price-{}", start.format("%Y%m%d%H%M")))
+        .with_payload_descriptors(vec![EventPayloadDescriptor {
+            units: Some(Unit::KWH),
+            ..EventPayloadDescriptor::new(EventType::Price)
+        }])
+}
+
+/// An event with a SIMPLE level, e.g., a load shed level from 0 to 3, for each hour, starting at `start`.
+///
+/// Panics if `levels` is empty, as an event needs at least one interval.
+pub fn simple_event(program_id: &ProgramId, start: DateTime<Utc>, levels: &[i64]) -> EventContent {
+    hourly_event(program_id, start, EventType::Simple, levels, Value::Integer)
+        .with_event_name(format!("simple-{}", start.format("%Y%m%d%H%M")))
+}
+
+fn hourly_event<T: Copy>(
+    program_id: &ProgramId,
+    start: DateTime<Utc>,
+    value_type: EventType,
+    values: &[T],
+    to_value: impl Fn(T) -> Value,
+) -> EventContent {
+    assert!(!values.is_empty(), "an event needs at least one interval");
+
+    let intervals = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| EventInterval {
+            id: index as i32,
+            interval_period: Some(hourly_period(start, index)),
+            payloads: vec![EventValuesMap {
+                value_type: value_type.clone(),
+                values: vec![to_value(*value)],
+            }],
+        })
+        .collect();
+
+    EventContent::new(program_id.clone(), intervals).with_interval_period(hourly_period(start, 0))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn program_id() -> ProgramId {
+        ProgramId::new("program-1").unwrap()
+    }
+
+    #[test]
+    fn interval_per_hour() {
+        let event = price_event(&program_id(), Utc::now(), &[0.21, 0.25]);
+        assert_eq!(event.intervals.len(), 2);
+        assert_eq!(event.intervals[1].id, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one interval")]
+    fn price_event_without_prices() {
+        price_event(&program_id(), Utc::now(), &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one interval")]
+    fn simple_event_without_levels() {
+        simple_event(&program_id(), Utc::now(), &[]);
+    }
+}
@@ -0,0 +1,52 @@
+//! Factories for realistic OpenADR objects, for the test suites of this workspace
+//! and of applications built on top of it.
+//!
+//! Each factory returns the content of an object as a client would create it at the VTN.
+//! All fields are public, so tests can adjust the objects further,
+//! e.g., with the `with_*` methods of the content types.
+//!
+//! ```
+//! # use chrono::{TimeZone, Utc};
+//! # use openadr_wire::{program::ProgramId, target::TargetLabel};
+//! let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+//! let program_id = ProgramId::new("program-1").unwrap();
+//!
+//! let program = openadr_testing::program("program-1");
+//! let event = openadr_testing::price_event(&program_id, start, &[0.21, 0.25, 0.19])
+//!     .with_targets(openadr_testing::targets([(TargetLabel::Group, "group-1")]));
+//! assert_eq!(event.intervals.len(), 3);
+//! ```
+
+mod event;
+mod program;
+mod report;
+
+pub use event::{price_event, simple_event};
+pub use program::program;
+pub use report::usage_report;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use openadr_wire::{
+    interval::IntervalPeriod,
+    target::{TargetEntry, TargetLabel, TargetMap},
+    Duration,
+};
+
+/// A target map with a single value per entry
+pub fn targets<'a>(entries: impl IntoIterator<Item = (TargetLabel, &'a str)>) -> TargetMap {
+    TargetMap(
+        entries
+            .into_iter()
+            .map(|(label, value)| TargetEntry::new(label, value))
+            .collect(),
+    )
+}
+
+/// The period of the `index`th interval in a series of consecutive one-hour intervals
+fn hourly_period(start: DateTime<Utc>, index: usize) -> IntervalPeriod {
+    IntervalPeriod {
+        start: start + TimeDelta::hours(index as i64),
+        duration: Some(Duration::PT1H),
+        randomize_start: None,
+    }
+}
@@ -0,0 +1,29 @@
+use openadr_wire::{
+    event::{EventPayloadDescriptor, EventType},
+    program::{PayloadDescriptor, ProgramContent, ProgramDescription},
+    Unit,
+};
+
+/// A dynamic pricing program of a retailer, with hourly prices per kWh
+pub fn program(name: &str) -> ProgramContent {
+    ProgramContent {
+        program_long_name: Some(format!("{name} dynamic pricing")),
+        retailer_name: Some("retailer".to_string()),
+        retailer_long_name: Some("Example Retailer".to_string()),
+        program_type: Some("PRICING_TARIFF".to_string()),
+        country: Some("NL".to_string()),
+        principal_subdivision: Some("GE".to_string()),
+        program_descriptions: Some(vec![ProgramDescription {
+            url: "https://example.com/programs/dynamic-pricing".to_string(),
+        }]),
+        binding_events: Some(false),
+        local_price: Some(false),
+        payload_descriptors: Some(vec![PayloadDescriptor::EventPayloadDescriptor(
+            EventPayloadDescriptor {
+                units: Some(Unit::KWH),
+                ..EventPayloadDescriptor::new(EventType::Price)
+            },
+        )]),
+        ..ProgramContent::new(name)
+    }
+}
@@ -0,0 +1,64 @@
+use openadr_wire::{
+    report::{
+        ReportContent, ReportInterval, ReportPayloadDescriptor, ReportResource, ReportType,
+        ReportValuesMap, ResourceName,
+    },
+    values_map::Value,
+    Event, Unit,
+};
+
+use crate::hourly_period;
+
+/// A report of the energy usage in kWh of a single resource during each hour of the event.
+///
+/// The readings start at the interval period of the event, or at its first interval.
+/// Panics if the event has neither.
+pub fn usage_report(
+    event: &Event,
+    client_name: &str,
+    resource_name: &str,
+    readings: &[f64],
+) -> ReportContent {
+    let start = event
+        .content
+        .interval_period
+        .as_ref()
+        .or(event
+            .content
+            .intervals
+            .first()
+            .and_then(|interval| interval.interval_period.as_ref()))
+        .expect("the event has no interval period")
+        .start;
+
+    let intervals = readings
+        .iter()
+        .enumerate()
+        .map(|(index, reading)| {
+            ReportInterval::new(
+                index as i32,
+                vec![ReportValuesMap::new(
+                    ReportType::Usage,
+                    vec![Value::Number(*reading)],
+                )],
+            )
+            .with_interval_period(hourly_period(start, index))
+        })
+        .collect();
+
+    ReportContent {
+        object_type: None,
+        program_id: event.content.program_id.clone(),
+        event_id: event.id.clone(),
+        client_name: client_name.to_string(),
+        report_name: None,
+        payload_descriptors: Some(vec![ReportPayloadDescriptor {
+            units: Some(Unit::KWH),
+            ..ReportPayloadDescriptor::new(ReportType::Usage)
+        }]),
+        resources: vec![
+            ReportResource::new(ResourceName::Private(resource_name.to_string()))
+                .with_intervals(intervals),
+        ],
+    }
+}
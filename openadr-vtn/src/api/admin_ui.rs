@@ -0,0 +1,146 @@
+//! A minimal admin UI to browse the programs, events and VENs of this VTN,
+//! and the delivery of notifications, e.g., to evaluate the VTN without writing a client first.
+//!
+//! The page is only served to users with the [`UserManager`](crate::jwt::AuthRole::UserManager)
+//! role, who authenticate with their client credentials using HTTP Basic authentication.
+//! The page does not contain any data itself, but an access token issued for the user,
+//! with which it retrieves all data using the regular API, which applies the usual permission checks.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::State,
+    http::{header, HeaderValue, StatusCode},
+    response::{Html, IntoResponse, Response},
+};
+use axum_extra::{
+    headers::{authorization::Basic, Authorization},
+    TypedHeader,
+};
+use tracing::error;
+
+use crate::{data_source::AuthSource, error::AppError, jwt::JwtManager};
+
+const INDEX_HTML: &str = include_str!("../../admin-ui/index.html");
+
+/// Replaced by the access token in [`INDEX_HTML`]
+const ACCESS_TOKEN_PLACEHOLDER: &str = "{{ACCESS_TOKEN}}";
+
+/// The lifetime of the access token of the page, after which the page needs to be reloaded
+const ACCESS_TOKEN_LIFETIME: Duration = Duration::from_secs(3600);
+
+pub async fn index(
+    State(auth_source): State<Arc<dyn AuthSource>>,
+    State(jwt_manager): State<Arc<JwtManager>>,
+    authorization: Option<TypedHeader<Authorization<Basic>>>,
+) -> Response {
+    let user = match &authorization {
+        Some(TypedHeader(auth)) => {
+            auth_source
+                .check_credentials(auth.username(), auth.password())
+                .await
+        }
+        None => None,
+    };
+    let Some(user) = user else {
+        // makes the browser ask for the client id and secret
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(
+                header::WWW_AUTHENTICATE,
+                r#"Basic realm="VTN admin UI", charset="UTF-8""#,
+            )],
+        )
+            .into_response();
+    };
+    if !user.roles.iter().any(|role| role.is_user_manager()) {
+        return AppError::Forbidden("The admin UI requires the UserManager role").into_response();
+    }
+
+    let token = match jwt_manager.create(ACCESS_TOKEN_LIFETIME, user.client_id, user.roles) {
+        Ok(token) => token,
+        Err(err) => {
+            error!(?err, "could not issue the access token of the admin UI");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    (
+        [
+            // the page loads nothing but the API of this VTN
+            (
+                header::CONTENT_SECURITY_POLICY,
+                HeaderValue::from_static(
+                    "default-src 'self'; script-src 'unsafe-inline'; style-src 'unsafe-inline'",
+                ),
+            ),
+            // the page contains an access token
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-store")),
+        ],
+        Html(INDEX_HTML.replace(ACCESS_TOKEN_PLACEHOLDER, &token)),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use crate::api::test::state;
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        response::Response,
+        Router,
+    };
+    use axum_extra::headers::{Authorization, HeaderMapExt};
+    use http_body_util::BodyExt;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn request(app: &Router, credentials: Option<(&str, &str)>) -> Response {
+        let mut request = Request::builder()
+            .uri("/admin/ui")
+            .body(Body::empty())
+            .unwrap();
+        if let Some((client_id, client_secret)) = credentials {
+            request
+                .headers_mut()
+                .typed_insert(Authorization::basic(client_id, client_secret));
+        }
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn user_managers_only(db: PgPool) {
+        let app = state(db).await.into_router();
+
+        let response = request(&app, None).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(response.headers()[header::WWW_AUTHENTICATE]
+            .to_str()
+            .unwrap()
+            .starts_with("Basic"));
+
+        let response = request(&app, Some(("admin", "wrong"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // a user without any roles
+        let response = request(&app, Some(("user-1-client-id", "user-1"))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let response = request(&app, Some(("admin", "admin"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            mime::TEXT_HTML_UTF_8.as_ref()
+        );
+        assert!(response
+            .headers()
+            .contains_key(header::CONTENT_SECURITY_POLICY));
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let page = String::from_utf8(body.to_vec()).unwrap();
+        assert!(!page.contains("{{ACCESS_TOKEN}}"));
+    }
+}
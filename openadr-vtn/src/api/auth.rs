@@ -1,6 +1,10 @@
 use std::sync::Arc;
 
-use crate::{api::ValidatedForm, data_source::AuthSource, jwt::JwtManager};
+use crate::{
+    api::{AppResponse, ValidatedForm},
+    data_source::{AuthInfo, AuthSource},
+    jwt::{role_scopes, JwtManager, User},
+};
 use axum::{
     extract::State,
     http::{Response, StatusCode},
@@ -11,7 +15,10 @@ use axum_extra::{
     headers::{authorization::Basic, Authorization},
     TypedHeader,
 };
-use openadr_wire::oauth::{OAuthError, OAuthErrorType};
+use openadr_wire::{
+    auth::WhoAmI,
+    oauth::{OAuthError, OAuthErrorType, Scope},
+};
 use reqwest::header;
 use serde::Deserialize;
 use validator::Validate;
@@ -19,10 +26,12 @@ use validator::Validate;
 #[derive(Debug, Deserialize, Validate)]
 pub struct AccessTokenRequest {
     grant_type: String,
-    // TODO: handle scope
-    // scope: Option<String>,
+    /// A space-separated list of [`Scope`]s, all scopes of the roles of the client if absent
+    scope: Option<String>,
     client_id: Option<String>,
     client_secret: Option<String>,
+    /// For the `refresh_token` grant
+    refresh_token: Option<String>,
 }
 
 pub struct ResponseOAuthError(pub OAuthError);
@@ -65,6 +74,8 @@ pub struct AccessTokenResponse {
     token_type: &'static str,
     expires_in: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     scope: Option<String>,
 }
 
@@ -74,19 +85,86 @@ impl IntoResponse for AccessTokenResponse {
     }
 }
 
-/// RFC 6749 client credentials grant flow
+/// RFC 6749 client credentials grant flow, and the refresh token grant
+/// if [enabled](JwtManager::with_refresh_tokens)
 pub(crate) async fn token(
     State(auth_source): State<Arc<dyn AuthSource>>,
     State(jwt_manager): State<Arc<JwtManager>>,
     authorization: Option<TypedHeader<Authorization<Basic>>>,
     ValidatedForm(request): ValidatedForm<AccessTokenRequest>,
 ) -> Result<AccessTokenResponse, ResponseOAuthError> {
-    if request.grant_type != "client_credentials" {
-        return Err(OAuthError::new(OAuthErrorType::UnsupportedGrantType)
-            .with_description("Only client_credentials grant type is supported".to_string())
-            .into());
-    }
+    let refresh_lifetime = jwt_manager.refresh_token_lifetime();
+
+    let (user, available_scopes) = match (request.grant_type.as_str(), refresh_lifetime) {
+        ("client_credentials", _) => {
+            client_credentials(auth_source.as_ref(), authorization, &request).await?
+        }
+        ("refresh_token", Some(_)) => {
+            refresh_token(auth_source.as_ref(), &jwt_manager, &request).await?
+        }
+        (_, None) => {
+            return Err(OAuthError::new(OAuthErrorType::UnsupportedGrantType)
+                .with_description("Only client_credentials grant type is supported".to_string())
+                .into())
+        }
+        (_, Some(_)) => {
+            return Err(OAuthError::new(OAuthErrorType::UnsupportedGrantType)
+                .with_description(
+                    "Only client_credentials and refresh_token grant types are supported"
+                        .to_string(),
+                )
+                .into())
+        }
+    };
 
+    // the requested scopes the client has, such that clients can ask for scopes optimistically
+    let scopes = match &request.scope {
+        None => available_scopes,
+        Some(requested) => {
+            let requested = Scope::parse_list(requested).map_err(|err| {
+                OAuthError::new(OAuthErrorType::InvalidScope).with_description(err.to_string())
+            })?;
+            let scopes: Vec<_> = available_scopes
+                .into_iter()
+                .filter(|scope| requested.contains(scope))
+                .collect();
+            if scopes.is_empty() {
+                return Err(OAuthError::new(OAuthErrorType::InvalidScope)
+                    .with_description(
+                        "None of the requested scopes are granted to the client".to_string(),
+                    )
+                    .into());
+            }
+            scopes
+        }
+    };
+
+    let refresh_token = refresh_lifetime
+        .map(|lifetime| {
+            jwt_manager.create_refresh_token(lifetime, user.client_id.clone(), scopes.clone())
+        })
+        .transpose()?;
+
+    let expiration = std::time::Duration::from_secs(3600 * 24 * 30);
+    let scope = Scope::format_list(&scopes);
+    let token = jwt_manager.create_with_scopes(expiration, user.client_id, user.roles, scopes)?;
+
+    Ok(AccessTokenResponse {
+        access_token: token,
+        token_type: "bearer",
+        expires_in: expiration.as_secs(),
+        refresh_token,
+        scope: (!scope.is_empty()).then_some(scope),
+    })
+}
+
+/// RFC 6749 section 4.4, authenticating the client with its id and secret.
+/// Returns the client, with all scopes of its roles.
+async fn client_credentials(
+    auth_source: &dyn AuthSource,
+    authorization: Option<TypedHeader<Authorization<Basic>>>,
+    request: &AccessTokenRequest,
+) -> Result<(AuthInfo, Vec<Scope>), ResponseOAuthError> {
     let auth_header = authorization
         .as_ref()
         .map(|TypedHeader(auth)| (auth.username(), auth.password()));
@@ -127,13 +205,48 @@ pub(crate) async fn token(
             .into());
     };
 
-    let expiration = std::time::Duration::from_secs(3600 * 24 * 30);
-    let token = jwt_manager.create(expiration, user.client_id, user.roles)?;
+    let scopes = role_scopes(&user.roles);
+    Ok((user, scopes))
+}
 
-    Ok(AccessTokenResponse {
-        access_token: token,
-        token_type: "bearer",
-        expires_in: expiration.as_secs(),
-        scope: None,
-    })
+/// RFC 6749 section 6, renewing the token of a client without its secret.
+/// Returns the client, with the scopes of its roles the refresh token was issued with.
+///
+/// The roles are looked up again, such that changed roles and removed credentials take effect.
+async fn refresh_token(
+    auth_source: &dyn AuthSource,
+    jwt_manager: &JwtManager,
+    request: &AccessTokenRequest,
+) -> Result<(AuthInfo, Vec<Scope>), ResponseOAuthError> {
+    let Some(refresh_token) = &request.refresh_token else {
+        return Err(OAuthError::new(OAuthErrorType::InvalidRequest)
+            .with_description("refresh_token required".to_string())
+            .into());
+    };
+
+    let invalid_grant = |description: &str| {
+        ResponseOAuthError::from(
+            OAuthError::new(OAuthErrorType::InvalidGrant).with_description(description.to_string()),
+        )
+    };
+
+    let (client_id, refresh_scopes) = jwt_manager
+        .validate_refresh_token(refresh_token)
+        .map_err(|_| invalid_grant("Invalid or expired refresh_token"))?;
+
+    let user = auth_source
+        .find_client(&client_id)
+        .await
+        .ok_or_else(|| invalid_grant("The credentials of the client no longer exist"))?;
+
+    let scopes = role_scopes(&user.roles)
+        .into_iter()
+        .filter(|scope| refresh_scopes.contains(scope))
+        .collect();
+    Ok((user, scopes))
+}
+
+/// Describe the claims of the token the request is authenticated with, e.g., to debug permissions
+pub async fn whoami(User(user): User) -> AppResponse<WhoAmI> {
+    Ok(Json(user.who_am_i()))
 }
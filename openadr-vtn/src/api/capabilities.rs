@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use openadr_wire::capabilities::{Capabilities, Feature, NotificationTransport};
+
+use crate::{
+    api::{AppResponse, PageSize},
+    notifier::Notifier,
+    signing::EventSigner,
+    target_labels::TargetLabelRegistry,
+};
+
+/// The version of the OpenADR specification this VTN implements
+pub const SPEC_VERSION: &str = openadr_wire::SPEC_VERSION;
+
+pub async fn get(
+    State(event_signer): State<Option<Arc<EventSigner>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(page_size): State<PageSize>,
+) -> AppResponse<Capabilities> {
+    let mut features = vec![
+        Feature::LongPolling,
+        Feature::EventDeltas,
+        Feature::CursorPagination,
+    ];
+    if event_signer.is_some() {
+        features.push(Feature::EventSignatures);
+    }
+    if !target_labels.is_restricted() {
+        features.push(Feature::AnyPrivateTargetType);
+    }
+
+    let mut notification_transports = vec![];
+    if notifier.is_some() {
+        notification_transports.push(NotificationTransport::Webhook);
+    }
+
+    Ok(Json(Capabilities {
+        spec_version: SPEC_VERSION.to_string(),
+        max_page_size: page_size.max(),
+        default_page_size: Some(page_size.default_limit()),
+        target_types: target_labels.labels(),
+        notification_transports,
+        features,
+    }))
+}
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use tracing::info;
+
+use crate::{
+    api::{AppResponse, ValidatedJson},
+    certification::{Certification, CertificationStatus},
+    jwt::UserManagerUser,
+};
+
+pub async fn get(
+    State(certification): State<Arc<Certification>>,
+    UserManagerUser(_): UserManagerUser,
+) -> AppResponse<CertificationStatus> {
+    Ok(Json(certification.status()))
+}
+
+pub async fn edit(
+    State(certification): State<Arc<Certification>>,
+    UserManagerUser(user): UserManagerUser,
+    ValidatedJson(status): ValidatedJson<CertificationStatus>,
+) -> AppResponse<CertificationStatus> {
+    certification.set_status(status);
+    info!(
+        ?status,
+        vectors = certification.vectors().len(),
+        client_id = user.sub,
+        "changed certification mode"
+    );
+
+    Ok(Json(status))
+}
@@ -0,0 +1,113 @@
+//! Read the [`ChangeLog`], e.g., to catch up on the changes made while a consumer was offline
+
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    api::{AppResponse, ValidatedQuery, MAX_PAGE_SIZE},
+    change_log::{ChangeLog, ObjectChange},
+    error::AppError,
+    jwt::UserManagerUser,
+};
+
+#[derive(Deserialize, Validate, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParams {
+    /// Only return the changes with a larger sequence number
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    after: i64,
+    #[validate(range(min = 1, max = 50))]
+    #[serde(default = "default_limit")]
+    limit: i64,
+}
+
+fn default_limit() -> i64 {
+    MAX_PAGE_SIZE as i64
+}
+
+pub async fn get_all(
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    UserManagerUser(_): UserManagerUser,
+    ValidatedQuery(query): ValidatedQuery<QueryParams>,
+) -> AppResponse<Vec<ObjectChange>> {
+    let change_log = change_log.ok_or(AppError::NotImplemented(
+        "The change log is not enabled on this VTN",
+    ))?;
+
+    Ok(Json(change_log.changes(query.after, query.limit).await?))
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use super::*;
+    use crate::{
+        api::test::{jwt_test_token, state},
+        data_source::{DataSource, PostgresStorage},
+        jwt::AuthRole,
+    };
+    use axum::{
+        body::Body,
+        http::{self, header, Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn request(app: &Router, method: http::Method, path: &str, token: &str) -> Vec<u8> {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        response
+            .into_body()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes()
+            .to_vec()
+    }
+
+    #[sqlx::test(fixtures("users", "programs"))]
+    async fn records_changes(db: PgPool) {
+        let change_log = PostgresStorage::new(db.clone())
+            .unwrap()
+            .change_log()
+            .unwrap();
+        let state = state(db).await.with_change_log(change_log);
+        let admin = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let business = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let app = state.into_router();
+
+        request(&app, http::Method::DELETE, "/programs/program-1", &business).await;
+
+        let changes: Vec<ObjectChange> = serde_json::from_slice(
+            &request(&app, http::Method::GET, "/admin/changes", &admin).await,
+        )
+        .unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].object_type, "PROGRAM");
+        assert_eq!(changes[0].object_id, "program-1");
+        assert_eq!(changes[0].operation, "DELETE");
+        assert_eq!(changes[0].actor, "test_admin");
+
+        let path = format!("/admin/changes?after={}", changes[0].seq);
+        let changes: Vec<ObjectChange> =
+            serde_json::from_slice(&request(&app, http::Method::GET, &path, &admin).await).unwrap();
+        assert!(changes.is_empty());
+    }
+}
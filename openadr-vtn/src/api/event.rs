@@ -1,111 +1,422 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
+
+use chrono::{DateTime, Utc};
 
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    async_trait,
+    extract::{FromRequest, Path, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     Json,
 };
 use serde::Deserialize;
-use tracing::{info, trace};
-use validator::{Validate, ValidationError};
+use tracing::{info, trace, warn};
+use validator::Validate;
 
 use openadr_wire::{
-    event::{EventContent, EventId},
+    event::{
+        EventContent, EventDelta, EventId, EventOrder, EVENT_DELTA_CONTENT_TYPE,
+        EVENT_SIGNATURE_HEADER,
+    },
+    oauth::Scope,
     program::ProgramId,
-    target::TargetLabel,
-    Event,
+    Event, SortBy, CHANGE_VERSION_HEADER,
 };
 
 use crate::{
-    api::{AppResponse, ValidatedJson, ValidatedQuery},
-    data_source::EventCrud,
+    api::{
+        list_params::{deserialize_flag, deserialize_number, deserialize_timestamp},
+        ActiveWindow, AppResponse, DryRun, ListParams, Page, Sorting, ValidatedJson,
+        ValidatedQuery, Wait,
+    },
+    change_log::{self, ChangeLog, ObjectType, Operation},
+    changes::ChangeNotifier,
+    data_source::{DataSource, EventCrud, ProgramCrud},
     error::AppError,
-    jwt::{BusinessUser, User},
+    jwt::{BusinessUser, Claims, User},
+    notifier::{self, Notifier},
+    signing::EventSigner,
+    target_labels::TargetLabelRegistry,
 };
 
 pub async fn get_all(
     State(event_source): State<Arc<dyn EventCrud>>,
-    ValidatedQuery(query_params): ValidatedQuery<QueryParams>,
+    State(event_changes): State<Arc<ChangeNotifier>>,
+    query_params: QueryParams,
     User(user): User,
-) -> AppResponse<Vec<Event>> {
+) -> Result<(HeaderMap, Page<Event>), AppError> {
+    user.require_scope(Scope::ReadAll)?;
     trace!(?query_params);
+    query_params.extension.sort.check(&[
+        SortBy::Created,
+        SortBy::Modified,
+        SortBy::Priority,
+        SortBy::Name,
+    ])?;
+    if query_params.extension.order_by.is_some() && query_params.extension.sort.sort_by.is_some() {
+        return Err(AppError::BadRequest(
+            "orderBy and sortBy cannot be combined",
+        ));
+    }
+    query_params.cursor()?;
 
-    let events = event_source.retrieve_all(&query_params, &user).await?;
+    // long-polling: hold the request until any event changed
+    if let Some(Wait(wait)) = query_params.extension.wait {
+        event_changes
+            .wait(query_params.extension.change_version, wait)
+            .await;
+    }
 
-    Ok(Json(events))
+    // read before the events, such that a change while retrieving them does not go unnoticed
+    // by the next long-polling request
+    let version = event_changes.version();
+    let events = event_source.retrieve_all(&query_params, &user).await?;
+    let total = event_source.count(&query_params, &user).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CHANGE_VERSION_HEADER, HeaderValue::from(version));
+
+    Ok((
+        headers,
+        Page {
+            objects: events,
+            total,
+        },
+    ))
 }
 
 pub async fn get(
     State(event_source): State<Arc<dyn EventCrud>>,
+    State(event_signer): State<Option<Arc<EventSigner>>>,
     Path(id): Path<EventId>,
     User(user): User,
-) -> AppResponse<Event> {
+) -> Result<(HeaderMap, Json<Event>), AppError> {
+    user.require_scope(Scope::ReadAll)?;
     let event = event_source.retrieve(&id, &user).await?;
-    Ok(Json(event))
+    let headers = signature_headers(event_signer.as_deref(), &event)?;
+    Ok((headers, Json(event)))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn add(
+    State(storage): State<Arc<dyn DataSource>>,
     State(event_source): State<Arc<dyn EventCrud>>,
+    State(event_changes): State<Arc<ChangeNotifier>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(event_signer): State<Option<Arc<EventSigner>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
+    State(program_source): State<Arc<dyn ProgramCrud>>,
+    State(program_defaults): State<MaterializeProgramDefaults>,
     BusinessUser(user): BusinessUser,
+    State(interval_order): State<IntervalOrderPolicy>,
+    ValidatedQuery(dry_run): ValidatedQuery<DryRun>,
     ValidatedJson(new_event): ValidatedJson<EventContent>,
-) -> Result<(StatusCode, Json<Event>), AppError> {
-    let event = event_source.create(new_event, &user).await?;
+) -> Result<(StatusCode, HeaderMap, Json<Event>), AppError> {
+    user.require_scope(Scope::WriteEvents)?;
+    let new_event = program_defaults
+        .apply(program_source.as_ref(), new_event, &user)
+        .await?;
+    target_labels.validate_target_map(new_event.targets.as_ref())?;
+    interval_order.check(&new_event)?;
+
+    if dry_run.validate_only {
+        let event = event_source.validate_create(new_event, &user).await?;
+        return Ok((StatusCode::OK, HeaderMap::new(), Json(event)));
+    }
+
+    let event = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Event,
+        Operation::Create,
+        &user,
+        |event: &Event| event.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.events().create(new_event, user).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Create, &event);
 
     info!(%event.id, event_name=?event.content.event_name, "event created");
+    event_changes.notify();
 
-    Ok((StatusCode::CREATED, Json(event)))
+    let headers = signature_headers(event_signer.as_deref(), &event)?;
+    Ok((StatusCode::CREATED, headers, Json(event)))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn edit(
+    State(storage): State<Arc<dyn DataSource>>,
     State(event_source): State<Arc<dyn EventCrud>>,
+    State(event_changes): State<Arc<ChangeNotifier>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(event_signer): State<Option<Arc<EventSigner>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
+    State(interval_order): State<IntervalOrderPolicy>,
+    State(program_source): State<Arc<dyn ProgramCrud>>,
+    State(program_defaults): State<MaterializeProgramDefaults>,
     Path(id): Path<EventId>,
     BusinessUser(user): BusinessUser,
-    ValidatedJson(content): ValidatedJson<EventContent>,
-) -> AppResponse<Event> {
-    let event = event_source.update(&id, content, &user).await?;
+    update: EventUpdate,
+) -> Result<(HeaderMap, Json<Event>), AppError> {
+    user.require_scope(Scope::WriteEvents)?;
+    // a delta only applies to the event as it was retrieved, not to a concurrent update of it
+    let (content, modification_date_time) = match update {
+        EventUpdate::Full(content) => (content, None),
+        EventUpdate::Delta(delta) => {
+            let event = event_source.retrieve(&id, &user).await?;
+            let mut content = event.content;
+            trace!(%id, changed = delta.intervals.len(), removed = delta.removed_intervals.len(), "applying event delta");
+            content.apply_delta(delta);
+            content.validate()?;
+            (content, Some(event.modification_date_time))
+        }
+    };
+    let content = program_defaults
+        .apply(program_source.as_ref(), content, &user)
+        .await?;
+
+    target_labels.validate_target_map(content.targets.as_ref())?;
+    interval_order.check(&content)?;
+    let event = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Event,
+        Operation::Update,
+        &user,
+        |event: &Event| event.id.to_string(),
+        move |tx, user| {
+            Box::pin(async move {
+                match modification_date_time {
+                    Some(modification_date_time) => {
+                        tx.events()
+                            .update_unmodified(&id, content, modification_date_time, user)
+                            .await
+                    }
+                    None => tx.events().update(&id, content, user).await,
+                }
+            })
+        },
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Update, &event);
 
     info!(%event.id, event_name=?event.content.event_name, "event updated");
+    event_changes.notify();
 
-    Ok(Json(event))
+    let headers = signature_headers(event_signer.as_deref(), &event)?;
+    Ok((headers, Json(event)))
 }
 
 pub async fn delete(
-    State(event_source): State<Arc<dyn EventCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(event_changes): State<Arc<ChangeNotifier>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
     Path(id): Path<EventId>,
     BusinessUser(user): BusinessUser,
 ) -> AppResponse<Event> {
-    let event = event_source.delete(&id, &user).await?;
+    user.require_scope(Scope::WriteEvents)?;
+    let event = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Event,
+        Operation::Delete,
+        &user,
+        |event: &Event| event.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.events().delete(&id, user).await }),
+    )
+    .await?;
+    let id = &event.id;
+    notifier::send(notifier.as_deref(), Operation::Delete, &event);
     info!(%id, "deleted event");
+    event_changes.notify();
     Ok(Json(event))
 }
 
-#[derive(Deserialize, Validate, Debug)]
-#[validate(schema(function = "validate_target_type_value_pair"))]
+/// Whether the VTN stores events with the defaults of their program filled in,
+/// see [`EventContent::with_program_defaults`].
+///
+/// Otherwise, events are stored as sent, and clients resolve the defaults themselves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaterializeProgramDefaults(pub bool);
+
+impl MaterializeProgramDefaults {
+    async fn apply(
+        self,
+        program_source: &dyn ProgramCrud,
+        content: EventContent,
+        user: &Claims,
+    ) -> Result<EventContent, AppError> {
+        if !self.0 {
+            return Ok(content);
+        }
+
+        let program = program_source.retrieve(&content.program_id, user).await?;
+        Ok(content.with_program_defaults(&program.content))
+    }
+}
+
+/// How the VTN treats events whose intervals with an explicit period are not ordered by start
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IntervalOrderPolicy {
+    /// Accept the event, but log a warning
+    #[default]
+    Warn,
+    /// Reject the event with a 400 Bad Request
+    Reject,
+}
+
+impl IntervalOrderPolicy {
+    fn check(self, content: &EventContent) -> Result<(), AppError> {
+        let Some(interval) = content.out_of_order_interval() else {
+            return Ok(());
+        };
+
+        match self {
+            IntervalOrderPolicy::Warn => {
+                warn!(
+                    interval.id,
+                    event_name=?content.event_name,
+                    "event intervals are not ordered by start"
+                );
+                Ok(())
+            }
+            IntervalOrderPolicy::Reject => Err(AppError::BadRequest(
+                "event intervals must be ordered by their start",
+            )),
+        }
+    }
+}
+
+impl FromStr for IntervalOrderPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "warn" => Ok(IntervalOrderPolicy::Warn),
+            "reject" => Ok(IntervalOrderPolicy::Reject),
+            _ => Err("expected `warn` or `reject`"),
+        }
+    }
+}
+
+/// The body of an event update: either the complete event,
+/// or only the changed intervals if sent with the [`EVENT_DELTA_CONTENT_TYPE`].
+///
+/// A delta is applied to the event as currently stored.
+pub enum EventUpdate {
+    Full(EventContent),
+    Delta(EventDelta),
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for EventUpdate {
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_delta = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .is_some_and(|mime| mime.essence_str() == EVENT_DELTA_CONTENT_TYPE);
+
+        if is_delta {
+            let ValidatedJson(delta) =
+                ValidatedJson::from_request_as(req, state, EVENT_DELTA_CONTENT_TYPE).await?;
+            Ok(EventUpdate::Delta(delta))
+        } else {
+            let ValidatedJson(content) = ValidatedJson::from_request(req, state).await?;
+            Ok(EventUpdate::Full(content))
+        }
+    }
+}
+
+fn signature_headers(
+    event_signer: Option<&EventSigner>,
+    event: &Event,
+) -> Result<HeaderMap, AppError> {
+    let mut headers = HeaderMap::new();
+
+    if let Some(event_signer) = event_signer {
+        let signature = event_signer.sign(event).map_err(AppError::EventSigning)?;
+        // a JWS in compact serialization only consists of base64url characters and dots
+        let value = HeaderValue::from_str(&signature)
+            .expect("JWS compact serialization is a valid header value");
+        headers.insert(EVENT_SIGNATURE_HEADER, value);
+    }
+
+    Ok(headers)
+}
+
+/// The query parameters of `GET /events`
+pub type QueryParams = ListParams<EventListParams>;
+
+/// The parameters of `GET /events` besides the common [`ListParams`]
+#[derive(Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
-pub struct QueryParams {
+pub struct EventListParams {
     #[serde(rename = "programID")]
     pub(crate) program_id: Option<ProgramId>,
-    pub(crate) target_type: Option<TargetLabel>,
-    pub(crate) target_values: Option<Vec<String>>,
-    #[serde(default)]
-    #[validate(range(min = 0))]
-    pub(crate) skip: i64,
-    // TODO how to interpret limit = 0 and what is the default?
-    #[validate(range(min = 1, max = 50))]
-    #[serde(default = "get_50")]
-    pub(crate) limit: i64,
+    pub(crate) order_by: Option<EventOrder>,
+    /// Wait for any event to change before responding, see [`Wait`]
+    pub(crate) wait: Option<Wait>,
+    /// Do not wait if any event changed since this version of a previous response,
+    /// see [`CHANGE_VERSION_HEADER`]
+    #[serde(default, deserialize_with = "deserialize_number")]
+    pub(crate) change_version: Option<u64>,
+    /// Also list the events moved to the archive, see [`event_archive`](crate::event_archive)
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    pub(crate) include_archived: bool,
+    /// Only list the events after the one with this ID, in combination with
+    /// `afterModificationDateTime`, see [`QueryParams::cursor`]
+    #[serde(rename = "afterID")]
+    pub(crate) after_id: Option<EventId>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub(crate) after_modification_date_time: Option<DateTime<Utc>>,
+    /// Only list the events with an interval within `activeAfter..activeBefore`
+    #[serde(flatten)]
+    pub(crate) active: ActiveWindow,
+    /// Sort the events, instead of an `orderBy`
+    #[serde(flatten)]
+    pub(crate) sort: Sorting,
 }
 
-fn validate_target_type_value_pair(query: &QueryParams) -> Result<(), ValidationError> {
-    if query.target_type.is_some() == query.target_values.is_some() {
-        Ok(())
-    } else {
-        Err(ValidationError::new("targetType and targetValues query parameter must either both be set or not set at the same time."))
-    }
-}
+impl QueryParams {
+    /// The modification time and ID of the last event of the previous page, if paginating by cursor.
+    ///
+    /// Without an `orderBy` or `sortBy`, the events are ordered by their modification time and ID,
+    /// such that the events after the cursor can be found using an index,
+    /// instead of skipping all events of the previous pages.
+    /// An event updated during the pagination moves to the end, so it may be listed twice,
+    /// but no event is left out.
+    pub(crate) fn cursor(&self) -> Result<Option<(DateTime<Utc>, &EventId)>, AppError> {
+        let cursor = match (
+            self.extension.after_modification_date_time,
+            self.extension.after_id.as_ref(),
+        ) {
+            (Some(modification_date_time), Some(id)) => (modification_date_time, id),
+            (None, None) => return Ok(None),
+            _ => {
+                return Err(AppError::BadRequest(
+                    "afterID and afterModificationDateTime must either both be set or not set",
+                ))
+            }
+        };
 
-fn get_50() -> i64 {
-    50
+        if self.extension.order_by.is_some()
+            || self.extension.sort.sort_by.is_some()
+            || self.skip != 0
+        {
+            return Err(AppError::BadRequest(
+                "A cursor cannot be combined with orderBy, sortBy or skip",
+            ));
+        }
+
+        Ok(Some(cursor))
+    }
 }
 
 #[cfg(test)]
@@ -125,7 +436,8 @@ mod test {
         Router,
     };
     use http_body_util::BodyExt;
-    use openadr_wire::event::Priority;
+    use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Validation};
+    use openadr_wire::{event::Priority, problem::Problem};
     use sqlx::PgPool;
     use tower::{Service, ServiceExt};
 
@@ -207,6 +519,41 @@ mod test {
         assert_eq!(event, db_event);
     }
 
+    #[sqlx::test(fixtures("programs"))]
+    async fn get_signed(db: PgPool) {
+        let (state, mut events) = state_with_events(vec![default_event_content()], db).await;
+        let event = events.remove(0);
+        let state = state.with_event_signer(EventSigner::new(
+            Algorithm::HS256,
+            EncodingKey::from_secret(b"signing-secret"),
+        ));
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        let response = get_help(event.id.as_str(), &token, &mut app).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let signature = response
+            .headers()
+            .get(EVENT_SIGNATURE_HEADER)
+            .unwrap()
+            .to_str()
+            .unwrap();
+
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        let signed = jsonwebtoken::decode::<Event>(
+            signature,
+            &DecodingKey::from_secret(b"signing-secret"),
+            &validation,
+        )
+        .unwrap();
+
+        assert_eq!(event, signed.claims);
+    }
+
     #[sqlx::test(fixtures("programs"))]
     async fn delete(db: PgPool) {
         let event1 = EventContent {
@@ -317,6 +664,86 @@ mod test {
         assert_eq!(response.status(), StatusCode::CREATED);
     }
 
+    #[sqlx::test(fixtures("programs"))]
+    async fn reject_out_of_order_intervals(db: PgPool) {
+        use chrono::{TimeZone, Utc};
+        use openadr_wire::{event::EventInterval, interval::IntervalPeriod};
+
+        let interval = |id, hour| EventInterval {
+            id,
+            interval_period: Some(IntervalPeriod::new(
+                Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap(),
+            )),
+            payloads: vec![],
+        };
+        let content = EventContent {
+            intervals: vec![interval(0, 2), interval(1, 1)],
+            ..default_event_content()
+        };
+
+        let (state, _) = state_with_events(vec![], db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+
+        let mut app = state.clone().into_router();
+        let response = help_create_event(&mut app, &content, &token).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let mut app = state
+            .with_interval_order(IntervalOrderPolicy::Reject)
+            .into_router();
+        let response = help_create_event(&mut app, &content, &token).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let mut sorted = content.clone();
+        sorted.normalize_interval_order();
+        let response = help_create_event(&mut app, &sorted, &token).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[sqlx::test(fixtures("programs"))]
+    async fn materialize_program_defaults(db: PgPool) {
+        use openadr_wire::{interval::IntervalPeriod, program::ProgramContent};
+
+        let (state, _) = state_with_events(vec![], db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+
+        let interval_period = IntervalPeriod::new("2024-01-01T00:00:00Z".parse().unwrap());
+        let program = state
+            .storage
+            .programs()
+            .create(
+                ProgramContent {
+                    interval_period: Some(interval_period.clone()),
+                    default_priority: Some(Priority::new(5)),
+                    ..ProgramContent::new("program-with-defaults")
+                },
+                &Claims::any_business_user(),
+            )
+            .await
+            .unwrap();
+        let content = EventContent {
+            program_id: program.id,
+            priority: Priority::UNSPECIFIED,
+            ..default_event_content()
+        };
+
+        // stored as sent by default
+        let mut app = state.clone().into_router();
+        let response = help_create_event(&mut app, &content, &token).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let event: Event = serde_json::from_slice(&body).unwrap();
+        assert_eq!(event.content, content);
+
+        let mut app = state.with_materialized_program_defaults().into_router();
+        let response = help_create_event(&mut app, &content, &token).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let event: Event = serde_json::from_slice(&body).unwrap();
+        assert_eq!(event.content.priority, Priority::new(5));
+        assert_eq!(event.content.interval_period, Some(interval_period));
+    }
+
     async fn retrieve_all_with_filter_help(
         app: &mut Router,
         query_params: &str,
@@ -396,6 +823,16 @@ mod test {
         let response = retrieve_all_with_filter_help(&mut app, "limit=0", &token).await;
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 
+        // the problem tells which parameter is invalid
+        let response = retrieve_all_with_filter_help(&mut app, "limit=51", &token).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let problem: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            problem.extensions["invalidParams"],
+            serde_json::json!([{ "name": "limit" }])
+        );
+
         // program name
         let response = retrieve_all_with_filter_help(&mut app, "targetType=NONSENSE", &token).await;
         assert_eq!(
@@ -457,6 +894,152 @@ mod test {
         assert_eq!(programs.len(), 1);
     }
 
+    #[sqlx::test(fixtures("programs"))]
+    async fn retrieve_all_by_cursor(db: PgPool) {
+        let new_events = (1..=3)
+            .map(|i| EventContent {
+                event_name: Some(format!("event{i}")),
+                ..default_event_content()
+            })
+            .collect();
+
+        let (state, events) = state_with_events(new_events, db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        let mut received: Vec<Event> = vec![];
+        loop {
+            let mut query = "limit=2".to_string();
+            if let Some(last) = received.last() {
+                let after = last
+                    .modification_date_time
+                    .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true);
+                query.push_str(&format!(
+                    "&afterID={}&afterModificationDateTime={after}",
+                    last.id
+                ));
+            }
+
+            let response = retrieve_all_with_filter_help(&mut app, &query, &token).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let page: Vec<Event> = serde_json::from_slice(&body).unwrap();
+
+            let last_page = page.len() < 2;
+            received.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        assert_eq!(received, events);
+
+        for invalid in [
+            "afterID=event-1",
+            "afterModificationDateTime=2024-07-25T08:31:10Z",
+            "afterID=event-1&afterModificationDateTime=2024-07-25T08:31:10Z&skip=1",
+            "afterID=event-1&afterModificationDateTime=2024-07-25T08:31:10Z&orderBy=priority",
+            "afterID=event-1&afterModificationDateTime=2024-07-25T08:31:10Z&sortBy=created",
+        ] {
+            let response = retrieve_all_with_filter_help(&mut app, invalid, &token).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{invalid}");
+        }
+    }
+
+    #[sqlx::test(fixtures("users", "programs"))]
+    async fn retrieve_all_sorted(db: PgPool) {
+        let new_events = ["event-b", "event-c", "event-a"]
+            .into_iter()
+            .map(|name| EventContent {
+                event_name: Some(name.to_string()),
+                ..default_event_content()
+            })
+            .chain([EventContent {
+                event_name: None,
+                ..default_event_content()
+            }])
+            .collect();
+
+        let (state, _) = state_with_events(new_events, db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        for (query, expected) in [
+            ("sortBy=name", ["event-a", "event-b", "event-c"]),
+            (
+                "sortBy=name&sortOrder=desc",
+                ["event-c", "event-b", "event-a"],
+            ),
+            ("sortBy=created", ["event-b", "event-c", "event-a"]),
+        ] {
+            let response = retrieve_all_with_filter_help(&mut app, query, &token).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            let events: Vec<Event> = serde_json::from_slice(&body).unwrap();
+
+            let names: Vec<_> = events
+                .iter()
+                .map(|event| event.content.event_name.as_deref())
+                .collect();
+            // the event without a name comes last
+            assert_eq!(names[..3], expected.map(Some), "{query}");
+            assert_eq!(names[3], None, "{query}");
+        }
+
+        for invalid in [
+            "sortBy=color",
+            "sortOrder=desc",
+            "sortBy=name&orderBy=start",
+        ] {
+            let response = retrieve_all_with_filter_help(&mut app, invalid, &token).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{invalid}");
+        }
+    }
+
+    #[sqlx::test(fixtures("programs"))]
+    async fn long_poll_since_version(db: PgPool) {
+        let (state, _) = state_with_events(vec![default_event_content()], db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        let response = retrieve_all_with_filter_help(&mut app, "", &token).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let version = response.headers()[CHANGE_VERSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // the event is created in between two polls
+        let response = help_create_event(&mut app, &default_event_content(), &token).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let query = format!("wait=30s&changeVersion={version}");
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            retrieve_all_with_filter_help(&mut app, &query, &token),
+        )
+        .await
+        .expect("answered without waiting for the next change");
+        assert_eq!(response.status(), StatusCode::OK);
+        let current = response.headers()[CHANGE_VERSION_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_ne!(current, version);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let events: Vec<Event> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(events.len(), 2);
+
+        // nothing changed since the current version
+        let query = format!("wait=1s&changeVersion={current}");
+        let response = tokio::time::timeout(
+            std::time::Duration::from_millis(500),
+            retrieve_all_with_filter_help(&mut app, &query, &token),
+        )
+        .await;
+        assert!(response.is_err());
+    }
+
     mod permissions {
         use super::*;
 
@@ -476,7 +1059,7 @@ mod test {
 
             let token = jwt_test_token(&state, vec![AuthRole::Business("business-2".to_string())]);
             let response = help_create_event(&mut app, &content, &token).await;
-            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
             let token = jwt_test_token(
                 &state,
@@ -501,7 +1084,7 @@ mod test {
                 )
                 .await
                 .unwrap();
-            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
             let token = jwt_test_token(&state, vec![AuthRole::Business("business-2".to_string())]);
             let response = app
@@ -517,7 +1100,7 @@ mod test {
                 )
                 .await
                 .unwrap();
-            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert_eq!(response.status(), StatusCode::FORBIDDEN);
 
             let token = jwt_test_token(&state, vec![AuthRole::Business("business-1".to_string())]);
             let response = app
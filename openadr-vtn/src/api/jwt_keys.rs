@@ -0,0 +1,213 @@
+//! Rotate the keys the VTN signs its tokens with, see [`JwtManager`]
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use tracing::info;
+use validator::Validate;
+
+use crate::{
+    api::{AppResponse, ValidatedJson},
+    error::AppError,
+    jwt::{JwtManager, KeyIds, SigningKey, UserManagerUser},
+};
+
+/// Either a `secret` for HS256, or the PEM encoded `publicKey` of the `algorithm`,
+/// with the `privateKey` unless the key only validates tokens
+#[derive(Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct NewKey {
+    #[validate(length(min = 1, max = 128))]
+    kid: String,
+    /// The base64 encoded secret of the key
+    secret: Option<String>,
+    algorithm: Option<Algorithm>,
+    private_key: Option<String>,
+    public_key: Option<String>,
+    /// Sign new tokens with this key immediately
+    #[serde(default)]
+    activate: bool,
+}
+
+impl NewKey {
+    fn signing_key(&self) -> Result<SigningKey, AppError> {
+        match (&self.secret, self.algorithm, &self.public_key) {
+            (Some(secret), None | Some(Algorithm::HS256), None) if self.private_key.is_none() => {
+                SigningKey::from_base64_secret(secret)
+                    .map_err(|_| AppError::BadRequest("The secret must be base64 encoded"))
+            }
+            (None, Some(algorithm), Some(public_key)) => {
+                let key = match &self.private_key {
+                    Some(private_key) => SigningKey::from_pem(
+                        algorithm,
+                        private_key.as_bytes(),
+                        public_key.as_bytes(),
+                    ),
+                    None => SigningKey::verify_only(algorithm, public_key.as_bytes()),
+                };
+                key.map_err(|_| {
+                    AppError::BadRequest("The keys must be PEM encoded keys of the algorithm")
+                })
+            }
+            _ => Err(AppError::BadRequest(
+                "Specify either a secret, or an algorithm with a public key",
+            )),
+        }
+    }
+}
+
+pub async fn get_all(
+    State(jwt_manager): State<Arc<JwtManager>>,
+    UserManagerUser(_): UserManagerUser,
+) -> AppResponse<KeyIds> {
+    Ok(Json(jwt_manager.key_ids()))
+}
+
+pub async fn add(
+    State(jwt_manager): State<Arc<JwtManager>>,
+    UserManagerUser(user): UserManagerUser,
+    ValidatedJson(new_key): ValidatedJson<NewKey>,
+) -> Result<(StatusCode, Json<KeyIds>), AppError> {
+    let key = new_key.signing_key()?;
+    let algorithm = key.algorithm();
+
+    jwt_manager.add_key(&new_key.kid, key)?;
+    info!(
+        kid = new_key.kid,
+        ?algorithm,
+        client_id = user.sub,
+        "added JWT key"
+    );
+
+    if new_key.activate {
+        jwt_manager.activate_key(&new_key.kid)?;
+        info!(kid = new_key.kid, client_id = user.sub, "activated JWT key");
+    }
+
+    Ok((StatusCode::CREATED, Json(jwt_manager.key_ids())))
+}
+
+pub async fn activate(
+    State(jwt_manager): State<Arc<JwtManager>>,
+    UserManagerUser(user): UserManagerUser,
+    Path(kid): Path<String>,
+) -> AppResponse<KeyIds> {
+    jwt_manager.activate_key(&kid)?;
+    info!(kid, client_id = user.sub, "activated JWT key");
+
+    Ok(Json(jwt_manager.key_ids()))
+}
+
+pub async fn retire(
+    State(jwt_manager): State<Arc<JwtManager>>,
+    UserManagerUser(user): UserManagerUser,
+    Path(kid): Path<String>,
+) -> AppResponse<KeyIds> {
+    jwt_manager.retire_key(&kid)?;
+    info!(kid, client_id = user.sub, "retired JWT key");
+
+    Ok(Json(jwt_manager.key_ids()))
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use crate::{
+        api::test::{jwt_test_token, state},
+        jwt::{AuthRole, KeyIds, INITIAL_KEY_ID},
+    };
+    use axum::{
+        body::Body,
+        http::{self, header, Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn request(
+        app: &Router,
+        method: http::Method,
+        path: &str,
+        token: &str,
+        body: Body,
+    ) -> (StatusCode, Option<KeyIds>) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(body)
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).ok())
+    }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn rotate(db: PgPool) {
+        let state = state(db).await;
+        let old_token = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let app = state.clone().into_router();
+
+        // "new secret" in base64
+        let new_key = Body::from(r#"{"kid":"new","secret":"bmV3IHNlY3JldA==","activate":true}"#);
+        let (status, keys) = request(
+            &app,
+            http::Method::POST,
+            "/admin/jwt-keys",
+            &old_token,
+            new_key,
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(
+            keys,
+            Some(KeyIds {
+                active: "new".to_string(),
+                keys: vec![INITIAL_KEY_ID.to_string(), "new".to_string()]
+            })
+        );
+
+        let new_token = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let path = format!("/admin/jwt-keys/{INITIAL_KEY_ID}");
+
+        let (status, _) =
+            request(&app, http::Method::DELETE, &path, &new_token, Body::empty()).await;
+        assert_eq!(status, StatusCode::OK);
+
+        // tokens signed with the retired key are no longer accepted
+        let (status, _) = request(
+            &app,
+            http::Method::GET,
+            "/admin/jwt-keys",
+            &old_token,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = request(
+            &app,
+            http::Method::DELETE,
+            "/admin/jwt-keys/new",
+            &new_token,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,171 @@
+//! Deserialization of large JSON bodies with a size limit, like reports with many resources
+
+use std::time::Duration;
+
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequest, Request},
+};
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+use crate::{api::media_type::require_content_type, error::AppError};
+
+/// The default [`ReportSizeLimit`] of 16 MiB
+pub const DEFAULT_REPORT_SIZE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// The time within which the whole body must be received, such that a slow client
+/// cannot hold on to a request indefinitely
+const BODY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum size of a report body in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportSizeLimit(pub usize);
+
+impl Default for ReportSizeLimit {
+    fn default() -> Self {
+        Self(DEFAULT_REPORT_SIZE_LIMIT)
+    }
+}
+
+/// A validated JSON body of at most the [`ReportSizeLimit`].
+///
+/// Unlike [`axum::Json`], which is limited by the default body limit of the router, larger bodies
+/// are accepted up to the configured limit. Bodies exceeding the limit are rejected as soon as the
+/// limit is exceeded, and bodies not received within [`BODY_TIMEOUT`] are rejected as well.
+#[derive(Debug, Clone)]
+pub struct LimitedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for LimitedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+    ReportSizeLimit: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        require_content_type(req.headers(), mime::APPLICATION_JSON.as_ref())?;
+
+        let ReportSizeLimit(limit) = ReportSizeLimit::from_ref(state);
+
+        let body = Limited::new(req.into_body(), limit).collect();
+        let bytes = tokio::time::timeout(BODY_TIMEOUT, body)
+            .await
+            .map_err(|_| AppError::RequestTimeout(BODY_TIMEOUT))?
+            .map_err(|err| {
+                if err.is::<LengthLimitError>() {
+                    AppError::PayloadTooLarge(limit)
+                } else {
+                    AppError::BadRequest("Failed to read the request body")
+                }
+            })?
+            .to_bytes();
+
+        let value: T = serde_json::from_slice(&bytes).map_err(AppError::SerdeJsonBadRequest)?;
+        value.validate()?;
+
+        Ok(LimitedJson(value))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use axum::{
+        body::{Body, Bytes},
+        http::header,
+    };
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Deserialize, Validate, Debug, PartialEq)]
+    struct Upload {
+        #[validate(length(min = 1))]
+        values: Vec<u32>,
+    }
+
+    async fn extract(body: String, content_type: &str, limit: usize) -> Result<Upload, AppError> {
+        let request = Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+
+        let LimitedJson(upload) =
+            LimitedJson::<Upload>::from_request(request, &ReportSizeLimit(limit)).await?;
+        Ok(upload)
+    }
+
+    fn upload(len: u32) -> String {
+        serde_json::json!({ "values": (0..len).collect::<Vec<_>>() }).to_string()
+    }
+
+    #[tokio::test]
+    async fn deserializes_large_bodies() {
+        let body = upload(1_000_000);
+        assert!(
+            body.len() > 2 * 1024 * 1024,
+            "larger than the default body limit"
+        );
+
+        let upload = extract(body, "application/json", 8 * 1024 * 1024)
+            .await
+            .unwrap();
+        assert_eq!(upload.values.len(), 1_000_000);
+        assert_eq!(upload.values[999_999], 999_999);
+    }
+
+    #[tokio::test]
+    async fn rejects_bodies_over_the_limit() {
+        let body = upload(1000);
+        let limit = body.len() - 1;
+
+        assert!(matches!(
+            extract(body, "application/json", limit).await,
+            Err(AppError::PayloadTooLarge(l)) if l == limit
+        ));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_bodies() {
+        let limit = DEFAULT_REPORT_SIZE_LIMIT;
+
+        assert!(matches!(
+            extract(upload(1), "text/plain", limit).await,
+            Err(AppError::UnsupportedMediaType(_))
+        ));
+        assert!(matches!(
+            extract(upload(1), "application/vnd.openadr+json", limit).await,
+            Err(AppError::UnsupportedMediaType(_))
+        ));
+        assert!(extract(upload(1), "application/json; charset=utf-8", limit)
+            .await
+            .is_ok());
+        assert!(matches!(
+            extract("{\"values\": [1,".to_string(), "application/json", limit).await,
+            Err(AppError::SerdeJsonBadRequest(_))
+        ));
+        assert!(matches!(
+            extract(upload(0), "application/json", limit).await,
+            Err(AppError::Validation(_))
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rejects_slow_bodies() {
+        let body = futures_util::stream::pending::<Result<Bytes, std::io::Error>>();
+        let request = Request::builder()
+            .method("POST")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from_stream(body))
+            .unwrap();
+
+        assert!(matches!(
+            LimitedJson::<Upload>::from_request(request, &ReportSizeLimit(1024)).await,
+            Err(AppError::RequestTimeout(BODY_TIMEOUT))
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, Uri},
+};
+use axum_extra::extract::QueryRejection;
+use chrono::{DateTime, Utc};
+use serde::{
+    de::{DeserializeOwned, Error as _, Unexpected},
+    Deserialize, Deserializer,
+};
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use openadr_wire::{interval::IntervalPeriod, target::TargetLabel, SortBy, SortOrder};
+
+use crate::{api::MAX_PAGE_SIZE, error::AppError};
+
+/// The query parameters shared by all list endpoints, e.g., `GET /programs`.
+///
+/// Endpoints with additional parameters, like the `programID` of `GET /events`,
+/// declare these in the extension `E`.
+/// Because the extension is flattened, its fields must deserialize from strings.
+///
+/// As an extractor, the `limit` defaults to, and may not exceed, the configured [`PageSize`].
+#[derive(Deserialize, Validate, Debug, Clone)]
+#[validate(schema(function = "validate_target_type_value_pair"))]
+#[serde(rename_all = "camelCase")]
+pub struct ListParams<E = NoExtension> {
+    pub(crate) target_type: Option<TargetLabel>,
+    pub(crate) target_values: Option<Vec<String>>,
+    #[serde(default)]
+    #[validate(range(min = 0))]
+    pub(crate) skip: i64,
+    // TODO how to interpret limit = 0?
+    #[validate(range(min = 1))]
+    #[serde(default = "default_limit")]
+    pub(crate) limit: i64,
+    /// Only list the objects with this text in their names or descriptions, see `GET /search`.
+    /// Not a query parameter of the list endpoints.
+    #[serde(skip)]
+    pub(crate) search: Option<String>,
+    #[serde(flatten)]
+    pub(crate) extension: E,
+}
+
+/// The extension of [`ListParams`] for endpoints without additional parameters
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct NoExtension {}
+
+/// The extension of [`ListParams`] for programs and events, to only list the objects
+/// active somewhere within `activeAfter..activeBefore`, e.g., the upcoming events.
+/// Either bound may be left out.
+///
+/// Like [`IntervalPeriod::overlaps`], a period without a duration never ends,
+/// and objects without an interval period are considered always active.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveWindow {
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub(crate) active_after: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "deserialize_timestamp")]
+    pub(crate) active_before: Option<DateTime<Utc>>,
+}
+
+impl ActiveWindow {
+    /// Whether the interval period, if any, overlaps the window. Postgres filters
+    /// in SQL instead, see `interval_period_overlaps`.
+    #[cfg_attr(not(feature = "in-memory"), allow(dead_code))]
+    pub(crate) fn overlaps(&self, period: Option<&IntervalPeriod>) -> bool {
+        period.map_or(true, |period| {
+            period.overlaps(
+                self.active_after.unwrap_or(DateTime::<Utc>::MIN_UTC),
+                self.active_before.unwrap_or(DateTime::<Utc>::MAX_UTC),
+            )
+        })
+    }
+}
+
+/// The extension of [`ListParams`] for programs, events and reports, to sort the objects
+/// by a `sortBy` property in the `sortOrder`, instead of the default order of the endpoint
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Sorting {
+    pub(crate) sort_by: Option<SortBy>,
+    pub(crate) sort_order: Option<SortOrder>,
+}
+
+impl Sorting {
+    /// Rejects a `sortOrder` without `sortBy`, and the properties the objects do not have
+    pub(crate) fn check(&self, supported: &[SortBy]) -> Result<(), AppError> {
+        match self.sort_by {
+            Some(sort_by) if !supported.contains(&sort_by) => Err(AppError::BadRequest(
+                "The objects of this endpoint cannot be sorted by this sortBy",
+            )),
+            None if self.sort_order.is_some() => {
+                Err(AppError::BadRequest("sortOrder requires a sortBy"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn is_descending(&self) -> bool {
+        self.sort_order == Some(SortOrder::Desc)
+    }
+}
+
+impl<E: Default> Default for ListParams<E> {
+    fn default() -> Self {
+        Self {
+            target_type: None,
+            target_values: None,
+            skip: 0,
+            limit: default_limit(),
+            search: None,
+            extension: E::default(),
+        }
+    }
+}
+
+/// The `limit` of list requests without one, and the largest `limit` accepted,
+/// advertised as the `maxPageSize` of the capabilities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageSize {
+    default: usize,
+    max: usize,
+}
+
+impl PageSize {
+    /// `None` unless `1 <= default <= max`
+    pub fn new(default: usize, max: usize) -> Option<Self> {
+        (1..=max)
+            .contains(&default)
+            .then_some(Self { default, max })
+    }
+
+    pub fn default_limit(&self) -> usize {
+        self.default
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+impl Default for PageSize {
+    fn default() -> Self {
+        Self {
+            default: MAX_PAGE_SIZE,
+            max: MAX_PAGE_SIZE,
+        }
+    }
+}
+
+impl<E: DeserializeOwned> ListParams<E> {
+    fn from_uri(uri: &Uri, page_size: PageSize) -> Result<Self, AppError> {
+        // the parser of the `Query` extractor of axum-extra, which accepts repeated keys
+        let mut params: Self = serde_html_form::from_str(uri.query().unwrap_or_default())
+            .map_err(|err| QueryRejection::FailedToDeserializeQueryString(axum::Error::new(err)))?;
+        params.validate()?;
+
+        let has_limit = uri.query().is_some_and(|query| {
+            url::form_urlencoded::parse(query.as_bytes()).any(|(key, _)| key == "limit")
+        });
+        if !has_limit {
+            params.limit = page_size.default as i64;
+        } else if params.limit > page_size.max as i64 {
+            let mut error = ValidationError::new("range");
+            error.add_param("max".into(), &page_size.max);
+            let mut errors = ValidationErrors::new();
+            errors.add("limit", error);
+            return Err(errors.into());
+        }
+
+        Ok(params)
+    }
+}
+
+#[async_trait]
+impl<E, S> FromRequestParts<S> for ListParams<E>
+where
+    E: DeserializeOwned,
+    S: Send + Sync,
+    PageSize: FromRef<S>,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Self::from_uri(&parts.uri, PageSize::from_ref(state))
+    }
+}
+
+fn validate_target_type_value_pair<E>(query: &ListParams<E>) -> Result<(), ValidationError> {
+    if query.target_type.is_some() == query.target_values.is_some() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("targetType and targetValues query parameter must either both be set or not set at the same time."))
+    }
+}
+
+/// Deserialize a `true` or `false` query parameter of an extension of [`ListParams`],
+/// which the flattening passes as a string
+pub(crate) fn deserialize_flag<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<bool, D::Error> {
+    match String::deserialize(deserializer)?.as_str() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(D::Error::invalid_value(
+            Unexpected::Str(other),
+            &"true or false",
+        )),
+    }
+}
+
+/// Deserialize an optional RFC 3339 timestamp query parameter of an extension of [`ListParams`],
+/// to be combined with `#[serde(default)]`
+pub(crate) fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    openadr_wire::serde_rfc3339::deserialize(deserializer).map(Some)
+}
+
+/// Deserialize an optional number query parameter of an extension of [`ListParams`],
+/// to be combined with `#[serde(default)]`
+pub(crate) fn deserialize_number<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u64>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| D::Error::invalid_value(Unexpected::Str(&value), &"a number"))
+}
+
+fn default_limit() -> i64 {
+    MAX_PAGE_SIZE as i64
+}
+
+#[cfg(test)]
+mod test {
+    use openadr_wire::program::ProgramId;
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, Default, PartialEq)]
+    struct ProgramExtension {
+        #[serde(rename = "programID")]
+        program_id: Option<ProgramId>,
+        #[serde(default, deserialize_with = "deserialize_flag")]
+        archived: bool,
+    }
+
+    fn parse_with<E: DeserializeOwned>(query: &str, page_size: PageSize) -> Option<ListParams<E>> {
+        let uri: Uri = format!("/objects?{query}").parse().unwrap();
+        ListParams::from_uri(&uri, page_size).ok()
+    }
+
+    fn parse<E: DeserializeOwned>(query: &str) -> Option<ListParams<E>> {
+        parse_with(query, PageSize::default())
+    }
+
+    #[test]
+    fn defaults() {
+        let params = parse::<NoExtension>("").unwrap();
+        assert_eq!(params.skip, 0);
+        assert_eq!(params.limit, MAX_PAGE_SIZE as i64);
+        assert!(params.target_type.is_none());
+        assert!(params.target_values.is_none());
+    }
+
+    #[test]
+    fn targets() {
+        let params =
+            parse::<NoExtension>("targetType=GROUP&targetValues=a&targetValues=b").unwrap();
+        assert_eq!(params.target_type, Some(TargetLabel::Group));
+        assert_eq!(
+            params.target_values,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+
+        assert!(parse::<NoExtension>("targetType=GROUP").is_none());
+        assert!(parse::<NoExtension>("targetValues=a").is_none());
+    }
+
+    #[test]
+    fn pagination() {
+        let params = parse::<NoExtension>("skip=10&limit=5").unwrap();
+        assert_eq!((params.skip, params.limit), (10, 5));
+
+        for invalid in ["skip=-1", "limit=0", "limit=51", "limit=many"] {
+            assert!(parse::<NoExtension>(invalid).is_none(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn configured_page_size() {
+        let page_size = PageSize::new(100, 1000).unwrap();
+        assert_eq!(parse_with::<NoExtension>("", page_size).unwrap().limit, 100);
+        assert_eq!(
+            parse_with::<NoExtension>("limit=1000", page_size)
+                .unwrap()
+                .limit,
+            1000
+        );
+        assert!(parse_with::<NoExtension>("limit=1001", page_size).is_none());
+
+        assert!(PageSize::new(0, 10).is_none());
+        assert!(PageSize::new(20, 10).is_none());
+    }
+
+    #[test]
+    fn extension() {
+        let params = parse::<ProgramExtension>("programID=program-1&limit=5").unwrap();
+        assert_eq!(params.limit, 5);
+        assert_eq!(
+            params.extension.program_id,
+            Some(ProgramId::new("program-1").unwrap())
+        );
+
+        let params = parse::<ProgramExtension>("targetType=GROUP&targetValues=a").unwrap();
+        assert_eq!(params.extension, ProgramExtension::default());
+
+        let params = parse::<ProgramExtension>("archived=true").unwrap();
+        assert!(params.extension.archived);
+        assert!(parse::<ProgramExtension>("archived=yes").is_none());
+    }
+
+    #[test]
+    fn active_window() {
+        let at = |hour| {
+            "2024-10-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+                + chrono::TimeDelta::hours(hour)
+        };
+
+        let window = parse::<ActiveWindow>("activeAfter=2024-10-01T02:00:00Z")
+            .unwrap()
+            .extension;
+        assert_eq!(window.active_after, Some(at(2)));
+        assert_eq!(window.active_before, None);
+        assert!(parse::<ActiveWindow>("activeBefore=tomorrow").is_none());
+
+        let window = parse::<ActiveWindow>(
+            "activeAfter=2024-10-01T02:00:00Z&activeBefore=2024-10-01T04:00:00Z",
+        )
+        .unwrap()
+        .extension;
+
+        let period = |start, hours: Option<i64>| IntervalPeriod {
+            duration: hours.map(|hours| openadr_wire::Duration::hours(hours as f32)),
+            ..IntervalPeriod::new(at(start))
+        };
+        assert!(window.overlaps(None));
+        assert!(window.overlaps(Some(&period(1, Some(2)))));
+        assert!(window.overlaps(Some(&period(0, None))));
+        assert!(!window.overlaps(Some(&period(0, Some(2)))));
+        assert!(!window.overlaps(Some(&period(4, Some(1)))));
+        assert!(ActiveWindow::default().overlaps(Some(&period(0, Some(1)))));
+    }
+
+    #[test]
+    fn sorting() {
+        let sorting = parse::<Sorting>("sortBy=priority&sortOrder=desc")
+            .unwrap()
+            .extension;
+        assert_eq!(sorting.sort_by, Some(SortBy::Priority));
+        assert!(sorting.is_descending());
+        assert!(sorting.check(&[SortBy::Created, SortBy::Priority]).is_ok());
+        assert!(sorting.check(&[SortBy::Created]).is_err());
+
+        let sorting = parse::<Sorting>("sortBy=name").unwrap().extension;
+        assert!(!sorting.is_descending());
+        assert!(parse::<Sorting>("sortBy=color").is_none());
+        assert!(parse::<Sorting>("sortBy=name&sortOrder=up").is_none());
+
+        let sorting = parse::<Sorting>("sortOrder=desc").unwrap().extension;
+        assert!(sorting.check(&[SortBy::Created]).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use tracing::info;
+
+use crate::{
+    api::{AppResponse, ValidatedJson},
+    jwt::UserManagerUser,
+    maintenance::{Maintenance, MaintenanceStatus},
+};
+
+pub async fn get(
+    State(maintenance): State<Arc<Maintenance>>,
+    UserManagerUser(_): UserManagerUser,
+) -> AppResponse<MaintenanceStatus> {
+    Ok(Json(maintenance.status()))
+}
+
+pub async fn edit(
+    State(maintenance): State<Arc<Maintenance>>,
+    UserManagerUser(user): UserManagerUser,
+    ValidatedJson(status): ValidatedJson<MaintenanceStatus>,
+) -> AppResponse<MaintenanceStatus> {
+    maintenance.set_status(status);
+    info!(?status, client_id = user.sub, "changed maintenance mode");
+
+    Ok(Json(status))
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use crate::{
+        api::test::{jwt_test_token, state},
+        jwt::AuthRole,
+    };
+    use axum::{
+        body::Body,
+        http::{self, header, Request, StatusCode},
+        Router,
+    };
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn request(
+        app: &Router,
+        method: http::Method,
+        path: &str,
+        token: &str,
+        body: Body,
+    ) -> http::Response<Body> {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(body)
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(fixtures("users", "programs"))]
+    async fn toggle_maintenance(db: PgPool) {
+        let state = state(db).await;
+        let admin = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let business = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let app = state.into_router();
+
+        let read_only = Body::from(r#"{"mode":"READ_ONLY","retryAfter":120}"#);
+        let response = request(
+            &app,
+            http::Method::PUT,
+            "/admin/maintenance",
+            &business,
+            read_only,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let read_only = Body::from(r#"{"mode":"READ_ONLY","retryAfter":120}"#);
+        let response = request(
+            &app,
+            http::Method::PUT,
+            "/admin/maintenance",
+            &admin,
+            read_only,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = request(
+            &app,
+            http::Method::GET,
+            "/programs",
+            &business,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = request(
+            &app,
+            http::Method::DELETE,
+            "/programs/program-1",
+            &business,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "120");
+
+        let off = Body::from(r#"{"mode":"OFF"}"#);
+        let response = request(&app, http::Method::PUT, "/admin/maintenance", &admin, off).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = request(
+            &app,
+            http::Method::DELETE,
+            "/programs/program-1",
+            &business,
+            Body::empty(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
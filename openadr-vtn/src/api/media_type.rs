@@ -0,0 +1,193 @@
+//! The media types of request and response bodies.
+//!
+//! Request bodies must be sent with the media type the endpoint expects, e.g., `application/json`.
+//! The only parameter accepted is `charset=utf-8`, as JSON is always UTF-8 encoded.
+//!
+//! The `Accept` header of a request is negotiated against the media types the API responds with,
+//! such that other formats, like NDJSON, can be offered next to JSON by adding them to [`PRODUCED`].
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use mime::Mime;
+
+use crate::error::AppError;
+
+/// The media types the API responds with, in order of preference
+pub const PRODUCED: &[&str] = &["application/json"];
+
+/// The media type of the response, as negotiated with the `Accept` header of the request.
+/// Inserted in the extensions of each request by [`negotiate_response_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseMediaType(pub &'static str);
+
+/// Check that the body of the request has the `expected` media type, e.g., `application/json`
+pub(crate) fn require_content_type(headers: &HeaderMap, expected: &str) -> Result<(), AppError> {
+    let unsupported = || {
+        AppError::UnsupportedMediaType(format!("Expected request with `Content-Type: {expected}`"))
+    };
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.parse::<Mime>().ok())
+        .ok_or_else(unsupported)?;
+
+    if content_type.essence_str() != expected {
+        return Err(unsupported());
+    }
+
+    for (name, value) in content_type.params() {
+        if name != mime::CHARSET {
+            return Err(AppError::UnsupportedMediaType(format!(
+                "Unsupported media type parameter `{name}`"
+            )));
+        }
+        if !value.as_str().eq_ignore_ascii_case("utf-8") {
+            return Err(AppError::UnsupportedMediaType(format!(
+                "Unsupported charset `{value}`, the body must be encoded as UTF-8"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// The most preferred of the `produced` media types that the `Accept` header allows, if any.
+///
+/// Each media type gets the quality of the most specific range matching it, e.g.,
+/// `application/json` gets `0.5` from `application/*;q=0.5, */*;q=0.1`.
+/// Without an `Accept` header, or without any valid media range in it, anything is accepted.
+pub(crate) fn negotiate(headers: &HeaderMap, produced: &[&'static str]) -> Option<&'static str> {
+    let ranges = headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|accept| accept.to_str().ok())
+        .flat_map(|accept| accept.split(','))
+        .filter_map(|range| range.trim().parse::<Mime>().ok())
+        .collect::<Vec<_>>();
+
+    if ranges.is_empty() {
+        return produced.first().copied();
+    }
+
+    let quality = |media_type: &str| {
+        let media_type = media_type.parse::<Mime>().ok()?;
+        ranges
+            .iter()
+            .filter_map(|range| {
+                let specificity = if range.essence_str() == media_type.essence_str() {
+                    2
+                } else if range.type_() == media_type.type_() && range.subtype() == mime::STAR {
+                    1
+                } else if range.type_() == mime::STAR && range.subtype() == mime::STAR {
+                    0
+                } else {
+                    return None;
+                };
+                let quality = range
+                    .get_param("q")
+                    .and_then(|q| q.as_str().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((specificity, quality))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality)
+    };
+
+    produced
+        .iter()
+        .filter_map(|media_type| Some((*media_type, quality(media_type)?)))
+        .filter(|(_, quality)| *quality > 0.0)
+        // the first of equally acceptable media types is preferred
+        .fold(
+            None,
+            |best: Option<(&'static str, f32)>, candidate| match best {
+                Some(best) if best.1 >= candidate.1 => Some(best),
+                _ => Some(candidate),
+            },
+        )
+        .map(|(media_type, _)| media_type)
+}
+
+/// Reject requests that accept none of the media types the API responds with,
+/// and let the handlers know the [`ResponseMediaType`] otherwise
+pub async fn negotiate_response_type(mut req: Request, next: Next) -> Result<Response, AppError> {
+    let Some(media_type) = negotiate(req.headers(), PRODUCED) else {
+        return Err(AppError::NotAcceptable(PRODUCED.join(", ")));
+    };
+
+    req.extensions_mut().insert(ResponseMediaType(media_type));
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn headers(name: header::HeaderName, value: &'static str) -> HeaderMap {
+        HeaderMap::from_iter([(name, HeaderValue::from_static(value))])
+    }
+
+    #[test]
+    fn content_type() {
+        let json = |content_type| {
+            require_content_type(
+                &headers(header::CONTENT_TYPE, content_type),
+                "application/json",
+            )
+        };
+
+        assert!(json("application/json").is_ok());
+        assert!(json("application/json; charset=utf-8").is_ok());
+        assert!(json("Application/JSON; charset=UTF-8").is_ok());
+
+        for invalid in [
+            "text/plain",
+            "application/problem+json",
+            "application/json; charset=iso-8859-1",
+            "application/json; profile=other",
+            "json",
+        ] {
+            assert!(
+                matches!(json(invalid), Err(AppError::UnsupportedMediaType(_))),
+                "{invalid}"
+            );
+        }
+        assert!(matches!(
+            require_content_type(&HeaderMap::new(), "application/json"),
+            Err(AppError::UnsupportedMediaType(_))
+        ));
+    }
+
+    #[test]
+    fn accept() {
+        const PRODUCED: &[&str] = &["application/json", "application/x-ndjson"];
+        let accept = |accept| negotiate(&headers(header::ACCEPT, accept), PRODUCED);
+
+        assert_eq!(
+            negotiate(&HeaderMap::new(), PRODUCED),
+            Some("application/json")
+        );
+        assert_eq!(accept("*/*"), Some("application/json"));
+        assert_eq!(accept("application/*"), Some("application/json"));
+        assert_eq!(accept("application/x-ndjson"), Some("application/x-ndjson"));
+        assert_eq!(
+            accept("application/json;q=0.5, application/x-ndjson"),
+            Some("application/x-ndjson")
+        );
+        // the most specific range determines the quality
+        assert_eq!(
+            accept("application/json;q=0, */*"),
+            Some("application/x-ndjson")
+        );
+        assert_eq!(accept("text/html"), None);
+        assert_eq!(accept("*/*;q=0"), None);
+        assert_eq!(accept("not a media type"), Some("application/json"));
+    }
+}
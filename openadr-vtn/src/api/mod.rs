@@ -5,22 +5,104 @@ use axum::{
         rejection::{FormRejection, JsonRejection},
         FromRequest, FromRequestParts, Request,
     },
+    response::{IntoResponse, Response},
     Form, Json,
 };
 use axum_extra::extract::{Query, QueryRejection};
-use serde::de::DeserializeOwned;
+use openadr_wire::TOTAL_COUNT_HEADER;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use std::time::Duration;
 use validator::Validate;
 
+#[cfg(feature = "admin-ui")]
+pub mod admin_ui;
 pub mod auth;
+pub mod capabilities;
+pub mod certification;
+pub mod change_log;
 pub mod event;
+pub mod jwt_keys;
+mod limited_json;
+mod list_params;
+pub mod maintenance;
+pub mod media_type;
+pub mod notifications;
 pub mod program;
 pub mod report;
 pub mod resource;
+pub mod search;
+pub mod stats;
 pub mod user;
 pub mod ven;
 
+pub use limited_json::{LimitedJson, ReportSizeLimit, DEFAULT_REPORT_SIZE_LIMIT};
+pub use list_params::{ActiveWindow, ListParams, NoExtension, PageSize, Sorting};
+
 pub type AppResponse<T> = Result<Json<T>, AppError>;
 
+pub type PageResponse<T> = Result<Page<T>, AppError>;
+
+/// A page of the objects matching a list query,
+/// sent together with the total number of matching objects in the [`TOTAL_COUNT_HEADER`]
+#[derive(Debug)]
+pub struct Page<T> {
+    pub objects: Vec<T>,
+    pub total: usize,
+}
+
+impl<T: Serialize> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        IntoResponse::into_response((
+            [(TOTAL_COUNT_HEADER, self.total.to_string())],
+            Json(self.objects),
+        ))
+    }
+}
+
+/// The default maximum `limit` accepted by the list endpoints, see [`PageSize`]
+pub const MAX_PAGE_SIZE: usize = 50;
+
+/// The longest time a long-polling request may wait for changes
+pub const MAX_WAIT: Duration = Duration::from_secs(60);
+
+/// The `wait` query parameter of a long-polling request, in seconds, e.g., `30s`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Wait(pub Duration);
+
+impl<'de> Deserialize<'de> for Wait {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let value = String::deserialize(deserializer)?;
+        let seconds: u64 = value
+            .strip_suffix('s')
+            .unwrap_or(&value)
+            .parse()
+            .map_err(|_| D::Error::custom("wait must be a number of seconds, e.g., 30s"))?;
+
+        let wait = Duration::from_secs(seconds);
+        if wait > MAX_WAIT {
+            return Err(D::Error::custom(format!(
+                "wait must not exceed {}s",
+                MAX_WAIT.as_secs()
+            )));
+        }
+
+        Ok(Wait(wait))
+    }
+}
+
+/// The `validateOnly` query parameter of the endpoints creating objects.
+///
+/// With `validateOnly=true`, the VTN runs all validation and permission checks,
+/// and responds with the object as it would be created, without storing it.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRun {
+    #[serde(default)]
+    pub validate_only: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct ValidatedForm<T>(T);
 
@@ -30,6 +112,21 @@ pub struct ValidatedQuery<T>(pub T);
 #[derive(Debug, Clone)]
 pub struct ValidatedJson<T>(pub T);
 
+impl<T: DeserializeOwned + Validate> ValidatedJson<T> {
+    /// Extract a body of a JSON based media type, e.g., the
+    /// [`EVENT_DELTA_CONTENT_TYPE`](openadr_wire::event::EVENT_DELTA_CONTENT_TYPE)
+    pub(crate) async fn from_request_as<S: Send + Sync>(
+        req: Request,
+        state: &S,
+        media_type: &str,
+    ) -> Result<Self, AppError> {
+        media_type::require_content_type(req.headers(), media_type)?;
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
 #[async_trait]
 impl<T, S> FromRequest<S> for ValidatedJson<T>
 where
@@ -40,9 +137,7 @@ where
     type Rejection = AppError;
 
     async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
-        let Json(value) = Json::<T>::from_request(req, state).await?;
-        value.validate()?;
-        Ok(ValidatedJson(value))
+        Self::from_request_as(req, state, mime::APPLICATION_JSON.as_ref()).await
     }
 }
 
@@ -86,7 +181,9 @@ where
 mod test {
     use crate::{
         data_source::PostgresStorage,
+        error::handle_panic,
         jwt::{AuthRole, JwtManager},
+        metrics::metrics,
         state::AppState,
     };
     use axum::{
@@ -96,9 +193,10 @@ mod test {
         response::Response,
     };
     use http_body_util::BodyExt;
-    use openadr_wire::problem::Problem;
+    use openadr_wire::{oauth::Scope, problem::Problem};
     use sqlx::PgPool;
     use tower::ServiceExt;
+    use tower_http::catch_panic::CatchPanicLayer;
 
     pub(crate) fn jwt_test_token(state: &AppState, roles: Vec<AuthRole>) -> String {
         state
@@ -142,7 +240,7 @@ mod test {
         assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
         into_problem(response).await;
 
-        let response = app
+        let response = (&mut app)
             .oneshot(
                 Request::builder()
                     .method(http::Method::POST)
@@ -155,6 +253,178 @@ mod test {
 
         assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
         into_problem(response).await;
+
+        let create = |content_type: &'static str| {
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/programs")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, content_type)
+                .body(Body::from(r#"{"programName": "program"}"#))
+                .unwrap()
+        };
+
+        for unsupported in [
+            "text/plain",
+            "application/json; charset=iso-8859-1",
+            "application/vnd.openadr+json",
+        ] {
+            let response = (&mut app).oneshot(create(unsupported)).await.unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "{unsupported}"
+            );
+            into_problem(response).await;
+        }
+
+        let response = (&mut app)
+            .oneshot(create("application/json; charset=utf-8"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[sqlx::test]
+    async fn not_acceptable(db: PgPool) {
+        let state = state(db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        let request = |accept: &'static str| {
+            Request::builder()
+                .method(http::Method::GET)
+                .uri("/programs")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::ACCEPT, accept)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        for accepted in [
+            "application/json",
+            "application/*",
+            "*/*",
+            "text/html, */*;q=0.1",
+        ] {
+            let response = (&mut app).oneshot(request(accepted)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{accepted}");
+        }
+
+        for not_accepted in ["text/html", "application/json;q=0"] {
+            let response = (&mut app).oneshot(request(not_accepted)).await.unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::NOT_ACCEPTABLE,
+                "{not_accepted}"
+            );
+            into_problem(response).await;
+        }
+    }
+
+    #[sqlx::test]
+    async fn unauthorized_vs_forbidden(db: PgPool) {
+        let state = state(db).await;
+        let ven_token = jwt_test_token(&state, vec![AuthRole::VEN("ven-1".parse().unwrap())]);
+        let app = state.into_router();
+
+        let request = |token: Option<&str>| {
+            let builder = Request::builder().method(http::Method::GET).uri("/users");
+            match token {
+                Some(token) => {
+                    builder.header(http::header::AUTHORIZATION, format!("Bearer {token}"))
+                }
+                None => builder,
+            }
+            .body(Body::empty())
+            .unwrap()
+        };
+
+        // no token
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[http::header::WWW_AUTHENTICATE],
+            r#"Bearer realm="VTN""#
+        );
+        into_problem(response).await;
+
+        // a token the VTN did not issue
+        let response = app.clone().oneshot(request(Some("invalid"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[http::header::WWW_AUTHENTICATE],
+            r#"Bearer realm="VTN", error="invalid_token""#
+        );
+        into_problem(response).await;
+
+        // a valid token without the required role
+        let response = app.oneshot(request(Some(&ven_token))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(!response
+            .headers()
+            .contains_key(http::header::WWW_AUTHENTICATE));
+        into_problem(response).await;
+    }
+
+    #[sqlx::test]
+    async fn insufficient_scope(db: PgPool) {
+        let state = state(db).await;
+        let token = state
+            .jwt_manager
+            .create_with_scopes(
+                std::time::Duration::from_secs(60),
+                "test_admin".to_string(),
+                vec![AuthRole::AnyBusiness, AuthRole::UserManager],
+                vec![Scope::ReadAll],
+            )
+            .unwrap();
+        let app = state.into_router();
+
+        let request = |method: http::Method, uri: &str, body: Body| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(http::header::AUTHORIZATION, format!("Bearer {token}"))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(body)
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request(http::Method::GET, "/programs", Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let program = serde_json::json!({"programName": "program"}).to_string();
+        let response = app
+            .clone()
+            .oneshot(request(
+                http::Method::POST,
+                "/programs",
+                Body::from(program),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers()[http::header::WWW_AUTHENTICATE],
+            r#"Bearer realm="VTN", error="insufficient_scope", scope="write_programs""#
+        );
+        into_problem(response).await;
+
+        // the endpoints of user managers require the admin scope
+        let response = app
+            .oneshot(request(http::Method::GET, "/users", Body::empty()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers()[http::header::WWW_AUTHENTICATE],
+            r#"Bearer realm="VTN", error="insufficient_scope", scope="admin""#
+        );
     }
 
     #[sqlx::test]
@@ -177,4 +447,33 @@ mod test {
 
         into_problem(response).await;
     }
+
+    async fn panicking_handler() -> StatusCode {
+        panic!("handler panicked on purpose")
+    }
+
+    #[tokio::test]
+    async fn handler_panic_becomes_problem() {
+        let app = axum::Router::new()
+            .route("/panic", axum::routing::get(panicking_handler))
+            .layer(CatchPanicLayer::custom(handle_panic));
+
+        let panics_before = metrics().handler_panics();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/panic")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let problem = into_problem(response).await;
+        assert_eq!(problem.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(metrics().handler_panics() > panics_before);
+    }
 }
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+
+use crate::{
+    api::AppResponse,
+    jwt::UserManagerUser,
+    notifier::{DeliveryState, Notifier},
+};
+
+/// The delivery state of each callback URL notified so far, none if notifications are disabled
+pub async fn get_all(
+    State(notifier): State<Option<Arc<Notifier>>>,
+    UserManagerUser(_): UserManagerUser,
+) -> AppResponse<Vec<DeliveryState>> {
+    Ok(Json(
+        notifier
+            .map(|notifier| notifier.deliveries())
+            .unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use crate::{
+        api::test::{jwt_test_token, state},
+        jwt::AuthRole,
+        notifier::{Notifier, RetryPolicy, StaticSubscriptions},
+    };
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use sqlx::PgPool;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn request(app: &Router, token: &str) -> (StatusCode, Vec<u8>) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/admin/notifications")
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, body.to_vec())
+    }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn user_managers_only(db: PgPool) {
+        let notifier = Notifier::spawn(
+            Arc::new(StaticSubscriptions::default()),
+            RetryPolicy::default(),
+        );
+        let state = state(db).await.with_notifier(notifier);
+        let admin = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let business = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let app = state.into_router();
+
+        let (status, _) = request(&app, &business).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = request(&app, &admin).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"[]");
+    }
+}
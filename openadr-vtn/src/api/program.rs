@@ -7,30 +7,61 @@ use axum::{
 use reqwest::StatusCode;
 use serde::Deserialize;
 use tracing::{info, trace};
-use validator::{Validate, ValidationError};
 
 use openadr_wire::{
-    program::{ProgramContent, ProgramId},
-    target::TargetLabel,
-    Program,
+    oauth::Scope,
+    program::{ProgramContent, ProgramId, ProgramVen},
+    ven::VenId,
+    Program, SortBy,
 };
 
 use crate::{
-    api::{AppResponse, ValidatedJson, ValidatedQuery},
-    data_source::ProgramCrud,
+    api::{
+        ActiveWindow, AppResponse, DryRun, ListParams, Page, PageResponse, Sorting, ValidatedJson,
+        ValidatedQuery,
+    },
+    change_log::{self, ChangeLog, ObjectType, Operation},
+    data_source::{DataSource, ProgramCrud},
     error::AppError,
     jwt::{BusinessUser, User},
+    notifier::{self, Notifier},
+    target_labels::TargetLabelRegistry,
 };
+
+/// The query parameters of `GET /programs`
+pub type QueryParams = ListParams<ProgramListParams>;
+
+/// The parameters of `GET /programs` besides the common [`ListParams`]
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct ProgramListParams {
+    /// Only list the programs with an interval period within `activeAfter..activeBefore`
+    #[serde(flatten)]
+    pub(crate) active: ActiveWindow,
+    #[serde(flatten)]
+    pub(crate) sort: Sorting,
+}
+
 pub async fn get_all(
     State(program_source): State<Arc<dyn ProgramCrud>>,
-    ValidatedQuery(query_params): ValidatedQuery<QueryParams>,
+    query_params: QueryParams,
     User(user): User,
-) -> AppResponse<Vec<Program>> {
+) -> PageResponse<Program> {
+    user.require_scope(Scope::ReadAll)?;
     trace!(?query_params);
+    query_params.extension.sort.check(&[
+        SortBy::Created,
+        SortBy::Modified,
+        SortBy::Priority,
+        SortBy::Name,
+    ])?;
 
     let programs = program_source.retrieve_all(&query_params, &user).await?;
+    let total = program_source.count(&query_params, &user).await?;
 
-    Ok(Json(programs))
+    Ok(Page {
+        objects: programs,
+        total,
+    })
 }
 
 pub async fn get(
@@ -38,27 +69,68 @@ pub async fn get(
     Path(id): Path<ProgramId>,
     User(user): User,
 ) -> AppResponse<Program> {
+    user.require_scope(Scope::ReadAll)?;
     let program = program_source.retrieve(&id, &user).await?;
     Ok(Json(program))
 }
 
 pub async fn add(
-    State(program_source): State<Arc<dyn ProgramCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
     BusinessUser(user): BusinessUser,
+    ValidatedQuery(dry_run): ValidatedQuery<DryRun>,
     ValidatedJson(new_program): ValidatedJson<ProgramContent>,
 ) -> Result<(StatusCode, Json<Program>), AppError> {
-    let program = program_source.create(new_program, &user).await?;
+    user.require_scope(Scope::WritePrograms)?;
+    target_labels.validate_target_map(new_program.targets.as_ref())?;
+
+    if dry_run.validate_only {
+        let program = storage
+            .programs()
+            .validate_create(new_program, &user)
+            .await?;
+        return Ok((StatusCode::OK, Json(program)));
+    }
+
+    let program = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Program,
+        Operation::Create,
+        &user,
+        |program: &Program| program.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.programs().create(new_program, user).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Create, &program);
 
     Ok((StatusCode::CREATED, Json(program)))
 }
 
 pub async fn edit(
-    State(program_source): State<Arc<dyn ProgramCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
     Path(id): Path<ProgramId>,
     BusinessUser(user): BusinessUser,
     ValidatedJson(content): ValidatedJson<ProgramContent>,
 ) -> AppResponse<Program> {
-    let program = program_source.update(&id, content, &user).await?;
+    user.require_scope(Scope::WritePrograms)?;
+    target_labels.validate_target_map(content.targets.as_ref())?;
+    let program = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Program,
+        Operation::Update,
+        &user,
+        |program: &Program| program.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.programs().update(&id, content, user).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Update, &program);
 
     info!(%program.id, program.program_name=program.content.program_name, "program updated");
 
@@ -66,40 +138,106 @@ pub async fn edit(
 }
 
 pub async fn delete(
-    State(program_source): State<Arc<dyn ProgramCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
     Path(id): Path<ProgramId>,
     BusinessUser(user): BusinessUser,
 ) -> AppResponse<Program> {
-    let program = program_source.delete(&id, &user).await?;
+    user.require_scope(Scope::WritePrograms)?;
+    let program = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Program,
+        Operation::Delete,
+        &user,
+        |program: &Program| program.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.programs().delete(&id, user).await }),
+    )
+    .await?;
+    let id = &program.id;
+    notifier::send(notifier.as_deref(), Operation::Delete, &program);
     info!(%id, "deleted program");
     Ok(Json(program))
 }
 
-#[derive(Deserialize, Validate, Debug)]
-#[validate(schema(function = "validate_target_type_value_pair"))]
-#[serde(rename_all = "camelCase")]
-pub struct QueryParams {
-    pub(crate) target_type: Option<TargetLabel>,
-    pub(crate) target_values: Option<Vec<String>>,
-    #[serde(default)]
-    #[validate(range(min = 0))]
-    pub(crate) skip: i64,
-    // TODO how to interpret limit = 0 and what is the default?
-    #[validate(range(min = 1, max = 50))]
-    #[serde(default = "get_50")]
-    pub(crate) limit: i64,
+pub async fn get_vens(
+    State(program_source): State<Arc<dyn ProgramCrud>>,
+    Path(id): Path<ProgramId>,
+    BusinessUser(user): BusinessUser,
+) -> AppResponse<Vec<ProgramVen>> {
+    user.require_scope(Scope::ReadAll)?;
+    let vens = program_source.vens(&id, &user).await?;
+    Ok(Json(
+        vens.into_iter()
+            .map(|ven_id| ProgramVen { ven_id })
+            .collect(),
+    ))
 }
 
-fn validate_target_type_value_pair(query: &QueryParams) -> Result<(), ValidationError> {
-    if query.target_type.is_some() == query.target_values.is_some() {
-        Ok(())
-    } else {
-        Err(ValidationError::new("targetType and targetValues query parameter must either both be set or not set at the same time."))
-    }
+pub async fn assign_ven(
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    Path(id): Path<ProgramId>,
+    BusinessUser(user): BusinessUser,
+    ValidatedJson(program_ven): ValidatedJson<ProgramVen>,
+) -> Result<(StatusCode, Json<ProgramVen>), AppError> {
+    user.require_scope(Scope::WritePrograms)?;
+    let object_id = format!("{id}/{}", program_ven.ven_id);
+    let program_id = id.clone();
+    let program_ven = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::ProgramVen,
+        Operation::Create,
+        &user,
+        move |_| object_id,
+        move |tx, user| {
+            Box::pin(async move {
+                tx.programs()
+                    .assign_ven(&program_id, &program_ven.ven_id, user)
+                    .await?;
+                Ok(program_ven)
+            })
+        },
+    )
+    .await?;
+
+    info!(%id, ven_id=%program_ven.ven_id, "assigned VEN to program");
+
+    Ok((StatusCode::CREATED, Json(program_ven)))
 }
 
-fn get_50() -> i64 {
-    50
+pub async fn unassign_ven(
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    Path((id, ven_id)): Path<(ProgramId, VenId)>,
+    BusinessUser(user): BusinessUser,
+) -> AppResponse<ProgramVen> {
+    user.require_scope(Scope::WritePrograms)?;
+    let object_id = format!("{id}/{ven_id}");
+    let program_id = id.clone();
+    let program_ven = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::ProgramVen,
+        Operation::Delete,
+        &user,
+        move |_| object_id,
+        move |tx, user| {
+            Box::pin(async move {
+                tx.programs()
+                    .unassign_ven(&program_id, &ven_id, user)
+                    .await?;
+                Ok(ProgramVen { ven_id })
+            })
+        },
+    )
+    .await?;
+
+    info!(%id, ven_id=%program_ven.ven_id, "unassigned VEN from program");
+
+    Ok(Json(program_ven))
 }
 
 #[cfg(test)]
@@ -121,7 +259,11 @@ mod test {
         Router,
     };
     use http_body_util::BodyExt;
-    use openadr_wire::Event;
+    use openadr_wire::{
+        problem::Problem,
+        target::{TargetEntry, TargetLabel, TargetMap},
+        Event,
+    };
     use sqlx::PgPool;
     use tower::{Service, ServiceExt};
     // for `call`, `oneshot`, and `ready`
@@ -142,6 +284,7 @@ mod test {
             binding_events: None,
             local_price: None,
             payload_descriptors: None,
+            default_priority: None,
             targets: None,
         }
     }
@@ -343,6 +486,35 @@ mod test {
             .unwrap()
     }
 
+    #[sqlx::test(fixtures("users"))]
+    async fn create_with_disallowed_target_label(db: PgPool) {
+        let (state, _) = state_with_programs(vec![], db).await;
+        let state = state.with_target_labels(TargetLabelRegistry::allow_only(["METER_ID"]));
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        let target = |label: &str| {
+            Some(TargetMap(vec![TargetEntry {
+                label: TargetLabel::Private(label.to_string()),
+                values: vec!["value".to_string()],
+            }]))
+        };
+
+        let program = ProgramContent {
+            targets: target("FEEDER"),
+            ..default_content()
+        };
+        let response = help_create_program(&mut app, &token, &program).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let program = ProgramContent {
+            targets: target("METER_ID"),
+            ..default_content()
+        };
+        let response = help_create_program(&mut app, &token, &program).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
     #[sqlx::test(fixtures("users"))]
     async fn create_same_name(db: PgPool) {
         let (state, _) = state_with_programs(vec![], db).await;
@@ -351,8 +523,60 @@ mod test {
 
         let response = help_create_program(&mut app, &token, &default_content()).await;
         assert_eq!(response.status(), StatusCode::CREATED);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let existing: Program = serde_json::from_slice(&body).unwrap();
+
+        let response = help_create_program(&mut app, &token, &default_content()).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let problem: Problem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(problem.extensions["conflictingId"], existing.id.as_str());
+        assert_eq!(
+            problem.extensions["conflictingModificationDateTime"],
+            existing.modification_date_time.to_rfc3339()
+        );
+    }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn create_validate_only(db: PgPool) {
+        let (state, _) = state_with_programs(vec![], db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let mut app = state.into_router();
+
+        let validate_only = |program: &ProgramContent| {
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/programs?validateOnly=true")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(serde_json::to_vec(program).unwrap()))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(validate_only(&default_content()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let program: Program = serde_json::from_slice(&body).unwrap();
+        assert_eq!(program.content, default_content());
+
+        // nothing was stored
+        let response = retrieve_all_with_filter_help(&mut app, "", &token).await;
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let programs: Vec<Program> = serde_json::from_slice(&body).unwrap();
+        assert!(programs.is_empty());
 
         let response = help_create_program(&mut app, &token, &default_content()).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let response = app
+            .clone()
+            .oneshot(validate_only(&default_content()))
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::CONFLICT);
     }
 
@@ -474,6 +698,112 @@ mod test {
         assert_eq!(programs.len(), 2);
     }
 
+    fn program_vens_request(
+        method: http::Method,
+        uri: &str,
+        body: Option<&ProgramVen>,
+        token: &str,
+    ) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(match body {
+                Some(body) => Body::from(serde_json::to_vec(body).unwrap()),
+                None => Body::empty(),
+            })
+            .unwrap()
+    }
+
+    #[sqlx::test(fixtures("users", "programs", "vens"))]
+    async fn assign_vens(db: PgPool) {
+        let (state, _) = state_with_programs(vec![], db).await;
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let ven_token = jwt_test_token(&state, vec![AuthRole::VEN("ven-1".parse().unwrap())]);
+        let other_ven_token = jwt_test_token(&state, vec![AuthRole::VEN("ven-2".parse().unwrap())]);
+        let app = state.into_router();
+
+        let program_ven = ProgramVen {
+            ven_id: "ven-1".parse().unwrap(),
+        };
+        let assign = || {
+            program_vens_request(
+                http::Method::POST,
+                "/programs/program-1/vens",
+                Some(&program_ven),
+                &token,
+            )
+        };
+
+        let response = app.clone().oneshot(assign()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = app.clone().oneshot(assign()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let unknown = ProgramVen {
+            ven_id: "unknown".parse().unwrap(),
+        };
+        let response = app
+            .clone()
+            .oneshot(program_vens_request(
+                http::Method::POST,
+                "/programs/program-1/vens",
+                Some(&unknown),
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(program_vens_request(
+                http::Method::GET,
+                "/programs/program-1/vens",
+                None,
+                &token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let vens: Vec<ProgramVen> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(vens, vec![program_ven.clone()]);
+
+        // only the assigned VENs can read the program
+        let response = get_help(&mut app.clone(), &ven_token, "program-1").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = get_help(&mut app.clone(), &other_ven_token, "program-1").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let unassign = || {
+            program_vens_request(
+                http::Method::DELETE,
+                "/programs/program-1/vens/ven-1",
+                None,
+                &token,
+            )
+        };
+        let response = app.clone().oneshot(unassign()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(unassign()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // VENs cannot manage the VENs of a program
+        let response = app
+            .clone()
+            .oneshot(program_vens_request(
+                http::Method::POST,
+                "/programs/program-1/vens",
+                Some(&program_ven),
+                &ven_token,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
     mod permissions {
         use super::*;
         use openadr_wire::target::{TargetEntry, TargetMap};
@@ -547,7 +877,7 @@ mod test {
             let content = ProgramContent {
                 targets: Some(TargetMap(vec![TargetEntry {
                     label: TargetLabel::VENName,
-                    values: ["ven-1-name".to_string()],
+                    values: vec!["ven-1-name".to_string()],
                 }])),
                 ..default_content()
             };
@@ -5,33 +5,71 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use tracing::{info, instrument};
-use validator::Validate;
 
 use openadr_wire::{
     event::EventId,
+    oauth::Scope,
     program::ProgramId,
-    report::{ReportContent, ReportId},
-    Report,
+    report::{ReportContent, ReportId, ResourceOperatingState},
+    Report, SortBy,
 };
 
 use crate::{
-    api::{AppResponse, ValidatedJson, ValidatedQuery},
-    data_source::ReportCrud,
+    api::{
+        AppResponse, DryRun, LimitedJson, ListParams, Page, PageResponse, Sorting, ValidatedQuery,
+    },
+    change_log::{self, ChangeLog, ObjectType, Operation},
+    data_source::{DataSource, ReportCrud},
     error::AppError,
     jwt::{BusinessUser, User, VENUser},
+    notifier::{self, Notifier},
+    report_quota::ReportQuotas,
 };
 
 #[instrument(skip(user, report_source))]
 pub async fn get_all(
     State(report_source): State<Arc<dyn ReportCrud>>,
-    ValidatedQuery(query_params): ValidatedQuery<QueryParams>,
+    query_params: QueryParams,
     User(user): User,
-) -> AppResponse<Vec<Report>> {
+) -> PageResponse<Report> {
+    user.require_scope(Scope::ReadAll)?;
+    if query_params.target_type.is_some() {
+        return Err(AppError::BadRequest("reports cannot be filtered by target"));
+    }
+    query_params
+        .extension
+        .sort
+        .check(&[SortBy::Created, SortBy::Modified, SortBy::Name])?;
+
     let reports = report_source.retrieve_all(&query_params, &user).await?;
+    let total = report_source.count(&query_params, &user).await?;
+
+    Ok(Page {
+        objects: reports,
+        total,
+    })
+}
+
+/// The latest operating state of each resource, e.g., for fleet dashboards,
+/// such that clients do not have to retrieve and search all reports
+#[instrument(skip(user, report_source))]
+pub async fn operating_states(
+    State(report_source): State<Arc<dyn ReportCrud>>,
+    query_params: QueryParams,
+    User(user): User,
+) -> AppResponse<Vec<ResourceOperatingState>> {
+    user.require_scope(Scope::ReadAll)?;
+    if query_params.target_type.is_some() {
+        return Err(AppError::BadRequest("reports cannot be filtered by target"));
+    }
 
-    Ok(Json(reports))
+    Ok(Json(
+        report_source
+            .latest_operating_states(&query_params, &user)
+            .await?,
+    ))
 }
 
 #[instrument(skip(user, report_source))]
@@ -40,65 +78,119 @@ pub async fn get(
     Path(id): Path<ReportId>,
     User(user): User,
 ) -> AppResponse<Report> {
+    user.require_scope(Scope::ReadAll)?;
     let report: Report = report_source.retrieve(&id, &user).await?;
     Ok(Json(report))
 }
 
-#[instrument(skip(user, report_source))]
+#[instrument(skip(user, storage, report_quotas, change_log))]
 pub async fn add(
-    State(report_source): State<Arc<dyn ReportCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(report_quotas): State<Arc<ReportQuotas>>,
     VENUser(user): VENUser,
-    ValidatedJson(new_report): ValidatedJson<ReportContent>,
+    ValidatedQuery(dry_run): ValidatedQuery<DryRun>,
+    LimitedJson(new_report): LimitedJson<ReportContent>,
 ) -> Result<(StatusCode, Json<Report>), AppError> {
-    let report = report_source.create(new_report, &user).await?;
+    user.require_scope(Scope::WriteReports)?;
+    report_quotas.check_intervals(&new_report)?;
+
+    // a dry run does not count towards the hourly quota
+    if dry_run.validate_only {
+        let report = storage.reports().validate_create(new_report, &user).await?;
+        return Ok((StatusCode::OK, Json(report)));
+    }
+
+    report_quotas.register_report(&user.ven_ids(), &new_report)?;
+
+    let report = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Report,
+        Operation::Create,
+        &user,
+        |report: &Report| report.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.reports().create(new_report, user).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Create, &report);
 
     info!(%report.id, report_name=?report.content.report_name, "report created");
 
     Ok((StatusCode::CREATED, Json(report)))
 }
 
-#[instrument(skip(user, report_source))]
+#[instrument(skip(user, storage, report_quotas, change_log))]
 pub async fn edit(
-    State(report_source): State<Arc<dyn ReportCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(report_quotas): State<Arc<ReportQuotas>>,
     Path(id): Path<ReportId>,
     VENUser(user): VENUser,
-    ValidatedJson(content): ValidatedJson<ReportContent>,
+    LimitedJson(content): LimitedJson<ReportContent>,
 ) -> AppResponse<Report> {
-    let report = report_source.update(&id, content, &user).await?;
+    user.require_scope(Scope::WriteReports)?;
+    report_quotas.check_intervals(&content)?;
+
+    let report = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Report,
+        Operation::Update,
+        &user,
+        |report: &Report| report.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.reports().update(&id, content, user).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Update, &report);
 
     info!(%report.id, report_name=?report.content.report_name, "report updated");
 
     Ok(Json(report))
 }
 
-#[instrument(skip(user, report_source))]
+#[instrument(skip(user, storage, change_log))]
 pub async fn delete(
-    State(report_source): State<Arc<dyn ReportCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
     // TODO this contradicts the spec, which says that only VENs have write access
     BusinessUser(user): BusinessUser,
     Path(id): Path<ReportId>,
 ) -> AppResponse<Report> {
-    let report = report_source.delete(&id, &user).await?;
+    user.require_scope(Scope::WriteReports)?;
+    let report = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Report,
+        Operation::Delete,
+        &user,
+        |report: &Report| report.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.reports().delete(&id, user).await }),
+    )
+    .await?;
+    let id = &report.id;
+    notifier::send(notifier.as_deref(), Operation::Delete, &report);
     info!(%id, "deleted report");
     Ok(Json(report))
 }
 
-#[derive(Serialize, Deserialize, Validate, Debug)]
+/// The query parameters of `GET /reports`.
+/// Reports have no targets, so the `targetType` and `targetValues` are rejected.
+pub type QueryParams = ListParams<ReportListParams>;
+
+/// The parameters of `GET /reports` besides the common [`ListParams`]
+#[derive(Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
-pub struct QueryParams {
+pub struct ReportListParams {
     #[serde(rename = "programID")]
     pub(crate) program_id: Option<ProgramId>,
     #[serde(rename = "eventID")]
     pub(crate) event_id: Option<EventId>,
     pub(crate) client_name: Option<String>,
-    #[serde(default)]
-    pub(crate) skip: i64,
-    // TODO how to interpret limit = 0 and what is the default?
-    #[validate(range(max = 50))]
-    #[serde(default = "get_50")]
-    pub(crate) limit: i64,
-}
-
-fn get_50() -> i64 {
-    50
+    /// Reports have no priority, so they cannot be sorted by it
+    #[serde(flatten)]
+    pub(crate) sort: Sorting,
 }
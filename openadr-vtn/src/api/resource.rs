@@ -6,20 +6,22 @@ use axum::{
 };
 use openadr_wire::ven::VenId;
 use reqwest::StatusCode;
-use serde::Deserialize;
 use tracing::{info, trace};
-use validator::{Validate, ValidationError};
 
 use openadr_wire::{
+    oauth::Scope,
+    report::LatestReportPayload,
     resource::{Resource, ResourceContent, ResourceId},
-    target::TargetLabel,
 };
 
 use crate::{
-    api::{AppResponse, ValidatedJson, ValidatedQuery},
-    data_source::ResourceCrud,
+    api::{AppResponse, ListParams, Page, PageResponse, ValidatedJson},
+    change_log::{self, ChangeLog, ObjectType, Operation},
+    data_source::{DataSource, ReportCrud, ResourceCrud, VenCrud, VenPermissions},
     error::AppError,
     jwt::{Claims, User},
+    notifier::{self, Notifier},
+    target_labels::TargetLabelRegistry,
 };
 
 fn has_write_permission(user_claims: &Claims, ven_id: &VenId) -> Result<(), AppError> {
@@ -39,17 +41,22 @@ fn has_write_permission(user_claims: &Claims, ven_id: &VenId) -> Result<(), AppE
 pub async fn get_all(
     State(resource_source): State<Arc<dyn ResourceCrud>>,
     Path(ven_id): Path<VenId>,
-    ValidatedQuery(query_params): ValidatedQuery<QueryParams>,
+    query_params: ListParams,
     User(user): User,
-) -> AppResponse<Vec<Resource>> {
+) -> PageResponse<Resource> {
+    user.require_scope(Scope::ReadAll)?;
     has_write_permission(&user, &ven_id)?;
     trace!(?query_params);
 
     let resources = resource_source
-        .retrieve_all(ven_id, &query_params, &user)
+        .retrieve_all(ven_id.clone(), &query_params, &user)
         .await?;
+    let total = resource_source.count(ven_id, &query_params, &user).await?;
 
-    Ok(Json(resources))
+    Ok(Page {
+        objects: resources,
+        total,
+    })
 }
 
 pub async fn get(
@@ -57,32 +64,93 @@ pub async fn get(
     Path((ven_id, id)): Path<(VenId, ResourceId)>,
     User(user): User,
 ) -> AppResponse<Resource> {
+    user.require_scope(Scope::ReadAll)?;
     has_write_permission(&user, &ven_id)?;
     let ven = resource_source.retrieve(&id, ven_id, &user).await?;
 
     Ok(Json(ven))
 }
 
-pub async fn add(
+/// The payload of each type that the VEN reported last for the resource,
+/// such that clients do not have to retrieve and sort all reports.
+///
+/// Reports refer to resources by name, and are matched by the name of the VEN as client name.
+pub async fn latest_reports(
     State(resource_source): State<Arc<dyn ResourceCrud>>,
+    State(ven_source): State<Arc<dyn VenCrud>>,
+    State(report_source): State<Arc<dyn ReportCrud>>,
+    Path((ven_id, id)): Path<(VenId, ResourceId)>,
+    User(user): User,
+) -> AppResponse<Vec<LatestReportPayload>> {
+    user.require_scope(Scope::ReadAll)?;
+    has_write_permission(&user, &ven_id)?;
+    let resource = resource_source.retrieve(&id, ven_id.clone(), &user).await?;
+    // the permission of the user for this VEN was checked above
+    let ven = ven_source
+        .retrieve(&ven_id, &VenPermissions::AllAllowed)
+        .await?;
+
+    let payloads = report_source
+        .latest_payloads(&ven.content.ven_name, &resource.content.resource_name)
+        .await?;
+
+    Ok(Json(payloads))
+}
+
+pub async fn add(
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
     User(user): User,
     Path(ven_id): Path<VenId>,
     ValidatedJson(new_resource): ValidatedJson<ResourceContent>,
 ) -> Result<(StatusCode, Json<Resource>), AppError> {
+    user.require_scope(Scope::WriteVens)?;
     has_write_permission(&user, &ven_id)?;
-    let ven = resource_source.create(new_resource, ven_id, &user).await?;
+    target_labels.validate_values_maps(new_resource.targets.as_deref())?;
+    let resource = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Resource,
+        Operation::Create,
+        &user,
+        |resource: &Resource| resource.id.to_string(),
+        move |tx, user| {
+            Box::pin(async move { tx.resources().create(new_resource, ven_id, user).await })
+        },
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Create, &resource);
 
-    Ok((StatusCode::CREATED, Json(ven)))
+    Ok((StatusCode::CREATED, Json(resource)))
 }
 
 pub async fn edit(
-    State(resource_source): State<Arc<dyn ResourceCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
     Path((ven_id, id)): Path<(VenId, ResourceId)>,
     User(user): User,
     ValidatedJson(content): ValidatedJson<ResourceContent>,
 ) -> AppResponse<Resource> {
+    user.require_scope(Scope::WriteVens)?;
     has_write_permission(&user, &ven_id)?;
-    let resource = resource_source.update(&id, ven_id, content, &user).await?;
+    target_labels.validate_values_maps(content.targets.as_deref())?;
+    let resource = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Resource,
+        Operation::Update,
+        &user,
+        |resource: &Resource| resource.id.to_string(),
+        move |tx, user| {
+            Box::pin(async move { tx.resources().update(&id, ven_id, content, user).await })
+        },
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Update, &resource);
 
     info!(%resource.id, resource.resource_name=resource.content.resource_name, "resource updated");
 
@@ -90,38 +158,211 @@ pub async fn edit(
 }
 
 pub async fn delete(
-    State(resource_source): State<Arc<dyn ResourceCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
     Path((ven_id, id)): Path<(VenId, ResourceId)>,
     User(user): User,
 ) -> AppResponse<Resource> {
+    user.require_scope(Scope::WriteVens)?;
     has_write_permission(&user, &ven_id)?;
-    let resource = resource_source.delete(&id, ven_id, &user).await?;
+    let resource = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Resource,
+        Operation::Delete,
+        &user,
+        |resource: &Resource| resource.id.to_string(),
+        move |tx, user| Box::pin(async move { tx.resources().delete(&id, ven_id, user).await }),
+    )
+    .await?;
+    let id = &resource.id;
+    notifier::send(notifier.as_deref(), Operation::Delete, &resource);
     info!(%id, "deleted resource");
     Ok(Json(resource))
 }
 
-#[derive(Deserialize, Validate, Debug)]
-#[validate(schema(function = "validate_target_type_value_pair"))]
-#[serde(rename_all = "camelCase")]
-pub struct QueryParams {
-    pub(crate) target_type: Option<TargetLabel>,
-    pub(crate) target_values: Option<Vec<String>>,
-    #[serde(default)]
-    #[validate(range(min = 0))]
-    pub(crate) skip: i64,
-    #[validate(range(min = 1, max = 50))]
-    #[serde(default = "get_50")]
-    pub(crate) limit: i64,
-}
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{self, Request, Response},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use openadr_wire::{
+        report::{
+            LatestReportPayload, ReportContent, ReportInterval, ReportResource, ReportType,
+            ReportValuesMap, ResourceName,
+        },
+        resource::Resource,
+        values_map::Value,
+        OperatingState,
+    };
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use crate::{
+        api::test::jwt_test_token,
+        data_source::PostgresStorage,
+        jwt::{AuthRole, Claims, JwtManager},
+        state::AppState,
+    };
 
-fn validate_target_type_value_pair(query: &QueryParams) -> Result<(), ValidationError> {
-    if query.target_type.is_some() == query.target_values.is_some() {
-        Ok(())
-    } else {
-        Err(ValidationError::new("targetType and targetValues query parameter must either both be set or not set at the same time."))
+    async fn request_all(app: Router, ven_id: &str, query: &str, token: &str) -> Response<Body> {
+        app.oneshot(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/vens/{ven_id}/resources?{query}"))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test(fixtures("users", "vens", "resources"))]
+    async fn get_all_filtered(db: PgPool) {
+        let state = AppState::new(
+            PostgresStorage::new(db).unwrap(),
+            JwtManager::from_base64_secret("test").unwrap(),
+        );
+        let token = jwt_test_token(&state, vec![AuthRole::VenManager]);
+        let app = state.into_router();
+
+        for (query, expected) in [
+            ("", 3),
+            ("targetType=VEN_NAME&targetValues=ven-2-name", 3),
+            ("targetType=VEN_NAME&targetValues=ven-1-name", 0),
+            ("targetType=RESOURCE_NAME&targetValues=resource-4-name", 1),
+            ("targetType=GROUP&targetValues=group-1", 0),
+            ("limit=2", 2),
+        ] {
+            let resp = request_all(app.clone(), "ven-2", query, &token).await;
+            assert_eq!(resp.status(), http::StatusCode::OK, "{query}");
+            let body = resp.into_body().collect().await.unwrap().to_bytes();
+            let resources: Vec<Resource> = serde_json::from_slice(&body).unwrap();
+            assert_eq!(resources.len(), expected, "{query}");
+        }
+
+        for query in [
+            "targetType=GROUP",
+            "targetValues=group-1",
+            "limit=51",
+            "skip=-1",
+        ] {
+            let resp = request_all(app.clone(), "ven-2", query, &token).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+
+    fn report(
+        client_name: &str,
+        resource_name: &str,
+        intervals: Vec<ReportInterval>,
+    ) -> ReportContent {
+        ReportContent {
+            object_type: None,
+            program_id: "program-1".parse().unwrap(),
+            event_id: "event-1".parse().unwrap(),
+            client_name: client_name.to_string(),
+            report_name: None,
+            payload_descriptors: None,
+            resources: vec![ReportResource {
+                resource_name: ResourceName::Private(resource_name.to_string()),
+                interval_period: None,
+                intervals,
+            }],
+        }
     }
-}
 
-fn get_50() -> i64 {
-    50
+    fn usage(id: i32, usage: f64) -> ReportInterval {
+        ReportInterval::new(
+            id,
+            vec![ReportValuesMap::new(
+                ReportType::Usage,
+                vec![Value::Number(usage)],
+            )],
+        )
+    }
+
+    #[sqlx::test(fixtures("users", "programs", "events", "vens", "resources"))]
+    async fn latest_reports(db: PgPool) {
+        let state = AppState::new(
+            PostgresStorage::new(db).unwrap(),
+            JwtManager::from_base64_secret("test").unwrap(),
+        );
+        let token = jwt_test_token(&state, vec![AuthRole::VenManager]);
+
+        let reports = state.storage.reports();
+        let user = Claims::any_business_user();
+        reports
+            .create(
+                report(
+                    "ven-1-name",
+                    "resource-1-name",
+                    vec![usage(0, 1.0), usage(1, 2.0)],
+                ),
+                &user,
+            )
+            .await
+            .unwrap();
+        let mut last = report("ven-1-name", "resource-1-name", vec![usage(0, 3.0)]);
+        last.resources[0].intervals[0]
+            .payloads
+            .push(ReportValuesMap::operating_state(
+                OperatingState::RunningNormal,
+            ));
+        let last = reports.create(last, &user).await.unwrap();
+        // reports of other clients and resources are ignored
+        for (client_name, resource_name) in [
+            ("ven-2-name", "resource-1-name"),
+            ("ven-1-name", "resource-3-name"),
+        ] {
+            reports
+                .create(
+                    report(client_name, resource_name, vec![usage(0, 4.0)]),
+                    &user,
+                )
+                .await
+                .unwrap();
+        }
+
+        let app = state.into_router();
+        let resp = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri("/vens/ven-1/resources/resource-1/reports/latest")
+                    .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        let mut payloads: Vec<LatestReportPayload> = serde_json::from_slice(&body).unwrap();
+        payloads.sort_by_key(|payload| payload.payload_type == ReportType::Usage);
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(
+            payloads[0].values_map(),
+            ReportValuesMap::operating_state(OperatingState::RunningNormal)
+        );
+        assert_eq!(payloads[1].values, vec![Value::Number(3.0)]);
+        assert_eq!(payloads[1].report_id, last.id);
+        assert_eq!(payloads[1].interval_id, 0);
+
+        // the payloads of a deleted report are replaced by those of the previous report
+        reports.delete(&last.id, &user).await.unwrap();
+        let payloads = reports
+            .latest_payloads("ven-1-name", "resource-1-name")
+            .await
+            .unwrap();
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0].values, vec![Value::Number(2.0)]);
+        assert_eq!(payloads[0].interval_id, 1);
+    }
 }
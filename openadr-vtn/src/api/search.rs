@@ -0,0 +1,367 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use tracing::trace;
+use validator::Validate;
+
+use openadr_wire::{
+    event::EventType,
+    oauth::Scope,
+    resource::Resource,
+    search::{SearchHit, SearchObjectType},
+    values_map::{Value, ValuesMap},
+    Event, Program, Ven,
+};
+
+use crate::{
+    api::{AppResponse, ListParams, ValidatedQuery},
+    data_source::{Crud, EventCrud, ProgramCrud, VenCrud, VenPermissions},
+    error::AppError,
+    jwt::User,
+};
+
+#[derive(Deserialize, Validate, Debug)]
+pub struct SearchParams {
+    /// Matched case-insensitively against the names and descriptions of all objects
+    #[validate(length(min = 1, max = 128))]
+    q: String,
+    /// The maximum number of hits
+    #[validate(range(min = 1, max = 200))]
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+/// Search the programs, events, VENs and resources the user has access to by name and description.
+/// Programs are described by the URLs of their program descriptions, events by the string values
+/// of their `DESCRIPTION` payloads, and VENs and resources by their `DESCRIPTION` attributes.
+///
+/// Each object type is retrieved through its regular list query with the [`ListParams::search`],
+/// so the same permissions apply as on the list endpoints. VENs and their resources are only
+/// searched for VEN managers and VENs. The search stops at the `limit` of hits.
+pub async fn search(
+    State(program_source): State<Arc<dyn ProgramCrud>>,
+    State(event_source): State<Arc<dyn EventCrud>>,
+    State(ven_source): State<Arc<dyn VenCrud>>,
+    ValidatedQuery(query_params): ValidatedQuery<SearchParams>,
+    User(user): User,
+) -> AppResponse<Vec<SearchHit>> {
+    user.require_scope(Scope::ReadAll)?;
+    trace!(?query_params);
+
+    let q = &query_params.q;
+    let mut hits = Hits::new(query_params.limit);
+
+    for program in find(program_source.as_ref(), &user, q, &hits).await? {
+        hits.push(SearchHit {
+            object_type: SearchObjectType::Program,
+            id: program.id.to_string(),
+            name: Some(program.content.program_name),
+            ven_id: None,
+            program_id: None,
+        });
+    }
+
+    for event in find(event_source.as_ref(), &user, q, &hits).await? {
+        hits.push(SearchHit {
+            object_type: SearchObjectType::Event,
+            id: event.id.to_string(),
+            name: event.content.event_name,
+            ven_id: None,
+            program_id: Some(event.content.program_id),
+        });
+    }
+
+    // Business users have no access to VENs, which is not an error when searching
+    let Ok(ven_permissions) = VenPermissions::try_from(user) else {
+        return Ok(Json(hits.into_vec()));
+    };
+
+    // a VEN is found if either itself or one of its resources matches
+    let is_match = contains(q);
+    for ven in find(ven_source.as_ref(), &ven_permissions, q, &hits).await? {
+        if ven_matches(&ven, &is_match) {
+            hits.push(SearchHit {
+                object_type: SearchObjectType::Ven,
+                id: ven.id.to_string(),
+                name: Some(ven.content.ven_name),
+                ven_id: None,
+                program_id: None,
+            });
+        }
+
+        for resource in ven.content.resources.into_iter().flatten() {
+            if resource_matches(&resource, &is_match) {
+                hits.push(SearchHit {
+                    object_type: SearchObjectType::Resource,
+                    id: resource.id.to_string(),
+                    name: Some(resource.content.resource_name),
+                    ven_id: Some(ven.id.clone()),
+                    program_id: None,
+                });
+            }
+        }
+    }
+
+    Ok(Json(hits.into_vec()))
+}
+
+/// Whether a text contains the search text, ignoring case, like `ILIKE` in the Postgres storage
+pub(crate) fn contains(search: &str) -> impl Fn(&str) -> bool {
+    let needle = search.to_lowercase();
+    move |text| text.to_lowercase().contains(&needle)
+}
+
+#[cfg_attr(not(feature = "in-memory"), allow(dead_code))]
+pub(crate) fn program_matches(program: &Program, is_match: impl Fn(&str) -> bool) -> bool {
+    is_match(&program.content.program_name)
+        || program
+            .content
+            .program_long_name
+            .as_deref()
+            .is_some_and(&is_match)
+        || program
+            .content
+            .program_descriptions
+            .iter()
+            .flatten()
+            .any(|description| is_match(&description.url))
+}
+
+#[cfg_attr(not(feature = "in-memory"), allow(dead_code))]
+pub(crate) fn event_matches(event: &Event, is_match: impl Fn(&str) -> bool) -> bool {
+    event.content.event_name.as_deref().is_some_and(&is_match)
+        || event
+            .content
+            .intervals
+            .iter()
+            .flat_map(|interval| &interval.payloads)
+            .filter(|payload| matches!(&payload.value_type, EventType::Private(name) if name == "DESCRIPTION"))
+            .flat_map(|payload| &payload.values)
+            .any(|value| matches!(value, Value::String(text) if is_match(text)))
+}
+
+/// Whether the VEN itself matches, regardless of its resources
+pub(crate) fn ven_matches(ven: &Ven, is_match: impl Fn(&str) -> bool) -> bool {
+    is_match(&ven.content.ven_name) || description_matches(&ven.content.attributes, is_match)
+}
+
+pub(crate) fn resource_matches(resource: &Resource, is_match: impl Fn(&str) -> bool) -> bool {
+    is_match(&resource.content.resource_name)
+        || description_matches(&resource.content.attributes, is_match)
+}
+
+fn description_matches(
+    attributes: &Option<Vec<ValuesMap>>,
+    is_match: impl Fn(&str) -> bool,
+) -> bool {
+    attributes
+        .iter()
+        .flatten()
+        .filter(|attribute| attribute.value_type.0 == "DESCRIPTION")
+        .flat_map(|attribute| &attribute.values)
+        .any(|value| matches!(value, Value::String(text) if is_match(text)))
+}
+
+/// The hits of a search, up to a limit
+struct Hits {
+    hits: Vec<SearchHit>,
+    limit: usize,
+}
+
+impl Hits {
+    fn new(limit: usize) -> Self {
+        Self {
+            hits: vec![],
+            limit,
+        }
+    }
+
+    fn push(&mut self, hit: SearchHit) {
+        if !self.is_full() {
+            self.hits.push(hit);
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.limit.saturating_sub(self.hits.len())
+    }
+
+    fn is_full(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    fn into_vec(self) -> Vec<SearchHit> {
+        self.hits
+    }
+}
+
+/// The objects of a list query matching the search text, at most as many as the remaining `hits`
+async fn find<C, E>(
+    source: &C,
+    permissions: &C::PermissionFilter,
+    search: &str,
+    hits: &Hits,
+) -> Result<Vec<C::Type>, AppError>
+where
+    C: Crud<Filter = ListParams<E>, Error = AppError> + ?Sized,
+    E: Default,
+{
+    if hits.is_full() {
+        return Ok(vec![]);
+    }
+
+    let filter = ListParams::<E> {
+        search: Some(search.to_string()),
+        limit: hits.remaining() as i64,
+        ..Default::default()
+    };
+
+    source.retrieve_all(&filter, permissions).await
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use openadr_wire::ven::VenId;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::{
+        api::test::jwt_test_token,
+        data_source::PostgresStorage,
+        jwt::{AuthRole, JwtManager},
+        state::AppState,
+    };
+
+    async fn search(app: &Router, q: &str, token: &str) -> (StatusCode, Vec<SearchHit>) {
+        let resp = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::GET)
+                    .uri(format!("/search?q={q}"))
+                    .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let status = resp.status();
+        if status != StatusCode::OK {
+            return (status, vec![]);
+        }
+        let body = resp.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    fn hit_ids(hits: &[SearchHit]) -> Vec<(SearchObjectType, &str)> {
+        hits.iter()
+            .map(|hit| (hit.object_type, hit.id.as_str()))
+            .collect()
+    }
+
+    #[sqlx::test(fixtures("users", "programs", "events", "vens", "resources"))]
+    async fn search_across_objects(db: PgPool) {
+        let state = AppState::new(
+            PostgresStorage::new(db).unwrap(),
+            JwtManager::from_base64_secret("test").unwrap(),
+        );
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness, AuthRole::VenManager]);
+        let app = state.into_router();
+
+        let (status, hits) = search(&app, "1-NAME", &token).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            hit_ids(&hits),
+            vec![
+                (SearchObjectType::Event, "event-1"),
+                (SearchObjectType::Ven, "ven-1"),
+                (SearchObjectType::Resource, "resource-1"),
+            ]
+        );
+        assert_eq!(hits[2].ven_id, Some(VenId::new("ven-1").unwrap()));
+
+        let (_, hits) = search(&app, "long%20name", &token).await;
+        assert_eq!(
+            hit_ids(&hits),
+            vec![(SearchObjectType::Program, "program-1")]
+        );
+
+        let (_, hits) = search(&app, "program-description-1", &token).await;
+        assert_eq!(
+            hit_ids(&hits),
+            vec![(SearchObjectType::Program, "program-1")]
+        );
+
+        // wildcards are matched literally
+        let (_, hits) = search(&app, "%25", &token).await;
+        assert_eq!(hit_ids(&hits), vec![]);
+
+        let (status, _) = search(&app, "", &token).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(fixtures("users", "programs", "events", "vens", "resources"))]
+    async fn search_stops_at_limit(db: PgPool) {
+        let state = AppState::new(
+            PostgresStorage::new(db).unwrap(),
+            JwtManager::from_base64_secret("test").unwrap(),
+        );
+        let token = jwt_test_token(&state, vec![AuthRole::AnyBusiness, AuthRole::VenManager]);
+        let app = state.into_router();
+
+        let (_, hits) = search(&app, "1-NAME", &token).await;
+        assert_eq!(hits.len(), 3);
+
+        let (status, hits) = search(&app, "1-NAME&limit=2", &token).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            hit_ids(&hits),
+            vec![
+                (SearchObjectType::Event, "event-1"),
+                (SearchObjectType::Ven, "ven-1"),
+            ]
+        );
+
+        let (status, _) = search(&app, "1-NAME&limit=0", &token).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(fixtures("users", "programs", "events", "vens", "resources"))]
+    async fn search_is_scoped_by_role(db: PgPool) {
+        let state = AppState::new(
+            PostgresStorage::new(db).unwrap(),
+            JwtManager::from_base64_secret("test").unwrap(),
+        );
+        let business = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let ven = jwt_test_token(&state, vec![AuthRole::VEN(VenId::new("ven-1").unwrap())]);
+        let app = state.into_router();
+
+        let (_, hits) = search(&app, "1-name", &business).await;
+        assert_eq!(hit_ids(&hits), vec![(SearchObjectType::Event, "event-1")]);
+
+        let (_, hits) = search(&app, "resource", &ven).await;
+        let mut ids = hit_ids(&hits);
+        ids.sort_by_key(|(_, id)| *id);
+        assert_eq!(
+            ids,
+            vec![
+                (SearchObjectType::Resource, "resource-1"),
+                (SearchObjectType::Resource, "resource-3"),
+            ]
+        );
+    }
+}
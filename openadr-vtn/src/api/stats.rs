@@ -0,0 +1,96 @@
+//! Statistics of the stored objects for operators, see [`Stats`]
+
+use std::sync::Arc;
+
+use axum::{extract::State, Json};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    api::{AppResponse, ValidatedQuery},
+    data_source::DataSource,
+    jwt::UserManagerUser,
+    stats::Stats,
+};
+
+#[derive(Deserialize, Validate, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParams {
+    /// The number of past days to report the report volume of
+    #[validate(range(min = 1, max = 366))]
+    #[serde(default = "default_days")]
+    days: i64,
+}
+
+fn default_days() -> i64 {
+    30
+}
+
+pub async fn get(
+    State(storage): State<Arc<dyn DataSource>>,
+    UserManagerUser(_): UserManagerUser,
+    ValidatedQuery(query): ValidatedQuery<QueryParams>,
+) -> AppResponse<Stats> {
+    let since = Utc::now() - Duration::days(query.days);
+
+    Ok(Json(storage.stats().stats(since).await?))
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use super::*;
+    use crate::{
+        api::test::{jwt_test_token, state},
+        jwt::AuthRole,
+    };
+    use axum::{
+        body::Body,
+        http::{header, Request, StatusCode},
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use sqlx::PgPool;
+    use tower::ServiceExt;
+
+    async fn request(app: &Router, path: &str, token: &str) -> (StatusCode, Vec<u8>) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get(path)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, body.to_vec())
+    }
+
+    #[sqlx::test(fixtures("users", "programs", "events"))]
+    async fn stats(db: PgPool) {
+        let state = state(db).await;
+        let admin = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let business = jwt_test_token(&state, vec![AuthRole::AnyBusiness]);
+        let app = state.into_router();
+
+        let (status, _) = request(&app, "/admin/stats", &business).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        let (status, body) = request(&app, "/admin/stats", &admin).await;
+        assert_eq!(status, StatusCode::OK);
+        let stats: Stats = serde_json::from_slice(&body).unwrap();
+        assert_eq!(stats.objects.programs, 3);
+        assert_eq!(stats.objects.events, 3);
+        assert_eq!(stats.events_per_program.len(), 3);
+        // the fixtures are older than 30 days
+        assert!(stats.report_volume.is_empty());
+
+        let (status, _) = request(&app, "/admin/stats?days=0", &admin).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
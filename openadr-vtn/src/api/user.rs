@@ -127,7 +127,12 @@ pub async fn delete_credential(
 #[cfg(feature = "live-db-test")]
 mod test {
     use super::*;
-    use crate::api::test::{jwt_test_token, state};
+    use crate::{
+        api::test::{jwt_test_token, state},
+        data_source::PostgresStorage,
+        jwt::JwtManager,
+        state::AppState,
+    };
     use axum::{
         body::Body,
         http,
@@ -292,7 +297,7 @@ mod test {
         .unwrap()
     }
 
-    async fn help_login(app: &mut Router, client_id: &str, client_secret: &str) -> Response<Body> {
+    async fn help_token(app: &mut Router, form: String) -> Response<Body> {
         app.oneshot(
             Request::builder()
                 .method(http::Method::POST)
@@ -301,15 +306,36 @@ mod test {
                     http::header::CONTENT_TYPE,
                     mime::APPLICATION_WWW_FORM_URLENCODED.as_ref(),
                 )
-                .body(Body::from(format!(
-                    "client_id={client_id}&client_secret={client_secret}&grant_type=client_credentials",
-                )))
+                .body(Body::from(form))
                 .unwrap(),
         )
         .await
         .unwrap()
     }
 
+    async fn help_login(app: &mut Router, client_id: &str, client_secret: &str) -> Response<Body> {
+        help_token(
+            app,
+            format!(
+                "client_id={client_id}&client_secret={client_secret}&grant_type=client_credentials",
+            ),
+        )
+        .await
+    }
+
+    async fn help_refresh(app: &mut Router, refresh_token: &str) -> Response<Body> {
+        help_token(
+            app,
+            format!("refresh_token={refresh_token}&grant_type=refresh_token"),
+        )
+        .await
+    }
+
+    async fn into_json(response: Response<Body>) -> serde_json::Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
     impl UserDetails {
         async fn from(response: Response<Body>) -> Self {
             let body = response.into_body().collect().await.unwrap().to_bytes();
@@ -502,4 +528,85 @@ mod test {
         let response = help_login(&mut app, "admin", "admin").await;
         assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn refresh_token(db: PgPool) {
+        let mut app = state(db.clone()).await.into_router();
+
+        // refresh tokens are only issued if enabled
+        let response = help_login(&mut app, "admin", "admin").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = into_json(response).await;
+        assert!(body.get("refresh_token").is_none());
+
+        let response = help_refresh(&mut app, "anything").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(into_json(response).await["error"], "unsupported_grant_type");
+
+        let store = PostgresStorage::new(db).unwrap();
+        let jwt_manager = JwtManager::from_base64_secret("test")
+            .unwrap()
+            .with_refresh_tokens(std::time::Duration::from_secs(3600));
+        let state = AppState::new(store, jwt_manager);
+        let token = jwt_test_token(&state, vec![AuthRole::UserManager]);
+        let mut app = state.into_router();
+
+        let response = help_login(&mut app, "admin", "admin").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = into_json(response).await;
+        let access_token = body["access_token"].as_str().unwrap().to_string();
+        let refresh_token = body["refresh_token"].as_str().unwrap().to_string();
+
+        let response = help_refresh(&mut app, &refresh_token).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = into_json(response).await;
+        assert!(body["access_token"].is_string());
+        assert!(body["refresh_token"].is_string());
+
+        // access tokens cannot be used to refresh
+        let response = help_refresh(&mut app, &access_token).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(into_json(response).await["error"], "invalid_grant");
+
+        // nor can refresh tokens of removed credentials
+        let response = help_delete(&mut app, &token, "/users/admin/admin").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = help_refresh(&mut app, &refresh_token).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(into_json(response).await["error"], "invalid_grant");
+    }
+
+    #[sqlx::test(fixtures("users"))]
+    async fn token_scopes(db: PgPool) {
+        let mut app = state(db).await.into_router();
+        let login = "client_id=admin&client_secret=admin&grant_type=client_credentials";
+
+        // all scopes of the roles by default
+        let response = help_token(&mut app, login.to_string()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            into_json(response).await["scope"],
+            "read_all write_programs write_events write_reports write_vens admin"
+        );
+
+        // the requested scopes the client has
+        let response = help_token(&mut app, format!("{login}&scope=write_events+read_all")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(into_json(response).await["scope"], "read_all write_events");
+
+        let response = help_token(&mut app, format!("{login}&scope=openid")).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(into_json(response).await["error"], "invalid_scope");
+
+        // user-1 has no roles, and therefore no scopes
+        let response = help_token(
+            &mut app,
+            "client_id=user-1-client-id&client_secret=user-1&grant_type=client_credentials&scope=read_all"
+                .to_string(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(into_json(response).await["error"], "invalid_scope");
+    }
 }
@@ -5,34 +5,39 @@ use axum::{
     Json,
 };
 use reqwest::StatusCode;
-use serde::Deserialize;
 use tracing::{info, trace};
-use validator::{Validate, ValidationError};
 
 use openadr_wire::{
-    target::TargetLabel,
+    oauth::Scope,
     ven::{Ven, VenContent, VenId},
 };
 
 use crate::{
-    api::{AppResponse, ValidatedJson, ValidatedQuery},
-    data_source::VenCrud,
+    api::{AppResponse, ListParams, Page, PageResponse, ValidatedJson},
+    change_log::{self, ChangeLog, ObjectType, Operation},
+    data_source::{DataSource, VenCrud, VenPermissions},
     error::AppError,
     jwt::{User, VenManagerUser},
+    notifier::{self, Notifier},
+    target_labels::TargetLabelRegistry,
 };
 
 pub async fn get_all(
     State(ven_source): State<Arc<dyn VenCrud>>,
-    ValidatedQuery(query_params): ValidatedQuery<QueryParams>,
+    query_params: ListParams,
     User(user): User,
-) -> AppResponse<Vec<Ven>> {
+) -> PageResponse<Ven> {
+    user.require_scope(Scope::ReadAll)?;
     trace!(?query_params);
 
-    let vens = ven_source
-        .retrieve_all(&query_params, &user.try_into()?)
-        .await?;
+    let permissions: VenPermissions = user.try_into()?;
+    let vens = ven_source.retrieve_all(&query_params, &permissions).await?;
+    let total = ven_source.count(&query_params, &permissions).await?;
 
-    Ok(Json(vens))
+    Ok(Page {
+        objects: vens,
+        total,
+    })
 }
 
 pub async fn get(
@@ -40,8 +45,9 @@ pub async fn get(
     Path(id): Path<VenId>,
     User(user): User,
 ) -> AppResponse<Ven> {
+    user.require_scope(Scope::ReadAll)?;
     if user.is_ven() {
-        if !user.ven_ids().iter().any(|vid| *vid == id) {
+        if !user.ven_ids().contains(&id) {
             return Err(AppError::Forbidden("User does not have access to this VEN"));
         }
     } else if !user.is_ven_manager() {
@@ -54,22 +60,54 @@ pub async fn get(
 }
 
 pub async fn add(
-    State(ven_source): State<Arc<dyn VenCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
     VenManagerUser(user): VenManagerUser,
     ValidatedJson(new_ven): ValidatedJson<VenContent>,
 ) -> Result<(StatusCode, Json<Ven>), AppError> {
-    let ven = ven_source.create(new_ven, &user.try_into()?).await?;
+    user.require_scope(Scope::WriteVens)?;
+    target_labels.validate_values_maps(new_ven.targets.as_deref())?;
+    let permissions: VenPermissions = user.clone().try_into()?;
+    let ven = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Ven,
+        Operation::Create,
+        &user,
+        |ven: &Ven| ven.id.to_string(),
+        move |tx, _| Box::pin(async move { tx.vens().create(new_ven, &permissions).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Create, &ven);
 
     Ok((StatusCode::CREATED, Json(ven)))
 }
 
 pub async fn edit(
-    State(ven_source): State<Arc<dyn VenCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
+    State(target_labels): State<Arc<TargetLabelRegistry>>,
     Path(id): Path<VenId>,
     VenManagerUser(user): VenManagerUser,
     ValidatedJson(content): ValidatedJson<VenContent>,
 ) -> AppResponse<Ven> {
-    let ven = ven_source.update(&id, content, &user.try_into()?).await?;
+    user.require_scope(Scope::WriteVens)?;
+    target_labels.validate_values_maps(content.targets.as_deref())?;
+    let permissions: VenPermissions = user.clone().try_into()?;
+    let ven = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Ven,
+        Operation::Update,
+        &user,
+        |ven: &Ven| ven.id.to_string(),
+        move |tx, _| Box::pin(async move { tx.vens().update(&id, content, &permissions).await }),
+    )
+    .await?;
+    notifier::send(notifier.as_deref(), Operation::Update, &ven);
 
     info!(%ven.id, ven.ven_name=ven.content.ven_name, "ven updated");
 
@@ -77,41 +115,30 @@ pub async fn edit(
 }
 
 pub async fn delete(
-    State(ven_source): State<Arc<dyn VenCrud>>,
+    State(storage): State<Arc<dyn DataSource>>,
+    State(change_log): State<Option<Arc<dyn ChangeLog>>>,
+    State(notifier): State<Option<Arc<Notifier>>>,
     Path(id): Path<VenId>,
     VenManagerUser(user): VenManagerUser,
 ) -> AppResponse<Ven> {
-    let ven = ven_source.delete(&id, &user.try_into()?).await?;
+    user.require_scope(Scope::WriteVens)?;
+    let permissions: VenPermissions = user.clone().try_into()?;
+    let ven = change_log::write(
+        storage.as_ref(),
+        change_log,
+        ObjectType::Ven,
+        Operation::Delete,
+        &user,
+        |ven: &Ven| ven.id.to_string(),
+        move |tx, _| Box::pin(async move { tx.vens().delete(&id, &permissions).await }),
+    )
+    .await?;
+    let id = &ven.id;
+    notifier::send(notifier.as_deref(), Operation::Delete, &ven);
     info!(%id, "deleted ven");
     Ok(Json(ven))
 }
 
-#[derive(Deserialize, Validate, Debug)]
-#[validate(schema(function = "validate_target_type_value_pair"))]
-#[serde(rename_all = "camelCase")]
-pub struct QueryParams {
-    pub(crate) target_type: Option<TargetLabel>,
-    pub(crate) target_values: Option<Vec<String>>,
-    #[serde(default)]
-    #[validate(range(min = 0))]
-    pub(crate) skip: i64,
-    #[validate(range(min = 1, max = 50))]
-    #[serde(default = "get_50")]
-    pub(crate) limit: i64,
-}
-
-fn validate_target_type_value_pair(query: &QueryParams) -> Result<(), ValidationError> {
-    if query.target_type.is_some() == query.target_values.is_some() {
-        Ok(())
-    } else {
-        Err(ValidationError::new("targetType and targetValues query parameter must either both be set or not set at the same time."))
-    }
-}
-
-fn get_50() -> i64 {
-    50
-}
-
 #[cfg(test)]
 mod tests {
     use axum::{
@@ -133,10 +160,14 @@ mod tests {
     };
 
     async fn request_all(app: Router, token: &str) -> Response<Body> {
+        request_all_with_query(app, "", token).await
+    }
+
+    async fn request_all_with_query(app: Router, query: &str, token: &str) -> Response<Body> {
         app.oneshot(
             Request::builder()
                 .method(http::Method::GET)
-                .uri("/vens")
+                .uri(format!("/vens?{query}"))
                 .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
                 .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
                 .body(Body::empty())
@@ -190,4 +221,61 @@ mod tests {
         assert_eq!(vens.len(), 1);
         assert_eq!(vens[0].id.as_str(), "ven-1");
     }
+
+    #[sqlx::test(fixtures("users", "vens"))]
+    async fn get_all_filtered(db: PgPool) {
+        let state = test_state(db);
+        let token = jwt_test_token(&state, vec![AuthRole::VenManager]);
+        let app = state.into_router();
+
+        for (query, expected) in [
+            ("targetType=GROUP&targetValues=group-1", vec!["ven-1"]),
+            ("targetType=GROUP&targetValues=group-2", vec![]),
+            ("targetType=VEN_NAME&targetValues=ven-2-name", vec!["ven-2"]),
+            (
+                "targetType=RESOURCE_NAME&targetValues=resource-1-name",
+                vec![],
+            ),
+        ] {
+            let resp = request_all_with_query(app.clone(), query, &token).await;
+            assert_eq!(resp.status(), http::StatusCode::OK, "{query}");
+            let vens: Vec<Ven> = get_response_json(resp).await;
+            let ids = vens.iter().map(|ven| ven.id.as_str()).collect::<Vec<_>>();
+            assert_eq!(ids, expected, "{query}");
+        }
+
+        for query in [
+            "targetType=GROUP",
+            "targetValues=group-1",
+            "limit=51",
+            "skip=-1",
+        ] {
+            let resp = request_all_with_query(app.clone(), query, &token).await;
+            assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+
+    #[sqlx::test(fixtures("users", "vens"))]
+    async fn get_all_total_count(db: PgPool) {
+        let state = test_state(db);
+        let token = jwt_test_token(&state, vec![AuthRole::VenManager]);
+        let app = state.into_router();
+
+        for (query, expected_len, expected_total) in [
+            ("", 2, "2"),
+            ("limit=1", 1, "2"),
+            ("skip=5", 0, "2"),
+            ("targetType=GROUP&targetValues=group-1", 1, "1"),
+        ] {
+            let resp = request_all_with_query(app.clone(), query, &token).await;
+            assert_eq!(resp.status(), http::StatusCode::OK, "{query}");
+            assert_eq!(
+                resp.headers()[openadr_wire::TOTAL_COUNT_HEADER],
+                expected_total,
+                "{query}"
+            );
+            let vens: Vec<Ven> = get_response_json(resp).await;
+            assert_eq!(vens.len(), expected_len, "{query}");
+        }
+    }
 }
@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Collects items per key to handle them together, e.g., to send the notifications
+/// of bulk changes to a subscriber in a single callback instead of one per object.
+///
+/// The first item of a batch starts its window, after which the whole batch is flushed.
+#[derive(Debug)]
+pub struct BatchWindows<K, T> {
+    pending: Arc<Mutex<HashMap<K, Vec<T>>>>,
+}
+
+impl<K, T> Default for BatchWindows<K, T> {
+    fn default() -> Self {
+        Self {
+            pending: Default::default(),
+        }
+    }
+}
+
+impl<K, T> Clone for BatchWindows<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<K, T> BatchWindows<K, T>
+where
+    K: Eq + Hash + Clone + Send + 'static,
+    T: Send + 'static,
+{
+    /// Add the item to the batch of the key.
+    /// If it starts a new batch, `flush` is called with the batch once the window elapsed.
+    pub fn push<F, Fut>(&self, key: K, item: T, window: Duration, flush: F)
+    where
+        F: FnOnce(Vec<T>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let mut pending = self.pending.lock().unwrap();
+        let batch = pending.entry(key.clone()).or_default();
+        batch.push(item);
+        if batch.len() > 1 {
+            return;
+        }
+
+        let pending = self.pending.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(window).await;
+            let Some(batch) = pending.lock().unwrap().remove(&key) else {
+                return;
+            };
+            flush(batch).await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn flush_after_window() {
+        let windows = BatchWindows::default();
+        let flushed = Arc::new(Mutex::new(Vec::new()));
+
+        let push = |key: &'static str, item: u32, window: u64| {
+            let flushed = flushed.clone();
+            windows.push(
+                key,
+                item,
+                Duration::from_secs(window),
+                move |batch| async move {
+                    flushed.lock().unwrap().push((key, batch));
+                },
+            );
+        };
+
+        push("a", 1, 10);
+        push("b", 2, 5);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        // the window of a batch is that of its first item
+        push("a", 3, 1);
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(*flushed.lock().unwrap(), [("b", vec![2])]);
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(flushed.lock().unwrap()[1], ("a", vec![1, 3]));
+
+        // a later item starts a new batch
+        push("a", 4, 1);
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert_eq!(flushed.lock().unwrap()[2], ("a", vec![4]));
+    }
+}
@@ -0,0 +1,120 @@
+//! Seed users into the [`AuthSource`] at startup,
+//! such that a small deployment or demo VTN does not need manual calls to the user API.
+
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{data_source::AuthSource, error::AppError, jwt::AuthRole};
+
+/// A user to create at startup, unless a user with the same `reference` exists
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BootstrapUser {
+    pub reference: String,
+    pub description: Option<String>,
+    pub roles: Vec<AuthRole>,
+    #[serde(default)]
+    pub credentials: Vec<BootstrapCredential>,
+}
+
+/// A credential to add to a [`BootstrapUser`], unless the user already has a credential
+/// with this `client_id`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BootstrapCredential {
+    pub client_id: String,
+    /// The client secret hashed as a PHC string, e.g., with `argon2`.
+    /// Plain secrets are not accepted, as they would be readable from the config file.
+    pub client_secret_hash: String,
+}
+
+/// Parse a JSON list of [`BootstrapUser`]s
+pub fn parse_users(json: &str) -> Result<Vec<BootstrapUser>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+/// Create the users and credentials that do not exist yet.
+///
+/// Existing users are left untouched, such that changes made through the user API,
+/// e.g., to the roles of a user, survive a restart.
+pub async fn seed_users(auth: &dyn AuthSource, users: &[BootstrapUser]) -> Result<(), AppError> {
+    let existing = auth.get_all_users().await?;
+
+    for user in users {
+        let (user_id, client_ids) = match existing
+            .iter()
+            .find(|existing| existing.reference == user.reference)
+        {
+            Some(existing) => (existing.id.clone(), existing.client_ids.clone()),
+            None => {
+                let created = auth
+                    .add_user(&user.reference, user.description.as_deref(), &user.roles)
+                    .await?;
+                info!(reference = user.reference, "created bootstrap user");
+                (created.id, vec![])
+            }
+        };
+
+        for credential in &user.credentials {
+            if client_ids.contains(&credential.client_id) {
+                continue;
+            }
+
+            auth.add_hashed_credential(
+                &user_id,
+                &credential.client_id,
+                &credential.client_secret_hash,
+            )
+            .await?;
+            info!(
+                reference = user.reference,
+                client_id = credential.client_id,
+                "created bootstrap credential"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod test {
+    use super::*;
+    use crate::data_source::{DataSource, PostgresStorage};
+    use argon2::{
+        password_hash::{rand_core::OsRng, SaltString},
+        Argon2, PasswordHasher,
+    };
+    use sqlx::PgPool;
+
+    fn users() -> Vec<BootstrapUser> {
+        let hash = Argon2::default()
+            .hash_password(b"demo-secret", &SaltString::generate(&mut OsRng))
+            .unwrap()
+            .to_string();
+
+        let json = serde_json::json!([{
+            "reference": "demo",
+            "description": "Demo business user",
+            "roles": [{"role": "AnyBusiness"}],
+            "credentials": [{"client_id": "demo-client", "client_secret_hash": hash}]
+        }]);
+        parse_users(&json.to_string()).unwrap()
+    }
+
+    #[sqlx::test(fixtures(path = "../../fixtures", scripts("users")))]
+    async fn seeds_missing_users(db: PgPool) {
+        let auth = PostgresStorage::new(db).unwrap().auth();
+        let before = auth.get_all_users().await.unwrap().len();
+
+        seed_users(auth.as_ref(), &users()).await.unwrap();
+        let info = auth
+            .check_credentials("demo-client", "demo-secret")
+            .await
+            .unwrap();
+        assert_eq!(info.roles, vec![AuthRole::AnyBusiness]);
+
+        // seeding again does not create duplicates
+        seed_users(auth.as_ref(), &users()).await.unwrap();
+        assert_eq!(auth.get_all_users().await.unwrap().len(), before + 1);
+    }
+}
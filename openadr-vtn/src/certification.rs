@@ -0,0 +1,280 @@
+//! A test mode in which the VTN replays canned responses, such that the official
+//! OpenADR Alliance certification test tool can be run against this implementation.
+//!
+//! Some certification tests expect exact responses, e.g., to requests with objects that
+//! only exist in the test tool's fixtures. Each [`Vector`] captures such a request and the
+//! response the test tool expects. All other requests are handled as usual.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+use validator::Validate;
+
+/// The maximum size of a request body that is compared to a [`Vector::request_body`]
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// A canned request and the response to replay for it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vector {
+    /// The name of the certification test case, used in the logs
+    pub name: String,
+    pub method: String,
+    pub path: String,
+    /// The exact query string, without the leading `?`, if the vector only applies to it
+    pub query: Option<String>,
+    /// The JSON body of the request, if the vector only applies to requests with this body
+    pub request_body: Option<Value>,
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<Value>,
+}
+
+impl Vector {
+    fn matches_request(&self, req: &Request) -> bool {
+        self.method.eq_ignore_ascii_case(req.method().as_str())
+            && self.path == req.uri().path()
+            && self
+                .query
+                .as_deref()
+                .map_or(true, |query| Some(query) == req.uri().query())
+    }
+
+    fn response(&self) -> Response {
+        let status = StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK);
+        let mut response = match &self.body {
+            Some(body) => (status, Json(body)).into_response(),
+            None => status.into_response(),
+        };
+
+        for (name, value) in &self.headers {
+            match (
+                HeaderName::try_from(name.as_str()),
+                HeaderValue::try_from(value.as_str()),
+            ) {
+                (Ok(name), Ok(value)) => {
+                    response.headers_mut().insert(name, value);
+                }
+                _ => warn!(vector = self.name, name, "invalid header in vector"),
+            }
+        }
+
+        response
+    }
+}
+
+/// Parse a JSON list of [`Vector`]s
+pub fn parse_vectors(json: &str) -> Result<Vec<Vector>, serde_json::Error> {
+    serde_json::from_str(json)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificationStatus {
+    pub enabled: bool,
+}
+
+/// The certification vectors of this VTN process, enabled through `PUT /admin/certification`.
+///
+/// Certification mode is disabled by default, such that the vectors can be loaded at startup
+/// and only replayed while the test tool runs.
+#[derive(Debug, Default)]
+pub struct Certification {
+    enabled: AtomicBool,
+    vectors: Vec<Vector>,
+}
+
+impl Certification {
+    pub fn new(vectors: Vec<Vector>) -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            vectors,
+        }
+    }
+
+    pub fn status(&self) -> CertificationStatus {
+        CertificationStatus {
+            enabled: self.enabled.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn set_status(&self, status: CertificationStatus) {
+        self.enabled.store(status.enabled, Ordering::Relaxed);
+    }
+
+    pub fn vectors(&self) -> &[Vector] {
+        &self.vectors
+    }
+}
+
+/// Middleware replaying the response of the first [`Vector`] matching a request,
+/// while certification mode is enabled
+pub(crate) async fn replay_vectors(
+    State(certification): State<Arc<Certification>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !certification.status().enabled {
+        return next.run(req).await;
+    }
+
+    let candidates = certification
+        .vectors
+        .iter()
+        .filter(|vector| vector.matches_request(&req))
+        .collect::<Vec<_>>();
+
+    if candidates.is_empty() {
+        return next.run(req).await;
+    }
+
+    // the body is only read if a vector depends on it
+    let (req, body) = if candidates
+        .iter()
+        .any(|vector| vector.request_body.is_some())
+    {
+        let (parts, body) = req.into_parts();
+        let Ok(bytes) = to_bytes(body, MAX_BODY_SIZE).await else {
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        };
+        let json = serde_json::from_slice::<Value>(&bytes).ok();
+        (Request::from_parts(parts, Body::from(bytes)), json)
+    } else {
+        (req, None)
+    };
+
+    let vector = candidates.into_iter().find(|vector| {
+        vector
+            .request_body
+            .as_ref()
+            .map_or(true, |expected| Some(expected) == body.as_ref())
+    });
+
+    match vector {
+        Some(vector) => {
+            debug!(vector = vector.name, "replaying certification vector");
+            vector.response()
+        }
+        None => next.run(req).await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{
+        http::{header, Method},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use http_body_util::BodyExt;
+    use tower::ServiceExt;
+
+    fn vectors() -> Vec<Vector> {
+        parse_vectors(
+            r#"[
+                {
+                    "name": "missing program",
+                    "method": "GET",
+                    "path": "/programs/unknown",
+                    "status": 404,
+                    "headers": {"x-test": "replayed"},
+                    "body": {"title": "Not Found", "status": 404}
+                },
+                {
+                    "name": "create program",
+                    "method": "POST",
+                    "path": "/programs",
+                    "requestBody": {"programName": "certification"},
+                    "status": 201,
+                    "body": {"id": "certification-1"}
+                }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn app(certification: Arc<Certification>) -> Router {
+        Router::new()
+            .route("/programs", get(|| async {}).post(|| async {}))
+            .route("/programs/:id", get(|| async {}))
+            .layer(middleware::from_fn_with_state(
+                certification,
+                replay_vectors,
+            ))
+    }
+
+    async fn send(app: &Router, method: Method, path: &str, body: &str) -> Response {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_matching_vectors() {
+        let certification = Arc::new(Certification::new(vectors()));
+        let app = app(certification.clone());
+
+        // disabled by default
+        let response = send(&app, Method::GET, "/programs/unknown", "").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        certification.set_status(CertificationStatus { enabled: true });
+
+        let response = send(&app, Method::GET, "/programs/unknown", "").await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()["x-test"], "replayed");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            serde_json::json!({"title": "Not Found", "status": 404})
+        );
+
+        let response = send(
+            &app,
+            Method::POST,
+            "/programs",
+            r#"{"programName": "certification"}"#,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // other requests are handled as usual
+        let response = send(
+            &app,
+            Method::POST,
+            "/programs",
+            r#"{"programName": "other"}"#,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = send(&app, Method::GET, "/programs/program-1", "").await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,258 @@
+//! A log of all changes made to objects through the API, for change-data-capture consumers,
+//! e.g., to replicate the objects into a data warehouse or to catch up after being offline.
+
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::{error, info};
+
+use crate::{data_source::DataSource, error::AppError, jwt::Claims};
+
+/// How often changes older than the retention period are removed
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ObjectType {
+    Program,
+    Event,
+    Report,
+    Ven,
+    Resource,
+    /// The assignment of a VEN to a program, identified by `{programID}/{venID}`
+    ProgramVen,
+}
+
+impl ObjectType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectType::Program => "PROGRAM",
+            ObjectType::Event => "EVENT",
+            ObjectType::Report => "REPORT",
+            ObjectType::Ven => "VEN",
+            ObjectType::Resource => "RESOURCE",
+            ObjectType::ProgramVen => "PROGRAM_VEN",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Create => "CREATE",
+            Operation::Update => "UPDATE",
+            Operation::Delete => "DELETE",
+        }
+    }
+}
+
+/// A change to record in the [`ChangeLog`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewChange {
+    pub object_type: ObjectType,
+    pub object_id: String,
+    pub operation: Operation,
+    /// The client that made the change
+    pub actor: String,
+    /// The canonical JSON of the object after the change, or before its deletion
+    pub payload: String,
+}
+
+impl NewChange {
+    pub fn new<T: Serialize>(
+        object_type: ObjectType,
+        object_id: &str,
+        operation: Operation,
+        user: &Claims,
+        object: &T,
+    ) -> Result<Self, AppError> {
+        Ok(Self {
+            object_type,
+            object_id: object_id.to_string(),
+            operation,
+            actor: user.sub.clone(),
+            payload: openadr_wire::to_canonical_json(object)
+                .map_err(AppError::SerdeJsonInternalServerError)?,
+        })
+    }
+}
+
+/// A change as stored in the [`ChangeLog`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectChange {
+    /// Increases with each change in the order the changes were committed,
+    /// such that consumers can continue after the last change they saw
+    pub seq: i64,
+    pub object_type: String,
+    #[serde(rename = "objectID")]
+    pub object_id: String,
+    pub operation: String,
+    pub actor: String,
+    #[serde(with = "openadr_wire::serde_rfc3339")]
+    pub changed_at: DateTime<Utc>,
+    /// The hex encoded SHA-256 hash of the [`NewChange::payload`]
+    pub payload_hash: String,
+}
+
+#[async_trait]
+pub trait ChangeLog: Send + Sync + 'static {
+    /// Record a change, as part of the transaction that made the change if there is one.
+    ///
+    /// Changes must become visible in the order of their sequence numbers,
+    /// such that consumers continuing after the last change they saw do not miss any.
+    async fn record(&self, change: NewChange) -> Result<(), AppError>;
+
+    /// The changes after the change with sequence number `after`, oldest first
+    async fn changes(&self, after: i64, limit: i64) -> Result<Vec<ObjectChange>, AppError>;
+
+    /// Remove the changes made before the given time, returning the number of removed changes
+    async fn prune(&self, before: DateTime<Utc>) -> Result<u64, AppError>;
+}
+
+/// Run the `write`, and record the change it made if the change log is enabled.
+///
+/// The change is recorded in the transaction of the `write`,
+/// such that a change is recorded if and only if the `write` is committed.
+/// The change log of the `storage` is used if it has one,
+/// otherwise the change is recorded in the given `change_log` before the transaction is committed.
+pub(crate) async fn write<T, F>(
+    storage: &dyn DataSource,
+    change_log: Option<Arc<dyn ChangeLog>>,
+    object_type: ObjectType,
+    operation: Operation,
+    user: &Claims,
+    object_id: impl FnOnce(&T) -> String + Send + 'static,
+    write: F,
+) -> Result<T, AppError>
+where
+    T: Serialize + Send + 'static,
+    F: for<'tx> FnOnce(
+            &'tx dyn DataSource,
+            &'tx Claims,
+        ) -> Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 'tx>>
+        + Send
+        + 'static,
+{
+    let Some(change_log) = change_log else {
+        return write(storage, user).await;
+    };
+
+    let user = user.clone();
+    storage
+        .transaction(move |tx| {
+            Box::pin(async move {
+                let object = write(tx, &user).await?;
+                let change =
+                    NewChange::new(object_type, &object_id(&object), operation, &user, &object)?;
+                tx.change_log()
+                    .unwrap_or(change_log)
+                    .record(change)
+                    .await
+                    .inspect_err(|err| {
+                        error!(
+                            ?err,
+                            object_type = object_type.as_str(),
+                            operation = operation.as_str(),
+                            "could not record change"
+                        )
+                    })?;
+                Ok(object)
+            })
+        })
+        .await
+}
+
+/// Periodically remove the changes older than the retention period
+pub fn spawn_retention(change_log: Arc<dyn ChangeLog>, retention: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            // a retention too long to represent keeps all changes
+            let Some(before) = chrono::Duration::from_std(retention)
+                .ok()
+                .and_then(|retention| Utc::now().checked_sub_signed(retention))
+            else {
+                continue;
+            };
+
+            match change_log.prune(before).await {
+                Ok(0) => {}
+                Ok(removed) => info!(removed, "removed changes older than the retention period"),
+                Err(err) => error!(?err, "could not remove old changes"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+#[cfg(feature = "in-memory")]
+mod test {
+    use super::*;
+    use crate::data_source::InMemoryStorage;
+    use openadr_wire::{program::ProgramContent, Program};
+
+    /// Fails to record any change
+    struct BrokenChangeLog;
+
+    #[async_trait]
+    impl ChangeLog for BrokenChangeLog {
+        async fn record(&self, _change: NewChange) -> Result<(), AppError> {
+            Err(AppError::NotImplemented("recording changes"))
+        }
+
+        async fn changes(&self, _after: i64, _limit: i64) -> Result<Vec<ObjectChange>, AppError> {
+            Ok(vec![])
+        }
+
+        async fn prune(&self, _before: DateTime<Utc>) -> Result<u64, AppError> {
+            Ok(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn write_is_rolled_back_if_the_change_is_not_recorded() {
+        let storage: Arc<dyn DataSource> = Arc::new(InMemoryStorage::new());
+        let user = Claims::any_business_user();
+
+        let result = write(
+            storage.as_ref(),
+            Some(Arc::new(BrokenChangeLog)),
+            ObjectType::Program,
+            Operation::Create,
+            &user,
+            |program: &Program| program.id.to_string(),
+            |tx, user| {
+                Box::pin(async move {
+                    tx.programs()
+                        .create(ProgramContent::new("program"), user)
+                        .await
+                })
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(AppError::NotImplemented(_))));
+
+        let programs = storage
+            .programs()
+            .retrieve_all(&Default::default(), &user)
+            .await
+            .unwrap();
+        assert!(programs.is_empty());
+    }
+}
@@ -0,0 +1,128 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::watch;
+
+#[cfg(feature = "chaos")]
+use crate::chaos::Chaos;
+
+/// Wakes up requests waiting for objects to change, used for long-polling.
+///
+/// Changes are only tracked within this VTN process.
+/// When running multiple instances of the VTN, a client might not be woken up
+/// by a change handled by another instance, and only receives it when its wait times out.
+#[derive(Debug)]
+pub struct ChangeNotifier {
+    sender: Arc<watch::Sender<u64>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<Chaos>>,
+}
+
+impl Default for ChangeNotifier {
+    fn default() -> Self {
+        Self {
+            sender: Arc::new(watch::Sender::new(0)),
+            #[cfg(feature = "chaos")]
+            chaos: None,
+        }
+    }
+}
+
+impl ChangeNotifier {
+    /// Drop a fraction of the notifications, see [`Chaos::notification_drop_rate`].
+    ///
+    /// The returned notifier shares the channel of this one,
+    /// such that requests waiting on either are woken up by the notifications of both.
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(&self, chaos: Arc<Chaos>) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            chaos: Some(chaos),
+        }
+    }
+
+    /// Wake up all requests waiting for a change
+    pub fn notify(&self) {
+        #[cfg(feature = "chaos")]
+        if self
+            .chaos
+            .as_ref()
+            .is_some_and(|chaos| chaos.drop_notification())
+        {
+            return;
+        }
+
+        self.sender
+            .send_modify(|version| *version = version.wrapping_add(1));
+    }
+
+    /// The current version of the objects, which changes with each notification
+    pub fn version(&self) -> u64 {
+        *self.sender.borrow()
+    }
+
+    /// Wait until the objects changed since the `version`, or until the timeout elapses.
+    /// Without a version, waits for the next change.
+    /// Returns whether a change occurred.
+    pub async fn wait(&self, version: Option<u64>, timeout: Duration) -> bool {
+        let mut receiver = self.sender.subscribe();
+        let version = version.unwrap_or_else(|| *receiver.borrow_and_update());
+        let changed =
+            tokio::time::timeout(timeout, receiver.wait_for(|current| *current != version))
+                .await
+                .is_ok_and(|changed| changed.is_ok());
+        changed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_for_change() {
+        let changes = Arc::new(ChangeNotifier::default());
+
+        assert!(!changes.wait(None, Duration::from_secs(30)).await);
+
+        let waiting = tokio::spawn({
+            let changes = changes.clone();
+            async move { changes.wait(None, Duration::from_secs(30)).await }
+        });
+        tokio::task::yield_now().await;
+        changes.notify();
+
+        assert!(waiting.await.unwrap());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn wait_since_version() {
+        let changes = ChangeNotifier::default();
+        let version = changes.version();
+        assert!(!changes.wait(Some(version), Duration::from_secs(30)).await);
+
+        // a change before waiting is not missed
+        changes.notify();
+        assert!(changes.wait(Some(version), Duration::from_secs(30)).await);
+        assert!(
+            !changes
+                .wait(Some(changes.version()), Duration::from_secs(30))
+                .await
+        );
+    }
+
+    #[cfg(feature = "chaos")]
+    #[tokio::test(start_paused = true)]
+    async fn chaos_shares_the_channel() {
+        let changes = Arc::new(ChangeNotifier::default());
+        let with_chaos = changes.with_chaos(Arc::new(Chaos::default()));
+
+        let waiting = tokio::spawn({
+            let changes = changes.clone();
+            async move { changes.wait(None, Duration::from_secs(30)).await }
+        });
+        tokio::task::yield_now().await;
+        with_chaos.notify();
+
+        assert!(waiting.await.unwrap());
+    }
+}
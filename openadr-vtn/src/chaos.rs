@@ -0,0 +1,162 @@
+//! Fault injection for resilience testing, only available with the `chaos` feature.
+//!
+//! Injects latency, server errors, and dropped change notifications,
+//! such that the retry and backoff logic of clients can be validated against a real VTN.
+//! Never enable this feature in production.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use openadr_wire::problem::Problem;
+use tracing::warn;
+use uuid::Uuid;
+
+/// The `Retry-After`, in seconds, of injected `503 Service Unavailable` responses
+const RETRY_AFTER: u64 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chaos {
+    /// Each request is delayed by a random duration up to this maximum
+    pub max_latency: Duration,
+    /// The fraction of requests, between 0 and 1, that fail with a server error
+    pub error_rate: f64,
+    /// The status of the injected server errors
+    pub error_status: Option<StatusCode>,
+    /// The fraction of change notifications, between 0 and 1, that are dropped,
+    /// such that long-polling clients are only informed when their wait times out
+    pub notification_drop_rate: f64,
+}
+
+impl Chaos {
+    /// Whether to drop a change notification
+    pub fn drop_notification(&self) -> bool {
+        let drop = happens(self.notification_drop_rate);
+        if drop {
+            warn!("chaos: dropped change notification");
+        }
+        drop
+    }
+
+    fn latency(&self) -> Duration {
+        self.max_latency.mul_f64(random())
+    }
+
+    fn error(&self) -> Option<Response> {
+        if !happens(self.error_rate) {
+            return None;
+        }
+
+        let status = self.error_status.unwrap_or(StatusCode::SERVICE_UNAVAILABLE);
+        warn!(%status, "chaos: injected server error");
+
+        let problem = Problem {
+            title: Some(status.to_string()),
+            status,
+            detail: Some("Injected fault".to_string()),
+            ..Default::default()
+        };
+        let mut response = (status, Json(problem)).into_response();
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(RETRY_AFTER));
+        }
+        Some(response)
+    }
+}
+
+/// A random number in `[0, 1)`.
+/// The random bits of a v4 UUID suffice here, which saves a dependency for a dev-only feature.
+fn random() -> f64 {
+    (Uuid::new_v4().as_u128() >> 80) as f64 / (1u64 << 48) as f64
+}
+
+fn happens(probability: f64) -> bool {
+    random() < probability
+}
+
+/// Middleware delaying requests and failing a fraction of them
+pub(crate) async fn inject_faults(
+    State(chaos): State<Arc<Chaos>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    tokio::time::sleep(chaos.latency()).await;
+
+    match chaos.error() {
+        Some(response) => response,
+        None => next.run(req).await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn status(chaos: Chaos) -> StatusCode {
+        Router::new()
+            .route("/programs", get(|| async {}))
+            .layer(middleware::from_fn_with_state(
+                Arc::new(chaos),
+                inject_faults,
+            ))
+            .oneshot(
+                Request::builder()
+                    .uri("/programs")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn error_rate() {
+        assert_eq!(status(Chaos::default()).await, StatusCode::OK);
+
+        let chaos = Chaos {
+            error_rate: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(status(chaos).await, StatusCode::SERVICE_UNAVAILABLE);
+
+        let chaos = Chaos {
+            error_rate: 1.0,
+            error_status: Some(StatusCode::BAD_GATEWAY),
+            ..Default::default()
+        };
+        assert_eq!(status(chaos).await, StatusCode::BAD_GATEWAY);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn latency() {
+        let chaos = Chaos {
+            max_latency: Duration::from_secs(10),
+            ..Default::default()
+        };
+
+        let start = tokio::time::Instant::now();
+        assert_eq!(status(chaos).await, StatusCode::OK);
+        assert!(start.elapsed() <= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn random_range() {
+        for _ in 0..1000 {
+            let random = random();
+            assert!((0.0..1.0).contains(&random));
+        }
+
+        assert!(!happens(0.0));
+        assert!(happens(1.0));
+    }
+}
@@ -0,0 +1,1104 @@
+//! Configuration of the VTN binary.
+//!
+//! The configuration is read from the TOML file at `OPENADR_CONFIG`, if set,
+//! and each setting can be overridden with an environment variable:
+//!
+//! ```toml
+//! listen_address = "0.0.0.0:3000"             # OPENADR_LISTEN_ADDRESS
+//! database_url = "postgres://..."             # DATABASE_URL
+//! default_page_size = 50                      # OPENADR_DEFAULT_PAGE_SIZE
+//! max_page_size = 50                          # OPENADR_MAX_PAGE_SIZE
+//! cors_origins = ["https://ui.example.com"]   # OPENADR_CORS_ORIGINS, comma-separated
+//! private_target_labels = ["METER_ID"]        # OPENADR_PRIVATE_TARGET_LABELS, comma-separated
+//! interval_order = "reject"                   # OPENADR_INTERVAL_ORDER, `warn` by default
+//! materialize_program_defaults = true         # OPENADR_MATERIALIZE_PROGRAM_DEFAULTS
+//! report_size_limit = 16777216                # OPENADR_REPORT_SIZE_LIMIT, in bytes
+//! webhooks = "EVENT=https://example.com/changes;batch=10"  # OPENADR_WEBHOOKS
+//! certification_vectors = "/etc/openadr/vectors.json"     # OPENADR_CERTIFICATION_VECTORS
+//! bootstrap_users_path = "/etc/openadr/users.json"        # OPENADR_BOOTSTRAP_USERS
+//! snapshot_path = "/var/lib/openadr/snapshot.json"        # OPENADR_SNAPSHOT_PATH
+//!
+//! [jwt]
+//! secret = "<base64>"                         # OPENADR_JWT_SECRET
+//! active_key = "2024-10"                      # sign new tokens with one of the keys
+//! refresh_token_days = 90                     # OPENADR_JWT_REFRESH_TOKEN_DAYS, issue refresh tokens
+//!
+//! # keys to validate tokens signed before a rotation, see `POST /admin/jwt-keys`
+//! [[jwt.keys]]
+//! kid = "2024-09"
+//! secret = "<base64>"
+//!
+//! # or key pairs, such that other services can validate the tokens with the public key
+//! [[jwt.keys]]
+//! kid = "2024-10"
+//! algorithm = "ES256"
+//! private_key_path = "/etc/openadr/jwt.pem"   # leave out for keys that only validate tokens
+//! public_key_path = "/etc/openadr/jwt.pub.pem"
+//!
+//! [tls]
+//! cert_path = "/etc/openadr/cert.pem"         # OPENADR_TLS_CERT
+//! key_path = "/etc/openadr/key.pem"           # OPENADR_TLS_KEY
+//!
+//! # accept the tokens of an external identity provider
+//! [oauth]
+//! jwks_url = "https://idp.example.com/.well-known/jwks.json"  # OPENADR_OAUTH_JWKS_URL
+//! issuer = "https://idp.example.com"          # OPENADR_OAUTH_ISSUER
+//! audience = "vtn"                            # OPENADR_OAUTH_AUDIENCE
+//! group_roles = "vtn-admins=UserManager,grid-operators=AnyBusiness"  # OPENADR_OAUTH_GROUP_ROLES
+//! groups_claim = "groups"
+//! ven_id_claim = "ven_id"                     # grants the VEN role for the VEN ids in the claim
+//! business_id_claim = "business_id"           # grants the Business role for the ids in the claim
+//!
+//! # or verify the tokens of an OIDC provider with its public key
+//! [oidc]
+//! issuer = "https://idp.example.com"          # OPENADR_OIDC_ISSUER
+//! audience = "vtn"                            # OPENADR_OIDC_AUDIENCE
+//! algorithm = "RS256"                         # OPENADR_OIDC_ALGORITHM
+//! key_path = "/etc/openadr/idp.pub.pem"       # OPENADR_OIDC_KEY
+//! group_roles = "vtn-admins=UserManager"      # OPENADR_OIDC_GROUP_ROLES
+//! groups_claim = "groups"                     # OPENADR_OIDC_GROUPS_CLAIM
+//!
+//! [event_signing]
+//! key_path = "/etc/openadr/signing.pem"       # OPENADR_EVENT_SIGNING_KEY
+//! algorithm = "ES256"                         # OPENADR_EVENT_SIGNING_ALGORITHM
+//!
+//! [report_quota]
+//! max_reports_per_hour = 60                   # OPENADR_REPORT_QUOTA_PER_HOUR
+//! max_intervals_per_report = 1000             # OPENADR_REPORT_MAX_INTERVALS
+//!
+//! # replaces the quota above for the reports of a program, by program id
+//! [report_quota.programs.program-1]
+//! max_reports_per_hour = 600
+//!
+//! [change_log]
+//! enabled = true                              # OPENADR_CHANGE_LOG
+//! retention_days = 30                         # OPENADR_CHANGE_LOG_RETENTION_DAYS
+//!
+//! [event_archive]
+//! enabled = true                              # OPENADR_EVENT_ARCHIVER
+//! archive_after_days = 90                     # OPENADR_EVENT_ARCHIVE_AFTER_DAYS
+//!
+//! # requires the `chaos` feature, never use in production
+//! [chaos]
+//! max_latency_ms = 500                        # OPENADR_CHAOS_MAX_LATENCY_MS
+//! error_rate = 0.1                            # OPENADR_CHAOS_ERROR_RATE
+//! error_status = 503                          # OPENADR_CHAOS_ERROR_STATUS
+//! notification_drop_rate = 0.1                # OPENADR_CHAOS_NOTIFICATION_DROP_RATE
+//!
+//! # users to create at startup, besides the users in the `bootstrap_users_path`
+//! [[bootstrap_users]]
+//! reference = "demo"
+//! roles = [{ role = "AnyBusiness" }]
+//! credentials = [{ client_id = "demo", client_secret_hash = "$argon2id$..." }]
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use axum::http::HeaderValue;
+use jsonwebtoken::{Algorithm, DecodingKey};
+use openadr_wire::program::ProgramId;
+use serde::Deserialize;
+
+use crate::{
+    api::{event::IntervalOrderPolicy, PageSize, MAX_PAGE_SIZE},
+    bootstrap::{self, BootstrapUser},
+    certification::{self, Vector},
+    data_source::directory::{DirectoryAuthSource, GroupMapping, OidcDirectory},
+    jwks::{ClaimRoles, JwksValidator},
+    jwt::{JwtManager, SigningKey},
+    notifier::StaticSubscriptions,
+    report_quota::{ReportQuota, ReportQuotas},
+    signing::EventSigner,
+};
+
+/// The environment variable with the path of the configuration file
+pub const CONFIG_PATH_VAR: &str = "OPENADR_CONFIG";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("could not read {}: {source}", path.display())]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid configuration file {}: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid {name}: {reason}")]
+    Var { name: &'static str, reason: String },
+    #[error("invalid configuration: {0}")]
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub listen_address: SocketAddr,
+    /// Only used by the `postgres` storage
+    pub database_url: Option<String>,
+    pub jwt: JwtConfig,
+    /// Defaults to the `max_page_size`, or 50 if that is larger
+    pub default_page_size: Option<usize>,
+    /// Defaults to 50
+    pub max_page_size: Option<usize>,
+    /// The origins of browser applications allowed to call the VTN, none by default
+    pub cors_origins: Vec<String>,
+    pub tls: Option<TlsConfig>,
+    pub oauth: Option<OAuthConfig>,
+    pub oidc: Option<OidcConfig>,
+    /// Only accept these private target labels, or any label if not set
+    pub private_target_labels: Option<Vec<String>>,
+    /// `warn` or `reject`, see [`IntervalOrderPolicy`]
+    pub interval_order: Option<String>,
+    pub materialize_program_defaults: bool,
+    /// The maximum size of a report body in bytes, 16 MiB by default
+    pub report_size_limit: Option<usize>,
+    pub report_quota: ReportQuotaConfig,
+    /// The callback URLs notified of changes, see [`StaticSubscriptions`]
+    pub webhooks: Option<String>,
+    pub event_signing: Option<EventSigningConfig>,
+    /// Only used by the `postgres` storage
+    pub change_log: ChangeLogConfig,
+    /// Only used by the `postgres` storage
+    pub event_archive: EventArchiveConfig,
+    /// A JSON file with the canned responses of the certification test tool
+    pub certification_vectors: Option<PathBuf>,
+    /// Users to create at startup, see [`bootstrap`]
+    pub bootstrap_users: Vec<BootstrapUser>,
+    /// A JSON file with further users to create at startup
+    pub bootstrap_users_path: Option<PathBuf>,
+    /// The snapshot to load the objects from and save them to,
+    /// only used by the `in-memory` storage
+    pub snapshot_path: Option<PathBuf>,
+    /// Requires the `chaos` feature
+    pub chaos: Option<ChaosConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen_address: (Ipv4Addr::UNSPECIFIED, 3000).into(),
+            database_url: None,
+            jwt: Default::default(),
+            default_page_size: None,
+            max_page_size: None,
+            cors_origins: vec![],
+            tls: None,
+            oauth: None,
+            oidc: None,
+            private_target_labels: None,
+            interval_order: None,
+            materialize_program_defaults: false,
+            report_size_limit: None,
+            report_quota: ReportQuotaConfig::default(),
+            webhooks: None,
+            event_signing: None,
+            change_log: ChangeLogConfig::default(),
+            event_archive: EventArchiveConfig::default(),
+            certification_vectors: None,
+            bootstrap_users: vec![],
+            bootstrap_users_path: None,
+            snapshot_path: None,
+            chaos: None,
+        }
+    }
+}
+
+/// The keys the VTN signs its tokens with. Debug output leaves out the secrets.
+#[derive(Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JwtConfig {
+    /// The base64 encoded secret to sign tokens with, identified as the
+    /// [`INITIAL_KEY_ID`](crate::jwt::INITIAL_KEY_ID)
+    pub secret: Option<String>,
+    /// Further keys to validate tokens with
+    pub keys: Vec<JwtKey>,
+    /// The `kid` of the key to sign new tokens with instead of the `secret`
+    pub active_key: Option<String>,
+    /// Issue refresh tokens valid for this many days with each access token
+    pub refresh_token_days: Option<u64>,
+}
+
+impl fmt::Debug for JwtConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("secret", &self.secret.as_ref().map(|_| "..."))
+            .field("keys", &self.keys)
+            .field("active_key", &self.active_key)
+            .field("refresh_token_days", &self.refresh_token_days)
+            .finish()
+    }
+}
+
+/// Either a `secret` for HS256, or the PEM encoded public key of the `algorithm`,
+/// with the private key unless the key only validates tokens
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JwtKey {
+    pub kid: String,
+    /// Base64 encoded
+    pub secret: Option<String>,
+    pub algorithm: Option<Algorithm>,
+    pub private_key_path: Option<PathBuf>,
+    pub public_key_path: Option<PathBuf>,
+}
+
+impl JwtKey {
+    fn signing_key(&self) -> Result<SigningKey, ConfigError> {
+        let invalid =
+            |reason: String| ConfigError::Invalid(format!("JWT key {}: {reason}", self.kid));
+
+        match (&self.secret, self.algorithm, &self.public_key_path) {
+            (Some(secret), None, None) if self.private_key_path.is_none() => {
+                SigningKey::from_base64_secret(secret)
+                    .map_err(|_| invalid("the secret is not valid base64".to_string()))
+            }
+            (None, Some(algorithm), Some(public_key_path)) => {
+                let public_pem = read_key(public_key_path)?;
+                let key = match &self.private_key_path {
+                    Some(path) => SigningKey::from_pem(algorithm, &read_key(path)?, &public_pem),
+                    None => SigningKey::verify_only(algorithm, &public_pem),
+                };
+                key.map_err(|err| invalid(format!("invalid {algorithm:?} key: {err}")))
+            }
+            _ => Err(invalid(
+                "configure either a `secret`, or an `algorithm` with a `public_key_path`"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+fn read_key(path: &Path) -> Result<Vec<u8>, ConfigError> {
+    std::fs::read(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+impl fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JwtKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .field("private_key_path", &self.private_key_path)
+            .field("public_key_path", &self.public_key_path)
+            .finish_non_exhaustive()
+    }
+}
+
+/// PEM encoded certificate chain and private key to terminate TLS with, requires the `tls` feature
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+/// An external identity provider whose tokens the VTN accepts, see [`JwksValidator`]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OAuthConfig {
+    pub jwks_url: String,
+    pub issuer: String,
+    pub audience: String,
+    /// Comma-separated `group=role` pairs, see [`GroupMapping`]
+    pub group_roles: String,
+    /// Defaults to `groups`
+    pub groups_claim: Option<String>,
+    pub ven_id_claim: Option<String>,
+    pub business_id_claim: Option<String>,
+}
+
+/// An OIDC provider whose tokens the VTN verifies with the public key of the provider,
+/// see [`OidcDirectory`]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub audience: String,
+    /// Defaults to RS256
+    pub algorithm: Option<Algorithm>,
+    /// The PEM encoded public key of the provider
+    pub key_path: Option<PathBuf>,
+    /// Comma-separated `group=role` pairs, see [`GroupMapping`]
+    pub group_roles: String,
+    /// Defaults to `groups`
+    pub groups_claim: Option<String>,
+}
+
+/// The [`ReportQuota`] of all programs, except the programs with a quota of their own
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportQuotaConfig {
+    pub max_reports_per_hour: Option<usize>,
+    pub max_intervals_per_report: Option<usize>,
+    pub programs: HashMap<ProgramId, ReportQuota>,
+}
+
+/// The PEM encoded private key to sign events with, see [`EventSigner`]
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventSigningConfig {
+    pub key_path: Option<PathBuf>,
+    /// Defaults to ES256
+    pub algorithm: Option<Algorithm>,
+}
+
+/// Record the changes made through the API in the `object_changes` table
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangeLogConfig {
+    pub enabled: bool,
+    pub retention_days: u64,
+}
+
+impl Default for ChangeLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            retention_days: 30,
+        }
+    }
+}
+
+/// Complete events once their last interval ended, and archive them some days later
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EventArchiveConfig {
+    pub enabled: bool,
+    /// Keep the completed events in the `event` table if not set
+    pub archive_after_days: Option<u64>,
+}
+
+/// The faults to inject for resilience testing, see `Chaos`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChaosConfig {
+    pub max_latency_ms: u64,
+    pub error_rate: f64,
+    /// Defaults to 503
+    pub error_status: Option<u16>,
+    pub notification_drop_rate: f64,
+}
+
+impl Config {
+    /// Read the configuration file at `OPENADR_CONFIG`, if set,
+    /// and override its settings with the environment variables
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match std::env::var_os(CONFIG_PATH_VAR) {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+
+        config.apply_env(|name| std::env::var(name).ok())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let toml = match std::fs::read_to_string(&path) {
+            Ok(toml) => toml,
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+
+        toml::from_str(&toml).map_err(|source| ConfigError::Parse { path, source })
+    }
+
+    /// Override the settings with the variables returned by `var`, e.g., [`std::env::var`]
+    pub fn apply_env(
+        &mut self,
+        var: impl Fn(&'static str) -> Option<String>,
+    ) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(name: &'static str, value: String) -> Result<T, ConfigError>
+        where
+            T::Err: fmt::Display,
+        {
+            value
+                .trim()
+                .parse()
+                .map_err(|err: T::Err| ConfigError::Var {
+                    name,
+                    reason: err.to_string(),
+                })
+        }
+
+        if let Some(address) = var("OPENADR_LISTEN_ADDRESS") {
+            self.listen_address = parse("OPENADR_LISTEN_ADDRESS", address)?;
+        }
+        if let Some(url) = var("DATABASE_URL") {
+            self.database_url = Some(url);
+        }
+        if let Some(secret) = var("OPENADR_JWT_SECRET") {
+            self.jwt.secret = Some(secret);
+        }
+        if let Some(days) = var("OPENADR_JWT_REFRESH_TOKEN_DAYS") {
+            self.jwt.refresh_token_days = Some(parse("OPENADR_JWT_REFRESH_TOKEN_DAYS", days)?);
+        }
+        if let Some(size) = var("OPENADR_DEFAULT_PAGE_SIZE") {
+            self.default_page_size = Some(parse("OPENADR_DEFAULT_PAGE_SIZE", size)?);
+        }
+        if let Some(size) = var("OPENADR_MAX_PAGE_SIZE") {
+            self.max_page_size = Some(parse("OPENADR_MAX_PAGE_SIZE", size)?);
+        }
+        if let Some(origins) = var("OPENADR_CORS_ORIGINS") {
+            self.cors_origins = origins
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+
+        match (var("OPENADR_TLS_CERT"), var("OPENADR_TLS_KEY")) {
+            (Some(cert_path), Some(key_path)) => {
+                self.tls = Some(TlsConfig {
+                    cert_path: cert_path.into(),
+                    key_path: key_path.into(),
+                })
+            }
+            (None, None) => {}
+            (Some(_), None) | (None, Some(_)) => {
+                return Err(ConfigError::Invalid(
+                    "set both OPENADR_TLS_CERT and OPENADR_TLS_KEY, or neither".to_string(),
+                ))
+            }
+        }
+
+        if let Some(url) = var("OPENADR_OAUTH_JWKS_URL") {
+            self.oauth.get_or_insert_with(Default::default).jwks_url = url;
+        }
+        if let Some(issuer) = var("OPENADR_OAUTH_ISSUER") {
+            self.oauth.get_or_insert_with(Default::default).issuer = issuer;
+        }
+        if let Some(audience) = var("OPENADR_OAUTH_AUDIENCE") {
+            self.oauth.get_or_insert_with(Default::default).audience = audience;
+        }
+        if let Some(group_roles) = var("OPENADR_OAUTH_GROUP_ROLES") {
+            self.oauth.get_or_insert_with(Default::default).group_roles = group_roles;
+        }
+
+        if let Some(issuer) = var("OPENADR_OIDC_ISSUER") {
+            self.oidc.get_or_insert_with(Default::default).issuer = issuer;
+        }
+        if let Some(audience) = var("OPENADR_OIDC_AUDIENCE") {
+            self.oidc.get_or_insert_with(Default::default).audience = audience;
+        }
+        if let Some(algorithm) = var("OPENADR_OIDC_ALGORITHM") {
+            self.oidc.get_or_insert_with(Default::default).algorithm =
+                Some(parse("OPENADR_OIDC_ALGORITHM", algorithm)?);
+        }
+        if let Some(key_path) = var("OPENADR_OIDC_KEY") {
+            self.oidc.get_or_insert_with(Default::default).key_path = Some(key_path.into());
+        }
+        if let Some(group_roles) = var("OPENADR_OIDC_GROUP_ROLES") {
+            self.oidc.get_or_insert_with(Default::default).group_roles = group_roles;
+        }
+        if let Some(claim) = var("OPENADR_OIDC_GROUPS_CLAIM") {
+            self.oidc.get_or_insert_with(Default::default).groups_claim = Some(claim);
+        }
+
+        if let Some(labels) = var("OPENADR_PRIVATE_TARGET_LABELS") {
+            self.private_target_labels = Some(
+                labels
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|label| !label.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+            );
+        }
+        if let Some(policy) = var("OPENADR_INTERVAL_ORDER") {
+            self.interval_order = Some(policy);
+        }
+        if let Some(enabled) = var("OPENADR_MATERIALIZE_PROGRAM_DEFAULTS") {
+            self.materialize_program_defaults =
+                parse("OPENADR_MATERIALIZE_PROGRAM_DEFAULTS", enabled)?;
+        }
+        if let Some(limit) = var("OPENADR_REPORT_SIZE_LIMIT") {
+            self.report_size_limit = Some(parse("OPENADR_REPORT_SIZE_LIMIT", limit)?);
+        }
+        if let Some(max) = var("OPENADR_REPORT_QUOTA_PER_HOUR") {
+            self.report_quota.max_reports_per_hour =
+                Some(parse("OPENADR_REPORT_QUOTA_PER_HOUR", max)?);
+        }
+        if let Some(max) = var("OPENADR_REPORT_MAX_INTERVALS") {
+            self.report_quota.max_intervals_per_report =
+                Some(parse("OPENADR_REPORT_MAX_INTERVALS", max)?);
+        }
+        if let Some(webhooks) = var("OPENADR_WEBHOOKS") {
+            self.webhooks = Some(webhooks);
+        }
+
+        if let Some(key_path) = var("OPENADR_EVENT_SIGNING_KEY") {
+            self.event_signing
+                .get_or_insert_with(Default::default)
+                .key_path = Some(key_path.into());
+        }
+        if let Some(algorithm) = var("OPENADR_EVENT_SIGNING_ALGORITHM") {
+            self.event_signing
+                .get_or_insert_with(Default::default)
+                .algorithm = Some(parse("OPENADR_EVENT_SIGNING_ALGORITHM", algorithm)?);
+        }
+
+        if let Some(enabled) = var("OPENADR_CHANGE_LOG") {
+            self.change_log.enabled = parse("OPENADR_CHANGE_LOG", enabled)?;
+        }
+        if let Some(days) = var("OPENADR_CHANGE_LOG_RETENTION_DAYS") {
+            self.change_log.retention_days = parse("OPENADR_CHANGE_LOG_RETENTION_DAYS", days)?;
+        }
+        if let Some(enabled) = var("OPENADR_EVENT_ARCHIVER") {
+            self.event_archive.enabled = parse("OPENADR_EVENT_ARCHIVER", enabled)?;
+        }
+        if let Some(days) = var("OPENADR_EVENT_ARCHIVE_AFTER_DAYS") {
+            self.event_archive.archive_after_days =
+                Some(parse("OPENADR_EVENT_ARCHIVE_AFTER_DAYS", days)?);
+        }
+
+        if let Some(path) = var("OPENADR_CERTIFICATION_VECTORS") {
+            self.certification_vectors = Some(path.into());
+        }
+        if let Some(path) = var("OPENADR_BOOTSTRAP_USERS") {
+            self.bootstrap_users_path = Some(path.into());
+        }
+        if let Some(path) = var("OPENADR_SNAPSHOT_PATH") {
+            self.snapshot_path = Some(path.into());
+        }
+
+        if let Some(latency) = var("OPENADR_CHAOS_MAX_LATENCY_MS") {
+            self.chaos
+                .get_or_insert_with(Default::default)
+                .max_latency_ms = parse("OPENADR_CHAOS_MAX_LATENCY_MS", latency)?;
+        }
+        if let Some(rate) = var("OPENADR_CHAOS_ERROR_RATE") {
+            self.chaos.get_or_insert_with(Default::default).error_rate =
+                parse("OPENADR_CHAOS_ERROR_RATE", rate)?;
+        }
+        if let Some(status) = var("OPENADR_CHAOS_ERROR_STATUS") {
+            self.chaos.get_or_insert_with(Default::default).error_status =
+                Some(parse("OPENADR_CHAOS_ERROR_STATUS", status)?);
+        }
+        if let Some(rate) = var("OPENADR_CHAOS_NOTIFICATION_DROP_RATE") {
+            self.chaos
+                .get_or_insert_with(Default::default)
+                .notification_drop_rate = parse("OPENADR_CHAOS_NOTIFICATION_DROP_RATE", rate)?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the settings, including the JWT keys they refer to,
+    /// such that the VTN fails at startup rather than at the first request
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.page_size()?;
+        self.cors_origins()?;
+        self.jwt_manager()?;
+        self.jwks_validator()?;
+        self.oidc_auth_source()?;
+        self.interval_order()?;
+        self.webhooks()?;
+        self.event_signer()?;
+        self.certification_vectors()?;
+        self.bootstrap_users()?;
+        #[cfg(feature = "chaos")]
+        self.chaos()?;
+        Ok(())
+    }
+
+    pub fn page_size(&self) -> Result<PageSize, ConfigError> {
+        let max = self.max_page_size.unwrap_or(MAX_PAGE_SIZE);
+        let default = self.default_page_size.unwrap_or(MAX_PAGE_SIZE.min(max));
+
+        PageSize::new(default, max).ok_or_else(|| {
+            ConfigError::Invalid(format!(
+                "the default page size {default} must be between 1 and the max page size {max}"
+            ))
+        })
+    }
+
+    pub fn cors_origins(&self) -> Result<Vec<HeaderValue>, ConfigError> {
+        self.cors_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin)
+                    .map_err(|_| ConfigError::Invalid(format!("invalid CORS origin {origin:?}")))
+            })
+            .collect()
+    }
+
+    /// The JWT manager with the configured keys, or `None` if neither a `secret` nor keys are configured
+    pub fn jwt_manager(&self) -> Result<Option<JwtManager>, ConfigError> {
+        let mut keys = self
+            .jwt
+            .keys
+            .iter()
+            .map(|key| Ok((key.kid.as_str(), key.signing_key()?)))
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+
+        let jwt_manager = match (&self.jwt.secret, &self.jwt.active_key) {
+            (Some(secret), _) => JwtManager::from_base64_secret(secret).map_err(|_| {
+                ConfigError::Invalid("the JWT secret is not valid base64".to_string())
+            })?,
+            (None, None) if keys.is_empty() => return Ok(None),
+            (None, None) => {
+                return Err(ConfigError::Invalid(
+                    "configure the JWT `active_key` to sign tokens with, or a JWT secret"
+                        .to_string(),
+                ))
+            }
+            // sign with the active key from the start
+            (None, Some(active)) => {
+                let Some(index) = keys.iter().position(|(kid, _)| kid == active) else {
+                    return Err(ConfigError::Invalid(format!("no JWT key with id {active}")));
+                };
+                let (kid, key) = keys.swap_remove(index);
+                if !key.can_sign() {
+                    return Err(ConfigError::Invalid(format!(
+                        "the active JWT key {kid} has no private key"
+                    )));
+                }
+                JwtManager::with_key(kid, key)
+            }
+        };
+
+        for (kid, key) in keys {
+            jwt_manager
+                .add_key(kid, key)
+                .map_err(|err| ConfigError::Invalid(err.to_string()))?;
+        }
+        if let Some(kid) = &self.jwt.active_key {
+            jwt_manager
+                .activate_key(kid)
+                .map_err(|err| ConfigError::Invalid(err.to_string()))?;
+        }
+
+        Ok(Some(jwt_manager))
+    }
+
+    /// The validator of the tokens of the external identity provider, if configured
+    pub fn jwks_validator(&self) -> Result<Option<JwksValidator>, ConfigError> {
+        let Some(oauth) = &self.oauth else {
+            return Ok(None);
+        };
+        let invalid = |reason: String| ConfigError::Invalid(format!("oauth: {reason}"));
+
+        let url = oauth
+            .jwks_url
+            .parse()
+            .map_err(|err| invalid(format!("invalid jwks_url {:?}: {err}", oauth.jwks_url)))?;
+        if oauth.issuer.is_empty() || oauth.audience.is_empty() {
+            return Err(invalid("configure the issuer and audience".to_string()));
+        }
+
+        let groups: GroupMapping = oauth
+            .group_roles
+            .parse()
+            .map_err(|err| invalid(format!("invalid group_roles: {err}")))?;
+        let mut roles = ClaimRoles::new(groups);
+        if let Some(claim) = &oauth.groups_claim {
+            roles = roles.with_groups_claim(claim);
+        }
+        if let Some(claim) = &oauth.ven_id_claim {
+            roles = roles.with_ven_id_claim(claim);
+        }
+        if let Some(claim) = &oauth.business_id_claim {
+            roles = roles.with_business_id_claim(claim);
+        }
+
+        Ok(Some(JwksValidator::new(
+            url,
+            &oauth.issuer,
+            &oauth.audience,
+            roles,
+        )))
+    }
+
+    /// The source of the roles of clients authenticating with the tokens of the OIDC provider,
+    /// if configured
+    pub fn oidc_auth_source(
+        &self,
+    ) -> Result<Option<DirectoryAuthSource<OidcDirectory>>, ConfigError> {
+        let Some(oidc) = &self.oidc else {
+            return Ok(None);
+        };
+        let invalid = |reason: String| ConfigError::Invalid(format!("oidc: {reason}"));
+
+        let (Some(key_path), false, false) = (
+            &oidc.key_path,
+            oidc.issuer.is_empty(),
+            oidc.audience.is_empty(),
+        ) else {
+            return Err(invalid(
+                "configure the issuer, audience and key_path".to_string(),
+            ));
+        };
+
+        let algorithm = oidc.algorithm.unwrap_or(Algorithm::RS256);
+        let pem = read_key(key_path)?;
+        let decoding_key = match algorithm {
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(&pem),
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(&pem),
+            _ => DecodingKey::from_rsa_pem(&pem),
+        }
+        .map_err(|err| invalid(format!("invalid {algorithm:?} key: {err}")))?;
+
+        let mapping: GroupMapping = oidc
+            .group_roles
+            .parse()
+            .map_err(|err| invalid(format!("invalid group_roles: {err}")))?;
+
+        let mut directory =
+            OidcDirectory::new(&oidc.issuer, &oidc.audience, algorithm, decoding_key);
+        if let Some(claim) = &oidc.groups_claim {
+            directory = directory.with_groups_claim(claim);
+        }
+
+        Ok(Some(DirectoryAuthSource::new(directory, mapping)))
+    }
+
+    pub fn interval_order(&self) -> Result<Option<IntervalOrderPolicy>, ConfigError> {
+        self.interval_order
+            .as_deref()
+            .map(|policy| {
+                policy.parse().map_err(|reason: &str| {
+                    ConfigError::Invalid(format!("invalid interval_order {policy:?}: {reason}"))
+                })
+            })
+            .transpose()
+    }
+
+    /// The report quotas, or `None` if no quota is configured
+    pub fn report_quotas(&self) -> Option<ReportQuotas> {
+        let config = &self.report_quota;
+        if *config == ReportQuotaConfig::default() {
+            return None;
+        }
+
+        let default = ReportQuota {
+            max_reports_per_hour: config.max_reports_per_hour,
+            max_intervals_per_report: config.max_intervals_per_report,
+        };
+        Some(
+            config
+                .programs
+                .iter()
+                .fold(ReportQuotas::new(default), |quotas, (program_id, quota)| {
+                    quotas.with_program(program_id.clone(), *quota)
+                }),
+        )
+    }
+
+    pub fn webhooks(&self) -> Result<Option<StaticSubscriptions>, ConfigError> {
+        self.webhooks
+            .as_deref()
+            .map(|webhooks| {
+                webhooks
+                    .parse()
+                    .map_err(|reason| ConfigError::Invalid(format!("invalid webhooks: {reason}")))
+            })
+            .transpose()
+    }
+
+    /// The signer of the events, if a signing key is configured
+    pub fn event_signer(&self) -> Result<Option<EventSigner>, ConfigError> {
+        let Some(signing) = &self.event_signing else {
+            return Ok(None);
+        };
+        let Some(key_path) = &signing.key_path else {
+            return Err(ConfigError::Invalid(
+                "event_signing: configure the key_path".to_string(),
+            ));
+        };
+
+        let algorithm = signing.algorithm.unwrap_or(Algorithm::ES256);
+        EventSigner::from_pem(algorithm, &read_key(key_path)?)
+            .map(Some)
+            .map_err(|err| {
+                ConfigError::Invalid(format!("event_signing: invalid {algorithm:?} key: {err}"))
+            })
+    }
+
+    pub fn certification_vectors(&self) -> Result<Option<Vec<Vector>>, ConfigError> {
+        let Some(path) = &self.certification_vectors else {
+            return Ok(None);
+        };
+
+        certification::parse_vectors(&read_to_string(path)?)
+            .map(Some)
+            .map_err(|err| {
+                ConfigError::Invalid(format!(
+                    "invalid certification vectors {}: {err}",
+                    path.display()
+                ))
+            })
+    }
+
+    /// The users of the configuration file, followed by the users in the `bootstrap_users_path`
+    pub fn bootstrap_users(&self) -> Result<Vec<BootstrapUser>, ConfigError> {
+        let mut users = self.bootstrap_users.clone();
+
+        if let Some(path) = &self.bootstrap_users_path {
+            let from_file = bootstrap::parse_users(&read_to_string(path)?).map_err(|err| {
+                ConfigError::Invalid(format!("invalid bootstrap users {}: {err}", path.display()))
+            })?;
+            users.extend(from_file);
+        }
+
+        Ok(users)
+    }
+
+    /// The faults to inject, none unless configured
+    #[cfg(feature = "chaos")]
+    pub fn chaos(&self) -> Result<crate::chaos::Chaos, ConfigError> {
+        let chaos = self.chaos.clone().unwrap_or_default();
+
+        let error_status = chaos
+            .error_status
+            .map(|status| {
+                axum::http::StatusCode::from_u16(status).map_err(|_| {
+                    ConfigError::Invalid(format!("chaos: invalid error_status {status}"))
+                })
+            })
+            .transpose()?;
+
+        Ok(crate::chaos::Chaos {
+            max_latency: std::time::Duration::from_millis(chaos.max_latency_ms),
+            error_rate: chaos.error_rate,
+            error_status,
+            notification_drop_rate: chaos.notification_drop_rate,
+        })
+    }
+}
+
+fn read_to_string(path: &Path) -> Result<String, ConfigError> {
+    std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_overrides_file() {
+        let mut config: Config = toml::from_str(
+            r#"
+            listen_address = "127.0.0.1:8080"
+            max_page_size = 100
+            cors_origins = ["https://ui.example.com"]
+
+            [jwt]
+            secret = "dGVzdA=="
+            keys = [{ kid = "next", secret = "bmV4dA==" }]
+            active_key = "next"
+
+            [oauth]
+            jwks_url = "https://idp.example.com/jwks.json"
+            issuer = "https://idp.example.com"
+            audience = "vtn"
+            group_roles = "vtn-admins=UserManager"
+            "#,
+        )
+        .unwrap();
+
+        config
+            .apply_env(|name| match name {
+                "OPENADR_LISTEN_ADDRESS" => Some("0.0.0.0:4000".to_string()),
+                "OPENADR_TLS_CERT" => Some("cert.pem".to_string()),
+                "OPENADR_TLS_KEY" => Some("key.pem".to_string()),
+                "OPENADR_OAUTH_AUDIENCE" => Some("openadr".to_string()),
+                "OPENADR_JWT_REFRESH_TOKEN_DAYS" => Some("90".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(config.listen_address, "0.0.0.0:4000".parse().unwrap());
+        assert_eq!(config.page_size().unwrap(), PageSize::new(50, 100).unwrap());
+        assert_eq!(config.cors_origins().unwrap(), ["https://ui.example.com"]);
+        assert_eq!(
+            config.tls,
+            Some(TlsConfig {
+                cert_path: "cert.pem".into(),
+                key_path: "key.pem".into(),
+            })
+        );
+        assert_eq!(config.oauth.as_ref().unwrap().audience, "openadr");
+        assert_eq!(config.jwt.refresh_token_days, Some(90));
+        assert!(config.jwks_validator().unwrap().is_some());
+        let key_ids = config.jwt_manager().unwrap().unwrap().key_ids();
+        assert_eq!(key_ids.active, "next");
+        assert!(!format!("{config:?}").contains("dGVzdA=="));
+    }
+
+    #[test]
+    fn features() {
+        let mut config: Config = toml::from_str(
+            r#"
+            interval_order = "reject"
+            webhooks = "EVENT=http://localhost/changes"
+
+            [report_quota]
+            max_reports_per_hour = 60
+
+            [report_quota.programs.program-1]
+            max_reports_per_hour = 600
+
+            [change_log]
+            enabled = true
+
+            [[bootstrap_users]]
+            reference = "demo"
+            roles = [{ role = "AnyBusiness" }]
+            credentials = [{ client_id = "demo", client_secret_hash = "$argon2id$..." }]
+            "#,
+        )
+        .unwrap();
+
+        config
+            .apply_env(|name| match name {
+                "OPENADR_CHANGE_LOG_RETENTION_DAYS" => Some("7".to_string()),
+                "OPENADR_REPORT_MAX_INTERVALS" => Some("1000".to_string()),
+                "OPENADR_PRIVATE_TARGET_LABELS" => Some("METER_ID, FEEDER".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        config.validate().unwrap();
+
+        assert_eq!(
+            config.interval_order().unwrap(),
+            Some(IntervalOrderPolicy::Reject)
+        );
+        assert!(config.webhooks().unwrap().is_some());
+        let quotas = config.report_quotas().unwrap();
+        assert_eq!(
+            quotas.quota(&ProgramId::new("program-2").unwrap()),
+            ReportQuota {
+                max_reports_per_hour: Some(60),
+                max_intervals_per_report: Some(1000),
+            }
+        );
+        assert_eq!(
+            quotas.quota(&ProgramId::new("program-1").unwrap()),
+            ReportQuota {
+                max_reports_per_hour: Some(600),
+                max_intervals_per_report: None,
+            }
+        );
+        assert_eq!(
+            config.change_log,
+            ChangeLogConfig {
+                enabled: true,
+                retention_days: 7,
+            }
+        );
+        assert_eq!(
+            config.private_target_labels,
+            Some(vec!["METER_ID".to_string(), "FEEDER".to_string()])
+        );
+        let users = config.bootstrap_users().unwrap();
+        assert_eq!(users[0].reference, "demo");
+        assert_eq!(users[0].credentials[0].client_id, "demo");
+
+        let err = Config::default()
+            .apply_env(|name| (name == "OPENADR_EVENT_ARCHIVER").then(|| "yes".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("OPENADR_EVENT_ARCHIVER"), "{err}");
+
+        let config = Config {
+            interval_order: Some("ignore".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        let mut config = Config::default();
+        config
+            .apply_env(|name| (name == "OPENADR_OIDC_ISSUER").then(|| "issuer".to_string()))
+            .unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("key_path"), "{err}");
+    }
+
+    #[test]
+    fn key_pairs() {
+        let keys = concat!(env!("CARGO_MANIFEST_DIR"), "/test-keys");
+        let mut config: Config = toml::from_str(&format!(
+            r#"
+            [jwt]
+            active_key = "es"
+
+            [[jwt.keys]]
+            kid = "es"
+            algorithm = "ES256"
+            private_key_path = "{keys}/es256.pem"
+            public_key_path = "{keys}/es256.pub.pem"
+            "#
+        ))
+        .unwrap();
+
+        let jwt_manager = config.jwt_manager().unwrap().unwrap();
+        assert_eq!(jwt_manager.key_ids().active, "es");
+        assert_eq!(jwt_manager.key_ids().keys, ["es"]);
+
+        // without the private key, the key cannot sign tokens
+        config.jwt.keys[0].private_key_path = None;
+        assert!(matches!(
+            config.jwt_manager(),
+            Err(ConfigError::Invalid(reason)) if reason.contains("no private key")
+        ));
+
+        config.jwt.keys[0].public_key_path = Some(format!("{keys}/missing.pem").into());
+        assert!(matches!(
+            config.jwt_manager(),
+            Err(ConfigError::Read { .. })
+        ));
+    }
+
+    #[test]
+    fn clear_errors() {
+        assert_eq!(Config::default().jwt_manager().unwrap().map(|_| ()), None);
+
+        let err = toml::from_str::<Config>("listen_adress = \"0.0.0.0:3000\"").unwrap_err();
+        assert!(err.to_string().contains("listen_adress"));
+
+        let err = Config::default()
+            .apply_env(|name| (name == "OPENADR_MAX_PAGE_SIZE").then(|| "many".to_string()))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid OPENADR_MAX_PAGE_SIZE: invalid digit found in string"
+        );
+
+        let config = Config {
+            default_page_size: Some(100),
+            ..Default::default()
+        };
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+
+        let err = Config::default()
+            .apply_env(|name| (name == "OPENADR_TLS_KEY").then(|| "key.pem".to_string()))
+            .unwrap_err();
+        assert!(err.to_string().contains("OPENADR_TLS_CERT"));
+
+        let mut config = Config::default();
+        config
+            .apply_env(|name| {
+                (name == "OPENADR_OAUTH_JWKS_URL")
+                    .then(|| "https://idp.example.com/jwks.json".to_string())
+            })
+            .unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("issuer and audience"), "{err}");
+    }
+}
@@ -0,0 +1,389 @@
+//! An [`AuthSource`] that authenticates clients against an enterprise directory,
+//! e.g., an OIDC provider or LDAP server, instead of the users stored by the VTN.
+//!
+//! The directory only provides the identity of a client and the groups it is a member of.
+//! A [`GroupMapping`] determines the [`AuthRole`]s of each group when a token is issued,
+//! such that operators do not have to maintain a parallel user store in the VTN.
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use axum::async_trait;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::Value;
+use tracing::{trace, warn};
+
+use crate::{
+    data_source::{AuthInfo, AuthSource, UserDetails},
+    error::AppError,
+    jwt::AuthRole,
+};
+
+/// A client as known by a [`Directory`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub subject: String,
+    pub groups: Vec<String>,
+}
+
+/// An external source of identities, e.g., an OIDC provider or LDAP server
+#[async_trait]
+pub trait Directory: Send + Sync + 'static {
+    /// Verify the credentials of a client, returning its identity if they are valid
+    async fn authenticate(&self, client_id: &str, client_secret: &str) -> Option<Identity>;
+}
+
+/// The roles granted to the members of each directory group
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupMapping {
+    groups: BTreeMap<String, Vec<AuthRole>>,
+}
+
+impl GroupMapping {
+    /// Grant the role to all members of the group
+    pub fn map(mut self, group: impl Into<String>, role: AuthRole) -> Self {
+        let roles = self.groups.entry(group.into()).or_default();
+        if !roles.contains(&role) {
+            roles.push(role);
+        }
+        self
+    }
+
+    /// The roles of a member of the given groups, without duplicates
+    pub fn roles(&self, groups: &[String]) -> Vec<AuthRole> {
+        let mut roles = vec![];
+        for role in groups
+            .iter()
+            .filter_map(|group| self.groups.get(group))
+            .flatten()
+        {
+            if !roles.contains(role) {
+                roles.push(role.clone());
+            }
+        }
+        roles
+    }
+}
+
+/// Parses a comma-separated list of `group=role` pairs, e.g.,
+/// `vtn-admins=UserManager,grid-operators=AnyBusiness,site-1=VEN:ven-1`.
+/// Roles with an id, i.e., `Business` and `VEN`, are written as `role:id`.
+impl FromStr for GroupMapping {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .try_fold(Self::default(), |mapping, pair| {
+                let (group, role) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected group=role, found {pair:?}"))?;
+
+                let role = match role.trim().split_once(':') {
+                    None => match role.trim() {
+                        "UserManager" => AuthRole::UserManager,
+                        "VenManager" => AuthRole::VenManager,
+                        "AnyBusiness" => AuthRole::AnyBusiness,
+                        other => return Err(format!("unknown role {other:?}")),
+                    },
+                    Some(("Business", id)) => AuthRole::Business(id.to_string()),
+                    Some(("VEN", id)) => AuthRole::VEN(
+                        id.parse()
+                            .map_err(|err| format!("invalid VEN id {id:?}: {err}"))?,
+                    ),
+                    Some((other, _)) => return Err(format!("unknown role {other:?}")),
+                };
+
+                Ok(mapping.map(group.trim(), role))
+            })
+    }
+}
+
+/// Authenticates clients with a [`Directory`], granting roles using a [`GroupMapping`].
+///
+/// Clients that are not a member of any mapped group are rejected.
+/// Users are managed in the directory, so the user management operations are not supported.
+pub struct DirectoryAuthSource<D> {
+    directory: D,
+    mapping: GroupMapping,
+}
+
+impl<D: Directory> DirectoryAuthSource<D> {
+    pub fn new(directory: D, mapping: GroupMapping) -> Self {
+        Self { directory, mapping }
+    }
+}
+
+const MANAGED_BY_DIRECTORY: AppError =
+    AppError::NotImplemented("users are managed in the directory");
+
+#[async_trait]
+impl<D: Directory> AuthSource for DirectoryAuthSource<D> {
+    async fn check_credentials(&self, client_id: &str, client_secret: &str) -> Option<AuthInfo> {
+        let identity = self
+            .directory
+            .authenticate(client_id, client_secret)
+            .await?;
+        let roles = self.mapping.roles(&identity.groups);
+
+        if roles.is_empty() {
+            warn!(
+                subject = identity.subject,
+                groups = ?identity.groups,
+                "client is not a member of any group with a role"
+            );
+            return None;
+        }
+
+        trace!(
+            subject = identity.subject,
+            ?roles,
+            "authenticated client with directory"
+        );
+        Some(AuthInfo {
+            client_id: identity.subject,
+            roles,
+        })
+    }
+
+    async fn find_client(&self, _client_id: &str) -> Option<AuthInfo> {
+        // the groups of a client can only be looked up with its secret,
+        // so clients authenticate with their credentials again instead
+        None
+    }
+
+    async fn get_user(&self, _user_id: &str) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn get_all_users(&self) -> Result<Vec<UserDetails>, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn add_user(
+        &self,
+        _reference: &str,
+        _description: Option<&str>,
+        _roles: &[AuthRole],
+    ) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn add_credential(
+        &self,
+        _user_id: &str,
+        _client_id: &str,
+        _client_secret: &str,
+    ) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn add_hashed_credential(
+        &self,
+        _user_id: &str,
+        _client_id: &str,
+        _client_secret_hash: &str,
+    ) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn remove_credentials(
+        &self,
+        _user_id: &str,
+        _client_id: &str,
+    ) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn remove_user(&self, _user_id: &str) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+
+    async fn edit_user(
+        &self,
+        _user_id: &str,
+        _reference: &str,
+        _description: Option<&str>,
+        _roles: &[AuthRole],
+    ) -> Result<UserDetails, AppError> {
+        Err(MANAGED_BY_DIRECTORY)
+    }
+}
+
+/// A [`Directory`] backed by an OIDC provider.
+///
+/// Instead of a client secret, the client sends a token issued by the OIDC provider,
+/// e.g., obtained with its own client credentials grant at the provider.
+/// The token must be signed by the provider, issued for the configured audience,
+/// and its `sub` must match the `client_id` the client authenticates with.
+/// The groups are read from the `groups` claim, which is a list of strings in most providers.
+pub struct OidcDirectory {
+    decoding_key: DecodingKey,
+    validation: Validation,
+    groups_claim: String,
+}
+
+impl OidcDirectory {
+    pub fn new(
+        issuer: &str,
+        audience: &str,
+        algorithm: Algorithm,
+        decoding_key: DecodingKey,
+    ) -> Self {
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        Self {
+            decoding_key,
+            validation,
+            groups_claim: "groups".to_string(),
+        }
+    }
+
+    /// Read the groups from another claim than `groups`, e.g., `roles`
+    pub fn with_groups_claim(mut self, claim: impl Into<String>) -> Self {
+        self.groups_claim = claim.into();
+        self
+    }
+}
+
+#[async_trait]
+impl Directory for OidcDirectory {
+    async fn authenticate(&self, client_id: &str, client_secret: &str) -> Option<Identity> {
+        let claims = jsonwebtoken::decode::<BTreeMap<String, Value>>(
+            client_secret,
+            &self.decoding_key,
+            &self.validation,
+        )
+        .inspect_err(|err| trace!(client_id, "invalid OIDC token: {err}"))
+        .ok()?
+        .claims;
+
+        let subject = claims.get("sub")?.as_str()?;
+        if subject != client_id {
+            trace!(
+                client_id,
+                subject,
+                "OIDC token was issued to another client"
+            );
+            return None;
+        }
+
+        let groups = match claims.get(&self.groups_claim) {
+            Some(Value::Array(groups)) => groups
+                .iter()
+                .filter_map(|group| group.as_str().map(ToString::to_string))
+                .collect(),
+            Some(Value::String(group)) => vec![group.clone()],
+            _ => vec![],
+        };
+
+        Some(Identity {
+            subject: subject.to_string(),
+            groups,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use openadr_wire::ven::VenId;
+
+    fn mapping() -> GroupMapping {
+        "vtn-admins=UserManager, grid-operators=AnyBusiness,site-1=VEN:ven-1,vtn-admins=VenManager"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_mapping() {
+        assert_eq!(
+            mapping(),
+            GroupMapping::default()
+                .map("vtn-admins", AuthRole::UserManager)
+                .map("vtn-admins", AuthRole::VenManager)
+                .map("grid-operators", AuthRole::AnyBusiness)
+                .map("site-1", AuthRole::VEN(VenId::new("ven-1").unwrap()))
+        );
+
+        assert!("admins".parse::<GroupMapping>().is_err());
+        assert!("admins=Superuser".parse::<GroupMapping>().is_err());
+        assert!("admins=Owner:1".parse::<GroupMapping>().is_err());
+    }
+
+    #[test]
+    fn roles_of_groups() {
+        let groups = ["site-1", "vtn-admins", "unmapped", "site-1"].map(String::from);
+        assert_eq!(
+            mapping().roles(&groups),
+            vec![
+                AuthRole::VEN(VenId::new("ven-1").unwrap()),
+                AuthRole::UserManager,
+                AuthRole::VenManager,
+            ]
+        );
+    }
+
+    fn oidc_token(sub: &str, aud: &str, groups: Value) -> String {
+        let claims = serde_json::json!({
+            "iss": "https://idp.example.com",
+            "aud": aud,
+            "sub": sub,
+            "exp": chrono::Utc::now().timestamp() + 60,
+            "groups": groups,
+        });
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"idp"),
+        )
+        .unwrap()
+    }
+
+    fn auth_source() -> DirectoryAuthSource<OidcDirectory> {
+        let directory = OidcDirectory::new(
+            "https://idp.example.com",
+            "vtn",
+            Algorithm::HS256,
+            DecodingKey::from_secret(b"idp"),
+        );
+        DirectoryAuthSource::new(directory, mapping())
+    }
+
+    #[tokio::test]
+    async fn oidc_credentials() {
+        let auth = auth_source();
+
+        let token = oidc_token("operator", "vtn", serde_json::json!(["grid-operators"]));
+        assert_eq!(
+            auth.check_credentials("operator", &token).await,
+            Some(AuthInfo {
+                client_id: "operator".to_string(),
+                roles: vec![AuthRole::AnyBusiness],
+            })
+        );
+
+        // the token was issued to another client
+        assert_eq!(auth.check_credentials("someone-else", &token).await, None);
+
+        // the token was issued for another audience
+        let token = oidc_token("operator", "other", serde_json::json!(["grid-operators"]));
+        assert_eq!(auth.check_credentials("operator", &token).await, None);
+
+        // no mapped groups
+        let token = oidc_token("guest", "vtn", serde_json::json!(["guests"]));
+        assert_eq!(auth.check_credentials("guest", &token).await, None);
+    }
+
+    #[tokio::test]
+    async fn users_are_managed_in_directory() {
+        assert!(matches!(
+            auth_source().get_all_users().await,
+            Err(AppError::NotImplemented(_))
+        ));
+    }
+}
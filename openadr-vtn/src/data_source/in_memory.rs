@@ -0,0 +1,2370 @@
+use std::{
+    io,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use argon2::{
+    password_hash::{rand_core::OsRng, SaltString},
+    Argon2, PasswordHash, PasswordHasher, PasswordVerifier,
+};
+use axum::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use openadr_wire::{
+    event::{EventContent, EventId, EventOrder},
+    program::{ProgramContent, ProgramId},
+    report::{LatestReportPayload, ReportContent, ReportId, ResourceName, ResourceOperatingState},
+    resource::{Resource, ResourceContent, ResourceId},
+    target::{TargetEntry, TargetLabel, TargetMap},
+    truncate_timestamp,
+    values_map::{Value, ValuesMap},
+    ven::{Ven, VenContent, VenId},
+    Event, Program, Report, SortBy,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard},
+    task::JoinHandle,
+    time::MissedTickBehavior,
+};
+use tracing::{error, info, trace, warn};
+use uuid::Uuid;
+
+use crate::{
+    api::{event, program, report, search, ListParams, Sorting},
+    data_source::{
+        AuthInfo, AuthSource, Crud, DataSource, EventCrud, ProgramCrud, ReportCrud, ResourceCrud,
+        TransactionFn, TransactionResult, UserDetails, VenCrud, VenPermissions, VenScopedCrud,
+    },
+    error::AppError,
+    jwt::{AuthRole, BusinessIds, Claims},
+    stats::{ObjectCounts, ProgramEvents, ReportVolume, Stats, StatsSource},
+};
+
+/// Keeps all objects in memory, such that small pilots, demos and tests can run without a database.
+///
+/// Optionally, the objects are persisted as a JSON snapshot on disk,
+/// see [`InMemoryStorage::with_snapshots`].
+/// Writes never wait for the disk: changes made after the last snapshot are lost
+/// when the VTN stops without calling [`InMemoryStorage::save_snapshot`].
+///
+/// All lookups scan the stored objects, so this storage is not meant for large deployments.
+#[derive(Clone, Default)]
+pub struct InMemoryStorage {
+    inner: Arc<Inner>,
+    /// Whether this storage is handed to the work of a transaction, see [`DataSource::run_transaction`]
+    in_transaction: bool,
+}
+
+#[derive(Default)]
+struct Inner {
+    objects: RwLock<Objects>,
+    snapshot_path: Option<PathBuf>,
+    /// Whether the objects changed since the last snapshot
+    dirty: AtomicBool,
+    /// Held while a transaction runs, such that transactions never interleave
+    transaction: Mutex<()>,
+}
+
+/// All stored objects, in the format of the snapshot file
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Objects {
+    programs: Vec<StoredProgram>,
+    events: Vec<Event>,
+    reports: Vec<Report>,
+    /// Without their resources, which are stored separately
+    vens: Vec<Ven>,
+    resources: Vec<Resource>,
+    users: Vec<UserDetails>,
+    credentials: Vec<StoredCredential>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredProgram {
+    program: Program,
+    business_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredCredential {
+    user_id: String,
+    client_id: String,
+    /// The client secret hashed as a PHC string, never the secret itself
+    client_secret_hash: String,
+}
+
+impl Inner {
+    async fn read(&self) -> RwLockReadGuard<'_, Objects> {
+        self.objects.read().await
+    }
+
+    /// Lock the objects for a modification, which is included in the next snapshot
+    async fn write(&self) -> RwLockWriteGuard<'_, Objects> {
+        let objects = self.objects.write().await;
+        self.dirty.store(true, Ordering::Relaxed);
+        objects
+    }
+}
+
+impl Objects {
+    fn program(&self, id: &ProgramId) -> Result<&StoredProgram, AppError> {
+        self.programs
+            .iter()
+            .find(|stored| &stored.program.id == id)
+            .ok_or(AppError::NotFound)
+    }
+
+    /// The VENs assigned to the program, through the targets of the program
+    fn program_vens<'a>(&'a self, program: &'a Program) -> impl Iterator<Item = &'a Ven> {
+        let targets = program.content.targets.as_ref();
+        self.vens.iter().filter(move |ven| {
+            targets_match(
+                targets,
+                &TargetLabel::VENName,
+                std::slice::from_ref(&ven.content.ven_name),
+            )
+        })
+    }
+
+    fn event(&self, id: &EventId) -> Result<&Event, AppError> {
+        self.events
+            .iter()
+            .find(|event| &event.id == id)
+            .ok_or(AppError::NotFound)
+    }
+
+    /// The checks of creating a program, returning the program to store
+    fn new_program(&self, new: ProgramContent, user: &Claims) -> Result<StoredProgram, AppError> {
+        let business_id = extract_business_id(user)?;
+
+        if let Some(existing) = self
+            .programs
+            .iter()
+            .find(|stored| stored.program.content.program_name == new.program_name)
+        {
+            return Err(duplicate_program_name(&existing.program));
+        }
+
+        let now = truncate_timestamp(Utc::now());
+        let program = Program {
+            id: new_id()?,
+            created_date_time: now,
+            modification_date_time: now,
+            content: new,
+        };
+
+        Ok(StoredProgram {
+            program,
+            business_id,
+        })
+    }
+
+    /// The checks of creating an event, returning the event to store
+    fn new_event(&self, new: EventContent, user: &Claims) -> Result<Event, AppError> {
+        check_write_permission(self.program(&new.program_id)?, user)?;
+
+        let now = truncate_timestamp(Utc::now());
+        Ok(Event {
+            id: new_id()?,
+            created_date_time: now,
+            modification_date_time: now,
+            content: new,
+        })
+    }
+
+    /// The checks of creating a report, returning the report to store
+    fn new_report(&self, new: ReportContent) -> Result<Report, AppError> {
+        if self.event(&new.event_id)?.content.program_id != new.program_id {
+            return Err(AppError::BadRequest(
+                "event_id and program_id have to point to the same program",
+            ));
+        }
+
+        if new.report_name.is_some()
+            && self
+                .reports
+                .iter()
+                .any(|report| report.content.report_name == new.report_name)
+        {
+            return Err(duplicate_name("report"));
+        }
+
+        let now = truncate_timestamp(Utc::now());
+        Ok(Report {
+            id: new_id()?,
+            created_date_time: now,
+            modification_date_time: now,
+            content: new,
+        })
+    }
+
+    fn ven(&self, id: &VenId) -> Result<&Ven, AppError> {
+        self.vens
+            .iter()
+            .find(|ven| &ven.id == id)
+            .ok_or(AppError::NotFound)
+    }
+
+    /// The VEN with its resources, as the Postgres storage returns it
+    fn with_resources(&self, ven: &Ven) -> Ven {
+        let mut ven = ven.clone();
+        ven.content.resources = Some(
+            self.resources
+                .iter()
+                .filter(|resource| resource.ven_id == ven.id)
+                .cloned()
+                .collect(),
+        );
+        ven
+    }
+
+    fn check_ven_name(&self, ven_name: &str, except: Option<&VenId>) -> Result<(), AppError> {
+        if self
+            .vens
+            .iter()
+            .any(|ven| Some(&ven.id) != except && ven.content.ven_name == ven_name)
+        {
+            return Err(AppError::Conflict(
+                "A VEN with this name already exists".to_string(),
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn check_resource_name(
+        &self,
+        resource_name: &str,
+        except: Option<&ResourceId>,
+    ) -> Result<(), AppError> {
+        if self.resources.iter().any(|resource| {
+            Some(&resource.id) != except && resource.content.resource_name == resource_name
+        }) {
+            return Err(AppError::Conflict(
+                "A resource with this name already exists".to_string(),
+                None,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn user(&self, id: &str) -> Result<&UserDetails, AppError> {
+        self.users
+            .iter()
+            .find(|user| user.id == id)
+            .ok_or(AppError::NotFound)
+    }
+
+    fn user_mut(&mut self, id: &str) -> Result<&mut UserDetails, AppError> {
+        self.users
+            .iter_mut()
+            .find(|user| user.id == id)
+            .ok_or(AppError::NotFound)
+    }
+
+    /// Like the foreign keys of the Postgres storage, VEN roles must refer to existing VENs
+    fn check_roles(&self, roles: &[AuthRole]) -> Result<(), AppError> {
+        for role in roles {
+            if let AuthRole::VEN(ven_id) = role {
+                self.ven(ven_id).map_err(|_| {
+                    AppError::ForeignKeyConstraintViolated(
+                        "The VEN does not exist".to_string(),
+                        None,
+                    )
+                })?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl InMemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the objects from the snapshot at `path`, if it exists,
+    /// and write later snapshots to the same file.
+    pub async fn with_snapshots(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+
+        let objects = match tokio::fs::read(&path).await {
+            Ok(json) => {
+                let objects: Objects = serde_json::from_slice(&json)?;
+                info!(
+                    path = %path.display(),
+                    programs = objects.programs.len(),
+                    events = objects.events.len(),
+                    reports = objects.reports.len(),
+                    vens = objects.vens.len(),
+                    resources = objects.resources.len(),
+                    users = objects.users.len(),
+                    "loaded snapshot"
+                );
+                objects
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                info!(path = %path.display(), "no snapshot found, starting empty");
+                Objects::default()
+            }
+            Err(err) => return Err(err),
+        };
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                objects: RwLock::new(objects),
+                snapshot_path: Some(path),
+                dirty: AtomicBool::new(false),
+                transaction: Mutex::new(()),
+            }),
+            in_transaction: false,
+        })
+    }
+
+    /// Write all objects to the snapshot file, if any.
+    ///
+    /// The snapshot is written to a temporary file first,
+    /// such that a crash while writing never corrupts the previous snapshot.
+    pub async fn save_snapshot(&self) -> io::Result<()> {
+        let Some(path) = &self.inner.snapshot_path else {
+            return Ok(());
+        };
+
+        let json = {
+            let objects = self.inner.read().await;
+            // no writer can be active while we hold the read lock
+            self.inner.dirty.store(false, Ordering::Relaxed);
+            serde_json::to_vec(&*objects)?
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        let result = async {
+            tokio::fs::write(&tmp_path, json).await?;
+            tokio::fs::rename(&tmp_path, path).await
+        }
+        .await;
+
+        match result {
+            Ok(()) => trace!(path = %path.display(), "saved snapshot"),
+            Err(_) => self.inner.dirty.store(true, Ordering::Relaxed),
+        }
+
+        result
+    }
+
+    /// Save a snapshot every `period`, if anything changed since the last one.
+    /// Abort the returned task to stop taking snapshots.
+    pub fn spawn_snapshots(&self, period: Duration) -> JoinHandle<()> {
+        let storage = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                if storage.inner.dirty.load(Ordering::Relaxed) {
+                    if let Err(err) = storage.save_snapshot().await {
+                        error!(%err, "failed to save snapshot");
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl DataSource for InMemoryStorage {
+    fn programs(&self) -> Arc<dyn ProgramCrud> {
+        Arc::new(InMemoryProgramStorage {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn reports(&self) -> Arc<dyn ReportCrud> {
+        Arc::new(InMemoryReportStorage {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn events(&self) -> Arc<dyn EventCrud> {
+        Arc::new(InMemoryEventStorage {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn vens(&self) -> Arc<dyn VenCrud> {
+        Arc::new(InMemoryVenStorage {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn resources(&self) -> Arc<dyn ResourceCrud> {
+        Arc::new(InMemoryResourceStorage {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn auth(&self) -> Arc<dyn AuthSource> {
+        Arc::new(InMemoryAuthSource {
+            inner: self.inner.clone(),
+        })
+    }
+
+    fn stats(&self) -> Arc<dyn StatsSource> {
+        Arc::new(InMemoryStatsSource {
+            inner: self.inner.clone(),
+        })
+    }
+
+    /// Transactions run one at a time, and are rolled back by restoring the objects
+    /// as they were when the transaction started.
+    /// Changes made outside of transactions while a transaction runs are rolled back as well.
+    async fn run_transaction(&self, f: TransactionFn<'_>) -> TransactionResult {
+        // nested transactions join the enclosing transaction
+        if self.in_transaction {
+            return f(self).await;
+        }
+
+        let _guard = self.inner.transaction.lock().await;
+        let backup = self.inner.read().await.clone();
+        let storage = InMemoryStorage {
+            inner: self.inner.clone(),
+            in_transaction: true,
+        };
+
+        let result = f(&storage).await;
+
+        if result.is_err() {
+            *self.inner.write().await = backup;
+        }
+
+        result
+    }
+}
+
+fn new_id<T: std::str::FromStr<Err = openadr_wire::IdentifierError>>() -> Result<T, AppError> {
+    Ok(Uuid::new_v4().to_string().parse()?)
+}
+
+fn paginate<T>(objects: impl Iterator<Item = T>, skip: i64, limit: i64) -> Vec<T> {
+    objects
+        .skip(skip.try_into().unwrap_or_default())
+        .take(limit.try_into().unwrap_or_default())
+        .collect()
+}
+
+/// The filter of a list query without pagination, to count all matching objects
+fn unpaginated<E: Clone>(filter: &ListParams<E>) -> ListParams<E> {
+    ListParams {
+        skip: 0,
+        limit: i64::MAX,
+        ..filter.clone()
+    }
+}
+
+/// A property to sort objects by, see [`sort`]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey<'a> {
+    Time(DateTime<Utc>),
+    Number(i64),
+    Text(&'a str),
+}
+
+trait Sortable {
+    fn sort_key(&self, sort_by: SortBy) -> Option<SortKey<'_>>;
+}
+
+impl Sortable for Program {
+    fn sort_key(&self, sort_by: SortBy) -> Option<SortKey<'_>> {
+        match sort_by {
+            SortBy::Created => Some(SortKey::Time(self.created_date_time)),
+            SortBy::Modified => Some(SortKey::Time(self.modification_date_time)),
+            SortBy::Priority => self
+                .content
+                .default_priority
+                .and_then(Option::<i64>::from)
+                .map(SortKey::Number),
+            SortBy::Name => Some(SortKey::Text(&self.content.program_name)),
+        }
+    }
+}
+
+impl Sortable for Event {
+    fn sort_key(&self, sort_by: SortBy) -> Option<SortKey<'_>> {
+        match sort_by {
+            SortBy::Created => Some(SortKey::Time(self.created_date_time)),
+            SortBy::Modified => Some(SortKey::Time(self.modification_date_time)),
+            SortBy::Priority => Option::<i64>::from(self.content.priority).map(SortKey::Number),
+            SortBy::Name => self.content.event_name.as_deref().map(SortKey::Text),
+        }
+    }
+}
+
+impl Sortable for Report {
+    fn sort_key(&self, sort_by: SortBy) -> Option<SortKey<'_>> {
+        match sort_by {
+            SortBy::Created => Some(SortKey::Time(self.created_date_time)),
+            SortBy::Modified => Some(SortKey::Time(self.modification_date_time)),
+            SortBy::Priority => None,
+            SortBy::Name => self.content.report_name.as_deref().map(SortKey::Text),
+        }
+    }
+}
+
+/// Like the Postgres storage, sort the objects by the `sortBy` in the `sortOrder`,
+/// with the objects without the property last in either order.
+/// The sort is stable, so objects with the same value keep their default order.
+fn sort<T: Sortable>(objects: &mut [&T], sorting: Sorting) {
+    let Some(sort_by) = sorting.sort_by else {
+        return;
+    };
+
+    objects.sort_by(|a, b| match (a.sort_key(sort_by), b.sort_key(sort_by)) {
+        (Some(a), Some(b)) if sorting.is_descending() => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    });
+}
+
+fn targets_match(targets: Option<&TargetMap>, label: &TargetLabel, values: &[String]) -> bool {
+    targets
+        .iter()
+        .flat_map(|targets| &targets.0)
+        .any(|entry| values.iter().any(|value| entry.contains(label, value)))
+}
+
+/// Like [`targets_match`], for the targets of VENs and resources, which are values maps
+fn values_maps_match(
+    targets: Option<&[ValuesMap]>,
+    label: &TargetLabel,
+    values: &[String],
+) -> bool {
+    targets.into_iter().flatten().any(|target| {
+        target.value_type.0 == label.as_str()
+            && target
+                .values
+                .iter()
+                .any(|value| matches!(value, Value::String(value) if values.contains(value)))
+    })
+}
+
+/// Same as the Postgres storage: users with a single business write on behalf of that business
+fn extract_business_id(user: &Claims) -> Result<Option<String>, AppError> {
+    match user.business_ids() {
+        BusinessIds::Specific(ids) if ids.len() == 1 => Ok(Some(ids[0].clone())),
+        BusinessIds::Specific(_) => Err(AppError::BadRequest("Cannot infer business id from user")),
+        BusinessIds::Any => Ok(None),
+    }
+}
+
+/// Whether the user may access objects of a program belonging to `business_id`
+fn is_business_of(user: &Claims, business_id: Option<&str>) -> bool {
+    match user.business_ids() {
+        BusinessIds::Specific(ids) => business_id.is_some_and(|id| ids.iter().any(|b| b == id)),
+        BusinessIds::Any => true,
+    }
+}
+
+/// If the program belongs to a business, only that business may write its events
+fn check_write_permission(program: &StoredProgram, user: &Claims) -> Result<(), AppError> {
+    match (user.business_ids(), &program.business_id) {
+        (BusinessIds::Specific(ids), Some(id)) if !ids.contains(id) => Err(AppError::Forbidden("You do not have write permissions for events belonging to a program that belongs to another business logic")),
+        _ => Ok(()),
+    }
+}
+
+fn duplicate_name(object: &str) -> AppError {
+    AppError::Conflict(format!("A {object} with this name already exists"), None)
+}
+
+fn duplicate_program_name(existing: &Program) -> AppError {
+    AppError::DuplicateName {
+        object: "program",
+        id: existing.id.to_string(),
+        modification_date_time: existing.modification_date_time,
+    }
+}
+
+struct InMemoryProgramStorage {
+    inner: Arc<Inner>,
+}
+
+#[async_trait]
+impl ProgramCrud for InMemoryProgramStorage {
+    async fn validate_create(
+        &self,
+        new: ProgramContent,
+        user: &Claims,
+    ) -> Result<Program, AppError> {
+        let stored = self.inner.read().await.new_program(new, user)?;
+        Ok(stored.program)
+    }
+
+    async fn vens(&self, id: &ProgramId, user: &Claims) -> Result<Vec<VenId>, AppError> {
+        let business_id = extract_business_id(user)?;
+        let objects = self.inner.read().await;
+
+        let stored = Some(objects.program(id)?)
+            .filter(|stored| business_id.is_none() || stored.business_id == business_id)
+            .ok_or(AppError::NotFound)?;
+
+        let mut ven_ids = objects
+            .program_vens(&stored.program)
+            .map(|ven| ven.id.clone())
+            .collect::<Vec<_>>();
+        ven_ids.sort();
+
+        Ok(ven_ids)
+    }
+
+    async fn assign_ven(
+        &self,
+        id: &ProgramId,
+        ven_id: &VenId,
+        user: &Claims,
+    ) -> Result<(), AppError> {
+        let business_id = extract_business_id(user)?;
+        let mut objects = self.inner.write().await;
+
+        // like the foreign keys of the Postgres storage
+        let ven_name = objects
+            .vens
+            .iter()
+            .find(|ven| &ven.id == ven_id)
+            .map(|ven| ven.content.ven_name.clone())
+            .ok_or_else(|| {
+                AppError::ForeignKeyConstraintViolated("The VEN does not exist".to_string(), None)
+            })?;
+
+        let stored = objects
+            .programs
+            .iter_mut()
+            .find(|stored| &stored.program.id == id)
+            .filter(|stored| business_id.is_none() || stored.business_id == business_id)
+            .ok_or(AppError::NotFound)?;
+
+        let targets = stored
+            .program
+            .content
+            .targets
+            .get_or_insert_with(|| TargetMap(vec![]));
+        if targets.contains(&TargetLabel::VENName, &ven_name) {
+            return Err(AppError::Conflict(
+                "The VEN is already linked to the program".to_string(),
+                None,
+            ));
+        }
+        targets
+            .0
+            .push(TargetEntry::new(TargetLabel::VENName, ven_name));
+
+        Ok(())
+    }
+
+    async fn unassign_ven(
+        &self,
+        id: &ProgramId,
+        ven_id: &VenId,
+        user: &Claims,
+    ) -> Result<(), AppError> {
+        let business_id = extract_business_id(user)?;
+        let mut objects = self.inner.write().await;
+
+        let ven_name = objects
+            .vens
+            .iter()
+            .find(|ven| &ven.id == ven_id)
+            .map(|ven| ven.content.ven_name.clone())
+            .ok_or(AppError::NotFound)?;
+
+        let stored = objects
+            .programs
+            .iter_mut()
+            .find(|stored| &stored.program.id == id)
+            .filter(|stored| business_id.is_none() || stored.business_id == business_id)
+            .ok_or(AppError::NotFound)?;
+
+        let Some(targets) = stored.program.content.targets.as_mut() else {
+            return Err(AppError::NotFound);
+        };
+        if !targets.contains(&TargetLabel::VENName, &ven_name) {
+            return Err(AppError::NotFound);
+        }
+        for entry in targets.0.iter_mut() {
+            if entry.label == TargetLabel::VENName {
+                entry.values.retain(|value| value != &ven_name);
+            }
+        }
+        targets.0.retain(|entry| !entry.values.is_empty());
+        if targets.0.is_empty() {
+            stored.program.content.targets = None;
+        }
+
+        Ok(())
+    }
+}
+
+impl InMemoryProgramStorage {
+    /// Same as the Postgres storage: VENs may only read the programs without assigned VENs,
+    /// and the programs assigned to one of their VENs
+    fn may_read(objects: &Objects, program: &Program, user: &Claims) -> bool {
+        if !user.is_ven() {
+            return true;
+        }
+
+        let ven_ids = user.ven_ids();
+        let mut vens = objects.program_vens(program).peekable();
+        vens.peek().is_none() || vens.any(|ven| ven_ids.contains(&ven.id))
+    }
+}
+
+#[async_trait]
+impl Crud for InMemoryProgramStorage {
+    type Type = Program;
+    type Id = ProgramId;
+    type NewType = ProgramContent;
+    type Error = AppError;
+    type Filter = program::QueryParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        let stored = objects.new_program(new, user)?;
+        let program = stored.program.clone();
+        objects.programs.push(stored);
+
+        Ok(program)
+    }
+
+    async fn retrieve(
+        &self,
+        id: &Self::Id,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let objects = self.inner.read().await;
+        let program = &objects.program(id)?.program;
+        if !Self::may_read(&objects, program, user) {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(program.clone())
+    }
+
+    async fn retrieve_all(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<Vec<Self::Type>, Self::Error> {
+        let objects = self.inner.read().await;
+
+        let matches = |program: &Program| match (&filter.target_type, &filter.target_values) {
+            (Some(TargetLabel::ProgramName), Some(values)) => {
+                values.contains(&program.content.program_name)
+            }
+            (Some(TargetLabel::EventName), Some(values)) => objects.events.iter().any(|event| {
+                event.content.program_id == program.id
+                    && event
+                        .content
+                        .event_name
+                        .as_ref()
+                        .is_some_and(|name| values.contains(name))
+            }),
+            (Some(label), Some(values)) => {
+                targets_match(program.content.targets.as_ref(), label, values)
+            }
+            _ => true,
+        } && filter
+            .extension
+            .active
+            .overlaps(program.content.interval_period.as_ref())
+            && filter.search.as_deref().map_or(true, |text| {
+                search::program_matches(program, search::contains(text))
+            });
+
+        let mut programs = objects
+            .programs
+            .iter()
+            .map(|stored| &stored.program)
+            .filter(|program| matches(program) && Self::may_read(&objects, program, user))
+            .collect::<Vec<_>>();
+
+        // the default order, like the Postgres storage
+        programs.sort_by(|a, b| {
+            (a.created_date_time, a.id.as_str()).cmp(&(b.created_date_time, b.id.as_str()))
+        });
+        sort(&mut programs, filter.extension.sort);
+
+        Ok(paginate(
+            programs.into_iter().cloned(),
+            filter.skip,
+            filter.limit,
+        ))
+    }
+
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        Ok(self.retrieve_all(&unpaginated(filter), user).await?.len())
+    }
+
+    async fn update(
+        &self,
+        id: &Self::Id,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let business_id = extract_business_id(user)?;
+        let mut objects = self.inner.write().await;
+
+        if let Some(existing) = objects.programs.iter().find(|stored| {
+            &stored.program.id != id && stored.program.content.program_name == new.program_name
+        }) {
+            return Err(duplicate_program_name(&existing.program));
+        }
+
+        let stored = objects
+            .programs
+            .iter_mut()
+            .find(|stored| &stored.program.id == id)
+            .filter(|stored| business_id.is_none() || stored.business_id == business_id)
+            .ok_or(AppError::NotFound)?;
+
+        stored.program.modification_date_time = truncate_timestamp(Utc::now());
+        stored.program.content = new;
+
+        Ok(stored.program.clone())
+    }
+
+    async fn delete(
+        &self,
+        id: &Self::Id,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let business_id = extract_business_id(user)?;
+        let mut objects = self.inner.write().await;
+
+        let index = objects
+            .programs
+            .iter()
+            .position(|stored| {
+                &stored.program.id == id
+                    && (business_id.is_none() || stored.business_id == business_id)
+            })
+            .ok_or(AppError::NotFound)?;
+
+        // like the foreign keys of the Postgres storage
+        if objects
+            .events
+            .iter()
+            .any(|event| &event.content.program_id == id)
+        {
+            return Err(AppError::ForeignKeyConstraintViolated(
+                "A foreign key constraint is violated".to_string(),
+                None,
+            ));
+        }
+
+        Ok(objects.programs.remove(index).program)
+    }
+}
+
+struct InMemoryEventStorage {
+    inner: Arc<Inner>,
+}
+
+#[async_trait]
+impl EventCrud for InMemoryEventStorage {
+    async fn validate_create(&self, new: EventContent, user: &Claims) -> Result<Event, AppError> {
+        self.inner.read().await.new_event(new, user)
+    }
+
+    async fn update_unmodified(
+        &self,
+        id: &EventId,
+        new: EventContent,
+        modification_date_time: DateTime<Utc>,
+        user: &Claims,
+    ) -> Result<Event, AppError> {
+        self.update_event(id, new, Some(modification_date_time), user)
+            .await
+    }
+}
+
+impl InMemoryEventStorage {
+    /// Update the event, but if a `modification_date_time` is given, only if it was last modified then
+    async fn update_event(
+        &self,
+        id: &EventId,
+        new: EventContent,
+        modification_date_time: Option<DateTime<Utc>>,
+        user: &Claims,
+    ) -> Result<Event, AppError> {
+        let mut objects = self.inner.write().await;
+        check_write_permission(objects.program(&new.program_id)?, user)?;
+
+        // make sure, you cannot 'steal' an event from another business
+        let previous_program_id = &objects.event(id)?.content.program_id;
+        if previous_program_id != &new.program_id {
+            check_write_permission(objects.program(previous_program_id)?, user)?;
+        }
+
+        let event = objects
+            .events
+            .iter_mut()
+            .find(|event| &event.id == id)
+            .ok_or(AppError::NotFound)?;
+        if modification_date_time.is_some_and(|modified| modified != event.modification_date_time) {
+            return Err(AppError::Conflict(
+                "the event was modified concurrently".to_string(),
+                None,
+            ));
+        }
+
+        // the modification time serves as the version of the event, so every update changes it
+        event.modification_date_time = truncate_timestamp(Utc::now())
+            .max(event.modification_date_time + TimeDelta::microseconds(1));
+        event.content = new;
+
+        Ok(event.clone())
+    }
+
+    /// Same as the Postgres storage: VENs may read all events,
+    /// business users only the events of their own programs
+    fn may_read(objects: &Objects, event: &Event, user: &Claims) -> bool {
+        let business_id = objects
+            .program(&event.content.program_id)
+            .ok()
+            .and_then(|stored| stored.business_id.as_deref());
+
+        user.is_ven() || (user.is_business() && is_business_of(user, business_id))
+    }
+}
+
+#[async_trait]
+impl Crud for InMemoryEventStorage {
+    type Type = Event;
+    type Id = EventId;
+    type NewType = EventContent;
+    type Error = AppError;
+    type Filter = event::QueryParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        let event = objects.new_event(new, user)?;
+        objects.events.push(event.clone());
+
+        Ok(event)
+    }
+
+    async fn retrieve(
+        &self,
+        id: &Self::Id,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let objects = self.inner.read().await;
+        let event = objects.event(id)?;
+
+        if !Self::may_read(&objects, event, user) {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(event.clone())
+    }
+
+    async fn retrieve_all(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<Vec<Self::Type>, Self::Error> {
+        let objects = self.inner.read().await;
+
+        let matches = |event: &Event| {
+            let program = objects.program(&event.content.program_id).ok();
+
+            let target_matches = match (&filter.target_type, &filter.target_values) {
+                (Some(TargetLabel::EventName), Some(values)) => event
+                    .content
+                    .event_name
+                    .as_ref()
+                    .is_some_and(|name| values.contains(name)),
+                (Some(TargetLabel::ProgramName), Some(values)) => program
+                    .is_some_and(|stored| values.contains(&stored.program.content.program_name)),
+                // VENs are linked to the events of a program through the targets of the program
+                (Some(label @ TargetLabel::VENName), Some(values)) => {
+                    program.is_some_and(|stored| {
+                        targets_match(stored.program.content.targets.as_ref(), label, values)
+                    })
+                }
+                (Some(label), Some(values)) => {
+                    targets_match(event.content.targets.as_ref(), label, values)
+                }
+                _ => true,
+            };
+
+            // like the Postgres storage, intervals without a period fall back to the event's
+            let window = &filter.extension.active;
+            let active = event.content.intervals.is_empty()
+                || event.content.intervals.iter().any(|interval| {
+                    window.overlaps(
+                        interval
+                            .interval_period
+                            .as_ref()
+                            .or(event.content.interval_period.as_ref()),
+                    )
+                });
+
+            filter
+                .extension
+                .program_id
+                .as_ref()
+                .map_or(true, |id| &event.content.program_id == id)
+                && target_matches
+                && active
+                && filter.search.as_deref().map_or(true, |text| {
+                    search::event_matches(event, search::contains(text))
+                })
+                && Self::may_read(&objects, event, user)
+        };
+
+        let cursor = filter.cursor()?;
+        let after_cursor = |event: &Event| {
+            cursor.map_or(true, |(modification_date_time, id)| {
+                (event.modification_date_time, event.id.as_str())
+                    > (modification_date_time, id.as_str())
+            })
+        };
+
+        let mut events = objects
+            .events
+            .iter()
+            .filter(|event| matches(event) && after_cursor(event))
+            .collect::<Vec<_>>();
+
+        // the order of the cursor, see `QueryParams::cursor`
+        events.sort_by(|a, b| {
+            (a.modification_date_time, a.id.as_str())
+                .cmp(&(b.modification_date_time, b.id.as_str()))
+        });
+
+        if let Some(order) = filter.extension.order_by {
+            events.sort_by_key(|event| {
+                let start = event
+                    .content
+                    .interval_period
+                    .as_ref()
+                    .or(event
+                        .content
+                        .intervals
+                        .first()
+                        .and_then(|interval| interval.interval_period.as_ref()))
+                    .map(|period| period.start);
+
+                let priority = match order {
+                    // the highest priority first, an unspecified priority is the lowest
+                    EventOrder::Priority => Some(std::cmp::Reverse(event.content.priority)),
+                    EventOrder::Start => None,
+                };
+
+                // events without a start come last
+                (priority, start.is_none(), start)
+            });
+        }
+        sort(&mut events, filter.extension.sort);
+
+        Ok(paginate(
+            events.into_iter().cloned(),
+            filter.skip,
+            filter.limit,
+        ))
+    }
+
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        // like the Postgres storage, the total includes the events before the cursor
+        let mut filter = unpaginated(filter);
+        filter.extension.after_id = None;
+        filter.extension.after_modification_date_time = None;
+        Ok(self.retrieve_all(&filter, user).await?.len())
+    }
+
+    async fn update(
+        &self,
+        id: &Self::Id,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.update_event(id, new, None, user).await
+    }
+
+    async fn delete(
+        &self,
+        id: &Self::Id,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        let program_id = &objects.event(id)?.content.program_id;
+        check_write_permission(objects.program(program_id)?, user)?;
+
+        // like the foreign keys of the Postgres storage
+        if objects
+            .reports
+            .iter()
+            .any(|report| &report.content.event_id == id)
+        {
+            return Err(AppError::ForeignKeyConstraintViolated(
+                "A foreign key constraint is violated".to_string(),
+                None,
+            ));
+        }
+
+        let index = objects
+            .events
+            .iter()
+            .position(|event| &event.id == id)
+            .ok_or(AppError::NotFound)?;
+
+        Ok(objects.events.remove(index))
+    }
+}
+
+struct InMemoryReportStorage {
+    inner: Arc<Inner>,
+}
+
+#[async_trait]
+impl ReportCrud for InMemoryReportStorage {
+    async fn validate_create(
+        &self,
+        new: ReportContent,
+        _user: &Claims,
+    ) -> Result<Report, AppError> {
+        self.inner.read().await.new_report(new)
+    }
+
+    async fn latest_operating_states(
+        &self,
+        filter: &report::QueryParams,
+        user: &Claims,
+    ) -> Result<Vec<ResourceOperatingState>, AppError> {
+        let reports = self.retrieve_all(&unpaginated(filter), user).await?;
+
+        let mut states: Vec<ResourceOperatingState> = vec![];
+        for report in reports {
+            for resource in &report.content.resources {
+                let Some(operating_state) = resource.operating_state() else {
+                    continue;
+                };
+
+                let state = ResourceOperatingState {
+                    client_name: report.content.client_name.clone(),
+                    resource_name: resource.resource_name.clone(),
+                    operating_state,
+                    report_id: report.id.clone(),
+                    reported_at: report.modification_date_time,
+                };
+
+                match states.iter_mut().find(|existing| {
+                    existing.client_name == state.client_name
+                        && existing.resource_name == state.resource_name
+                }) {
+                    Some(existing) if existing.reported_at <= state.reported_at => {
+                        *existing = state
+                    }
+                    Some(_) => {}
+                    None => states.push(state),
+                }
+            }
+        }
+
+        Ok(states)
+    }
+
+    async fn latest_payloads(
+        &self,
+        client_name: &str,
+        resource_name: &str,
+    ) -> Result<Vec<LatestReportPayload>, AppError> {
+        let objects = self.inner.read().await;
+        let reports = objects
+            .reports
+            .iter()
+            .filter(|report| report.content.client_name == client_name);
+
+        let mut payloads: Vec<LatestReportPayload> = vec![];
+        for report in reports {
+            let resources = report.content.resources.iter().filter(|resource| {
+                matches!(&resource.resource_name, ResourceName::Private(name) if name == resource_name)
+            });
+
+            for resource in resources {
+                for interval in &resource.intervals {
+                    for values_map in &interval.payloads {
+                        let payload = LatestReportPayload {
+                            payload_type: values_map.value_type.clone(),
+                            values: values_map.values.clone(),
+                            report_id: report.id.clone(),
+                            interval_id: interval.id,
+                            interval_period: interval
+                                .interval_period
+                                .clone()
+                                .or_else(|| resource.interval_period.clone()),
+                            reported_at: report.modification_date_time,
+                        };
+
+                        // later intervals of the same report replace earlier ones
+                        match payloads
+                            .iter_mut()
+                            .find(|existing| existing.payload_type == payload.payload_type)
+                        {
+                            Some(existing) if existing.reported_at <= payload.reported_at => {
+                                *existing = payload
+                            }
+                            Some(_) => {}
+                            None => payloads.push(payload),
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(payloads)
+    }
+}
+
+impl InMemoryReportStorage {
+    fn may_access(objects: &Objects, report: &Report, user: &Claims) -> bool {
+        let business_id = objects
+            .program(&report.content.program_id)
+            .ok()
+            .and_then(|stored| stored.business_id.as_deref());
+
+        is_business_of(user, business_id)
+    }
+}
+
+#[async_trait]
+impl Crud for InMemoryReportStorage {
+    type Type = Report;
+    type Id = ReportId;
+    type NewType = ReportContent;
+    type Error = AppError;
+    type Filter = report::QueryParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        _user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        let report = objects.new_report(new)?;
+        objects.reports.push(report.clone());
+        info!(report_id = report.id.as_str(), "created report");
+
+        Ok(report)
+    }
+
+    async fn retrieve(
+        &self,
+        id: &Self::Id,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let objects = self.inner.read().await;
+
+        objects
+            .reports
+            .iter()
+            .find(|report| &report.id == id && Self::may_access(&objects, report, user))
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn retrieve_all(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<Vec<Self::Type>, Self::Error> {
+        let objects = self.inner.read().await;
+
+        let matches = |report: &Report| {
+            let params = &filter.extension;
+
+            params
+                .program_id
+                .as_ref()
+                .map_or(true, |id| &report.content.program_id == id)
+                && params
+                    .event_id
+                    .as_ref()
+                    .map_or(true, |id| &report.content.event_id == id)
+                && params
+                    .client_name
+                    .as_ref()
+                    .map_or(true, |name| &report.content.client_name == name)
+                && Self::may_access(&objects, report, user)
+        };
+
+        let mut reports = objects
+            .reports
+            .iter()
+            .filter(|report| matches(report))
+            .collect::<Vec<_>>();
+
+        // the default order, like the Postgres storage
+        reports.sort_by(|a, b| {
+            (a.created_date_time, a.id.as_str()).cmp(&(b.created_date_time, b.id.as_str()))
+        });
+        sort(&mut reports, filter.extension.sort);
+
+        let reports = paginate(reports.into_iter().cloned(), filter.skip, filter.limit);
+
+        trace!("retrieved {} reports", reports.len());
+
+        Ok(reports)
+    }
+
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        Ok(self.retrieve_all(&unpaginated(filter), user).await?.len())
+    }
+
+    async fn update(
+        &self,
+        id: &Self::Id,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+
+        if new.report_name.is_some()
+            && objects
+                .reports
+                .iter()
+                .any(|report| &report.id != id && report.content.report_name == new.report_name)
+        {
+            return Err(duplicate_name("report"));
+        }
+
+        let index = objects
+            .reports
+            .iter()
+            .position(|report| &report.id == id && Self::may_access(&objects, report, user))
+            .ok_or(AppError::NotFound)?;
+
+        let report = &mut objects.reports[index];
+        report.modification_date_time = truncate_timestamp(Utc::now());
+        report.content = new;
+        info!(report_id = report.id.as_str(), "updated report");
+
+        Ok(report.clone())
+    }
+
+    async fn delete(
+        &self,
+        id: &Self::Id,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+
+        let index = objects
+            .reports
+            .iter()
+            .position(|report| &report.id == id && Self::may_access(&objects, report, user))
+            .ok_or(AppError::NotFound)?;
+
+        let report = objects.reports.remove(index);
+        info!(report_id = report.id.as_str(), "deleted report");
+
+        Ok(report)
+    }
+}
+
+struct InMemoryVenStorage {
+    inner: Arc<Inner>,
+}
+
+impl VenCrud for InMemoryVenStorage {}
+
+impl InMemoryVenStorage {
+    fn may_access(ven: &Ven, permissions: &VenPermissions) -> bool {
+        match permissions {
+            VenPermissions::AllAllowed => true,
+            VenPermissions::Specific(ids) => ids.contains(&ven.id),
+        }
+    }
+}
+
+#[async_trait]
+impl Crud for InMemoryVenStorage {
+    type Type = Ven;
+    type Id = VenId;
+    type NewType = VenContent;
+    type Error = AppError;
+    type Filter = ListParams;
+    type PermissionFilter = VenPermissions;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        _permissions: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        objects.check_ven_name(&new.ven_name, None)?;
+
+        let now = truncate_timestamp(Utc::now());
+        let ven = Ven {
+            id: new_id()?,
+            created_date_time: now,
+            modification_date_time: now,
+            content: VenContent {
+                resources: None,
+                ..new
+            },
+        };
+        objects.vens.push(ven.clone());
+        trace!(ven_id = ven.id.as_str(), "created ven");
+
+        Ok(objects.with_resources(&ven))
+    }
+
+    async fn retrieve(
+        &self,
+        id: &Self::Id,
+        permissions: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let objects = self.inner.read().await;
+        let ven = objects.ven(id)?;
+
+        if !Self::may_access(ven, permissions) {
+            return Err(AppError::NotFound);
+        }
+
+        Ok(objects.with_resources(ven))
+    }
+
+    async fn retrieve_all(
+        &self,
+        filter: &Self::Filter,
+        permissions: &Self::PermissionFilter,
+    ) -> Result<Vec<Self::Type>, Self::Error> {
+        let objects = self.inner.read().await;
+
+        let matches = |ven: &Ven| {
+            let target_matches = match (&filter.target_type, &filter.target_values) {
+                (Some(TargetLabel::VENName), Some(values)) => {
+                    values.contains(&ven.content.ven_name)
+                }
+                (Some(TargetLabel::ResourceName), Some(values)) => {
+                    objects.resources.iter().any(|resource| {
+                        resource.ven_id == ven.id
+                            && values.contains(&resource.content.resource_name)
+                    })
+                }
+                (Some(label), Some(values)) => {
+                    values_maps_match(ven.content.targets.as_deref(), label, values)
+                }
+                _ => true,
+            };
+
+            target_matches && Self::may_access(ven, permissions)
+        };
+
+        Ok(paginate(
+            objects
+                .vens
+                .iter()
+                .filter(|ven| matches(ven))
+                .map(|ven| objects.with_resources(ven))
+                .filter(|ven| {
+                    filter.search.as_deref().map_or(true, |text| {
+                        let is_match = search::contains(text);
+                        search::ven_matches(ven, &is_match)
+                            || ven
+                                .content
+                                .resources
+                                .iter()
+                                .flatten()
+                                .any(|resource| search::resource_matches(resource, &is_match))
+                    })
+                }),
+            filter.skip,
+            filter.limit,
+        ))
+    }
+
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        permissions: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        Ok(self
+            .retrieve_all(&unpaginated(filter), permissions)
+            .await?
+            .len())
+    }
+
+    async fn update(
+        &self,
+        id: &Self::Id,
+        new: Self::NewType,
+        _permissions: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        objects.check_ven_name(&new.ven_name, Some(id))?;
+
+        let ven = objects
+            .vens
+            .iter_mut()
+            .find(|ven| &ven.id == id)
+            .ok_or(AppError::NotFound)?;
+
+        ven.modification_date_time = truncate_timestamp(Utc::now());
+        ven.content = VenContent {
+            resources: None,
+            ..new
+        };
+        trace!(ven_id = id.as_str(), "updated ven");
+
+        let ven = ven.clone();
+        Ok(objects.with_resources(&ven))
+    }
+
+    async fn delete(
+        &self,
+        id: &Self::Id,
+        _permissions: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+
+        if objects
+            .resources
+            .iter()
+            .any(|resource| &resource.ven_id == id)
+        {
+            return Err(AppError::Forbidden(
+                "Cannot delete VEN with associated resources",
+            ));
+        }
+
+        let index = objects
+            .vens
+            .iter()
+            .position(|ven| &ven.id == id)
+            .ok_or(AppError::NotFound)?;
+
+        let mut ven = objects.vens.remove(index);
+        ven.content.resources = Some(vec![]);
+
+        // like the cascading foreign key of the Postgres storage
+        for user in &mut objects.users {
+            user.roles
+                .retain(|role| !matches!(role, AuthRole::VEN(ven_id) if ven_id == id));
+        }
+        trace!(ven_id = id.as_str(), "deleted ven");
+
+        Ok(ven)
+    }
+}
+
+struct InMemoryResourceStorage {
+    inner: Arc<Inner>,
+}
+
+impl ResourceCrud for InMemoryResourceStorage {}
+
+#[async_trait]
+impl VenScopedCrud for InMemoryResourceStorage {
+    type Type = Resource;
+    type Id = ResourceId;
+    type NewType = ResourceContent;
+    type Error = AppError;
+    type Filter = ListParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        ven_id: VenId,
+        _user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+
+        objects.ven(&ven_id).map_err(|_| {
+            AppError::ForeignKeyConstraintViolated(
+                "The VEN of the resource does not exist".to_string(),
+                None,
+            )
+        })?;
+        objects.check_resource_name(&new.resource_name, None)?;
+
+        let now = truncate_timestamp(Utc::now());
+        let resource = Resource {
+            id: new_id()?,
+            created_date_time: now,
+            modification_date_time: now,
+            ven_id,
+            content: new,
+        };
+        objects.resources.push(resource.clone());
+
+        Ok(resource)
+    }
+
+    async fn retrieve(
+        &self,
+        id: &Self::Id,
+        ven_id: VenId,
+        _user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.inner
+            .read()
+            .await
+            .resources
+            .iter()
+            .find(|resource| &resource.id == id && resource.ven_id == ven_id)
+            .cloned()
+            .ok_or(AppError::NotFound)
+    }
+
+    async fn retrieve_all(
+        &self,
+        ven_id: VenId,
+        filter: &Self::Filter,
+        _user: &Self::PermissionFilter,
+    ) -> Result<Vec<Self::Type>, Self::Error> {
+        let objects = self.inner.read().await;
+        let Ok(ven) = objects.ven(&ven_id) else {
+            return Ok(vec![]);
+        };
+
+        let matches = |resource: &Resource| match (&filter.target_type, &filter.target_values) {
+            (Some(TargetLabel::ResourceName), Some(values)) => {
+                values.contains(&resource.content.resource_name)
+            }
+            (Some(TargetLabel::VENName), Some(values)) => values.contains(&ven.content.ven_name),
+            (Some(label), Some(values)) => {
+                values_maps_match(resource.content.targets.as_deref(), label, values)
+            }
+            _ => true,
+        };
+
+        let resources = paginate(
+            objects
+                .resources
+                .iter()
+                .filter(|resource| resource.ven_id == ven_id && matches(resource))
+                .cloned(),
+            filter.skip,
+            filter.limit,
+        );
+
+        trace!(
+            ven_id = ven_id.as_str(),
+            "retrieved {} resources",
+            resources.len()
+        );
+
+        Ok(resources)
+    }
+
+    async fn count(
+        &self,
+        ven_id: VenId,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        Ok(self
+            .retrieve_all(ven_id, &unpaginated(filter), user)
+            .await?
+            .len())
+    }
+
+    async fn update(
+        &self,
+        id: &Self::Id,
+        ven_id: VenId,
+        new: Self::NewType,
+        _user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+        objects.check_resource_name(&new.resource_name, Some(id))?;
+
+        let resource = objects
+            .resources
+            .iter_mut()
+            .find(|resource| &resource.id == id && resource.ven_id == ven_id)
+            .ok_or(AppError::NotFound)?;
+
+        resource.modification_date_time = truncate_timestamp(Utc::now());
+        resource.content = new;
+
+        Ok(resource.clone())
+    }
+
+    async fn delete(
+        &self,
+        id: &Self::Id,
+        ven_id: VenId,
+        _user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        let mut objects = self.inner.write().await;
+
+        let index = objects
+            .resources
+            .iter()
+            .position(|resource| &resource.id == id && resource.ven_id == ven_id)
+            .ok_or(AppError::NotFound)?;
+
+        Ok(objects.resources.remove(index))
+    }
+}
+
+/// Stores the client secrets hashed with Argon2, like the Postgres storage
+struct InMemoryAuthSource {
+    inner: Arc<Inner>,
+}
+
+#[async_trait]
+impl AuthSource for InMemoryAuthSource {
+    async fn check_credentials(&self, client_id: &str, client_secret: &str) -> Option<AuthInfo> {
+        let objects = self.inner.read().await;
+
+        let credential = objects
+            .credentials
+            .iter()
+            .find(|credential| credential.client_id == client_id)?;
+
+        let parsed_hash = PasswordHash::new(&credential.client_secret_hash)
+            .inspect_err(|err| warn!("Failed to parse stored client_secret_hash: {}", err))
+            .ok()?;
+
+        Argon2::default()
+            .verify_password(client_secret.as_bytes(), &parsed_hash)
+            .ok()?;
+
+        let user = objects.user(&credential.user_id).ok()?;
+
+        Some(AuthInfo {
+            client_id: client_id.to_string(),
+            roles: user.roles.clone(),
+        })
+    }
+
+    async fn find_client(&self, client_id: &str) -> Option<AuthInfo> {
+        let objects = self.inner.read().await;
+
+        let credential = objects
+            .credentials
+            .iter()
+            .find(|credential| credential.client_id == client_id)?;
+        let user = objects.user(&credential.user_id).ok()?;
+
+        Some(AuthInfo {
+            client_id: client_id.to_string(),
+            roles: user.roles.clone(),
+        })
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<UserDetails, AppError> {
+        Ok(self.inner.read().await.user(user_id)?.clone())
+    }
+
+    async fn get_all_users(&self) -> Result<Vec<UserDetails>, AppError> {
+        Ok(self.inner.read().await.users.clone())
+    }
+
+    async fn add_user(
+        &self,
+        reference: &str,
+        description: Option<&str>,
+        roles: &[AuthRole],
+    ) -> Result<UserDetails, AppError> {
+        let mut objects = self.inner.write().await;
+        objects.check_roles(roles)?;
+
+        let now = truncate_timestamp(Utc::now());
+        let user = UserDetails {
+            id: Uuid::new_v4().to_string(),
+            reference: reference.to_string(),
+            description: description.map(ToString::to_string),
+            roles: roles.to_vec(),
+            client_ids: vec![],
+            created: now,
+            modified: now,
+        };
+        objects.users.push(user.clone());
+
+        Ok(user)
+    }
+
+    async fn add_credential(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<UserDetails, AppError> {
+        let salt = SaltString::generate(&mut OsRng);
+
+        let argon2 = Argon2::default();
+        let hash = argon2
+            .hash_password(client_secret.as_bytes(), &salt)?
+            .to_string();
+
+        self.add_hashed_credential(user_id, client_id, &hash).await
+    }
+
+    async fn add_hashed_credential(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        client_secret_hash: &str,
+    ) -> Result<UserDetails, AppError> {
+        // reject hashes `check_credentials` cannot parse
+        PasswordHash::new(client_secret_hash)?;
+
+        let mut objects = self.inner.write().await;
+
+        if objects
+            .credentials
+            .iter()
+            .any(|credential| credential.client_id == client_id)
+        {
+            return Err(AppError::Conflict(
+                "Credentials with this client id already exist".to_string(),
+                None,
+            ));
+        }
+
+        let user = objects.user_mut(user_id)?;
+        user.client_ids.push(client_id.to_string());
+        let user = user.clone();
+
+        objects.credentials.push(StoredCredential {
+            user_id: user_id.to_string(),
+            client_id: client_id.to_string(),
+            client_secret_hash: client_secret_hash.to_string(),
+        });
+
+        Ok(user)
+    }
+
+    async fn remove_credentials(
+        &self,
+        user_id: &str,
+        client_id: &str,
+    ) -> Result<UserDetails, AppError> {
+        let mut objects = self.inner.write().await;
+
+        objects.credentials.retain(|credential| {
+            credential.user_id != user_id || credential.client_id != client_id
+        });
+
+        let user = objects.user_mut(user_id)?;
+        user.client_ids.retain(|id| id != client_id);
+
+        Ok(user.clone())
+    }
+
+    async fn remove_user(&self, user_id: &str) -> Result<UserDetails, AppError> {
+        let mut objects = self.inner.write().await;
+
+        let index = objects
+            .users
+            .iter()
+            .position(|user| user.id == user_id)
+            .ok_or(AppError::NotFound)?;
+        objects
+            .credentials
+            .retain(|credential| credential.user_id != user_id);
+
+        Ok(objects.users.remove(index))
+    }
+
+    async fn edit_user(
+        &self,
+        user_id: &str,
+        reference: &str,
+        description: Option<&str>,
+        roles: &[AuthRole],
+    ) -> Result<UserDetails, AppError> {
+        let mut objects = self.inner.write().await;
+        objects.check_roles(roles)?;
+
+        let user = objects.user_mut(user_id)?;
+        user.reference = reference.to_string();
+        user.description = description.map(ToString::to_string);
+        user.roles = roles.to_vec();
+        user.modified = truncate_timestamp(Utc::now());
+
+        Ok(user.clone())
+    }
+}
+
+struct InMemoryStatsSource {
+    inner: Arc<Inner>,
+}
+
+#[async_trait]
+impl StatsSource for InMemoryStatsSource {
+    async fn stats(&self, since: DateTime<Utc>) -> Result<Stats, AppError> {
+        let objects = self.inner.read().await;
+
+        let mut events_per_program = objects
+            .programs
+            .iter()
+            .map(|stored| ProgramEvents {
+                program_id: stored.program.id.to_string(),
+                program_name: stored.program.content.program_name.clone(),
+                events: objects
+                    .events
+                    .iter()
+                    .filter(|event| event.content.program_id == stored.program.id)
+                    .count() as i64,
+            })
+            .collect::<Vec<_>>();
+        events_per_program.sort_by(|a, b| a.program_name.cmp(&b.program_name));
+
+        let mut report_volume: Vec<ReportVolume> = vec![];
+        for report in &objects.reports {
+            if report.created_date_time < since {
+                continue;
+            }
+
+            let day = report
+                .created_date_time
+                .date_naive()
+                .and_time(Default::default())
+                .and_utc();
+            match report_volume.iter_mut().find(|volume| volume.day == day) {
+                Some(volume) => volume.reports += 1,
+                None => report_volume.push(ReportVolume { day, reports: 1 }),
+            }
+        }
+        report_volume.sort_by_key(|volume| volume.day);
+
+        Ok(Stats {
+            objects: ObjectCounts {
+                programs: objects.programs.len() as i64,
+                events: objects.events.len() as i64,
+                reports: objects.reports.len() as i64,
+                vens: objects.vens.len() as i64,
+                resources: objects.resources.len() as i64,
+                users: objects.users.len() as i64,
+            },
+            events_per_program,
+            report_volume,
+            database_size: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openadr_wire::event::EventInterval;
+
+    fn snapshot_path() -> PathBuf {
+        std::env::temp_dir().join(format!("openadr-snapshot-{}.json", Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn snapshot_round_trip() {
+        let path = snapshot_path();
+        let user = Claims::any_business_user();
+
+        let storage = InMemoryStorage::with_snapshots(&path).await.unwrap();
+        let program = storage
+            .programs()
+            .create(ProgramContent::new("program"), &user)
+            .await
+            .unwrap();
+        let event = storage
+            .events()
+            .create(
+                EventContent::new(program.id.clone(), vec![EventInterval::new(0, vec![])]),
+                &user,
+            )
+            .await
+            .unwrap();
+        storage.save_snapshot().await.unwrap();
+
+        let restored = InMemoryStorage::with_snapshots(&path).await.unwrap();
+        assert_eq!(
+            restored
+                .programs()
+                .retrieve(&program.id, &user)
+                .await
+                .unwrap(),
+            program
+        );
+        assert_eq!(
+            restored.events().retrieve(&event.id, &user).await.unwrap(),
+            event
+        );
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn timestamps_match_storage_precision() {
+        let storage = InMemoryStorage::new();
+        let user = Claims::any_business_user();
+
+        let created = storage
+            .programs()
+            .create(ProgramContent::new("program"), &user)
+            .await
+            .unwrap();
+        assert_eq!(created.created_date_time.timestamp_subsec_nanos() % 1000, 0);
+
+        let updated = storage
+            .programs()
+            .update(&created.id, ProgramContent::new("program"), &user)
+            .await
+            .unwrap();
+        assert_eq!(
+            updated.modification_date_time.timestamp_subsec_nanos() % 1000,
+            0
+        );
+
+        let retrieved = storage
+            .programs()
+            .retrieve(&created.id, &user)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&updated).unwrap(),
+            serde_json::to_string(&retrieved).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn duplicate_program_name() {
+        let storage = InMemoryStorage::new();
+        let user = Claims::any_business_user();
+
+        let existing = storage
+            .programs()
+            .create(ProgramContent::new("program"), &user)
+            .await
+            .unwrap();
+        let err = storage
+            .programs()
+            .create(ProgramContent::new("program"), &user)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::DuplicateName { id, .. } if id == existing.id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn update_unmodified_event() {
+        let storage = InMemoryStorage::new();
+        let user = Claims::any_business_user();
+
+        let program = storage
+            .programs()
+            .create(ProgramContent::new("program"), &user)
+            .await
+            .unwrap();
+        let content = EventContent::new(program.id, vec![EventInterval::new(0, vec![])]);
+        let created = storage
+            .events()
+            .create(content.clone(), &user)
+            .await
+            .unwrap();
+        storage
+            .events()
+            .update_unmodified(
+                &created.id,
+                content.clone(),
+                created.modification_date_time,
+                &user,
+            )
+            .await
+            .unwrap();
+
+        let err = storage
+            .events()
+            .update_unmodified(&created.id, content, created.modification_date_time, &user)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::Conflict(..)));
+    }
+
+    #[tokio::test]
+    async fn vens_with_resources() {
+        let storage = InMemoryStorage::new();
+        let user = Claims::any_business_user();
+
+        let ven = storage
+            .vens()
+            .create(VenContent::new("ven-1"), &VenPermissions::AllAllowed)
+            .await
+            .unwrap();
+        let err = storage
+            .vens()
+            .create(VenContent::new("ven-1"), &VenPermissions::AllAllowed)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::Conflict(..)));
+
+        let resource = storage
+            .resources()
+            .create(ResourceContent::new("resource-1"), ven.id.clone(), &user)
+            .await
+            .unwrap();
+        let retrieved = storage
+            .vens()
+            .retrieve(&ven.id, &VenPermissions::AllAllowed)
+            .await
+            .unwrap();
+        assert_eq!(retrieved.content.resources, Some(vec![resource.clone()]));
+
+        // other VENs cannot see the VEN
+        let err = storage
+            .vens()
+            .retrieve(&ven.id, &VenPermissions::Specific(vec![]))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotFound));
+
+        let err = storage
+            .vens()
+            .delete(&ven.id, &VenPermissions::AllAllowed)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::Forbidden(_)));
+
+        storage
+            .resources()
+            .delete(&resource.id, ven.id.clone(), &user)
+            .await
+            .unwrap();
+        storage
+            .vens()
+            .delete(&ven.id, &VenPermissions::AllAllowed)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn vens_list_assigned_programs_only() {
+        let storage = InMemoryStorage::new();
+        let user = Claims::any_business_user();
+
+        let ven_1 = storage
+            .vens()
+            .create(VenContent::new("ven-1"), &VenPermissions::AllAllowed)
+            .await
+            .unwrap();
+        let ven_2 = storage
+            .vens()
+            .create(VenContent::new("ven-2"), &VenPermissions::AllAllowed)
+            .await
+            .unwrap();
+
+        let mut programs = Vec::new();
+        for name in ["unassigned", "assigned-1", "assigned-2"] {
+            programs.push(
+                storage
+                    .programs()
+                    .create(ProgramContent::new(name), &user)
+                    .await
+                    .unwrap(),
+            );
+        }
+        storage
+            .programs()
+            .assign_ven(&programs[1].id, &ven_1.id, &user)
+            .await
+            .unwrap();
+        storage
+            .programs()
+            .assign_ven(&programs[2].id, &ven_2.id, &user)
+            .await
+            .unwrap();
+
+        let ven_user = Claims::new(vec![AuthRole::VEN(ven_1.id.clone())]);
+        let listed = storage
+            .programs()
+            .retrieve_all(&Default::default(), &ven_user)
+            .await
+            .unwrap();
+        let names: Vec<_> = listed
+            .iter()
+            .map(|program| program.content.program_name.as_str())
+            .collect();
+        assert_eq!(names, ["unassigned", "assigned-1"]);
+        assert_eq!(
+            storage
+                .programs()
+                .count(&Default::default(), &ven_user)
+                .await
+                .unwrap(),
+            2
+        );
+
+        assert!(storage
+            .programs()
+            .retrieve(&programs[1].id, &ven_user)
+            .await
+            .is_ok());
+        let err = storage
+            .programs()
+            .retrieve(&programs[2].id, &ven_user)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotFound));
+
+        // business users are not restricted by the assignments
+        let listed = storage
+            .programs()
+            .retrieve_all(&Default::default(), &user)
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn assign_vens_to_program() {
+        let storage = InMemoryStorage::new();
+        let user = Claims::any_business_user();
+
+        let program = storage
+            .programs()
+            .create(ProgramContent::new("program"), &user)
+            .await
+            .unwrap();
+        let ven = storage
+            .vens()
+            .create(VenContent::new("ven-1"), &VenPermissions::AllAllowed)
+            .await
+            .unwrap();
+
+        storage
+            .programs()
+            .assign_ven(&program.id, &ven.id, &user)
+            .await
+            .unwrap();
+        let err = storage
+            .programs()
+            .assign_ven(&program.id, &ven.id, &user)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::Conflict(..)));
+        assert_eq!(
+            storage.programs().vens(&program.id, &user).await.unwrap(),
+            vec![ven.id.clone()]
+        );
+
+        let err = storage
+            .programs()
+            .assign_ven(&program.id, &"unknown".parse().unwrap(), &user)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::ForeignKeyConstraintViolated(..)));
+
+        storage
+            .programs()
+            .unassign_ven(&program.id, &ven.id, &user)
+            .await
+            .unwrap();
+        let err = storage
+            .programs()
+            .unassign_ven(&program.id, &ven.id, &user)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::NotFound));
+        assert!(storage
+            .programs()
+            .vens(&program.id, &user)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn credentials_are_hashed() {
+        let storage = InMemoryStorage::new();
+        let auth = storage.auth();
+
+        let user = auth
+            .add_user("user-1", None, &[AuthRole::AnyBusiness])
+            .await
+            .unwrap();
+        let user = auth
+            .add_credential(&user.id, "client-1", "secret")
+            .await
+            .unwrap();
+        assert_eq!(user.client_ids, ["client-1"]);
+
+        let stored = storage.inner.read().await.credentials[0].clone();
+        assert_ne!(stored.client_secret_hash, "secret");
+
+        assert_eq!(
+            auth.check_credentials("client-1", "secret").await,
+            Some(AuthInfo {
+                client_id: "client-1".to_string(),
+                roles: vec![AuthRole::AnyBusiness],
+            })
+        );
+        assert_eq!(auth.check_credentials("client-1", "wrong").await, None);
+
+        auth.remove_user(&user.id).await.unwrap();
+        assert_eq!(auth.check_credentials("client-1", "secret").await, None);
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_rolls_back() {
+        let storage: &dyn DataSource = &InMemoryStorage::new();
+
+        let result = storage
+            .transaction(|tx| {
+                Box::pin(async move {
+                    let user = Claims::any_business_user();
+                    tx.programs()
+                        .create(ProgramContent::new("program"), &user)
+                        .await?;
+                    Err::<(), _>(AppError::NotFound)
+                })
+            })
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+
+        let programs = storage
+            .programs()
+            .retrieve_all(&Default::default(), &Claims::any_business_user())
+            .await
+            .unwrap();
+        assert!(programs.is_empty());
+    }
+}
@@ -1,12 +1,17 @@
+pub mod directory;
+#[cfg(feature = "in-memory")]
+mod in_memory;
 #[cfg(feature = "postgres")]
 mod postgres;
 
 use axum::async_trait;
 use chrono::{DateTime, Utc};
+#[cfg(feature = "in-memory")]
+pub use in_memory::InMemoryStorage;
 use openadr_wire::{
     event::{EventContent, EventId},
     program::{ProgramContent, ProgramId},
-    report::{ReportContent, ReportId},
+    report::{LatestReportPayload, ReportContent, ReportId, ResourceOperatingState},
     resource::{Resource, ResourceContent, ResourceId},
     ven::{Ven, VenContent, VenId},
     Event, Program, Report,
@@ -14,11 +19,13 @@ use openadr_wire::{
 #[cfg(feature = "postgres")]
 pub use postgres::PostgresStorage;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{any::Any, future::Future, pin::Pin, sync::Arc};
 
 use crate::{
+    change_log::ChangeLog,
     error::AppError,
     jwt::{AuthRole, Claims},
+    stats::StatsSource,
 };
 
 #[async_trait]
@@ -45,6 +52,12 @@ pub trait Crud: Send + Sync + 'static {
         filter: &Self::Filter,
         permission_filter: &Self::PermissionFilter,
     ) -> Result<Vec<Self::Type>, Self::Error>;
+    /// The number of objects `retrieve_all` returns without pagination
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        permission_filter: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error>;
     async fn update(
         &self,
         id: &Self::Id,
@@ -85,6 +98,13 @@ pub trait VenScopedCrud: Send + Sync + 'static {
         filter: &Self::Filter,
         permission_filter: &Self::PermissionFilter,
     ) -> Result<Vec<Self::Type>, Self::Error>;
+    /// The number of objects `retrieve_all` returns without pagination
+    async fn count(
+        &self,
+        ven_id: VenId,
+        filter: &Self::Filter,
+        permission_filter: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error>;
     async fn update(
         &self,
         id: &Self::Id,
@@ -100,6 +120,7 @@ pub trait VenScopedCrud: Send + Sync + 'static {
     ) -> Result<Self::Type, Self::Error>;
 }
 
+#[async_trait]
 pub trait ProgramCrud:
     Crud<
     Type = Program,
@@ -110,7 +131,34 @@ pub trait ProgramCrud:
     PermissionFilter = Claims,
 >
 {
+    /// Run the checks of [`create`](Crud::create), e.g., permissions and conflicting names,
+    /// without storing anything, returning the program as it would be created
+    async fn validate_create(
+        &self,
+        new: ProgramContent,
+        user: &Claims,
+    ) -> Result<Program, AppError>;
+
+    /// The VENs assigned to the program, ordered by their id
+    async fn vens(&self, id: &ProgramId, user: &Claims) -> Result<Vec<VenId>, AppError>;
+
+    /// Assign the VEN to the program, failing with a conflict if it already is
+    async fn assign_ven(
+        &self,
+        id: &ProgramId,
+        ven_id: &VenId,
+        user: &Claims,
+    ) -> Result<(), AppError>;
+
+    /// Remove the VEN from the program, failing with [`AppError::NotFound`] if it is not assigned
+    async fn unassign_ven(
+        &self,
+        id: &ProgramId,
+        ven_id: &VenId,
+        user: &Claims,
+    ) -> Result<(), AppError>;
 }
+#[async_trait]
 pub trait ReportCrud:
     Crud<
     Type = Report,
@@ -121,7 +169,28 @@ pub trait ReportCrud:
     PermissionFilter = Claims,
 >
 {
+    /// Run the checks of [`create`](Crud::create), e.g., permissions and conflicting names,
+    /// without storing anything, returning the report as it would be created
+    async fn validate_create(&self, new: ReportContent, user: &Claims) -> Result<Report, AppError>;
+
+    /// The operating state each client reported last for each of its resources,
+    /// in the reports matching the program, event and client name of the filter
+    async fn latest_operating_states(
+        &self,
+        filter: &crate::api::report::QueryParams,
+        user: &Claims,
+    ) -> Result<Vec<ResourceOperatingState>, AppError>;
+
+    /// The payload of each type that a client reported last for one of its resources.
+    /// Unlike the other report queries, this is not limited to the businesses of the user,
+    /// the caller checks the permission on the VEN of the client instead.
+    async fn latest_payloads(
+        &self,
+        client_name: &str,
+        resource_name: &str,
+    ) -> Result<Vec<LatestReportPayload>, AppError>;
 }
+#[async_trait]
 pub trait EventCrud:
     Crud<
     Type = Event,
@@ -132,6 +201,20 @@ pub trait EventCrud:
     PermissionFilter = Claims,
 >
 {
+    /// Run the checks of [`create`](Crud::create), e.g., permissions and conflicting names,
+    /// without storing anything, returning the event as it would be created
+    async fn validate_create(&self, new: EventContent, user: &Claims) -> Result<Event, AppError>;
+
+    /// [`update`](Crud::update) the event, unless it was modified after `modification_date_time`,
+    /// in which case it fails with a [`AppError::Conflict`] and leaves the event alone.
+    /// This makes a read-modify-write, e.g., applying a delta, atomic.
+    async fn update_unmodified(
+        &self,
+        id: &EventId,
+        new: EventContent,
+        modification_date_time: DateTime<Utc>,
+        user: &Claims,
+    ) -> Result<Event, AppError>;
 }
 
 pub enum VenPermissions {
@@ -172,7 +255,7 @@ pub trait VenCrud:
     Id = VenId,
     NewType = VenContent,
     Error = AppError,
-    Filter = crate::api::ven::QueryParams,
+    Filter = crate::api::ListParams,
     PermissionFilter = VenPermissions,
 >
 {
@@ -184,13 +267,13 @@ pub trait ResourceCrud:
     Id = ResourceId,
     NewType = ResourceContent,
     Error = AppError,
-    Filter = crate::api::resource::QueryParams,
+    Filter = crate::api::ListParams,
     PermissionFilter = Claims,
 >
 {
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct UserDetails {
     pub(crate) id: String,
     pub(crate) reference: String,
@@ -212,6 +295,10 @@ impl UserDetails {
 #[async_trait]
 pub trait AuthSource: Send + Sync + 'static {
     async fn check_credentials(&self, client_id: &str, client_secret: &str) -> Option<AuthInfo>;
+    /// The roles of the client with the `client_id` without checking its secret,
+    /// e.g., to renew the token of a client with a refresh token.
+    /// `None` if the credentials of the client were removed in the meantime
+    async fn find_client(&self, client_id: &str) -> Option<AuthInfo>;
     async fn get_user(&self, user_id: &str) -> Result<UserDetails, AppError>;
     async fn get_all_users(&self) -> Result<Vec<UserDetails>, AppError>;
     async fn add_user(
@@ -226,6 +313,14 @@ pub trait AuthSource: Send + Sync + 'static {
         client_id: &str,
         client_secret: &str,
     ) -> Result<UserDetails, AppError>;
+    /// Like [`Self::add_credential`], but with a secret that is already hashed,
+    /// as a PHC string, e.g., `$argon2id$v=19$...`
+    async fn add_hashed_credential(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        client_secret_hash: &str,
+    ) -> Result<UserDetails, AppError>;
     async fn remove_credentials(
         &self,
         user_id: &str,
@@ -241,6 +336,19 @@ pub trait AuthSource: Send + Sync + 'static {
     ) -> Result<UserDetails, AppError>;
 }
 
+/// The type-erased result of a transaction, keeping [`DataSource`] object safe
+pub type TransactionResult = Result<Box<dyn Any + Send>, AppError>;
+
+/// The type-erased work of a transaction, see [`DataSource::run_transaction`]
+pub type TransactionFn<'f> = Box<
+    dyn for<'tx> FnOnce(
+            &'tx dyn DataSource,
+        ) -> Pin<Box<dyn Future<Output = TransactionResult> + Send + 'tx>>
+        + Send
+        + 'f,
+>;
+
+#[async_trait]
 pub trait DataSource: Send + Sync + 'static {
     fn programs(&self) -> Arc<dyn ProgramCrud>;
     fn reports(&self) -> Arc<dyn ReportCrud>;
@@ -248,9 +356,68 @@ pub trait DataSource: Send + Sync + 'static {
     fn vens(&self) -> Arc<dyn VenCrud>;
     fn resources(&self) -> Arc<dyn ResourceCrud>;
     fn auth(&self) -> Arc<dyn AuthSource>;
+    fn stats(&self) -> Arc<dyn StatsSource>;
+
+    /// A change log that records the changes in the transactions of this data source, if it has one,
+    /// see [`change_log::write`](crate::change_log::write)
+    fn change_log(&self) -> Option<Arc<dyn ChangeLog>> {
+        None
+    }
+
+    /// Run `f` on a data source whose operations all belong to a single transaction.
+    /// The transaction is committed if `f` succeeds, and rolled back if it returns an error.
+    ///
+    /// Handlers should use the typed [`transaction`](#method.transaction) instead.
+    async fn run_transaction(&self, f: TransactionFn<'_>) -> TransactionResult;
+}
+
+impl dyn DataSource + '_ {
+    /// Compose multiple operations into one atomic workflow.
+    ///
+    /// ```ignore
+    /// let event = storage
+    ///     .transaction(|tx| {
+    ///         Box::pin(async move {
+    ///             let program = tx.programs().create(new_program, &user).await?;
+    ///             tx.events().create(new_event(&program), &user).await
+    ///         })
+    ///     })
+    ///     .await?;
+    /// ```
+    pub async fn transaction<T, F>(&self, f: F) -> Result<T, AppError>
+    where
+        T: Send + 'static,
+        F: for<'tx> FnOnce(
+                &'tx dyn DataSource,
+            )
+                -> Pin<Box<dyn Future<Output = Result<T, AppError>> + Send + 'tx>>
+            + Send,
+    {
+        let result = self
+            .run_transaction(erase(move |tx| {
+                let future = f(tx);
+                Box::pin(async move { Ok(Box::new(future.await?) as Box<dyn Any + Send>) })
+            }))
+            .await?;
+
+        Ok(*result
+            .downcast()
+            .expect("a transaction returns the result of its closure"))
+    }
+}
+
+fn erase<'f, F>(f: F) -> TransactionFn<'f>
+where
+    F: for<'tx> FnOnce(
+            &'tx dyn DataSource,
+        ) -> Pin<Box<dyn Future<Output = TransactionResult> + Send + 'tx>>
+        + Send
+        + 'f,
+{
+    Box::new(f)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AuthInfo {
     pub client_id: String,
     pub roles: Vec<AuthRole>,
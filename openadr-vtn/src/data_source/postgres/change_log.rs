@@ -0,0 +1,180 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    change_log::{ChangeLog, NewChange, ObjectChange},
+    data_source::postgres::PgDb,
+    error::AppError,
+};
+
+/// Stores the changes in the `object_changes` table
+pub(crate) struct PgChangeLog {
+    db: PgDb,
+}
+
+impl From<PgDb> for PgChangeLog {
+    fn from(db: PgDb) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ChangeLog for PgChangeLog {
+    /// The sequence number is taken while holding a lock until the end of the transaction,
+    /// such that the changes are committed in the order of their sequence numbers.
+    /// Otherwise, a consumer could see a change committed before a change with a lower sequence number,
+    /// and miss the latter when continuing after the former.
+    async fn record(&self, change: NewChange) -> Result<(), AppError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO object_changes (object_type, object_id, operation, actor, changed_at, payload_hash)
+            SELECT $1, $2, $3, $4, now(), encode(sha256(convert_to($5, 'UTF8')), 'hex')
+            FROM (SELECT pg_advisory_xact_lock('object_changes'::regclass::oid::bigint)) AS commit_order
+            "#,
+            change.object_type.as_str(),
+            change.object_id,
+            change.operation.as_str(),
+            change.actor,
+            change.payload,
+        )
+        .execute(&mut *self.db.acquire().await?)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn changes(&self, after: i64, limit: i64) -> Result<Vec<ObjectChange>, AppError> {
+        Ok(sqlx::query_as!(
+            ObjectChange,
+            r#"
+            SELECT seq,
+                   object_type,
+                   object_id,
+                   operation,
+                   actor,
+                   changed_at,
+                   payload_hash
+            FROM object_changes
+            WHERE seq > $1
+            ORDER BY seq
+            LIMIT $2
+            "#,
+            after,
+            limit,
+        )
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?)
+    }
+
+    async fn prune(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        Ok(sqlx::query!(
+            r#"
+            DELETE FROM object_changes
+            WHERE changed_at < $1
+            "#,
+            before,
+        )
+        .execute(&mut *self.db.acquire().await?)
+        .await?
+        .rows_affected())
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod tests {
+    use super::*;
+    use crate::{
+        change_log::{ObjectType, Operation},
+        jwt::Claims,
+    };
+    use chrono::Duration;
+    use openadr_wire::program::ProgramContent;
+    use sqlx::PgPool;
+    use std::sync::Arc;
+    use tokio::sync::Mutex;
+
+    fn change(object_id: &str) -> NewChange {
+        NewChange::new(
+            ObjectType::Program,
+            object_id,
+            Operation::Create,
+            &Claims::any_business_user(),
+            &ProgramContent::new("program"),
+        )
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn record_and_prune(db: PgPool) {
+        let change_log: PgChangeLog = PgDb::from(db).into();
+        let mut user = Claims::any_business_user();
+        user.sub = "business-client".to_string();
+
+        for operation in [Operation::Create, Operation::Update] {
+            let change = NewChange::new(
+                ObjectType::Program,
+                "program-1",
+                operation,
+                &user,
+                &ProgramContent::new("program"),
+            )
+            .unwrap();
+            change_log.record(change).await.unwrap();
+        }
+
+        let changes = change_log.changes(0, 10).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].object_type, "PROGRAM");
+        assert_eq!(changes[0].object_id, "program-1");
+        assert_eq!(changes[0].operation, "CREATE");
+        assert_eq!(changes[0].actor, "business-client");
+        assert_eq!(changes[0].payload_hash.len(), 64);
+        // the same payload results in the same hash
+        assert_eq!(changes[0].payload_hash, changes[1].payload_hash);
+
+        let after_first = change_log.changes(changes[0].seq, 10).await.unwrap();
+        assert_eq!(after_first, changes[1..]);
+
+        assert_eq!(
+            change_log
+                .prune(Utc::now() - Duration::hours(1))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            change_log
+                .prune(Utc::now() + Duration::hours(1))
+                .await
+                .unwrap(),
+            2
+        );
+        assert!(change_log.changes(0, 10).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn changes_are_committed_in_order(db: PgPool) {
+        let first = PgDb::Transaction(Arc::new(Mutex::new(Some(db.begin().await.unwrap()))));
+        let first_log: PgChangeLog = first.clone().into();
+        first_log.record(change("program-1")).await.unwrap();
+
+        // waits for the first transaction, which took the lower sequence number
+        let second_log: PgChangeLog = PgDb::from(db.clone()).into();
+        let second = tokio::spawn(async move { second_log.record(change("program-2")).await });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!second.is_finished());
+
+        let PgDb::Transaction(tx) = first else {
+            unreachable!()
+        };
+        tx.lock().await.take().unwrap().commit().await.unwrap();
+        second.await.unwrap().unwrap();
+
+        let change_log: PgChangeLog = PgDb::from(db).into();
+        let changes = change_log.changes(0, 10).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].object_id, "program-1");
+        assert_eq!(changes[1].object_id, "program-2");
+    }
+}
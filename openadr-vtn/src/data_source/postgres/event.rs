@@ -1,32 +1,54 @@
 use crate::{
     api::event::QueryParams,
     data_source::{
-        postgres::{extract_business_ids, to_json_value, PgId, PgTargetsFilter},
+        postgres::{extract_business_ids, filter::PostgresFilter, to_json_value, PgDb, PgId},
         Crud, EventCrud,
     },
     error::AppError,
+    event_archive::EventArchive,
     jwt::{BusinessIds, Claims},
 };
 use axum::async_trait;
 use chrono::{DateTime, Utc};
 use openadr_wire::{
-    event::{EventContent, EventId, Priority},
+    event::{EventContent, EventId, EventOrder, Priority},
     target::TargetLabel,
-    Event,
+    Event, SortBy,
 };
-use sqlx::PgPool;
+use sqlx::{Connection, PgPool};
 use std::str::FromStr;
 use tracing::{error, trace};
 
 #[async_trait]
-impl EventCrud for PgEventStorage {}
+impl EventCrud for PgEventStorage {
+    async fn validate_create(&self, new: EventContent, user: &Claims) -> Result<Event, AppError> {
+        self.insert(new, user, true).await
+    }
+
+    async fn update_unmodified(
+        &self,
+        id: &EventId,
+        new: EventContent,
+        modification_date_time: DateTime<Utc>,
+        user: &Claims,
+    ) -> Result<Event, AppError> {
+        self.update_event(id, new, Some(modification_date_time), user)
+            .await
+    }
+}
 
 pub(crate) struct PgEventStorage {
-    db: PgPool,
+    db: PgDb,
 }
 
 impl From<PgPool> for PgEventStorage {
     fn from(db: PgPool) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+impl From<PgDb> for PgEventStorage {
+    fn from(db: PgDb) -> Self {
         Self { db }
     }
 }
@@ -44,6 +66,9 @@ struct PostgresEvent {
     payload_descriptors: Option<serde_json::Value>,
     interval_period: Option<serde_json::Value>,
     intervals: serde_json::Value,
+    /// Only used by the [`EventArchive`], not part of an [`Event`]
+    #[allow(dead_code)]
+    completed_date_time: Option<DateTime<Utc>>,
 }
 
 impl TryFrom<PostgresEvent> for Event {
@@ -116,50 +141,6 @@ impl TryFrom<PostgresEvent> for Event {
     }
 }
 
-#[derive(Default, Debug)]
-struct PostgresFilter<'a> {
-    program_id: Option<&'a str>,
-    ven_names: Option<&'a [String]>,
-    event_names: Option<&'a [String]>,
-    program_names: Option<&'a [String]>,
-    // TODO check whether we also need to extract `PowerServiceLocation`, `ServiceArea`,
-    //  `ResourceNames`, and `Group`, i.e., only leave the `Private`
-    targets: Vec<PgTargetsFilter<'a>>,
-
-    skip: i64,
-    limit: i64,
-}
-
-impl<'a> From<&'a QueryParams> for PostgresFilter<'a> {
-    fn from(query: &'a QueryParams) -> Self {
-        let mut filter = Self {
-            program_id: query.program_id.as_ref().map(|id| id.as_str()),
-            skip: query.skip,
-            limit: query.limit,
-            ..Default::default()
-        };
-        match query.target_type {
-            Some(TargetLabel::VENName) => filter.ven_names = query.target_values.as_deref(),
-            Some(TargetLabel::EventName) => filter.event_names = query.target_values.as_deref(),
-            Some(TargetLabel::ProgramName) => filter.program_names = query.target_values.as_deref(),
-            Some(ref label) => {
-                if let Some(values) = query.target_values.as_ref() {
-                    filter.targets = values
-                        .iter()
-                        .map(|value| PgTargetsFilter {
-                            label: label.as_str(),
-                            value: [value.clone()],
-                        })
-                        .collect()
-                }
-            }
-            None => {}
-        };
-
-        filter
-    }
-}
-
 struct MaybePgId {
     id: Option<String>,
 }
@@ -167,7 +148,7 @@ struct MaybePgId {
 async fn check_write_permission(
     program_id: &str,
     user: &Claims,
-    db: &PgPool,
+    db: &PgDb,
 ) -> Result<(), AppError> {
     if let Some(business_ids) = extract_business_ids(user) {
         let MaybePgId { id } = sqlx::query_as!(
@@ -177,36 +158,112 @@ async fn check_write_permission(
             "#,
             program_id
         )
-        .fetch_one(db)
+        .fetch_one(&mut *db.acquire().await?)
         .await?;
 
         // If no business is connected, anyone may write
         if let Some(id) = id {
             if !business_ids.contains(&id) {
-                Err(AppError::Auth("You do not have write permissions for events belonging to a program that belongs to another business logic".to_string()))?;
+                Err(AppError::Forbidden("You do not have write permissions for events belonging to a program that belongs to another business logic"))?;
             }
         }
     };
     Ok(())
 }
 
-#[async_trait]
-impl Crud for PgEventStorage {
-    type Type = Event;
-    type Id = EventId;
-    type NewType = EventContent;
-    type Error = AppError;
-    type Filter = QueryParams;
-    type PermissionFilter = Claims;
+impl PgEventStorage {
+    /// Update the event, but if a `modification_date_time` is given, only if it was last modified then
+    async fn update_event(
+        &self,
+        id: &EventId,
+        new: EventContent,
+        modification_date_time: Option<DateTime<Utc>>,
+        user: &Claims,
+    ) -> Result<Event, AppError> {
+        check_write_permission(new.program_id.as_str(), user, &self.db).await?;
 
-    async fn create(
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        // an archived event is updated like any other, and moves back to the active events
+        let previous_program_id = sqlx::query_as!(
+            PgId,
+            r#"
+            WITH restored AS (
+                DELETE FROM event_archive WHERE id = $1 RETURNING *
+            ), inserted AS (
+                INSERT INTO event SELECT * FROM restored RETURNING program_id
+            )
+            SELECT program_id AS "id!" FROM inserted
+            UNION ALL
+            SELECT program_id FROM event WHERE id = $1
+            "#,
+            id.as_str()
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // make sure, you cannot 'steal' an event from another business
+        if previous_program_id.id != new.program_id.as_str() {
+            check_write_permission(&previous_program_id.id, user, &self.db).await?;
+        }
+
+        let event = sqlx::query_as!(
+            PostgresEvent,
+            r#"
+            UPDATE event
+            -- the modification time serves as the version of the event, so every update changes it
+            SET modification_date_time = GREATEST(now(), modification_date_time + interval '1 microsecond'),
+                program_id = $2,
+                event_name = $3,
+                priority = $4,
+                targets = $5,
+                report_descriptors = $6,
+                payload_descriptors = $7,
+                interval_period = $8,
+                intervals = $9,
+                -- the event may end later now
+                completed_date_time = NULL
+            WHERE id = $1
+              AND ($10::timestamptz IS NULL OR modification_date_time = $10)
+            RETURNING *
+            "#,
+            id.as_str(),
+            new.program_id.as_str(),
+            new.event_name,
+            Into::<Option<i64>>::into(new.priority),
+            to_json_value(new.targets)?,
+            to_json_value(new.report_descriptors)?,
+            to_json_value(new.payload_descriptors)?,
+            to_json_value(new.interval_period)?,
+            serde_json::to_value(&new.intervals).map_err(AppError::SerdeJsonBadRequest)?,
+            modification_date_time,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| match modification_date_time {
+            Some(_) => AppError::Conflict("the event was modified concurrently".to_string(), None),
+            None => AppError::NotFound,
+        })?
+        .try_into()?;
+
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    /// Insert the event in a transaction, which a dry run rolls back
+    async fn insert(
         &self,
-        new: Self::NewType,
-        user: &Self::PermissionFilter,
-    ) -> Result<Self::Type, Self::Error> {
+        new: EventContent,
+        user: &Claims,
+        dry_run: bool,
+    ) -> Result<Event, AppError> {
         check_write_permission(new.program_id.as_str(), user, &self.db).await?;
 
-        Ok(sqlx::query_as!(
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let event: Event = sqlx::query_as!(
             PostgresEvent,
             r#"
             INSERT INTO event (id, created_date_time, modification_date_time, program_id, event_name, priority, targets, report_descriptors, payload_descriptors, interval_period, intervals)
@@ -222,10 +279,32 @@ impl Crud for PgEventStorage {
             to_json_value(new.interval_period)?,
             serde_json::to_value(&new.intervals).map_err(AppError::SerdeJsonBadRequest)?,
         )
-            .fetch_one(&self.db)
+            .fetch_one(&mut *tx)
             .await?
-            .try_into()?
-        )
+            .try_into()?;
+
+        if !dry_run {
+            tx.commit().await?;
+        }
+        Ok(event)
+    }
+}
+
+#[async_trait]
+impl Crud for PgEventStorage {
+    type Type = Event;
+    type Id = EventId;
+    type NewType = EventContent;
+    type Error = AppError;
+    type Filter = QueryParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.insert(new, user, false).await
     }
 
     async fn retrieve(
@@ -241,12 +320,27 @@ impl Crud for PgEventStorage {
         Ok(sqlx::query_as!(
             PostgresEvent,
             r#"
-            SELECT e.*
-            FROM event e
+            SELECT e.id AS "id!",
+                   e.created_date_time AS "created_date_time!",
+                   e.modification_date_time AS "modification_date_time!",
+                   e.program_id AS "program_id!",
+                   e.event_name,
+                   e.priority,
+                   e.report_descriptors,
+                   e.payload_descriptors,
+                   e.interval_period,
+                   e.intervals AS "intervals!",
+                   e.targets,
+                   e.completed_date_time
+            FROM (
+                SELECT * FROM event WHERE id = $1
+                UNION ALL
+                -- archived events are retrieved by their id as well
+                SELECT * FROM event_archive WHERE id = $1
+            ) e
               JOIN program p ON e.program_id = p.id
               LEFT JOIN ven_program vp ON p.id = vp.program_id
-            WHERE e.id = $1
-              AND (
+            WHERE (
                   ($2 AND (vp.ven_id IS NULL OR vp.ven_id = ANY($3))) 
                   OR 
                   ($4 AND ($5::text[] IS NULL OR p.business_id = ANY ($5)))
@@ -258,19 +352,31 @@ impl Crud for PgEventStorage {
             user.is_business(),
             business_ids.as_deref(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?)
     }
 
+    #[tracing::instrument(name = "PgEventStorage::retrieve_all", skip_all)]
     async fn retrieve_all(
         &self,
         filter: &Self::Filter,
         user: &Self::PermissionFilter,
     ) -> Result<Vec<Self::Type>, Self::Error> {
-        let pg_filter: PostgresFilter = filter.into();
+        let pg_filter = PostgresFilter::new(
+            filter,
+            &[
+                TargetLabel::VENName,
+                TargetLabel::ProgramName,
+                TargetLabel::EventName,
+            ],
+        );
         trace!(?pg_filter);
 
+        let program_id = filter.extension.program_id.as_ref().map(|id| id.as_str());
+        let order_by = filter.extension.order_by.as_ref().map(EventOrder::as_str);
+        let cursor = filter.cursor()?;
+
         let business_ids = match user.business_ids() {
             BusinessIds::Specific(ids) => Some(ids),
             BusinessIds::Any => None,
@@ -279,98 +385,240 @@ impl Crud for PgEventStorage {
         Ok(sqlx::query_as!(
             PostgresEvent,
             r#"
-            SELECT e.*
-            FROM event e
-              JOIN program p on p.id = e.program_id
-              LEFT JOIN ven_program vp ON p.id = vp.program_id
-              LEFT JOIN ven v ON v.id = vp.ven_id
-              LEFT JOIN LATERAL ( 
-                  SELECT e.id as e_id, 
-                         json_array(jsonb_array_elements(e.targets)) <@ $5::jsonb AS target_test )
-                  ON e.id = e_id
-            WHERE ($1::text IS NULL OR e.program_id like $1)
-              AND ($2::text[] IS NULL OR e.event_name = ANY($2))
-              AND ($3::text[] IS NULL OR p.program_name = ANY($3))
-              AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
-              AND ($5::jsonb = '[]'::jsonb OR target_test)
-              AND (
-                  ($6 AND (vp.ven_id IS NULL OR vp.ven_id = ANY($7))) 
-                  OR 
-                  ($8 AND ($9::text[] IS NULL OR p.business_id = ANY ($9)))
-                  )
-            GROUP BY e.id
+            SELECT e.id AS "id!",
+                   e.created_date_time AS "created_date_time!",
+                   e.modification_date_time AS "modification_date_time!",
+                   e.program_id AS "program_id!",
+                   e.event_name,
+                   e.priority,
+                   e.report_descriptors,
+                   e.payload_descriptors,
+                   e.interval_period,
+                   e.intervals AS "intervals!",
+                   e.targets,
+                   e.completed_date_time
+            FROM (
+                SELECT e.*
+                FROM event e
+                  JOIN program p on p.id = e.program_id
+                  LEFT JOIN ven_program vp ON p.id = vp.program_id
+                  LEFT JOIN ven v ON v.id = vp.ven_id
+                  LEFT JOIN LATERAL ( 
+                      SELECT e.id as e_id, 
+                             target @> ANY (SELECT jsonb_array_elements($5::jsonb)) AS target_test
+                      FROM jsonb_array_elements(e.targets) target )
+                      ON e.id = e_id
+                WHERE ($1::text IS NULL OR e.program_id like $1)
+                  AND ($2::text[] IS NULL OR e.event_name = ANY($2))
+                  AND ($3::text[] IS NULL OR p.program_name = ANY($3))
+                  AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
+                  AND ($5::jsonb = '[]'::jsonb OR target_test)
+                  AND (
+                      ($6 AND (vp.ven_id IS NULL OR vp.ven_id = ANY($7))) 
+                      OR 
+                      ($8 AND ($9::text[] IS NULL OR p.business_id = ANY ($9)))
+                      )
+                  AND ($14::timestamptz IS NULL OR (e.modification_date_time, e.id) > ($14, $15))
+                  AND event_overlaps(e.interval_period, e.intervals, $16, $17)
+                  AND ($20::text IS NULL OR e.event_name ILIKE $20
+                       OR EXISTS (SELECT FROM jsonb_array_elements(e.intervals) event_interval,
+                                              jsonb_array_elements(event_interval -> 'payloads') payload,
+                                              jsonb_array_elements(payload -> 'values') description
+                                  WHERE payload ->> 'type' = 'DESCRIPTION'
+                                    AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $20))
+                GROUP BY e.id
+                UNION ALL
+                -- only scanned with `includeArchived=true`
+                SELECT e.*
+                FROM event_archive e
+                  JOIN program p on p.id = e.program_id
+                  LEFT JOIN ven_program vp ON p.id = vp.program_id
+                  LEFT JOIN ven v ON v.id = vp.ven_id
+                  LEFT JOIN LATERAL ( 
+                      SELECT e.id as e_id, 
+                             target @> ANY (SELECT jsonb_array_elements($5::jsonb)) AS target_test
+                      FROM jsonb_array_elements(e.targets) target )
+                      ON e.id = e_id
+                WHERE $13
+                  AND ($1::text IS NULL OR e.program_id like $1)
+                  AND ($2::text[] IS NULL OR e.event_name = ANY($2))
+                  AND ($3::text[] IS NULL OR p.program_name = ANY($3))
+                  AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
+                  AND ($5::jsonb = '[]'::jsonb OR target_test)
+                  AND (
+                      ($6 AND (vp.ven_id IS NULL OR vp.ven_id = ANY($7))) 
+                      OR 
+                      ($8 AND ($9::text[] IS NULL OR p.business_id = ANY ($9)))
+                      )
+                  AND ($14::timestamptz IS NULL OR (e.modification_date_time, e.id) > ($14, $15))
+                  AND event_overlaps(e.interval_period, e.intervals, $16, $17)
+                  AND ($20::text IS NULL OR e.event_name ILIKE $20
+                       OR EXISTS (SELECT FROM jsonb_array_elements(e.intervals) event_interval,
+                                              jsonb_array_elements(event_interval -> 'payloads') payload,
+                                              jsonb_array_elements(payload -> 'values') description
+                                  WHERE payload ->> 'type' = 'DESCRIPTION'
+                                    AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $20))
+                GROUP BY e.id
+            ) e
+            ORDER BY
+              -- a lower number indicates a higher priority, an unspecified priority is the lowest
+              CASE WHEN $12::text = 'priority' THEN e.priority END ASC NULLS LAST,
+              CASE WHEN $12::text IN ('priority', 'start')
+                   THEN COALESCE(e.interval_period ->> 'start',
+                                 e.intervals -> 0 -> 'intervalPeriod' ->> 'start')::timestamptz
+                  END ASC NULLS LAST,
+              -- the `sortBy` in the `sortOrder`, see `Sorting`
+              CASE WHEN $18::text = 'created' AND NOT $19 THEN e.created_date_time END ASC,
+              CASE WHEN $18::text = 'created' AND $19 THEN e.created_date_time END DESC,
+              CASE WHEN $18::text = 'modified' AND NOT $19 THEN e.modification_date_time END ASC,
+              CASE WHEN $18::text = 'modified' AND $19 THEN e.modification_date_time END DESC,
+              CASE WHEN $18::text = 'priority' AND NOT $19 THEN e.priority END ASC NULLS LAST,
+              CASE WHEN $18::text = 'priority' AND $19 THEN e.priority END DESC NULLS LAST,
+              CASE WHEN $18::text = 'name' AND NOT $19 THEN e.event_name COLLATE "C" END ASC NULLS LAST,
+              CASE WHEN $18::text = 'name' AND $19 THEN e.event_name COLLATE "C" END DESC NULLS LAST,
+              -- the order of the cursor, see `QueryParams::cursor`
+              e.modification_date_time, e.id
             OFFSET $10 LIMIT $11
             "#,
-            pg_filter.program_id,
+            program_id,
             pg_filter.event_names,
             pg_filter.program_names,
             pg_filter.ven_names,
-            serde_json::to_value(pg_filter.targets)
-                .map_err(AppError::SerdeJsonInternalServerError)?,
+            pg_filter.targets_json()?,
             user.is_ven(),
             &user.ven_ids_string(),
             user.is_business(),
             business_ids.as_deref(),
             pg_filter.skip,
-            pg_filter.limit
+            pg_filter.limit,
+            order_by,
+            filter.extension.include_archived,
+            cursor.map(|(modification_date_time, _)| modification_date_time),
+            cursor.map(|(_, id)| id.as_str()),
+            filter.extension.active.active_after,
+            filter.extension.active.active_before,
+            filter.extension.sort.sort_by.as_ref().map(SortBy::as_str),
+            filter.extension.sort.is_descending(),
+            pg_filter.search,
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
         .collect::<Result<_, _>>()?)
     }
 
-    async fn update(
+    #[tracing::instrument(name = "PgEventStorage::count", skip_all)]
+    async fn count(
         &self,
-        id: &Self::Id,
-        new: Self::NewType,
+        filter: &Self::Filter,
         user: &Self::PermissionFilter,
-    ) -> Result<Self::Type, Self::Error> {
-        check_write_permission(new.program_id.as_str(), user, &self.db).await?;
-
-        let previous_program_id = sqlx::query_as!(
-            PgId,
-            r#"SELECT program_id AS id FROM event WHERE id = $1"#,
-            id.as_str()
-        )
-        .fetch_one(&self.db)
-        .await?;
+    ) -> Result<usize, Self::Error> {
+        let pg_filter = PostgresFilter::new(
+            filter,
+            &[
+                TargetLabel::VENName,
+                TargetLabel::ProgramName,
+                TargetLabel::EventName,
+            ],
+        );
+
+        let program_id = filter.extension.program_id.as_ref().map(|id| id.as_str());
 
-        // make sure, you cannot 'steal' an event from another business
-        if previous_program_id.id != new.program_id.as_str() {
-            check_write_permission(&previous_program_id.id, user, &self.db).await?;
-        }
+        let business_ids = match user.business_ids() {
+            BusinessIds::Specific(ids) => Some(ids),
+            BusinessIds::Any => None,
+        };
 
-        Ok(sqlx::query_as!(
-            PostgresEvent,
+        let count = sqlx::query_scalar!(
             r#"
-            UPDATE event
-            SET modification_date_time = now(),
-                program_id = $2,
-                event_name = $3,
-                priority = $4,
-                targets = $5,
-                report_descriptors = $6,
-                payload_descriptors = $7,
-                interval_period = $8,
-                intervals = $9
-            WHERE id = $1
-            RETURNING *
+            SELECT COUNT(*) AS "count!"
+            FROM (
+                SELECT DISTINCT e.id
+                FROM event e
+                  JOIN program p on p.id = e.program_id
+                  LEFT JOIN ven_program vp ON p.id = vp.program_id
+                  LEFT JOIN ven v ON v.id = vp.ven_id
+                  LEFT JOIN LATERAL ( 
+                      SELECT e.id as e_id, 
+                             target @> ANY (SELECT jsonb_array_elements($5::jsonb)) AS target_test
+                      FROM jsonb_array_elements(e.targets) target )
+                      ON e.id = e_id
+                WHERE ($1::text IS NULL OR e.program_id like $1)
+                  AND ($2::text[] IS NULL OR e.event_name = ANY($2))
+                  AND ($3::text[] IS NULL OR p.program_name = ANY($3))
+                  AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
+                  AND ($5::jsonb = '[]'::jsonb OR target_test)
+                  AND (
+                      ($6 AND (vp.ven_id IS NULL OR vp.ven_id = ANY($7))) 
+                      OR 
+                      ($8 AND ($9::text[] IS NULL OR p.business_id = ANY ($9)))
+                      )
+                  AND event_overlaps(e.interval_period, e.intervals, $11, $12)
+                  AND ($13::text IS NULL OR e.event_name ILIKE $13
+                       OR EXISTS (SELECT FROM jsonb_array_elements(e.intervals) event_interval,
+                                              jsonb_array_elements(event_interval -> 'payloads') payload,
+                                              jsonb_array_elements(payload -> 'values') description
+                                  WHERE payload ->> 'type' = 'DESCRIPTION'
+                                    AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $13))
+                UNION ALL
+                SELECT DISTINCT e.id
+                FROM event_archive e
+                  JOIN program p on p.id = e.program_id
+                  LEFT JOIN ven_program vp ON p.id = vp.program_id
+                  LEFT JOIN ven v ON v.id = vp.ven_id
+                  LEFT JOIN LATERAL ( 
+                      SELECT e.id as e_id, 
+                             target @> ANY (SELECT jsonb_array_elements($5::jsonb)) AS target_test
+                      FROM jsonb_array_elements(e.targets) target )
+                      ON e.id = e_id
+                WHERE $10
+                  AND ($1::text IS NULL OR e.program_id like $1)
+                  AND ($2::text[] IS NULL OR e.event_name = ANY($2))
+                  AND ($3::text[] IS NULL OR p.program_name = ANY($3))
+                  AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
+                  AND ($5::jsonb = '[]'::jsonb OR target_test)
+                  AND (
+                      ($6 AND (vp.ven_id IS NULL OR vp.ven_id = ANY($7))) 
+                      OR 
+                      ($8 AND ($9::text[] IS NULL OR p.business_id = ANY ($9)))
+                      )
+                  AND event_overlaps(e.interval_period, e.intervals, $11, $12)
+                  AND ($13::text IS NULL OR e.event_name ILIKE $13
+                       OR EXISTS (SELECT FROM jsonb_array_elements(e.intervals) event_interval,
+                                              jsonb_array_elements(event_interval -> 'payloads') payload,
+                                              jsonb_array_elements(payload -> 'values') description
+                                  WHERE payload ->> 'type' = 'DESCRIPTION'
+                                    AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $13))
+            ) e
             "#,
-            id.as_str(),
-            new.program_id.as_str(),
-            new.event_name,
-            Into::<Option<i64>>::into(new.priority),
-            to_json_value(new.targets)?,
-            to_json_value(new.report_descriptors)?,
-            to_json_value(new.payload_descriptors)?,
-            to_json_value(new.interval_period)?,
-            serde_json::to_value(&new.intervals).map_err(AppError::SerdeJsonBadRequest)?,
+            program_id,
+            pg_filter.event_names,
+            pg_filter.program_names,
+            pg_filter.ven_names,
+            pg_filter.targets_json()?,
+            user.is_ven(),
+            &user.ven_ids_string(),
+            user.is_business(),
+            business_ids.as_deref(),
+            filter.extension.include_archived,
+            filter.extension.active.active_after,
+            filter.extension.active.active_before,
+            pg_filter.search,
         )
-        .fetch_one(&self.db)
-        .await?
-        .try_into()?)
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+
+        Ok(count as usize)
+    }
+
+    async fn update(
+        &self,
+        id: &Self::Id,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.update_event(id, new, None, user).await
     }
 
     async fn delete(
@@ -380,59 +628,111 @@ impl Crud for PgEventStorage {
     ) -> Result<Self::Type, Self::Error> {
         let program_id = sqlx::query_as!(
             PgId,
-            r#"SELECT program_id AS id FROM event WHERE id = $1"#,
+            r#"
+            SELECT program_id AS "id!" FROM event WHERE id = $1
+            UNION ALL
+            SELECT program_id FROM event_archive WHERE id = $1
+            "#,
             id.as_str()
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?;
 
         check_write_permission(&program_id.id, user, &self.db).await?;
 
+        // archived events are deleted as well, e.g., to be able to delete their program
         Ok(sqlx::query_as!(
             PostgresEvent,
             r#"
-            DELETE FROM event WHERE id = $1 RETURNING *
+            WITH deleted AS (
+                DELETE FROM event WHERE id = $1 RETURNING *
+            ), deleted_archived AS (
+                DELETE FROM event_archive WHERE id = $1 RETURNING *
+            )
+            SELECT id AS "id!",
+                   created_date_time AS "created_date_time!",
+                   modification_date_time AS "modification_date_time!",
+                   program_id AS "program_id!",
+                   event_name,
+                   priority,
+                   report_descriptors,
+                   payload_descriptors,
+                   interval_period,
+                   intervals AS "intervals!",
+                   targets,
+                   completed_date_time
+            FROM deleted
+            UNION ALL
+            SELECT * FROM deleted_archived
             "#,
             id.as_str()
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?)
     }
 }
 
+#[async_trait]
+impl EventArchive for PgEventStorage {
+    async fn complete(&self, now: DateTime<Utc>) -> Result<u64, AppError> {
+        Ok(sqlx::query!(
+            r#"
+            UPDATE event
+            SET completed_date_time = event_end(interval_period, intervals)
+            WHERE completed_date_time IS NULL
+              AND event_end(interval_period, intervals) <= $1
+            "#,
+            now,
+        )
+        .execute(&mut *self.db.acquire().await?)
+        .await?
+        .rows_affected())
+    }
+
+    async fn archive(&self, completed_before: DateTime<Utc>) -> Result<u64, AppError> {
+        Ok(sqlx::query!(
+            r#"
+            WITH archived AS (
+                DELETE FROM event e
+                WHERE e.completed_date_time < $1
+                  AND NOT EXISTS (SELECT FROM report r WHERE r.event_id = e.id)
+                RETURNING e.*
+            )
+            INSERT INTO event_archive
+            SELECT * FROM archived
+            "#,
+            completed_before,
+        )
+        .execute(&mut *self.db.acquire().await?)
+        .await?
+        .rows_affected())
+    }
+}
+
 #[cfg(test)]
 #[cfg(feature = "live-db-test")]
 mod tests {
     use sqlx::PgPool;
 
     use crate::{
-        api::event::QueryParams,
-        data_source::{postgres::event::PgEventStorage, Crud},
+        api::{
+            event::{EventListParams, QueryParams},
+            ActiveWindow,
+        },
+        data_source::{postgres::event::PgEventStorage, Crud, EventCrud},
         error::AppError,
         jwt::Claims,
     };
     use chrono::{DateTime, Duration, Utc};
     use openadr_wire::{
-        event::{EventContent, EventInterval, EventType, EventValuesMap},
+        event::{EventContent, EventInterval, EventOrder, EventType, EventValuesMap},
         interval::IntervalPeriod,
         target::{TargetEntry, TargetLabel, TargetMap},
         values_map::Value,
         Event,
     };
 
-    impl Default for QueryParams {
-        fn default() -> Self {
-            Self {
-                program_id: None,
-                target_type: None,
-                target_values: None,
-                skip: 0,
-                limit: 50,
-            }
-        }
-    }
-
     fn event_1() -> Event {
         Event {
             id: "event-1".parse().unwrap(),
@@ -446,11 +746,11 @@ mod tests {
                 targets: Some(TargetMap(vec![
                     TargetEntry {
                         label: TargetLabel::Group,
-                        values: ["group-1".to_string()],
+                        values: vec!["group-1".to_string()],
                     },
                     TargetEntry {
                         label: TargetLabel::Private("PRIVATE_LABEL".to_string()),
-                        values: ["private value".to_string()],
+                        values: vec!["private value".to_string()],
                     },
                 ])),
                 report_descriptors: None,
@@ -488,7 +788,7 @@ mod tests {
                 priority: None.into(),
                 targets: Some(TargetMap(vec![TargetEntry {
                     label: TargetLabel::Private("SOME_TARGET".to_string()),
-                    values: ["target-1".to_string()],
+                    values: vec!["target-1".to_string()],
                 }])),
                 report_descriptors: None,
                 payload_descriptors: None,
@@ -668,6 +968,100 @@ mod tests {
             assert_eq!(events.len(), 0);
         }
 
+        #[sqlx::test(fixtures("programs"))]
+        async fn filter_multiple_values(db: PgPool) {
+            let repo: PgEventStorage = db.into();
+            let user = Claims::any_business_user();
+
+            let mut content = event_1().content;
+            content.targets = Some(TargetMap(vec![TargetEntry::with_values(
+                TargetLabel::Group,
+                ["group-1", "group-2"],
+            )]));
+            let event = repo.create(content, &user).await.unwrap();
+
+            let groups = |values: &[&str]| QueryParams {
+                target_type: Some(TargetLabel::Group),
+                target_values: Some(values.iter().map(ToString::to_string).collect()),
+                ..Default::default()
+            };
+
+            for values in [&["group-2"][..], &["group-3", "group-1"]] {
+                let events = repo.retrieve_all(&groups(values), &user).await.unwrap();
+                assert_eq!(events, vec![event.clone()], "{values:?}");
+                assert_eq!(repo.count(&groups(values), &user).await.unwrap(), 1);
+            }
+
+            let events = repo
+                .retrieve_all(&groups(&["group-3"]), &user)
+                .await
+                .unwrap();
+            assert!(events.is_empty());
+        }
+
+        #[sqlx::test(fixtures("programs", "events"))]
+        async fn filter_active_window(db: PgPool) {
+            let repo: PgEventStorage = db.into();
+            let user = Claims::any_business_user();
+
+            let window = |after: &str, before: &str| QueryParams {
+                extension: EventListParams {
+                    active: ActiveWindow {
+                        active_after: after.parse().ok(),
+                        active_before: before.parse().ok(),
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            // event 1 is the only event with an interval period, 09:30 until 10:30
+            for (after, before) in [
+                ("2023-06-15T10:00:00Z", "2023-06-15T11:00:00Z"),
+                ("2023-06-15T09:00:00Z", ""),
+                ("", "2023-06-15T10:00:00Z"),
+            ] {
+                let filter = window(after, before);
+                let events = repo.retrieve_all(&filter, &user).await.unwrap();
+                assert_eq!(events.len(), 3, "{after}..{before}");
+                assert_eq!(repo.count(&filter, &user).await.unwrap(), 3);
+            }
+
+            for (after, before) in [("2023-06-15T10:30:00Z", ""), ("", "2023-06-15T09:30:00Z")] {
+                let filter = window(after, before);
+                let events = repo.retrieve_all(&filter, &user).await.unwrap();
+                assert!(
+                    events.iter().all(|event| event.id != event_1().id),
+                    "{after}..{before}"
+                );
+                assert_eq!(repo.count(&filter, &user).await.unwrap(), 2);
+            }
+        }
+
+        #[sqlx::test(fixtures("programs", "events"))]
+        async fn order_by_priority_get_all(db: PgPool) {
+            let repo: PgEventStorage = db.into();
+
+            for order_by in [EventOrder::Priority, EventOrder::Start] {
+                let events = repo
+                    .retrieve_all(
+                        &QueryParams {
+                            extension: EventListParams {
+                                order_by: Some(order_by),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        },
+                        &Claims::any_business_user(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(events.len(), 3);
+                // event 1 is the only event with a priority and a start
+                assert_eq!(events[0], event_1());
+            }
+        }
+
         #[sqlx::test(fixtures("programs", "events"))]
         async fn filter_program_id_get_all(db: PgPool) {
             let repo: PgEventStorage = db.into();
@@ -675,7 +1069,10 @@ mod tests {
             let events = repo
                 .retrieve_all(
                     &QueryParams {
-                        program_id: Some("program-1".parse().unwrap()),
+                        extension: EventListParams {
+                            program_id: Some("program-1".parse().unwrap()),
+                            ..Default::default()
+                        },
                         ..Default::default()
                     },
                     &Claims::any_business_user(),
@@ -688,7 +1085,10 @@ mod tests {
             let events = repo
                 .retrieve_all(
                     &QueryParams {
-                        program_id: Some("program-1".parse().unwrap()),
+                        extension: EventListParams {
+                            program_id: Some("program-1".parse().unwrap()),
+                            ..Default::default()
+                        },
                         target_type: Some(TargetLabel::Group),
                         ..Default::default()
                     },
@@ -702,7 +1102,10 @@ mod tests {
             let events = repo
                 .retrieve_all(
                     &QueryParams {
-                        program_id: Some("not-existent".parse().unwrap()),
+                        extension: EventListParams {
+                            program_id: Some("not-existent".parse().unwrap()),
+                            ..Default::default()
+                        },
                         ..Default::default()
                     },
                     &Claims::any_business_user(),
@@ -812,6 +1215,41 @@ mod tests {
             assert_eq!(event.content, updated);
         }
 
+        #[sqlx::test(fixtures("programs", "events"))]
+        async fn update_unmodified(db: PgPool) {
+            let repo: PgEventStorage = db.into();
+            let id = "event-1".parse().unwrap();
+            let user = Claims::any_business_user();
+            let retrieved = repo.retrieve(&id, &user).await.unwrap();
+
+            let updated = repo
+                .update_unmodified(
+                    &id,
+                    event_2().content,
+                    retrieved.modification_date_time,
+                    &user,
+                )
+                .await
+                .unwrap();
+            assert_eq!(updated.content, event_2().content);
+
+            // the event was modified since it was retrieved
+            let err = repo
+                .update_unmodified(
+                    &id,
+                    event_1().content,
+                    retrieved.modification_date_time,
+                    &user,
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(err, AppError::Conflict(..)));
+            assert_eq!(
+                repo.retrieve(&id, &user).await.unwrap().content,
+                event_2().content
+            );
+        }
+
         #[sqlx::test(fixtures("programs", "events"))]
         async fn update_name_conflict(db: PgPool) {
             let repo: PgEventStorage = db.into();
@@ -862,4 +1300,92 @@ mod tests {
             assert!(matches!(event, Err(AppError::NotFound)));
         }
     }
+
+    mod archive {
+        use super::*;
+        use crate::{
+            data_source::postgres::program::PgProgramStorage, event_archive::EventArchive,
+        };
+
+        #[sqlx::test(fixtures("programs", "events"))]
+        async fn complete_and_archive(db: PgPool) {
+            let repo: PgEventStorage = db.into();
+            let user = Claims::any_business_user();
+            let archived = QueryParams {
+                extension: EventListParams {
+                    include_archived: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+
+            // only event-1 has intervals that end
+            assert_eq!(repo.complete(Utc::now()).await.unwrap(), 1);
+            assert_eq!(repo.complete(Utc::now()).await.unwrap(), 0);
+
+            assert_eq!(
+                repo.archive(Utc::now() - Duration::days(365 * 100))
+                    .await
+                    .unwrap(),
+                0
+            );
+            assert_eq!(repo.archive(Utc::now()).await.unwrap(), 1);
+
+            let mut events = repo.retrieve_all(&Default::default(), &user).await.unwrap();
+            events.sort_by(|a, b| a.id.as_str().cmp(b.id.as_str()));
+            assert_eq!(events, vec![event_2(), event_3()]);
+            assert_eq!(repo.count(&Default::default(), &user).await.unwrap(), 2);
+
+            let mut events = repo.retrieve_all(&archived, &user).await.unwrap();
+            events.sort_by(|a, b| a.id.as_str().cmp(b.id.as_str()));
+            assert_eq!(events, vec![event_1(), event_2(), event_3()]);
+            assert_eq!(repo.count(&archived, &user).await.unwrap(), 3);
+
+            let event = repo.retrieve(&"event-1".parse().unwrap(), &user).await;
+            assert_eq!(event.unwrap(), event_1());
+        }
+
+        #[sqlx::test(fixtures("programs", "events"))]
+        async fn update_restores_archived_event(db: PgPool) {
+            let repo: PgEventStorage = db.into();
+            let user = Claims::any_business_user();
+            repo.complete(Utc::now()).await.unwrap();
+            assert_eq!(repo.archive(Utc::now()).await.unwrap(), 1);
+
+            let mut updated = event_1().content;
+            updated.event_name = Some("updated-name".to_string());
+            let event = repo
+                .update(&"event-1".parse().unwrap(), updated.clone(), &user)
+                .await
+                .unwrap();
+            assert_eq!(event.content, updated);
+
+            // the event is active again, until it is completed and archived once more
+            let events = repo.retrieve_all(&Default::default(), &user).await.unwrap();
+            assert!(events.iter().any(|event| event.id.as_str() == "event-1"));
+            assert_eq!(repo.complete(Utc::now()).await.unwrap(), 1);
+        }
+
+        #[sqlx::test(fixtures("programs", "events"))]
+        async fn delete_archived_event_and_program(db: PgPool) {
+            let programs = PgProgramStorage::from(db.clone());
+            let repo: PgEventStorage = db.into();
+            let user = Claims::any_business_user();
+            repo.complete(Utc::now()).await.unwrap();
+            assert_eq!(repo.archive(Utc::now()).await.unwrap(), 1);
+
+            let program = programs.delete(&"program-1".parse().unwrap(), &user).await;
+            assert!(matches!(program, Err(AppError::Conflict(_, _))));
+
+            let event = repo.delete(&"event-1".parse().unwrap(), &user).await;
+            assert_eq!(event.unwrap(), event_1());
+            let event = repo.retrieve(&"event-1".parse().unwrap(), &user).await;
+            assert!(matches!(event, Err(AppError::NotFound)));
+
+            programs
+                .delete(&"program-1".parse().unwrap(), &user)
+                .await
+                .unwrap();
+        }
+    }
 }
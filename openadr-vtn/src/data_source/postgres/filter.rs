@@ -0,0 +1,219 @@
+use openadr_wire::target::TargetLabel;
+use serde::Serialize;
+
+use crate::{api::ListParams, error::AppError};
+
+/// A single target to match against the `targets` column, as a JSON array of targets
+#[derive(Serialize, Debug, PartialEq)]
+pub(super) struct PgTargetsFilter<'a> {
+    #[serde(rename = "type")]
+    label: &'a str,
+    #[serde(rename = "values")]
+    value: [String; 1],
+}
+
+/// The target filter and pagination of a list query, shared by all object types.
+///
+/// The names of VENs, resources, programs and events are stored in columns instead of the
+/// `targets` of an object. Each object type declares which of these names its query can match,
+/// e.g., through a join. Filters on any other label, including names the query of an object
+/// type cannot match, are matched against the `targets` of the objects.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct PostgresFilter<'a> {
+    pub(super) ven_names: Option<&'a [String]>,
+    pub(super) resource_names: Option<&'a [String]>,
+    pub(super) program_names: Option<&'a [String]>,
+    pub(super) event_names: Option<&'a [String]>,
+    // TODO check whether we also need to extract `PowerServiceLocation`, `ServiceArea`,
+    //  and `Group`, i.e., only leave the `Private`
+    pub(super) targets: Vec<PgTargetsFilter<'a>>,
+    /// The `ILIKE` pattern finding the [`ListParams::search`] text anywhere in a name or description
+    pub(super) search: Option<String>,
+
+    pub(super) skip: i64,
+    pub(super) limit: i64,
+}
+
+impl<'a> PostgresFilter<'a> {
+    /// The filter for a query that matches the names with the given labels in their columns
+    pub(super) fn new<E>(query: &'a ListParams<E>, name_columns: &[TargetLabel]) -> Self {
+        let mut filter = Self {
+            search: query.search.as_deref().map(search_pattern),
+            skip: query.skip,
+            limit: query.limit,
+            ..Default::default()
+        };
+
+        let (Some(label), Some(values)) = (&query.target_type, &query.target_values) else {
+            return filter;
+        };
+
+        let has_column = name_columns.contains(label);
+        let names = Some(values.as_slice());
+
+        match label {
+            TargetLabel::VENName if has_column => filter.ven_names = names,
+            TargetLabel::ResourceName if has_column => filter.resource_names = names,
+            TargetLabel::ProgramName if has_column => filter.program_names = names,
+            TargetLabel::EventName if has_column => filter.event_names = names,
+            _ => {
+                filter.targets = values
+                    .iter()
+                    .map(|value| PgTargetsFilter {
+                        label: label.as_str(),
+                        value: [value.clone()],
+                    })
+                    .collect()
+            }
+        }
+
+        filter
+    }
+
+    /// The targets as a JSON array, any of which a stored target entry contains with `@>`
+    pub(super) fn targets_json(&self) -> Result<serde_json::Value, AppError> {
+        serde_json::to_value(&self.targets).map_err(AppError::SerdeJsonInternalServerError)
+    }
+}
+
+/// The `ILIKE` pattern of a text anywhere in a string, escaping the wildcards in the text
+fn search_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(label: TargetLabel, values: &[&str]) -> ListParams {
+        ListParams {
+            target_type: Some(label),
+            target_values: Some(values.iter().map(ToString::to_string).collect()),
+            ..Default::default()
+        }
+    }
+
+    const ALL_NAMES: &[TargetLabel] = &[
+        TargetLabel::VENName,
+        TargetLabel::ResourceName,
+        TargetLabel::ProgramName,
+        TargetLabel::EventName,
+    ];
+
+    #[test]
+    fn pagination() {
+        let query: ListParams = ListParams {
+            skip: 5,
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES),
+            PostgresFilter {
+                skip: 5,
+                limit: 10,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn names_in_columns() {
+        let values = ["a".to_string(), "b".to_string()];
+        let names = Some(values.as_slice());
+
+        let query = params(TargetLabel::VENName, &["a", "b"]);
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES),
+            PostgresFilter {
+                ven_names: names,
+                limit: 50,
+                ..Default::default()
+            }
+        );
+
+        let query = params(TargetLabel::ResourceName, &["a", "b"]);
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES),
+            PostgresFilter {
+                resource_names: names,
+                limit: 50,
+                ..Default::default()
+            }
+        );
+
+        let query = params(TargetLabel::ProgramName, &["a", "b"]);
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES),
+            PostgresFilter {
+                program_names: names,
+                limit: 50,
+                ..Default::default()
+            }
+        );
+
+        let query = params(TargetLabel::EventName, &["a", "b"]);
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES),
+            PostgresFilter {
+                event_names: names,
+                limit: 50,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn names_without_column_match_targets() {
+        let query = params(TargetLabel::ResourceName, &["a"]);
+        let filter = PostgresFilter::new(&query, &[TargetLabel::VENName]);
+        assert_eq!(filter.resource_names, None);
+        assert_eq!(
+            filter.targets_json().unwrap(),
+            serde_json::json!([{"type": "RESOURCE_NAME", "values": ["a"]}])
+        );
+    }
+
+    #[test]
+    fn other_labels_match_targets() {
+        let query = params(TargetLabel::Group, &["a", "b"]);
+        let filter = PostgresFilter::new(&query, ALL_NAMES);
+        assert_eq!(filter.ven_names, None);
+        assert_eq!(
+            filter.targets_json().unwrap(),
+            serde_json::json!([
+                {"type": "GROUP", "values": ["a"]},
+                {"type": "GROUP", "values": ["b"]}
+            ])
+        );
+
+        let query = params(TargetLabel::Private("METER_ID".to_string()), &["1"]);
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES)
+                .targets_json()
+                .unwrap(),
+            serde_json::json!([{"type": "METER_ID", "values": ["1"]}])
+        );
+    }
+
+    #[test]
+    fn search_escapes_wildcards() {
+        let query: ListParams = ListParams {
+            search: Some(r"50%_a\b".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            PostgresFilter::new(&query, ALL_NAMES).search.as_deref(),
+            Some(r"%50\%\_a\\b%")
+        );
+    }
+}
@@ -1,34 +1,155 @@
 use crate::{
+    change_log::ChangeLog,
     data_source::{
         postgres::{
-            event::PgEventStorage, program::PgProgramStorage, report::PgReportStorage,
-            user::PgAuthSource, ven::PgVenStorage,
+            change_log::PgChangeLog, event::PgEventStorage, program::PgProgramStorage,
+            report::PgReportStorage, stats::PgStatsSource, user::PgAuthSource, ven::PgVenStorage,
         },
-        AuthSource, DataSource, EventCrud, ProgramCrud, ReportCrud, ResourceCrud, VenCrud,
+        AuthSource, DataSource, EventCrud, ProgramCrud, ReportCrud, ResourceCrud, TransactionFn,
+        TransactionResult, VenCrud,
     },
     error::AppError,
+    event_archive::EventArchive,
     jwt::{BusinessIds, Claims},
+    metrics::metrics,
+    stats::StatsSource,
 };
+use axum::async_trait;
 use dotenvy::dotenv;
+use log::LevelFilter;
 use openadr_wire::target::{TargetLabel, TargetMap};
 use resource::PgResourceStorage;
 use serde::Serialize;
-use sqlx::PgPool;
-use std::sync::Arc;
-use tracing::{error, info, trace};
+use sqlx::{
+    pool::PoolConnection, postgres::PgConnectOptions, ConnectOptions, PgConnection, PgPool,
+    Postgres, Transaction,
+};
+use std::{
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use tracing::{error, info, trace, warn};
+use uuid::Uuid;
 
+/// Queries taking longer are logged, unless `OPENADR_SLOW_QUERY_THRESHOLD_MS` is set,
+/// see [`PostgresStorage::log_slow_queries`]
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_secs(1);
+
+mod change_log;
 mod event;
+mod filter;
 mod program;
 mod report;
 mod resource;
+mod stats;
 mod user;
 mod ven;
 
 #[derive(Clone)]
 pub struct PostgresStorage {
-    db: PgPool,
+    db: PgDb,
+}
+
+/// Where the storages execute their queries
+#[derive(Clone)]
+pub(crate) enum PgDb {
+    /// Each query uses a connection from the pool
+    Pool(PgPool),
+    /// All queries are part of a single transaction, see [`DataSource::run_transaction`].
+    /// The transaction is taken out once it is committed or rolled back.
+    Transaction(Arc<Mutex<Option<Transaction<'static, Postgres>>>>),
+}
+
+impl From<PgPool> for PgDb {
+    fn from(db: PgPool) -> Self {
+        PgDb::Pool(db)
+    }
+}
+
+/// How often a transaction is attempted when it fails on a concurrent transaction
+const MAX_TRANSACTION_ATTEMPTS: u32 = 4;
+/// The upper bound of the random wait before the first retry, doubling with each retry
+const TRANSACTION_RETRY_BACKOFF: Duration = Duration::from_millis(25);
+
+impl PgDb {
+    pub(crate) async fn acquire(&self) -> Result<PgConn<'_>, sqlx::Error> {
+        match self {
+            PgDb::Pool(pool) => Ok(PgConn::Pool(pool.acquire().await?)),
+            // storages used after their transaction finished behave like storages of a closed pool
+            PgDb::Transaction(tx) => MutexGuard::try_map(tx.lock().await, Option::as_mut)
+                .map(PgConn::Transaction)
+                .map_err(|_| sqlx::Error::PoolClosed),
+        }
+    }
+
+    /// Run the transaction `attempt` again if it fails on a serialization failure or deadlock,
+    /// which usually succeeds once the concurrent transaction is finished.
+    ///
+    /// Within a [`DataSource::run_transaction`], the failure aborts the enclosing transaction,
+    /// so it is passed on to the caller of `run_transaction` instead.
+    pub(crate) async fn retry_on_conflict<T, F, Fut>(&self, mut attempt: F) -> Result<T, AppError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let mut attempts = 1;
+        loop {
+            match attempt().await {
+                Err(AppError::SerializationFailure(err))
+                    if matches!(self, PgDb::Pool(_)) && attempts < MAX_TRANSACTION_ATTEMPTS =>
+                {
+                    // random waits spread out the retries of the conflicting transactions
+                    let backoff = jitter(TRANSACTION_RETRY_BACKOFF * 2u32.pow(attempts - 1));
+                    warn!(attempts, ?backoff, %err, "transaction conflicted with a concurrent transaction, retrying");
+                    metrics().record_transaction_retry();
+
+                    tokio::time::sleep(backoff).await;
+                    attempts += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A random duration in `[0, max)`.
+/// The random bits of a v4 UUID suffice here, which saves a dependency.
+fn jitter(max: Duration) -> Duration {
+    max.mul_f64((Uuid::new_v4().as_u128() >> 80) as f64 / (1u64 << 48) as f64)
+}
+
+/// A connection acquired from a [`PgDb`]
+// only ever lives on the stack for the duration of a query, boxing would cost an allocation each time
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum PgConn<'a> {
+    Pool(PoolConnection<Postgres>),
+    Transaction(MappedMutexGuard<'a, Transaction<'static, Postgres>>),
+}
+
+impl Deref for PgConn<'_> {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            PgConn::Pool(conn) => conn,
+            PgConn::Transaction(tx) => tx,
+        }
+    }
+}
+
+impl DerefMut for PgConn<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            PgConn::Pool(conn) => conn,
+            PgConn::Transaction(tx) => tx,
+        }
+    }
 }
 
+#[async_trait]
 impl DataSource for PostgresStorage {
     fn programs(&self) -> Arc<dyn ProgramCrud> {
         Arc::<PgProgramStorage>::new(self.db.clone().into())
@@ -53,11 +174,61 @@ impl DataSource for PostgresStorage {
     fn auth(&self) -> Arc<dyn AuthSource> {
         Arc::<PgAuthSource>::new(self.db.clone().into())
     }
+
+    fn stats(&self) -> Arc<dyn StatsSource> {
+        Arc::<PgStatsSource>::new(self.db.clone().into())
+    }
+
+    /// A change log in the `object_changes` table, see [`AppState::with_change_log`](crate::state::AppState::with_change_log)
+    fn change_log(&self) -> Option<Arc<dyn ChangeLog>> {
+        Some(Arc::<PgChangeLog>::new(self.db.clone().into()))
+    }
+
+    async fn run_transaction(&self, f: TransactionFn<'_>) -> TransactionResult {
+        let pool = match &self.db {
+            PgDb::Pool(pool) => pool,
+            // nested transactions join the enclosing transaction
+            PgDb::Transaction(_) => return f(self).await,
+        };
+
+        let tx = Arc::new(Mutex::new(Some(pool.begin().await?)));
+        let storage = PostgresStorage {
+            db: PgDb::Transaction(tx.clone()),
+        };
+
+        let result = f(&storage).await;
+
+        let tx = tx
+            .lock()
+            .await
+            .take()
+            .expect("the transaction is only finished here");
+        match result {
+            Ok(_) => tx.commit().await?,
+            Err(_) => tx.rollback().await?,
+        }
+
+        result
+    }
 }
 
 impl PostgresStorage {
     pub fn new(db: PgPool) -> Result<Self, sqlx::Error> {
-        Ok(Self { db })
+        Ok(Self { db: db.into() })
+    }
+
+    /// Completes and archives the events in the `event` table, see [`spawn_archiver`](crate::event_archive::spawn_archiver)
+    pub fn event_archive(&self) -> Arc<dyn EventArchive> {
+        Arc::<PgEventStorage>::new(self.db.clone().into())
+    }
+
+    /// Log the queries that take longer than the `threshold` at `WARN` level,
+    /// with their statement, duration, and the number of rows they returned or affected.
+    ///
+    /// The target-filtered list queries run in spans named after their storage and operation,
+    /// e.g., `PgProgramStorage::retrieve_all`, such that slow queries can be traced back to them.
+    pub fn log_slow_queries(options: PgConnectOptions, threshold: Duration) -> PgConnectOptions {
+        options.log_slow_statements(LevelFilter::Warn, threshold)
     }
 
     pub async fn from_env() -> Result<Self, sqlx::Error> {
@@ -65,7 +236,23 @@ impl PostgresStorage {
         let db_url = std::env::var("DATABASE_URL")
             .expect("Missing DATABASE_URL env var even though the 'postgres' feature is active");
 
-        let db = PgPool::connect(&db_url).await?;
+        Self::connect(&db_url).await
+    }
+
+    /// Connect to the database at `db_url`, logging slow queries as configured with
+    /// `OPENADR_SLOW_QUERY_THRESHOLD_MS`
+    pub async fn connect(db_url: &str) -> Result<Self, sqlx::Error> {
+        let slow_query_threshold = std::env::var("OPENADR_SLOW_QUERY_THRESHOLD_MS")
+            .map(|millis| {
+                Duration::from_millis(
+                    millis
+                        .parse()
+                        .expect("invalid OPENADR_SLOW_QUERY_THRESHOLD_MS"),
+                )
+            })
+            .unwrap_or(DEFAULT_SLOW_QUERY_THRESHOLD);
+        let options = Self::log_slow_queries(db_url.parse()?, slow_query_threshold);
+        let db = PgPool::connect_with(options).await?;
 
         let connect_options = db.connect_options();
         let safe_db_url = format!(
@@ -91,14 +278,6 @@ fn to_json_value<T: Serialize>(v: Option<T>) -> Result<Option<serde_json::Value>
         .transpose()
 }
 
-#[derive(Serialize, Debug)]
-struct PgTargetsFilter<'a> {
-    #[serde(rename = "type")]
-    label: &'a str,
-    #[serde(rename = "values")]
-    value: [String; 1],
-}
-
 #[tracing::instrument(level = "trace")]
 fn extract_vens(targets: Option<TargetMap>) -> (Option<TargetMap>, Option<Vec<String>>) {
     if let Some(TargetMap(targets)) = targets {
@@ -106,10 +285,7 @@ fn extract_vens(targets: Option<TargetMap>) -> (Option<TargetMap>, Option<Vec<St
             .into_iter()
             .partition(|t| t.label == TargetLabel::VENName);
 
-        let vens = vens
-            .into_iter()
-            .map(|t| t.values[0].clone())
-            .collect::<Vec<_>>();
+        let vens = vens.into_iter().flat_map(|t| t.values).collect::<Vec<_>>();
 
         let targets = if targets.is_empty() {
             None
@@ -149,3 +325,356 @@ fn extract_business_ids(user: &Claims) -> Option<Vec<String>> {
 struct PgId {
     id: String,
 }
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod tests {
+    use super::PgDb;
+    use crate::{
+        api::ListParams,
+        data_source::{DataSource, PostgresStorage},
+        error::AppError,
+        jwt::Claims,
+        metrics::metrics,
+    };
+    use openadr_wire::program::ProgramContent;
+    use sqlx::{
+        error::{DatabaseError, ErrorKind},
+        PgPool,
+    };
+    use std::borrow::Cow;
+
+    #[sqlx::test]
+    async fn transaction(db: PgPool) {
+        let storage: &dyn DataSource = &PostgresStorage::new(db).unwrap();
+
+        let program = storage
+            .transaction(|tx| {
+                Box::pin(async move {
+                    let user = Claims::any_business_user();
+                    tx.programs()
+                        .create(ProgramContent::new("committed"), &user)
+                        .await
+                })
+            })
+            .await
+            .unwrap();
+
+        let err = storage
+            .transaction(|tx| {
+                Box::pin(async move {
+                    let user = Claims::any_business_user();
+                    tx.programs()
+                        .create(ProgramContent::new("rolled-back"), &user)
+                        .await?;
+                    Err::<(), _>(AppError::BadRequest("abort"))
+                })
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+
+        let programs = storage
+            .programs()
+            .retrieve_all(&ListParams::default(), &Claims::any_business_user())
+            .await
+            .unwrap();
+        assert_eq!(programs, vec![program]);
+    }
+
+    /// A serialization failure without a database, as real ones depend on the timing of transactions
+    #[derive(Debug)]
+    struct SerializationFailure;
+
+    impl std::fmt::Display for SerializationFailure {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str(self.message())
+        }
+    }
+
+    impl std::error::Error for SerializationFailure {}
+
+    impl DatabaseError for SerializationFailure {
+        fn message(&self) -> &str {
+            "could not serialize access due to concurrent update"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some("40001".into())
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn serialization_failure() -> AppError {
+        AppError::from(Box::new(SerializationFailure) as Box<dyn DatabaseError>)
+    }
+
+    #[sqlx::test]
+    async fn retries_serialization_failures(db: PgPool) {
+        let db = PgDb::Pool(db);
+        let retries_before = metrics().transaction_retries();
+
+        let mut attempts = 0;
+        let result = db
+            .retry_on_conflict(|| {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt < 3 {
+                        Err(serialization_failure())
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+        assert!(metrics().transaction_retries() >= retries_before + 2);
+
+        // other errors are not retried
+        let mut attempts = 0;
+        let result: Result<(), _> = db
+            .retry_on_conflict(|| {
+                attempts += 1;
+                async { Err(AppError::NotFound) }
+            })
+            .await;
+        assert!(matches!(result, Err(AppError::NotFound)));
+        assert_eq!(attempts, 1);
+
+        // the retries are bounded
+        let result: Result<(), _> = db
+            .retry_on_conflict(|| async { Err(serialization_failure()) })
+            .await;
+        assert!(matches!(result, Err(AppError::SerializationFailure(_))));
+    }
+
+    mod constraints {
+        use crate::error::{constraint_message, AppError, Violation};
+        use axum::{http::StatusCode, response::IntoResponse};
+        use sqlx::PgPool;
+
+        async fn violate(db: &PgPool, sql: &str) -> AppError {
+            sqlx::query(sql).execute(db).await.unwrap_err().into()
+        }
+
+        fn constraint(err: &AppError) -> Option<&str> {
+            match err {
+                AppError::Conflict(_, Some(err))
+                | AppError::ForeignKeyConstraintViolated(_, Some(err)) => err.constraint(),
+                _ => None,
+            }
+        }
+
+        #[sqlx::test]
+        async fn every_constraint_has_a_message(db: PgPool) {
+            // the delete action of foreign keys, or a space for other constraints
+            let constraints: Vec<(String, String)> = sqlx::query_as(
+                r#"
+                SELECT conname::text, confdeltype::text
+                FROM pg_constraint
+                WHERE connamespace = 'public'::regnamespace
+                  AND contype IN ('p', 'u', 'f')
+                  AND conrelid::regclass::text NOT LIKE '\_sqlx%'
+                UNION ALL
+                SELECT indexname::text, ' '
+                FROM pg_indexes
+                WHERE schemaname = 'public'
+                  AND indexdef LIKE 'CREATE UNIQUE INDEX%'
+                  AND indexname NOT IN (SELECT conname FROM pg_constraint)
+                "#,
+            )
+            .fetch_all(&db)
+            .await
+            .unwrap();
+            assert!(!constraints.is_empty());
+
+            for (name, on_delete) in constraints {
+                assert!(
+                    constraint_message(&name, Violation::Write).is_some(),
+                    "no message for {name}"
+                );
+                // foreign keys without `on delete cascade`
+                if on_delete == "a" || on_delete == "r" {
+                    assert!(
+                        constraint_message(&name, Violation::Delete).is_some(),
+                        "no delete message for {name}"
+                    );
+                }
+            }
+        }
+
+        #[sqlx::test(fixtures(
+            "users",
+            "programs",
+            "business",
+            "events",
+            "reports",
+            "vens",
+            "resources",
+            "vens-programs"
+        ))]
+        async fn unique_violations(db: PgPool) {
+            let cases = [
+                ("business_pk", "INSERT INTO business (id) VALUES ('business-1')"),
+                ("program_pk", "INSERT INTO program (id, created_date_time, modification_date_time, program_name) VALUES ('program-1', now(), now(), 'new-name')"),
+                ("event_pk", "INSERT INTO event (id, created_date_time, modification_date_time, program_id, intervals) VALUES ('event-1', now(), now(), 'program-1', '[]')"),
+                ("event_archive_pkey", "INSERT INTO event_archive SELECT e.* FROM event e CROSS JOIN generate_series(1, 2) WHERE e.id = 'event-1'"),
+                ("report_pk", "INSERT INTO report (id, created_date_time, modification_date_time, program_id, event_id, client_name, resources) VALUES ('report-1', now(), now(), 'program-1', 'event-1', 'client', '{}')"),
+                ("ven_pk", "INSERT INTO ven (id, created_date_time, modification_date_time, ven_name) VALUES ('ven-1', now(), now(), 'new-name')"),
+                ("resource_pk", "INSERT INTO resource (id, created_date_time, modification_date_time, resource_name, ven_id) VALUES ('resource-1', now(), now(), 'new-name', 'ven-1')"),
+                ("user_pkey", "INSERT INTO \"user\" (id, reference, created, modified) VALUES ('user-1', 'new-ref', now(), now())"),
+                ("object_changes_pk", "INSERT INTO object_changes (seq, object_type, object_id, operation, actor, changed_at, payload_hash) VALUES (1, 'PROGRAM', 'program-1', 'CREATE', 'admin', now(), ''), (1, 'PROGRAM', 'program-1', 'CREATE', 'admin', now(), '')"),
+                ("program_program_name_uindex", "INSERT INTO program (id, created_date_time, modification_date_time, program_name) VALUES ('program-new', now(), now(), 'program-1')"),
+                ("report_report_name_uindex", "UPDATE report SET report_name = 'report'"),
+                ("ven_ven_name_uindex", "INSERT INTO ven (id, created_date_time, modification_date_time, ven_name) VALUES ('ven-new', now(), now(), 'ven-1-name')"),
+                ("resource_resource_name_key", "INSERT INTO resource (id, created_date_time, modification_date_time, resource_name, ven_id) VALUES ('resource-new', now(), now(), 'resource-1-name', 'ven-1')"),
+                ("user_credentials_pkey", "INSERT INTO user_credentials (user_id, client_id, client_secret) VALUES ('user-1', 'admin', 'secret')"),
+                ("ven_program_pk", "INSERT INTO ven_program (program_id, ven_id) VALUES ('program-1', 'ven-1')"),
+                ("uindex_user_business", "INSERT INTO user_business (user_id, business_id) VALUES ('user-1', 'business-1')"),
+                ("ven_manager_pkey", "INSERT INTO ven_manager (user_id) VALUES ('admin')"),
+                ("user_manager_pkey", "INSERT INTO user_manager (user_id) VALUES ('admin')"),
+                ("any_business_user_pkey", "INSERT INTO any_business_user (user_id) VALUES ('admin')"),
+            ];
+
+            for (name, sql) in cases {
+                let err = violate(&db, sql).await;
+                let expected = constraint_message(name, Violation::Write).unwrap();
+                assert!(
+                    matches!(&err, AppError::Conflict(message, _) if message == expected),
+                    "{name}: {err:?}"
+                );
+                assert_eq!(constraint(&err), Some(name));
+            }
+        }
+
+        #[sqlx::test(fixtures("users", "programs", "business", "events", "vens"))]
+        async fn foreign_key_violations(db: PgPool) {
+            let cases = [
+                ("program_business_id_fkey", "UPDATE program SET business_id = 'unknown' WHERE id = 'program-1'"),
+                ("event_program_id_fkey", "INSERT INTO event (id, created_date_time, modification_date_time, program_id, intervals) VALUES ('event-new', now(), now(), 'unknown', '[]')"),
+                ("event_archive_program_id_fkey", "INSERT INTO event_archive (id, created_date_time, modification_date_time, program_id, intervals) VALUES ('event-new', now(), now(), 'unknown', '[]')"),
+                ("report_program_id_fkey", "INSERT INTO report (id, created_date_time, modification_date_time, program_id, event_id, client_name, resources) VALUES ('report-new', now(), now(), 'unknown', 'event-1', 'client', '{}')"),
+                ("report_event_id_fkey", "INSERT INTO report (id, created_date_time, modification_date_time, program_id, event_id, client_name, resources) VALUES ('report-new', now(), now(), 'program-1', 'unknown', 'client', '{}')"),
+                ("resource_ven_id_fkey", "INSERT INTO resource (id, created_date_time, modification_date_time, resource_name, ven_id) VALUES ('resource-new', now(), now(), 'new-name', 'unknown')"),
+                ("ven_program_program_id_fkey", "INSERT INTO ven_program (program_id, ven_id) VALUES ('unknown', 'ven-1')"),
+                ("ven_program_ven_id_fkey", "INSERT INTO ven_program (program_id, ven_id) VALUES ('program-1', 'unknown')"),
+                ("user_ven_ven_id_fkey", "INSERT INTO user_ven (ven_id, user_id) VALUES ('unknown', 'user-1')"),
+                ("user_ven_user_id_fkey", "INSERT INTO user_ven (ven_id, user_id) VALUES ('ven-1', 'unknown')"),
+                ("user_business_user_id_fkey", "INSERT INTO user_business (user_id, business_id) VALUES ('unknown', 'business-1')"),
+                ("user_business_business_id_fkey", "INSERT INTO user_business (user_id, business_id) VALUES ('user-1', 'unknown')"),
+                ("user_credentials_user_id_fkey", "INSERT INTO user_credentials (user_id, client_id, client_secret) VALUES ('unknown', 'client-new', 'secret')"),
+                ("ven_manager_user_id_fkey", "INSERT INTO ven_manager (user_id) VALUES ('unknown')"),
+                ("user_manager_user_id_fkey", "INSERT INTO user_manager (user_id) VALUES ('unknown')"),
+                ("any_business_user_user_id_fkey", "INSERT INTO any_business_user (user_id) VALUES ('unknown')"),
+            ];
+
+            for (name, sql) in cases {
+                let err = violate(&db, sql).await;
+                let expected = constraint_message(name, Violation::Write).unwrap();
+                assert!(
+                    matches!(&err, AppError::ForeignKeyConstraintViolated(message, _) if message == expected),
+                    "{name}: {err:?}"
+                );
+                assert_eq!(constraint(&err), Some(name));
+            }
+        }
+
+        #[sqlx::test(fixtures("users", "programs", "business"))]
+        async fn deleting_referenced_objects(db: PgPool) {
+            let cases = [
+                ("program_business_id_fkey", None, "DELETE FROM business WHERE id = 'business-1'"),
+                (
+                    "event_program_id_fkey",
+                    Some("INSERT INTO event (id, created_date_time, modification_date_time, program_id, intervals) VALUES ('event-a', now(), now(), 'program-2', '[]')"),
+                    "DELETE FROM program WHERE id = 'program-2'",
+                ),
+                (
+                    "report_program_id_fkey",
+                    Some("INSERT INTO report (id, created_date_time, modification_date_time, program_id, event_id, client_name, resources) VALUES ('report-a', now(), now(), 'program-1', 'event-a', 'client', '{}')"),
+                    "DELETE FROM program WHERE id = 'program-1'",
+                ),
+                ("report_event_id_fkey", None, "DELETE FROM event WHERE id = 'event-a'"),
+                (
+                    "event_archive_program_id_fkey",
+                    Some("INSERT INTO program (id, created_date_time, modification_date_time, program_name) VALUES ('program-a', now(), now(), 'program-a'); INSERT INTO event_archive (id, created_date_time, modification_date_time, program_id, intervals) VALUES ('event-b', now(), now(), 'program-a', '[]')"),
+                    "DELETE FROM program WHERE id = 'program-a'",
+                ),
+                (
+                    "resource_ven_id_fkey",
+                    Some("INSERT INTO ven (id, created_date_time, modification_date_time, ven_name) VALUES ('ven-a', now(), now(), 'ven-a'); INSERT INTO resource (id, created_date_time, modification_date_time, resource_name, ven_id) VALUES ('resource-a', now(), now(), 'resource-a', 'ven-a')"),
+                    "DELETE FROM ven WHERE id = 'ven-a'",
+                ),
+            ];
+
+            for (name, setup, sql) in cases {
+                if let Some(setup) = setup {
+                    sqlx::raw_sql(setup).execute(&db).await.unwrap();
+                }
+                let err = violate(&db, sql).await;
+                let expected = constraint_message(name, Violation::Delete).unwrap();
+                assert!(
+                    matches!(&err, AppError::Conflict(message, _) if message == expected),
+                    "{name}: {err:?}"
+                );
+                assert_eq!(constraint(&err), Some(name));
+            }
+        }
+
+        #[sqlx::test]
+        async fn serialization_failure(db: PgPool) {
+            let insert = |id: &str| {
+                format!("INSERT INTO program (id, created_date_time, modification_date_time, program_name) VALUES ('{id}', now(), now(), '{id}')")
+            };
+
+            // both transactions read the programs the other one adds to
+            let mut first = db.begin().await.unwrap();
+            let mut second = db.begin().await.unwrap();
+            for tx in [&mut first, &mut second] {
+                sqlx::query("SET TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+                    .execute(&mut **tx)
+                    .await
+                    .unwrap();
+                sqlx::query("SELECT count(*) FROM program")
+                    .execute(&mut **tx)
+                    .await
+                    .unwrap();
+            }
+            sqlx::query(&insert("program-a"))
+                .execute(&mut *first)
+                .await
+                .unwrap();
+
+            let err = async move {
+                sqlx::query(&insert("program-b"))
+                    .execute(&mut *second)
+                    .await?;
+                first.commit().await?;
+                second.commit().await
+            }
+            .await
+            .unwrap_err();
+
+            let err = AppError::from(err);
+            assert!(matches!(err, AppError::SerializationFailure(_)), "{err:?}");
+            let response = err.into_response();
+            assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+            assert!(response.headers().contains_key("retry-after"));
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use crate::{
     api::program::QueryParams,
     data_source::{
-        postgres::{extract_business_id, extract_vens, to_json_value, PgTargetsFilter},
+        postgres::{
+            extract_business_id, extract_vens, filter::PostgresFilter, to_json_value, PgDb,
+        },
         Crud, ProgramCrud,
     },
     error::AppError,
@@ -12,20 +14,119 @@ use chrono::{DateTime, Utc};
 use openadr_wire::{
     program::{ProgramContent, ProgramId},
     target::TargetLabel,
-    Program,
+    ven::VenId,
+    Program, SortBy,
 };
-use sqlx::PgPool;
+use sqlx::{Connection, PgPool};
 use tracing::{error, trace};
 
 #[async_trait]
-impl ProgramCrud for PgProgramStorage {}
+impl ProgramCrud for PgProgramStorage {
+    async fn validate_create(
+        &self,
+        new: ProgramContent,
+        user: &Claims,
+    ) -> Result<Program, AppError> {
+        self.insert(new, user, true).await
+    }
+
+    async fn vens(&self, id: &ProgramId, user: &Claims) -> Result<Vec<VenId>, AppError> {
+        let business_id = extract_business_id(user)?;
+
+        let program = sqlx::query!(
+            r#"
+            SELECT array_remove(array_agg(vp.ven_id ORDER BY vp.ven_id), NULL) AS "ven_ids!"
+            FROM program p
+              LEFT JOIN ven_program vp ON p.id = vp.program_id
+            WHERE p.id = $1
+              AND ($2::text IS NULL OR p.business_id = $2)
+            GROUP BY p.id
+            "#,
+            id.as_str(),
+            business_id,
+        )
+        .fetch_optional(&mut *self.db.acquire().await?)
+        .await?
+        .ok_or(AppError::NotFound)?;
+
+        program
+            .ven_ids
+            .into_iter()
+            .map(|ven_id| Ok(ven_id.parse::<VenId>()?))
+            .collect()
+    }
+
+    async fn assign_ven(
+        &self,
+        id: &ProgramId,
+        ven_id: &VenId,
+        user: &Claims,
+    ) -> Result<(), AppError> {
+        let business_id = extract_business_id(user)?;
+
+        let rows_affected = sqlx::query!(
+            r#"
+            INSERT INTO ven_program (program_id, ven_id)
+                (SELECT id, $2 FROM program WHERE id = $1 AND ($3::text IS NULL OR business_id = $3))
+            "#,
+            id.as_str(),
+            ven_id.as_str(),
+            business_id,
+        )
+        .execute(&mut *self.db.acquire().await?)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+
+    async fn unassign_ven(
+        &self,
+        id: &ProgramId,
+        ven_id: &VenId,
+        user: &Claims,
+    ) -> Result<(), AppError> {
+        let business_id = extract_business_id(user)?;
+
+        let rows_affected = sqlx::query!(
+            r#"
+            DELETE FROM ven_program vp
+            USING program p
+            WHERE vp.program_id = p.id
+              AND p.id = $1
+              AND vp.ven_id = $2
+              AND ($3::text IS NULL OR p.business_id = $3)
+            "#,
+            id.as_str(),
+            ven_id.as_str(),
+            business_id,
+        )
+        .execute(&mut *self.db.acquire().await?)
+        .await?
+        .rows_affected();
+
+        if rows_affected == 0 {
+            return Err(AppError::NotFound);
+        }
+        Ok(())
+    }
+}
 
 pub(crate) struct PgProgramStorage {
-    db: PgPool,
+    db: PgDb,
 }
 
 impl From<PgPool> for PgProgramStorage {
     fn from(db: PgPool) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+impl From<PgDb> for PgProgramStorage {
+    fn from(db: PgDb) -> Self {
         Self { db }
     }
 }
@@ -47,6 +148,7 @@ struct PostgresProgram {
     binding_events: Option<bool>,
     local_price: Option<bool>,
     payload_descriptors: Option<serde_json::Value>,
+    default_priority: Option<i64>,
     targets: Option<serde_json::Value>,
 }
 
@@ -116,74 +218,79 @@ impl TryFrom<PostgresProgram> for Program {
                 binding_events: value.binding_events,
                 local_price: value.local_price,
                 payload_descriptors,
+                default_priority: value.default_priority.map(|priority| Some(priority).into()),
                 targets,
             },
         })
     }
 }
 
-#[derive(Debug, Default)]
-struct PostgresFilter<'a> {
-    ven_names: Option<&'a [String]>,
-    event_names: Option<&'a [String]>,
-    program_names: Option<&'a [String]>,
-    // TODO check whether we also need to extract `PowerServiceLocation`, `ServiceArea`,
-    //  `ResourceNames`, and `Group`, i.e., only leave the `Private`
-    targets: Vec<PgTargetsFilter<'a>>,
-
-    skip: i64,
-    limit: i64,
-}
+impl PgProgramStorage {
+    /// A conflict with the existing program if the error violates the unique program names,
+    /// otherwise the error as is
+    async fn duplicate_name(&self, err: sqlx::Error, program_name: &str) -> AppError {
+        let constraint = err.as_database_error().and_then(|err| err.constraint());
+        if constraint != Some("program_program_name_uindex") {
+            return err.into();
+        }
 
-impl<'a> From<&'a QueryParams> for PostgresFilter<'a> {
-    fn from(query: &'a QueryParams) -> Self {
-        let mut filter = Self {
-            skip: query.skip,
-            limit: query.limit,
-            ..Default::default()
-        };
-        match query.target_type {
-            Some(TargetLabel::VENName) => filter.ven_names = query.target_values.as_deref(),
-            Some(TargetLabel::EventName) => filter.event_names = query.target_values.as_deref(),
-            Some(TargetLabel::ProgramName) => filter.program_names = query.target_values.as_deref(),
-            Some(ref label) => {
-                if let Some(values) = query.target_values.as_ref() {
-                    filter.targets = values
-                        .iter()
-                        .map(|value| PgTargetsFilter {
-                            label: label.as_str(),
-                            value: [value.clone()],
-                        })
-                        .collect()
-                }
-            }
-            None => {}
-        };
+        // the transaction that failed is rolled back, so the lookup needs a connection of its own
+        let existing = async {
+            sqlx::query!(
+                r#"
+                SELECT id, modification_date_time FROM program WHERE program_name = $1
+                "#,
+                program_name
+            )
+            .fetch_optional(&mut *self.db.acquire().await?)
+            .await
+        }
+        .await;
 
-        filter
+        match existing {
+            Ok(Some(existing)) => AppError::DuplicateName {
+                object: "program",
+                id: existing.id,
+                modification_date_time: existing.modification_date_time,
+            },
+            // the existing program was deleted in the meantime
+            Ok(None) => err.into(),
+            Err(lookup_err) => {
+                error!(
+                    ?lookup_err,
+                    "could not look up the program with the duplicate name"
+                );
+                err.into()
+            }
+        }
     }
-}
 
-#[async_trait]
-impl Crud for PgProgramStorage {
-    type Type = Program;
-    type Id = ProgramId;
-    type NewType = ProgramContent;
-    type Error = AppError;
-    type Filter = QueryParams;
-    type PermissionFilter = Claims;
+    /// Insert the program, retrying if the transaction conflicts with a concurrent transaction
+    async fn insert(
+        &self,
+        new: ProgramContent,
+        user: &Claims,
+        dry_run: bool,
+    ) -> Result<Program, AppError> {
+        self.db
+            .retry_on_conflict(move || self.try_insert(new.clone(), user, dry_run))
+            .await
+    }
 
-    async fn create(
+    /// Insert the program in a transaction, which a dry run rolls back
+    async fn try_insert(
         &self,
-        new: Self::NewType,
-        user: &Self::PermissionFilter,
-    ) -> Result<Self::Type, Self::Error> {
+        new: ProgramContent,
+        user: &Claims,
+        dry_run: bool,
+    ) -> Result<Program, AppError> {
         let (targets, vens) = extract_vens(new.targets);
         let business_id = extract_business_id(user)?;
 
-        let mut tx = self.db.begin().await?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
 
-        let program: Program = sqlx::query_as!(
+        let program = sqlx::query_as!(
             PostgresProgram,
             r#"
             INSERT INTO program (id,
@@ -201,9 +308,10 @@ impl Crud for PgProgramStorage {
                                  binding_events,
                                  local_price,
                                  payload_descriptors,
+                                 default_priority,
                                  targets,
                                  business_id)
-            VALUES (gen_random_uuid(), now(), now(), $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            VALUES (gen_random_uuid(), now(), now(), $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
             RETURNING id,
                       created_date_time,
                       modification_date_time,
@@ -219,6 +327,7 @@ impl Crud for PgProgramStorage {
                       binding_events,
                       local_price,
                       payload_descriptors,
+                      default_priority,
                       targets
             "#,
             new.program_name,
@@ -233,12 +342,20 @@ impl Crud for PgProgramStorage {
             new.binding_events,
             new.local_price,
             to_json_value(new.payload_descriptors)?,
+            new.default_priority.and_then(Option::<i64>::from),
             to_json_value(targets)?,
             business_id,
         )
             .fetch_one(&mut *tx)
-            .await?
-            .try_into()?;
+            .await;
+        let program: Program = match program {
+            Ok(program) => program.try_into()?,
+            Err(err) => {
+                drop(tx);
+                drop(conn);
+                return Err(self.duplicate_name(err, &new.program_name).await);
+            }
+        };
 
         if let Some(vens) = vens {
             let rows_affected = sqlx::query!(
@@ -259,9 +376,140 @@ impl Crud for PgProgramStorage {
                 ))?
             }
         };
+        if !dry_run {
+            tx.commit().await?;
+        }
+        Ok(program)
+    }
+
+    /// Update the program and the VENs it is linked to in a transaction
+    async fn try_update(
+        &self,
+        id: &ProgramId,
+        new: ProgramContent,
+        user: &Claims,
+    ) -> Result<Program, AppError> {
+        let (targets, vens) = extract_vens(new.targets);
+        let business_id = extract_business_id(user)?;
+
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
+        let program = sqlx::query_as!(
+            PostgresProgram,
+            r#"
+            UPDATE program p
+            SET modification_date_time = now(),
+                program_name = $2,
+                program_long_name = $3,
+                retailer_name = $4,
+                retailer_long_name = $5,
+                program_type = $6,
+                country = $7,
+                principal_subdivision = $8,
+                interval_period = $9,
+                program_descriptions = $10,
+                binding_events = $11,
+                local_price = $12,
+                payload_descriptors = $13,
+                default_priority = $14,
+                targets = $15
+            WHERE id = $1
+                AND ($16::text IS NULL OR business_id = $16)
+            RETURNING p.id,
+                   p.created_date_time,
+                   p.modification_date_time,
+                   p.program_name,
+                   p.program_long_name,
+                   p.retailer_name,
+                   p.retailer_long_name,
+                   p.program_type,
+                   p.country,
+                   p.principal_subdivision,
+                   p.interval_period,
+                   p.program_descriptions,
+                   p.binding_events,
+                   p.local_price,
+                   p.payload_descriptors,
+                   p.default_priority,
+                   p.targets
+            "#,
+            id.as_str(),
+            new.program_name,
+            new.program_long_name,
+            new.retailer_name,
+            new.retailer_long_name,
+            new.program_type,
+            new.country,
+            new.principal_subdivision,
+            to_json_value(new.interval_period)?,
+            to_json_value(new.program_descriptions)?,
+            new.binding_events,
+            new.local_price,
+            to_json_value(new.payload_descriptors)?,
+            new.default_priority.and_then(Option::<i64>::from),
+            to_json_value(targets)?,
+            business_id
+        )
+        .fetch_one(&mut *tx)
+        .await;
+        let program: Program = match program {
+            Ok(program) => program.try_into()?,
+            Err(err) => {
+                drop(tx);
+                drop(conn);
+                return Err(self.duplicate_name(err, &new.program_name).await);
+            }
+        };
+
+        if let Some(vens) = vens {
+            sqlx::query!(
+                r#"
+                DELETE FROM ven_program WHERE program_id = $1
+                "#,
+                program.id.as_str()
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            let rows_affected = sqlx::query!(
+                r#"
+                INSERT INTO ven_program (program_id, ven_id)
+                    (SELECT $1, id FROM ven WHERE ven_name = ANY($2))
+                "#,
+                program.id.as_str(),
+                &vens
+            )
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if rows_affected as usize != vens.len() {
+                Err(AppError::BadRequest(
+                    "One or multiple VEN names linked in the program do not exist",
+                ))?
+            }
+        };
         tx.commit().await?;
         Ok(program)
     }
+}
+
+#[async_trait]
+impl Crud for PgProgramStorage {
+    type Type = Program;
+    type Id = ProgramId;
+    type NewType = ProgramContent;
+    type Error = AppError;
+    type Filter = QueryParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.insert(new, user, false).await
+    }
 
     async fn retrieve(
         &self,
@@ -286,6 +534,7 @@ impl Crud for PgProgramStorage {
                    p.binding_events,
                    p.local_price,
                    p.payload_descriptors,
+                   p.default_priority,
                    p.targets
             FROM program p
               LEFT JOIN ven_program vp ON p.id = vp.program_id
@@ -296,17 +545,25 @@ impl Crud for PgProgramStorage {
             user.is_ven(),
             &user.ven_ids_string()
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?)
     }
 
+    #[tracing::instrument(name = "PgProgramStorage::retrieve_all", skip_all)]
     async fn retrieve_all(
         &self,
         filter: &Self::Filter,
         user: &Self::PermissionFilter,
     ) -> Result<Vec<Self::Type>, Self::Error> {
-        let pg_filter: PostgresFilter = filter.into();
+        let pg_filter = PostgresFilter::new(
+            filter,
+            &[
+                TargetLabel::VENName,
+                TargetLabel::ProgramName,
+                TargetLabel::EventName,
+            ],
+        );
         trace!(?pg_filter);
 
         Ok(sqlx::query_as!(
@@ -327,6 +584,7 @@ impl Crud for PgProgramStorage {
                    p.binding_events,
                    p.local_price,
                    p.payload_descriptors,
+                   p.default_priority,
                    p.targets
             FROM program p
               LEFT JOIN event e ON p.id = e.program_id
@@ -334,130 +592,115 @@ impl Crud for PgProgramStorage {
               LEFT JOIN ven v ON v.id = vp.ven_id
               LEFT JOIN LATERAL ( 
                   SELECT p.id as p_id, 
-                         json_array(jsonb_array_elements(p.targets)) <@ $4::jsonb AS target_test )
+                         target @> ANY (SELECT jsonb_array_elements($4::jsonb)) AS target_test
+                  FROM jsonb_array_elements(p.targets) target )
                   ON p.id = p_id
             WHERE ($1::text[] IS NULL OR e.event_name = ANY($1))
               AND ($2::text[] IS NULL OR p.program_name = ANY($2))
               AND ($3::text[] IS NULL OR v.ven_name = ANY($3))
               AND ($4::jsonb = '[]'::jsonb OR target_test)
               AND (NOT $5 OR v.id IS NULL OR v.id = ANY($6)) -- Filter for VEN ids
+              AND interval_period_overlaps(p.interval_period, $9, $10)
+              AND ($13::text IS NULL OR p.program_name ILIKE $13 OR p.program_long_name ILIKE $13
+                   OR EXISTS (SELECT FROM jsonb_array_elements(p.program_descriptions) description
+                              WHERE description ->> 'URL' ILIKE $13))
             GROUP BY p.id
+            ORDER BY
+              -- the `sortBy` in the `sortOrder`, see `Sorting`
+              CASE WHEN $11::text = 'created' AND NOT $12 THEN p.created_date_time END ASC,
+              CASE WHEN $11::text = 'created' AND $12 THEN p.created_date_time END DESC,
+              CASE WHEN $11::text = 'modified' AND NOT $12 THEN p.modification_date_time END ASC,
+              CASE WHEN $11::text = 'modified' AND $12 THEN p.modification_date_time END DESC,
+              CASE WHEN $11::text = 'priority' AND NOT $12 THEN p.default_priority END ASC NULLS LAST,
+              CASE WHEN $11::text = 'priority' AND $12 THEN p.default_priority END DESC NULLS LAST,
+              CASE WHEN $11::text = 'name' AND NOT $12 THEN p.program_name COLLATE "C" END ASC NULLS LAST,
+              CASE WHEN $11::text = 'name' AND $12 THEN p.program_name COLLATE "C" END DESC NULLS LAST,
+              p.created_date_time, p.id
             OFFSET $7 LIMIT $8
             "#,
             pg_filter.event_names,
             pg_filter.program_names,
             pg_filter.ven_names,
-            serde_json::to_value(pg_filter.targets)
-                .map_err(AppError::SerdeJsonInternalServerError)?,
+            pg_filter.targets_json()?,
             user.is_ven(),
             &user.ven_ids_string(),
             pg_filter.skip,
             pg_filter.limit,
+            filter.extension.active.active_after,
+            filter.extension.active.active_before,
+            filter.extension.sort.sort_by.as_ref().map(SortBy::as_str),
+            filter.extension.sort.is_descending(),
+            pg_filter.search,
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
         .collect::<Result<_, _>>()?)
     }
 
-    async fn update(
+    #[tracing::instrument(name = "PgProgramStorage::count", skip_all)]
+    async fn count(
         &self,
-        id: &Self::Id,
-        new: Self::NewType,
+        filter: &Self::Filter,
         user: &Self::PermissionFilter,
-    ) -> Result<Self::Type, Self::Error> {
-        let (targets, vens) = extract_vens(new.targets);
-        let business_id = extract_business_id(user)?;
-
-        let mut tx = self.db.begin().await?;
-
-        let program: Program = sqlx::query_as!(
-            PostgresProgram,
+    ) -> Result<usize, Self::Error> {
+        let pg_filter = PostgresFilter::new(
+            filter,
+            &[
+                TargetLabel::VENName,
+                TargetLabel::ProgramName,
+                TargetLabel::EventName,
+            ],
+        );
+
+        let count = sqlx::query_scalar!(
             r#"
-            UPDATE program p
-            SET modification_date_time = now(),
-                program_name = $2,
-                program_long_name = $3,
-                retailer_name = $4,
-                retailer_long_name = $5,
-                program_type = $6,
-                country = $7,
-                principal_subdivision = $8,
-                interval_period = $9,
-                program_descriptions = $10,
-                binding_events = $11,
-                local_price = $12,
-                payload_descriptors = $13,
-                targets = $14
-            WHERE id = $1
-                AND ($15::text IS NULL OR business_id = $15)
-            RETURNING p.id,
-                   p.created_date_time,
-                   p.modification_date_time,
-                   p.program_name,
-                   p.program_long_name,
-                   p.retailer_name,
-                   p.retailer_long_name,
-                   p.program_type,
-                   p.country,
-                   p.principal_subdivision,
-                   p.interval_period,
-                   p.program_descriptions,
-                   p.binding_events,
-                   p.local_price,
-                   p.payload_descriptors,
-                   p.targets
+            SELECT COUNT(DISTINCT p.id) AS "count!"
+            FROM program p
+              LEFT JOIN event e ON p.id = e.program_id
+              LEFT JOIN ven_program vp ON p.id = vp.program_id
+              LEFT JOIN ven v ON v.id = vp.ven_id
+              LEFT JOIN LATERAL ( 
+                  SELECT p.id as p_id, 
+                         target @> ANY (SELECT jsonb_array_elements($4::jsonb)) AS target_test
+                  FROM jsonb_array_elements(p.targets) target )
+                  ON p.id = p_id
+            WHERE ($1::text[] IS NULL OR e.event_name = ANY($1))
+              AND ($2::text[] IS NULL OR p.program_name = ANY($2))
+              AND ($3::text[] IS NULL OR v.ven_name = ANY($3))
+              AND ($4::jsonb = '[]'::jsonb OR target_test)
+              AND (NOT $5 OR v.id IS NULL OR v.id = ANY($6)) -- Filter for VEN ids
+              AND interval_period_overlaps(p.interval_period, $7, $8)
+              AND ($9::text IS NULL OR p.program_name ILIKE $9 OR p.program_long_name ILIKE $9
+                   OR EXISTS (SELECT FROM jsonb_array_elements(p.program_descriptions) description
+                              WHERE description ->> 'URL' ILIKE $9))
             "#,
-            id.as_str(),
-            new.program_name,
-            new.program_long_name,
-            new.retailer_name,
-            new.retailer_long_name,
-            new.program_type,
-            new.country,
-            new.principal_subdivision,
-            to_json_value(new.interval_period)?,
-            to_json_value(new.program_descriptions)?,
-            new.binding_events,
-            new.local_price,
-            to_json_value(new.payload_descriptors)?,
-            to_json_value(targets)?,
-            business_id
+            pg_filter.event_names,
+            pg_filter.program_names,
+            pg_filter.ven_names,
+            pg_filter.targets_json()?,
+            user.is_ven(),
+            &user.ven_ids_string(),
+            filter.extension.active.active_after,
+            filter.extension.active.active_before,
+            pg_filter.search,
         )
-        .fetch_one(&mut *tx)
-        .await?
-        .try_into()?;
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
 
-        if let Some(vens) = vens {
-            sqlx::query!(
-                r#"
-                DELETE FROM ven_program WHERE program_id = $1
-                "#,
-                program.id.as_str()
-            )
-            .execute(&mut *tx)
-            .await?;
+        Ok(count as usize)
+    }
 
-            let rows_affected = sqlx::query!(
-                r#"
-                INSERT INTO ven_program (program_id, ven_id)
-                    (SELECT $1, id FROM ven WHERE ven_name = ANY($2))
-                "#,
-                program.id.as_str(),
-                &vens
-            )
-            .execute(&mut *tx)
-            .await?
-            .rows_affected();
-            if rows_affected as usize != vens.len() {
-                Err(AppError::BadRequest(
-                    "One or multiple VEN names linked in the program do not exist",
-                ))?
-            }
-        };
-        tx.commit().await?;
-        Ok(program)
+    async fn update(
+        &self,
+        id: &Self::Id,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.db
+            .retry_on_conflict(move || self.try_update(id, new.clone(), user))
+            .await
     }
 
     async fn delete(
@@ -488,12 +731,13 @@ impl Crud for PgProgramStorage {
                    p.binding_events,
                    p.local_price,
                    p.payload_descriptors,
+                   p.default_priority,
                    p.targets
             "#,
             id.as_str(),
             business_id,
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?)
     }
@@ -503,7 +747,7 @@ impl Crud for PgProgramStorage {
 #[cfg(feature = "live-db-test")]
 mod tests {
     use crate::{
-        api::program::QueryParams,
+        api::{program::ProgramListParams, ActiveWindow, ListParams, Sorting},
         data_source::{postgres::program::PgProgramStorage, Crud},
         error::AppError,
         jwt::Claims,
@@ -513,21 +757,10 @@ mod tests {
         interval::IntervalPeriod,
         program::{PayloadDescriptor, ProgramContent, ProgramDescription},
         target::{TargetEntry, TargetLabel, TargetMap},
-        Program,
+        Program, SortBy, SortOrder,
     };
     use sqlx::PgPool;
 
-    impl Default for QueryParams {
-        fn default() -> Self {
-            Self {
-                target_type: None,
-                target_values: None,
-                skip: 0,
-                limit: 50,
-            }
-        }
-    }
-
     fn program_1() -> Program {
         Program {
             id: "program-1".parse().unwrap(),
@@ -554,14 +787,15 @@ mod tests {
                 payload_descriptors: Some(vec![PayloadDescriptor::EventPayloadDescriptor(
                     EventPayloadDescriptor::new(EventType::ExportPrice),
                 )]),
+                default_priority: None,
                 targets: Some(TargetMap(vec![
                     TargetEntry {
                         label: TargetLabel::Group,
-                        values: ["group-1".to_string()],
+                        values: vec!["group-1".to_string()],
                     },
                     TargetEntry {
                         label: TargetLabel::Private("PRIVATE_LABEL".to_string()),
-                        values: ["private value".to_string()],
+                        values: vec!["private value".to_string()],
                     },
                 ])),
             },
@@ -588,6 +822,7 @@ mod tests {
                 binding_events: None,
                 local_price: None,
                 payload_descriptors: None,
+                default_priority: None,
                 targets: None,
             },
         }
@@ -625,7 +860,7 @@ mod tests {
             let repo: PgProgramStorage = db.into();
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         limit: 1,
                         ..Default::default()
                     },
@@ -641,7 +876,7 @@ mod tests {
             let repo: PgProgramStorage = db.into();
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         skip: 1,
                         ..Default::default()
                     },
@@ -653,7 +888,7 @@ mod tests {
 
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         skip: 3,
                         ..Default::default()
                     },
@@ -670,7 +905,7 @@ mod tests {
 
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::Group),
                         target_values: Some(vec!["group-1".to_string()]),
                         ..Default::default()
@@ -683,7 +918,7 @@ mod tests {
 
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::Group),
                         target_values: Some(vec!["not-existent".to_string()]),
                         ..Default::default()
@@ -696,7 +931,7 @@ mod tests {
 
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::ProgramName),
                         target_values: Some(vec!["program-2".to_string()]),
                         ..Default::default()
@@ -710,7 +945,7 @@ mod tests {
 
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::ProgramName),
                         target_values: Some(vec!["program-not-existent".to_string()]),
                         ..Default::default()
@@ -722,13 +957,108 @@ mod tests {
             assert_eq!(programs.len(), 0);
         }
 
+        #[sqlx::test(fixtures("programs"))]
+        async fn filter_active_window(db: PgPool) {
+            let repo: PgProgramStorage = db.into();
+            let user = Claims::any_business_user();
+
+            // program 1 starts at 2024-07-25 08:31 and never ends, the others have no period
+            let before_start = ListParams {
+                extension: ProgramListParams {
+                    active: ActiveWindow {
+                        active_after: None,
+                        active_before: "2024-07-25T00:00:00Z".parse().ok(),
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let mut programs = repo.retrieve_all(&before_start, &user).await.unwrap();
+            programs.sort_by(|a, b| a.id.as_str().cmp(b.id.as_str()));
+            assert_eq!(programs, vec![program_2(), program_3()]);
+            assert_eq!(repo.count(&before_start, &user).await.unwrap(), 2);
+
+            let far_future = ListParams {
+                extension: ProgramListParams {
+                    active: ActiveWindow {
+                        active_after: "2100-01-01T00:00:00Z".parse().ok(),
+                        active_before: None,
+                    },
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            let programs = repo.retrieve_all(&far_future, &user).await.unwrap();
+            assert_eq!(programs.len(), 3);
+        }
+
+        #[sqlx::test(fixtures("programs"))]
+        async fn sort(db: PgPool) {
+            sqlx::query("UPDATE program SET default_priority = 5 WHERE id = 'program-2'")
+                .execute(&db)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE program SET default_priority = 1 WHERE id = 'program-3'")
+                .execute(&db)
+                .await
+                .unwrap();
+            let repo: PgProgramStorage = db.into();
+            let user = Claims::any_business_user();
+
+            let sorted_ids = |sort_by, sort_order| {
+                let repo = &repo;
+                let user = &user;
+                async move {
+                    let filter = ListParams {
+                        extension: ProgramListParams {
+                            sort: Sorting {
+                                sort_by,
+                                sort_order,
+                            },
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    };
+                    repo.retrieve_all(&filter, user)
+                        .await
+                        .unwrap()
+                        .into_iter()
+                        .map(|program| program.id.to_string())
+                        .collect::<Vec<_>>()
+                }
+            };
+
+            // the programs of the fixture were created at the same time, so these keep the default order
+            assert_eq!(
+                sorted_ids(None, None).await,
+                ["program-1", "program-2", "program-3"]
+            );
+            assert_eq!(
+                sorted_ids(Some(SortBy::Created), Some(SortOrder::Desc)).await,
+                ["program-1", "program-2", "program-3"]
+            );
+            assert_eq!(
+                sorted_ids(Some(SortBy::Name), Some(SortOrder::Desc)).await,
+                ["program-3", "program-2", "program-1"]
+            );
+            // programs without a priority come last in either order
+            assert_eq!(
+                sorted_ids(Some(SortBy::Priority), None).await,
+                ["program-3", "program-2", "program-1"]
+            );
+            assert_eq!(
+                sorted_ids(Some(SortBy::Priority), Some(SortOrder::Desc)).await,
+                ["program-2", "program-3", "program-1"]
+            );
+        }
+
         #[sqlx::test(fixtures("programs"))]
         async fn filter_multiple_targets(db: PgPool) {
             let repo: PgProgramStorage = db.into();
 
             let programs = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::Group),
                         target_values: Some(vec!["private value".to_string()]),
                         ..Default::default()
@@ -787,6 +1117,19 @@ mod tests {
             assert!(program.modification_date_time > Utc::now() - Duration::minutes(10));
         }
 
+        #[sqlx::test]
+        async fn add_then_retrieve_identical_timestamps(db: PgPool) {
+            let repo: PgProgramStorage = db.into();
+            let user = Claims::any_business_user();
+
+            let created = repo.create(program_1().content, &user).await.unwrap();
+            let retrieved = repo.retrieve(&created.id, &user).await.unwrap();
+            assert_eq!(
+                serde_json::to_string(&created).unwrap(),
+                serde_json::to_string(&retrieved).unwrap()
+            );
+        }
+
         #[sqlx::test(fixtures("programs"))]
         async fn add_existing_name(db: PgPool) {
             let repo: PgProgramStorage = db.into();
@@ -794,7 +1137,10 @@ mod tests {
             let program = repo
                 .create(program_1().content, &Claims::any_business_user())
                 .await;
-            assert!(matches!(program, Err(AppError::Conflict(_, _))));
+            assert!(matches!(
+                program,
+                Err(AppError::DuplicateName { id, .. }) if id == "program-1"
+            ));
         }
     }
 
@@ -1,7 +1,7 @@
 use crate::{
     api::report::QueryParams,
     data_source::{
-        postgres::{extract_business_ids, to_json_value, PgId},
+        postgres::{extract_business_ids, to_json_value, PgDb, PgId},
         Crud, ReportCrud,
     },
     error::AppError,
@@ -10,20 +10,105 @@ use crate::{
 use axum::async_trait;
 use chrono::{DateTime, Utc};
 use openadr_wire::{
-    report::{ReportContent, ReportId},
-    Report,
+    report::{LatestReportPayload, ReportContent, ReportId, ResourceOperatingState},
+    Report, SortBy,
 };
-use sqlx::PgPool;
+use sqlx::{Connection, PgPool};
 use tracing::{error, info, trace};
 
 #[async_trait]
-impl ReportCrud for PgReportStorage {}
+impl ReportCrud for PgReportStorage {
+    async fn validate_create(&self, new: ReportContent, user: &Claims) -> Result<Report, AppError> {
+        self.insert(new, user, true).await
+    }
+
+    async fn latest_operating_states(
+        &self,
+        filter: &QueryParams,
+        user: &Claims,
+    ) -> Result<Vec<ResourceOperatingState>, AppError> {
+        let business_ids = extract_business_ids(user);
+
+        // the last interval with an operating state of the last modified report of each resource
+        sqlx::query_as!(
+            PostgresOperatingState,
+            r#"
+            SELECT DISTINCT ON (r.client_name, resource ->> 'resourceName')
+                   r.id AS "report_id!",
+                   r.client_name AS "client_name!",
+                   resource ->> 'resourceName' AS "resource_name!",
+                   payload -> 'values' ->> 0 AS "operating_state!",
+                   r.modification_date_time AS "reported_at!"
+            FROM report r
+                JOIN program p ON p.id = r.program_id
+                LEFT JOIN ven_program v ON v.program_id = r.program_id
+                CROSS JOIN LATERAL jsonb_array_elements(r.resources) resource
+                CROSS JOIN LATERAL jsonb_array_elements(resource -> 'intervals') WITH ORDINALITY AS i(interval, position)
+                CROSS JOIN LATERAL jsonb_array_elements(i.interval -> 'payloads') payload
+            WHERE payload ->> 'type' = 'OPERATING_STATE'
+              AND ($1::text IS NULL OR $1 like r.program_id)
+              AND ($2::text IS NULL OR $2 like r.event_id)
+              AND ($3::text IS NULL OR $3 like r.client_name)
+              AND (NOT $4 OR v.ven_id IS NULL OR v.ven_id = ANY($5))
+              AND ($6::text[] IS NULL OR p.business_id = ANY($6))
+            ORDER BY r.client_name, resource ->> 'resourceName', r.modification_date_time DESC, i.position DESC
+            "#,
+            filter.extension.program_id.clone().map(|x| x.to_string()),
+            filter.extension.event_id.clone().map(|x| x.to_string()),
+            filter.extension.client_name,
+            user.is_ven(),
+            &user.ven_ids_string(),
+            business_ids.as_deref(),
+        )
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+
+    async fn latest_payloads(
+        &self,
+        client_name: &str,
+        resource_name: &str,
+    ) -> Result<Vec<LatestReportPayload>, AppError> {
+        // maintained on each change of the reports, see the `latest_report_payload` migration
+        sqlx::query_as!(
+            PostgresLatestPayload,
+            r#"
+            SELECT report_id,
+                   payload_type,
+                   payload_values AS "values",
+                   interval_id,
+                   interval_period,
+                   reported_at
+            FROM latest_report_payload
+            WHERE client_name = $1
+              AND resource_name = $2
+            ORDER BY payload_type
+            "#,
+            client_name,
+            resource_name,
+        )
+        .fetch_all(&mut *self.db.acquire().await?)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+}
 
 pub(crate) struct PgReportStorage {
-    db: PgPool,
+    db: PgDb,
 }
 impl From<PgPool> for PgReportStorage {
     fn from(db: PgPool) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+impl From<PgDb> for PgReportStorage {
+    fn from(db: PgDb) -> Self {
         Self { db }
     }
 }
@@ -78,20 +163,72 @@ impl TryFrom<PostgresReport> for Report {
     }
 }
 
-#[async_trait]
-impl Crud for PgReportStorage {
-    type Type = Report;
-    type Id = ReportId;
-    type NewType = ReportContent;
+#[derive(Debug)]
+struct PostgresOperatingState {
+    report_id: String,
+    client_name: String,
+    resource_name: String,
+    operating_state: String,
+    reported_at: DateTime<Utc>,
+}
+
+impl TryFrom<PostgresOperatingState> for ResourceOperatingState {
     type Error = AppError;
-    type Filter = QueryParams;
-    type PermissionFilter = Claims;
 
-    async fn create(
+    fn try_from(value: PostgresOperatingState) -> Result<Self, Self::Error> {
+        Ok(Self {
+            client_name: value.client_name,
+            resource_name: serde_json::from_value(serde_json::Value::String(value.resource_name))
+                .map_err(AppError::SerdeJsonInternalServerError)?,
+            operating_state: value
+                .operating_state
+                .parse()
+                .unwrap_or_else(|never| match never {}),
+            report_id: value.report_id.parse()?,
+            reported_at: value.reported_at,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct PostgresLatestPayload {
+    report_id: String,
+    payload_type: String,
+    values: serde_json::Value,
+    interval_id: i32,
+    interval_period: Option<serde_json::Value>,
+    reported_at: DateTime<Utc>,
+}
+
+impl TryFrom<PostgresLatestPayload> for LatestReportPayload {
+    type Error = AppError;
+
+    fn try_from(value: PostgresLatestPayload) -> Result<Self, Self::Error> {
+        Ok(Self {
+            payload_type: serde_json::from_value(serde_json::Value::String(value.payload_type))
+                .map_err(AppError::SerdeJsonInternalServerError)?,
+            values: serde_json::from_value(value.values)
+                .map_err(AppError::SerdeJsonInternalServerError)?,
+            report_id: value.report_id.parse()?,
+            interval_id: value.interval_id,
+            interval_period: value
+                .interval_period
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(AppError::SerdeJsonInternalServerError)?,
+            reported_at: value.reported_at,
+        })
+    }
+}
+
+impl PgReportStorage {
+    /// Insert the report in a transaction, which a dry run rolls back
+    async fn insert(
         &self,
-        new: Self::NewType,
-        user: &Self::PermissionFilter,
-    ) -> Result<Self::Type, Self::Error> {
+        new: ReportContent,
+        user: &Claims,
+        dry_run: bool,
+    ) -> Result<Report, AppError> {
         let permitted_vens = sqlx::query_as!(
             PgId,
             r#"
@@ -99,7 +236,7 @@ impl Crud for PgReportStorage {
             "#,
             new.program_id.as_str()
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(|id| id.id)
@@ -121,7 +258,7 @@ impl Crud for PgReportStorage {
             "#,
             new.event_id.as_str(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?;
 
         if program_id.id != new.program_id.as_str() {
@@ -130,6 +267,9 @@ impl Crud for PgReportStorage {
             ));
         }
 
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
+
         let report: Report = sqlx::query_as!(
             PostgresReport,
             r#"
@@ -144,14 +284,35 @@ impl Crud for PgReportStorage {
             to_json_value(new.payload_descriptors)?,
             serde_json::to_value(new.resources).map_err(AppError::SerdeJsonBadRequest)?,
         )
-            .fetch_one(&self.db)
+            .fetch_one(&mut *tx)
             .await?
             .try_into()?;
 
-        info!(report_id = report.id.as_str(), "created report");
+        if !dry_run {
+            tx.commit().await?;
+            info!(report_id = report.id.as_str(), "created report");
+        }
 
         Ok(report)
     }
+}
+
+#[async_trait]
+impl Crud for PgReportStorage {
+    type Type = Report;
+    type Id = ReportId;
+    type NewType = ReportContent;
+    type Error = AppError;
+    type Filter = QueryParams;
+    type PermissionFilter = Claims;
+
+    async fn create(
+        &self,
+        new: Self::NewType,
+        user: &Self::PermissionFilter,
+    ) -> Result<Self::Type, Self::Error> {
+        self.insert(new, user, false).await
+    }
 
     async fn retrieve(
         &self,
@@ -176,7 +337,7 @@ impl Crud for PgReportStorage {
             &user.ven_ids_string(),
             business_ids.as_deref()
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -185,6 +346,7 @@ impl Crud for PgReportStorage {
         Ok(report)
     }
 
+    #[tracing::instrument(name = "PgReportStorage::retrieve_all", skip_all)]
     async fn retrieve_all(
         &self,
         filter: &Self::Filter,
@@ -204,18 +366,29 @@ impl Crud for PgReportStorage {
               AND ($3::text IS NULL OR $3 like r.client_name)
               AND (NOT $4 OR v.ven_id IS NULL OR v.ven_id = ANY($5))
               AND ($6::text[] IS NULL OR p.business_id = ANY($6))
+            ORDER BY
+              -- the `sortBy` in the `sortOrder`, see `Sorting`
+              CASE WHEN $9::text = 'created' AND NOT $10 THEN r.created_date_time END ASC,
+              CASE WHEN $9::text = 'created' AND $10 THEN r.created_date_time END DESC,
+              CASE WHEN $9::text = 'modified' AND NOT $10 THEN r.modification_date_time END ASC,
+              CASE WHEN $9::text = 'modified' AND $10 THEN r.modification_date_time END DESC,
+              CASE WHEN $9::text = 'name' AND NOT $10 THEN r.report_name COLLATE "C" END ASC NULLS LAST,
+              CASE WHEN $9::text = 'name' AND $10 THEN r.report_name COLLATE "C" END DESC NULLS LAST,
+              r.created_date_time, r.id
             LIMIT $7 OFFSET $8
             "#,
-            filter.program_id.clone().map(|x| x.to_string()),
-            filter.event_id.clone().map(|x| x.to_string()),
-            filter.client_name,
+            filter.extension.program_id.clone().map(|x| x.to_string()),
+            filter.extension.event_id.clone().map(|x| x.to_string()),
+            filter.extension.client_name,
             user.is_ven(),
             &user.ven_ids_string(),
             business_ids.as_deref(),
-            filter.skip,
             filter.limit,
+            filter.skip,
+            filter.extension.sort.sort_by.as_ref().map(SortBy::as_str),
+            filter.extension.sort.is_descending(),
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
@@ -226,6 +399,40 @@ impl Crud for PgReportStorage {
         Ok(reports)
     }
 
+    #[tracing::instrument(name = "PgReportStorage::count", skip_all)]
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        user: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        let business_ids = extract_business_ids(user);
+
+        // counts the rows of `retrieve_all`, such that the pages add up to the count
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!"
+            FROM report r
+                JOIN program p ON p.id = r.program_id
+                LEFT JOIN ven_program v ON v.program_id = r.program_id
+            WHERE ($1::text IS NULL OR $1 like r.program_id)
+              AND ($2::text IS NULL OR $2 like r.event_id)
+              AND ($3::text IS NULL OR $3 like r.client_name)
+              AND (NOT $4 OR v.ven_id IS NULL OR v.ven_id = ANY($5))
+              AND ($6::text[] IS NULL OR p.business_id = ANY($6))
+            "#,
+            filter.extension.program_id.clone().map(|x| x.to_string()),
+            filter.extension.event_id.clone().map(|x| x.to_string()),
+            filter.extension.client_name,
+            user.is_ven(),
+            &user.ven_ids_string(),
+            business_ids.as_deref(),
+        )
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+
+        Ok(count as usize)
+    }
+
     async fn update(
         &self,
         id: &Self::Id,
@@ -263,7 +470,7 @@ impl Crud for PgReportStorage {
             to_json_value(new.payload_descriptors)?,
             serde_json::to_value(new.resources).map_err(AppError::SerdeJsonBadRequest)?,
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -292,7 +499,7 @@ impl Crud for PgReportStorage {
             id.as_str(),
             business_ids.as_deref(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -1,7 +1,7 @@
 use crate::{
-    api::resource::QueryParams,
+    api::ListParams,
     data_source::{
-        postgres::{to_json_value, PgTargetsFilter},
+        postgres::{filter::PostgresFilter, to_json_value, PgDb},
         ResourceCrud, VenScopedCrud,
     },
     error::AppError,
@@ -21,11 +21,17 @@ use tracing::{error, trace};
 impl ResourceCrud for PgResourceStorage {}
 
 pub(crate) struct PgResourceStorage {
-    db: PgPool,
+    db: PgDb,
 }
 
 impl From<PgPool> for PgResourceStorage {
     fn from(db: PgPool) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+impl From<PgDb> for PgResourceStorage {
+    fn from(db: PgDb) -> Self {
         Self { db }
     }
 }
@@ -81,51 +87,13 @@ impl TryFrom<PostgresResource> for Resource {
     }
 }
 
-#[derive(Debug, Default)]
-struct PostgresFilter<'a> {
-    resource_names: Option<&'a [String]>,
-    targets: Vec<PgTargetsFilter<'a>>,
-    skip: i64,
-    limit: i64,
-}
-
-impl<'a> From<&'a QueryParams> for PostgresFilter<'a> {
-    fn from(query: &'a QueryParams) -> Self {
-        let mut filter = Self {
-            skip: query.skip,
-            limit: query.limit,
-            ..Default::default()
-        };
-        match query.target_type {
-            Some(TargetLabel::VENName) => filter.resource_names = query.target_values.as_deref(),
-            Some(TargetLabel::ResourceName) => {
-                filter.resource_names = query.target_values.as_deref()
-            }
-            Some(ref label) => {
-                if let Some(values) = query.target_values.as_ref() {
-                    filter.targets = values
-                        .iter()
-                        .map(|value| PgTargetsFilter {
-                            label: label.as_str(),
-                            value: [value.clone()],
-                        })
-                        .collect()
-                }
-            }
-            None => {}
-        };
-
-        filter
-    }
-}
-
 #[async_trait]
 impl VenScopedCrud for PgResourceStorage {
     type Type = Resource;
     type Id = ResourceId;
     type NewType = ResourceContent;
     type Error = AppError;
-    type Filter = QueryParams;
+    type Filter = ListParams;
     type PermissionFilter = Claims;
 
     async fn create(
@@ -154,7 +122,7 @@ impl VenScopedCrud for PgResourceStorage {
             to_json_value(new.attributes)?,
             to_json_value(new.targets)?,
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -184,26 +152,28 @@ impl VenScopedCrud for PgResourceStorage {
             id.as_str(),
             ven_id.as_str(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
         Ok(resource)
     }
 
+    #[tracing::instrument(name = "PgResourceStorage::retrieve_all", skip_all)]
     async fn retrieve_all(
         &self,
         ven_id: VenId,
         filter: &Self::Filter,
         _user: &Self::PermissionFilter,
     ) -> Result<Vec<Self::Type>, Self::Error> {
-        let pg_filter: PostgresFilter = filter.into();
+        let pg_filter =
+            PostgresFilter::new(filter, &[TargetLabel::VENName, TargetLabel::ResourceName]);
         trace!(?pg_filter);
 
         let res = sqlx::query_as!(
             PostgresResource,
             r#"
-            SELECT
+            SELECT DISTINCT
                 r.id AS "id!", 
                 r.created_date_time AS "created_date_time!", 
                 r.modification_date_time AS "modification_date_time!",
@@ -212,23 +182,27 @@ impl VenScopedCrud for PgResourceStorage {
                 r.attributes,
                 r.targets
             FROM resource r
+              JOIN ven v ON v.id = r.ven_id
               LEFT JOIN LATERAL ( 
                   SELECT r.id as r_id, 
-                         json_array(jsonb_array_elements(r.targets)) <@ $3::jsonb AS target_test )
+                         target @> ANY (SELECT jsonb_array_elements($3::jsonb)) AS target_test
+                  FROM jsonb_array_elements(r.targets) target )
                   ON r.id = r_id
             WHERE r.ven_id = $1
                 AND ($2::text[] IS NULL OR r.resource_name = ANY($2))
                 AND ($3::jsonb = '[]'::jsonb OR target_test)
-            OFFSET $4 LIMIT $5
+                AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
+            ORDER BY r.created_date_time DESC
+            OFFSET $5 LIMIT $6
             "#,
             ven_id.as_str(),
             pg_filter.resource_names,
-            serde_json::to_value(pg_filter.targets)
-                .map_err(AppError::SerdeJsonInternalServerError)?,
+            pg_filter.targets_json()?,
+            pg_filter.ven_names,
             pg_filter.skip,
             pg_filter.limit,
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
@@ -243,6 +217,42 @@ impl VenScopedCrud for PgResourceStorage {
         Ok(res)
     }
 
+    #[tracing::instrument(name = "PgResourceStorage::count", skip_all)]
+    async fn count(
+        &self,
+        ven_id: VenId,
+        filter: &Self::Filter,
+        _user: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        let pg_filter =
+            PostgresFilter::new(filter, &[TargetLabel::VENName, TargetLabel::ResourceName]);
+
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT r.id) AS "count!"
+            FROM resource r
+              JOIN ven v ON v.id = r.ven_id
+              LEFT JOIN LATERAL ( 
+                  SELECT r.id as r_id, 
+                         target @> ANY (SELECT jsonb_array_elements($3::jsonb)) AS target_test
+                  FROM jsonb_array_elements(r.targets) target )
+                  ON r.id = r_id
+            WHERE r.ven_id = $1
+                AND ($2::text[] IS NULL OR r.resource_name = ANY($2))
+                AND ($3::jsonb = '[]'::jsonb OR target_test)
+                AND ($4::text[] IS NULL OR v.ven_name = ANY($4))
+            "#,
+            ven_id.as_str(),
+            pg_filter.resource_names,
+            pg_filter.targets_json()?,
+            pg_filter.ven_names,
+        )
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+
+        Ok(count as usize)
+    }
+
     async fn update(
         &self,
         id: &Self::Id,
@@ -269,7 +279,7 @@ impl VenScopedCrud for PgResourceStorage {
             to_json_value(new.attributes)?,
             to_json_value(new.targets)?
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -292,7 +302,7 @@ impl VenScopedCrud for PgResourceStorage {
             id.as_str(),
             ven_id.as_str(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?)
     }
@@ -300,7 +310,7 @@ impl VenScopedCrud for PgResourceStorage {
 
 impl PgResourceStorage {
     pub(crate) async fn retrieve_by_ven(
-        db: &PgPool,
+        db: &PgDb,
         ven_id: &VenId,
     ) -> Result<Vec<Resource>, AppError> {
         sqlx::query_as!(
@@ -319,7 +329,7 @@ impl PgResourceStorage {
             "#,
             ven_id.as_str(),
         )
-        .fetch_all(db)
+        .fetch_all(&mut *db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
@@ -327,7 +337,7 @@ impl PgResourceStorage {
     }
 
     pub(crate) async fn retrieve_by_vens(
-        db: &PgPool,
+        db: &PgDb,
         ven_ids: &[String],
     ) -> Result<Vec<Resource>, AppError> {
         sqlx::query_as!(
@@ -346,7 +356,7 @@ impl PgResourceStorage {
             "#,
             ven_ids,
         )
-        .fetch_all(db)
+        .fetch_all(&mut *db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
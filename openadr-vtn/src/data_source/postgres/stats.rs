@@ -0,0 +1,127 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    data_source::postgres::PgDb,
+    error::AppError,
+    stats::{ObjectCounts, ProgramEvents, ReportVolume, Stats, StatsSource},
+};
+
+pub(crate) struct PgStatsSource {
+    db: PgDb,
+}
+
+impl From<PgDb> for PgStatsSource {
+    fn from(db: PgDb) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl StatsSource for PgStatsSource {
+    async fn stats(&self, since: DateTime<Utc>) -> Result<Stats, AppError> {
+        let mut conn = self.db.acquire().await?;
+
+        let counts = sqlx::query!(
+            r#"
+            SELECT (SELECT count(*) FROM program)             AS "programs!",
+                   (SELECT count(*) FROM event)               AS "events!",
+                   (SELECT count(*) FROM report)              AS "reports!",
+                   (SELECT count(*) FROM ven)                 AS "vens!",
+                   (SELECT count(*) FROM resource)            AS "resources!",
+                   (SELECT count(*) FROM "user")              AS "users!",
+                   pg_database_size(current_database())       AS "database_size!"
+            "#
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let events_per_program = sqlx::query_as!(
+            ProgramEvents,
+            r#"
+            SELECT p.id           AS program_id,
+                   p.program_name,
+                   count(e.id)    AS "events!"
+            FROM program p
+                LEFT JOIN event e ON e.program_id = p.id
+            GROUP BY p.id
+            ORDER BY p.program_name
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let report_volume = sqlx::query_as!(
+            ReportVolume,
+            r#"
+            SELECT date_trunc('day', created_date_time, 'UTC') AS "day!",
+                   count(*)                                     AS "reports!"
+            FROM report
+            WHERE created_date_time >= $1
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+            since,
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(Stats {
+            objects: ObjectCounts {
+                programs: counts.programs,
+                events: counts.events,
+                reports: counts.reports,
+                vens: counts.vens,
+                resources: counts.resources,
+                users: counts.users,
+            },
+            events_per_program,
+            report_volume,
+            database_size: Some(counts.database_size),
+        })
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "live-db-test")]
+mod tests {
+    use super::*;
+    use sqlx::PgPool;
+
+    #[sqlx::test(fixtures("users", "programs", "events", "vens", "resources", "reports"))]
+    async fn stats(db: PgPool) {
+        let stats: PgStatsSource = PgDb::from(db).into();
+        let stats = stats
+            .stats("2024-07-01T00:00:00Z".parse().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            stats.objects,
+            ObjectCounts {
+                programs: 3,
+                events: 3,
+                reports: 2,
+                vens: 2,
+                resources: 5,
+                users: 2,
+            }
+        );
+        assert_eq!(
+            stats
+                .events_per_program
+                .iter()
+                .map(|program| (program.program_id.as_str(), program.events))
+                .collect::<Vec<_>>(),
+            [("program-1", 1), ("program-2", 1), ("program-3", 1)]
+        );
+        assert_eq!(
+            stats.report_volume,
+            [ReportVolume {
+                day: "2024-07-25T00:00:00Z".parse().unwrap(),
+                reports: 2,
+            }]
+        );
+        assert!(stats.database_size.unwrap() > 0);
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    data_source::{postgres::PgId, AuthInfo, AuthSource, UserDetails},
+    data_source::{
+        postgres::{PgDb, PgId},
+        AuthInfo, AuthSource, UserDetails,
+    },
     error::AppError,
     jwt::AuthRole,
 };
@@ -9,15 +12,21 @@ use argon2::{
 };
 use axum::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{PgConnection, PgPool};
+use sqlx::{Connection, PgConnection, PgPool};
 use tracing::warn;
 
 pub struct PgAuthSource {
-    db: PgPool,
+    db: PgDb,
 }
 
 impl From<PgPool> for PgAuthSource {
     fn from(db: PgPool) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+impl From<PgDb> for PgAuthSource {
+    fn from(db: PgDb) -> Self {
         Self { db }
     }
 }
@@ -92,8 +101,13 @@ struct IdAndSecret {
 #[async_trait]
 impl AuthSource for PgAuthSource {
     async fn check_credentials(&self, client_id: &str, client_secret: &str) -> Option<AuthInfo> {
-        let mut tx = self
+        let mut conn = self
             .db
+            .acquire()
+            .await
+            .inspect_err(|err| warn!(client_id, "failed to acquire connection: {err}"))
+            .ok()?;
+        let mut tx = conn
             .begin()
             .await
             .inspect_err(|err| warn!(client_id, "failed to open transaction: {err}"))
@@ -133,8 +147,44 @@ impl AuthSource for PgAuthSource {
         })
     }
 
+    async fn find_client(&self, client_id: &str) -> Option<AuthInfo> {
+        let mut conn = self
+            .db
+            .acquire()
+            .await
+            .inspect_err(|err| warn!(client_id, "failed to acquire connection: {err}"))
+            .ok()?;
+        let mut tx = conn
+            .begin()
+            .await
+            .inspect_err(|err| warn!(client_id, "failed to open transaction: {err}"))
+            .ok()?;
+
+        let user_id = sqlx::query_scalar!(
+            r#"
+            SELECT user_id FROM user_credentials WHERE client_id = $1
+            "#,
+            client_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .inspect_err(|err| warn!(client_id, "error fetching credentials: {err}"))
+        .ok()??;
+
+        let user = Self::get_user(&mut tx, &user_id)
+            .await
+            .inspect_err(|err| warn!(client_id, "error fetching user: {err}"))
+            .ok()?;
+
+        Some(AuthInfo {
+            client_id: client_id.to_string(),
+            roles: user.roles,
+        })
+    }
+
     async fn get_user(&self, user_id: &str) -> Result<UserDetails, AppError> {
-        let mut tx = self.db.begin().await?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
         Self::get_user(&mut tx, user_id).await
     }
 
@@ -164,7 +214,7 @@ impl AuthSource for PgAuthSource {
                      vm.user_id
             "#,
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
@@ -177,7 +227,8 @@ impl AuthSource for PgAuthSource {
         description: Option<&str>,
         roles: &[AuthRole],
     ) -> Result<UserDetails, AppError> {
-        let mut tx = self.db.begin().await?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
 
         let user = sqlx::query_as!(
             PgId,
@@ -224,7 +275,20 @@ impl AuthSource for PgAuthSource {
             .hash_password(client_secret.as_bytes(), &salt)?
             .to_string();
 
-        let mut tx = self.db.begin().await?;
+        self.add_hashed_credential(user_id, client_id, &hash).await
+    }
+
+    async fn add_hashed_credential(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        client_secret_hash: &str,
+    ) -> Result<UserDetails, AppError> {
+        // reject hashes `check_credentials` cannot parse
+        PasswordHash::new(client_secret_hash)?;
+
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
 
         sqlx::query!(
             r#"
@@ -235,7 +299,7 @@ impl AuthSource for PgAuthSource {
             "#,
             user_id,
             client_id,
-            &hash
+            client_secret_hash
         )
         .execute(&mut *tx)
         .await?;
@@ -250,7 +314,8 @@ impl AuthSource for PgAuthSource {
         user_id: &str,
         client_id: &str,
     ) -> Result<UserDetails, AppError> {
-        let mut tx = self.db.begin().await?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
         sqlx::query!(
             r#"
             DELETE FROM user_credentials WHERE user_id = $1 AND client_id = $2
@@ -266,7 +331,8 @@ impl AuthSource for PgAuthSource {
     }
 
     async fn remove_user(&self, user_id: &str) -> Result<UserDetails, AppError> {
-        let mut tx = self.db.begin().await?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
         let user = Self::get_user(&mut tx, user_id).await?;
         sqlx::query!(
             r#"
@@ -288,7 +354,8 @@ impl AuthSource for PgAuthSource {
         description: Option<&str>,
         roles: &[AuthRole],
     ) -> Result<UserDetails, AppError> {
-        let mut tx = self.db.begin().await?;
+        let mut conn = self.db.acquire().await?;
+        let mut tx = conn.begin().await?;
 
         sqlx::query!(
             r#"
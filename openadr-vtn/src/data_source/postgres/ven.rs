@@ -1,7 +1,7 @@
 use crate::{
-    api::ven::QueryParams,
+    api::ListParams,
     data_source::{
-        postgres::{to_json_value, PgTargetsFilter},
+        postgres::{filter::PostgresFilter, to_json_value, PgDb},
         Crud, VenCrud, VenPermissions,
     },
     error::AppError,
@@ -21,11 +21,17 @@ use super::resource::PgResourceStorage;
 impl VenCrud for PgVenStorage {}
 
 pub(crate) struct PgVenStorage {
-    db: PgPool,
+    db: PgDb,
 }
 
 impl From<PgPool> for PgVenStorage {
     fn from(db: PgPool) -> Self {
+        Self { db: db.into() }
+    }
+}
+
+impl From<PgDb> for PgVenStorage {
+    fn from(db: PgDb) -> Self {
         Self { db }
     }
 }
@@ -80,52 +86,13 @@ impl TryFrom<PostgresVen> for Ven {
     }
 }
 
-#[derive(Debug, Default)]
-struct PostgresFilter<'a> {
-    ven_names: Option<&'a [String]>,
-    resource_names: Option<&'a [String]>,
-    targets: Vec<PgTargetsFilter<'a>>,
-    skip: i64,
-    limit: i64,
-}
-
-impl<'a> From<&'a QueryParams> for PostgresFilter<'a> {
-    fn from(query: &'a QueryParams) -> Self {
-        let mut filter = Self {
-            skip: query.skip,
-            limit: query.limit,
-            ..Default::default()
-        };
-        match query.target_type {
-            Some(TargetLabel::VENName) => filter.ven_names = query.target_values.as_deref(),
-            Some(TargetLabel::ResourceName) => {
-                filter.resource_names = query.target_values.as_deref()
-            }
-            Some(ref label) => {
-                if let Some(values) = query.target_values.as_ref() {
-                    filter.targets = values
-                        .iter()
-                        .map(|value| PgTargetsFilter {
-                            label: label.as_str(),
-                            value: [value.clone()],
-                        })
-                        .collect()
-                }
-            }
-            None => {}
-        };
-
-        filter
-    }
-}
-
 #[async_trait]
 impl Crud for PgVenStorage {
     type Type = Ven;
     type Id = VenId;
     type NewType = VenContent;
     type Error = AppError;
-    type Filter = QueryParams;
+    type Filter = ListParams;
     type PermissionFilter = VenPermissions;
 
     async fn create(
@@ -151,7 +118,7 @@ impl Crud for PgVenStorage {
             to_json_value(new.attributes)?,
             to_json_value(new.targets)?,
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -178,7 +145,7 @@ impl Crud for PgVenStorage {
             id.as_str(),
             ids.as_deref(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -188,12 +155,14 @@ impl Crud for PgVenStorage {
         Ok(ven)
     }
 
+    #[tracing::instrument(name = "PgVenStorage::retrieve_all", skip_all)]
     async fn retrieve_all(
         &self,
         filter: &Self::Filter,
         permissions: &Self::PermissionFilter,
     ) -> Result<Vec<Self::Type>, Self::Error> {
-        let pg_filter: PostgresFilter = filter.into();
+        let pg_filter =
+            PostgresFilter::new(filter, &[TargetLabel::VENName, TargetLabel::ResourceName]);
         trace!(?pg_filter);
 
         let ids = permissions.as_value();
@@ -212,24 +181,34 @@ impl Crud for PgVenStorage {
               LEFT JOIN resource r ON r.ven_id = v.id
               LEFT JOIN LATERAL (
                   SELECT v.id as v_id, 
-                         json_array(jsonb_array_elements(v.targets)) <@ $3::jsonb AS target_test )
+                         target @> ANY (SELECT jsonb_array_elements($3::jsonb)) AS target_test
+                  FROM jsonb_array_elements(v.targets) target )
                   ON v.id = v_id
             WHERE ($1::text[] IS NULL OR v.ven_name = ANY($1))
               AND ($2::text[] IS NULL OR r.resource_name = ANY($2))
               AND ($3::jsonb = '[]'::jsonb OR target_test)
               AND ($4::text[] IS NULL OR v.id = ANY($4))
+              AND ($7::text IS NULL OR v.ven_name ILIKE $7 OR r.resource_name ILIKE $7
+                   OR EXISTS (SELECT FROM jsonb_array_elements(v.attributes) attribute,
+                                          jsonb_array_elements(attribute -> 'values') description
+                              WHERE attribute ->> 'type' = 'DESCRIPTION'
+                                AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $7)
+                   OR EXISTS (SELECT FROM jsonb_array_elements(r.attributes) attribute,
+                                          jsonb_array_elements(attribute -> 'values') description
+                              WHERE attribute ->> 'type' = 'DESCRIPTION'
+                                AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $7))
             ORDER BY v.created_date_time DESC
             OFFSET $5 LIMIT $6
             "#,
             pg_filter.ven_names,
             pg_filter.resource_names,
-            serde_json::to_value(pg_filter.targets)
-                .map_err(AppError::SerdeJsonInternalServerError)?,
+            pg_filter.targets_json()?,
             ids.as_deref(),
             pg_filter.skip,
             pg_filter.limit,
+            pg_filter.search,
         )
-        .fetch_all(&self.db)
+        .fetch_all(&mut *self.db.acquire().await?)
         .await?
         .into_iter()
         .map(TryInto::try_into)
@@ -255,6 +234,53 @@ impl Crud for PgVenStorage {
         Ok(vens)
     }
 
+    #[tracing::instrument(name = "PgVenStorage::count", skip_all)]
+    async fn count(
+        &self,
+        filter: &Self::Filter,
+        permissions: &Self::PermissionFilter,
+    ) -> Result<usize, Self::Error> {
+        let pg_filter =
+            PostgresFilter::new(filter, &[TargetLabel::VENName, TargetLabel::ResourceName]);
+
+        let ids = permissions.as_value();
+
+        let count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(DISTINCT v.id) AS "count!"
+            FROM ven v
+              LEFT JOIN resource r ON r.ven_id = v.id
+              LEFT JOIN LATERAL (
+                  SELECT v.id as v_id, 
+                         target @> ANY (SELECT jsonb_array_elements($3::jsonb)) AS target_test
+                  FROM jsonb_array_elements(v.targets) target )
+                  ON v.id = v_id
+            WHERE ($1::text[] IS NULL OR v.ven_name = ANY($1))
+              AND ($2::text[] IS NULL OR r.resource_name = ANY($2))
+              AND ($3::jsonb = '[]'::jsonb OR target_test)
+              AND ($4::text[] IS NULL OR v.id = ANY($4))
+              AND ($5::text IS NULL OR v.ven_name ILIKE $5 OR r.resource_name ILIKE $5
+                   OR EXISTS (SELECT FROM jsonb_array_elements(v.attributes) attribute,
+                                          jsonb_array_elements(attribute -> 'values') description
+                              WHERE attribute ->> 'type' = 'DESCRIPTION'
+                                AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $5)
+                   OR EXISTS (SELECT FROM jsonb_array_elements(r.attributes) attribute,
+                                          jsonb_array_elements(attribute -> 'values') description
+                              WHERE attribute ->> 'type' = 'DESCRIPTION'
+                                AND jsonb_typeof(description) = 'string' AND description #>> '{}' ILIKE $5))
+            "#,
+            pg_filter.ven_names,
+            pg_filter.resource_names,
+            pg_filter.targets_json()?,
+            ids.as_deref(),
+            pg_filter.search,
+        )
+        .fetch_one(&mut *self.db.acquire().await?)
+        .await?;
+
+        Ok(count as usize)
+    }
+
     async fn update(
         &self,
         id: &Self::Id,
@@ -277,7 +303,7 @@ impl Crud for PgVenStorage {
             to_json_value(new.attributes)?,
             to_json_value(new.targets)?
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -310,7 +336,7 @@ impl Crud for PgVenStorage {
             "#,
             id.as_str(),
         )
-        .fetch_one(&self.db)
+        .fetch_one(&mut *self.db.acquire().await?)
         .await?
         .try_into()?;
 
@@ -325,7 +351,7 @@ impl Crud for PgVenStorage {
 #[cfg(feature = "live-db-test")]
 mod tests {
     use crate::{
-        api::ven::QueryParams,
+        api::ListParams,
         data_source::{postgres::ven::PgVenStorage, Crud},
         error::AppError,
     };
@@ -335,17 +361,6 @@ mod tests {
     };
     use sqlx::PgPool;
 
-    impl Default for QueryParams {
-        fn default() -> Self {
-            Self {
-                target_type: None,
-                target_values: None,
-                skip: 0,
-                limit: 50,
-            }
-        }
-    }
-
     fn ven_1() -> Ven {
         Ven {
             id: "ven-1".parse().unwrap(),
@@ -408,7 +423,7 @@ mod tests {
             let repo: PgVenStorage = db.into();
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         limit: 1,
                         ..Default::default()
                     },
@@ -424,7 +439,7 @@ mod tests {
             let repo: PgVenStorage = db.into();
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         skip: 1,
                         ..Default::default()
                     },
@@ -436,7 +451,7 @@ mod tests {
 
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         skip: 2,
                         ..Default::default()
                     },
@@ -453,7 +468,7 @@ mod tests {
 
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::Group),
                         target_values: Some(vec!["group-1".to_string()]),
                         ..Default::default()
@@ -466,7 +481,7 @@ mod tests {
 
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::Group),
                         target_values: Some(vec!["not-existent".to_string()]),
                         ..Default::default()
@@ -479,7 +494,7 @@ mod tests {
 
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::VENName),
                         target_values: Some(vec!["ven-2-name".to_string()]),
                         ..Default::default()
@@ -493,7 +508,7 @@ mod tests {
 
             let vens = repo
                 .retrieve_all(
-                    &QueryParams {
+                    &ListParams {
                         target_type: Some(TargetLabel::VENName),
                         target_values: Some(vec!["ven-not-existent".to_string()]),
                         ..Default::default()
@@ -1,15 +1,18 @@
+use crate::jwt::KeyRotationError;
+use crate::metrics::metrics;
 use argon2::password_hash;
 use axum::{
     extract::rejection::{FormRejection, JsonRejection},
-    http::StatusCode,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use axum_extra::extract::QueryRejection;
-use openadr_wire::{problem::Problem, IdentifierError};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use openadr_wire::{oauth::Scope, problem::Problem, IdentifierError};
 #[cfg(feature = "sqlx")]
-use sqlx::error::DatabaseError;
+use sqlx::error::{DatabaseError, ErrorKind};
+use std::any::Any;
 use tracing::{error, info, trace, warn};
 use uuid::Uuid;
 
@@ -34,11 +37,28 @@ pub enum AppError {
     #[cfg(feature = "sqlx")]
     #[error("Conflict: {0}")]
     Conflict(String, Option<Box<dyn DatabaseError>>),
+    #[error("Conflict: a {object} with this name already exists")]
+    DuplicateName {
+        object: &'static str,
+        /// The id of the existing object with the name
+        id: String,
+        modification_date_time: DateTime<Utc>,
+    },
     #[cfg(feature = "sqlx")]
     #[error("Unprocessable Content: {0}")]
     ForeignKeyConstraintViolated(String, Option<Box<dyn DatabaseError>>),
+    #[cfg(feature = "sqlx")]
+    #[error("Serialization failure: {0}")]
+    SerializationFailure(Box<dyn DatabaseError>),
+    /// The request has no bearer token. Requests with a token lacking the required role
+    /// or ownership fail with [`AppError::Forbidden`] instead
     #[error("Authentication error: {0}")]
     Auth(String),
+    #[error("Invalid authentication token")]
+    InvalidToken,
+    /// The token is valid, but was not granted the scope the request requires
+    #[error("The token lacks the {0} scope")]
+    InsufficientScope(Scope),
     #[cfg(feature = "sqlx")]
     #[error("Database error: {0}")]
     Sql(sqlx::Error),
@@ -57,27 +77,150 @@ pub enum AppError {
     PasswordHashError(password_hash::Error),
     #[error("Unsupported Media Type: {0}")]
     UnsupportedMediaType(String),
+    #[error("Not acceptable, the response is one of: {0}")]
+    NotAcceptable(String),
+    #[error("Payload too large, the limit is {0} bytes")]
+    PayloadTooLarge(usize),
+    #[error("Request timeout, the body was not received within {0:?}")]
+    RequestTimeout(std::time::Duration),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(&'static str, u64),
+    #[error("A report must not contain more than {0} intervals")]
+    TooManyIntervals(usize),
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(&'static str, u64),
+    #[error("Handler panicked: {0}")]
+    Panic(String),
+    #[error("Could not sign event: {0}")]
+    EventSigning(jsonwebtoken::errors::Error),
+    #[error("Key rotation error: {0}")]
+    KeyRotation(#[from] KeyRotationError),
+    #[error("Target label not allowed: {0}")]
+    TargetLabelNotAllowed(String),
 }
 
+/// The seconds after which clients may retry a request that failed on a concurrent transaction
+#[cfg(feature = "sqlx")]
+const SERIALIZATION_FAILURE_RETRY_AFTER: u64 = 1;
+
 #[cfg(feature = "sqlx")]
 impl From<sqlx::Error> for AppError {
     fn from(err: sqlx::Error) -> Self {
         match err {
             sqlx::Error::RowNotFound => Self::NotFound,
-            sqlx::Error::Database(err) if err.is_unique_violation() => {
-                Self::Conflict("Conflict".to_string(), Some(err))
+            sqlx::Error::Database(err) => err.into(),
+            _ => Self::Sql(err),
+        }
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl From<Box<dyn DatabaseError>> for AppError {
+    fn from(err: Box<dyn DatabaseError>) -> Self {
+        // serialization_failure and deadlock_detected, which succeed when retried
+        if matches!(err.code().as_deref(), Some("40001" | "40P01")) {
+            return Self::SerializationFailure(err);
+        }
+
+        let violation = match err.kind() {
+            ErrorKind::ForeignKeyViolation if err.message().starts_with("update or delete") => {
+                Violation::Delete
+            }
+            _ => Violation::Write,
+        };
+        let message = err
+            .constraint()
+            .and_then(|constraint| constraint_message(constraint, violation));
+
+        match (err.kind(), violation) {
+            (ErrorKind::UniqueViolation, _) => {
+                Self::Conflict(message.unwrap_or("Conflict").to_string(), Some(err))
             }
-            sqlx::Error::Database(err) if err.is_foreign_key_violation() => {
+            (ErrorKind::ForeignKeyViolation, Violation::Write) => {
                 Self::ForeignKeyConstraintViolated(
-                    "A foreign key constraint is violated".to_string(),
+                    message
+                        .unwrap_or("A foreign key constraint is violated")
+                        .to_string(),
                     Some(err),
                 )
             }
-            _ => Self::Sql(err),
+            (ErrorKind::ForeignKeyViolation, Violation::Delete) => Self::Conflict(
+                message
+                    .unwrap_or("The object is still referenced by other objects")
+                    .to_string(),
+                Some(err),
+            ),
+            _ => Self::Sql(sqlx::Error::Database(err)),
         }
     }
 }
 
+/// Whether a constraint is violated by the object written,
+/// or by deleting an object other objects still refer to
+#[cfg(feature = "sqlx")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Violation {
+    Write,
+    Delete,
+}
+
+/// What the violation of a constraint of the database schema means for clients.
+///
+/// Constraints that clients cannot violate, e.g., the primary keys of generated ids,
+/// are listed to tell them apart from constraints added to the schema without a message.
+#[cfg(feature = "sqlx")]
+pub(crate) fn constraint_message(constraint: &str, violation: Violation) -> Option<&'static str> {
+    use Violation::*;
+
+    Some(match (constraint, violation) {
+        ("business_pk", _) => "A business with this id already exists",
+        (
+            "program_pk" | "event_pk" | "event_archive_pkey" | "report_pk" | "ven_pk"
+            | "resource_pk",
+            _,
+        ) => "An object with this id already exists",
+        ("user_pkey", _) => "A user with this id already exists",
+        ("object_changes_pk", _) => "The change is already recorded",
+        ("latest_report_payload_pkey", _) => "The payload is already recorded",
+        ("program_program_name_uindex", _) => "A program with this name already exists",
+        ("report_report_name_uindex", _) => "A report with this name already exists",
+        ("ven_ven_name_uindex", _) => "A VEN with this name already exists",
+        ("resource_resource_name_key", _) => "A resource with this name already exists",
+        ("user_credentials_pkey", _) => "Credentials with this client id already exist",
+        ("ven_program_pk", _) => "The VEN is already linked to the program",
+        ("uindex_user_business", _) => "The user already belongs to this business",
+        ("ven_manager_pkey" | "user_manager_pkey" | "any_business_user_pkey", _) => {
+            "The user already has this role"
+        }
+        ("program_business_id_fkey", Write) => "The business of the program does not exist",
+        ("program_business_id_fkey", Delete) => "The business still has programs",
+        ("event_program_id_fkey", Write) => "The program of the event does not exist",
+        ("event_program_id_fkey", Delete) => "The program still has events",
+        ("event_archive_program_id_fkey", Write) => "The program of the event does not exist",
+        ("event_archive_program_id_fkey", Delete) => "The program still has archived events",
+        ("report_program_id_fkey", Write) => "The program of the report does not exist",
+        ("report_program_id_fkey", Delete) => "The program still has reports",
+        ("report_event_id_fkey", Write) => "The event of the report does not exist",
+        ("report_event_id_fkey", Delete) => "The event still has reports",
+        ("resource_ven_id_fkey", Write) => "The VEN of the resource does not exist",
+        ("resource_ven_id_fkey", Delete) => "The VEN still has resources",
+        ("ven_program_program_id_fkey", _) => "The program does not exist",
+        ("ven_program_ven_id_fkey", _) => "The VEN does not exist",
+        ("user_ven_ven_id_fkey", _) => "The VEN does not exist",
+        ("user_business_business_id_fkey", _) => "The business does not exist",
+        (
+            "user_credentials_user_id_fkey"
+            | "user_ven_user_id_fkey"
+            | "user_business_user_id_fkey"
+            | "ven_manager_user_id_fkey"
+            | "user_manager_user_id_fkey"
+            | "any_business_user_user_id_fkey",
+            _,
+        ) => "The user does not exist",
+        _ => return None,
+    })
+}
+
 impl From<JsonRejection> for AppError {
     fn from(rejection: JsonRejection) -> Self {
         match rejection {
@@ -116,12 +259,26 @@ impl AppError {
                     "Received invalid request: {}",
                     err
                 );
+                // the fields that failed validation, such that clients can tell which one to fix,
+                // e.g., a `limit` exceeding the page size of the VTN
+                let mut fields: Vec<_> = err.field_errors().into_keys().collect();
+                fields.sort();
+                let invalid_params = fields
+                    .into_iter()
+                    .map(|name| serde_json::json!({ "name": name }))
+                    .collect();
+                let mut extensions = serde_json::Map::new();
+                extensions.insert(
+                    "invalidParams".to_string(),
+                    serde_json::Value::Array(invalid_params),
+                );
                 Problem {
                     r#type: Default::default(),
                     title: Some(StatusCode::BAD_REQUEST.to_string()),
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions,
                 }
             }
             AppError::Json(err) => {
@@ -135,6 +292,7 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::Form(err) => {
@@ -148,6 +306,7 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::QueryParams(err) => {
@@ -161,6 +320,7 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::NotFound => {
@@ -171,6 +331,7 @@ impl AppError {
                     status: StatusCode::NOT_FOUND,
                     detail: None,
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::BadRequest(err) => {
@@ -184,6 +345,7 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::Forbidden(err) => {
@@ -197,6 +359,7 @@ impl AppError {
                     status: StatusCode::FORBIDDEN,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::NotImplemented(err) => {
@@ -207,6 +370,7 @@ impl AppError {
                     status: StatusCode::NOT_IMPLEMENTED,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             #[cfg(feature = "sqlx")]
@@ -218,6 +382,28 @@ impl AppError {
                     status: StatusCode::CONFLICT,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::DuplicateName {
+                object,
+                id,
+                modification_date_time,
+            } => {
+                trace!(%reference, "Conflict: a {} with this name already exists: {}", object, id);
+                let mut extensions = serde_json::Map::new();
+                extensions.insert("conflictingId".to_string(), id.into());
+                extensions.insert(
+                    "conflictingModificationDateTime".to_string(),
+                    modification_date_time.to_rfc3339().into(),
+                );
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::CONFLICT.to_string()),
+                    status: StatusCode::CONFLICT,
+                    detail: Some(format!("A {object} with this name already exists")),
+                    instance: Some(reference.to_string()),
+                    extensions,
                 }
             }
             AppError::Auth(err) => {
@@ -231,6 +417,29 @@ impl AppError {
                     status: StatusCode::UNAUTHORIZED,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::InsufficientScope(scope) => {
+                trace!(%reference, %scope, "Token lacks the required scope");
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::FORBIDDEN.to_string()),
+                    status: StatusCode::FORBIDDEN,
+                    detail: Some(format!("The token lacks the {scope} scope")),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::InvalidToken => {
+                trace!(%reference, "Invalid authentication token");
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::UNAUTHORIZED.to_string()),
+                    status: StatusCode::UNAUTHORIZED,
+                    detail: Some("Invalid authentication token provided".to_string()),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             #[cfg(feature = "sqlx")]
@@ -242,6 +451,7 @@ impl AppError {
                     status: StatusCode::INTERNAL_SERVER_ERROR,
                     detail: Some("A database error occurred".to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             #[cfg(feature = "sqlx")]
@@ -253,6 +463,7 @@ impl AppError {
                     status: StatusCode::INTERNAL_SERVER_ERROR,
                     detail: None,
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             #[cfg(feature = "sqlx")]
@@ -264,6 +475,7 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::Identifier(err) => {
@@ -277,6 +489,7 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             #[cfg(feature = "sqlx")]
@@ -292,6 +505,22 @@ impl AppError {
                     status: StatusCode::BAD_REQUEST,
                     detail: Some(err.to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            #[cfg(feature = "sqlx")]
+            AppError::SerializationFailure(err) => {
+                info!(%reference, "Serialization failure: {}", err);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::SERVICE_UNAVAILABLE.to_string()),
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    detail: Some(
+                        "The request conflicted with a concurrent request, please retry"
+                            .to_string(),
+                    ),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::MethodNotAllowed => {
@@ -304,6 +533,7 @@ impl AppError {
                     status: StatusCode::METHOD_NOT_ALLOWED,
                     detail: Some("See allow headers for allowed methods".to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::PasswordHashError(err) => {
@@ -316,6 +546,7 @@ impl AppError {
                     status: StatusCode::INTERNAL_SERVER_ERROR,
                     detail: Some("An internal error occurred".to_string()),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
             AppError::UnsupportedMediaType(err) => {
@@ -326,6 +557,130 @@ impl AppError {
                     status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
                     detail: Some(err),
                     instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::NotAcceptable(produced) => {
+                info!(%reference, "Not acceptable: {}", produced);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::NOT_ACCEPTABLE.to_string()),
+                    status: StatusCode::NOT_ACCEPTABLE,
+                    detail: Some(format!(
+                        "The Accept header must allow one of the media types: {produced}"
+                    )),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::PayloadTooLarge(limit) => {
+                info!(%reference, "Request body exceeds the limit of {} bytes", limit);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::PAYLOAD_TOO_LARGE.to_string()),
+                    status: StatusCode::PAYLOAD_TOO_LARGE,
+                    detail: Some(format!("The request body must not exceed {limit} bytes")),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::RequestTimeout(timeout) => {
+                info!(%reference, "Request body not received within {:?}", timeout);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::REQUEST_TIMEOUT.to_string()),
+                    status: StatusCode::REQUEST_TIMEOUT,
+                    detail: Some(format!(
+                        "The request body must be received within {} seconds",
+                        timeout.as_secs()
+                    )),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::TooManyRequests(err, _) => {
+                info!(%reference, "Too many requests: {}", err);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::TOO_MANY_REQUESTS.to_string()),
+                    status: StatusCode::TOO_MANY_REQUESTS,
+                    detail: Some(err.to_string()),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::TooManyIntervals(limit) => {
+                info!(%reference, "Report exceeds the limit of {} intervals", limit);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::BAD_REQUEST.to_string()),
+                    status: StatusCode::BAD_REQUEST,
+                    detail: Some(format!(
+                        "A report must not contain more than {limit} intervals"
+                    )),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::ServiceUnavailable(err, _) => {
+                trace!(%reference, "Service unavailable: {}", err);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::SERVICE_UNAVAILABLE.to_string()),
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    detail: Some(err.to_string()),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::EventSigning(err) => {
+                error!(%reference, "Could not sign event: {}", err);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::INTERNAL_SERVER_ERROR.to_string()),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    detail: Some("An internal error occurred".to_string()),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::KeyRotation(err) => {
+                info!(%reference, "Key rotation error: {}", err);
+                let status = match err {
+                    KeyRotationError::UnknownKey(_) => StatusCode::NOT_FOUND,
+                    KeyRotationError::DuplicateKey(_)
+                    | KeyRotationError::ActiveKey(_)
+                    | KeyRotationError::VerifyOnly(_) => StatusCode::CONFLICT,
+                };
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(status.to_string()),
+                    status,
+                    detail: Some(err.to_string()),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::TargetLabelNotAllowed(label) => {
+                trace!(%reference, "Received request with disallowed target label: {}", label);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::BAD_REQUEST.to_string()),
+                    status: StatusCode::BAD_REQUEST,
+                    detail: Some(format!("Target label '{label}' is not allowed by this VTN")),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
+                }
+            }
+            AppError::Panic(err) => {
+                error!(%reference, "Request handler panicked: {}", err);
+                Problem {
+                    r#type: Default::default(),
+                    title: Some(StatusCode::INTERNAL_SERVER_ERROR.to_string()),
+                    status: StatusCode::INTERNAL_SERVER_ERROR,
+                    detail: Some("An internal error occurred".to_string()),
+                    instance: Some(reference.to_string()),
+                    extensions: Default::default(),
                 }
             }
         }
@@ -334,16 +689,55 @@ impl AppError {
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let retry_after = match self {
+            AppError::TooManyRequests(_, retry_after)
+            | AppError::ServiceUnavailable(_, retry_after) => Some(retry_after),
+            #[cfg(feature = "sqlx")]
+            AppError::SerializationFailure(_) => Some(SERIALIZATION_FAILURE_RETRY_AFTER),
+            _ => None,
+        };
+
+        // the challenge of RFC 6750, such that clients know to (re)authenticate
+        let www_authenticate = match &self {
+            AppError::Auth(_) => Some(r#"Bearer realm="VTN""#.to_string()),
+            AppError::InvalidToken => {
+                Some(r#"Bearer realm="VTN", error="invalid_token""#.to_string())
+            }
+            AppError::InsufficientScope(scope) => Some(format!(
+                r#"Bearer realm="VTN", error="insufficient_scope", scope="{scope}""#
+            )),
+            _ => None,
+        };
+
         let problem = self.into_problem();
-        (problem.status, Json(problem)).into_response()
+        let mut response = (problem.status, Json(problem)).into_response();
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        if let Some(www_authenticate) =
+            www_authenticate.and_then(|value| HeaderValue::try_from(value).ok())
+        {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, www_authenticate);
+        }
+        response
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
-pub struct ProblemUri(String);
+/// Used by the `CatchPanicLayer` to turn a panicking handler into a regular problem response
+pub(crate) fn handle_panic(err: Box<dyn Any + Send + 'static>) -> Response {
+    metrics().record_handler_panic();
 
-impl Default for ProblemUri {
-    fn default() -> Self {
-        Self("about:blank".to_string())
-    }
+    let message = if let Some(message) = err.downcast_ref::<String>() {
+        message.clone()
+    } else if let Some(message) = err.downcast_ref::<&str>() {
+        message.to_string()
+    } else {
+        "unknown panic payload".to_string()
+    };
+
+    AppError::Panic(message).into_response()
 }
@@ -0,0 +1,68 @@
+//! Marks events as completed once their last interval ended,
+//! and moves them to an archive after a while, to keep the queries on the active events fast.
+//!
+//! Archived events are left out of `GET /events`, unless requested with `includeArchived=true`.
+//! They can still be retrieved and deleted by their id, and an update moves them back to the active events.
+
+use std::{sync::Arc, time::Duration};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tracing::{error, info};
+
+use crate::error::AppError;
+
+/// How often the archiver looks for completed events
+const ARCHIVE_INTERVAL: Duration = Duration::from_secs(600);
+
+#[async_trait]
+pub trait EventArchive: Send + Sync + 'static {
+    /// Mark the events whose last interval ended before `now` as completed,
+    /// returning the number of newly completed events.
+    ///
+    /// Events that never end, because an interval has no duration, are never completed.
+    async fn complete(&self, now: DateTime<Utc>) -> Result<u64, AppError>;
+
+    /// Move the events completed before the given time to the archive,
+    /// returning the number of archived events.
+    ///
+    /// Events with reports are not archived, as the reports refer to them.
+    async fn archive(&self, completed_before: DateTime<Utc>) -> Result<u64, AppError>;
+}
+
+/// Periodically mark the completed events, and archive them `archive_after` they completed, if set
+pub fn spawn_archiver(
+    archive: Arc<dyn EventArchive>,
+    archive_after: Option<Duration>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(ARCHIVE_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            let now = Utc::now();
+
+            match archive.complete(now).await {
+                Ok(0) => {}
+                Ok(completed) => info!(completed, "marked events as completed"),
+                Err(err) => error!(?err, "could not mark completed events"),
+            }
+
+            // a period too long to represent keeps all events
+            let Some(before) = archive_after
+                .and_then(|period| chrono::Duration::from_std(period).ok())
+                .and_then(|period| now.checked_sub_signed(period))
+            else {
+                continue;
+            };
+
+            match archive.archive(before).await {
+                Ok(0) => {}
+                Ok(archived) => info!(archived, "archived completed events"),
+                Err(err) => error!(?err, "could not archive completed events"),
+            }
+        }
+    })
+}
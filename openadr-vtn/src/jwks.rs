@@ -0,0 +1,437 @@
+//! Validates bearer tokens issued by an external identity provider, e.g., an OAuth authorization server,
+//! with the keys the provider publishes at its JWKS URL.
+//!
+//! The VTN then accepts the tokens of the provider next to the tokens of its own `/auth/token` endpoint,
+//! see [`JwtManager::with_external_tokens`](crate::jwt::JwtManager::with_external_tokens).
+//! The [`AuthRole`]s of a client are derived from the claims of its token with a [`ClaimRoles`].
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use jsonwebtoken::{
+    jwk::{AlgorithmParameters, EllipticCurve, Jwk, JwkSet, PublicKeyUse},
+    Algorithm, DecodingKey, Validation,
+};
+use serde_json::Value;
+use tokio::{sync::Mutex, time::Instant};
+use tracing::{info, trace, warn};
+use url::Url;
+
+use crate::{
+    data_source::directory::GroupMapping,
+    jwt::{AuthRole, Claims},
+};
+
+/// How long the keys are used before they are fetched again
+const KEYS_TTL: Duration = Duration::from_secs(3600);
+
+/// Tokens signed with an unknown key cause the keys to be fetched again, at most this often
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwksError {
+    #[error("could not fetch the JWKS: {0}")]
+    Fetch(#[from] reqwest::Error),
+    #[error("the token has no kid header")]
+    MissingKeyId,
+    #[error("no key with id {0} in the JWKS")]
+    UnknownKey(String),
+    #[error("invalid token: {0}")]
+    Token(#[from] jsonwebtoken::errors::Error),
+    #[error("the {0} claim is not a string")]
+    InvalidClaim(&'static str),
+    #[error("the token of {0} grants no roles")]
+    NoRoles(String),
+}
+
+/// Derives the [`AuthRole`]s of a client from the claims of its token.
+///
+/// The values of the groups claim, `groups` by default, are mapped to roles with a [`GroupMapping`].
+/// Optionally, the values of a VEN id claim grant the `VEN` role for those VENs,
+/// and the values of a business id claim grant the `Business` role for those businesses.
+/// Claims are either a string or a list of strings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClaimRoles {
+    groups_claim: String,
+    groups: GroupMapping,
+    ven_id_claim: Option<String>,
+    business_id_claim: Option<String>,
+}
+
+impl ClaimRoles {
+    pub fn new(groups: GroupMapping) -> Self {
+        Self {
+            groups_claim: "groups".to_string(),
+            groups,
+            ven_id_claim: None,
+            business_id_claim: None,
+        }
+    }
+
+    /// Read the groups from another claim than `groups`, e.g., `roles`
+    pub fn with_groups_claim(mut self, claim: impl Into<String>) -> Self {
+        self.groups_claim = claim.into();
+        self
+    }
+
+    pub fn with_ven_id_claim(mut self, claim: impl Into<String>) -> Self {
+        self.ven_id_claim = Some(claim.into());
+        self
+    }
+
+    pub fn with_business_id_claim(mut self, claim: impl Into<String>) -> Self {
+        self.business_id_claim = Some(claim.into());
+        self
+    }
+
+    /// The roles granted by the claims, without duplicates
+    pub fn roles(&self, claims: &BTreeMap<String, Value>) -> Vec<AuthRole> {
+        let mut roles = self.groups.roles(&strings(claims, &self.groups_claim));
+
+        let ven_ids = self
+            .ven_id_claim
+            .iter()
+            .flat_map(|claim| strings(claims, claim))
+            .filter_map(|id| {
+                id.parse()
+                    .inspect_err(|err| warn!(id, "ignoring invalid VEN id in token: {err}"))
+                    .ok()
+            })
+            .map(AuthRole::VEN);
+        let business_ids = self
+            .business_id_claim
+            .iter()
+            .flat_map(|claim| strings(claims, claim))
+            .map(AuthRole::Business);
+
+        for role in ven_ids.chain(business_ids) {
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        }
+
+        roles
+    }
+}
+
+fn strings(claims: &BTreeMap<String, Value>, claim: &str) -> Vec<String> {
+    match claims.get(claim) {
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(ToString::to_string))
+            .collect(),
+        Some(Value::String(value)) => vec![value.clone()],
+        _ => vec![],
+    }
+}
+
+/// Validates the tokens of an identity provider with the keys published at its JWKS URL.
+///
+/// The tokens must have a `kid` header, be issued by the configured issuer for the configured audience,
+/// and grant at least one role. The keys are cached for an hour, and fetched again earlier
+/// if a token is signed with a key that is not known yet, e.g., after the provider rotated its keys.
+/// Symmetric keys are ignored, as anyone who can read the JWKS could sign tokens with them.
+pub struct JwksValidator {
+    client: reqwest::Client,
+    url: Url,
+    issuer: String,
+    audience: String,
+    roles: ClaimRoles,
+    keys: Mutex<CachedKeys>,
+}
+
+#[derive(Default)]
+struct CachedKeys {
+    keys: HashMap<String, ProviderKey>,
+    fetched_at: Option<Instant>,
+    /// The last attempt to fetch the keys, also if it failed
+    attempted_at: Option<Instant>,
+}
+
+#[derive(Clone)]
+struct ProviderKey {
+    algorithm: Algorithm,
+    decoding_key: DecodingKey,
+}
+
+impl JwksValidator {
+    pub fn new(url: Url, issuer: &str, audience: &str, roles: ClaimRoles) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .build()
+            .expect("the TLS backend can be initialized");
+
+        Self {
+            client,
+            url,
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            roles,
+            keys: Default::default(),
+        }
+    }
+
+    /// Validate a token of the provider, returning the claims with the roles it grants
+    pub async fn validate(&self, token: &str) -> Result<Claims, JwksError> {
+        let header = jsonwebtoken::decode_header(token)?;
+        let kid = header.kid.ok_or(JwksError::MissingKeyId)?;
+        let key = self.key(&kid).await?;
+
+        // the algorithm of the key, not of the token header, to prevent algorithm confusion
+        let mut validation = Validation::new(key.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims =
+            jsonwebtoken::decode::<BTreeMap<String, Value>>(token, &key.decoding_key, &validation)?
+                .claims;
+
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .ok_or(JwksError::InvalidClaim("sub"))?;
+        let roles = self.roles.roles(&claims);
+        if roles.is_empty() {
+            return Err(JwksError::NoRoles(subject.to_string()));
+        }
+
+        let timestamp =
+            |claim: &str| claims.get(claim).and_then(Value::as_u64).unwrap_or(0) as usize;
+        trace!(subject, ?roles, "validated token of the identity provider");
+
+        Ok(Claims::external(
+            subject.to_string(),
+            roles,
+            timestamp("exp"),
+            timestamp("nbf"),
+        ))
+    }
+
+    async fn key(&self, kid: &str) -> Result<ProviderKey, JwksError> {
+        let mut cache = self.keys.lock().await;
+
+        let stale = cache
+            .fetched_at
+            .map_or(true, |fetched_at| fetched_at.elapsed() > KEYS_TTL);
+        let may_refresh = cache.attempted_at.map_or(true, |attempted_at| {
+            attempted_at.elapsed() > MIN_REFRESH_INTERVAL
+        });
+
+        if (stale || !cache.keys.contains_key(kid)) && may_refresh {
+            cache.attempted_at = Some(Instant::now());
+            match self.fetch().await {
+                Ok(keys) => {
+                    info!(url = %self.url, keys = keys.len(), "fetched the JWKS");
+                    cache.keys = keys;
+                    cache.fetched_at = cache.attempted_at;
+                }
+                // keep using the previous keys, the provider may be unavailable for a moment
+                Err(err) if !cache.keys.is_empty() => {
+                    warn!(url = %self.url, %err, "could not refresh the JWKS")
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        cache
+            .keys
+            .get(kid)
+            .cloned()
+            .ok_or_else(|| JwksError::UnknownKey(kid.to_string()))
+    }
+
+    async fn fetch(&self) -> Result<HashMap<String, ProviderKey>, JwksError> {
+        let jwks: JwkSet = self
+            .client
+            .get(self.url.clone())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(jwks
+            .keys
+            .iter()
+            .filter_map(|jwk| {
+                let kid = jwk.common.key_id.clone()?;
+                let Some(algorithm) = signing_algorithm(jwk) else {
+                    trace!(kid, "ignoring JWK that does not validate signatures");
+                    return None;
+                };
+                let decoding_key = DecodingKey::from_jwk(jwk)
+                    .inspect_err(|err| warn!(kid, "ignoring invalid JWK: {err}"))
+                    .ok()?;
+
+                Some((
+                    kid,
+                    ProviderKey {
+                        algorithm,
+                        decoding_key,
+                    },
+                ))
+            })
+            .collect())
+    }
+}
+
+/// The algorithm to validate signatures with, if the key is an asymmetric signing key
+fn signing_algorithm(jwk: &Jwk) -> Option<Algorithm> {
+    if matches!(jwk.common.public_key_use, Some(PublicKeyUse::Encryption)) {
+        return None;
+    }
+
+    let algorithm = match (&jwk.common.key_algorithm, &jwk.algorithm) {
+        (_, AlgorithmParameters::OctetKey(_)) => return None,
+        (Some(algorithm), _) => algorithm.to_string().parse().ok()?,
+        // the algorithm is optional, so derive it from the type of the key
+        (None, AlgorithmParameters::RSA(_)) => Algorithm::RS256,
+        (None, AlgorithmParameters::EllipticCurve(params)) => match params.curve {
+            EllipticCurve::P256 => Algorithm::ES256,
+            EllipticCurve::P384 => Algorithm::ES384,
+            _ => return None,
+        },
+        (None, AlgorithmParameters::OctetKeyPair(_)) => Algorithm::EdDSA,
+    };
+
+    (!matches!(
+        algorithm,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ))
+    .then_some(algorithm)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use axum::{routing::get, Json, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use openadr_wire::ven::VenId;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    const PRIVATE_KEY: &str = include_str!("../test-keys/es256.pem");
+
+    /// Serve the JWKS with the public key of the test key pair, counting the requests
+    async fn serve_jwks() -> (Url, Arc<AtomicUsize>) {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let jwks = serde_json::json!({"keys": [
+            {
+                "kty": "EC",
+                "crv": "P-256",
+                "kid": "idp-1",
+                "use": "sig",
+                "x": "FV5bndvuyrEIkqhamxBD4lSd32QMCsPVekKfD9T9iug",
+                "y": "7j9OMFi3h9wuDFitbG1YRSj5mQF_XJfMJl4gP1KZmMc",
+            },
+            // symmetric keys are ignored
+            {"kty": "oct", "kid": "shared", "alg": "HS256", "k": "c2VjcmV0"},
+        ]});
+
+        let counter = fetches.clone();
+        let app = Router::new().route(
+            "/jwks.json",
+            get(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::future::ready(Json(jwks.clone()))
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/jwks.json", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        (url.parse().unwrap(), fetches)
+    }
+
+    fn token(kid: &str, audience: &str, extra: Value) -> String {
+        let mut claims = serde_json::json!({
+            "iss": "https://idp.example.com",
+            "aud": audience,
+            "sub": "operator",
+            "exp": chrono::Utc::now().timestamp() + 60,
+        });
+        claims
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+
+        let header = Header {
+            kid: Some(kid.to_string()),
+            ..Header::new(Algorithm::ES256)
+        };
+        let key = EncodingKey::from_ec_pem(PRIVATE_KEY.as_bytes()).unwrap();
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    #[tokio::test]
+    async fn validates_provider_tokens() {
+        let (url, fetches) = serve_jwks().await;
+        let roles = ClaimRoles::new("grid-operators=AnyBusiness".parse().unwrap())
+            .with_ven_id_claim("ven_id");
+        let validator = JwksValidator::new(url, "https://idp.example.com", "vtn", roles);
+
+        let claims = validator
+            .validate(&token(
+                "idp-1",
+                "vtn",
+                serde_json::json!({"groups": ["grid-operators", "unmapped"], "ven_id": "ven-1"}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(claims.sub, "operator");
+        assert_eq!(
+            claims.roles,
+            vec![
+                AuthRole::AnyBusiness,
+                AuthRole::VEN(VenId::new("ven-1").unwrap())
+            ]
+        );
+
+        let err = validator
+            .validate(&token(
+                "idp-1",
+                "other",
+                serde_json::json!({"groups": "grid-operators"}),
+            ))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, JwksError::Token(_)), "{err}");
+
+        let err = validator
+            .validate(&token(
+                "idp-1",
+                "vtn",
+                serde_json::json!({"groups": ["guests"]}),
+            ))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, JwksError::NoRoles(_)), "{err}");
+
+        // the keys are cached
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // unknown keys are fetched again, but not for every token
+        for kid in ["idp-2", "idp-2", "shared"] {
+            let err = validator
+                .validate(&token(
+                    kid,
+                    "vtn",
+                    serde_json::json!({"groups": "grid-operators"}),
+                ))
+                .await
+                .unwrap_err();
+            assert!(matches!(err, JwksError::UnknownKey(_)), "{err}");
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    }
+}
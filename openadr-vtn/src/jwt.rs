@@ -1,4 +1,7 @@
-use std::sync::Arc;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
 
 use axum::{
     async_trait,
@@ -9,17 +12,165 @@ use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
-use jsonwebtoken::{encode, DecodingKey, EncodingKey, Header};
-use openadr_wire::ven::VenId;
-use tracing::trace;
+use jsonwebtoken::{encode, Algorithm, DecodingKey, EncodingKey, Header};
+use openadr_wire::{auth::WhoAmI, oauth::Scope, ven::VenId};
+use tracing::{trace, Span};
 
-use crate::error::AppError;
+use crate::{
+    error::AppError,
+    jwks::{JwksError, JwksValidator},
+};
 
+/// The id of the key a [`JwtManager`] is created with.
+/// Tokens without a `kid` header, e.g., created before keys were rotated, are validated with this key.
+pub const INITIAL_KEY_ID: &str = "initial";
+
+/// Creates and validates the tokens of the VTN.
+///
+/// To rotate keys without invalidating the outstanding tokens, [add](Self::add_key) a new key,
+/// [activate](Self::activate_key) it such that new tokens are signed with it,
+/// and [retire](Self::retire_key) the old key once all tokens signed with it expired.
+/// Tokens carry the id of the key they are signed with in their `kid` header.
+///
+/// Keys are either shared secrets (HS256), or key pairs (e.g., RS256 or ES256),
+/// such that other services can validate the tokens with the public key only.
+///
+/// Optionally, tokens of an external identity provider are accepted as well,
+/// see [`with_external_tokens`](Self::with_external_tokens).
 pub struct JwtManager {
-    encoding_key: EncodingKey,
+    keys: RwLock<KeyRing>,
+    external: Option<JwksValidator>,
+    /// The lifetime of refresh tokens, which are only issued if set
+    refresh_tokens: Option<std::time::Duration>,
+}
+
+struct KeyRing {
+    active: String,
+    keys: BTreeMap<String, SigningKey>,
+}
+
+/// A key to sign and validate tokens with, see [`JwtManager::add_key`]
+#[derive(Clone)]
+pub struct SigningKey {
+    algorithm: Algorithm,
+    /// `None` for keys that can only validate tokens
+    encoding_key: Option<EncodingKey>,
     decoding_key: DecodingKey,
 }
 
+impl SigningKey {
+    /// A shared secret, signing with HS256
+    pub fn from_secret(secret: &[u8]) -> Self {
+        Self {
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(EncodingKey::from_secret(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+        }
+    }
+
+    /// A base64 encoded shared secret, signing with HS256
+    pub fn from_base64_secret(secret: &str) -> Result<Self, jsonwebtoken::errors::Error> {
+        Ok(Self {
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(EncodingKey::from_base64_secret(secret)?),
+            decoding_key: DecodingKey::from_base64_secret(secret)?,
+        })
+    }
+
+    /// A PEM encoded private and public key.
+    /// The type of key is determined by the algorithm, which cannot be an HMAC algorithm.
+    pub fn from_pem(
+        algorithm: Algorithm,
+        private_pem: &[u8],
+        public_pem: &[u8],
+    ) -> Result<Self, jsonwebtoken::errors::Error> {
+        let encoding_key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => EncodingKey::from_rsa_pem(private_pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(private_pem)?,
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(private_pem)?,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                return Err(jsonwebtoken::errors::ErrorKind::InvalidAlgorithm.into())
+            }
+        };
+
+        Ok(Self {
+            encoding_key: Some(encoding_key),
+            ..Self::verify_only(algorithm, public_pem)?
+        })
+    }
+
+    /// A PEM encoded public key, which only validates tokens, e.g., signed by another instance of the VTN
+    pub fn verify_only(
+        algorithm: Algorithm,
+        public_pem: &[u8],
+    ) -> Result<Self, jsonwebtoken::errors::Error> {
+        let decoding_key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => DecodingKey::from_rsa_pem(public_pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(public_pem)?,
+            Algorithm::EdDSA => DecodingKey::from_ed_pem(public_pem)?,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                return Err(jsonwebtoken::errors::ErrorKind::InvalidAlgorithm.into())
+            }
+        };
+
+        Ok(Self {
+            algorithm,
+            encoding_key: None,
+            decoding_key,
+        })
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Whether tokens can be signed with this key, or only validated
+    pub fn can_sign(&self) -> bool {
+        self.encoding_key.is_some()
+    }
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum KeyRotationError {
+    #[error("No key with id {0}")]
+    UnknownKey(String),
+    #[error("A key with id {0} already exists")]
+    DuplicateKey(String),
+    #[error("The active key {0} cannot be retired")]
+    ActiveKey(String),
+    #[error("The key {0} can only validate tokens")]
+    VerifyOnly(String),
+}
+
+/// Why a token was rejected, see [`JwtManager::validate`]
+#[derive(thiserror::Error, Debug)]
+pub enum TokenError {
+    #[error(transparent)]
+    Invalid(#[from] jsonwebtoken::errors::Error),
+    #[error("token of the identity provider rejected: {0}")]
+    External(#[from] JwksError),
+}
+
+/// The ids of the keys of a [`JwtManager`], see [`JwtManager::key_ids`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyIds {
+    /// The key new tokens are signed with
+    pub active: String,
+    /// All keys tokens are validated with, including the active key
+    pub keys: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[cfg_attr(test, derive(PartialOrd, Ord))]
 #[serde(tag = "role", content = "id")]
@@ -47,14 +198,106 @@ impl AuthRole {
     pub fn is_ven_manager(&self) -> bool {
         matches!(self, AuthRole::VenManager)
     }
+
+    /// The scopes a token of a client with this role can be granted.
+    /// Every role can read, such that the admin UI works with any role.
+    pub fn scopes(&self) -> &'static [Scope] {
+        match self {
+            AuthRole::UserManager => &[Scope::ReadAll, Scope::Admin],
+            AuthRole::VenManager => &[Scope::ReadAll, Scope::WriteVens],
+            AuthRole::Business(_) | AuthRole::AnyBusiness => &[
+                Scope::ReadAll,
+                Scope::WritePrograms,
+                Scope::WriteEvents,
+                Scope::WriteReports,
+            ],
+            AuthRole::VEN(_) => &[Scope::ReadAll, Scope::WriteReports, Scope::WriteVens],
+        }
+    }
+
+    /// The name of the role, as serialized in the `role` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuthRole::UserManager => "UserManager",
+            AuthRole::VenManager => "VenManager",
+            AuthRole::Business(_) => "Business",
+            AuthRole::AnyBusiness => "AnyBusiness",
+            AuthRole::VEN(_) => "VEN",
+        }
+    }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize)]
+/// The scopes granted by any of the roles, without duplicates
+pub fn role_scopes(roles: &[AuthRole]) -> Vec<Scope> {
+    Scope::ALL
+        .into_iter()
+        .filter(|scope| roles.iter().any(|role| role.scopes().contains(scope)))
+        .collect()
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Claims {
     exp: usize,
     nbf: usize,
     pub sub: String,
     pub roles: Vec<AuthRole>,
+    /// The scopes of the token. Tokens without a `scope` claim, e.g., of an external identity
+    /// provider or issued before scopes were introduced, have all scopes of their roles.
+    #[serde(
+        rename = "scope",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "scope_claim"
+    )]
+    scopes: Option<Vec<Scope>>,
+}
+
+/// The `scope` claim is a space-separated list, like the `scope` parameter of RFC 6749
+mod scope_claim {
+    use openadr_wire::oauth::Scope;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        scopes: &Option<Vec<Scope>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match scopes {
+            Some(scopes) => serializer.serialize_str(&Scope::format_list(scopes)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<Scope>>, D::Error> {
+        let Some(list) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        Scope::parse_list(&list)
+            .map(Some)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// The claims of a refresh token, see [`JwtManager::create_refresh_token`].
+///
+/// Unlike [`Claims`], these have no roles but a `token_use`,
+/// such that neither kind of token deserializes as the other.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct RefreshClaims {
+    exp: usize,
+    nbf: usize,
+    sub: String,
+    token_use: TokenUse,
+    /// The scopes of the access tokens issued for the refresh token
+    #[serde(rename = "scope", with = "scope_claim")]
+    scopes: Option<Vec<Scope>>,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum TokenUse {
+    Refresh,
 }
 
 #[cfg(test)]
@@ -66,6 +309,7 @@ impl Claims {
             nbf: 0,
             sub: "".to_string(),
             roles,
+            scopes: None,
         }
     }
 
@@ -81,6 +325,17 @@ pub enum BusinessIds {
 }
 
 impl Claims {
+    /// The claims of a token of an external identity provider, see [`JwksValidator`]
+    pub(crate) fn external(sub: String, roles: Vec<AuthRole>, exp: usize, nbf: usize) -> Self {
+        Self {
+            exp,
+            nbf,
+            sub,
+            roles,
+            scopes: None,
+        }
+    }
+
     pub fn ven_ids(&self) -> Vec<VenId> {
         self.roles
             .iter()
@@ -136,37 +391,192 @@ impl Claims {
     pub fn is_ven_manager(&self) -> bool {
         self.roles.iter().any(AuthRole::is_ven_manager)
     }
+
+    /// The scopes of the token, see [`Scope`]
+    pub fn scopes(&self) -> Vec<Scope> {
+        match &self.scopes {
+            Some(scopes) => scopes.clone(),
+            None => role_scopes(&self.roles),
+        }
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.contains(&scope),
+            None => self.roles.iter().any(|role| role.scopes().contains(&scope)),
+        }
+    }
+
+    /// Reject the request if the token does not have the `scope`,
+    /// in addition to the checks of the roles of the client
+    pub fn require_scope(&self, scope: Scope) -> Result<(), AppError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(AppError::InsufficientScope(scope))
+        }
+    }
+
+    /// When the token expires
+    pub fn expires_at(&self) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::from_timestamp(self.exp as i64, 0).unwrap_or_default()
+    }
+
+    /// The claims as described to the client in `GET /auth/whoami`
+    pub fn who_am_i(&self) -> WhoAmI {
+        let (any_business, business_ids) = match self.business_ids() {
+            BusinessIds::Any => (true, vec![]),
+            BusinessIds::Specific(ids) => (false, ids),
+        };
+
+        WhoAmI {
+            client_id: self.sub.clone(),
+            roles: self
+                .roles
+                .iter()
+                .map(|role| role.name().to_string())
+                .collect(),
+            ven_ids: self.ven_ids(),
+            any_business,
+            business_ids,
+            scopes: self.scopes(),
+            expires_at: self.expires_at(),
+        }
+    }
 }
 
 impl JwtManager {
     /// Create a new JWT manager from a base64 encoded secret
     pub fn from_base64_secret(secret: &str) -> Result<Self, jsonwebtoken::errors::Error> {
-        let encoding_key = EncodingKey::from_base64_secret(secret)?;
-        let decoding_key = DecodingKey::from_base64_secret(secret)?;
-        Ok(Self::new(encoding_key, decoding_key))
+        Ok(Self::with_key(
+            INITIAL_KEY_ID,
+            SigningKey::from_base64_secret(secret)?,
+        ))
     }
 
     /// Create a new JWT manager from some secret bytes
     pub fn from_secret(secret: &[u8]) -> Self {
-        let encoding_key = EncodingKey::from_secret(secret);
-        let decoding_key = DecodingKey::from_secret(secret);
-        Self::new(encoding_key, decoding_key)
+        Self::with_key(INITIAL_KEY_ID, SigningKey::from_secret(secret))
     }
 
-    /// Create a new JWT manager with a specific encoding and decoding key
+    /// Create a new JWT manager with a specific HS256 encoding and decoding key,
+    /// identified by the [`INITIAL_KEY_ID`]
     pub fn new(encoding_key: EncodingKey, decoding_key: DecodingKey) -> Self {
-        Self {
-            encoding_key,
+        let key = SigningKey {
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(encoding_key),
             decoding_key,
+        };
+        Self::with_key(INITIAL_KEY_ID, key)
+    }
+
+    /// Create a new JWT manager signing tokens with the `key`, identified by `kid`.
+    ///
+    /// # Panics
+    /// If the `key` [can only validate tokens](SigningKey::can_sign)
+    pub fn with_key(kid: &str, key: SigningKey) -> Self {
+        assert!(
+            key.can_sign(),
+            "the initial key must be able to sign tokens"
+        );
+
+        Self {
+            keys: RwLock::new(KeyRing {
+                active: kid.to_string(),
+                keys: BTreeMap::from([(kid.to_string(), key)]),
+            }),
+            external: None,
+            refresh_tokens: None,
+        }
+    }
+
+    /// Also accept the tokens of an external identity provider, validated with its JWKS.
+    ///
+    /// Tokens with a `kid` that is not one of the keys of the VTN are validated with the `validator`.
+    pub fn with_external_tokens(mut self, validator: JwksValidator) -> Self {
+        self.external = Some(validator);
+        self
+    }
+
+    /// Issue refresh tokens valid for `lifetime` with each access token,
+    /// such that clients can renew their access token without sending their credentials again
+    pub fn with_refresh_tokens(mut self, lifetime: std::time::Duration) -> Self {
+        self.refresh_tokens = Some(lifetime);
+        self
+    }
+
+    /// The lifetime of refresh tokens, or `None` if refresh tokens are not issued
+    pub fn refresh_token_lifetime(&self) -> Option<std::time::Duration> {
+        self.refresh_tokens
+    }
+
+    /// Add a key to validate tokens with. Call [`Self::activate_key`] to sign new tokens with it.
+    pub fn add_key(&self, kid: &str, key: SigningKey) -> Result<(), KeyRotationError> {
+        let mut keys = self.keys.write().unwrap();
+        if keys.keys.contains_key(kid) {
+            return Err(KeyRotationError::DuplicateKey(kid.to_string()));
+        }
+
+        keys.keys.insert(kid.to_string(), key);
+        Ok(())
+    }
+
+    /// Sign new tokens with the key, previously added with [`Self::add_key`]
+    pub fn activate_key(&self, kid: &str) -> Result<(), KeyRotationError> {
+        let mut keys = self.keys.write().unwrap();
+        match keys.keys.get(kid) {
+            None => return Err(KeyRotationError::UnknownKey(kid.to_string())),
+            Some(key) if !key.can_sign() => {
+                return Err(KeyRotationError::VerifyOnly(kid.to_string()))
+            }
+            Some(_) => {}
+        }
+
+        keys.active = kid.to_string();
+        Ok(())
+    }
+
+    /// Remove a key, such that tokens signed with it are no longer valid.
+    /// The active key cannot be retired, activate another key first.
+    pub fn retire_key(&self, kid: &str) -> Result<(), KeyRotationError> {
+        let mut keys = self.keys.write().unwrap();
+        if keys.active == kid {
+            return Err(KeyRotationError::ActiveKey(kid.to_string()));
+        }
+
+        keys.keys
+            .remove(kid)
+            .map(|_| ())
+            .ok_or_else(|| KeyRotationError::UnknownKey(kid.to_string()))
+    }
+
+    pub fn key_ids(&self) -> KeyIds {
+        let keys = self.keys.read().unwrap();
+        KeyIds {
+            active: keys.active.clone(),
+            keys: keys.keys.keys().cloned().collect(),
         }
     }
 
-    /// Create a new JWT token with the given claims and expiration time
+    /// Create a new JWT token with the given claims and expiration time,
+    /// with all scopes of the roles
     pub fn create(
         &self,
         expires_in: std::time::Duration,
         client_id: String,
         roles: Vec<AuthRole>,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let scopes = role_scopes(&roles);
+        self.create_with_scopes(expires_in, client_id, roles, scopes)
+    }
+
+    /// Create a new JWT token restricted to the `scopes`
+    pub fn create_with_scopes(
+        &self,
+        expires_in: std::time::Duration,
+        client_id: String,
+        roles: Vec<AuthRole>,
+        scopes: Vec<Scope>,
     ) -> Result<String, jsonwebtoken::errors::Error> {
         let now = chrono::Utc::now();
         let exp = now + expires_in;
@@ -176,19 +586,106 @@ impl JwtManager {
             nbf: now.timestamp() as usize,
             sub: client_id,
             roles,
+            scopes: Some(scopes),
         };
 
-        let token = encode(&Header::default(), &claims, &self.encoding_key)?;
+        self.sign(&claims)
+    }
+
+    /// Create a refresh token for the client, see [`Self::with_refresh_tokens`].
+    ///
+    /// Refresh tokens carry no roles, and are not accepted as access tokens, nor vice versa.
+    /// The access tokens issued for the refresh token have at most the `scopes`.
+    pub fn create_refresh_token(
+        &self,
+        expires_in: std::time::Duration,
+        client_id: String,
+        scopes: Vec<Scope>,
+    ) -> Result<String, jsonwebtoken::errors::Error> {
+        let now = chrono::Utc::now();
+        let exp = now + expires_in;
+
+        let claims = RefreshClaims {
+            exp: exp.timestamp() as usize,
+            nbf: now.timestamp() as usize,
+            sub: client_id,
+            token_use: TokenUse::Refresh,
+            scopes: Some(scopes),
+        };
+
+        self.sign(&claims)
+    }
+
+    /// Validate a refresh token, returning the id of the client it was issued to,
+    /// and the scopes it was issued with
+    pub fn validate_refresh_token(
+        &self,
+        token: &str,
+    ) -> Result<(String, Vec<Scope>), jsonwebtoken::errors::Error> {
+        let claims: RefreshClaims = self.decode(token)?;
+        let scopes = claims.scopes.unwrap_or_default();
+        Ok((claims.sub, scopes))
+    }
+
+    /// Sign the claims with the active key
+    fn sign<T: serde::Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        let keys = self.keys.read().unwrap();
+        let key = &keys.keys[&keys.active];
+        let header = Header {
+            kid: Some(keys.active.clone()),
+            ..Header::new(key.algorithm)
+        };
+        let encoding_key = key
+            .encoding_key
+            .as_ref()
+            .expect("only keys that can sign are activated");
+        let token = encode(&header, claims, encoding_key)?;
 
         Ok(token)
     }
 
-    /// Decode and validate a given JWT token, returning the validated claims
+    /// Decode and validate a given JWT token, returning the validated claims.
+    /// The token is validated with the key identified by its `kid` header.
     pub fn decode_and_validate(&self, token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-        let validation = jsonwebtoken::Validation::default();
-        let token_data = jsonwebtoken::decode::<Claims>(token, &self.decoding_key, &validation)?;
+        self.decode(token)
+    }
+
+    fn decode<T: serde::de::DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> Result<T, jsonwebtoken::errors::Error> {
+        let header = jsonwebtoken::decode_header(token)?;
+        let kid = header.kid.as_deref().unwrap_or(INITIAL_KEY_ID);
+
+        let keys = self.keys.read().unwrap();
+        let Some(key) = keys.keys.get(kid) else {
+            return Err(jsonwebtoken::errors::ErrorKind::InvalidToken.into());
+        };
+
+        // the algorithm of the key, not of the token header, to prevent algorithm confusion
+        let validation = jsonwebtoken::Validation::new(key.algorithm);
+        let token_data = jsonwebtoken::decode::<T>(token, &key.decoding_key, &validation)?;
         Ok(token_data.claims)
     }
+
+    /// Validate a token of the VTN, or of the external identity provider, if configured
+    pub async fn validate(&self, token: &str) -> Result<Claims, TokenError> {
+        let Some(external) = &self.external else {
+            return Ok(self.decode_and_validate(token)?);
+        };
+
+        let header = jsonwebtoken::decode_header(token)?;
+        let is_own_key = match &header.kid {
+            Some(kid) => self.keys.read().unwrap().keys.contains_key(kid),
+            None => true,
+        };
+
+        if is_own_key {
+            Ok(self.decode_and_validate(token)?)
+        } else {
+            Ok(external.validate(token).await?)
+        }
+    }
 }
 
 /// User claims extracted from the request
@@ -200,7 +697,8 @@ pub struct BusinessUser(pub Claims);
 /// User claims extracted from the request, with the requirement that the user is a VEN user
 pub struct VENUser(pub Claims);
 
-/// User claims extracted from the request, with the requirement that the user is a user manager
+/// User claims extracted from the request, with the requirement that the user is a user manager.
+/// All endpoints for user managers administer the VTN, so the token must have the [`Scope::Admin`] scope.
 pub struct UserManagerUser(pub Claims);
 
 /// User claims extracted from the request, with the requirement that the user is a VEN manager
@@ -224,11 +722,16 @@ where
 
         let jwt_manager = Arc::<JwtManager>::from_ref(state);
 
-        let Ok(claims) = jwt_manager.decode_and_validate(bearer.0.token()) else {
-            return Err(AppError::Forbidden("Invalid authentication token provided"));
+        let claims = match jwt_manager.validate(bearer.0.token()).await {
+            Ok(claims) => claims,
+            Err(err) => {
+                trace!(%err, "rejected authentication token");
+                return Err(AppError::InvalidToken);
+            }
         };
 
         trace!(user = ?claims, "Extracted User from request");
+        Span::current().record("client_id", claims.sub.as_str());
 
         Ok(User(claims))
     }
@@ -278,6 +781,7 @@ where
         if !user.is_user_manager() {
             return Err(AppError::Forbidden("User does not have the required role"));
         }
+        user.require_scope(Scope::Admin)?;
         Ok(UserManagerUser(user))
     }
 }
@@ -292,10 +796,219 @@ where
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let User(user) = User::from_request_parts(parts, state).await?;
         if !user.is_ven_manager() {
-            return Err(AppError::Auth(
-                "User does not have the required role".to_string(),
-            ));
+            return Err(AppError::Forbidden("User does not have the required role"));
         }
         Ok(VenManagerUser(user))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn token(jwt_manager: &JwtManager) -> String {
+        jwt_manager
+            .create(Duration::from_secs(60), "client".to_string(), vec![])
+            .unwrap()
+    }
+
+    #[test]
+    fn rotate_keys() {
+        let jwt_manager = JwtManager::from_secret(b"old secret");
+        let old_token = token(&jwt_manager);
+
+        jwt_manager
+            .add_key("new", SigningKey::from_secret(b"new secret"))
+            .unwrap();
+        jwt_manager.activate_key("new").unwrap();
+        let new_token = token(&jwt_manager);
+
+        assert_eq!(
+            jsonwebtoken::decode_header(&new_token)
+                .unwrap()
+                .kid
+                .as_deref(),
+            Some("new")
+        );
+        assert!(jwt_manager.decode_and_validate(&old_token).is_ok());
+        assert!(jwt_manager.decode_and_validate(&new_token).is_ok());
+
+        jwt_manager.retire_key(INITIAL_KEY_ID).unwrap();
+        assert!(jwt_manager.decode_and_validate(&old_token).is_err());
+        assert!(jwt_manager.decode_and_validate(&new_token).is_ok());
+        assert_eq!(
+            jwt_manager.key_ids(),
+            KeyIds {
+                active: "new".to_string(),
+                keys: vec!["new".to_string()]
+            }
+        );
+    }
+
+    #[test]
+    fn refresh_tokens_are_no_access_tokens() {
+        let jwt_manager = JwtManager::from_secret(b"secret");
+        let access_token = token(&jwt_manager);
+        let refresh_token = jwt_manager
+            .create_refresh_token(
+                Duration::from_secs(60),
+                "client".to_string(),
+                vec![Scope::ReadAll],
+            )
+            .unwrap();
+
+        assert_eq!(
+            jwt_manager.validate_refresh_token(&refresh_token).unwrap(),
+            ("client".to_string(), vec![Scope::ReadAll])
+        );
+        assert!(jwt_manager.decode_and_validate(&refresh_token).is_err());
+        assert!(jwt_manager.validate_refresh_token(&access_token).is_err());
+    }
+
+    #[test]
+    fn scopes() {
+        let jwt_manager = JwtManager::from_secret(b"secret");
+        let token = jwt_manager
+            .create_with_scopes(
+                Duration::from_secs(60),
+                "client".to_string(),
+                vec![AuthRole::AnyBusiness],
+                vec![Scope::ReadAll],
+            )
+            .unwrap();
+
+        let claims = jwt_manager.decode_and_validate(&token).unwrap();
+        assert_eq!(claims.scopes(), [Scope::ReadAll]);
+        assert!(claims.require_scope(Scope::ReadAll).is_ok());
+        assert!(matches!(
+            claims.require_scope(Scope::WriteEvents),
+            Err(AppError::InsufficientScope(Scope::WriteEvents))
+        ));
+
+        // without a scope claim, the token has all scopes of its roles
+        let ven = AuthRole::VEN(VenId::new("ven-1").unwrap());
+        let claims = Claims::external("client".to_string(), vec![ven], 0, 0);
+        assert_eq!(
+            claims.scopes(),
+            [Scope::ReadAll, Scope::WriteReports, Scope::WriteVens]
+        );
+        assert!(!claims.has_scope(Scope::WritePrograms));
+    }
+
+    const EC_PRIVATE_KEY: &str = include_str!("../test-keys/es256.pem");
+    const EC_PUBLIC_KEY: &str = include_str!("../test-keys/es256.pub.pem");
+
+    #[test]
+    fn asymmetric_keys() {
+        let key = SigningKey::from_pem(
+            Algorithm::ES256,
+            EC_PRIVATE_KEY.as_bytes(),
+            EC_PUBLIC_KEY.as_bytes(),
+        )
+        .unwrap();
+        let jwt_manager = JwtManager::with_key("es", key);
+        let token = token(&jwt_manager);
+
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::ES256);
+        assert_eq!(header.kid.as_deref(), Some("es"));
+        assert!(jwt_manager.decode_and_validate(&token).is_ok());
+
+        // another instance only knowing the public key validates the tokens
+        let verify_only =
+            SigningKey::verify_only(Algorithm::ES256, EC_PUBLIC_KEY.as_bytes()).unwrap();
+        assert!(!verify_only.can_sign());
+        let other = JwtManager::from_secret(b"secret");
+        other.add_key("es", verify_only).unwrap();
+        assert!(other.decode_and_validate(&token).is_ok());
+        assert_eq!(
+            other.activate_key("es"),
+            Err(KeyRotationError::VerifyOnly("es".to_string()))
+        );
+
+        // a token signed with the public key as HMAC secret is rejected
+        let forged = encode(
+            &Header {
+                kid: Some("es".to_string()),
+                ..Header::new(Algorithm::HS256)
+            },
+            &jsonwebtoken::decode::<Claims>(
+                &token,
+                &DecodingKey::from_ec_pem(EC_PUBLIC_KEY.as_bytes()).unwrap(),
+                &jsonwebtoken::Validation::new(Algorithm::ES256),
+            )
+            .unwrap()
+            .claims,
+            &EncodingKey::from_secret(EC_PUBLIC_KEY.as_bytes()),
+        )
+        .unwrap();
+        assert!(other.decode_and_validate(&forged).is_err());
+
+        assert!(SigningKey::verify_only(Algorithm::HS256, EC_PUBLIC_KEY.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn tokens_without_kid_use_initial_key() {
+        let jwt_manager = JwtManager::from_secret(b"secret");
+        let claims = Claims {
+            exp: (chrono::Utc::now().timestamp() + 60) as usize,
+            nbf: chrono::Utc::now().timestamp() as usize,
+            sub: "client".to_string(),
+            roles: vec![],
+            scopes: None,
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            jwt_manager.decode_and_validate(&token).unwrap().sub,
+            "client"
+        );
+    }
+
+    #[test]
+    fn who_am_i() {
+        let jwt_manager = JwtManager::from_secret(b"secret");
+        let token = jwt_manager
+            .create(
+                Duration::from_secs(60),
+                "client".to_string(),
+                vec![
+                    AuthRole::VEN(VenId::new("ven-1").unwrap()),
+                    AuthRole::Business("business-1".to_string()),
+                ],
+            )
+            .unwrap();
+
+        let who_am_i = jwt_manager.decode_and_validate(&token).unwrap().who_am_i();
+        assert_eq!(who_am_i.client_id, "client");
+        assert_eq!(who_am_i.roles, ["VEN", "Business"]);
+        assert_eq!(who_am_i.ven_ids, [VenId::new("ven-1").unwrap()]);
+        assert!(!who_am_i.any_business);
+        assert_eq!(who_am_i.business_ids, ["business-1"]);
+        assert!(who_am_i.expires_at > chrono::Utc::now());
+    }
+
+    #[test]
+    fn rotation_errors() {
+        let jwt_manager = JwtManager::from_secret(b"secret");
+
+        assert_eq!(
+            jwt_manager.retire_key(INITIAL_KEY_ID),
+            Err(KeyRotationError::ActiveKey(INITIAL_KEY_ID.to_string()))
+        );
+        assert_eq!(
+            jwt_manager.activate_key("unknown"),
+            Err(KeyRotationError::UnknownKey("unknown".to_string()))
+        );
+        assert_eq!(
+            jwt_manager.add_key(INITIAL_KEY_ID, SigningKey::from_secret(b"other")),
+            Err(KeyRotationError::DuplicateKey(INITIAL_KEY_ID.to_string()))
+        );
+    }
+}
@@ -1,5 +1,24 @@
 pub mod api;
+pub mod batch;
+pub mod bootstrap;
+pub mod certification;
+pub mod change_log;
+pub mod changes;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod config;
 pub mod data_source;
 mod error;
+pub mod event_archive;
+pub mod jwks;
 pub mod jwt;
+pub mod maintenance;
+pub mod metrics;
+pub mod notifier;
+pub mod report_quota;
+pub mod signing;
 pub mod state;
+pub mod stats;
+pub mod target_labels;
+#[cfg(feature = "otel")]
+pub mod telemetry;
@@ -1,38 +1,339 @@
+use std::{sync::Arc, time::Duration};
+
 use tokio::{net::TcpListener, signal};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
+#[cfg(all(feature = "in-memory", not(feature = "postgres")))]
+use openadr_vtn::data_source::InMemoryStorage;
+use openadr_vtn::{
+    api::PageSize,
+    bootstrap,
+    config::Config,
+    jwt::JwtManager,
+    notifier::{Notifier, RetryPolicy},
+    state::AppState,
+    target_labels::TargetLabelRegistry,
+};
 #[cfg(feature = "postgres")]
-use openadr_vtn::data_source::PostgresStorage;
-use openadr_vtn::{jwt::JwtManager, state::AppState};
+use openadr_vtn::{
+    change_log,
+    data_source::{DataSource, PostgresStorage},
+    event_archive,
+};
+use uuid::Uuid;
+
+#[cfg(feature = "tls")]
+use axum_server::tls_rustls::RustlsConfig;
+#[cfg(feature = "tls")]
+use openadr_vtn::config::TlsConfig;
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::registry()
-        .with(fmt::layer().with_file(true).with_line_number(true))
-        .with(EnvFilter::from_default_env())
-        .init();
+    // Structured JSON logs are easier to ingest in log aggregation systems
+    let json_logs =
+        std::env::var("OPENADR_LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json"));
+
+    #[cfg(feature = "otel")]
+    let tracer_provider = openadr_vtn::telemetry::tracer_provider_from_env()
+        .expect("invalid OpenTelemetry exporter configuration");
 
-    let addr = "0.0.0.0:3000";
-    let listener = TcpListener::bind(addr).await.unwrap();
-    info!("listening on http://{}", listener.local_addr().unwrap());
+    let registry = tracing_subscriber::registry()
+        .with(json_logs.then(|| fmt::layer().json().with_file(true).with_line_number(true)))
+        .with((!json_logs).then(|| fmt::layer().with_file(true).with_line_number(true)))
+        .with(EnvFilter::from_default_env());
+    #[cfg(feature = "otel")]
+    let registry = registry.with(tracer_provider.as_ref().map(openadr_vtn::telemetry::layer));
+    registry.init();
+
+    #[cfg(feature = "otel")]
+    if tracer_provider.is_some() {
+        info!("exporting spans to the OTLP collector");
+    }
 
     #[cfg(feature = "postgres")]
-    let storage = PostgresStorage::from_env().await.unwrap();
+    dotenvy::dotenv().ok();
+
+    let config = Config::load().unwrap_or_else(|err| exit_with(err));
+    #[cfg(feature = "tls")]
+    let tls = match &config.tls {
+        Some(tls) => Some(rustls_config(tls).await),
+        None => None,
+    };
+    #[cfg(not(feature = "tls"))]
+    if config.tls.is_some() {
+        exit_with("TLS is configured, but the VTN was built without the `tls` feature");
+    }
+
+    let listener = TcpListener::bind(config.listen_address)
+        .await
+        .unwrap_or_else(|err| {
+            exit_with(format!(
+                "could not listen on {}: {err}",
+                config.listen_address
+            ))
+        });
+    let scheme = if config.tls.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    info!("listening on {scheme}://{}", listener.local_addr().unwrap());
 
-    #[cfg(not(feature = "postgres"))]
+    #[cfg(feature = "postgres")]
+    let storage = {
+        let Some(db_url) = &config.database_url else {
+            exit_with("missing DATABASE_URL, or `database_url` in the configuration file");
+        };
+        PostgresStorage::connect(db_url)
+            .await
+            .unwrap_or_else(|err| exit_with(format!("could not connect to the database: {err}")))
+    };
+
+    #[cfg(all(feature = "in-memory", not(feature = "postgres")))]
+    let storage = in_memory_storage(&config).await;
+
+    #[cfg(not(any(feature = "postgres", feature = "in-memory")))]
     compile_error!(
-        "No storage backend selected. Please enable the `postgres` feature flag during compilation"
+        "No storage backend selected. Please enable the `postgres` or `in-memory` feature flag during compilation"
     );
 
-    // TODO make the JWT secret secure and configurable
-    let state = AppState::new(storage, JwtManager::from_base64_secret("test").unwrap());
-    if let Err(e) = axum::serve(listener, state.into_router())
-        .with_graceful_shutdown(shutdown_signal())
-        .await
+    let mut jwt_manager = match config.jwt_manager() {
+        Ok(Some(jwt_manager)) => jwt_manager,
+        Ok(None) => {
+            warn!("no JWT secret configured, signing tokens with a random secret that is lost when the VTN stops");
+            let secret = [Uuid::new_v4().into_bytes(), Uuid::new_v4().into_bytes()].concat();
+            JwtManager::from_secret(&secret)
+        }
+        Err(err) => exit_with(err),
+    };
+
+    let jwks_validator = config.jwks_validator().unwrap_or_else(|err| exit_with(err));
+    if let (Some(validator), Some(oauth)) = (jwks_validator, &config.oauth) {
+        info!(
+            jwks_url = %oauth.jwks_url,
+            issuer = %oauth.issuer,
+            "accepting tokens of the identity provider"
+        );
+        jwt_manager = jwt_manager.with_external_tokens(validator);
+    }
+
+    if let Some(days) = config.jwt.refresh_token_days {
+        info!(days, "issuing refresh tokens");
+        jwt_manager = jwt_manager.with_refresh_tokens(Duration::from_secs(days * 24 * 3600));
+    }
+
+    let mut state = AppState::new(storage.clone(), jwt_manager);
+
+    let cors_origins = config.cors_origins().unwrap_or_else(|err| exit_with(err));
+    if !cors_origins.is_empty() {
+        info!(?cors_origins, "allowing cross-origin requests");
+        state = state.with_cors_origins(cors_origins);
+    }
+
+    if let Some(auth_source) = config
+        .oidc_auth_source()
+        .unwrap_or_else(|err| exit_with(err))
     {
+        info!("authenticating clients with OIDC tokens");
+        state = state.with_auth_source(auth_source);
+    }
+
+    if let Some(event_signer) = config.event_signer().unwrap_or_else(|err| exit_with(err)) {
+        info!("signing events with {:?}", event_signer.algorithm());
+        state = state.with_event_signer(event_signer);
+    }
+
+    if let Some(labels) = &config.private_target_labels {
+        info!(?labels, "restricting private target labels");
+        state = state.with_target_labels(TargetLabelRegistry::allow_only(labels));
+    }
+
+    if let Some(policy) = config.interval_order().unwrap_or_else(|err| exit_with(err)) {
+        info!(?policy, "interval order policy");
+        state = state.with_interval_order(policy);
+    }
+
+    if config.materialize_program_defaults {
+        info!("materializing program defaults on events");
+        state = state.with_materialized_program_defaults();
+    }
+
+    if let Some(limit) = config.report_size_limit {
+        info!(limit, "report size limit");
+        state = state.with_report_size_limit(limit);
+    }
+
+    let page_size = config.page_size().unwrap_or_else(|err| exit_with(err));
+    if page_size != PageSize::default() {
+        info!(?page_size, "page size");
+        state = state.with_page_size(page_size);
+    }
+
+    if let Some(report_quotas) = config.report_quotas() {
+        info!(report_quota = ?config.report_quota, "report quota");
+        state = state.with_report_quotas(report_quotas);
+    }
+
+    #[cfg(feature = "postgres")]
+    if config.change_log.enabled {
+        let retention_days = config.change_log.retention_days;
+        info!(retention_days, "recording changes in the change log");
+
+        let Some(change_log) = storage.change_log() else {
+            exit_with("the storage has no change log");
+        };
+        change_log::spawn_retention(
+            change_log.clone(),
+            Duration::from_secs(retention_days * 24 * 3600),
+        );
+        state = state.with_change_log(change_log);
+    }
+
+    #[cfg(feature = "postgres")]
+    if config.event_archive.enabled {
+        let archive_after_days = config.event_archive.archive_after_days;
+        info!(?archive_after_days, "completing and archiving events");
+
+        event_archive::spawn_archiver(
+            storage.event_archive(),
+            archive_after_days.map(|days| Duration::from_secs(days * 24 * 3600)),
+        );
+    }
+
+    if let Some(subscriptions) = config.webhooks().unwrap_or_else(|err| exit_with(err)) {
+        info!(?subscriptions, "notifying webhooks of changes");
+        state = state.with_notifier(Notifier::spawn(
+            Arc::new(subscriptions),
+            RetryPolicy::default(),
+        ));
+    }
+
+    #[cfg(feature = "chaos")]
+    {
+        let chaos = config.chaos().unwrap_or_else(|err| exit_with(err));
+        warn!(?chaos, "injecting faults, never use this in production");
+        state = state.with_chaos(chaos);
+    }
+    #[cfg(not(feature = "chaos"))]
+    if config.chaos.is_some() {
+        exit_with("chaos is configured, but the VTN was built without the `chaos` feature");
+    }
+
+    if let Some(vectors) = config
+        .certification_vectors()
+        .unwrap_or_else(|err| exit_with(err))
+    {
+        info!(vectors = vectors.len(), "loaded certification vectors");
+        state = state.with_certification_vectors(vectors);
+    }
+
+    let users = config
+        .bootstrap_users()
+        .unwrap_or_else(|err| exit_with(err));
+    if !users.is_empty() {
+        bootstrap::seed_users(state.storage.auth().as_ref(), &users)
+            .await
+            .unwrap_or_else(|err| exit_with(format!("could not seed the bootstrap users: {err}")));
+        info!(users = users.len(), "seeded bootstrap users");
+    }
+
+    let router = state.into_router();
+    #[cfg(feature = "tls")]
+    let result = match tls {
+        Some(tls) => serve_tls(listener, tls, router).await,
+        None => serve_http(listener, router).await,
+    };
+    #[cfg(not(feature = "tls"))]
+    let result = serve_http(listener, router).await;
+
+    if let Err(e) = result {
         error!("webserver crashed: {}", e);
     }
+
+    #[cfg(all(feature = "in-memory", not(feature = "postgres")))]
+    if let Err(err) = storage.save_snapshot().await {
+        error!(%err, "failed to save snapshot");
+    }
+
+    #[cfg(feature = "otel")]
+    if let Some(Err(err)) = tracer_provider.map(|provider| provider.shutdown()) {
+        error!(%err, "failed to export the remaining spans");
+    }
+}
+
+/// Plain HTTP, for development or behind a proxy terminating TLS
+async fn serve_http(listener: TcpListener, router: axum::Router) -> std::io::Result<()> {
+    warn!("serving plain HTTP, configure a TLS certificate unless a proxy terminates TLS");
+
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown_signal())
+        .await
+}
+
+/// Loads the PEM encoded certificate chain and private key to terminate TLS with
+#[cfg(feature = "tls")]
+async fn rustls_config(tls: &TlsConfig) -> RustlsConfig {
+    // reqwest enables the ring provider as well, so rustls cannot pick one by itself
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+        .await
+        .unwrap_or_else(|err| {
+            exit_with(format!(
+                "could not load the TLS certificate {} and key {}: {err}",
+                tls.cert_path.display(),
+                tls.key_path.display()
+            ))
+        })
+}
+
+#[cfg(feature = "tls")]
+async fn serve_tls(
+    listener: TcpListener,
+    tls: RustlsConfig,
+    router: axum::Router,
+) -> std::io::Result<()> {
+    let handle = axum_server::Handle::new();
+    tokio::spawn({
+        let handle = handle.clone();
+        async move {
+            shutdown_signal().await;
+            handle.graceful_shutdown(Some(Duration::from_secs(30)));
+        }
+    });
+
+    axum_server::from_tcp_rustls(listener.into_std()?, tls)
+        .handle(handle)
+        .serve(router.into_make_service())
+        .await
+}
+
+/// Keeps the objects in memory, persisted in a snapshot every minute
+/// if the `snapshot_path` is configured
+#[cfg(all(feature = "in-memory", not(feature = "postgres")))]
+async fn in_memory_storage(config: &Config) -> InMemoryStorage {
+    let Some(path) = &config.snapshot_path else {
+        warn!("keeping all objects in memory, they are lost when the VTN stops");
+        return InMemoryStorage::new();
+    };
+
+    let storage = InMemoryStorage::with_snapshots(path)
+        .await
+        .unwrap_or_else(|err| {
+            exit_with(format!(
+                "could not load the snapshot {}: {err}",
+                path.display()
+            ))
+        });
+    storage.spawn_snapshots(Duration::from_secs(60));
+    storage
+}
+
+/// Log a clear reason why the VTN cannot start, rather than panicking with a backtrace
+fn exit_with(reason: impl std::fmt::Display) -> ! {
+    error!("{reason}");
+    std::process::exit(1)
 }
 
 async fn shutdown_signal() {
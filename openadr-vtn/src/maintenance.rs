@@ -0,0 +1,191 @@
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    extract::{Request, State},
+    http::Method,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::error::AppError;
+
+/// The `Retry-After`, in seconds, sent when no other value is configured
+pub const DEFAULT_RETRY_AFTER: u64 = 60;
+
+/// Paths that remain available in maintenance mode,
+/// such that an administrator can log in and end the maintenance
+const ALWAYS_AVAILABLE: [&str; 2] = ["/auth/token", "/admin/maintenance"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MaintenanceMode {
+    /// The VTN handles all requests
+    #[default]
+    Off,
+    /// The VTN only handles requests that do not modify any objects
+    ReadOnly,
+    /// The VTN rejects all requests
+    Unavailable,
+}
+
+/// Whether the VTN is in maintenance mode, e.g., during a planned database migration.
+///
+/// Rejected requests receive a `503 Service Unavailable` with a `Retry-After` header,
+/// such that clients know to retry them later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceStatus {
+    pub mode: MaintenanceMode,
+    /// The number of seconds clients should wait before retrying a rejected request
+    #[serde(default = "default_retry_after")]
+    #[validate(range(max = 86400))]
+    pub retry_after: u64,
+}
+
+impl Default for MaintenanceStatus {
+    fn default() -> Self {
+        Self {
+            mode: MaintenanceMode::Off,
+            retry_after: DEFAULT_RETRY_AFTER,
+        }
+    }
+}
+
+fn default_retry_after() -> u64 {
+    DEFAULT_RETRY_AFTER
+}
+
+/// The maintenance status of this VTN process, toggled through `PUT /admin/maintenance`.
+///
+/// Like the [`ChangeNotifier`](crate::changes::ChangeNotifier), the status is not shared
+/// between multiple instances of the VTN, each instance has to be toggled separately.
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    status: RwLock<MaintenanceStatus>,
+}
+
+impl Maintenance {
+    pub fn status(&self) -> MaintenanceStatus {
+        *self.status.read().unwrap()
+    }
+
+    pub fn set_status(&self, status: MaintenanceStatus) {
+        *self.status.write().unwrap() = status;
+    }
+
+    fn check(&self, method: &Method, path: &str) -> Result<(), AppError> {
+        let status = self.status();
+        if ALWAYS_AVAILABLE.contains(&path) {
+            return Ok(());
+        }
+
+        let is_read = matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+        match status.mode {
+            MaintenanceMode::Off => Ok(()),
+            MaintenanceMode::ReadOnly if is_read => Ok(()),
+            MaintenanceMode::ReadOnly => Err(AppError::ServiceUnavailable(
+                "The VTN is in read-only maintenance mode",
+                status.retry_after,
+            )),
+            MaintenanceMode::Unavailable => Err(AppError::ServiceUnavailable(
+                "The VTN is in maintenance mode",
+                status.retry_after,
+            )),
+        }
+    }
+}
+
+/// Middleware rejecting the requests the current maintenance mode does not allow
+pub(crate) async fn reject_during_maintenance(
+    State(maintenance): State<Arc<Maintenance>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    match maintenance.check(req.method(), req.uri().path()) {
+        Ok(()) => next.run(req).await,
+        Err(err) => err.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{
+        body::Body,
+        http::{header, StatusCode},
+        middleware,
+        routing::get,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    fn app(maintenance: Arc<Maintenance>) -> Router {
+        Router::new()
+            .route("/programs", get(|| async {}).post(|| async {}))
+            .route("/auth/token", get(|| async {}).post(|| async {}))
+            .layer(middleware::from_fn_with_state(
+                maintenance,
+                reject_during_maintenance,
+            ))
+    }
+
+    async fn status(app: &Router, method: Method, path: &str) -> (StatusCode, Option<String>) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(path)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .map(|value| value.to_str().unwrap().to_string());
+        (response.status(), retry_after)
+    }
+
+    #[tokio::test]
+    async fn modes() {
+        let maintenance = Arc::new(Maintenance::default());
+        let app = app(maintenance.clone());
+
+        assert_eq!(
+            status(&app, Method::POST, "/programs").await,
+            (StatusCode::OK, None)
+        );
+
+        maintenance.set_status(MaintenanceStatus {
+            mode: MaintenanceMode::ReadOnly,
+            retry_after: 120,
+        });
+        assert_eq!(
+            status(&app, Method::GET, "/programs").await,
+            (StatusCode::OK, None)
+        );
+        assert_eq!(
+            status(&app, Method::POST, "/programs").await,
+            (StatusCode::SERVICE_UNAVAILABLE, Some("120".to_string()))
+        );
+
+        maintenance.set_status(MaintenanceStatus {
+            mode: MaintenanceMode::Unavailable,
+            retry_after: 30,
+        });
+        assert_eq!(
+            status(&app, Method::GET, "/programs").await,
+            (StatusCode::SERVICE_UNAVAILABLE, Some("30".to_string()))
+        );
+        // administrators must still be able to log in
+        assert_eq!(
+            status(&app, Method::POST, "/auth/token").await,
+            (StatusCode::OK, None)
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Process-wide counters describing the health of the VTN.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    handler_panics: AtomicU64,
+    report_quota_rejections: AtomicU64,
+    transaction_retries: AtomicU64,
+}
+
+static METRICS: Metrics = Metrics::new();
+
+/// The global metrics of this VTN instance
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Metrics {
+    const fn new() -> Self {
+        Self {
+            handler_panics: AtomicU64::new(0),
+            report_quota_rejections: AtomicU64::new(0),
+            transaction_retries: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of request handlers that panicked since startup
+    pub fn handler_panics(&self) -> u64 {
+        self.handler_panics.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_handler_panic(&self) {
+        self.handler_panics.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of reports rejected because they exceeded a [`ReportQuota`](crate::report_quota::ReportQuota)
+    pub fn report_quota_rejections(&self) -> u64 {
+        self.report_quota_rejections.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_report_quota_rejection(&self) {
+        self.report_quota_rejections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of database transactions retried after a serialization failure or deadlock
+    pub fn transaction_retries(&self) -> u64 {
+        self.transaction_retries.load(Ordering::Relaxed)
+    }
+
+    #[cfg_attr(not(feature = "postgres"), allow(dead_code))]
+    pub(crate) fn record_transaction_retry(&self) {
+        self.transaction_retries.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,602 @@
+//! Delivers [`Notification`]s of changes to objects to the callback URLs of subscribers.
+//!
+//! Notifications are delivered in the background, retrying with exponential backoff,
+//! such that slow or unreachable subscribers do not delay the requests that changed the objects.
+//!
+//! A subscription may ask for batches instead, to receive the notifications of bulk changes
+//! in a single callback: the notifications within its batch window are posted as a JSON array
+//! once the window, starting at the first of them, elapsed.
+
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use axum::{async_trait, body::Bytes, http::header};
+use chrono::{DateTime, Utc};
+use openadr_wire::notification::{
+    Notification, NotificationObject, NotificationObjectType, NotificationOperation,
+};
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::{debug, error, warn};
+use url::Url;
+
+use crate::{batch::BatchWindows, change_log::Operation, error::AppError};
+
+/// The number of notifications waiting for the subscriptions to be looked up,
+/// beyond which new notifications are dropped
+const QUEUE_SIZE: usize = 1024;
+
+#[async_trait]
+pub trait SubscriptionSource: Send + Sync + 'static {
+    /// The callback URLs of the subscriptions matching the notification
+    async fn callback_urls(&self, notification: &Notification) -> Result<Vec<Url>, AppError>;
+
+    /// The batch window of the subscription to the object type with the callback URL,
+    /// if it asked for batches
+    fn batch_window(&self, _object_type: NotificationObjectType, _url: &Url) -> Option<Duration> {
+        None
+    }
+}
+
+/// Subscriptions configured when starting the VTN,
+/// parsed from a comma separated list of `OBJECT_TYPE=url` pairs,
+/// e.g., `EVENT=https://bl.example.com/events,REPORT=https://bl.example.com/reports`.
+///
+/// A URL followed by `;batch=<seconds>` receives batches, e.g., `EVENT=https://bl.example.com/events;batch=10`
+/// for at most one callback every 10 seconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StaticSubscriptions(Vec<StaticSubscription>);
+
+#[derive(Debug, Clone, PartialEq)]
+struct StaticSubscription {
+    object_type: NotificationObjectType,
+    url: Url,
+    batch_window: Option<Duration>,
+}
+
+impl FromStr for StaticSubscriptions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (object_type, url) = pair
+                    .split_once('=')
+                    .ok_or_else(|| format!("expected OBJECT_TYPE=url, found `{pair}`"))?;
+                let object_type = serde_json::from_value(object_type.trim().into())
+                    .map_err(|_| format!("unknown object type `{object_type}`"))?;
+                let (url, batch_window) = match url.rsplit_once(";batch=") {
+                    Some((url, seconds)) => {
+                        let window = seconds
+                            .trim()
+                            .parse()
+                            .ok()
+                            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                            .filter(|window| !window.is_zero())
+                            .ok_or_else(|| format!("invalid batch window `{seconds}`"))?;
+                        (url, Some(window))
+                    }
+                    None => (url, None),
+                };
+                let url = url
+                    .trim()
+                    .parse()
+                    .map_err(|err| format!("invalid URL `{url}`: {err}"))?;
+                Ok(StaticSubscription {
+                    object_type,
+                    url,
+                    batch_window,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+#[async_trait]
+impl SubscriptionSource for StaticSubscriptions {
+    async fn callback_urls(&self, notification: &Notification) -> Result<Vec<Url>, AppError> {
+        Ok(self
+            .0
+            .iter()
+            .filter(|subscription| subscription.object_type == notification.object_type)
+            .map(|subscription| subscription.url.clone())
+            .collect())
+    }
+
+    fn batch_window(&self, object_type: NotificationObjectType, url: &Url) -> Option<Duration> {
+        self.0
+            .iter()
+            .find(|subscription| {
+                subscription.object_type == object_type && &subscription.url == url
+            })
+            .and_then(|subscription| subscription.batch_window)
+    }
+}
+
+/// How often and how long to try delivering a notification to a callback URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// The wait after the first failed attempt, which doubles after each further attempt
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// The timeout of each attempt
+    pub timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(300),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait after the given failed attempt, starting at 1
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+impl From<Operation> for NotificationOperation {
+    fn from(operation: Operation) -> Self {
+        match operation {
+            Operation::Create => NotificationOperation::Post,
+            Operation::Update => NotificationOperation::Put,
+            Operation::Delete => NotificationOperation::Delete,
+        }
+    }
+}
+
+/// How the delivery of the notifications to a callback URL went since the VTN started
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryState {
+    pub url: String,
+    /// The notifications delivered, possibly after retries
+    pub delivered: u64,
+    /// The notifications given up on after their last attempt failed
+    pub failed: u64,
+    /// The attempts that failed, including those that were retried
+    pub failed_attempts: u64,
+    pub last_delivered: Option<DeliveryAttempt>,
+    pub last_failed: Option<DeliveryAttempt>,
+}
+
+/// A single attempt to deliver a notification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeliveryAttempt {
+    #[serde(with = "openadr_wire::serde_rfc3339")]
+    pub date_time: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DeliveryState {
+    fn new(url: &Url) -> Self {
+        Self {
+            url: url.to_string(),
+            delivered: 0,
+            failed: 0,
+            failed_attempts: 0,
+            last_delivered: None,
+            last_failed: None,
+        }
+    }
+}
+
+/// The [`DeliveryState`] of each callback URL notified so far
+type Deliveries = Arc<Mutex<BTreeMap<Url, DeliveryState>>>;
+
+/// Queues notifications for the background task delivering them
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    sender: mpsc::Sender<Notification>,
+    deliveries: Deliveries,
+}
+
+impl Notifier {
+    /// Spawn the task delivering the notifications to the subscribers
+    pub fn spawn(subscriptions: Arc<dyn SubscriptionSource>, retry: RetryPolicy) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        let deliveries = Deliveries::default();
+        tokio::spawn(dispatch(receiver, subscriptions, retry, deliveries.clone()));
+
+        Self { sender, deliveries }
+    }
+
+    /// The delivery state of each callback URL notified so far, ordered by URL
+    pub fn deliveries(&self) -> Vec<DeliveryState> {
+        let deliveries = self.deliveries.lock().unwrap();
+        deliveries.values().cloned().collect()
+    }
+
+    /// Queue a notification, or drop it if the subscribers cannot keep up
+    pub fn notify(&self, notification: Notification) {
+        if let Err(err) = self.sender.try_send(notification) {
+            warn!(?err, "dropped notification");
+        }
+    }
+}
+
+/// Notify the subscribers of an operation on an object, if notifications are enabled
+pub(crate) fn send<T>(notifier: Option<&Notifier>, operation: Operation, object: &T)
+where
+    T: Clone + Into<NotificationObject>,
+{
+    if let Some(notifier) = notifier {
+        notifier.notify(Notification::new(operation.into(), object.clone().into()));
+    }
+}
+
+/// The notifications waiting for the batch window of each subscription to elapse,
+/// by object type and callback URL
+type Batches = BatchWindows<(NotificationObjectType, Url), Notification>;
+
+async fn dispatch(
+    mut receiver: mpsc::Receiver<Notification>,
+    subscriptions: Arc<dyn SubscriptionSource>,
+    retry: RetryPolicy,
+    deliveries: Deliveries,
+) {
+    let client = match reqwest::Client::builder().timeout(retry.timeout).build() {
+        Ok(client) => client,
+        Err(err) => {
+            error!(?err, "could not create the HTTP client for notifications");
+            return;
+        }
+    };
+    let batches = Batches::default();
+
+    while let Some(notification) = receiver.recv().await {
+        let urls = match subscriptions.callback_urls(&notification).await {
+            Ok(urls) => urls,
+            Err(err) => {
+                error!(
+                    ?err,
+                    "could not look up the subscriptions of a notification"
+                );
+                continue;
+            }
+        };
+
+        let body = match serde_json::to_vec(&notification) {
+            Ok(body) => Bytes::from(body),
+            Err(err) => {
+                error!(?err, "could not serialize a notification");
+                continue;
+            }
+        };
+
+        // each subscriber gets its own task, such that a slow subscriber does not delay others
+        for url in urls {
+            if let Some(window) = subscriptions.batch_window(notification.object_type, &url) {
+                batch(
+                    &client,
+                    url,
+                    &notification,
+                    window,
+                    &retry,
+                    &batches,
+                    &deliveries,
+                );
+                continue;
+            }
+
+            tokio::spawn(deliver(
+                client.clone(),
+                url,
+                body.clone(),
+                1,
+                retry.clone(),
+                deliveries.clone(),
+            ));
+        }
+    }
+}
+
+/// Add the notification to the batch of its object type and the URL. The first notification
+/// of a batch starts the window, after which the batch is delivered.
+fn batch(
+    client: &reqwest::Client,
+    url: Url,
+    notification: &Notification,
+    window: Duration,
+    retry: &RetryPolicy,
+    batches: &Batches,
+    deliveries: &Deliveries,
+) {
+    let key = (notification.object_type, url.clone());
+    let (client, retry, deliveries) = (client.clone(), retry.clone(), deliveries.clone());
+    batches.push(key, notification.clone(), window, move |batch| async move {
+        let count = batch.len() as u64;
+        match serde_json::to_vec(&batch) {
+            Ok(body) => deliver(client, url, body.into(), count, retry, deliveries).await,
+            Err(err) => error!(?err, "could not serialize a batch of notifications"),
+        }
+    });
+}
+
+/// Post the JSON `body` with `count` notifications to the URL
+async fn deliver(
+    client: reqwest::Client,
+    url: Url,
+    body: Bytes,
+    count: u64,
+    retry: RetryPolicy,
+    deliveries: Deliveries,
+) {
+    let record = |update: &dyn Fn(&mut DeliveryState)| {
+        let mut deliveries = deliveries.lock().unwrap();
+        update(
+            deliveries
+                .entry(url.clone())
+                .or_insert_with(|| DeliveryState::new(&url)),
+        );
+    };
+
+    for attempt in 1..=retry.max_attempts {
+        let result = client
+            .post(url.clone())
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+
+        if let Err(err) = &result {
+            let give_up = attempt == retry.max_attempts;
+            record(&|state| {
+                state.failed_attempts += 1;
+                if give_up {
+                    state.failed += count;
+                }
+                state.last_failed = Some(DeliveryAttempt {
+                    date_time: Utc::now(),
+                    error: Some(err.to_string()),
+                });
+            });
+        }
+
+        match result {
+            Ok(_) => {
+                debug!(%url, attempt, count, "delivered notification");
+                record(&|state| {
+                    state.delivered += count;
+                    state.last_delivered = Some(DeliveryAttempt {
+                        date_time: Utc::now(),
+                        error: None,
+                    });
+                });
+                return;
+            }
+            Err(err) if attempt < retry.max_attempts => {
+                let backoff = retry.backoff(attempt);
+                warn!(%url, attempt, ?backoff, ?err, "could not deliver notification, retrying");
+                tokio::time::sleep(backoff).await;
+            }
+            Err(err) => error!(%url, attempt, ?err, "could not deliver notification, giving up"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use openadr_wire::{program::ProgramContent, Program};
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn parses_static_subscriptions() {
+        let subscriptions = "EVENT=http://localhost/events, REPORT=http://localhost/reports"
+            .parse::<StaticSubscriptions>()
+            .unwrap();
+        assert_eq!(subscriptions.0.len(), 2);
+        assert_eq!(
+            subscriptions.0[0].object_type,
+            NotificationObjectType::Event
+        );
+        assert_eq!(subscriptions.0[1].url.path(), "/reports");
+        assert_eq!(subscriptions.0[1].batch_window, None);
+
+        let batched = "EVENT=http://localhost/events;batch=2.5"
+            .parse::<StaticSubscriptions>()
+            .unwrap();
+        assert_eq!(batched.0[0].url.path(), "/events");
+        assert_eq!(
+            batched.batch_window(NotificationObjectType::Event, &batched.0[0].url),
+            Some(Duration::from_millis(2500))
+        );
+
+        // the window is that of the subscription to the object type
+        let mixed = "EVENT=http://localhost/changes;batch=10, PROGRAM=http://localhost/changes"
+            .parse::<StaticSubscriptions>()
+            .unwrap();
+        let url = &mixed.0[0].url;
+        assert_eq!(
+            mixed.batch_window(NotificationObjectType::Event, url),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            mixed.batch_window(NotificationObjectType::Program, url),
+            None
+        );
+        assert!("EVENT=http://localhost/events;batch=0"
+            .parse::<StaticSubscriptions>()
+            .is_err());
+        assert!("EVENT=http://localhost/events;batch=soon"
+            .parse::<StaticSubscriptions>()
+            .is_err());
+
+        assert!("EVENT".parse::<StaticSubscriptions>().is_err());
+        assert!("THING=http://localhost"
+            .parse::<StaticSubscriptions>()
+            .is_err());
+        assert!("EVENT=not a url".parse::<StaticSubscriptions>().is_err());
+    }
+
+    #[test]
+    fn backoff_doubles_until_max() {
+        let retry = RetryPolicy::default();
+        assert_eq!(retry.backoff(1), Duration::from_secs(1));
+        assert_eq!(retry.backoff(4), Duration::from_secs(8));
+        assert_eq!(retry.backoff(20), Duration::from_secs(300));
+        assert_eq!(retry.backoff(u32::MAX), Duration::from_secs(300));
+    }
+
+    #[derive(Clone, Default)]
+    struct Subscriber {
+        attempts: Arc<Mutex<Vec<Notification>>>,
+    }
+
+    /// Fails the first attempt, to test the retries
+    async fn callback(
+        State(subscriber): State<Subscriber>,
+        Json(notification): Json<Notification>,
+    ) -> StatusCode {
+        let mut attempts = subscriber.attempts.lock().unwrap();
+        attempts.push(notification);
+        if attempts.len() == 1 {
+            StatusCode::SERVICE_UNAVAILABLE
+        } else {
+            StatusCode::OK
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_with_retries() {
+        let subscriber = Subscriber::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route("/programs", post(callback))
+            .with_state(subscriber.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let subscriptions = format!("PROGRAM=http://{addr}/programs,EVENT=http://{addr}/events")
+            .parse::<StaticSubscriptions>()
+            .unwrap();
+        let notifier = Notifier::spawn(
+            Arc::new(subscriptions),
+            RetryPolicy {
+                initial_backoff: Duration::from_millis(10),
+                ..Default::default()
+            },
+        );
+
+        let program = Program {
+            id: "program-1".parse().unwrap(),
+            created_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            modification_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            content: ProgramContent::new("program-1"),
+        };
+        send(Some(&notifier), Operation::Update, &program);
+
+        // the subscriber sees the retry before the notifier records its success
+        for _ in 0..100 {
+            if notifier
+                .deliveries()
+                .first()
+                .is_some_and(|delivery| delivery.delivered > 0)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let attempts = subscriber.attempts.lock().unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert_eq!(attempts[0], attempts[1]);
+        assert_eq!(attempts[1].operation, NotificationOperation::Put);
+        assert_eq!(
+            attempts[1].object,
+            NotificationObject::Program(Box::new(program))
+        );
+        drop(attempts);
+
+        let deliveries = notifier.deliveries();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].url, format!("http://{addr}/programs"));
+        assert_eq!(deliveries[0].delivered, 1);
+        assert_eq!(deliveries[0].failed, 0);
+        assert_eq!(deliveries[0].failed_attempts, 1);
+        assert!(deliveries[0].last_failed.as_ref().unwrap().error.is_some());
+    }
+
+    #[tokio::test]
+    async fn delivers_batches() {
+        let batches = Arc::new(Mutex::new(Vec::<Vec<Notification>>::new()));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Router::new()
+            .route(
+                "/programs",
+                post(
+                    |State(batches): State<Arc<Mutex<Vec<Vec<Notification>>>>>,
+                     Json(batch): Json<Vec<Notification>>| async move {
+                        batches.lock().unwrap().push(batch);
+                    },
+                ),
+            )
+            .with_state(batches.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let subscriptions = format!("PROGRAM=http://{addr}/programs;batch=0.2")
+            .parse::<StaticSubscriptions>()
+            .unwrap();
+        let notifier = Notifier::spawn(Arc::new(subscriptions), RetryPolicy::default());
+
+        let programs: Vec<_> = (1..=3)
+            .map(|i| Program {
+                id: format!("program-{i}").parse().unwrap(),
+                created_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+                modification_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+                content: ProgramContent::new(format!("program-{i}")),
+            })
+            .collect();
+        for program in &programs {
+            send(Some(&notifier), Operation::Create, program);
+        }
+
+        // the delivery is recorded only once the response of the subscriber is received
+        for _ in 0..100 {
+            if notifier
+                .deliveries()
+                .first()
+                .is_some_and(|delivery| delivery.delivered > 0)
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.len(), 1);
+        let ids: Vec<_> = batches[0]
+            .iter()
+            .map(|notification| match &notification.object {
+                NotificationObject::Program(program) => program.id.to_string(),
+                object => panic!("unexpected {object:?}"),
+            })
+            .collect();
+        assert_eq!(ids, ["program-1", "program-2", "program-3"]);
+        assert_eq!(notifier.deliveries()[0].delivered, 3);
+    }
+}
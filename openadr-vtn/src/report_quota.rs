@@ -0,0 +1,258 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::Duration,
+};
+
+use openadr_wire::{program::ProgramId, report::ReportContent, ven::VenId};
+use serde::Deserialize;
+use tokio::time::Instant;
+
+use crate::{error::AppError, metrics::metrics};
+
+/// The window in which [`ReportQuota::max_reports_per_hour`] is enforced
+const WINDOW: Duration = Duration::from_secs(3600);
+
+/// Limits the reports a single VEN can submit to a program,
+/// such that a misbehaving VEN cannot flood the report store
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportQuota {
+    /// The number of reports a VEN can create within any hour
+    pub max_reports_per_hour: Option<usize>,
+    /// The number of intervals a report may contain, summed over all its resources
+    pub max_intervals_per_report: Option<usize>,
+}
+
+/// The [`ReportQuota`]s of all programs.
+///
+/// The reports created per hour are counted per program and per VEN,
+/// within this VTN process only.
+/// A report created by a client acting for multiple VENs counts toward the quota of each of them.
+/// Like the created reports, rejected attempts to create a report count toward the quota.
+#[derive(Debug, Default)]
+pub struct ReportQuotas {
+    default: ReportQuota,
+    programs: HashMap<ProgramId, ReportQuota>,
+    created: Mutex<Created>,
+}
+
+/// The reports created within the last [`WINDOW`]
+#[derive(Debug, Default)]
+struct Created {
+    reports: HashMap<(ProgramId, VenId), VecDeque<Instant>>,
+    /// When the windows of VENs that stopped reporting were last evicted
+    evicted: Option<Instant>,
+}
+
+impl Created {
+    fn prune(recent: &mut VecDeque<Instant>, now: Instant) {
+        while recent
+            .front()
+            .is_some_and(|created| now.duration_since(*created) >= WINDOW)
+        {
+            recent.pop_front();
+        }
+    }
+
+    /// Remove the windows without any recent report, at most once per [`WINDOW`]
+    fn evict_expired(&mut self, now: Instant) {
+        if self
+            .evicted
+            .is_some_and(|evicted| now.duration_since(evicted) < WINDOW)
+        {
+            return;
+        }
+        self.evicted = Some(now);
+
+        self.reports.retain(|_, recent| {
+            Self::prune(recent, now);
+            !recent.is_empty()
+        });
+    }
+}
+
+impl ReportQuotas {
+    /// Apply the quota to all programs without a quota of their own
+    pub fn new(default: ReportQuota) -> Self {
+        Self {
+            default,
+            ..Default::default()
+        }
+    }
+
+    /// Apply a different quota to the reports of a specific program
+    pub fn with_program(mut self, program_id: ProgramId, quota: ReportQuota) -> Self {
+        self.programs.insert(program_id, quota);
+        self
+    }
+
+    pub fn quota(&self, program_id: &ProgramId) -> ReportQuota {
+        self.programs
+            .get(program_id)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Check the number of intervals of a new or updated report
+    pub(crate) fn check_intervals(&self, report: &ReportContent) -> Result<(), AppError> {
+        let Some(max) = self.quota(&report.program_id).max_intervals_per_report else {
+            return Ok(());
+        };
+
+        let intervals = report
+            .resources
+            .iter()
+            .map(|resource| resource.intervals.len())
+            .sum::<usize>();
+
+        if intervals > max {
+            metrics().record_report_quota_rejection();
+            return Err(AppError::TooManyIntervals(max));
+        }
+
+        Ok(())
+    }
+
+    /// Count a report the VENs are about to create, unless it exceeds the hourly quota of any of them
+    pub(crate) fn register_report(
+        &self,
+        ven_ids: &[VenId],
+        report: &ReportContent,
+    ) -> Result<(), AppError> {
+        let Some(max) = self.quota(&report.program_id).max_reports_per_hour else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let mut created = self.created.lock().unwrap();
+        created.evict_expired(now);
+
+        // the earliest moment a report leaves the window of every VEN that exceeded its quota
+        let mut retry_after = None;
+        for ven_id in ven_ids {
+            let Some(recent) = created
+                .reports
+                .get_mut(&(report.program_id.clone(), ven_id.clone()))
+            else {
+                continue;
+            };
+            Created::prune(recent, now);
+
+            if recent.len() >= max {
+                let wait = recent
+                    .front()
+                    .map_or(WINDOW, |oldest| WINDOW - now.duration_since(*oldest));
+                retry_after = retry_after.max(Some(wait));
+            }
+        }
+
+        if let Some(retry_after) = retry_after {
+            metrics().record_report_quota_rejection();
+            // rounded up to whole seconds
+            return Err(AppError::TooManyRequests(
+                "The VEN exceeded the number of reports it can create per hour",
+                retry_after.as_secs() + 1,
+            ));
+        }
+
+        for ven_id in ven_ids {
+            created
+                .reports
+                .entry((report.program_id.clone(), ven_id.clone()))
+                .or_default()
+                .push_back(now);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use openadr_wire::report::{ReportResource, ResourceName};
+
+    fn report(program_id: &str, intervals: usize) -> ReportContent {
+        let resource = ReportResource::new(ResourceName::Private("resource-1".to_string()));
+        ReportContent {
+            object_type: None,
+            program_id: ProgramId::new(program_id).unwrap(),
+            event_id: "event-1".parse().unwrap(),
+            client_name: "client".to_string(),
+            report_name: None,
+            payload_descriptors: None,
+            resources: vec![ReportResource {
+                intervals: vec![Default::default(); intervals],
+                ..resource
+            }],
+        }
+    }
+
+    #[test]
+    fn intervals() {
+        let quotas = ReportQuotas::new(ReportQuota {
+            max_intervals_per_report: Some(2),
+            ..Default::default()
+        })
+        .with_program(ProgramId::new("program-2").unwrap(), ReportQuota::default());
+
+        assert!(quotas.check_intervals(&report("program-1", 2)).is_ok());
+        assert!(matches!(
+            quotas.check_intervals(&report("program-1", 3)),
+            Err(AppError::TooManyIntervals(2))
+        ));
+        assert!(quotas.check_intervals(&report("program-2", 3)).is_ok());
+    }
+
+    fn vens(ids: &[&str]) -> Vec<VenId> {
+        ids.iter().map(|id| id.parse().unwrap()).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_per_hour() {
+        let quotas = ReportQuotas::new(ReportQuota {
+            max_reports_per_hour: Some(2),
+            ..Default::default()
+        });
+        let report = report("program-1", 0);
+        let ven_1 = vens(&["ven-1"]);
+
+        assert!(quotas.register_report(&ven_1, &report).is_ok());
+        tokio::time::advance(Duration::from_secs(1800)).await;
+        assert!(quotas.register_report(&ven_1, &report).is_ok());
+        assert!(matches!(
+            quotas.register_report(&ven_1, &report),
+            Err(AppError::TooManyRequests(_, 1801))
+        ));
+
+        // other VENs have a quota of their own
+        assert!(quotas.register_report(&vens(&["ven-2"]), &report).is_ok());
+        // a client acting for multiple VENs is limited by each of their quotas
+        assert!(matches!(
+            quotas.register_report(&vens(&["ven-2", "ven-1"]), &report),
+            Err(AppError::TooManyRequests(_, 1801))
+        ));
+
+        tokio::time::advance(Duration::from_secs(1800)).await;
+        assert!(quotas.register_report(&ven_1, &report).is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn evicts_expired_windows() {
+        let quotas = ReportQuotas::new(ReportQuota {
+            max_reports_per_hour: Some(2),
+            ..Default::default()
+        });
+        let report = report("program-1", 0);
+
+        assert!(quotas.register_report(&vens(&["ven-1"]), &report).is_ok());
+        tokio::time::advance(WINDOW).await;
+        assert!(quotas.register_report(&vens(&["ven-2"]), &report).is_ok());
+
+        let created = quotas.created.lock().unwrap();
+        assert_eq!(created.reports.len(), 1);
+        assert!(created
+            .reports
+            .contains_key(&(report.program_id.clone(), "ven-2".parse().unwrap())));
+    }
+}
@@ -0,0 +1,58 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use openadr_wire::Event;
+
+/// Signs events as JWS, such that VENs can prove the authenticity of the events they received,
+/// even if TLS is terminated before reaching the VTN.
+///
+/// The signature is a JWS in compact serialization with the event as payload.
+/// It is sent in the [`EVENT_SIGNATURE_HEADER`](openadr_wire::event::EVENT_SIGNATURE_HEADER)
+/// header of responses containing a single event.
+pub struct EventSigner {
+    header: Header,
+    encoding_key: EncodingKey,
+}
+
+impl EventSigner {
+    /// Create a new event signer using a specific algorithm and key
+    pub fn new(algorithm: Algorithm, encoding_key: EncodingKey) -> Self {
+        Self {
+            header: Header::new(algorithm),
+            encoding_key,
+        }
+    }
+
+    /// Create a new event signer from a PEM encoded private key.
+    /// The type of key is determined by the algorithm.
+    pub fn from_pem(algorithm: Algorithm, pem: &[u8]) -> Result<Self, jsonwebtoken::errors::Error> {
+        let encoding_key = match algorithm {
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => EncodingKey::from_rsa_pem(pem)?,
+            Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(pem)?,
+            Algorithm::EdDSA => EncodingKey::from_ed_pem(pem)?,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                return Err(jsonwebtoken::errors::ErrorKind::InvalidAlgorithm.into())
+            }
+        };
+
+        Ok(Self::new(algorithm, encoding_key))
+    }
+
+    /// Set the key id (`kid`) in the JWS header, such that verifiers can select the right key
+    pub fn with_key_id(mut self, key_id: impl ToString) -> Self {
+        self.header.kid = Some(key_id.to_string());
+        self
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.header.alg
+    }
+
+    /// Sign an event, returning the JWS in compact serialization
+    pub fn sign(&self, event: &Event) -> Result<String, jsonwebtoken::errors::Error> {
+        encode(&self.header, event, &self.encoding_key)
+    }
+}
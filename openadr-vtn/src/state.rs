@@ -1,45 +1,205 @@
 use crate::{
+    certification::{self, Certification, Vector},
+    change_log::ChangeLog,
+    changes::ChangeNotifier,
     data_source::{
         AuthSource, DataSource, EventCrud, ProgramCrud, ReportCrud, ResourceCrud, VenCrud,
     },
-    error::AppError,
+    error::{handle_panic, AppError},
     jwt::JwtManager,
+    maintenance::{self, Maintenance},
+    notifier::Notifier,
+    report_quota::ReportQuotas,
+    signing::EventSigner,
+    target_labels::TargetLabelRegistry,
 };
 use axum::{
     extract::{FromRef, Request},
+    http::{header, HeaderName, HeaderValue, Method},
     middleware,
     middleware::Next,
     response::IntoResponse,
     routing::{delete, get, post},
 };
+use openadr_wire::{CHANGE_VERSION_HEADER, TOTAL_COUNT_HEADER};
 use reqwest::StatusCode;
 use std::sync::Arc;
-use tower_http::trace::TraceLayer;
+use tower_http::{
+    catch_panic::CatchPanicLayer,
+    cors::{AllowOrigin, CorsLayer},
+    sensitive_headers::SetSensitiveHeadersLayer,
+    trace::{DefaultOnResponse, TraceLayer},
+    LatencyUnit,
+};
+use tracing::{field, info_span, Level, Span};
 
-use crate::api::{auth, event, program, report, resource, user, ven};
+use crate::api::{
+    auth, capabilities, certification as certification_api, change_log as change_log_api,
+    event::{self, IntervalOrderPolicy, MaterializeProgramDefaults},
+    jwt_keys, maintenance as maintenance_api, media_type, notifications as notifications_api,
+    program, report, resource, search, stats as stats_api, user, ven, PageSize, ReportSizeLimit,
+};
 
 #[derive(Clone, FromRef)]
 pub struct AppState {
     pub storage: Arc<dyn DataSource>,
+    /// Authenticates clients instead of the users in the `storage`, if set
+    pub auth_source: Option<Arc<dyn AuthSource>>,
     pub jwt_manager: Arc<JwtManager>,
+    pub event_signer: Option<Arc<EventSigner>>,
+    pub target_labels: Arc<TargetLabelRegistry>,
+    pub event_changes: Arc<ChangeNotifier>,
+    /// Records all changes to objects, if set
+    pub change_log: Option<Arc<dyn ChangeLog>>,
+    /// Notifies subscribers of all changes to objects, if set
+    pub notifier: Option<Arc<Notifier>>,
+    pub interval_order: IntervalOrderPolicy,
+    pub program_defaults: MaterializeProgramDefaults,
+    pub report_size_limit: ReportSizeLimit,
+    pub page_size: PageSize,
+    pub maintenance: Arc<Maintenance>,
+    pub report_quotas: Arc<ReportQuotas>,
+    pub certification: Arc<Certification>,
+    /// Allows browser applications of these origins to call the VTN, if set
+    #[from_ref(skip)]
+    pub cors: Option<CorsLayer>,
+    #[cfg(feature = "chaos")]
+    #[from_ref(skip)]
+    pub chaos: Option<Arc<crate::chaos::Chaos>>,
 }
 
 impl AppState {
     pub fn new<S: DataSource>(storage: S, jwt_manager: JwtManager) -> Self {
         Self {
             storage: Arc::new(storage),
+            auth_source: None,
             jwt_manager: Arc::new(jwt_manager),
+            event_signer: None,
+            target_labels: Default::default(),
+            event_changes: Default::default(),
+            change_log: None,
+            notifier: None,
+            interval_order: Default::default(),
+            program_defaults: Default::default(),
+            report_size_limit: Default::default(),
+            page_size: Default::default(),
+            maintenance: Default::default(),
+            report_quotas: Default::default(),
+            certification: Default::default(),
+            cors: None,
+            #[cfg(feature = "chaos")]
+            chaos: None,
         }
     }
 
-    fn router_without_state() -> axum::Router<Self> {
-        axum::Router::new()
+    /// Authenticate clients with another source than the users in the storage,
+    /// e.g., a [`DirectoryAuthSource`](crate::data_source::directory::DirectoryAuthSource)
+    pub fn with_auth_source<A: AuthSource>(mut self, auth_source: A) -> Self {
+        self.auth_source = Some(Arc::new(auth_source));
+        self
+    }
+
+    /// Record all changes made to objects through the API, see [`ChangeLog`]
+    pub fn with_change_log(mut self, change_log: Arc<dyn ChangeLog>) -> Self {
+        self.change_log = Some(change_log);
+        self
+    }
+
+    /// Send notifications of changes made through the API to subscribers, see [`Notifier`]
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(Arc::new(notifier));
+        self
+    }
+
+    /// Sign all events sent in single-event responses with the given signer
+    pub fn with_event_signer(mut self, event_signer: EventSigner) -> Self {
+        self.event_signer = Some(Arc::new(event_signer));
+        self
+    }
+
+    /// Restrict the target labels accepted on programs, events, VENs and resources
+    pub fn with_target_labels(mut self, target_labels: TargetLabelRegistry) -> Self {
+        self.target_labels = Arc::new(target_labels);
+        self
+    }
+
+    /// Whether to warn about or reject events with intervals that are not ordered by start
+    pub fn with_interval_order(mut self, interval_order: IntervalOrderPolicy) -> Self {
+        self.interval_order = interval_order;
+        self
+    }
+
+    /// Store events with the defaults of their program filled in, see [`MaterializeProgramDefaults`]
+    pub fn with_materialized_program_defaults(mut self) -> Self {
+        self.program_defaults = MaterializeProgramDefaults(true);
+        self
+    }
+
+    /// The maximum size of a report body in bytes, see [`ReportSizeLimit`]
+    pub fn with_report_size_limit(mut self, limit: usize) -> Self {
+        self.report_size_limit = ReportSizeLimit(limit);
+        self
+    }
+
+    /// The default and maximum `limit` of the list endpoints, see [`PageSize`]
+    pub fn with_page_size(mut self, page_size: PageSize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Limit the reports VENs can submit, see [`ReportQuotas`]
+    pub fn with_report_quotas(mut self, report_quotas: ReportQuotas) -> Self {
+        self.report_quotas = Arc::new(report_quotas);
+        self
+    }
+
+    /// Replay canned responses while certification mode is enabled, see [`Certification`]
+    pub fn with_certification_vectors(mut self, vectors: Vec<Vector>) -> Self {
+        self.certification = Arc::new(Certification::new(vectors));
+        self
+    }
+
+    /// Allow browser applications of the `origins` to call the VTN
+    pub fn with_cors_origins(mut self, origins: Vec<HeaderValue>) -> Self {
+        self.cors = Some(
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::list(origins))
+                .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+                .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+                .expose_headers([
+                    header::RETRY_AFTER,
+                    HeaderName::from_bytes(TOTAL_COUNT_HEADER.as_bytes())
+                        .expect("valid header name"),
+                    HeaderName::from_bytes(CHANGE_VERSION_HEADER.as_bytes())
+                        .expect("valid header name"),
+                ]),
+        );
+        self
+    }
+
+    /// Inject faults for resilience testing, see [`Chaos`](crate::chaos::Chaos)
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: crate::chaos::Chaos) -> Self {
+        let chaos = Arc::new(chaos);
+        self.event_changes = Arc::new(self.event_changes.with_chaos(chaos.clone()));
+        self.chaos = Some(chaos);
+        self
+    }
+
+    fn router_without_state(&self) -> axum::Router<Self> {
+        let router = axum::Router::new()
             .route("/programs", get(program::get_all).post(program::add))
             .route(
                 "/programs/:id",
                 get(program::get).put(program::edit).delete(program::delete),
             )
+            .route(
+                "/programs/:id/vens",
+                get(program::get_vens).post(program::assign_ven),
+            )
+            .route("/programs/:id/vens/:ven_id", delete(program::unassign_ven))
             .route("/reports", get(report::get_all).post(report::add))
+            .route("/reports/operating-states", get(report::operating_states))
             .route(
                 "/reports/:id",
                 get(report::get).put(report::edit).delete(report::delete),
@@ -64,7 +224,31 @@ impl AppState {
                     .put(resource::edit)
                     .delete(resource::delete),
             )
+            .route(
+                "/vens/:ven_id/resources/:id/reports/latest",
+                get(resource::latest_reports),
+            )
+            .route("/search", get(search::search))
             .route("/auth/token", post(auth::token))
+            .route("/auth/whoami", get(auth::whoami))
+            .route(
+                "/admin/jwt-keys",
+                get(jwt_keys::get_all).post(jwt_keys::add),
+            )
+            .route("/admin/jwt-keys/:kid", delete(jwt_keys::retire))
+            .route("/admin/jwt-keys/:kid/activate", post(jwt_keys::activate))
+            .route("/admin/changes", get(change_log_api::get_all))
+            .route("/admin/stats", get(stats_api::get))
+            .route("/admin/notifications", get(notifications_api::get_all))
+            .route(
+                "/admin/maintenance",
+                get(maintenance_api::get).put(maintenance_api::edit),
+            )
+            .route(
+                "/admin/certification",
+                get(certification_api::get).put(certification_api::edit),
+            )
+            .route("/.well-known/openadr", get(capabilities::get))
             .route("/users", get(user::get_all).post(user::add_user))
             .route(
                 "/users/:id",
@@ -77,15 +261,77 @@ impl AppState {
                 "/users/:user_id/:client_id",
                 delete(user::delete_credential),
             )
+            // only applies to the routes above, as the admin UI responds with HTML
+            .layer(middleware::from_fn(media_type::negotiate_response_type));
+
+        #[cfg(feature = "admin-ui")]
+        let router = router.route("/admin/ui", get(crate::api::admin_ui::index));
+
+        #[cfg(feature = "chaos")]
+        let router = match &self.chaos {
+            Some(chaos) => router.layer(middleware::from_fn_with_state(
+                chaos.clone(),
+                crate::chaos::inject_faults,
+            )),
+            None => router,
+        };
+
+        let router = router
+            .layer(middleware::from_fn_with_state(
+                self.certification.clone(),
+                certification::replay_vectors,
+            ))
+            .layer(middleware::from_fn_with_state(
+                self.maintenance.clone(),
+                maintenance::reject_during_maintenance,
+            ))
             .layer(middleware::from_fn(method_not_allowed))
-            .layer(TraceLayer::new_for_http())
+            .layer(CatchPanicLayer::custom(handle_panic))
+            .layer(
+                TraceLayer::new_for_http()
+                    .make_span_with(make_request_span)
+                    .on_response(
+                        DefaultOnResponse::new()
+                            .level(Level::INFO)
+                            .latency_unit(LatencyUnit::Millis),
+                    ),
+            )
+            .layer(SetSensitiveHeadersLayer::new([
+                header::AUTHORIZATION,
+                header::COOKIE,
+                header::SET_COOKIE,
+            ]));
+
+        // outermost, such that preflight requests are answered regardless of maintenance
+        match &self.cors {
+            Some(cors) => router.layer(cors.clone()),
+            None => router,
+        }
     }
 
     pub fn into_router(self) -> axum::Router {
-        Self::router_without_state().with_state(self)
+        self.router_without_state().with_state(self)
     }
 }
 
+/// Only the method and path are logged, as query parameters, headers and bodies may contain secrets.
+/// The `client_id` is filled in as soon as the request is authenticated.
+///
+/// With the `otel` feature, the span continues the trace of a W3C `traceparent` header of the request.
+fn make_request_span(request: &Request) -> Span {
+    let span = info_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        client_id = field::Empty,
+    );
+
+    #[cfg(feature = "otel")]
+    crate::telemetry::continue_trace(&span, request.headers());
+
+    span
+}
+
 pub async fn method_not_allowed(req: Request, next: Next) -> impl IntoResponse {
     let resp = next.run(req).await;
     let status = resp.status();
@@ -97,7 +343,10 @@ pub async fn method_not_allowed(req: Request, next: Next) -> impl IntoResponse {
 
 impl FromRef<AppState> for Arc<dyn AuthSource> {
     fn from_ref(state: &AppState) -> Arc<dyn AuthSource> {
-        state.storage.auth()
+        state
+            .auth_source
+            .clone()
+            .unwrap_or_else(|| state.storage.auth())
     }
 }
 
@@ -0,0 +1,56 @@
+//! Statistics of the stored objects, such that operators can watch the growth of a VTN
+//! without direct access to its database, see `GET /admin/stats`
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub objects: ObjectCounts,
+    /// The number of events of every program, ordered by program name
+    pub events_per_program: Vec<ProgramEvents>,
+    /// The number of reports created per day, for the days on which any report was created
+    pub report_volume: Vec<ReportVolume>,
+    /// The size of the database in bytes, if the storage can tell
+    pub database_size: Option<i64>,
+}
+
+/// The number of stored objects of every type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObjectCounts {
+    pub programs: i64,
+    pub events: i64,
+    pub reports: i64,
+    pub vens: i64,
+    pub resources: i64,
+    pub users: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramEvents {
+    #[serde(rename = "programID")]
+    pub program_id: String,
+    pub program_name: String,
+    pub events: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportVolume {
+    /// The start of the day, in UTC
+    #[serde(with = "openadr_wire::serde_rfc3339")]
+    pub day: DateTime<Utc>,
+    pub reports: i64,
+}
+
+#[async_trait]
+pub trait StatsSource: Send + Sync + 'static {
+    /// The statistics of all stored objects, with the report volume of the days since `since`
+    async fn stats(&self, since: DateTime<Utc>) -> Result<Stats, AppError>;
+}
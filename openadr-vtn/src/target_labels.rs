@@ -0,0 +1,116 @@
+use std::collections::BTreeSet;
+
+use openadr_wire::{
+    target::{TargetLabel, TargetMap},
+    values_map::ValuesMap,
+};
+
+use crate::error::AppError;
+
+/// Declares which target labels a VTN accepts on programs, events, VENs and resources.
+///
+/// The labels defined by the OpenADR specification are always accepted.
+/// By default, any private label is accepted as well,
+/// but deployments can restrict these to an explicit allowlist.
+#[derive(Debug, Clone, Default)]
+pub struct TargetLabelRegistry {
+    allowed_private: Option<BTreeSet<String>>,
+}
+
+impl TargetLabelRegistry {
+    /// Accept the standard target labels and any private target label
+    pub fn allow_any() -> Self {
+        Self::default()
+    }
+
+    /// Accept the standard target labels and only the given private target labels
+    pub fn allow_only<I, S>(private_labels: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed_private: Some(private_labels.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// Whether private labels are restricted to an allowlist
+    pub fn is_restricted(&self) -> bool {
+        self.allowed_private.is_some()
+    }
+
+    pub fn is_allowed(&self, label: &TargetLabel) -> bool {
+        match (label, &self.allowed_private) {
+            (TargetLabel::Private(label), Some(allowed)) => allowed.contains(label),
+            _ => true,
+        }
+    }
+
+    /// The standard labels, followed by the allowed private labels
+    pub fn labels(&self) -> Vec<TargetLabel> {
+        TargetLabel::STANDARD
+            .into_iter()
+            .chain(
+                self.allowed_private
+                    .iter()
+                    .flatten()
+                    .map(|label| TargetLabel::Private(label.clone())),
+            )
+            .collect()
+    }
+
+    fn validate<'a>(
+        &self,
+        labels: impl IntoIterator<Item = &'a TargetLabel>,
+    ) -> Result<(), AppError> {
+        match labels.into_iter().find(|label| !self.is_allowed(label)) {
+            Some(label) => Err(AppError::TargetLabelNotAllowed(label.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn validate_target_map(&self, targets: Option<&TargetMap>) -> Result<(), AppError> {
+        self.validate(
+            targets
+                .iter()
+                .flat_map(|targets| &targets.0)
+                .map(|entry| &entry.label),
+        )
+    }
+
+    /// VENs and resources use a list of [`ValuesMap`] to describe their targets
+    pub(crate) fn validate_values_maps(
+        &self,
+        targets: Option<&[ValuesMap]>,
+    ) -> Result<(), AppError> {
+        let labels = targets
+            .unwrap_or_default()
+            .iter()
+            .map(|target| TargetLabel::from(target.value_type.0.as_str()))
+            .collect::<Vec<_>>();
+
+        self.validate(&labels)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn allowlist() {
+        let registry = TargetLabelRegistry::allow_only(["METER_ID"]);
+
+        assert!(registry.is_allowed(&TargetLabel::Group));
+        assert!(registry.is_allowed(&TargetLabel::Private("METER_ID".to_string())));
+        assert!(!registry.is_allowed(&TargetLabel::Private("FEEDER".to_string())));
+        assert_eq!(
+            registry.labels().last(),
+            Some(&TargetLabel::Private("METER_ID".to_string()))
+        );
+
+        let any = TargetLabelRegistry::allow_any();
+        assert!(any.is_allowed(&TargetLabel::Private("FEEDER".to_string())));
+        assert_eq!(any.labels(), TargetLabel::STANDARD);
+    }
+}
@@ -0,0 +1,117 @@
+//! Tracing with OpenTelemetry, only available with the `otel` feature.
+//!
+//! The spans of the VTN are exported to an OTLP collector, and requests with a W3C `traceparent`
+//! header continue the trace of the client. As the queries of the storage run in the span of
+//! the request, a request can be traced from the client, e.g., a VEN, through the VTN into the database.
+
+use axum::http::HeaderMap;
+use opentelemetry::{
+    propagation::TextMapPropagator,
+    trace::{TraceContextExt, TracerProvider as _},
+    KeyValue,
+};
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator, runtime, trace::TracerProvider, Resource,
+};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// The service name of the spans, unless set with `OTEL_SERVICE_NAME`
+const SERVICE_NAME: &str = "openadr-vtn";
+
+/// Exports spans in batches to the OTLP/HTTP collector at `OTEL_EXPORTER_OTLP_ENDPOINT`,
+/// or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`, if either is set.
+///
+/// Shut the provider down before the VTN stops, to export the spans that are still queued.
+pub fn tracer_provider_from_env() -> Result<Option<TracerProvider>, opentelemetry::trace::TraceError>
+{
+    let configured = [
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+    ]
+    .into_iter()
+    .any(|name| std::env::var_os(name).is_some());
+    if !configured {
+        return Ok(None);
+    }
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .build()?;
+    let service_name =
+        std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| SERVICE_NAME.to_string());
+    let resource = Resource::default().merge(&Resource::new([KeyValue::new(
+        "service.name",
+        service_name,
+    )]));
+
+    Ok(Some(
+        TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(resource)
+            .build(),
+    ))
+}
+
+/// A layer of the tracing subscriber, exporting the spans with the `provider`
+pub fn layer<S>(
+    provider: &TracerProvider,
+) -> OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_opentelemetry::layer().with_tracer(provider.tracer(SERVICE_NAME))
+}
+
+/// Make the span a child of the span of the client, if the request has a valid `traceparent` header
+pub(crate) fn continue_trace(span: &Span, headers: &HeaderMap) {
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+
+    if parent.span().span_context().is_valid() {
+        span.set_parent(parent);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use axum::http::HeaderValue;
+    use opentelemetry::trace::TraceId;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn trace_id(headers: &HeaderMap) -> TraceId {
+        let provider = TracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry().with(layer(&provider));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("request");
+            continue_trace(&span, headers);
+            span.context().span().span_context().trace_id()
+        })
+    }
+
+    #[test]
+    fn continues_trace_of_client() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+        );
+
+        assert_eq!(
+            trace_id(&headers),
+            TraceId::from_hex("0af7651916cd43dd8448eb211c80319c").unwrap()
+        );
+    }
+
+    #[test]
+    fn starts_trace_without_valid_traceparent() {
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", HeaderValue::from_static("invalid"));
+
+        assert_ne!(trace_id(&headers), TraceId::INVALID);
+    }
+}
@@ -0,0 +1,36 @@
+//! Types used by the authentication endpoints of the VTN, besides the [OAuth](crate::oauth) token endpoint
+//!
+//! These endpoints are an extension to the OpenADR specification.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{oauth::Scope, ven::VenId};
+
+/// Path of the endpoint describing the caller, relative to the base URL of the VTN
+pub const WHOAMI_PATH: &str = "auth/whoami";
+
+/// The claims of the token a request was authenticated with, as resolved by the VTN
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhoAmI {
+    /// The client the token was issued to
+    #[serde(rename = "clientID")]
+    pub client_id: String,
+    /// The names of the roles of the client, e.g., `VEN` or `UserManager`
+    pub roles: Vec<String>,
+    /// The VENs the client can act as
+    #[serde(rename = "venIDs")]
+    pub ven_ids: Vec<VenId>,
+    /// Whether the client can access the objects of any business
+    pub any_business: bool,
+    /// The businesses whose objects the client can access, unless it can access any business
+    #[serde(rename = "businessIDs")]
+    pub business_ids: Vec<String>,
+    /// The scopes of the token, which restrict the requests it is accepted for besides the roles
+    #[serde(default)]
+    pub scopes: Vec<Scope>,
+    /// When the token expires
+    #[serde(with = "crate::serde_rfc3339")]
+    pub expires_at: DateTime<Utc>,
+}
@@ -0,0 +1,99 @@
+//! Canonical JSON serialization of wire types
+//!
+//! The regular serialization of the wire types follows the field order of the Rust structs,
+//! which is not guaranteed to be stable between versions of this crate.
+//! The canonical form sorts all object keys recursively and formats numbers in their shortest
+//! round-trip representation, so that the same value always results in the exact same bytes.
+//! This is useful for byte-level comparisons in conformance tests and for signing payloads.
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Serialize a value to its canonical, compact JSON representation
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&canonicalize(serde_json::to_value(value)?))
+}
+
+/// Serialize a value to its canonical JSON representation, pretty-printed for human consumption
+pub fn to_canonical_json_pretty<T: Serialize + ?Sized>(
+    value: &T,
+) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(&canonicalize(serde_json::to_value(value)?))
+}
+
+fn canonicalize(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            // re-inserting in sorted order keeps the keys sorted even if serde_json
+            // is compiled with the `preserve_order` feature
+            let mut sorted = Map::with_capacity(entries.len());
+            for (key, value) in entries {
+                sorted.insert(key, canonicalize(value));
+            }
+
+            Value::Object(sorted)
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(canonicalize).collect()),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Event;
+
+    #[test]
+    fn keys_are_sorted_recursively() {
+        let value = serde_json::json!({
+            "zeta": 1,
+            "alpha": { "delta": [ { "b": true, "a": null } ], "charlie": 1.5 },
+        });
+
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            r#"{"alpha":{"charlie":1.5,"delta":[{"a":null,"b":true}]},"zeta":1}"#
+        );
+    }
+
+    #[test]
+    fn numbers_are_formatted_stably() {
+        let value = serde_json::json!([1, -0.5, 1.0, 0.1]);
+
+        assert_eq!(to_canonical_json(&value).unwrap(), "[1,-0.5,1.0,0.1]");
+    }
+
+    #[test]
+    fn wire_types_round_trip() {
+        let example = r#"{
+            "id": "object-999",
+            "createdDateTime": "2023-06-15T09:30:00Z",
+            "modificationDateTime": "2023-06-15T09:30:00Z",
+            "objectType": "EVENT",
+            "programID": "object-999",
+            "eventName": "price event 11-18-2022",
+            "priority": 0,
+            "targets": null,
+            "reportDescriptors": null,
+            "payloadDescriptors": [{"payloadType": "PRICE", "units": "KWH"}],
+            "intervalPeriod": null,
+            "intervals": [{"id": 0, "intervalPeriod": null, "payloads": [{"type": "PRICE", "values": [0.17]}]}]
+        }"#;
+
+        let event: Event = serde_json::from_str(example).unwrap();
+        let canonical = to_canonical_json(&event).unwrap();
+
+        assert_eq!(serde_json::from_str::<Event>(&canonical).unwrap(), event);
+        assert_eq!(
+            to_canonical_json(&serde_json::from_str::<Event>(&canonical).unwrap()).unwrap(),
+            canonical
+        );
+        assert!(canonical.starts_with(r#"{"createdDateTime":"#));
+
+        let pretty = to_canonical_json_pretty(&event).unwrap();
+        assert_eq!(serde_json::from_str::<Event>(&pretty).unwrap(), event);
+    }
+}
@@ -0,0 +1,108 @@
+//! Types used for the capabilities endpoint
+//!
+//! This endpoint is an extension to the OpenADR specification,
+//! allowing clients to discover which features a VTN supports.
+
+use serde::{Deserialize, Serialize};
+
+use crate::target::TargetLabel;
+
+/// Path of the capabilities document, relative to the base URL of the VTN
+pub const CAPABILITIES_PATH: &str = ".well-known/openadr";
+
+/// Describes the features a VTN supports
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    /// Version of the OpenADR specification implemented by the VTN, e.g., `3.0.1`
+    pub spec_version: String,
+    /// The largest `limit` query parameter the VTN accepts on list endpoints
+    pub max_page_size: usize,
+    /// The `limit` the VTN uses for list requests without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_page_size: Option<usize>,
+    /// The target types the VTN accepts on programs, events, VENs and resources.
+    /// Private target types not in this list are only accepted if the VTN lists
+    /// [`Feature::AnyPrivateTargetType`].
+    pub target_types: Vec<TargetLabel>,
+    /// The transports the VTN can use to deliver notifications to subscribers
+    #[serde(default)]
+    pub notification_transports: Vec<NotificationTransport>,
+    /// Optional features, beyond the OpenADR specification, supported by the VTN
+    #[serde(default)]
+    pub features: Vec<Feature>,
+}
+
+impl Capabilities {
+    /// Whether the VTN accepts objects targeted using this label
+    pub fn supports_target_type(&self, label: &TargetLabel) -> bool {
+        self.target_types.contains(label)
+            || (label.is_private() && self.features.contains(&Feature::AnyPrivateTargetType))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationTransport {
+    /// Notifications are sent as HTTP POST requests to a callback URL
+    Webhook,
+    /// Notifications are published to an MQTT broker
+    #[serde(rename = "MQTT")]
+    MQTT,
+    /// A transport unknown to this version of the library
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Feature {
+    /// The VTN signs events, see [`EVENT_SIGNATURE_HEADER`](crate::event::EVENT_SIGNATURE_HEADER)
+    EventSignatures,
+    /// The VTN accepts any private target type, not just the ones listed in [`Capabilities::target_types`]
+    AnyPrivateTargetType,
+    /// The event list endpoint supports long-polling using the `wait` query parameter, e.g., `wait=30s`
+    LongPolling,
+    /// Events can be updated by sending only the intervals that changed,
+    /// see [`EventDelta`](crate::event::EventDelta)
+    EventDeltas,
+    /// The event list endpoint continues after the event given by the `afterID` and
+    /// `afterModificationDateTime` query parameters, instead of skipping a number of events
+    CursorPagination,
+    /// A feature unknown to this version of the library
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unknown_features() {
+        let example = r#"{
+            "specVersion": "3.0.1",
+            "maxPageSize": 50,
+            "targetTypes": ["EVENT_NAME", "CUSTOM"],
+            "features": ["EVENT_SIGNATURES", "TIME_TRAVEL"]
+        }"#;
+
+        assert_eq!(
+            serde_json::from_str::<Capabilities>(example).unwrap(),
+            Capabilities {
+                spec_version: "3.0.1".to_string(),
+                max_page_size: 50,
+                default_page_size: None,
+                target_types: vec![
+                    TargetLabel::EventName,
+                    TargetLabel::Private("CUSTOM".to_string())
+                ],
+                notification_transports: vec![],
+                features: vec![
+                    Feature::EventSignatures,
+                    Feature::Other("TIME_TRAVEL".to_string())
+                ],
+            }
+        );
+    }
+}
@@ -1,17 +1,22 @@
 //! Types used for the `event/` endpoint
 
 use crate::{
-    interval::IntervalPeriod, program::ProgramId, report::ReportDescriptor, target::TargetMap,
-    values_map::Value, Identifier, IdentifierError, Unit,
+    interval::{validate_interval_ids, IntervalPeriod},
+    program::{PayloadDescriptor, ProgramContent, ProgramId},
+    report::ReportDescriptor,
+    target::TargetMap,
+    values_map::Value,
+    Identifier, IdentifierError, Unit,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::{
     fmt::{Display, Formatter},
+    ops::Range,
     str::FromStr,
 };
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 /// Event object to communicate a Demand Response request to VEN. If intervalPeriod is present, sets
 /// start time and duration of intervals.
@@ -31,6 +36,35 @@ pub struct Event {
     pub content: EventContent,
 }
 
+/// HTTP header in which a VTN can send a JWS signature of the event contained in the response.
+/// This is an extension to the OpenADR specification.
+pub const EVENT_SIGNATURE_HEADER: &str = "X-OpenADR-Event-Signature";
+
+/// Content type of an [`EventDelta`], used to update an event by sending only the intervals that changed.
+/// This is an extension to the OpenADR specification.
+pub const EVENT_DELTA_CONTENT_TYPE: &str = "application/vnd.openadr.event-delta+json";
+
+/// Order of the events returned by the `orderBy` query parameter of the event list endpoint.
+/// This is an extension to the OpenADR specification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventOrder {
+    /// Highest priority first, events with an unspecified priority last.
+    /// Events with the same priority are ordered by their start.
+    Priority,
+    /// Earliest start first, events without a start last
+    Start,
+}
+
+impl EventOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventOrder::Priority => "priority",
+            EventOrder::Start => "start",
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
@@ -48,15 +82,45 @@ pub struct EventContent {
     /// A list of valuesMap objects.
     pub targets: Option<TargetMap>,
     /// A list of reportDescriptor objects. Used to request reports from VEN.
+    #[validate(nested)]
     pub report_descriptors: Option<Vec<ReportDescriptor>>,
     /// A list of payloadDescriptor objects.
     pub payload_descriptors: Option<Vec<EventPayloadDescriptor>>,
     /// Defines default start and durations of intervals.
     pub interval_period: Option<IntervalPeriod>,
     /// A list of interval objects.
+    #[validate(custom(function = "validate_event_interval_ids"))]
     pub intervals: Vec<EventInterval>,
 }
 
+/// A compact update of the intervals of an event, e.g., when a single slot of a day-ahead
+/// schedule changes. Intervals are identified by their [`id`](EventInterval::id).
+///
+/// Sent with the [`EVENT_DELTA_CONTENT_TYPE`] to update an event on a VTN that lists
+/// [`Feature::EventDeltas`](crate::capabilities::Feature::EventDeltas) in its capabilities.
+/// This is an extension to the OpenADR specification.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct EventDelta {
+    /// Intervals replacing the interval with the same id, or added if the event has no such interval
+    #[serde(default)]
+    #[validate(custom(function = "validate_event_interval_ids"))]
+    pub intervals: Vec<EventInterval>,
+    /// Ids of the intervals to remove
+    #[serde(default)]
+    pub removed_intervals: Vec<i32>,
+}
+
+impl EventDelta {
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty() && self.removed_intervals.is_empty()
+    }
+}
+
+fn validate_event_interval_ids(intervals: &[EventInterval]) -> Result<(), ValidationError> {
+    validate_interval_ids(intervals.iter().map(|interval| interval.id))
+}
+
 impl EventContent {
     pub fn new(program_id: ProgramId, intervals: Vec<EventInterval>) -> Self {
         assert!(
@@ -113,6 +177,320 @@ impl EventContent {
         self.intervals = intervals;
         self
     }
+
+    /// The changes to the intervals that turn this event into `new`.
+    ///
+    /// Returns `None` if anything but the intervals differs,
+    /// such that the event must be updated as a whole.
+    pub fn delta_to(&self, new: &EventContent) -> Option<EventDelta> {
+        let without_intervals = |content: &EventContent| EventContent {
+            intervals: vec![],
+            ..content.clone()
+        };
+        if without_intervals(self) != without_intervals(new) {
+            return None;
+        }
+
+        let intervals = new
+            .intervals
+            .iter()
+            .filter(|interval| !self.intervals.contains(interval))
+            .cloned()
+            .collect();
+        let removed_intervals = self
+            .intervals
+            .iter()
+            .filter(|old| !new.intervals.iter().any(|interval| interval.id == old.id))
+            .map(|old| old.id)
+            .collect();
+
+        Some(EventDelta {
+            intervals,
+            removed_intervals,
+        })
+    }
+
+    /// Apply the changes to the intervals of this event.
+    /// Replaced intervals keep their position, added intervals are appended.
+    pub fn apply_delta(&mut self, delta: EventDelta) {
+        self.intervals
+            .retain(|interval| !delta.removed_intervals.contains(&interval.id));
+
+        for interval in delta.intervals {
+            match self
+                .intervals
+                .iter_mut()
+                .find(|existing| existing.id == interval.id)
+            {
+                Some(existing) => *existing = interval,
+                None => self.intervals.push(interval),
+            }
+        }
+    }
+
+    /// Fill in what this event omits with the defaults of its program:
+    /// the [`ProgramContent::default_priority`], the interval period,
+    /// and the payload descriptors of the program that describe event payloads.
+    ///
+    /// Whatever the event specifies itself takes precedence.
+    pub fn with_program_defaults(mut self, program: &ProgramContent) -> Self {
+        self.priority = self.effective_priority(program);
+
+        if self.interval_period.is_none() {
+            self.interval_period.clone_from(&program.interval_period);
+        }
+
+        if self.payload_descriptors.is_none() {
+            let descriptors = program
+                .payload_descriptors
+                .iter()
+                .flatten()
+                .filter_map(|descriptor| match descriptor {
+                    PayloadDescriptor::EventPayloadDescriptor(descriptor) => {
+                        Some(descriptor.clone())
+                    }
+                    PayloadDescriptor::ReportPayloadDescriptor(_) => None,
+                })
+                .collect::<Vec<_>>();
+            self.payload_descriptors = (!descriptors.is_empty()).then_some(descriptors);
+        }
+
+        self
+    }
+
+    /// The descriptor of the payloads of the given type, e.g., with the units of a capacity limit.
+    ///
+    /// A descriptor of the event itself takes precedence over the
+    /// [one of the program](ProgramContent::event_payload_descriptor) with the same payload type.
+    pub fn payload_descriptor<'a>(
+        &'a self,
+        payload_type: &EventType,
+        program: &'a ProgramContent,
+    ) -> Option<&'a EventPayloadDescriptor> {
+        self.payload_descriptors
+            .iter()
+            .flatten()
+            .find(|descriptor| &descriptor.payload_type == payload_type)
+            .or_else(|| program.event_payload_descriptor(payload_type))
+    }
+
+    /// The priority of this event, or the [`ProgramContent::default_priority`] if it has none
+    pub fn effective_priority(&self, program: &ProgramContent) -> Priority {
+        match (self.priority, program.default_priority) {
+            (Priority::UNSPECIFIED, Some(default)) => default,
+            (priority, _) => priority,
+        }
+    }
+
+    /// The time between the start of the first and the end of the last interval
+    /// that is not covered by any interval, in chronological order.
+    ///
+    /// Intervals without a period of their own use the period of the event,
+    /// like in the [`Timeline`](crate::timeline::Timeline).
+    /// Intervals without any period are ignored, and an interval without a duration never ends.
+    pub fn gaps(&self) -> Vec<Range<DateTime<Utc>>> {
+        let mut ranges = self
+            .intervals
+            .iter()
+            .filter_map(|interval| self.interval_span(interval))
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|(start, _)| *start);
+
+        let mut gaps = vec![];
+        let mut ranges = ranges.into_iter();
+        let Some((_, mut covered_until)) = ranges.next() else {
+            return gaps;
+        };
+
+        for (start, end) in ranges {
+            // an interval without a duration covers everything after its start
+            let Some(covered) = covered_until else {
+                break;
+            };
+
+            if covered < start {
+                gaps.push(covered..start);
+            }
+
+            covered_until = end.map(|end| end.max(covered));
+        }
+
+        gaps
+    }
+
+    /// The end of the interval that ends last, i.e., when the event is completed.
+    ///
+    /// `None` if the event never ends, because an interval has no duration,
+    /// or if no interval has a period.
+    pub fn end(&self) -> Option<DateTime<Utc>> {
+        let mut end = None;
+
+        let spans = self
+            .intervals
+            .iter()
+            .filter_map(|interval| self.interval_span(interval));
+
+        for (_, interval_end) in spans {
+            let interval_end = interval_end?;
+            end = Some(end.map_or(interval_end, |end: DateTime<Utc>| end.max(interval_end)));
+        }
+
+        end
+    }
+
+    /// The first interval with an explicit period that starts before the explicit period
+    /// of an interval listed earlier, if any.
+    ///
+    /// Intervals without a period of their own are not considered.
+    pub fn out_of_order_interval(&self) -> Option<&EventInterval> {
+        let mut latest_start = None;
+
+        for interval in &self.intervals {
+            let Some(period) = &interval.interval_period else {
+                continue;
+            };
+
+            if latest_start.is_some_and(|latest| period.start < latest) {
+                return Some(interval);
+            }
+
+            latest_start = Some(period.start);
+        }
+
+        None
+    }
+
+    /// Sort the intervals by their start, such that
+    /// [`out_of_order_interval`](Self::out_of_order_interval) returns `None`.
+    ///
+    /// Intervals without a period of their own use the period of the event.
+    /// The sort is stable, and intervals without any period are moved to the front.
+    pub fn normalize_interval_order(&mut self) {
+        let event_period = self.interval_period.as_ref();
+
+        self.intervals.sort_by_key(|interval| {
+            interval
+                .interval_period
+                .as_ref()
+                .or(event_period)
+                .map(|period| period.start)
+        });
+    }
+
+    /// The start and, if it has a duration, the end of an interval of this event.
+    /// Intervals without a period of their own use the period of the event.
+    fn interval_span(
+        &self,
+        interval: &EventInterval,
+    ) -> Option<(DateTime<Utc>, Option<DateTime<Utc>>)> {
+        let period = interval
+            .interval_period
+            .as_ref()
+            .or(self.interval_period.as_ref())?;
+        let end = period
+            .duration
+            .as_ref()
+            .map(|duration| period.start + duration.to_chrono_at_datetime(period.start));
+
+        Some((period.start, end))
+    }
+
+    /// Render the intervals as a table for humans, one `start → end → values` row per interval.
+    /// Values are followed by their unit, if the payload descriptors specify one.
+    ///
+    /// ```text
+    /// 2024-01-01T00:00:00Z → 2024-01-01T01:00:00Z → PRICE 0.25
+    /// 2024-01-01T01:00:00Z → 2024-01-01T02:00:00Z → PRICE 0.5
+    /// ```
+    pub fn to_table(&self) -> String {
+        let mut table = String::new();
+
+        for interval in &self.intervals {
+            let span = self.interval_span(interval);
+            let values = interval
+                .payloads
+                .iter()
+                .map(|payload| match self.unit_of(&payload.value_type) {
+                    Some(unit) => format!("{payload} {unit}"),
+                    None => payload.to_string(),
+                })
+                .collect::<Vec<_>>();
+
+            table.push_str(&schedule_row(
+                span.map(|(start, _)| start),
+                span.and_then(|(_, end)| end),
+                &values.join("; "),
+            ));
+        }
+
+        table
+    }
+
+    fn unit_of(&self, payload_type: &EventType) -> Option<&Unit> {
+        self.payload_descriptors
+            .iter()
+            .flatten()
+            .find(|descriptor| &descriptor.payload_type == payload_type)?
+            .units
+            .as_ref()
+    }
+}
+
+impl Display for EventContent {
+    /// A one-line summary of the event, e.g. for log messages
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.event_name {
+            Some(name) => write!(f, "event {name:?}")?,
+            None => write!(f, "unnamed event")?,
+        }
+        write!(
+            f,
+            " of program {} with {} interval(s)",
+            self.program_id,
+            self.intervals.len()
+        )?;
+
+        let spans = self
+            .intervals
+            .iter()
+            .filter_map(|interval| self.interval_span(interval));
+        let start = spans.clone().map(|(start, _)| start).min();
+        // an interval without a duration never ends
+        let end = spans
+            .map(|(_, end)| end)
+            .reduce(|a, b| a.zip(b).map(|(a, b)| a.max(b)));
+
+        match (start, end) {
+            (Some(start), Some(Some(end))) => {
+                write!(
+                    f,
+                    " from {} to {}",
+                    fmt_datetime(&start),
+                    fmt_datetime(&end)
+                )
+            }
+            (Some(start), _) => write!(f, " from {}", fmt_datetime(&start)),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+/// Format a timestamp for humans, in UTC with second precision
+pub(crate) fn fmt_datetime(datetime: &DateTime<Utc>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A row of a table rendering a schedule, see [`EventContent::to_table`].
+/// An unknown start is rendered as `?`, and a missing end as `∞`.
+pub(crate) fn schedule_row(
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+    values: &str,
+) -> String {
+    let start = start.map_or_else(|| "?".to_string(), |start| fmt_datetime(&start));
+    let end = end.map_or_else(|| "∞".to_string(), |end| fmt_datetime(&end));
+
+    format!("{start} → {end} → {values}\n")
 }
 
 /// URL safe VTN assigned object ID
@@ -258,6 +636,7 @@ impl EventInterval {
 
 /// Represents one or more values associated with a type. E.g. a type of PRICE contains a single float value.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct EventValuesMap {
     /// Enumerated or private string signifying the nature of values. E.G. \"PRICE\" indicates value is to be interpreted as a currency.
     #[serde(rename = "type")]
@@ -267,6 +646,17 @@ pub struct EventValuesMap {
     pub values: Vec<Value>,
 }
 
+impl Display for EventValuesMap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.value_type)?;
+        for (i, value) in self.values.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{separator}{value}")?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum EventType {
@@ -313,9 +703,19 @@ pub enum EventType {
     Private(String),
 }
 
+impl Display for EventType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        crate::fmt_serialized_name(self, f)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{values_map::Value, Duration};
+    use crate::{
+        report::{ReportPayloadDescriptor, ReportType},
+        values_map::Value,
+        Duration,
+    };
 
     use super::*;
 
@@ -436,4 +836,254 @@ mod tests {
             expected
         );
     }
+
+    fn interval_at(id: i32, start_hour: u32, hours: Option<f32>) -> EventInterval {
+        use chrono::TimeZone;
+
+        EventInterval {
+            id,
+            interval_period: Some(IntervalPeriod {
+                start: Utc.with_ymd_and_hms(2024, 1, 1, start_hour, 0, 0).unwrap(),
+                duration: hours.map(Duration::hours),
+                randomize_start: None,
+            }),
+            payloads: vec![],
+        }
+    }
+
+    #[test]
+    fn interval_ids_are_validated() {
+        let program_id = ProgramId("p".parse().unwrap());
+        let event = |ids: &[i32]| {
+            let intervals = ids
+                .iter()
+                .map(|id| EventInterval::new(*id, vec![]))
+                .collect();
+            EventContent::new(program_id.clone(), intervals)
+        };
+
+        assert!(event(&[0, 2, 1]).validate().is_ok());
+        assert!(event(&[0, -1]).validate().is_err());
+        assert!(event(&[0, 1, 0]).validate().is_err());
+    }
+
+    #[test]
+    fn gaps_between_intervals() {
+        use chrono::TimeZone;
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        let program_id = ProgramId("p".parse().unwrap());
+
+        let event = EventContent::new(
+            program_id.clone(),
+            vec![
+                interval_at(2, 6, Some(1.0)),
+                interval_at(0, 0, Some(2.0)),
+                interval_at(1, 1, Some(2.0)),
+                interval_at(3, 8, Some(1.0)),
+            ],
+        );
+        assert_eq!(event.gaps(), vec![at(3)..at(6), at(7)..at(8)]);
+
+        // an interval without a duration covers the rest of the event
+        let event = EventContent::new(
+            program_id.clone(),
+            vec![
+                interval_at(0, 0, None),
+                interval_at(1, 4, Some(1.0)),
+                interval_at(2, 8, Some(1.0)),
+            ],
+        );
+        assert!(event.gaps().is_empty());
+
+        // intervals default to the period of the event
+        let event = EventContent::new(program_id, vec![EventInterval::new(0, vec![])])
+            .with_interval_period(IntervalPeriod::new(at(0)));
+        assert!(event.gaps().is_empty());
+    }
+
+    #[test]
+    fn end_of_last_interval() {
+        use chrono::TimeZone;
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        let program_id = ProgramId("p".parse().unwrap());
+
+        let event = EventContent::new(
+            program_id.clone(),
+            vec![interval_at(0, 3, Some(2.0)), interval_at(1, 1, Some(1.0))],
+        );
+        assert_eq!(event.end(), Some(at(5)));
+
+        let event = EventContent::new(
+            program_id.clone(),
+            vec![interval_at(0, 0, Some(1.0)), interval_at(1, 1, None)],
+        );
+        assert_eq!(event.end(), None);
+
+        let event = EventContent::new(program_id, vec![EventInterval::new(0, vec![])]);
+        assert_eq!(event.end(), None);
+    }
+
+    #[test]
+    fn program_defaults() {
+        let at = "2024-01-01T00:00:00Z".parse().unwrap();
+        let program = ProgramContent {
+            interval_period: Some(IntervalPeriod::new(at)),
+            payload_descriptors: Some(vec![
+                PayloadDescriptor::ReportPayloadDescriptor(ReportPayloadDescriptor::new(
+                    ReportType::Usage,
+                )),
+                PayloadDescriptor::EventPayloadDescriptor(EventPayloadDescriptor::new(
+                    EventType::Price,
+                )),
+            ]),
+            default_priority: Some(Priority::new(3)),
+            ..ProgramContent::new("program")
+        };
+        let event = EventContent::new(
+            ProgramId("p".parse().unwrap()),
+            vec![EventInterval::new(0, vec![])],
+        );
+
+        let resolved = event.clone().with_program_defaults(&program);
+        assert_eq!(resolved.priority, Priority::new(3));
+        assert_eq!(resolved.interval_period, program.interval_period);
+        assert_eq!(
+            resolved.payload_descriptors,
+            Some(vec![EventPayloadDescriptor::new(EventType::Price)])
+        );
+
+        // the event takes precedence
+        let event = event
+            .with_priority(Priority::new(1))
+            .with_interval_period(IntervalPeriod::new(at + chrono::TimeDelta::hours(1)))
+            .with_payload_descriptors(vec![]);
+        assert_eq!(event.clone().with_program_defaults(&program), event);
+
+        // programs without defaults change nothing
+        let program = ProgramContent::new("program");
+        let event = EventContent::new(
+            ProgramId("p".parse().unwrap()),
+            vec![EventInterval::new(0, vec![])],
+        );
+        assert_eq!(event.clone().with_program_defaults(&program), event);
+    }
+
+    #[test]
+    fn payload_descriptor_lookup() {
+        let kwh = |payload_type| EventPayloadDescriptor {
+            units: Some(Unit::KWH),
+            ..EventPayloadDescriptor::new(payload_type)
+        };
+        let program = ProgramContent {
+            payload_descriptors: Some(vec![
+                PayloadDescriptor::ReportPayloadDescriptor(ReportPayloadDescriptor::new(
+                    ReportType::Usage,
+                )),
+                PayloadDescriptor::EventPayloadDescriptor(EventPayloadDescriptor::new(
+                    EventType::Price,
+                )),
+                PayloadDescriptor::EventPayloadDescriptor(kwh(EventType::ImportCapacityLimit)),
+            ]),
+            ..ProgramContent::new("program")
+        };
+        assert_eq!(
+            program.event_payload_descriptor(&EventType::Price),
+            Some(&EventPayloadDescriptor::new(EventType::Price))
+        );
+        assert_eq!(program.event_payload_descriptor(&EventType::Simple), None);
+
+        // the event overrides the program for the payload types it describes
+        let event = EventContent::new(
+            ProgramId("p".parse().unwrap()),
+            vec![EventInterval::new(0, vec![])],
+        )
+        .with_payload_descriptors(vec![kwh(EventType::Price)]);
+        assert_eq!(
+            event.payload_descriptor(&EventType::Price, &program),
+            Some(&kwh(EventType::Price))
+        );
+        assert_eq!(
+            event.payload_descriptor(&EventType::ImportCapacityLimit, &program),
+            Some(&kwh(EventType::ImportCapacityLimit))
+        );
+        assert_eq!(event.payload_descriptor(&EventType::Simple, &program), None);
+    }
+
+    #[test]
+    fn interval_order() {
+        let program_id = ProgramId("p".parse().unwrap());
+
+        let mut event = EventContent::new(
+            program_id,
+            vec![
+                interval_at(0, 0, Some(1.0)),
+                interval_at(1, 2, Some(1.0)),
+                EventInterval::new(2, vec![]),
+                interval_at(3, 1, Some(1.0)),
+                interval_at(4, 1, Some(1.0)),
+            ],
+        );
+        assert_eq!(event.out_of_order_interval().map(|i| i.id), Some(3));
+
+        event.normalize_interval_order();
+        let ids = event.intervals.iter().map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 0, 3, 4, 1]);
+        assert!(event.out_of_order_interval().is_none());
+    }
+
+    #[test]
+    fn render() {
+        let mut first = interval_at(0, 0, Some(1.0));
+        first.payloads = vec![EventValuesMap {
+            value_type: EventType::Price,
+            values: vec![Value::Number(0.25)],
+        }];
+        let mut second = interval_at(1, 1, None);
+        second.payloads = vec![EventValuesMap {
+            value_type: EventType::ImportCapacityLimit,
+            values: vec![Value::Integer(10), Value::Integer(20)],
+        }];
+
+        let event = EventContent::new(ProgramId("p".parse().unwrap()), vec![first, second])
+            .with_event_name("prices")
+            .with_payload_descriptors(vec![EventPayloadDescriptor {
+                payload_type: EventType::ImportCapacityLimit,
+                units: Some(Unit::KW),
+                currency: None,
+            }]);
+
+        assert_eq!(
+            event.to_table(),
+            "2024-01-01T00:00:00Z → 2024-01-01T01:00:00Z → PRICE 0.25\n\
+             2024-01-01T01:00:00Z → ∞ → IMPORT_CAPACITY_LIMIT 10, 20 KW\n"
+        );
+        assert_eq!(
+            event.to_string(),
+            r#"event "prices" of program p with 2 interval(s) from 2024-01-01T00:00:00Z"#
+        );
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let old = EventContent::new(
+            ProgramId("p".parse().unwrap()),
+            (0..288).map(|id| interval_at(id, 0, Some(1.0))).collect(),
+        );
+
+        let mut new = old.clone();
+        new.intervals[7].interval_period = None;
+        new.intervals.remove(3);
+        new.intervals.push(interval_at(300, 1, None));
+
+        let delta = old.delta_to(&new).unwrap();
+        assert_eq!(delta.intervals.len(), 2);
+        assert_eq!(delta.removed_intervals, [3]);
+
+        let mut updated = old.clone();
+        updated.apply_delta(delta);
+        assert_eq!(updated, new);
+
+        assert!(old.delta_to(&old).unwrap().is_empty());
+        assert!(old.delta_to(&new.with_event_name("renamed")).is_none());
+    }
 }
@@ -3,6 +3,8 @@
 use crate::{values_map::ValuesMap, Duration};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use validator::ValidationError;
 
 /// An object defining a temporal window and a list of valuesMaps. if intervalPeriod present may set
 /// temporal aspects of interval or override event.intervalPeriod.
@@ -52,4 +54,60 @@ impl IntervalPeriod {
             randomize_start: None,
         }
     }
+
+    /// Whether this period overlaps with `start..end`. A period without a duration never ends.
+    pub fn overlaps(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+        let ends_after_start = match &self.duration {
+            Some(duration) => self.start + duration.to_chrono_at_datetime(self.start) > start,
+            None => true,
+        };
+
+        self.start < end && ends_after_start
+    }
+}
+
+/// Interval ids must be non-negative and unique within an event or report resource
+pub(crate) fn validate_interval_ids(
+    ids: impl IntoIterator<Item = i32>,
+) -> Result<(), ValidationError> {
+    let mut seen = HashSet::new();
+
+    for id in ids {
+        if id < 0 {
+            return Err(ValidationError::new("negative_interval_id")
+                .with_message("interval ids must be non-negative".into()));
+        }
+
+        if !seen.insert(id) {
+            return Err(ValidationError::new("duplicate_interval_id")
+                .with_message(format!("interval id {id} is used more than once").into()));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn period_overlaps() {
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        let period = IntervalPeriod {
+            start: at(10),
+            duration: Some(Duration::hours(2.0)),
+            randomize_start: None,
+        };
+
+        assert!(period.overlaps(at(9), at(11)));
+        assert!(period.overlaps(at(11), at(13)));
+        assert!(!period.overlaps(at(8), at(10)));
+        assert!(!period.overlaps(at(12), at(13)));
+
+        let endless = IntervalPeriod::new(at(10));
+        assert!(endless.overlaps(at(20), at(21)));
+        assert!(!endless.overlaps(at(8), at(9)));
+    }
 }
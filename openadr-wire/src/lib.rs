@@ -6,27 +6,111 @@
 
 use std::fmt::Display;
 
+pub use canonical::to_canonical_json;
 pub use event::Event;
 pub use program::Program;
 pub use report::Report;
 use serde::{de::Unexpected, Deserialize, Deserializer, Serialize, Serializer};
 pub use ven::Ven;
 
+pub mod auth;
+pub mod canonical;
+pub mod capabilities;
 pub mod event;
 pub mod interval;
+pub mod notification;
 pub mod oauth;
 pub mod problem;
 pub mod program;
 pub mod report;
 pub mod resource;
+pub mod search;
 pub mod target;
+pub mod timeline;
 pub mod values_map;
 pub mod ven;
 
+/// HTTP header in which a VTN sends the total number of objects matching a list query,
+/// regardless of the `skip` and `limit` of the requested page.
+/// This is an extension to the OpenADR specification.
+pub const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+
+/// HTTP header in which a VTN sends the version of the events when listing them.
+/// A long-polling request passing it in the `changeVersion` query parameter is answered immediately
+/// if any event changed since, instead of waiting for the next change.
+/// This is an extension to the OpenADR specification.
+pub const CHANGE_VERSION_HEADER: &str = "X-Change-Version";
+
+/// Property to sort the program, event and report lists by, with the `sortBy` query parameter.
+/// This is an extension to the OpenADR specification.
+///
+/// Objects without the property, e.g., unnamed events, come last in either [`SortOrder`].
+/// Objects with an equal property keep the default order of the list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortBy {
+    /// The `createdDateTime`
+    Created,
+    /// The `modificationDateTime`
+    Modified,
+    /// The priority number of events, or the default priority of programs.
+    /// As a lower number is a higher priority, ascending lists the highest priority first.
+    /// Reports have no priority.
+    Priority,
+    /// The program, event or report name
+    Name,
+}
+
+impl SortBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortBy::Created => "created",
+            SortBy::Modified => "modified",
+            SortBy::Priority => "priority",
+            SortBy::Name => "name",
+        }
+    }
+}
+
+/// Direction of the [`SortBy`] with the `sortOrder` query parameter, ascending by default
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+/// The revision of the OpenADR specification this crate is compiled for,
+/// selected with the `spec-3_0_0` and `spec-3_0_1` features.
+///
+/// Fields of newer revisions are left out when compiling for an older one,
+/// and are ignored when deserializing messages of a counterpart that sends them anyway.
+#[cfg(feature = "spec-3_0_1")]
+pub const SPEC_VERSION: &str = "3.0.1";
+/// The revision of the OpenADR specification this crate is compiled for
+#[cfg(not(feature = "spec-3_0_1"))]
+pub const SPEC_VERSION: &str = "3.0.0";
+
+/// (De)serialization of RFC 3339 timestamps
+///
+/// The timestamp is deserialized into the time zone of the field. For `DateTime<Utc>` fields the
+/// original offset is lost, which is fine for computations. Fields where the local wall time
+/// matters, e.g., for display or auditing, can use `DateTime<FixedOffset>` to preserve the offset
+/// of the original string, which is then also used when serializing.
 pub mod serde_rfc3339 {
     use super::*;
 
-    use chrono::{DateTime, TimeZone, Utc};
+    use chrono::{DateTime, FixedOffset, TimeZone};
 
     pub fn serialize<S, Tz>(time: &DateTime<Tz>, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -36,9 +120,11 @@ pub mod serde_rfc3339 {
         serializer.serialize_str(&time.to_rfc3339())
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    pub fn deserialize<'de, D, Tz>(deserializer: D) -> Result<DateTime<Tz>, D::Error>
     where
         D: Deserializer<'de>,
+        Tz: TimeZone,
+        DateTime<Tz>: From<DateTime<FixedOffset>>,
     {
         let rfc_str = <String as Deserialize>::deserialize(deserializer)?;
 
@@ -52,6 +138,24 @@ pub mod serde_rfc3339 {
     }
 }
 
+/// The number of fractional digits of a second with which timestamps are stored.
+///
+/// Postgres stores timestamps with microsecond precision, while [`chrono`] uses nanoseconds.
+pub const TIMESTAMP_PRECISION: u16 = 6;
+
+/// Truncate a timestamp to [`TIMESTAMP_PRECISION`].
+///
+/// Timestamps set by a VTN, e.g., the `createdDateTime` of an object, should be truncated,
+/// such that a client receives the same timestamp when creating the object as when fetching it later,
+/// regardless of the storage backend.
+pub fn truncate_timestamp<Tz: chrono::TimeZone>(
+    time: chrono::DateTime<Tz>,
+) -> chrono::DateTime<Tz> {
+    use chrono::SubsecRound;
+
+    time.trunc_subsecs(TIMESTAMP_PRECISION)
+}
+
 pub fn string_within_range_inclusive<'de, const MIN: usize, const MAX: usize, D>(
     deserializer: D,
 ) -> Result<String, D::Error>
@@ -80,10 +184,11 @@ impl<'de> Deserialize<'de> for Identifier {
     where
         D: Deserializer<'de>,
     {
-        let borrowed_str = <&str as Deserialize>::deserialize(deserializer)?;
+        // not borrowed, such that identifiers can also be read from a `serde_json::Value`
+        let raw = String::deserialize(deserializer)?;
 
-        borrowed_str.parse::<Identifier>().map_err(|e| {
-            serde::de::Error::invalid_value(Unexpected::Str(borrowed_str), &e.to_string().as_str())
+        raw.parse::<Identifier>().map_err(|e| {
+            serde::de::Error::invalid_value(Unexpected::Str(&raw), &e.to_string().as_str())
         })
     }
 }
@@ -131,8 +236,15 @@ impl Display for Identifier {
 }
 
 /// An ISO 8601 formatted duration
+///
+/// Besides the `PnYnMnDTnHnMnS` form, week-based durations like `P1W` and negative durations
+/// like `-PT5M` are accepted. Weeks are normalized to days when the duration is formatted.
 #[derive(Clone, Debug, PartialEq)]
-pub struct Duration(iso8601_duration::Duration);
+pub struct Duration {
+    /// A negative duration, e.g., a negative `randomize_start`, points backwards in time
+    negative: bool,
+    duration: iso8601_duration::Duration,
+}
 
 impl<'de> Deserialize<'de> for Duration {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -140,12 +252,7 @@ impl<'de> Deserialize<'de> for Duration {
         D: Deserializer<'de>,
     {
         let raw = String::deserialize(deserializer)?;
-        let duration = raw
-            .parse::<iso8601_duration::Duration>()
-            .map_err(|_| "iso8601_duration::ParseDurationError")
-            .map_err(serde::de::Error::custom)?;
-
-        Ok(Self(duration))
+        raw.parse::<Duration>().map_err(serde::de::Error::custom)
     }
 }
 
@@ -159,20 +266,39 @@ impl Serialize for Duration {
 }
 
 impl Duration {
+    const fn positive(duration: iso8601_duration::Duration) -> Self {
+        Self {
+            negative: false,
+            duration,
+        }
+    }
+
     /// because iso8601 durations can include months and years, they don't independently have a
     /// fixed duration. Their real duration (in real units like seconds) can only be determined
     /// when a starting time is given.
     ///
+    /// A negative duration yields the negated length of its positive counterpart starting at `at`.
+    ///
     /// NOTE: does not consider leap seconds!
     pub fn to_chrono_at_datetime<Tz: chrono::TimeZone>(
         &self,
         at: chrono::DateTime<Tz>,
     ) -> chrono::Duration {
-        self.0.to_chrono_at_datetime(at)
+        let duration = self.duration.to_chrono_at_datetime(at);
+        if self.negative {
+            -duration
+        } else {
+            duration
+        }
+    }
+
+    /// Whether this duration points backwards in time, e.g., `-PT5M`
+    pub fn is_negative(&self) -> bool {
+        self.negative
     }
 
     /// One (1) hour
-    pub const PT1H: Self = Self(iso8601_duration::Duration {
+    pub const PT1H: Self = Self::positive(iso8601_duration::Duration {
         year: 0.0,
         month: 0.0,
         day: 0.0,
@@ -183,7 +309,7 @@ impl Duration {
 
     /// Indicates that an event's intervals continue indefinitely into the future until the event is
     /// deleted or modified. This effectively represents an infinite duration.
-    pub const P999Y: Self = Self(iso8601_duration::Duration {
+    pub const P999Y: Self = Self::positive(iso8601_duration::Duration {
         year: 9999.0,
         month: 0.0,
         day: 0.0,
@@ -193,7 +319,7 @@ impl Duration {
     });
 
     pub const fn hours(hour: f32) -> Self {
-        Self(iso8601_duration::Duration {
+        Self::positive(iso8601_duration::Duration {
             year: 0.0,
             month: 0.0,
             day: 0.0,
@@ -204,12 +330,48 @@ impl Duration {
     }
 }
 
+const ZERO_DURATION: iso8601_duration::Duration = iso8601_duration::Duration {
+    year: 0.0,
+    month: 0.0,
+    day: 0.0,
+    hour: 0.0,
+    minute: 0.0,
+    second: 0.0,
+};
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid ISO 8601 duration: {0}")]
+pub struct ParseDurationError(String);
+
 impl std::str::FromStr for Duration {
-    type Err = iso8601_duration::ParseDurationError;
+    type Err = ParseDurationError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let duration = s.parse::<iso8601_duration::Duration>()?;
-        Ok(Self(duration))
+        let error = || ParseDurationError(s.to_string());
+
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let date_and_time = unsigned.strip_prefix('P').ok_or_else(error)?;
+
+        // The iso8601_duration crate does not know about weeks, so these are converted to days.
+        // Strictly, ISO 8601 does not allow combining weeks with other units, but we accept it.
+        let (weeks, rest) = match date_and_time.split_once('W') {
+            Some((weeks, rest)) => (weeks.parse::<f32>().map_err(|_| error())?, rest),
+            None => (0.0, date_and_time),
+        };
+
+        let mut duration = if rest.is_empty() && date_and_time.ends_with('W') {
+            ZERO_DURATION
+        } else {
+            format!("P{rest}")
+                .parse::<iso8601_duration::Duration>()
+                .map_err(|_| error())?
+        };
+        duration.day += weeks * 7.0;
+
+        Ok(Self { negative, duration })
     }
 }
 
@@ -222,10 +384,12 @@ impl Display for Duration {
             hour,
             minute,
             second,
-        } = self.0;
+        } = self.duration;
+
+        let sign = if self.negative { "-" } else { "" };
 
         f.write_fmt(format_args!(
-            "P{}Y{}M{}DT{}H{}M{}S",
+            "{sign}P{}Y{}M{}DT{}H{}M{}S",
             year, month, day, hour, minute, second
         ))
     }
@@ -250,6 +414,53 @@ pub enum OperatingState {
     Private(String),
 }
 
+impl OperatingState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            OperatingState::Normal => "NORMAL",
+            OperatingState::Error => "ERROR",
+            OperatingState::IdleNormal => "IDLE_NORMAL",
+            OperatingState::RunningNormal => "RUNNING_NORMAL",
+            OperatingState::RunningCurtailed => "RUNNING_CURTAILED",
+            OperatingState::RunningHeightened => "RUNNING_HEIGHTENED",
+            OperatingState::IdleCurtailed => "IDLE_CURTAILED",
+            OperatingState::SGDErrorCondition => "SGD_ERROR_CONDITION",
+            OperatingState::IdleHeightened => "IDLE_HEIGHTENED",
+            OperatingState::IdleOptedOut => "IDLE_OPTED_OUT",
+            OperatingState::RunningOptedOut => "RUNNING_OPTED_OUT",
+            OperatingState::Private(state) => state,
+        }
+    }
+}
+
+impl Display for OperatingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Unknown states are parsed as [`OperatingState::Private`]
+impl std::str::FromStr for OperatingState {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "NORMAL" => OperatingState::Normal,
+            "ERROR" => OperatingState::Error,
+            "IDLE_NORMAL" => OperatingState::IdleNormal,
+            "RUNNING_NORMAL" => OperatingState::RunningNormal,
+            "RUNNING_CURTAILED" => OperatingState::RunningCurtailed,
+            "RUNNING_HEIGHTENED" => OperatingState::RunningHeightened,
+            "IDLE_CURTAILED" => OperatingState::IdleCurtailed,
+            "SGD_ERROR_CONDITION" => OperatingState::SGDErrorCondition,
+            "IDLE_HEIGHTENED" => OperatingState::IdleHeightened,
+            "IDLE_OPTED_OUT" => OperatingState::IdleOptedOut,
+            "RUNNING_OPTED_OUT" => OperatingState::RunningOptedOut,
+            other => OperatingState::Private(other.to_string()),
+        })
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum DataQuality {
@@ -327,6 +538,24 @@ pub enum Unit {
     Private(String),
 }
 
+impl Display for Unit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_serialized_name(self, f)
+    }
+}
+
+/// Write the name an enum variant serializes to, e.g., `KWH` for [`Unit::KWH`].
+/// Only works for types that serialize to a string.
+pub(crate) fn fmt_serialized_name(
+    value: &impl Serialize,
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => f.write_str(&name),
+        _ => Err(std::fmt::Error),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{Attribute, DataQuality, Identifier, OperatingState, Unit};
@@ -356,6 +585,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn operating_state_display_matches_serde() {
+        for state in [
+            OperatingState::Normal,
+            OperatingState::RunningCurtailed,
+            OperatingState::SGDErrorCondition,
+            OperatingState::RunningOptedOut,
+            OperatingState::Private("CHARGING".to_string()),
+        ] {
+            assert_eq!(
+                serde_json::to_string(&state).unwrap(),
+                format!("\"{state}\"")
+            );
+            assert_eq!(state.to_string().parse::<OperatingState>().unwrap(), state);
+        }
+    }
+
     #[test]
     fn test_data_quality_serialization() {
         assert_eq!(serde_json::to_string(&DataQuality::Ok).unwrap(), r#""OK""#);
@@ -414,14 +660,17 @@ mod tests {
         fn arbitrary(g: &mut quickcheck::Gen) -> Self {
             // the iso8601_duration library uses an f32 to store the values, which starts losing
             // precision at 24-bit integers.
-            super::Duration(iso8601_duration::Duration {
-                year: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
-                month: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
-                day: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
-                hour: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
-                minute: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
-                second: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
-            })
+            super::Duration {
+                negative: <bool as quickcheck::Arbitrary>::arbitrary(g),
+                duration: iso8601_duration::Duration {
+                    year: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
+                    month: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
+                    day: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
+                    hour: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
+                    minute: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
+                    second: (<u32 as quickcheck::Arbitrary>::arbitrary(g) & 0x00FF_FFFF) as f32,
+                },
+            }
         }
     }
 
@@ -432,9 +681,94 @@ mod tests {
         fn test(input: super::Duration) -> bool {
             let roundtrip = input.to_string().parse::<super::Duration>().unwrap();
 
-            assert_eq!(input.0, roundtrip.0);
+            assert_eq!(input, roundtrip);
+
+            input == roundtrip
+        }
+    }
+
+    #[test]
+    fn rfc3339_offset() {
+        use chrono::{DateTime, FixedOffset, Utc};
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Times {
+            #[serde(with = "super::serde_rfc3339")]
+            utc: DateTime<Utc>,
+            #[serde(with = "super::serde_rfc3339")]
+            local: DateTime<FixedOffset>,
+        }
+
+        let json = r#"{"utc":"2024-06-01T08:00:00+02:00","local":"2024-06-01T08:00:00+02:00"}"#;
+        let times: Times = serde_json::from_str(json).unwrap();
+        assert_eq!(times.utc, times.local);
+        assert_eq!(times.local.offset().local_minus_utc(), 2 * 3600);
+        assert_eq!(
+            serde_json::to_string(&times).unwrap(),
+            r#"{"utc":"2024-06-01T06:00:00+00:00","local":"2024-06-01T08:00:00+02:00"}"#
+        );
+
+        for invalid in ["2024-06-01", "2024-06-01T08:00:00", "01-06-2024T08:00:00Z"] {
+            let json = format!(r#"{{"utc":"{invalid}","local":"{invalid}"}}"#);
+            assert!(serde_json::from_str::<Times>(&json).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn truncated_timestamp_round_trip() {
+        use chrono::{DateTime, Utc};
+
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Time(#[serde(with = "super::serde_rfc3339")] DateTime<Utc>);
+
+        let time: DateTime<Utc> = "2024-06-01T08:00:00.123456789Z".parse().unwrap();
+        let truncated = super::truncate_timestamp(time);
+        assert_eq!(truncated.to_rfc3339(), "2024-06-01T08:00:00.123456+00:00");
+        assert_eq!(super::truncate_timestamp(truncated), truncated);
+
+        let json = serde_json::to_string(&Time(truncated)).unwrap();
+        assert_eq!(json, r#""2024-06-01T08:00:00.123456+00:00""#);
+        let Time(parsed) = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, truncated);
+        assert_eq!(serde_json::to_string(&Time(parsed)).unwrap(), json);
+    }
+
+    #[test]
+    fn duration_weeks_and_sign() {
+        use chrono::{DateTime, Utc};
+
+        let parse = |s: &str| s.parse::<super::Duration>().unwrap();
+        let at = DateTime::<Utc>::UNIX_EPOCH;
+
+        assert_eq!(parse("P1W"), parse("P7D"));
+        assert_eq!(parse("P1W").to_string(), "P0Y0M7DT0H0M0S");
+        assert_eq!(parse("P2W1DT1H"), parse("P15DT1H"));
+        assert_eq!(
+            parse("P0.5W").to_chrono_at_datetime(at),
+            chrono::Duration::hours(84)
+        );
+
+        let negative = parse("-PT5M");
+        assert!(negative.is_negative());
+        assert_eq!(negative.to_string(), "-P0Y0M0DT0H5M0S");
+        assert_eq!(
+            negative.to_chrono_at_datetime(at),
+            chrono::Duration::minutes(-5)
+        );
+        assert_eq!(parse(&negative.to_string()), negative);
+        assert_eq!(
+            parse("-P1W").to_chrono_at_datetime(at),
+            chrono::Duration::days(-7)
+        );
+
+        assert!(!parse("+PT5M").is_negative());
+        assert_eq!(
+            serde_json::from_str::<super::Duration>(r#""-PT5M""#).unwrap(),
+            negative
+        );
 
-            input.0 == roundtrip.0
+        for invalid in ["", "1W", "--PT5M", "PxW", "PT1W"] {
+            assert!(invalid.parse::<super::Duration>().is_err(), "{invalid}");
         }
     }
 
@@ -0,0 +1,270 @@
+//! Types of the notifications a VTN sends to the callback URLs of subscribers
+//! when objects are created, updated or deleted
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use validator::{Validate, ValidationErrors};
+
+#[cfg(feature = "spec-3_0_1")]
+use crate::target::TargetMap;
+use crate::{program::ProgramId, resource::Resource, Event, Program, Report, Ven};
+
+/// VTN generated object included in request to subscription callbackUrl.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "RawNotification")]
+pub struct Notification {
+    pub object_type: NotificationObjectType,
+    /// the operation on on object that triggered the notification.
+    pub operation: NotificationOperation,
+    pub object: NotificationObject,
+    /// A list of valuesMap objects. Added in OpenADR 3.0.1.
+    #[cfg(feature = "spec-3_0_1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targets: Option<TargetMap>,
+}
+
+impl Notification {
+    /// A notification of the object, with the targets of the object
+    pub fn new(operation: NotificationOperation, object: NotificationObject) -> Self {
+        Self {
+            object_type: object.object_type(),
+            operation,
+            #[cfg(feature = "spec-3_0_1")]
+            targets: object.targets(),
+            object,
+        }
+    }
+}
+
+impl Validate for Notification {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        self.object.validate()
+    }
+}
+
+/// The object of a notification is deserialized according to its `objectType`,
+/// as the objects cannot be told apart reliably by their fields
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawNotification {
+    object_type: NotificationObjectType,
+    operation: NotificationOperation,
+    object: Value,
+    #[cfg(feature = "spec-3_0_1")]
+    targets: Option<TargetMap>,
+}
+
+impl TryFrom<RawNotification> for Notification {
+    type Error = serde_json::Error;
+
+    fn try_from(raw: RawNotification) -> Result<Self, Self::Error> {
+        let object = match raw.object_type {
+            NotificationObjectType::Program => {
+                NotificationObject::Program(serde_json::from_value(raw.object)?)
+            }
+            NotificationObjectType::Event => {
+                NotificationObject::Event(serde_json::from_value(raw.object)?)
+            }
+            NotificationObjectType::Report => {
+                NotificationObject::Report(serde_json::from_value(raw.object)?)
+            }
+            NotificationObjectType::Ven => {
+                NotificationObject::Ven(serde_json::from_value(raw.object)?)
+            }
+            NotificationObjectType::Resource => {
+                NotificationObject::Resource(serde_json::from_value(raw.object)?)
+            }
+        };
+
+        Ok(Self {
+            object_type: raw.object_type,
+            operation: raw.operation,
+            object,
+            #[cfg(feature = "spec-3_0_1")]
+            targets: raw.targets,
+        })
+    }
+}
+
+/// Types of objects of which subscribers can be notified
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationObjectType {
+    Program,
+    Event,
+    Report,
+    Ven,
+    Resource,
+}
+
+/// the operation on on object that triggered the notification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum NotificationOperation {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum NotificationObject {
+    Program(Box<Program>),
+    Event(Box<Event>),
+    Report(Box<Report>),
+    Ven(Box<Ven>),
+    Resource(Box<Resource>),
+}
+
+impl NotificationObject {
+    pub fn object_type(&self) -> NotificationObjectType {
+        match self {
+            NotificationObject::Program(_) => NotificationObjectType::Program,
+            NotificationObject::Event(_) => NotificationObjectType::Event,
+            NotificationObject::Report(_) => NotificationObjectType::Report,
+            NotificationObject::Ven(_) => NotificationObjectType::Ven,
+            NotificationObject::Resource(_) => NotificationObjectType::Resource,
+        }
+    }
+
+    /// The program the object belongs to, if any
+    pub fn program_id(&self) -> Option<&ProgramId> {
+        match self {
+            NotificationObject::Program(program) => Some(&program.id),
+            NotificationObject::Event(event) => Some(&event.content.program_id),
+            NotificationObject::Report(report) => Some(&report.content.program_id),
+            NotificationObject::Ven(_) | NotificationObject::Resource(_) => None,
+        }
+    }
+
+    #[cfg(feature = "spec-3_0_1")]
+    fn targets(&self) -> Option<TargetMap> {
+        match self {
+            NotificationObject::Program(program) => program.content.targets.clone(),
+            NotificationObject::Event(event) => event.content.targets.clone(),
+            NotificationObject::Report(_) => None,
+            NotificationObject::Ven(ven) => ven
+                .content
+                .targets
+                .as_deref()
+                .map(|targets| TargetMap::from_values_maps(Some(targets))),
+            NotificationObject::Resource(resource) => resource
+                .content
+                .targets
+                .as_deref()
+                .map(|targets| TargetMap::from_values_maps(Some(targets))),
+        }
+    }
+}
+
+impl Validate for NotificationObject {
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        match self {
+            NotificationObject::Program(program) => program.validate(),
+            NotificationObject::Event(event) => event.validate(),
+            NotificationObject::Report(report) => report.validate(),
+            NotificationObject::Ven(ven) => ven.validate(),
+            NotificationObject::Resource(resource) => resource.validate(),
+        }
+    }
+}
+
+impl From<Program> for NotificationObject {
+    fn from(program: Program) -> Self {
+        NotificationObject::Program(Box::new(program))
+    }
+}
+
+impl From<Event> for NotificationObject {
+    fn from(event: Event) -> Self {
+        NotificationObject::Event(Box::new(event))
+    }
+}
+
+impl From<Report> for NotificationObject {
+    fn from(report: Report) -> Self {
+        NotificationObject::Report(Box::new(report))
+    }
+}
+
+impl From<Ven> for NotificationObject {
+    fn from(ven: Ven) -> Self {
+        NotificationObject::Ven(Box::new(ven))
+    }
+}
+
+impl From<Resource> for NotificationObject {
+    fn from(resource: Resource) -> Self {
+        NotificationObject::Resource(Box::new(resource))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        program::ProgramContent,
+        target::{TargetEntry, TargetLabel, TargetMap},
+    };
+
+    #[test]
+    fn deserializes_by_object_type() {
+        let program = Program {
+            id: "program-1".parse().unwrap(),
+            created_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            modification_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            content: ProgramContent::new("program-1"),
+        };
+        let notification = Notification::new(
+            NotificationOperation::Post,
+            NotificationObject::Program(Box::new(program)),
+        );
+
+        let json = serde_json::to_value(&notification).unwrap();
+        assert_eq!(json["objectType"], "PROGRAM");
+        assert_eq!(json["operation"], "POST");
+        assert_eq!(json["object"]["programName"], "program-1");
+        assert_eq!(
+            serde_json::from_value::<Notification>(json.clone()).unwrap(),
+            notification
+        );
+
+        let mut json = json;
+        json["objectType"] = "EVENT".into();
+        assert!(serde_json::from_value::<Notification>(json).is_err());
+    }
+
+    #[test]
+    fn targets_depend_on_spec_revision() {
+        let mut content = ProgramContent::new("program-1");
+        content.targets = Some(TargetMap(vec![TargetEntry::new(
+            TargetLabel::Group,
+            "group-1",
+        )]));
+        let program = Program {
+            id: "program-1".parse().unwrap(),
+            created_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            modification_date_time: "2024-06-01T00:00:00Z".parse().unwrap(),
+            content,
+        };
+        let notification = Notification::new(NotificationOperation::Put, program.into());
+        let json = serde_json::to_value(&notification).unwrap();
+
+        #[cfg(feature = "spec-3_0_1")]
+        assert_eq!(
+            json["targets"],
+            serde_json::json!([{"type": "GROUP", "values": ["group-1"]}])
+        );
+        #[cfg(not(feature = "spec-3_0_1"))]
+        assert!(json.get("targets").is_none());
+
+        // a 3.0.1 counterpart sends the targets, which a 3.0.0 build ignores
+        let mut json = json;
+        json["targets"] = serde_json::json!([{"type": "GROUP", "values": ["group-1"]}]);
+        assert_eq!(
+            serde_json::from_value::<Notification>(json).unwrap(),
+            notification
+        );
+    }
+}
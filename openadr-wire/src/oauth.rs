@@ -3,14 +3,16 @@
 pub enum OAuthErrorType {
     InvalidRequest,
     InvalidClient,
-    // InvalidGrant,
+    InvalidGrant,
     // UnauthorizedClient,
     UnsupportedGrantType,
-    // InvalidScope,
+    InvalidScope,
     ServerError,
 }
 
+/// Field names follow RFC 6749, section 5.2, instead of the camelCase used by OpenADR
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct OAuthError {
     pub error: OAuthErrorType,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -38,3 +40,106 @@ impl OAuthError {
         self
     }
 }
+
+/// The scopes of a token, restricting which requests the VTN accepts it for,
+/// in addition to the roles of the client.
+///
+/// Scopes are sent as a space-separated list in the `scope` parameter of a token request,
+/// see [`Scope::parse_list`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    /// Read programs, events, reports, VENs and resources
+    ReadAll,
+    WritePrograms,
+    WriteEvents,
+    WriteReports,
+    /// Create and modify VENs and their resources
+    WriteVens,
+    /// Manage the users and the configuration of the VTN, an extension to the OpenADR specification
+    Admin,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown scope {0:?}")]
+pub struct UnknownScope(pub String);
+
+impl Scope {
+    pub const ALL: [Scope; 6] = [
+        Scope::ReadAll,
+        Scope::WritePrograms,
+        Scope::WriteEvents,
+        Scope::WriteReports,
+        Scope::WriteVens,
+        Scope::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::ReadAll => "read_all",
+            Scope::WritePrograms => "write_programs",
+            Scope::WriteEvents => "write_events",
+            Scope::WriteReports => "write_reports",
+            Scope::WriteVens => "write_vens",
+            Scope::Admin => "admin",
+        }
+    }
+
+    /// Parse a space-separated list of scopes, as in RFC 6749, section 3.3
+    pub fn parse_list(list: &str) -> Result<Vec<Scope>, UnknownScope> {
+        list.split_whitespace().map(str::parse).collect()
+    }
+
+    /// Format scopes as a space-separated list, as in RFC 6749, section 3.3
+    pub fn format_list(scopes: &[Scope]) -> String {
+        scopes
+            .iter()
+            .map(Scope::as_str)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = UnknownScope;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Scope::ALL
+            .into_iter()
+            .find(|scope| scope.as_str() == s)
+            .ok_or_else(|| UnknownScope(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_list() {
+        let scopes = Scope::parse_list(" read_all  write_events").unwrap();
+        assert_eq!(scopes, [Scope::ReadAll, Scope::WriteEvents]);
+        assert_eq!(Scope::format_list(&scopes), "read_all write_events");
+
+        assert_eq!(
+            Scope::parse_list("read_all openid"),
+            Err(UnknownScope("openid".to_string()))
+        );
+        assert_eq!(Scope::parse_list("").unwrap(), []);
+
+        for scope in Scope::ALL {
+            assert_eq!(
+                serde_json::to_value(scope).unwrap(),
+                serde_json::Value::String(scope.to_string())
+            );
+        }
+    }
+}
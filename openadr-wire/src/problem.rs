@@ -34,6 +34,10 @@ pub struct Problem {
     /// An absolute URI that identifies the specific occurrence of the problem.
     /// It may or may not yield further information if dereferenced.
     pub instance: Option<String>,
+    /// Extension members with further details of this occurrence of the problem,
+    /// e.g., the object a `409 Conflict` conflicts with.
+    #[serde(flatten)]
+    pub extensions: serde_json::Map<String, serde_json::Value>,
 }
 
 mod status_code_serialization {
@@ -1,8 +1,12 @@
 //! Types used for the `program/` endpoint
 
 use crate::{
-    event::EventPayloadDescriptor, interval::IntervalPeriod, report::ReportPayloadDescriptor,
-    target::TargetMap, Duration, IdentifierError,
+    event::{EventPayloadDescriptor, EventType, Priority},
+    interval::IntervalPeriod,
+    report::ReportPayloadDescriptor,
+    target::TargetMap,
+    ven::VenId,
+    Duration, IdentifierError,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -79,6 +83,11 @@ pub struct ProgramContent {
     pub local_price: Option<bool>,
     /// A list of payloadDescriptors.
     pub payload_descriptors: Option<Vec<PayloadDescriptor>>,
+    /// The priority of the events of this program that do not specify one.
+    ///
+    /// Not part of the OpenADR specification, see
+    /// [`EventContent::with_program_defaults`](crate::event::EventContent::with_program_defaults).
+    pub default_priority: Option<Priority>,
     /// A list of valuesMap objects.
     pub targets: Option<TargetMap>,
 }
@@ -100,9 +109,30 @@ impl ProgramContent {
             binding_events: Default::default(),
             local_price: Default::default(),
             payload_descriptors: Default::default(),
+            default_priority: None,
             targets: Default::default(),
         }
     }
+
+    /// The descriptor of the event payloads of the given type, e.g., with the currency of prices.
+    ///
+    /// Events may override it, see [`EventContent::payload_descriptor`](crate::event::EventContent::payload_descriptor).
+    pub fn event_payload_descriptor(
+        &self,
+        payload_type: &EventType,
+    ) -> Option<&EventPayloadDescriptor> {
+        self.payload_descriptors
+            .iter()
+            .flatten()
+            .find_map(|descriptor| match descriptor {
+                PayloadDescriptor::EventPayloadDescriptor(descriptor)
+                    if &descriptor.payload_type == payload_type =>
+                {
+                    Some(descriptor)
+                }
+                _ => None,
+            })
+    }
 }
 
 // example: object-999
@@ -144,6 +174,7 @@ pub enum ProgramObjectType {
 }
 
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
 pub struct ProgramDescription {
     /// A human or machine readable program description
     #[serde(rename = "URL")]
@@ -158,6 +189,16 @@ pub enum PayloadDescriptor {
     ReportPayloadDescriptor(ReportPayloadDescriptor),
 }
 
+/// A VEN assigned to a program, managed with `POST /programs/{programID}/vens`
+/// and `DELETE /programs/{programID}/vens/{venID}`.
+/// Not part of the OpenADR specification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgramVen {
+    #[serde(rename = "venID")]
+    pub ven_id: VenId,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -216,6 +257,7 @@ mod test {
                 binding_events: Some(false),
                 local_price: Some(false),
                 payload_descriptors: None,
+                default_priority: None,
                 targets: None,
             },
         }];
@@ -244,6 +286,7 @@ mod test {
                 binding_events: None,
                 local_price: None,
                 payload_descriptors: None,
+                default_priority: None,
                 targets: None,
             }
         );
@@ -2,11 +2,11 @@
 
 use crate::{
     event::EventId,
-    interval::{Interval, IntervalPeriod},
+    interval::{validate_interval_ids, IntervalPeriod},
     program::ProgramId,
     target::TargetMap,
     values_map::Value,
-    Identifier, IdentifierError, Unit,
+    Identifier, IdentifierError, OperatingState, Unit,
 };
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -15,7 +15,7 @@ use std::{
     fmt::{Display, Formatter},
     str::FromStr,
 };
-use validator::{Validate, ValidateRange};
+use validator::{Validate, ValidateRange, ValidationError};
 
 /// report object.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Validate)]
@@ -58,7 +58,8 @@ pub struct ReportContent {
     #[validate(nested)]
     pub payload_descriptors: Option<Vec<ReportPayloadDescriptor>>,
     /// A list of objects containing report data for a set of resources.
-    pub resources: Vec<Resource>,
+    #[validate(nested)]
+    pub resources: Vec<ReportResource>,
 }
 
 impl ReportContent {
@@ -77,10 +78,16 @@ impl ReportContent {
         self
     }
 
-    pub fn with_resources(mut self, resources: Vec<Resource>) -> Self {
+    pub fn with_resources(mut self, resources: Vec<ReportResource>) -> Self {
         self.resources = resources;
         self
     }
+
+    /// Add the data of a resource to the report
+    pub fn with_resource(mut self, resource: ReportResource) -> Self {
+        self.resources.push(resource);
+        self
+    }
 }
 
 /// URL safe VTN assigned object ID
@@ -118,9 +125,20 @@ pub enum ReportObjectType {
 }
 
 /// Report data associated with a resource.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+///
+/// ```
+/// # use openadr_wire::report::{ReportInterval, ReportResource, ReportType, ReportValuesMap, ResourceName};
+/// # use openadr_wire::{interval::IntervalPeriod, values_map::Value};
+/// let resource = ReportResource::new(ResourceName::Private("meter-1".to_string()))
+///     .with_interval_period(IntervalPeriod::new(chrono::Utc::now()))
+///     .with_interval(ReportInterval::new(
+///         0,
+///         vec![ReportValuesMap::new(ReportType::Usage, vec![Value::Number(1.5)])],
+///     ));
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
-pub struct Resource {
+pub struct ReportResource {
     /// User generated identifier. A value of AGGREGATED_REPORT indicates an aggregation of more
     /// that one resource's data
     pub resource_name: ResourceName,
@@ -128,25 +146,53 @@ pub struct Resource {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interval_period: Option<IntervalPeriod>,
     /// A list of interval objects.
-    pub intervals: Vec<Interval>,
+    #[validate(custom(function = "validate_report_interval_ids"))]
+    pub intervals: Vec<ReportInterval>,
 }
 
-impl Resource {
-    /// Report data associated with a resource.
-    pub fn new(resource_name: ResourceName, intervals: Vec<Interval>) -> Resource {
-        Resource {
+impl ReportResource {
+    /// Report data associated with a resource, without any intervals yet
+    pub fn new(resource_name: ResourceName) -> Self {
+        Self {
             resource_name,
             interval_period: None,
-            intervals,
+            intervals: vec![],
         }
     }
+
+    pub fn with_interval_period(mut self, interval_period: IntervalPeriod) -> Self {
+        self.interval_period = Some(interval_period);
+        self
+    }
+
+    pub fn with_intervals(mut self, intervals: Vec<ReportInterval>) -> Self {
+        self.intervals = intervals;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: ReportInterval) -> Self {
+        self.intervals.push(interval);
+        self
+    }
+
+    /// The operating state in the last interval with an `OPERATING_STATE` payload
+    pub fn operating_state(&self) -> Option<OperatingState> {
+        self.intervals
+            .iter()
+            .rev()
+            .flat_map(|interval| &interval.payloads)
+            .find_map(ReportValuesMap::as_operating_state)
+    }
+}
+
+fn validate_report_interval_ids(intervals: &[ReportInterval]) -> Result<(), ValidationError> {
+    validate_interval_ids(intervals.iter().map(|interval| interval.id))
 }
 
 /// An object that may be used to request a report from a VEN. See OpenADR REST User Guide for
 /// detailed description of how configure a report request.
-// TODO: replace "-1 means" with proper enum
 #[skip_serializing_none]
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Validate)]
 #[serde(rename_all = "camelCase")]
 pub struct ReportDescriptor {
     /// Enumerated or private string signifying the nature of values.
@@ -161,21 +207,24 @@ pub struct ReportDescriptor {
     /// True if report should aggregate results from all targeted resources. False if report includes results for each resource.
     #[serde(default = "bool_false")]
     pub aggregate: bool,
-    /// The interval on which to generate a report. -1 indicates generate report at end of last interval.
-    #[serde(default = "neg_one")]
-    pub start_interval: i32,
-    /// The number of intervals to include in a report. -1 indicates that all intervals are to be included.
-    #[serde(default = "neg_one")]
-    pub num_intervals: i32,
+    /// The interval on which to generate a report. Unspecified (-1) indicates generate report at end of last interval.
+    #[serde(default)]
+    pub start_interval: DescriptorCount,
+    /// The number of intervals to include in a report. Unspecified (-1) indicates that all intervals are to be included.
+    #[serde(default)]
+    #[validate(custom(function = "validate_positive_count"))]
+    pub num_intervals: DescriptorCount,
     /// True indicates report on intervals preceding startInterval. False indicates report on intervals following startInterval (e.g. forecast).
     #[serde(default = "bool_true")]
     pub historical: bool,
-    /// Number of intervals that elapse between reports. -1 indicates same as numIntervals.
-    #[serde(default = "neg_one")]
-    pub frequency: i32,
-    /// Number of times to repeat report. 1 indicates generate one report. -1 indicates repeat indefinitely.
+    /// Number of intervals that elapse between reports. Unspecified (-1) indicates same as numIntervals.
+    #[serde(default)]
+    #[validate(custom(function = "validate_positive_count"))]
+    pub frequency: DescriptorCount,
+    /// Number of times to repeat report. 1 indicates generate one report. Unspecified (-1) indicates repeat indefinitely.
     #[serde(default = "pos_one")]
-    pub repeat: i32,
+    #[validate(custom(function = "validate_positive_count"))]
+    pub repeat: DescriptorCount,
 }
 
 impl ReportDescriptor {
@@ -187,11 +236,11 @@ impl ReportDescriptor {
             units: None,
             targets: None,
             aggregate: false,
-            start_interval: -1,
-            num_intervals: -1,
+            start_interval: DescriptorCount::UNSPECIFIED,
+            num_intervals: DescriptorCount::UNSPECIFIED,
             historical: true,
-            frequency: -1,
-            repeat: 1,
+            frequency: DescriptorCount::UNSPECIFIED,
+            repeat: DescriptorCount::new(1),
         }
     }
 }
@@ -204,12 +253,61 @@ fn bool_true() -> bool {
     true
 }
 
-fn neg_one() -> i32 {
-    -1
+fn pos_one() -> DescriptorCount {
+    DescriptorCount::new(1)
+}
+
+fn validate_positive_count(count: &DescriptorCount) -> Result<(), ValidationError> {
+    if count.get() == Some(0) {
+        return Err(ValidationError::new("must be positive or -1"));
+    }
+
+    Ok(())
+}
+
+/// A number of intervals or reports in a [`ReportDescriptor`].
+///
+/// On the wire, -1 indicates [`Self::UNSPECIFIED`], of which the meaning depends on the field.
+/// Other negative numbers are rejected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "i32", into = "i32")]
+pub struct DescriptorCount(Option<u32>);
+
+impl DescriptorCount {
+    pub const UNSPECIFIED: Self = Self(None);
+
+    /// # Panics
+    ///
+    /// If the count does not fit in an `i32`
+    pub const fn new(count: u32) -> Self {
+        assert!(count <= i32::MAX as u32, "count out of range");
+        Self(Some(count))
+    }
+
+    /// The count, or `None` if unspecified
+    pub const fn get(self) -> Option<u32> {
+        self.0
+    }
+}
+
+impl TryFrom<i32> for DescriptorCount {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            -1 => Ok(Self::UNSPECIFIED),
+            value => u32::try_from(value)
+                .map(|count| Self(Some(count)))
+                .map_err(|_| format!("expected -1 or a non-negative number, found {value}")),
+        }
+    }
 }
 
-fn pos_one() -> i32 {
-    1
+impl From<DescriptorCount> for i32 {
+    fn from(value: DescriptorCount) -> Self {
+        // `DescriptorCount::new` ensures the count fits
+        value.0.map_or(-1, |count| count as i32)
+    }
 }
 
 /// Contextual information used to interpret report payload values. E.g. a USAGE payload simply
@@ -260,8 +358,8 @@ impl ValidateRange<Confidence> for Confidence {
 
 /// An object defining a temporal window and a list of valuesMaps. if intervalPeriod present may set
 /// temporal aspects of interval or override event.intervalPeriod.
-#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 #[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ReportInterval {
     /// A client generated number assigned an interval object. Not a sequence number.
@@ -280,10 +378,16 @@ impl ReportInterval {
             payloads,
         }
     }
+
+    pub fn with_interval_period(mut self, interval_period: IntervalPeriod) -> Self {
+        self.interval_period = Some(interval_period);
+        self
+    }
 }
 
 /// Represents one or more values associated with a type. E.g. a type of PRICE contains a single float value.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ReportValuesMap {
     /// Enumerated or private string signifying the nature of values. E.G. \"PRICE\" indicates value is to be interpreted as a currency.
     #[serde(rename = "type")]
@@ -293,15 +397,118 @@ pub struct ReportValuesMap {
     pub values: Vec<Value>,
 }
 
+impl ReportValuesMap {
+    pub fn new(value_type: ReportType, values: Vec<Value>) -> Self {
+        Self { value_type, values }
+    }
+
+    /// An `OPERATING_STATE` payload
+    pub fn operating_state(state: OperatingState) -> Self {
+        Self::new(
+            ReportType::OperatingState,
+            vec![Value::String(state.to_string())],
+        )
+    }
+
+    /// The state of an `OPERATING_STATE` payload
+    pub fn as_operating_state(&self) -> Option<OperatingState> {
+        match (&self.value_type, self.values.as_slice()) {
+            (ReportType::OperatingState, [Value::String(state)]) => state.parse().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// The path of the latest [`ResourceOperatingState`]s, relative to the VTN base URL.
+/// Not part of the OpenADR specification.
+pub const OPERATING_STATES_PATH: &str = "reports/operating-states";
+
+/// The latest operating state a client reported for one of its resources
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceOperatingState {
+    pub client_name: String,
+    pub resource_name: ResourceName,
+    pub operating_state: OperatingState,
+    /// The report that contained the operating state
+    #[serde(rename = "reportID")]
+    pub report_id: ReportId,
+    /// The last modification of that report
+    #[serde(with = "crate::serde_rfc3339")]
+    pub reported_at: DateTime<Utc>,
+}
+
+/// The most recent payload of one type that a client reported for a resource.
+/// Not part of the OpenADR specification.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestReportPayload {
+    #[serde(rename = "type")]
+    pub payload_type: ReportType,
+    pub values: Vec<Value>,
+    /// The report that contained the payload
+    #[serde(rename = "reportID")]
+    pub report_id: ReportId,
+    /// The interval of that report that contained the payload
+    #[serde(rename = "intervalID")]
+    pub interval_id: i32,
+    /// The period of that interval, or else the default period of the resource in that report
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval_period: Option<IntervalPeriod>,
+    /// The last modification of that report
+    #[serde(with = "crate::serde_rfc3339")]
+    pub reported_at: DateTime<Utc>,
+}
+
+impl LatestReportPayload {
+    pub fn values_map(&self) -> ReportValuesMap {
+        ReportValuesMap::new(self.payload_type.clone(), self.values.clone())
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{
-        values_map::{Value, ValueType, ValuesMap},
-        Duration,
-    };
+    use crate::{values_map::Value, Duration};
 
     use super::*;
 
+    #[test]
+    fn operating_state_payloads() {
+        let resource = ReportResource::new(ResourceName::Private("charger-1".to_string()))
+            .with_interval(ReportInterval::new(
+                0,
+                vec![ReportValuesMap::operating_state(OperatingState::IdleNormal)],
+            ))
+            .with_interval(ReportInterval::new(
+                1,
+                vec![
+                    ReportValuesMap::new(ReportType::Usage, vec![Value::Number(3.0)]),
+                    ReportValuesMap::operating_state(OperatingState::RunningCurtailed),
+                ],
+            ))
+            .with_interval(ReportInterval::new(
+                2,
+                vec![ReportValuesMap::new(
+                    ReportType::Usage,
+                    vec![Value::Number(1.0)],
+                )],
+            ));
+
+        assert_eq!(
+            resource.operating_state(),
+            Some(OperatingState::RunningCurtailed)
+        );
+        assert_eq!(
+            serde_json::to_value(ReportValuesMap::operating_state(OperatingState::IdleNormal))
+                .unwrap(),
+            serde_json::json!({"type": "OPERATING_STATE", "values": ["IDLE_NORMAL"]})
+        );
+        assert_eq!(
+            ReportResource::new(ResourceName::AggregatedReport).operating_state(),
+            None
+        );
+    }
+
     #[test]
     fn test_report_type_serialization() {
         assert_eq!(
@@ -364,6 +571,29 @@ mod tests {
         );
     }
 
+    #[test]
+    fn descriptor_counts() {
+        let json = r#"{"payloadType":"USAGE","startInterval":2,"numIntervals":4,"frequency":-1,"repeat":-1}"#;
+        let descriptor = serde_json::from_str::<ReportDescriptor>(json).unwrap();
+
+        assert_eq!(descriptor.start_interval, DescriptorCount::new(2));
+        assert_eq!(descriptor.num_intervals.get(), Some(4));
+        assert_eq!(descriptor.frequency, DescriptorCount::UNSPECIFIED);
+        assert_eq!(descriptor.repeat.get(), None);
+        assert!(descriptor.validate().is_ok());
+        assert_eq!(
+            serde_json::to_value(&descriptor).unwrap()["frequency"],
+            serde_json::json!(-1)
+        );
+
+        let json = r#"{"payloadType":"USAGE","startInterval":-2}"#;
+        assert!(serde_json::from_str::<ReportDescriptor>(json).is_err());
+
+        let mut descriptor = ReportDescriptor::new(ReportType::Usage);
+        descriptor.frequency = DescriptorCount::new(0);
+        assert!(descriptor.validate().is_err());
+    }
+
     #[test]
     fn parses_minimal_report() {
         let example = r#"{"programID":"p1","eventID":"e1","clientName":"c","resources":[]}"#;
@@ -383,6 +613,46 @@ mod tests {
         );
     }
 
+    /// Counterparts implementing a later revision of the specification may send fields
+    /// this crate does not know yet, which must be ignored rather than rejected
+    #[test]
+    fn tolerates_fields_of_newer_revisions() {
+        fn add_unknown_fields(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Array(values) => values.iter_mut().for_each(add_unknown_fields),
+                serde_json::Value::Object(fields) => {
+                    fields.values_mut().for_each(add_unknown_fields);
+                    fields.insert("futureField".to_string(), serde_json::json!({"a": [1]}));
+                }
+                _ => {}
+            }
+        }
+
+        let report = serde_json::json!({
+            "id": "report-1",
+            "createdDateTime": "2023-06-15T09:30:00Z",
+            "modificationDateTime": "2023-06-15T09:30:00Z",
+            "objectType": "REPORT",
+            "programID": "program-1",
+            "eventID": "event-1",
+            "clientName": "ven-1",
+            "payloadDescriptors": [{"payloadType": "USAGE", "units": "KWH"}],
+            "resources": [{
+                "resourceName": "meter-1",
+                "intervalPeriod": {"start": "2023-06-15T09:30:00Z", "duration": "PT1H"},
+                "intervals": [{"id": 0, "payloads": [{"type": "USAGE", "values": [1.5]}]}]
+            }]
+        });
+        let expected: Report = serde_json::from_value(report.clone()).unwrap();
+
+        let mut extended = report;
+        add_unknown_fields(&mut extended);
+        assert_eq!(
+            serde_json::from_value::<Report>(extended).unwrap(),
+            expected
+        );
+    }
+
     #[test]
     fn test_resource_name_serialization() {
         assert_eq!(
@@ -457,22 +727,22 @@ mod tests {
                 client_name: "VEN-999".into(),
                 report_name: Some("Battery_usage_04112023".into()),
                 payload_descriptors: None,
-                resources: vec![Resource {
+                resources: vec![ReportResource {
                     resource_name: ResourceName::Private("RESOURCE-999".into()),
                     interval_period: Some(IntervalPeriod {
                         start: "2023-06-15T09:30:00Z".parse().unwrap(),
                         duration: Some(Duration::PT1H),
                         randomize_start: Some(Duration::PT1H),
                     }),
-                    intervals: vec![Interval {
+                    intervals: vec![ReportInterval {
                         id: 0,
                         interval_period: Some(IntervalPeriod {
                             start: "2023-06-15T09:30:00Z".parse().unwrap(),
                             duration: Some(Duration::PT1H),
                             randomize_start: Some(Duration::PT1H),
                         }),
-                        payloads: vec![ValuesMap {
-                            value_type: ValueType("PRICE".into()),
+                        payloads: vec![ReportValuesMap {
+                            value_type: ReportType::Private("PRICE".into()),
                             values: vec![Value::Number(0.17)],
                         }],
                     }],
@@ -485,6 +755,30 @@ mod tests {
             expected
         );
     }
+
+    #[test]
+    fn resource_interval_ids_are_validated() {
+        let content = |ids: &[i32]| {
+            let resource = ids.iter().fold(
+                ReportResource::new(ResourceName::AggregatedReport),
+                |resource, id| resource.with_interval(ReportInterval::new(*id, vec![])),
+            );
+            ReportContent {
+                object_type: None,
+                program_id: ProgramId("p1".parse().unwrap()),
+                event_id: EventId("e1".parse().unwrap()),
+                client_name: "c".to_string(),
+                report_name: None,
+                payload_descriptors: None,
+                resources: vec![],
+            }
+            .with_resource(resource)
+        };
+
+        assert!(content(&[0, 1]).validate().is_ok());
+        assert!(content(&[1, 1]).validate().is_err());
+        assert!(content(&[-1]).validate().is_err());
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
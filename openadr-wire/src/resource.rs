@@ -42,6 +42,17 @@ pub struct ResourceContent {
     pub targets: Option<Vec<ValuesMap>>,
 }
 
+impl ResourceContent {
+    pub fn new(name: impl ToString) -> ResourceContent {
+        ResourceContent {
+            object_type: Some(ObjectType::Resource),
+            resource_name: name.to_string(),
+            attributes: None,
+            targets: None,
+        }
+    }
+}
+
 /// Used as discriminator, e.g. notification.object
 #[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
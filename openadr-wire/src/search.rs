@@ -0,0 +1,35 @@
+//! Results of the cross-object search of a VTN, i.e., `GET /search?q=`
+
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{program::ProgramId, ven::VenId};
+
+/// The type of object a [`SearchHit`] refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SearchObjectType {
+    Program,
+    Event,
+    Ven,
+    Resource,
+}
+
+/// An object whose name or description matches a search query
+#[skip_serializing_none]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    /// The type of the object, which determines the endpoint to retrieve it from
+    pub object_type: SearchObjectType,
+    /// The ID of the object
+    pub id: String,
+    /// The name of the object, if it has one
+    pub name: Option<String>,
+    /// The VEN a resource belongs to, as needed to retrieve it from `/vens/{venID}/resources/{id}`
+    #[serde(rename = "venID")]
+    pub ven_id: Option<VenId>,
+    /// The program an event belongs to
+    #[serde(rename = "programID")]
+    pub program_id: Option<ProgramId>,
+}
@@ -3,15 +3,155 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
+use crate::{
+    resource::ResourceContent,
+    values_map::{Value, ValuesMap},
+    ven::VenContent,
+};
+
 #[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct TargetMap(pub Vec<TargetEntry>);
 
+impl TargetMap {
+    /// The target entries describing a VEN: its name and its own targets, e.g., its groups
+    pub fn from_ven(ven: &VenContent) -> Self {
+        let name = TargetEntry::new(TargetLabel::VENName, &ven.ven_name);
+        Self(vec![name]).union(&Self::from_values_maps(ven.targets.as_deref()))
+    }
+
+    /// The target entries describing a resource: its name and its own targets
+    pub fn from_resource(resource: &ResourceContent) -> Self {
+        let name = TargetEntry::new(TargetLabel::ResourceName, &resource.resource_name);
+        Self(vec![name]).union(&Self::from_values_maps(resource.targets.as_deref()))
+    }
+
+    /// The string values of the values maps, as used for the targets of VENs and resources
+    pub fn from_values_maps(values_maps: Option<&[ValuesMap]>) -> Self {
+        let entries = values_maps.into_iter().flatten().flat_map(|values_map| {
+            let label = TargetLabel::from(values_map.value_type.0.as_str());
+            values_map
+                .values
+                .iter()
+                .filter_map(move |value| match value {
+                    Value::String(value) => Some(TargetEntry::new(label.clone(), value)),
+                    _ => None,
+                })
+        });
+
+        Self::default().union(&Self(entries.collect()))
+    }
+
+    /// Each label and value of the map, i.e., an entry with multiple values is listed once per value
+    pub fn label_values(&self) -> impl Iterator<Item = (&TargetLabel, &str)> {
+        self.0.iter().flat_map(|entry| {
+            entry
+                .values
+                .iter()
+                .map(move |value| (&entry.label, value.as_str()))
+        })
+    }
+
+    /// Whether any entry of the map has the label and value
+    pub fn contains(&self, label: &TargetLabel, value: &str) -> bool {
+        self.0.iter().any(|entry| entry.contains(label, value))
+    }
+
+    /// All label values of both maps, without duplicates, as an entry per value
+    pub fn union(&self, other: &TargetMap) -> TargetMap {
+        let mut union = TargetMap(Vec::with_capacity(self.0.len() + other.0.len()));
+        for (label, value) in self.label_values().chain(other.label_values()) {
+            if !union.contains(label, value) {
+                union.0.push(TargetEntry::new(label.clone(), value));
+            }
+        }
+
+        union
+    }
+
+    /// The label values that occur in both maps, as an entry per value
+    pub fn intersection(&self, other: &TargetMap) -> TargetMap {
+        TargetMap(
+            self.label_values()
+                .filter(|(label, value)| other.contains(label, value))
+                .map(|(label, value)| TargetEntry::new(label.clone(), value))
+                .collect(),
+        )
+    }
+
+    /// Whether an object described by `attributes`, e.g., [`TargetMap::from_ven`], is targeted.
+    ///
+    /// The values of a single label are alternatives, while all labels must match,
+    /// e.g., `GROUP: [a, b], VEN_NAME: [c]` targets VEN `c` if it is in group `a` or `b`.
+    /// This holds both for multiple values in one entry and for multiple entries with the same label.
+    /// An empty map targets everything.
+    pub fn matches(&self, attributes: &TargetMap) -> bool {
+        self.label_values().all(|(required, _)| {
+            self.label_values()
+                .filter(|(label, _)| *label == required)
+                .any(|(label, value)| attributes.contains(label, value))
+        })
+    }
+
+    /// Whether the VEN itself, or any of its resources, is targeted.
+    /// The resources are combined with the attributes of the VEN,
+    /// such that, e.g., a resource of a VEN in a targeted group is targeted as well.
+    pub fn matches_ven(&self, ven: &VenContent) -> bool {
+        self.matches_ven_resources(
+            ven,
+            ven.resources
+                .iter()
+                .flatten()
+                .map(|resource| &resource.content),
+        )
+    }
+
+    /// Like [`Self::matches_ven`], but with the resources given separately from the VEN
+    pub fn matches_ven_resources<'a>(
+        &self,
+        ven: &VenContent,
+        resources: impl IntoIterator<Item = &'a ResourceContent>,
+    ) -> bool {
+        let ven_attributes = Self::from_ven(ven);
+
+        self.matches(&ven_attributes)
+            || resources
+                .into_iter()
+                .any(|resource| self.matches(&ven_attributes.union(&Self::from_resource(resource))))
+    }
+}
+
 // TODO: Handle strong typing of values
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TargetEntry {
     #[serde(rename = "type")]
     pub label: TargetLabel,
-    pub values: [String; 1],
+    /// Alternative values of the label, any of which matches
+    pub values: Vec<String>,
+}
+
+impl TargetEntry {
+    pub fn new(label: TargetLabel, value: impl ToString) -> Self {
+        Self {
+            label,
+            values: vec![value.to_string()],
+        }
+    }
+
+    pub fn with_values(
+        label: TargetLabel,
+        values: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        Self {
+            label,
+            values: values.into_iter().map(|value| value.to_string()).collect(),
+        }
+    }
+
+    /// Whether the entry has the label, and the value is one of its values
+    pub fn contains(&self, label: &TargetLabel, value: &str) -> bool {
+        &self.label == label && self.values.iter().any(|own| own == value)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Eq, Debug)]
@@ -40,6 +180,22 @@ pub enum TargetLabel {
 }
 
 impl TargetLabel {
+    /// All target labels defined by the OpenADR specification
+    pub const STANDARD: [TargetLabel; 7] = [
+        TargetLabel::PowerServiceLocation,
+        TargetLabel::ServiceArea,
+        TargetLabel::Group,
+        TargetLabel::ResourceName,
+        TargetLabel::VENName,
+        TargetLabel::EventName,
+        TargetLabel::ProgramName,
+    ];
+
+    /// Whether this label is not defined by the OpenADR specification
+    pub fn is_private(&self) -> bool {
+        matches!(self, TargetLabel::Private(_))
+    }
+
     pub fn as_str(&self) -> &str {
         match self {
             TargetLabel::PowerServiceLocation => "POWER_SERVICE_LOCATION",
@@ -54,6 +210,15 @@ impl TargetLabel {
     }
 }
 
+impl From<&str> for TargetLabel {
+    fn from(label: &str) -> Self {
+        TargetLabel::STANDARD
+            .into_iter()
+            .find(|standard| standard.as_str() == label)
+            .unwrap_or_else(|| TargetLabel::Private(label.to_string()))
+    }
+}
+
 impl Display for TargetLabel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str(self.as_str())
@@ -83,4 +248,152 @@ mod tests {
             TargetLabel::Private(String::from("something else"))
         );
     }
+
+    fn map(entries: &[(TargetLabel, &str)]) -> TargetMap {
+        TargetMap(
+            entries
+                .iter()
+                .map(|(label, value)| TargetEntry::new(label.clone(), value))
+                .collect(),
+        )
+    }
+
+    fn ven() -> VenContent {
+        VenContent {
+            object_type: None,
+            ven_name: "ven-1".to_string(),
+            attributes: None,
+            targets: Some(vec![ValuesMap {
+                value_type: crate::values_map::ValueType("GROUP".to_string()),
+                values: vec![Value::String("group-1".to_string()), Value::Integer(1)],
+            }]),
+            resources: None,
+        }
+    }
+
+    #[test]
+    fn union_and_intersection() {
+        let a = map(&[(TargetLabel::Group, "a"), (TargetLabel::Group, "b")]);
+        let b = map(&[(TargetLabel::Group, "b"), (TargetLabel::VENName, "c")]);
+
+        assert_eq!(
+            a.union(&b),
+            map(&[
+                (TargetLabel::Group, "a"),
+                (TargetLabel::Group, "b"),
+                (TargetLabel::VENName, "c")
+            ])
+        );
+        assert_eq!(a.intersection(&b), map(&[(TargetLabel::Group, "b")]));
+        assert_eq!(a.intersection(&TargetMap::default()), TargetMap::default());
+    }
+
+    #[test]
+    fn from_ven() {
+        assert_eq!(
+            TargetMap::from_ven(&ven()),
+            map(&[
+                (TargetLabel::VENName, "ven-1"),
+                (TargetLabel::Group, "group-1")
+            ])
+        );
+    }
+
+    #[test]
+    fn matches() {
+        let ven = TargetMap::from_ven(&ven());
+
+        assert!(TargetMap::default().matches(&ven));
+        assert!(map(&[(TargetLabel::Group, "group-1")]).matches(&ven));
+        // values of the same label are alternatives
+        assert!(map(&[
+            (TargetLabel::Group, "group-2"),
+            (TargetLabel::Group, "group-1")
+        ])
+        .matches(&ven));
+        // all labels must match
+        assert!(map(&[
+            (TargetLabel::Group, "group-1"),
+            (TargetLabel::VENName, "ven-1")
+        ])
+        .matches(&ven));
+        assert!(!map(&[
+            (TargetLabel::Group, "group-1"),
+            (TargetLabel::VENName, "ven-2")
+        ])
+        .matches(&ven));
+        assert!(!map(&[(TargetLabel::ServiceArea, "area-1")]).matches(&ven));
+    }
+
+    #[test]
+    fn multiple_values() {
+        let json = r#"[{"type":"GROUP","values":["group-2","group-1"]}]"#;
+        let targets: TargetMap = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            targets,
+            TargetMap(vec![TargetEntry::with_values(
+                TargetLabel::Group,
+                ["group-2", "group-1"]
+            )])
+        );
+        assert_eq!(serde_json::to_string(&targets).unwrap(), json);
+
+        assert!(targets.contains(&TargetLabel::Group, "group-1"));
+        assert!(!targets.contains(&TargetLabel::VENName, "group-1"));
+        assert!(targets.matches(&TargetMap::from_ven(&ven())));
+
+        let other_groups = TargetMap(vec![TargetEntry::with_values(
+            TargetLabel::Group,
+            ["group-2", "group-3"],
+        )]);
+        assert!(!other_groups.matches(&TargetMap::from_ven(&ven())));
+
+        // the set operations result in an entry per value
+        assert_eq!(
+            targets.union(&other_groups),
+            map(&[
+                (TargetLabel::Group, "group-2"),
+                (TargetLabel::Group, "group-1"),
+                (TargetLabel::Group, "group-3")
+            ])
+        );
+        assert_eq!(
+            targets.intersection(&other_groups),
+            map(&[(TargetLabel::Group, "group-2")])
+        );
+    }
+
+    #[test]
+    fn matches_ven_resources() {
+        let ven = ven();
+        let resource = ResourceContent {
+            object_type: None,
+            resource_name: "resource-1".to_string(),
+            attributes: None,
+            targets: None,
+        };
+
+        let targets = map(&[(TargetLabel::ResourceName, "resource-1")]);
+        assert!(!targets.matches_ven_resources(&ven, []));
+        assert!(targets.matches_ven_resources(&ven, [&resource]));
+
+        // the resource inherits the group of its VEN
+        let targets = map(&[
+            (TargetLabel::ResourceName, "resource-1"),
+            (TargetLabel::Group, "group-1"),
+        ]);
+        assert!(targets.matches_ven_resources(&ven, [&resource]));
+    }
+
+    #[test]
+    fn test_target_from_str() {
+        for label in TargetLabel::STANDARD {
+            assert_eq!(TargetLabel::from(label.as_str()), label);
+            assert!(!label.is_private());
+        }
+        assert_eq!(
+            TargetLabel::from("METER_ID"),
+            TargetLabel::Private(String::from("METER_ID"))
+        );
+    }
 }
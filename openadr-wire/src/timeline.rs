@@ -0,0 +1,818 @@
+#![allow(dead_code)]
+use std::{
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    ops::Range,
+};
+
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+use crate::{
+    event::{fmt_datetime, schedule_row, EventContent, EventValuesMap, Priority},
+    interval::IntervalPeriod,
+    program::ProgramContent,
+    Event, Program,
+};
+
+/// The time range in which an interval of a [`Timeline`] is active.
+///
+/// Intervals without a duration end at `DateTime::<Utc>::MAX_UTC`.
+pub type IntervalRange = Range<DateTime<Utc>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InternalInterval {
+    /// Index of the event in the events the timeline was built from.
+    /// Also used so that split itervals with a randomized start don't start randomly twice
+    id: u32,
+    /// Relative priority of event
+    priority: Priority,
+    /// Indicates a randomization time that may be applied to start.
+    randomize_start: Option<chrono::Duration>,
+    /// The actual values that are active during this interval
+    value_map: Vec<EventValuesMap>,
+}
+
+/// How a [`Timeline`] resolves the intervals of different events that overlap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeMode {
+    /// The event with the highest [`Priority`] wins, as required by the specification.
+    /// Of events with equal priority, the one that comes last in the given events wins.
+    #[default]
+    Priority,
+    /// Ignore priorities, the event that comes last in the given events wins
+    Sequential,
+}
+
+/// Options for building a [`Timeline`] with [`Timeline::from_events_with`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimelineOptions {
+    pub merge_mode: MergeMode,
+    /// Apply the `randomizeStart` of intervals with this seed, see [`Self::randomize_start`]
+    pub randomize_start_seed: Option<u64>,
+}
+
+impl TimelineOptions {
+    pub fn merge_mode(mut self, merge_mode: MergeMode) -> Self {
+        self.merge_mode = merge_mode;
+        self
+    }
+
+    /// Delay the start of every interval with a `randomizeStart` by a pseudo-random part of it,
+    /// such that a fleet of devices does not start consuming all at once.
+    ///
+    /// The offset is derived from the seed and the event, so rebuilding the timeline with the
+    /// same seed, e.g., after polling the VTN again, keeps the offsets of the events the same.
+    /// Use a different seed for every device, e.g., derived from the name of the VEN or resource.
+    /// The intervals of the resulting timeline no longer have a [`Interval::randomize_start`].
+    pub fn randomize_start(mut self, seed: u64) -> Self {
+        self.randomize_start_seed = Some(seed);
+        self
+    }
+}
+
+impl From<MergeMode> for TimelineOptions {
+    fn from(merge_mode: MergeMode) -> Self {
+        Self::default().merge_mode(merge_mode)
+    }
+}
+
+/// A pseudo-random number in `[0, 1)` for the event, the same for every call with the same seed.
+///
+/// Implemented here instead of with a hasher of `std`, which does not guarantee stable output
+/// across Rust versions, such that the offsets of a device do not change with an update.
+fn randomization_fraction(seed: u64, event: &EventContent) -> f64 {
+    let first_start = event
+        .intervals
+        .first()
+        .and_then(|interval| interval.interval_period.as_ref())
+        .or(event.interval_period.as_ref())
+        .map(|period| period.start.timestamp_millis())
+        .unwrap_or_default();
+    let key = format!(
+        "{}/{}/{first_start}",
+        event.program_id,
+        event.event_name.as_deref().unwrap_or_default()
+    );
+
+    // FNV-1a, followed by the finalizer of splitmix64 to spread the bits
+    let mut hash = 0xcbf29ce484222325 ^ seed;
+    for byte in key.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+    hash ^= hash >> 31;
+
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// A sequence of ordered, non-overlapping intervals and associated values.
+///
+/// Intervals are sorted by their timestamp. The intervals will not overlap, but there may be gaps
+/// between intervals.
+#[allow(unused)]
+#[derive(Clone, Default, Debug)]
+pub struct Timeline {
+    data: rangemap::RangeMap<DateTime<Utc>, InternalInterval>,
+    /// The last time the data of this timeline was successfully retrieved from the VTN
+    last_contact: Option<DateTime<Utc>>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self {
+            data: rangemap::RangeMap::new(),
+            last_contact: None,
+        }
+    }
+
+    /// Record when the data of this timeline was last successfully retrieved from the VTN
+    pub fn with_last_contact(mut self, last_contact: DateTime<Utc>) -> Self {
+        self.last_contact = Some(last_contact);
+        self
+    }
+
+    /// The last time the data of this timeline was successfully retrieved from the VTN, if known
+    pub fn last_contact(&self) -> Option<DateTime<Utc>> {
+        self.last_contact
+    }
+
+    /// How long ago the data of this timeline was retrieved from the VTN.
+    ///
+    /// Returns `None` if it is unknown when the VTN was last contacted.
+    pub fn staleness(&self) -> Option<chrono::Duration> {
+        self.staleness_at(Utc::now())
+    }
+
+    /// Like [`Self::staleness`], but relative to the given moment instead of now
+    pub fn staleness_at(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        self.last_contact.map(|last_contact| now - last_contact)
+    }
+
+    /// Let a [`StalenessPolicy`] decide which timeline to follow, given how stale this one is.
+    ///
+    /// Timelines without a known last contact are considered to be up-to-date.
+    pub fn apply_staleness_policy(self, policy: &dyn StalenessPolicy) -> Timeline {
+        match self.staleness() {
+            Some(staleness) => policy.apply(self, staleness),
+            None => self,
+        }
+    }
+
+    /// Build the timeline of a program from its events, as retrieved from a VTN.
+    /// The [`Interval::event`] of every interval is an index in `events`.
+    ///
+    /// Returns `None` if an interval has no interval period, see [`Self::from_events`].
+    pub fn from_program_events(program: &Program, events: &[Event]) -> Option<Self> {
+        Self::from_events(
+            &program.content,
+            events.iter().map(|event| &event.content).collect(),
+        )
+    }
+
+    /// Build a timeline in which overlapping events are resolved by [`MergeMode::Priority`].
+    ///
+    /// Returns:
+    ///
+    /// - `None` if no interval is specified in the input
+    /// - `Some(timeline)` otherwise
+    pub fn from_events(program: &ProgramContent, events: Vec<&EventContent>) -> Option<Self> {
+        Self::from_events_with(program, events, MergeMode::Priority)
+    }
+
+    /// Like [`Self::from_events`], but resolves overlapping events according to the merge mode
+    /// of the `options`, and optionally randomizes the start of intervals.
+    ///
+    /// The [`Interval::event`] of every interval of the timeline is the index in `events`
+    /// of the event that won it.
+    pub fn from_events_with(
+        program: &ProgramContent,
+        events: Vec<&EventContent>,
+        options: impl Into<TimelineOptions>,
+    ) -> Option<Self> {
+        let TimelineOptions {
+            merge_mode: mode,
+            randomize_start_seed,
+        } = options.into();
+        let mut data = Self::default();
+
+        // later events overwrite earlier ones, so the winners must come last
+        let mut events: Vec<_> = events.into_iter().enumerate().collect();
+        if mode == MergeMode::Priority {
+            // events without a priority of their own get the default priority of the program
+            events.sort_by_key(|(_, e)| e.effective_priority(program));
+        }
+
+        for (id, event) in events {
+            let priority = event.effective_priority(program);
+            let randomization =
+                randomize_start_seed.map(|seed| randomization_fraction(seed, event));
+
+            // SPEC ASSUMPTION: At least one of the following `interval_period`s must be given on the program,
+            // on the event, or on the interval
+            let default_period = event
+                .interval_period
+                .as_ref()
+                .or(program.interval_period.as_ref());
+
+            for event_interval in &event.intervals {
+                // use the even't interval period when the interval doesn't specify one
+                let period = event_interval.interval_period.as_ref().or(default_period)?;
+
+                let IntervalPeriod {
+                    start,
+                    duration,
+                    randomize_start,
+                } = period;
+
+                let mut range = match duration {
+                    Some(duration) => *start..*start + duration.to_chrono_at_datetime(*start),
+                    None => *start..DateTime::<Utc>::MAX_UTC,
+                };
+                let mut randomize_start = randomize_start
+                    .as_ref()
+                    .map(|d| d.to_chrono_at_datetime(*start));
+
+                if let (Some(fraction), Some(max_offset)) = (randomization, randomize_start) {
+                    let offset = max_offset.num_milliseconds() as f64 * fraction;
+                    range.start += chrono::Duration::milliseconds(offset as i64);
+                    randomize_start = None;
+
+                    // the interval was delayed past its end
+                    if range.is_empty() {
+                        continue;
+                    }
+                }
+
+                let interval = InternalInterval {
+                    id: id as u32,
+                    randomize_start,
+                    value_map: event_interval.payloads.clone(),
+                    priority,
+                };
+
+                for (existing_range, existing) in data.data.overlapping(&range) {
+                    if mode == MergeMode::Priority && existing.priority == priority {
+                        warn!(?existing_range, ?existing, new_range = ?range, new = ?interval, "Overlapping ranges with equal priority");
+                    }
+                }
+
+                data.data.insert(range, interval);
+            }
+        }
+
+        Some(data)
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            iter: self.data.iter(),
+            seen: HashSet::default(),
+        }
+    }
+
+    pub fn at_datetime(
+        &self,
+        datetime: &DateTime<Utc>,
+    ) -> Option<(&Range<DateTime<Utc>>, Interval<'_>)> {
+        let (range, internal_interval) = self.data.get_key_value(datetime)?;
+
+        let interval = Interval {
+            event: internal_interval.id as usize,
+            randomize_start: internal_interval.randomize_start,
+            value_map: &internal_interval.value_map,
+        };
+
+        Some((range, interval))
+    }
+
+    pub fn next_update(&self, datetime: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if let Some((k, _)) = self.at_datetime(datetime) {
+            return Some(k.end);
+        }
+
+        let (last_range, _) = self.data.last_range_value()?;
+
+        let (range, _) = self.data.overlapping(*datetime..last_range.end).next()?;
+
+        Some(range.start)
+    }
+
+    /// Render the timeline as a table for humans, one `start → end → values` row per interval.
+    ///
+    /// ```text
+    /// 2024-01-01T00:00:00Z → 2024-01-01T01:00:00Z → PRICE 0.25
+    /// 2024-01-01T01:00:00Z → 2024-01-01T02:00:00Z → PRICE 0.5
+    /// ```
+    pub fn to_table(&self) -> String {
+        self.iter()
+            .map(|(range, interval)| {
+                let values = interval
+                    .value_map
+                    .iter()
+                    .map(|values| values.to_string())
+                    .collect::<Vec<_>>();
+
+                // intervals without a duration end at `DateTime::<Utc>::MAX_UTC`
+                let end = Some(range.end).filter(|end| *end != DateTime::<Utc>::MAX_UTC);
+                schedule_row(Some(range.start), end, &values.join("; "))
+            })
+            .collect()
+    }
+}
+
+impl Display for Timeline {
+    /// A one-line summary of the timeline, e.g. for log messages
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let (Some((first, _)), Some((last, _))) =
+            (self.data.first_range_value(), self.data.last_range_value())
+        else {
+            return write!(f, "empty timeline");
+        };
+
+        write!(
+            f,
+            "timeline of {} interval(s) from {}",
+            self.data.len(),
+            fmt_datetime(&first.start)
+        )?;
+        if last.end != DateTime::<Utc>::MAX_UTC {
+            write!(f, " to {}", fmt_datetime(&last.end))?;
+        }
+        if let Some(last_contact) = self.last_contact {
+            write!(f, ", last contact at {}", fmt_datetime(&last_contact))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decides how a VEN should behave when it could not reach the VTN for some time,
+/// e.g., during an outage of the VTN.
+pub trait StalenessPolicy: Send + Sync {
+    /// Returns the timeline to follow, given the last known timeline and how stale it is
+    fn apply(&self, timeline: Timeline, staleness: chrono::Duration) -> Timeline;
+}
+
+/// Revert to a default timeline when the VTN could not be reached for too long
+#[derive(Debug, Clone)]
+pub struct RevertToDefault {
+    pub max_staleness: chrono::Duration,
+    pub default: Timeline,
+}
+
+impl StalenessPolicy for RevertToDefault {
+    fn apply(&self, timeline: Timeline, staleness: chrono::Duration) -> Timeline {
+        if staleness > self.max_staleness {
+            warn!(
+                %staleness,
+                last_contact = ?timeline.last_contact(),
+                "no contact with the VTN for too long, reverting to the default timeline"
+            );
+            self.default.clone()
+        } else {
+            timeline
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interval<'a> {
+    /// Index of the event this interval belongs to, in the events the timeline was built from,
+    /// i.e., the event that won this interval if events overlap
+    pub event: usize,
+    /// Indicates a randomization time that may be applied to start.
+    pub randomize_start: Option<chrono::Duration>,
+    /// The actual values that are active during this interval
+    pub value_map: &'a [EventValuesMap],
+}
+
+pub struct Iter<'a> {
+    iter: rangemap::map::Iter<'a, DateTime<Utc>, InternalInterval>,
+    seen: HashSet<u32>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a Range<DateTime<Utc>>, Interval<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (range, internal) = self.iter.next()?;
+
+        let interval = Interval {
+            event: internal.id as usize,
+            // only the first occurence of an id should randomize its start
+            randomize_start: match self.seen.insert(internal.id) {
+                true => internal.randomize_start,
+                false => None,
+            },
+            value_map: &internal.value_map,
+        };
+
+        Some((range, interval))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Range;
+
+    use chrono::{DateTime, Duration, Utc};
+
+    use crate::{event::EventInterval, program::ProgramId, values_map::Value};
+
+    use super::*;
+
+    fn test_program_id() -> ProgramId {
+        ProgramId::new("test-program-id").unwrap()
+    }
+
+    fn test_event_content(range: Range<u32>, value: i64) -> EventContent {
+        EventContent::new(
+            test_program_id(),
+            vec![event_interval_with_value(range, value)],
+        )
+    }
+
+    fn event_interval_with_value(range: Range<u32>, value: i64) -> EventInterval {
+        EventInterval {
+            id: range.start as _,
+            interval_period: Some(IntervalPeriod {
+                start: DateTime::UNIX_EPOCH + Duration::hours(range.start.into()),
+                duration: Some(crate::Duration::hours((range.end - range.start) as _)),
+                randomize_start: None,
+            }),
+            payloads: vec![EventValuesMap {
+                value_type: crate::event::EventType::Price,
+                values: vec![Value::Integer(value)],
+            }],
+        }
+    }
+
+    fn interval_with_value(
+        id: u32,
+        range: Range<u32>,
+        value: i64,
+        priority: Priority,
+    ) -> (Range<DateTime<Utc>>, InternalInterval) {
+        let start = DateTime::UNIX_EPOCH + Duration::hours(range.start.into());
+        let end = DateTime::UNIX_EPOCH + Duration::hours(range.end.into());
+
+        (
+            start..end,
+            InternalInterval {
+                id,
+                randomize_start: None,
+                value_map: vec![EventValuesMap {
+                    value_type: crate::event::EventType::Price,
+                    values: vec![Value::Integer(value)],
+                }],
+                priority,
+            },
+        )
+    }
+
+    // the spec does not specify the behavior when two intervals with the same priority overlap.
+    // Our current implementation uses `RangeMap`, and its behavior is to overwrite the existing
+    // range with a new one. In other words: the event that is inserted last wins.
+    #[test]
+    fn overlap_same_priority() {
+        let program = ProgramContent::new("p");
+
+        let event1 = test_event_content(0..10, 42);
+        let event2 = test_event_content(5..15, 43);
+
+        // first come, last serve
+        let tl1 = Timeline::from_events(&program, vec![&event1, &event2]).unwrap();
+        assert_eq!(
+            tl1.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(0, 0..5, 42, Priority::UNSPECIFIED),
+                interval_with_value(1, 5..15, 43, Priority::UNSPECIFIED),
+            ]
+        );
+
+        // first come, last serve
+        let tl2 = Timeline::from_events(&program, vec![&event2, &event1]).unwrap();
+        assert_eq!(
+            tl2.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(1, 0..10, 42, Priority::UNSPECIFIED),
+                interval_with_value(0, 10..15, 43, Priority::UNSPECIFIED),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlap_lower_priority() {
+        let event1 = test_event_content(0..10, 42).with_priority(Priority::new(1));
+        let event2 = test_event_content(5..15, 43).with_priority(Priority::new(2));
+
+        let tl = Timeline::from_events(&ProgramContent::new("p"), vec![&event1, &event2]).unwrap();
+        assert_eq!(
+            tl.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(0, 0..10, 42, Priority::new(1)),
+                interval_with_value(1, 10..15, 43, Priority::new(2)),
+            ],
+            "a lower priority event MUST NOT overwrite a higher priority one",
+        );
+
+        let tl = Timeline::from_events(&ProgramContent::new("p"), vec![&event2, &event1]).unwrap();
+        assert_eq!(
+            tl.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(1, 0..10, 42, Priority::new(1)),
+                interval_with_value(0, 10..15, 43, Priority::new(2)),
+            ],
+            "a lower priority event MUST NOT overwrite a higher priority one",
+        );
+    }
+
+    #[test]
+    fn overlap_higher_priority() {
+        let event1 = test_event_content(0..10, 42).with_priority(Priority::new(2));
+        let event2 = test_event_content(5..15, 43).with_priority(Priority::new(1));
+
+        let tl = Timeline::from_events(&ProgramContent::new("p"), vec![&event1, &event2]).unwrap();
+        assert_eq!(
+            tl.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(0, 0..5, 42, Priority::new(2)),
+                interval_with_value(1, 5..15, 43, Priority::new(1)),
+            ],
+            "a higher priority event MUST overwrite a lower priority one",
+        );
+
+        let tl = Timeline::from_events(&ProgramContent::new("p"), vec![&event2, &event1]).unwrap();
+        assert_eq!(
+            tl.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(1, 0..5, 42, Priority::new(2)),
+                interval_with_value(0, 5..15, 43, Priority::new(1)),
+            ],
+            "a higher priority event MUST overwrite a lower priority one",
+        );
+    }
+
+    /// The winner of every interval, as `(start hour, end hour, event)`
+    fn winners(timeline: &Timeline) -> Vec<(i64, i64, usize)> {
+        let hours = |datetime: &DateTime<Utc>| (*datetime - DateTime::UNIX_EPOCH).num_hours();
+        timeline
+            .iter()
+            .map(|(range, interval)| (hours(&range.start), hours(&range.end), interval.event))
+            .collect()
+    }
+
+    #[test]
+    fn winners_of_ties() {
+        let program = ProgramContent::new("p");
+        let event1 = test_event_content(0..10, 42).with_priority(Priority::new(1));
+        let event2 = test_event_content(5..15, 43).with_priority(Priority::new(1));
+
+        let tl = Timeline::from_events(&program, vec![&event1, &event2]).unwrap();
+        assert_eq!(winners(&tl), vec![(0, 5, 0), (5, 15, 1)]);
+
+        let tl = Timeline::from_events(&program, vec![&event2, &event1]).unwrap();
+        assert_eq!(
+            winners(&tl),
+            vec![(0, 10, 1), (10, 15, 0)],
+            "of events with equal priority, the last one wins",
+        );
+    }
+
+    #[test]
+    fn winners_of_nested_overlaps() {
+        let program = ProgramContent::new("p");
+        let outer = test_event_content(0..10, 42).with_priority(Priority::new(3));
+        let middle = test_event_content(2..8, 43).with_priority(Priority::new(2));
+        let inner = test_event_content(4..6, 44).with_priority(Priority::new(1));
+
+        let tl = Timeline::from_events(&program, vec![&outer, &middle, &inner]).unwrap();
+        assert_eq!(
+            winners(&tl),
+            vec![(0, 2, 0), (2, 4, 1), (4, 6, 2), (6, 8, 1), (8, 10, 0)]
+        );
+
+        // the order of the events does not matter, only their priority
+        let tl = Timeline::from_events(&program, vec![&inner, &middle, &outer]).unwrap();
+        assert_eq!(
+            winners(&tl),
+            vec![(0, 2, 2), (2, 4, 1), (4, 6, 0), (6, 8, 1), (8, 10, 2)]
+        );
+        assert_eq!(
+            tl.at_datetime(&(DateTime::UNIX_EPOCH + Duration::hours(5)))
+                .unwrap()
+                .1
+                .value_map[0]
+                .values,
+            vec![Value::Integer(44)]
+        );
+    }
+
+    #[test]
+    fn sequential_merge_ignores_priority() {
+        let program = ProgramContent::new("p");
+        let event1 = test_event_content(0..10, 42).with_priority(Priority::new(1));
+        let event2 = test_event_content(5..15, 43).with_priority(Priority::new(2));
+
+        let tl =
+            Timeline::from_events_with(&program, vec![&event1, &event2], MergeMode::Sequential)
+                .unwrap();
+        assert_eq!(winners(&tl), vec![(0, 5, 0), (5, 15, 1)]);
+
+        let tl = Timeline::from_events_with(&program, vec![&event1, &event2], MergeMode::Priority)
+            .unwrap();
+        assert_eq!(winners(&tl), vec![(0, 10, 0), (10, 15, 1)]);
+    }
+
+    #[test]
+    fn program_default_priority() {
+        let program = ProgramContent {
+            default_priority: Some(Priority::new(1)),
+            ..ProgramContent::new("p")
+        };
+        let event1 = test_event_content(0..10, 42).with_priority(Priority::new(2));
+        let event2 = test_event_content(5..15, 43);
+
+        let tl = Timeline::from_events(&program, vec![&event2, &event1]).unwrap();
+        assert_eq!(
+            tl.data.into_iter().collect::<Vec<_>>(),
+            vec![
+                interval_with_value(1, 0..5, 42, Priority::new(2)),
+                interval_with_value(0, 5..15, 43, Priority::new(1)),
+            ],
+            "an event without a priority MUST get the default priority of its program",
+        );
+    }
+
+    #[test]
+    fn staleness() {
+        let event = test_event_content(0..10, 42);
+        let last_contact = DateTime::UNIX_EPOCH + Duration::hours(2);
+
+        let tl = Timeline::from_events(&ProgramContent::new("p"), vec![&event]).unwrap();
+        assert_eq!(tl.staleness(), None);
+
+        let tl = tl.with_last_contact(last_contact);
+        assert_eq!(
+            tl.staleness_at(last_contact + Duration::hours(3)),
+            Some(Duration::hours(3))
+        );
+
+        let policy = RevertToDefault {
+            max_staleness: Duration::hours(1),
+            default: Timeline::new(),
+        };
+        assert_eq!(
+            policy
+                .apply(tl.clone(), Duration::minutes(30))
+                .iter()
+                .count(),
+            1
+        );
+        assert_eq!(
+            policy.apply(tl.clone(), Duration::hours(3)).iter().count(),
+            0
+        );
+
+        // the epoch is long ago, so the default applies
+        assert_eq!(tl.apply_staleness_policy(&policy).iter().count(), 0);
+    }
+
+    #[test]
+    fn randomize_start_not_duplicated() {
+        let event1 = test_event_content(5..10, 42).with_priority(Priority::MAX);
+
+        let event2 = {
+            let range = 0..15;
+            let value = 43;
+            EventContent::new(
+                test_program_id(),
+                vec![EventInterval {
+                    id: range.start as _,
+                    interval_period: Some(IntervalPeriod {
+                        start: DateTime::UNIX_EPOCH + Duration::hours(range.start.into()),
+                        duration: Some(crate::Duration::hours((range.end - range.start) as _)),
+                        randomize_start: Some(crate::Duration::hours(5.0)),
+                    }),
+                    payloads: vec![EventValuesMap {
+                        value_type: crate::event::EventType::Price,
+                        values: vec![Value::Integer(value)],
+                    }],
+                }],
+            )
+        };
+
+        let tl = Timeline::from_events(&ProgramContent::new("p"), vec![&event1, &event2]).unwrap();
+        assert_eq!(
+            tl.iter().map(|(_, i)| i).collect::<Vec<_>>(),
+            vec![
+                Interval {
+                    event: 1,
+                    randomize_start: Some(Duration::hours(5)),
+                    value_map: &[EventValuesMap {
+                        value_type: crate::event::EventType::Price,
+                        values: vec![Value::Integer(43)],
+                    }],
+                },
+                Interval {
+                    event: 0,
+                    randomize_start: None,
+                    value_map: &[EventValuesMap {
+                        value_type: crate::event::EventType::Price,
+                        values: vec![Value::Integer(42)],
+                    }],
+                },
+                Interval {
+                    event: 1,
+                    randomize_start: None,
+                    value_map: &[EventValuesMap {
+                        value_type: crate::event::EventType::Price,
+                        values: vec![Value::Integer(43)],
+                    }],
+                },
+            ],
+            "when an event is split, only the first interval should retain `randomize_start`",
+        );
+    }
+
+    fn randomized_event(randomize_start: crate::Duration) -> EventContent {
+        let mut event = test_event_content(0..10, 42).with_event_name("event-1");
+        event.intervals[0]
+            .interval_period
+            .as_mut()
+            .unwrap()
+            .randomize_start = Some(randomize_start);
+        event
+    }
+
+    fn first_range(timeline: &Timeline) -> Range<DateTime<Utc>> {
+        timeline.iter().next().unwrap().0.clone()
+    }
+
+    #[test]
+    fn randomize_start_with_seed() {
+        let program = ProgramContent::new("p");
+        let event = randomized_event(crate::Duration::hours(1.0));
+        let randomized = |seed| {
+            let options = TimelineOptions::default().randomize_start(seed);
+            Timeline::from_events_with(&program, vec![&event], options).unwrap()
+        };
+
+        // without a seed, applying the randomization is up to the user
+        let tl = Timeline::from_events(&program, vec![&event]).unwrap();
+        assert_eq!(first_range(&tl).start, DateTime::UNIX_EPOCH);
+        assert_eq!(
+            tl.iter().next().unwrap().1.randomize_start,
+            Some(Duration::hours(1))
+        );
+
+        let mut starts = HashSet::new();
+        for seed in 0..20 {
+            let tl = randomized(seed);
+            let range = first_range(&tl);
+            assert!(range.start >= DateTime::UNIX_EPOCH);
+            assert!(range.start < DateTime::UNIX_EPOCH + Duration::hours(1));
+            assert_eq!(range.end, DateTime::UNIX_EPOCH + Duration::hours(10));
+            assert_eq!(tl.iter().next().unwrap().1.randomize_start, None);
+
+            // the same seed results in the same offset
+            assert_eq!(first_range(&randomized(seed)), range);
+            starts.insert(range.start);
+        }
+        assert!(starts.len() > 1, "different seeds should stagger the start");
+    }
+
+    #[test]
+    fn randomize_start_backwards() {
+        let program = ProgramContent::new("p");
+        let event = randomized_event("-PT1H".parse().unwrap());
+        let options = TimelineOptions::default().randomize_start(7);
+        let tl = Timeline::from_events_with(&program, vec![&event], options).unwrap();
+
+        let range = first_range(&tl);
+        assert!(range.start <= DateTime::UNIX_EPOCH);
+        assert!(range.start > DateTime::UNIX_EPOCH - Duration::hours(1));
+    }
+
+    #[test]
+    fn render() {
+        let program = ProgramContent::new("p");
+        let event1 = test_event_content(0..2, 42);
+        let event2 = test_event_content(3..4, 43);
+
+        let timeline = Timeline::from_events(&program, vec![&event1, &event2]).unwrap();
+        assert_eq!(
+            timeline.to_table(),
+            "1970-01-01T00:00:00Z → 1970-01-01T02:00:00Z → PRICE 42\n\
+             1970-01-01T03:00:00Z → 1970-01-01T04:00:00Z → PRICE 43\n"
+        );
+        assert_eq!(
+            timeline.to_string(),
+            "timeline of 2 interval(s) from 1970-01-01T00:00:00Z to 1970-01-01T04:00:00Z"
+        );
+        assert_eq!(Timeline::new().to_string(), "empty timeline");
+    }
+}
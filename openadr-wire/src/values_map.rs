@@ -1,10 +1,12 @@
 //! Helper types to realize type values relations
 
-use serde::{Deserialize, Serialize};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use std::fmt::{Display, Formatter};
 
 /// ValuesMap : Represents one or more values associated with a type. E.g. a type of PRICE contains a single float value.
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ValuesMap {
     /// Enumerated or private string signifying the nature of values. E.G. \"PRICE\" indicates value is to be interpreted as a currency.
     #[serde(rename = "type")]
@@ -13,6 +15,50 @@ pub struct ValuesMap {
     pub values: Vec<Value>,
 }
 
+impl ValuesMap {
+    /// The type of the values maps describing a single geographic point, see [`crate::Attribute::Location`]
+    pub const LOCATION: &'static str = "LOCATION";
+
+    pub fn new(value_type: impl Into<String>, values: impl IntoIterator<Item = Value>) -> Self {
+        Self {
+            value_type: ValueType(value_type.into()),
+            values: values.into_iter().collect(),
+        }
+    }
+
+    /// A `LOCATION` values map, containing the longitude and latitude as two floats
+    pub fn location(location: GeoLocation) -> Self {
+        Self::new(
+            Self::LOCATION,
+            [
+                Value::Number(location.longitude),
+                Value::Number(location.latitude),
+            ],
+        )
+    }
+
+    /// The geographic point of a `LOCATION` values map.
+    ///
+    /// Accepts both two numbers and a single [`Point`], with the longitude on the x axis.
+    pub fn as_location(&self) -> Option<GeoLocation> {
+        if self.value_type.0 != Self::LOCATION {
+            return None;
+        }
+
+        match self.values.as_slice() {
+            [longitude, latitude] => Some(GeoLocation {
+                longitude: longitude.as_f64()?,
+                latitude: latitude.as_f64()?,
+            }),
+            [Value::Point(Point { x, y })] => Some(GeoLocation {
+                longitude: *x,
+                latitude: *y,
+            }),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ValueType(
     #[serde(deserialize_with = "crate::string_within_range_inclusive::<1, 128, _>")] pub String,
@@ -24,8 +70,94 @@ pub enum Value {
     Integer(i64),
     Number(f64),
     Boolean(bool),
+    #[serde(deserialize_with = "deserialize_point_object")]
     Point(Point),
     String(String),
+    /// Any other JSON value, e.g., a GeoJSON object of an `AREA`, or a nested array.
+    /// The variants above take precedence, so this never holds a number, boolean, point or string.
+    Json(serde_json::Value),
+}
+
+impl Value {
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value as a float, also for integers, as JSON does not distinguish them
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_point(&self) -> Option<&Point> {
+        match self {
+            Value::Point(point) => Some(point),
+            _ => None,
+        }
+    }
+
+    pub fn as_json(&self) -> Option<&serde_json::Value> {
+        match self {
+            Value::Json(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Boolean(value)
+    }
+}
+
+impl From<Point> for Value {
+    fn from(value: Point) -> Self {
+        Value::Point(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
 }
 
 impl PartialEq for Value {
@@ -35,6 +167,7 @@ impl PartialEq for Value {
             (Self::Boolean(s), Self::Boolean(o)) => s == o,
             (Self::Point(s), Self::Point(o)) => s == o,
             (Self::String(s), Self::String(o)) => s == o,
+            (Self::Json(s), Self::Json(o)) => s == o,
             (Self::Number(s), Self::Number(o)) if s.is_nan() && o.is_nan() => true,
             (Self::Number(s), Self::Number(o)) => s == o,
             _ => false,
@@ -44,16 +177,138 @@ impl PartialEq for Value {
 
 impl Eq for Value {}
 
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Number(value) => write!(f, "{value}"),
+            Value::Boolean(value) => write!(f, "{value}"),
+            Value::Point(Point { x, y }) => write!(f, "({x}, {y})"),
+            Value::String(value) => write!(f, "{value:?}"),
+            Value::Json(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// A pair of floats typically used as a point on a 2 dimensional grid.
+///
+/// Objects with other fields than `x` and `y` are not a point, but a [`Value::Json`].
 #[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Point {
     /// A value on an x axis.
-    pub x: f32,
+    pub x: f64,
     /// A value on a y axis.
-    pub y: f32,
+    pub y: f64,
 }
 
 impl Point {
-    pub fn new(x: f32, y: f32) -> Self {
+    pub fn new(x: f64, y: f64) -> Self {
         Self { x, y }
     }
 }
+
+/// Only objects are points, whereas the derived implementation also accepts `[x, y]`,
+/// which must remain a [`Value::Json`] instead
+fn deserialize_point_object<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Point, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        object @ serde_json::Value::Object(_) => {
+            serde_json::from_value(object).map_err(D::Error::custom)
+        }
+        _ => Err(D::Error::custom("a point must be an object")),
+    }
+}
+
+/// A single geographic point, as described by a `LOCATION` values map
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub struct GeoLocation {
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+impl GeoLocation {
+    pub fn new(longitude: f64, latitude: f64) -> Self {
+        Self {
+            longitude,
+            latitude,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn round_trip(json: serde_json::Value) -> ValuesMap {
+        let values_map: ValuesMap = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&values_map).unwrap(), json);
+        values_map
+    }
+
+    #[test]
+    fn point_values() {
+        let values_map = round_trip(json!({"type": "GRID", "values": [{"x": 1.5, "y": -2.0}]}));
+        assert_eq!(values_map.values, vec![Value::Point(Point::new(1.5, -2.0))]);
+        assert_eq!(
+            values_map.values[0].as_point(),
+            Some(&Point::new(1.5, -2.0))
+        );
+        assert_eq!(values_map.values[0].as_f64(), None);
+    }
+
+    #[test]
+    fn array_of_objects() {
+        let polygon =
+            json!({"type": "Polygon", "coordinates": [[[5.1, 52.0], [5.2, 52.1], [5.1, 52.0]]]});
+        let values_map = round_trip(json!({
+            "type": "AREA",
+            "values": [polygon.clone(), {"x": 1.0, "y": 2.0, "z": 3.0}, [1, 2]]
+        }));
+
+        assert_eq!(values_map.values[0].as_json(), Some(&polygon));
+        assert_eq!(values_map.values[1].as_point(), None);
+        assert_eq!(
+            values_map.values[1].as_json(),
+            Some(&json!({"x": 1.0, "y": 2.0, "z": 3.0}))
+        );
+        assert_eq!(values_map.values[2], Value::Json(json!([1, 2])));
+    }
+
+    #[test]
+    fn scalar_accessors() {
+        let values_map = round_trip(json!({"type": "MIXED", "values": [3, 0.25, true, "text"]}));
+        let values = values_map.values;
+
+        assert_eq!(values[0].as_i64(), Some(3));
+        assert_eq!(values[0].as_f64(), Some(3.0));
+        assert_eq!(values[1].as_f64(), Some(0.25));
+        assert_eq!(values[1].as_i64(), None);
+        assert_eq!(values[2].as_bool(), Some(true));
+        assert_eq!(values[3].as_str(), Some("text"));
+        assert_eq!(values[3].as_json(), None);
+    }
+
+    #[test]
+    fn location() {
+        let location = GeoLocation::new(5.12, 52.09);
+        let values_map = ValuesMap::location(location);
+        assert_eq!(
+            serde_json::to_value(&values_map).unwrap(),
+            json!({"type": "LOCATION", "values": [5.12, 52.09]})
+        );
+        assert_eq!(values_map.as_location(), Some(location));
+
+        let integers = round_trip(json!({"type": "LOCATION", "values": [5, 52]}));
+        assert_eq!(integers.as_location(), Some(GeoLocation::new(5.0, 52.0)));
+
+        let point = round_trip(json!({"type": "LOCATION", "values": [{"x": 5.12, "y": 52.09}]}));
+        assert_eq!(point.as_location(), Some(location));
+
+        let single = round_trip(json!({"type": "LOCATION", "values": [5.12]}));
+        assert_eq!(single.as_location(), None);
+
+        let other = ValuesMap::new("PRICE", [Value::from(5.12), Value::from(52.09)]);
+        assert_eq!(other.as_location(), None);
+    }
+}
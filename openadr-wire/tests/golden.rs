@@ -0,0 +1,274 @@
+//! Golden tests of the wire format.
+//!
+//! Every file in `tests/golden` contains an object as it is serialized by this crate,
+//! with every field set. Each file must round-trip unchanged, which catches renamed or dropped fields.
+//!
+//! Additionally, the serde attributes in `src` are checked against the conventions of this crate,
+//! such that new fields cannot accidentally serialize with the wrong case:
+//!
+//! - every struct that is (de)serialized has a `#[serde(rename_all = "camelCase")]` attribute,
+//!   unless it is listed in [`RENAME_ALL_EXCEPTIONS`] or has unnamed fields
+//! - fields ending in `_id` are renamed explicitly to end in `ID`, e.g., `programID`
+//! - every serialized field name occurs in at least one golden file
+
+use std::{collections::BTreeSet, fmt::Debug, fs, path::Path};
+
+use openadr_wire::{
+    auth::WhoAmI,
+    capabilities::Capabilities,
+    event::EventDelta,
+    notification::Notification,
+    oauth::OAuthError,
+    problem::Problem,
+    report::{LatestReportPayload, ResourceOperatingState},
+    search::SearchHit,
+    Event, Program, Report, Ven,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+/// Structs that use another case than camelCase, because a non-OpenADR specification defines them
+const RENAME_ALL_EXCEPTIONS: &[(&str, &str)] = &[("OAuthError", "snake_case")];
+
+fn golden_dir() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+}
+
+fn read_golden(name: &str) -> Value {
+    let path = golden_dir().join(format!("{name}.json"));
+    let json = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{path:?}: {err}"));
+    serde_json::from_str(&json).unwrap()
+}
+
+fn assert_no_nulls(value: &Value, path: &str) {
+    match value {
+        Value::Null => panic!("{path} is null, golden files must set every field"),
+        Value::Array(values) => {
+            for (i, value) in values.iter().enumerate() {
+                assert_no_nulls(value, &format!("{path}[{i}]"));
+            }
+        }
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                assert_no_nulls(value, &format!("{path}.{key}"));
+            }
+        }
+        _ => {}
+    }
+}
+
+fn assert_round_trip<T: Serialize + DeserializeOwned + Debug>(name: &str) {
+    let golden = read_golden(name);
+    assert_no_nulls(&golden, name);
+
+    let parsed: T = serde_json::from_value(golden.clone())
+        .unwrap_or_else(|err| panic!("{name} does not parse: {err}"));
+    assert_eq!(
+        serde_json::to_value(&parsed).unwrap(),
+        golden,
+        "{name} does not round-trip: {parsed:#?}"
+    );
+}
+
+#[test]
+fn golden_round_trips() {
+    assert_round_trip::<Program>("program");
+    assert_round_trip::<Event>("event");
+    assert_round_trip::<EventDelta>("event_delta");
+    assert_round_trip::<Report>("report");
+    assert_round_trip::<Ven>("ven");
+    assert_round_trip::<Capabilities>("capabilities");
+    assert_round_trip::<Problem>("problem");
+    assert_round_trip::<OAuthError>("oauth_error");
+    assert_round_trip::<Vec<SearchHit>>("search");
+    assert_round_trip::<WhoAmI>("whoami");
+    assert_round_trip::<Vec<ResourceOperatingState>>("operating_states");
+    assert_round_trip::<Vec<LatestReportPayload>>("latest_payloads");
+    // without the top-level targets of OpenADR 3.0.1, such that it round-trips with any revision
+    assert_round_trip::<Notification>("notification");
+}
+
+/// A field of a (de)serialized struct, as declared in the source code
+#[derive(Debug)]
+struct Field {
+    strukt: String,
+    name: String,
+    serde_attributes: Vec<String>,
+}
+
+impl Field {
+    fn serde_attribute(&self, key: &str) -> Option<&str> {
+        self.serde_attributes
+            .iter()
+            .find_map(|attribute| attribute_value(attribute, key))
+    }
+
+    fn is_flattened_or_skipped(&self) -> bool {
+        self.serde_attributes.iter().any(|attribute| {
+            attribute.contains("flatten")
+                || attribute.contains("skip)")
+                || attribute.contains("skip,")
+        })
+    }
+
+    /// The name of the field in JSON, given the `rename_all` case of its struct
+    fn serialized_name(&self, case: &str) -> String {
+        if let Some(name) = self.serde_attribute("rename") {
+            return name.to_string();
+        }
+
+        let name = self.name.trim_start_matches("r#");
+        if case == "snake_case" {
+            return name.to_string();
+        }
+
+        let mut camel_case = String::new();
+        for (i, part) in name.split('_').enumerate() {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) if i > 0 => {
+                    camel_case.extend(first.to_uppercase());
+                    camel_case.push_str(chars.as_str());
+                }
+                _ => camel_case.push_str(part),
+            }
+        }
+        camel_case
+    }
+}
+
+/// The value of `key = "value"` in an attribute
+fn attribute_value<'a>(attribute: &'a str, key: &str) -> Option<&'a str> {
+    let start = attribute.find(&format!("{key} = \""))? + key.len() + 4;
+    let len = attribute[start..].find('"')?;
+    Some(&attribute[start..start + len])
+}
+
+/// Collect the attributes, struct declarations and fields of a source file.
+/// Relies on the formatting of rustfmt, e.g., a single field per line.
+fn serialized_structs(source: &str) -> Vec<(String, Vec<String>, Vec<Field>)> {
+    let mut structs = vec![];
+    let mut attributes = vec![];
+    let mut current: Option<(String, Vec<String>, Vec<Field>)> = None;
+
+    let mut lines = source.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+
+        if line.starts_with("#[") {
+            // attributes may span multiple lines
+            let mut attribute = line.to_string();
+            while attribute.matches('[').count() > attribute.matches(']').count() {
+                attribute.push_str(lines.next().unwrap_or_default().trim());
+            }
+            attributes.push(attribute);
+            continue;
+        }
+
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+
+        if let Some((name, fields)) = current.as_mut().map(|(name, _, fields)| (name, fields)) {
+            if line == "}" {
+                structs.extend(current.take());
+            } else if let Some((field, _)) = line
+                .trim_start_matches("pub(crate) ")
+                .trim_start_matches("pub ")
+                .split_once(": ")
+            {
+                fields.push(Field {
+                    strukt: name.clone(),
+                    name: field.to_string(),
+                    serde_attributes: attributes
+                        .drain(..)
+                        .filter(|attribute| attribute.starts_with("#[serde("))
+                        .collect(),
+                });
+            }
+            attributes.clear();
+            continue;
+        }
+
+        let is_serialized = attributes.iter().any(|attribute| {
+            attribute.starts_with("#[derive(")
+                && (attribute.contains("Serialize") || attribute.contains("Deserialize"))
+        });
+        if let (true, Some(declaration)) = (is_serialized, line.strip_prefix("pub struct ")) {
+            if let Some(name) = declaration.strip_suffix(" {") {
+                current = Some((name.to_string(), attributes.clone(), vec![]));
+            }
+        }
+        attributes.clear();
+    }
+
+    structs
+}
+
+fn collect_keys(value: &Value, keys: &mut BTreeSet<String>) {
+    match value {
+        Value::Array(values) => values.iter().for_each(|value| collect_keys(value, keys)),
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                keys.insert(key.clone());
+                collect_keys(value, keys);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[test]
+fn serde_conventions() {
+    let mut golden_keys = BTreeSet::new();
+    for entry in fs::read_dir(golden_dir()).unwrap() {
+        let json = fs::read_to_string(entry.unwrap().path()).unwrap();
+        collect_keys(&serde_json::from_str(&json).unwrap(), &mut golden_keys);
+    }
+
+    let src = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/src"));
+    let mut violations = vec![];
+
+    for entry in fs::read_dir(src).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+
+        for (name, attributes, fields) in serialized_structs(&source) {
+            let expected_case = RENAME_ALL_EXCEPTIONS
+                .iter()
+                .find(|(exception, _)| *exception == name)
+                .map_or("camelCase", |(_, case)| case);
+            let rename_all = attributes
+                .iter()
+                .find_map(|attribute| attribute_value(attribute, "rename_all"));
+            if rename_all != Some(expected_case) {
+                violations.push(format!(
+                    "{path:?}: struct {name} must have #[serde(rename_all = \"{expected_case}\")]"
+                ));
+            }
+
+            for field in fields
+                .iter()
+                .filter(|field| !field.is_flattened_or_skipped())
+            {
+                let serialized = field.serialized_name(expected_case);
+
+                if field.name.ends_with("_id") && !serialized.ends_with("ID") {
+                    violations.push(format!(
+                        "{path:?}: field {}::{} must be renamed to end in `ID`, e.g., `programID`",
+                        field.strukt, field.name
+                    ));
+                }
+
+                if !golden_keys.contains(&serialized) {
+                    violations.push(format!(
+                        "{path:?}: field {}::{} (`{serialized}`) does not occur in any golden file",
+                        field.strukt, field.name
+                    ));
+                }
+            }
+        }
+    }
+
+    assert!(violations.is_empty(), "{}", violations.join("\n"));
+}